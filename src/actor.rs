@@ -0,0 +1,337 @@
+// src/actor.rs
+use crate::hardware::{
+    self, create_driver, DriverCaps, DriverError, DriverEvent, DriverEventCallback, DriverInfo, DriverType,
+    HardwareConfig, InputDriver, LatencyMetrics, MouseButtons, TrackedDriver,
+};
+use crate::keymap;
+use serde::Deserialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use windows::Win32::Foundation::BOOL;
+use windows::Win32::System::Console::SetConsoleCtrlHandler;
+use windows::Win32::UI::Input::KeyboardAndMouse::GetAsyncKeyState;
+
+// ==========================================
+// 1. 驱动线程
+// ==========================================
+// 驱动只归一个线程所有，其他线程通过 DriverHandle 投递调用并等待结果，
+// 因此各驱动只需 Send，不再需要 Sync，也没有全局驱动锁
+
+type Job = Box<dyn FnOnce(&mut dyn InputDriver) + Send>;
+
+enum Msg {
+    Call(Job),
+    /// 换上新驱动，旧驱动松开按键后释放
+    Swap(Box<dyn InputDriver>, mpsc::SyncSender<DriverInfo>),
+    /// 记录连接事件回调，换驱动后自动转挂到新驱动上
+    SetCallback(DriverEventCallback),
+    /// 松开当前按住的键与鼠标键 (panic / Ctrl+C)
+    Release(mpsc::SyncSender<()>),
+    /// 最后一个句柄已释放
+    Shutdown,
+}
+
+/// 转发给共享回调的包装，供每一任驱动注册
+fn forward(cb: &Arc<DriverEventCallback>) -> DriverEventCallback {
+    let cb = Arc::clone(cb);
+    Box::new(move |event| cb(event))
+}
+
+fn gone() -> DriverError {
+    DriverError::Io("驱动线程已退出".to_string())
+}
+
+const DRIVER_THREAD: &str = "driver";
+
+/// 句柄共享的发送端；最后一个句柄释放时通知驱动线程退出
+/// (异常退出用的发送端不计入，因此不会让驱动线程一直存活)
+struct Inner {
+    tx: mpsc::Sender<Msg>,
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        let _ = self.tx.send(Msg::Shutdown);
+    }
+}
+
+/// 驱动线程的句柄，可随意克隆并在线程间传递；最后一个句柄释放后驱动线程退出
+#[derive(Clone)]
+pub struct DriverHandle {
+    inner: Arc<Inner>,
+}
+
+impl DriverHandle {
+    /// 启动驱动线程并接管 driver (外层自动包上按键状态跟踪，见 hardware::TrackedDriver)
+    pub fn spawn(driver: Box<dyn InputDriver>) -> Result<Self, String> {
+        let mut driver: Box<dyn InputDriver> = Box::new(TrackedDriver::new(driver));
+        let (tx, rx) = mpsc::channel::<Msg>();
+        thread::Builder::new()
+            .name(DRIVER_THREAD.to_string())
+            .spawn(move || {
+                let mut on_event: Option<Arc<DriverEventCallback>> = None;
+                for msg in rx {
+                    match msg {
+                        Msg::Call(job) => {
+                            // 单次调用 panic 时先松开按键，驱动线程继续服务其他句柄
+                            if panic::catch_unwind(AssertUnwindSafe(|| job(driver.as_mut()))).is_err() {
+                                let _ = hardware::release_held(driver.as_mut());
+                            }
+                        }
+                        Msg::Swap(next, done) => {
+                            let _ = driver.key_up();
+                            let _ = driver.mouse_up();
+                            let _ = driver.flush();
+                            let mut next: Box<dyn InputDriver> = Box::new(TrackedDriver::new(next));
+                            if let Some(cb) = &on_event {
+                                next.set_event_callback(forward(cb));
+                            }
+                            driver = next;
+                            let _ = done.send(driver.info());
+                            // 换上的驱动可用时视为重新连接，让暂停中的自动化继续
+                            if let (Some(cb), true) = (&on_event, driver.is_online()) {
+                                cb(&DriverEvent::Reconnected);
+                            }
+                        }
+                        Msg::SetCallback(cb) => {
+                            let cb = Arc::new(cb);
+                            driver.set_event_callback(forward(&cb));
+                            on_event = Some(cb);
+                        }
+                        Msg::Release(done) => {
+                            let _ = hardware::release_held(driver.as_mut());
+                            let _ = done.send(());
+                        }
+                        Msg::Shutdown => break,
+                    }
+                }
+                // 所有句柄已释放：退出前松开按键，避免残留按下状态
+                let _ = driver.key_up();
+                let _ = driver.mouse_up();
+                let _ = driver.flush();
+            })
+            .map_err(|e| format!("无法启动驱动线程: {}", e))?;
+        if let Ok(mut emergency) = EMERGENCY.lock() {
+            *emergency = Some(tx.clone());
+        }
+        Ok(Self { inner: Arc::new(Inner { tx }) })
+    }
+
+    /// 在驱动线程上执行一组连续调用并返回结果；期间不会插入其他线程的指令
+    pub fn call<R: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut dyn InputDriver) -> R + Send + 'static,
+    ) -> Result<R, DriverError> {
+        let (done_tx, done_rx) = mpsc::sync_channel(1);
+        self.inner
+            .tx
+            .send(Msg::Call(Box::new(move |d| {
+                let _ = done_tx.send(f(d));
+            })))
+            .map_err(|_| gone())?;
+        // 驱动调用中 panic 时 done_tx 被丢弃，这里同样返回错误
+        done_rx.recv().map_err(|_| gone())
+    }
+
+    /// 运行中更换驱动后端 (例如硬件盒子掉线时切到软件模拟)；
+    /// 持有句柄的 HumanDriver / NavEngine / 正在执行的任务不受影响，后续指令直接发往新驱动
+    pub fn swap(
+        &self,
+        t: DriverType,
+        port: &str,
+        config: &HardwareConfig,
+        screen_w: u16,
+        screen_h: u16,
+    ) -> Result<DriverInfo, String> {
+        let next = create_driver(t, port, config, screen_w, screen_h)?;
+        self.swap_driver(next)
+    }
+
+    /// 换上一个已创建好的驱动，返回新驱动的信息
+    pub fn swap_driver(&self, next: Box<dyn InputDriver>) -> Result<DriverInfo, String> {
+        let (done_tx, done_rx) = mpsc::sync_channel(1);
+        self.inner.tx.send(Msg::Swap(next, done_tx)).map_err(|_| gone().to_string())?;
+        done_rx.recv().map_err(|_| gone().to_string())
+    }
+
+    fn exec(
+        &self,
+        f: impl FnOnce(&mut dyn InputDriver) -> Result<(), DriverError> + Send + 'static,
+    ) -> Result<(), DriverError> {
+        self.call(f)?
+    }
+}
+
+// ==========================================
+// 2. 句柄本身也是 InputDriver (逐条转发)
+// ==========================================
+
+impl InputDriver for DriverHandle {
+    fn heartbeat(&mut self) -> Result<(), DriverError> { self.exec(|d| d.heartbeat()) }
+    fn mouse_abs(&mut self, x: u16, y: u16) -> Result<(), DriverError> { self.exec(move |d| d.mouse_abs(x, y)) }
+    fn mouse_move(&mut self, dx: i32, dy: i32, wheel: i8) -> Result<(), DriverError> {
+        self.exec(move |d| d.mouse_move(dx, dy, wheel))
+    }
+    fn mouse_down(&mut self, buttons: MouseButtons) -> Result<(), DriverError> { self.exec(move |d| d.mouse_down(buttons)) }
+    fn mouse_up(&mut self) -> Result<(), DriverError> { self.exec(|d| d.mouse_up()) }
+    fn key_down(&mut self, keycode: u8, modifier: u8) -> Result<(), DriverError> {
+        self.exec(move |d| d.key_down(keycode, modifier))
+    }
+    fn key_up(&mut self) -> Result<(), DriverError> { self.exec(|d| d.key_up()) }
+    fn keys_down(&mut self, keycodes: &[u8], modifier: u8) -> Result<(), DriverError> {
+        let keycodes = keycodes.to_vec();
+        self.exec(move |d| d.keys_down(&keycodes, modifier))
+    }
+    fn switch_identity(&mut self, index: u8) -> Result<(), DriverError> { self.exec(move |d| d.switch_identity(index)) }
+    fn mouse_hscroll(&mut self, delta: i8) -> Result<(), DriverError> { self.exec(move |d| d.mouse_hscroll(delta)) }
+    fn type_text(&mut self, text: &str) -> Result<(), DriverError> {
+        let text = text.to_string();
+        self.exec(move |d| d.type_text(&text))
+    }
+    fn send_custom(&mut self, event_type: u8, payload: [u8; 6], delay: u16) -> Result<(), DriverError> {
+        self.exec(move |d| d.send_custom(event_type, payload, delay))
+    }
+    fn flush(&mut self) -> Result<(), DriverError> { self.exec(|d| d.flush()) }
+    fn pending(&self) -> usize { self.call(|d| d.pending()).unwrap_or(0) }
+    fn is_online(&self) -> bool { self.call(|d| d.is_online()).unwrap_or(false) }
+    fn set_event_callback(&mut self, callback: DriverEventCallback) {
+        let _ = self.inner.tx.send(Msg::SetCallback(callback));
+    }
+    fn info(&self) -> DriverInfo { self.call(|d| d.info()).unwrap_or_default() }
+    fn latency_metrics(&self) -> Option<LatencyMetrics> { self.call(|d| d.latency_metrics()).ok().flatten() }
+    fn capabilities(&self) -> DriverCaps { self.call(|d| d.capabilities()).unwrap_or_default() }
+}
+
+// ==========================================
+// 3. 故障切换 (config.toml 中的 [failover])
+// ==========================================
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct FailoverConfig {
+    /// 在 --port 指定的驱动与软件模拟之间来回切换的热键 (F1 ~ F12)，留空表示不启用
+    #[serde(default)]
+    pub hotkey: String,
+    /// 设备离线超过该秒数后自动切到软件模拟，0 表示不自动切换
+    #[serde(default)]
+    pub auto_after_secs: u64,
+}
+
+/// --port 解析出的主驱动，切回时重新创建
+pub struct PrimaryDriver {
+    pub driver_type: DriverType,
+    pub port: String,
+    pub config: HardwareConfig,
+    pub screen_w: u16,
+    pub screen_h: u16,
+}
+
+/// 在后台线程中处理故障切换热键与离线自动切换；online 为主循环使用的在线标志
+pub fn spawn_failover(
+    handle: DriverHandle,
+    cfg: &FailoverConfig,
+    primary: PrimaryDriver,
+    online: Arc<AtomicBool>,
+) -> Result<(), String> {
+    let hotkey = match cfg.hotkey.trim() {
+        "" => None,
+        key => Some(keymap::parse_fkey(key).ok_or_else(|| format!("无法识别的切换热键: {} (支持 F1 ~ F12)", key))?),
+    };
+    let auto_after = (cfg.auto_after_secs > 0).then(|| Duration::from_secs(cfg.auto_after_secs));
+    if hotkey.is_none() && auto_after.is_none() {
+        return Ok(());
+    }
+    if primary.driver_type == DriverType::Software {
+        return Ok(());
+    }
+
+    thread::Builder::new()
+        .name("failover".to_string())
+        .spawn(move || {
+            let mut on_primary = true;
+            let mut hotkey_down = false;
+            let mut offline_since: Option<Instant> = None;
+            loop {
+                let mut toggle = false;
+                if let Some(vk) = hotkey {
+                    // 最高位表示当前按下，只在按下的瞬间触发一次
+                    let down = unsafe { GetAsyncKeyState(vk) } as u16 & 0x8000 != 0;
+                    toggle = down && !hotkey_down;
+                    hotkey_down = down;
+                }
+
+                let offline = !online.load(Ordering::SeqCst);
+                offline_since = if offline { offline_since.or(Some(Instant::now())) } else { None };
+                let auto = on_primary && matches!((auto_after, offline_since), (Some(limit), Some(t)) if t.elapsed() >= limit);
+
+                if (toggle || auto) && on_primary {
+                    let reason = if auto { "设备离线过久" } else { "热键" };
+                    let result = handle.swap(DriverType::Software, "", &primary.config, primary.screen_w, primary.screen_h);
+                    match result {
+                        Ok(info) => {
+                            println!("🔀 [Failover] 已切换到 {} ({})", info.name, reason);
+                            on_primary = false;
+                        }
+                        Err(e) => println!("⚠️ [Failover] 切换到软件模拟失败: {}", e),
+                    }
+                } else if toggle {
+                    let result =
+                        handle.swap(primary.driver_type, &primary.port, &primary.config, primary.screen_w, primary.screen_h);
+                    match result {
+                        Ok(info) => {
+                            println!("🔀 [Failover] 已切回 {}", info.name);
+                            on_primary = true;
+                        }
+                        Err(e) => println!("⚠️ [Failover] 无法切回主驱动，继续使用软件模拟: {}", e),
+                    }
+                }
+                thread::sleep(Duration::from_millis(100));
+            }
+        })
+        .map(|_| ())
+        .map_err(|e| format!("无法启动故障切换线程: {}", e))
+}
+
+// ==========================================
+// 4. 异常退出保护 (panic / Ctrl+C)
+// ==========================================
+
+/// 最近启动的驱动线程，供 panic 钩子与 Ctrl+C 处理函数投递释放指令
+static EMERGENCY: Mutex<Option<mpsc::Sender<Msg>>> = Mutex::new(None);
+/// 释放指令的最长等待时间，驱动卡死时也要能退出
+const RELEASE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// 让驱动线程松开所有仍按住的键与鼠标键，最多等待 RELEASE_TIMEOUT
+pub fn release_all_held() {
+    // 驱动线程自己 panic 时由 catch_unwind 处理，这里等待只会超时
+    if thread::current().name() == Some(DRIVER_THREAD) {
+        return;
+    }
+    let Some(tx) = EMERGENCY.lock().ok().and_then(|e| e.clone()) else {
+        return;
+    };
+    let (done_tx, done_rx) = mpsc::sync_channel(1);
+    if tx.send(Msg::Release(done_tx)).is_ok() {
+        let _ = done_rx.recv_timeout(RELEASE_TIMEOUT);
+    }
+}
+
+unsafe extern "system" fn on_console_ctrl(_ctrl_type: u32) -> BOOL {
+    println!("🛑 收到退出信号，正在释放按键...");
+    release_all_held();
+    // 返回 FALSE 交给系统默认处理 (结束进程)
+    BOOL(0)
+}
+
+/// 安装 panic 钩子与 Ctrl+C / 关闭控制台处理函数：退出前松开所有仍按住的输入
+pub fn install_release_hooks() {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        previous(info);
+        release_all_held();
+    }));
+    if let Err(e) = unsafe { SetConsoleCtrlHandler(Some(on_console_ctrl), true) } {
+        println!("⚠️ 无法注册 Ctrl+C 处理函数: {}", e);
+    }
+}
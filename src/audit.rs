@@ -0,0 +1,352 @@
+// src/audit.rs
+use crate::actor::DriverHandle;
+use crate::hardware::{DriverCaps, DriverError, DriverInfo, DriverFeatures, InputDriver, MouseButtons};
+use crate::human::{CursorSync, HumanDriver};
+use crate::profile::HumanProfile;
+use crate::trace::{TraceCommand, TraceEntry};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// ==========================================
+// 1. 统计摘要
+// ==========================================
+
+/// 相邻两个轨迹点间隔超过此值即视为一段新的移动
+const STROKE_GAP_MS: u64 = 80;
+/// 短于此距离的移动 (微调、漂移) 不参与轨迹形状统计
+const MIN_STROKE_PX: f32 = 30.0;
+/// 样本少于此数时不做"过于规律"判断
+const MIN_SAMPLES: usize = 5;
+
+#[derive(Debug, Clone, Default)]
+pub struct Summary {
+    pub n: usize,
+    pub mean: f64,
+    pub std: f64,
+    pub min: f64,
+    pub p50: f64,
+    pub p95: f64,
+    pub max: f64,
+}
+
+impl Summary {
+    pub fn of(values: &[f64]) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+        let mut sorted = values.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let n = sorted.len();
+        let mean = sorted.iter().sum::<f64>() / n as f64;
+        let var = sorted.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n as f64;
+        let pick = |p: f64| sorted[((n - 1) as f64 * p).round() as usize];
+        Self { n, mean, std: var.sqrt(), min: sorted[0], p50: pick(0.5), p95: pick(0.95), max: sorted[n - 1] }
+    }
+
+    /// 变异系数 (标准差 / 均值)
+    pub fn cv(&self) -> f64 {
+        if self.mean.abs() < f64::EPSILON { 0.0 } else { self.std / self.mean.abs() }
+    }
+}
+
+impl std::fmt::Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.n == 0 {
+            return write!(f, "n=0");
+        }
+        write!(
+            f,
+            "n={:<4} 均值 {:>8.2}  标准差 {:>7.2}  CV {:>5.2}  [最小 {:.2} / 中位 {:.2} / P95 {:.2} / 最大 {:.2}]",
+            self.n, self.mean, self.std, self.cv(), self.min, self.p50, self.p95, self.max
+        )
+    }
+}
+
+// ==========================================
+// 2. 轨迹分析
+// ==========================================
+
+pub struct Metric {
+    pub name: &'static str,
+    pub unit: &'static str,
+    pub summary: Summary,
+}
+
+pub struct AuditReport {
+    pub metrics: Vec<Metric>,
+    /// 被判定为"过于规律"的指标说明
+    pub flags: Vec<String>,
+}
+
+/// 单段移动的形状
+struct Stroke {
+    /// 路径长度 / 直线距离 - 1 (0 表示完全是直线)
+    curvature: f64,
+    /// 峰值速度出现在整段时长中的位置 (0 ~ 1)
+    peak_at: f64,
+    /// 各采样间速度的变异系数
+    speed_cv: f64,
+    duration_ms: f64,
+}
+
+fn finish_stroke(points: &[(u64, f32, f32)]) -> Option<Stroke> {
+    let (&(t0, x0, y0), &(t1, x1, y1)) = (points.first()?, points.last()?);
+    let straight = ((x1 - x0).powi(2) + (y1 - y0).powi(2)).sqrt();
+    if points.len() < MIN_SAMPLES || straight < MIN_STROKE_PX || t1 <= t0 {
+        return None;
+    }
+    let mut length = 0.0f32;
+    // 同一毫秒内的多个点合并计入下一段速度
+    let (mut pending, mut last_t) = (0.0f32, t0);
+    let mut speeds: Vec<(u64, f64)> = Vec::new();
+    for w in points.windows(2) {
+        let d = ((w[1].1 - w[0].1).powi(2) + (w[1].2 - w[0].2).powi(2)).sqrt();
+        length += d;
+        pending += d;
+        if w[1].0 > last_t {
+            speeds.push((w[1].0, pending as f64 / (w[1].0 - last_t) as f64));
+            pending = 0.0;
+            last_t = w[1].0;
+        }
+    }
+    let (peak_t, _) = speeds.iter().copied().max_by(|a, b| a.1.total_cmp(&b.1))?;
+    let speed = Summary::of(&speeds.iter().map(|s| s.1).collect::<Vec<_>>());
+    Some(Stroke {
+        curvature: (length / straight - 1.0).max(0.0) as f64,
+        peak_at: (peak_t - t0) as f64 / (t1 - t0) as f64,
+        speed_cv: speed.cv(),
+        duration_ms: (t1 - t0) as f64,
+    })
+}
+
+/// 从驱动指令轨迹中重建光标位置、按键与点击时序，统计各项分布
+pub fn analyze(entries: &[TraceEntry]) -> AuditReport {
+    let mut pos: Option<(f32, f32)> = None;
+    let mut stroke: Vec<(u64, f32, f32)> = Vec::new();
+    let mut strokes: Vec<Stroke> = Vec::new();
+
+    let mut click_down: Option<(u64, bool)> = None;
+    let (mut click_holds, mut click_gaps) = (Vec::new(), Vec::new());
+    let mut last_click: Option<u64> = None;
+    let mut click_pixels: HashMap<(i32, i32), usize> = HashMap::new();
+
+    let mut held: HashMap<u8, u64> = HashMap::new();
+    let (mut key_holds, mut key_gaps) = (Vec::new(), Vec::new());
+    let mut last_key: Option<u64> = None;
+
+    let mut keys_to = |next: &[u8], t: u64, held: &mut HashMap<u8, u64>| {
+        held.retain(|k, down| {
+            let stays = next.contains(k);
+            if !stays {
+                key_holds.push((t - *down) as f64);
+            }
+            stays
+        });
+        for &k in next.iter().filter(|&&k| k != 0) {
+            if let std::collections::hash_map::Entry::Vacant(e) = held.entry(k) {
+                e.insert(t);
+                if let Some(prev) = last_key.replace(t) {
+                    key_gaps.push((t - prev) as f64);
+                }
+            }
+        }
+    };
+
+    for entry in entries {
+        let t = entry.t_ms;
+        match &entry.cmd {
+            TraceCommand::MouseAbs { x, y } => pos = Some((*x as f32, *y as f32)),
+            TraceCommand::MouseMove { dx, dy, .. } if *dx != 0 || *dy != 0 => {
+                // 相对位移在没有绝对坐标参照时从原点开始累计，只影响位置、不影响形状
+                let (x, y) = pos.unwrap_or((0.0, 0.0));
+                pos = Some((x + *dx as f32, y + *dy as f32));
+            }
+            TraceCommand::MouseDown { .. } => {
+                strokes.extend(finish_stroke(&stroke));
+                stroke.clear();
+                click_down = Some((t, false));
+                if let Some(prev) = last_click.replace(t) {
+                    click_gaps.push((t - prev) as f64);
+                }
+                if let Some((x, y)) = pos {
+                    *click_pixels.entry((x.round() as i32, y.round() as i32)).or_default() += 1;
+                }
+                continue;
+            }
+            TraceCommand::MouseUp => {
+                strokes.extend(finish_stroke(&stroke));
+                stroke.clear();
+                // 按住期间移动过的是拖拽，不计入点击时长
+                if let Some((down, false)) = click_down.take() {
+                    click_holds.push((t - down) as f64);
+                }
+                continue;
+            }
+            TraceCommand::KeyDown { keycode, .. } => keys_to(&[*keycode], t, &mut held),
+            TraceCommand::KeysDown { keycodes, .. } => keys_to(keycodes, t, &mut held),
+            TraceCommand::KeyUp => keys_to(&[], t, &mut held),
+            _ => {}
+        }
+        if let (TraceCommand::MouseAbs { .. } | TraceCommand::MouseMove { .. }, Some((x, y))) = (&entry.cmd, pos) {
+            if matches!(&entry.cmd, TraceCommand::MouseMove { dx: 0, dy: 0, .. }) {
+                continue;
+            }
+            if let Some((down, moved)) = click_down.as_mut() {
+                *moved = *moved || t > *down;
+            }
+            if stroke.last().is_some_and(|&(last, _, _)| t - last > STROKE_GAP_MS) {
+                strokes.extend(finish_stroke(&stroke));
+                stroke.clear();
+            }
+            stroke.push((t, x, y));
+        }
+    }
+    strokes.extend(finish_stroke(&stroke));
+
+    let field = |f: fn(&Stroke) -> f64| Summary::of(&strokes.iter().map(f).collect::<Vec<_>>());
+    let metrics = vec![
+        Metric { name: "点击按住时长", unit: "ms", summary: Summary::of(&click_holds) },
+        Metric { name: "点击间隔", unit: "ms", summary: Summary::of(&click_gaps) },
+        Metric { name: "按键按住时长", unit: "ms", summary: Summary::of(&key_holds) },
+        Metric { name: "按键间隔", unit: "ms", summary: Summary::of(&key_gaps) },
+        Metric { name: "移动耗时", unit: "ms", summary: field(|s| s.duration_ms) },
+        Metric { name: "路径弯曲度", unit: "", summary: field(|s| s.curvature) },
+        Metric { name: "速度峰值位置", unit: "", summary: field(|s| s.peak_at) },
+        Metric { name: "段内速度 CV", unit: "", summary: field(|s| s.speed_cv) },
+    ];
+
+    let mut flags = Vec::new();
+    let regular = |name: &str, s: &Summary, min_cv: f64, flags: &mut Vec<String>| {
+        if s.n >= MIN_SAMPLES && s.cv() < min_cv {
+            flags.push(format!("{}几乎不变 (CV {:.3} < {})", name, s.cv(), min_cv));
+        }
+    };
+    regular("点击按住时长", &metrics[0].summary, 0.08, &mut flags);
+    regular("点击间隔", &metrics[1].summary, 0.1, &mut flags);
+    regular("按键按住时长", &metrics[2].summary, 0.08, &mut flags);
+    regular("按键间隔", &metrics[3].summary, 0.1, &mut flags);
+    regular("移动耗时", &metrics[4].summary, 0.05, &mut flags);
+
+    let curvature = &metrics[5].summary;
+    if curvature.n >= MIN_SAMPLES && curvature.p50 < 0.002 {
+        flags.push(format!("移动轨迹几乎都是直线 (弯曲度中位数 {:.4})", curvature.p50));
+    }
+    let peak = &metrics[6].summary;
+    if peak.n >= MIN_SAMPLES && peak.std < 0.03 {
+        flags.push(format!("每段移动的速度曲线形状相同 (峰值位置标准差 {:.3})", peak.std));
+    }
+    let speed = &metrics[7].summary;
+    if speed.n >= MIN_SAMPLES && speed.p50 < 0.05 {
+        flags.push(format!("移动过程近似匀速 (段内速度 CV 中位数 {:.3})", speed.p50));
+    }
+    let clicks: usize = click_pixels.values().sum();
+    if let Some((&(x, y), &hits)) = click_pixels.iter().max_by_key(|(_, &n)| n) {
+        if clicks >= MIN_SAMPLES && hits >= 3 && hits * 5 >= clicks {
+            flags.push(format!("{} / {} 次点击落在同一像素 ({}, {})", hits, clicks, x, y));
+        }
+    }
+
+    AuditReport { metrics, flags }
+}
+
+impl std::fmt::Display for AuditReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for m in &self.metrics {
+            let label = if m.unit.is_empty() { m.name.to_string() } else { format!("{} ({})", m.name, m.unit) };
+            writeln!(f, "  {:<16} {}", label, m.summary)?;
+        }
+        if self.flags.is_empty() {
+            write!(f, "✅ 未发现过于规律的指标")
+        } else {
+            for (i, flag) in self.flags.iter().enumerate() {
+                if i > 0 {
+                    writeln!(f)?;
+                }
+                write!(f, "⚠️ {}", flag)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+// ==========================================
+// 3. 离线模拟 (不发送任何真实输入)
+// ==========================================
+
+/// 只把指令记入内存的驱动，能力与自制串口固件相同
+struct SinkDriver {
+    entries: Arc<Mutex<Vec<TraceEntry>>>,
+    started: Instant,
+}
+
+impl SinkDriver {
+    fn push(&self, cmd: TraceCommand) -> Result<(), DriverError> {
+        let entry = TraceEntry { t_ms: self.started.elapsed().as_millis() as u64, cmd };
+        self.entries.lock().unwrap().push(entry);
+        Ok(())
+    }
+}
+
+impl InputDriver for SinkDriver {
+    fn info(&self) -> DriverInfo {
+        DriverInfo::named("自检模拟 (Audit)", DriverFeatures::all())
+    }
+    fn capabilities(&self) -> DriverCaps {
+        DriverCaps::default()
+    }
+    fn heartbeat(&mut self) -> Result<(), DriverError> { Ok(()) }
+    fn mouse_abs(&mut self, x: u16, y: u16) -> Result<(), DriverError> { self.push(TraceCommand::MouseAbs { x, y }) }
+    fn mouse_move(&mut self, dx: i32, dy: i32, wheel: i8) -> Result<(), DriverError> {
+        self.push(TraceCommand::MouseMove { dx, dy, wheel })
+    }
+    fn mouse_down(&mut self, buttons: MouseButtons) -> Result<(), DriverError> {
+        self.push(TraceCommand::MouseDown { buttons: buttons.bits() })
+    }
+    fn mouse_up(&mut self) -> Result<(), DriverError> { self.push(TraceCommand::MouseUp) }
+    fn key_down(&mut self, keycode: u8, modifier: u8) -> Result<(), DriverError> {
+        self.push(TraceCommand::KeyDown { keycode, modifier })
+    }
+    fn key_up(&mut self) -> Result<(), DriverError> { self.push(TraceCommand::KeyUp) }
+    fn keys_down(&mut self, keycodes: &[u8], modifier: u8) -> Result<(), DriverError> {
+        self.push(TraceCommand::KeysDown { keycodes: keycodes.to_vec(), modifier })
+    }
+    fn switch_identity(&mut self, _index: u8) -> Result<(), DriverError> { Ok(()) }
+}
+
+/// 模拟中按钮的大小 (px)
+const BUTTON: [i32; 2] = [160, 48];
+const SAMPLE_TEXT: &str = "nzm auto";
+
+/// 以指定档案在内存驱动上跑一段典型操作 (点击按钮、输入文字、滚动)，返回录下的指令
+/// 动作按真实节奏执行，rounds 轮约需 rounds * 2 秒
+pub fn simulate(profile: HumanProfile, seed: u64, rounds: usize, screen_w: u16, screen_h: u16) -> Result<Vec<TraceEntry>, String> {
+    let entries = Arc::new(Mutex::new(Vec::new()));
+    let sink = SinkDriver { entries: Arc::clone(&entries), started: Instant::now() };
+    let device = DriverHandle::spawn(Box::new(sink))?;
+    let mut human = HumanDriver::builder(device)
+        .start(screen_w / 2, screen_h / 2)
+        .profile(profile)
+        .seed(seed)
+        .cursor_sync(CursorSync::Off)
+        .build();
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let (w, h) = (screen_w as i32, screen_h as i32);
+    for round in 0..rounds {
+        let (x, y) = (rng.gen_range(0..w - BUTTON[0]), rng.gen_range(0..h - BUTTON[1]));
+        human
+            .click_in_rect_humanly([x, y, x + BUTTON[0], y + BUTTON[1]], MouseButtons::LEFT)
+            .and_then(|_| match round % 5 {
+                2 => human.type_humanly(SAMPLE_TEXT, 60.0),
+                4 => human.scroll_humanly(if rng.gen_bool(0.5) { 6 } else { -6 }),
+                _ => Ok(()),
+            })
+            .map_err(|e| format!("模拟第 {} 轮失败: {}", round + 1, e))?;
+        std::thread::sleep(Duration::from_millis(rng.gen_range(200..800)));
+    }
+    let recorded = entries.lock().unwrap().clone();
+    Ok(recorded)
+}
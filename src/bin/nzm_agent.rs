@@ -0,0 +1,61 @@
+// src/bin/nzm_agent.rs
+// 运行在游戏机上的键鼠代理：接收控制端 (-p remote:host:port) 发来的指令并交给本机驱动执行
+use clap::Parser;
+use nzm_cmd::actor::{self, DriverHandle};
+use nzm_cmd::hardware::{create_driver, DriverType, HardwareConfig};
+use nzm_cmd::remote::{self, DEFAULT_AGENT_PORT};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "NZM_CMD 远程键鼠代理", long_about = None)]
+struct Args {
+    /// 监听地址
+    #[arg(short, long, default_value_t = format!("0.0.0.0:{}", DEFAULT_AGENT_PORT))]
+    listen: String,
+
+    /// 连接口令 (必填)，控制端地址写作 remote:host:port@口令
+    #[arg(long)]
+    token: String,
+
+    /// 本机驱动: SOFT (软件模拟) 或自制固件串口号 (如 COM3)
+    #[arg(short, long, default_value = "SOFT")]
+    port: String,
+
+    #[arg(long, default_value_t = 115200)]
+    baud: u32,
+
+    #[arg(long, default_value_t = 1920)]
+    screen_w: u16,
+
+    #[arg(long, default_value_t = 1080)]
+    screen_h: u16,
+}
+
+fn main() {
+    let args = Args::parse();
+    println!("========================================");
+    println!("   NZM_CMD 远程代理 (nzm_agent)");
+    println!("========================================");
+
+    let driver_type = if args.port.eq_ignore_ascii_case("SOFT") { DriverType::Software } else { DriverType::Hardware };
+    let hw_config = HardwareConfig { baud_rate: args.baud, ..Default::default() };
+    let driver = match create_driver(driver_type, &args.port, &hw_config, args.screen_w, args.screen_h) {
+        Ok(d) => d,
+        Err(e) => {
+            println!("❌ 无法初始化本机驱动: {}", e);
+            return;
+        }
+    };
+    let mut device = match DriverHandle::spawn(driver) {
+        Ok(d) => d,
+        Err(e) => {
+            println!("❌ {}", e);
+            return;
+        }
+    };
+    // 代理被 Ctrl+C 结束时，控制端按住的键同样要松开
+    actor::install_release_hooks();
+
+    if let Err(e) = remote::serve(&args.listen, &args.token, &mut device) {
+        println!("❌ {}", e);
+    }
+}
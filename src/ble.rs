@@ -0,0 +1,203 @@
+// src/ble.rs
+// BLE UART (Nordic UART Service) 传输层：在 GATT 上收发与 USB 串口相同的帧
+// 直接使用 WinRT 的 Windows.Devices.Bluetooth 而不是 btleplug：
+// - btleplug 在 Windows 上本身就是这套 WinRT GATT 接口的封装，本项目只支持 Windows，多一层抽象换不来跨平台
+// - btleplug (0.11) 的接口全部是 async 并依赖 tokio；驱动链其余部分都是同步调用 + 后台线程
+// - 分包需要协商后的 MTU (GattSession::MaxPduSize)，btleplug 的 Peripheral 接口没有暴露这一项，只能固定按 20 字节分包
+// - btleplug 自带另一个大版本的 windows crate (0.61)，而这里只需在已有的 windows 依赖上多开三个 feature
+use crate::hardware::FrameTransport;
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::sync::mpsc;
+use std::time::Duration;
+use windows::core::{GUID, HSTRING};
+use windows::Devices::Bluetooth::GenericAttributeProfile::{
+    GattCharacteristic, GattClientCharacteristicConfigurationDescriptorValue, GattCommunicationStatus,
+    GattDeviceService, GattSession, GattValueChangedEventArgs, GattWriteOption,
+};
+use windows::Devices::Bluetooth::{BluetoothCacheMode, BluetoothConnectionStatus, BluetoothLEDevice};
+use windows::Devices::Enumeration::DeviceInformation;
+use windows::Foundation::TypedEventHandler;
+use windows::Storage::Streams::{DataReader, DataWriter};
+
+// ==========================================
+// 1. Nordic UART Service
+// ==========================================
+// 盒子通过 NUS 暴露一个双向字节流，帧格式与 USB 串口完全相同 (见 hardware.rs)
+
+const NUS_SERVICE: GUID = GUID::from_u128(0x6e400001_b5a3_f393_e0a9_e50e24dcca9e);
+/// 主机 -> 设备 (Write Without Response)
+const NUS_RX: GUID = GUID::from_u128(0x6e400002_b5a3_f393_e0a9_e50e24dcca9e);
+/// 设备 -> 主机 (Notify)
+const NUS_TX: GUID = GUID::from_u128(0x6e400003_b5a3_f393_e0a9_e50e24dcca9e);
+/// 默认 MTU (23) 下单次写入的最大负载，协商不到更大的 MTU 时按此分包
+const DEFAULT_CHUNK: usize = 20;
+
+fn winrt<T>(r: windows::core::Result<T>, what: &str) -> Result<T, String> {
+    r.map_err(|e| format!("{}失败: {}", what, e))
+}
+
+fn check(status: GattCommunicationStatus, what: &str) -> Result<(), String> {
+    if status == GattCommunicationStatus::Success {
+        Ok(())
+    } else {
+        Err(format!("{}失败 (GATT 状态 {})", what, status.0))
+    }
+}
+
+/// "AA:BB:CC:DD:EE:FF" -> 48 位蓝牙地址
+fn parse_address(s: &str) -> Option<u64> {
+    let parts: Vec<&str> = s.split(':').collect();
+    if parts.len() != 6 {
+        return None;
+    }
+    parts.iter().try_fold(0u64, |acc, p| {
+        (p.len() == 2).then_some(())?;
+        Some((acc << 8) | u8::from_str_radix(p, 16).ok()? as u64)
+    })
+}
+
+/// 按地址或设备名查找已配对的 BLE 设备
+fn find_device(spec: &str) -> Result<BluetoothLEDevice, String> {
+    if let Some(addr) = parse_address(spec) {
+        return winrt(BluetoothLEDevice::FromBluetoothAddressAsync(addr).and_then(|op| op.get()), "连接 BLE 设备")
+            .map_err(|e| format!("{} ({})", e, spec));
+    }
+    let selector = winrt(BluetoothLEDevice::GetDeviceSelectorFromDeviceName(&HSTRING::from(spec)), "构造设备筛选")?;
+    let found = winrt(DeviceInformation::FindAllAsyncAqsFilter(&selector).and_then(|op| op.get()), "枚举 BLE 设备")?;
+    if winrt(found.Size(), "枚举 BLE 设备")? == 0 {
+        return Err(format!("未找到名为 {} 的 BLE 设备 (需先在系统蓝牙设置中配对)", spec));
+    }
+    let id = winrt(found.GetAt(0).and_then(|d| d.Id()), "读取设备 ID")?;
+    winrt(BluetoothLEDevice::FromIdAsync(&id).and_then(|op| op.get()), "连接 BLE 设备").map_err(|e| format!("{} ({})", e, spec))
+}
+
+fn characteristic(service: &GattDeviceService, uuid: GUID) -> Result<GattCharacteristic, String> {
+    let result = winrt(service.GetCharacteristicsForUuidAsync(uuid).and_then(|op| op.get()), "查询 NUS 特征")?;
+    check(winrt(result.Status(), "查询 NUS 特征")?, "查询 NUS 特征")?;
+    winrt(result.Characteristics().and_then(|c| c.GetAt(0)), "查询 NUS 特征")
+}
+
+// ==========================================
+// 2. BLE UART 字节通道
+// ==========================================
+
+/// 以 NUS 特征模拟串口：写入按 MTU 分包发送，设备通知的数据缓存后供 read 读取
+pub struct BleUart {
+    device: BluetoothLEDevice,
+    /// 服务对象释放后通知会停止，因此与特征一起持有
+    _service: GattDeviceService,
+    rx: GattCharacteristic,
+    _tx: GattCharacteristic,
+    incoming: mpsc::Receiver<Vec<u8>>,
+    buffered: VecDeque<u8>,
+    read_timeout: Duration,
+    chunk: usize,
+}
+
+impl BleUart {
+    /// spec 为设备名或 AA:BB:CC:DD:EE:FF 形式的地址
+    pub fn connect(spec: &str, read_timeout: Duration) -> Result<Self, String> {
+        let device = find_device(spec)?;
+        let services = winrt(
+            device
+                .GetGattServicesForUuidWithCacheModeAsync(NUS_SERVICE, BluetoothCacheMode::Uncached)
+                .and_then(|op| op.get()),
+            "查询 GATT 服务",
+        )?;
+        check(winrt(services.Status(), "查询 GATT 服务")?, "查询 GATT 服务")?;
+        let service = services
+            .Services()
+            .and_then(|s| s.GetAt(0))
+            .map_err(|_| format!("设备 {} 未提供 Nordic UART 服务", spec))?;
+        let rx = characteristic(&service, NUS_RX)?;
+        let tx = characteristic(&service, NUS_TX)?;
+
+        let (sender, incoming) = mpsc::channel();
+        let handler = TypedEventHandler::new(move |_, args: &Option<GattValueChangedEventArgs>| {
+            if let Some(args) = args {
+                let buf = args.CharacteristicValue()?;
+                let mut data = vec![0u8; buf.Length()? as usize];
+                DataReader::FromBuffer(&buf)?.ReadBytes(&mut data)?;
+                let _ = sender.send(data);
+            }
+            Ok(())
+        });
+        winrt(tx.ValueChanged(&handler), "订阅 NUS 通知")?;
+        let status = winrt(
+            tx.WriteClientCharacteristicConfigurationDescriptorAsync(
+                GattClientCharacteristicConfigurationDescriptorValue::Notify,
+            )
+            .and_then(|op| op.get()),
+            "开启 NUS 通知",
+        )?;
+        check(status, "开启 NUS 通知")?;
+
+        // ATT 头占 3 字节；拿不到协商结果时按默认 MTU 分包
+        let chunk = device
+            .BluetoothDeviceId()
+            .and_then(|id| GattSession::FromDeviceIdAsync(&id))
+            .and_then(|op| op.get())
+            .and_then(|s| s.MaxPduSize())
+            .map(|pdu| (pdu as usize).saturating_sub(3).max(DEFAULT_CHUNK))
+            .unwrap_or(DEFAULT_CHUNK);
+        println!("📶 [BLE] 已连接 {} (单包 {} 字节)", spec, chunk);
+
+        Ok(Self { device, _service: service, rx, _tx: tx, incoming, buffered: VecDeque::new(), read_timeout, chunk })
+    }
+
+    fn connected(&self) -> bool {
+        matches!(self.device.ConnectionStatus(), Ok(BluetoothConnectionStatus::Connected))
+    }
+}
+
+impl Write for BleUart {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.connected() {
+            return Err(io::Error::new(io::ErrorKind::NotConnected, "BLE 设备未连接"));
+        }
+        for chunk in buf.chunks(self.chunk) {
+            let writer = DataWriter::new()?;
+            writer.WriteBytes(chunk)?;
+            let data = writer.DetachBuffer()?;
+            let status = self.rx.WriteValueWithOptionAsync(&data, GattWriteOption::WriteWithoutResponse)?.get()?;
+            if status != GattCommunicationStatus::Success {
+                return Err(io::Error::new(io::ErrorKind::BrokenPipe, format!("BLE 写入失败 (GATT 状态 {})", status.0)));
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Read for BleUart {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if self.buffered.is_empty() {
+            match self.incoming.recv_timeout(self.read_timeout) {
+                Ok(data) => self.buffered.extend(data),
+                Err(_) => return Err(io::Error::new(io::ErrorKind::TimedOut, "BLE 读取超时")),
+            }
+        }
+        let n = out.len().min(self.buffered.len());
+        for (o, b) in out.iter_mut().zip(self.buffered.drain(..n)) {
+            *o = b;
+        }
+        Ok(n)
+    }
+}
+
+impl FrameTransport for BleUart {
+    fn clear_input(&mut self) {
+        self.buffered.clear();
+        while self.incoming.try_recv().is_ok() {}
+    }
+}
+
+impl Drop for BleUart {
+    fn drop(&mut self) {
+        let _ = self.device.Close();
+    }
+}
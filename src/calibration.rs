@@ -0,0 +1,129 @@
+// src/calibration.rs
+use crate::hardware::{AbsCalibration, CalibrationSample, DriverError, InputDriver};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::thread;
+use std::time::Duration;
+use windows::Win32::Foundation::POINT;
+use windows::Win32::UI::WindowsAndMessaging::GetCursorPos;
+
+// ==========================================
+// 1. 持久化 (与运行统计共用 SQLite 状态库)
+// ==========================================
+// 偏差来自主机对 HID 绝对坐标的映射，因此按屏幕分辨率而不是串口区分
+
+fn open(path: &str) -> Result<Connection, String> {
+    let conn = Connection::open(path).map_err(|e| format!("无法打开状态库 {}: {}", path, e))?;
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS abs_calibration (
+            screen_w INTEGER NOT NULL,
+            screen_h INTEGER NOT NULL,
+            x_scale  REAL NOT NULL,
+            x_offset REAL NOT NULL,
+            y_scale  REAL NOT NULL,
+            y_offset REAL NOT NULL,
+            PRIMARY KEY (screen_w, screen_h)
+        );",
+    )
+    .map_err(|e| format!("校准表初始化失败: {}", e))?;
+    Ok(conn)
+}
+
+/// 读取该分辨率下保存的校准结果
+pub fn load(path: &str, screen_w: u16, screen_h: u16) -> Result<Option<AbsCalibration>, String> {
+    open(path)?
+        .query_row(
+            "SELECT x_scale, x_offset, y_scale, y_offset FROM abs_calibration WHERE screen_w = ?1 AND screen_h = ?2",
+            params![screen_w, screen_h],
+            |row| {
+                Ok(AbsCalibration {
+                    x_scale: row.get::<_, f64>(0)? as f32,
+                    x_offset: row.get::<_, f64>(1)? as f32,
+                    y_scale: row.get::<_, f64>(2)? as f32,
+                    y_offset: row.get::<_, f64>(3)? as f32,
+                })
+            },
+        )
+        .optional()
+        .map_err(|e| format!("读取校准结果失败: {}", e))
+}
+
+pub fn save(path: &str, screen_w: u16, screen_h: u16, cal: &AbsCalibration) -> Result<(), String> {
+    open(path)?
+        .execute(
+            "INSERT OR REPLACE INTO abs_calibration (screen_w, screen_h, x_scale, x_offset, y_scale, y_offset)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                screen_w,
+                screen_h,
+                cal.x_scale as f64,
+                cal.x_offset as f64,
+                cal.y_scale as f64,
+                cal.y_offset as f64
+            ],
+        )
+        .map_err(|e| format!("写入校准结果失败: {}", e))?;
+    Ok(())
+}
+
+// ==========================================
+// 2. 校准流程
+// ==========================================
+
+/// 样本点：屏幕 10% / 50% / 90% 处的 3x3 网格 (避开边缘的夹紧区)
+const GRID: [f32; 3] = [0.1, 0.5, 0.9];
+/// 发送坐标后等待系统光标稳定的时间
+const SETTLE: Duration = Duration::from_millis(150);
+
+/// 当前系统光标的屏幕坐标
+pub fn cursor_pos() -> Result<(f32, f32), String> {
+    let mut p = POINT::default();
+    unsafe { GetCursorPos(&mut p) }.map_err(|e| format!("读取光标位置失败: {}", e))?;
+    Ok((p.x as f32, p.y as f32))
+}
+
+/// 移动到 (x, y) 并读回系统光标位置
+fn probe(driver: &mut dyn InputDriver, x: f32, y: f32) -> Result<(f32, f32), String> {
+    driver
+        .mouse_abs(x.round().max(0.0) as u16, y.round().max(0.0) as u16)
+        .and_then(|_| driver.flush())
+        .map_err(|e: DriverError| e.to_string())?;
+    thread::sleep(SETTLE);
+    cursor_pos()
+}
+
+/// 依次移动到网格点并读回真实光标位置，拟合逐轴校正，再用校正后的坐标复测一遍
+/// driver 必须是未加载校准的驱动；期间请勿触碰鼠标
+pub fn run(driver: &mut dyn InputDriver, screen_w: u16, screen_h: u16) -> Result<AbsCalibration, String> {
+    let points: Vec<(f32, f32)> = GRID
+        .iter()
+        .flat_map(|fy| GRID.iter().map(move |fx| (fx * screen_w as f32, fy * screen_h as f32)))
+        .collect();
+
+    let mut samples = Vec::with_capacity(points.len());
+    for &(x, y) in &points {
+        let actual = probe(driver, x, y)?;
+        println!("    📍 目标 ({:.0}, {:.0}) -> 实际 ({:.0}, {:.0})", x, y, actual.0, actual.1);
+        samples.push(((x, y), actual));
+    }
+    let before = max_error(&samples);
+    let cal = AbsCalibration::fit(&samples)?;
+
+    let mut verify = Vec::with_capacity(points.len());
+    for &(x, y) in &points {
+        let (cx, cy) = cal.apply(x, y);
+        verify.push(((x, y), probe(driver, cx, cy)?));
+    }
+    let after = max_error(&verify);
+    println!("🎯 [校准] 最大偏差 {:.1}px -> {:.1}px", before, after);
+    if after > before {
+        return Err(format!("校准后偏差反而变大 ({:.1}px)，请确认校准期间没有移动鼠标", after));
+    }
+    Ok(cal)
+}
+
+fn max_error(samples: &[CalibrationSample]) -> f32 {
+    samples
+        .iter()
+        .map(|(t, a)| (t.0 - a.0).abs().max((t.1 - a.1).abs()))
+        .fold(0.0, f32::max)
+}
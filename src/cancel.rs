@@ -0,0 +1,144 @@
+// src/cancel.rs
+use crate::hardware::DriverError;
+use crate::keymap;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use windows::Win32::UI::Input::KeyboardAndMouse::GetAsyncKeyState;
+
+// ==========================================
+// 1. 取消令牌
+// ==========================================
+
+const RUNNING: u8 = 0;
+const PAUSED: u8 = 1;
+const ABORTED: u8 = 2;
+
+/// 暂停期间检查状态的间隔
+const POLL: Duration = Duration::from_millis(50);
+/// interrupt 中止后转入暂停前的等待：足够让进行中的动作走到下一个检查点 (POLL 的数倍)
+const INTERRUPT_GRACE: Duration = Duration::from_millis(300);
+
+/// 拟人动作的暂停 / 中止开关 (可跨线程克隆共享)
+/// 动作在每个轨迹点、每个字符、每段长按之间检查：中止时返回 DriverError::Cancelled，
+/// 由 HumanDriver 松开所有仍按住的键与鼠标键；暂停只在两个动作之间等待，
+/// 不会停在按住键鼠或占用多开输入闸门的途中
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    state: Arc<AtomicU8>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn pause(&self) {
+        let _ = self.state.compare_exchange(RUNNING, PAUSED, Ordering::SeqCst, Ordering::SeqCst);
+    }
+
+    pub fn resume(&self) {
+        let _ = self.state.compare_exchange(PAUSED, RUNNING, Ordering::SeqCst, Ordering::SeqCst);
+    }
+
+    /// 中止进行中与之后的所有动作，直到 reset
+    pub fn abort(&self) {
+        self.state.store(ABORTED, Ordering::SeqCst);
+    }
+
+    /// 立即停下进行中的动作并转入暂停：动作在下一个检查点以 Cancelled 结束并松开按键，
+    /// 之后的动作等待 resume (阻塞约 INTERRUPT_GRACE)
+    pub fn interrupt(&self) {
+        self.abort();
+        thread::sleep(INTERRUPT_GRACE);
+        let _ = self.state.compare_exchange(ABORTED, PAUSED, Ordering::SeqCst, Ordering::SeqCst);
+    }
+
+    pub fn reset(&self) {
+        self.state.store(RUNNING, Ordering::SeqCst);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.state.load(Ordering::SeqCst) == PAUSED
+    }
+
+    pub fn is_aborted(&self) -> bool {
+        self.state.load(Ordering::SeqCst) == ABORTED
+    }
+
+    /// 暂停时阻塞到恢复；已中止或超过 deadline 时返回 Cancelled
+    pub fn check(&self, deadline: Option<Instant>) -> Result<(), DriverError> {
+        loop {
+            self.check_cancelled(deadline)?;
+            if !self.is_paused() {
+                return Ok(());
+            }
+            thread::sleep(POLL);
+        }
+    }
+
+    /// 只检查中止与 deadline，暂停时不等待 (动作进行中使用)
+    pub fn check_cancelled(&self, deadline: Option<Instant>) -> Result<(), DriverError> {
+        if self.is_aborted() {
+            return Err(DriverError::Cancelled("动作已中止".to_string()));
+        }
+        if deadline.is_some_and(|d| Instant::now() >= d) {
+            return Err(DriverError::Cancelled("动作超时".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// 进程内共享的令牌：HumanDriver 默认使用它，暂停热键作用于所有实例
+pub fn global() -> CancellationToken {
+    static GLOBAL: OnceLock<CancellationToken> = OnceLock::new();
+    GLOBAL.get_or_init(CancellationToken::new).clone()
+}
+
+// ==========================================
+// 2. 暂停热键 (config.toml 中 [human] 的 pause_hotkey)
+// ==========================================
+
+/// 按住热键超过此时长视为紧急停止
+const ABORT_HOLD: Duration = Duration::from_secs(1);
+
+/// 在后台线程中监听热键 (F1 ~ F12)：短按在暂停与继续之间切换 (暂停在当前动作结束后生效)；
+/// 按住 1 秒立即中止进行中的移动 / 长按 / 打字并松开按键，随后处于暂停状态，再短按一次继续
+pub fn spawn_pause_hotkey(token: CancellationToken, key: &str) -> Result<(), String> {
+    let vk = keymap::parse_fkey(key).ok_or_else(|| format!("无法识别的暂停热键: {} (支持 F1 ~ F12)", key))?;
+    thread::Builder::new()
+        .name("pause-hotkey".to_string())
+        .spawn(move || {
+            // 本次按下的时刻，以及是否已按长按处理
+            let mut pressed: Option<(Instant, bool)> = None;
+            loop {
+                // 最高位表示当前按下
+                let down = unsafe { GetAsyncKeyState(vk) } as u16 & 0x8000 != 0;
+                match (down, pressed) {
+                    (true, None) => pressed = Some((Instant::now(), false)),
+                    (true, Some((since, false))) if since.elapsed() >= ABORT_HOLD => {
+                        pressed = Some((since, true));
+                        token.interrupt();
+                        println!("⏹️ [暂停热键] 已中止进行中的动作并暂停 (短按继续)");
+                    }
+                    // 长按已处理，松开时不再切换
+                    (false, Some((_, true))) => pressed = None,
+                    (false, Some((_, false))) => {
+                        pressed = None;
+                        if token.is_paused() {
+                            token.resume();
+                            println!("▶️ [暂停热键] 继续运行");
+                        } else {
+                            token.pause();
+                            println!("⏸️ [暂停热键] 已暂停，当前动作完成后停住 (短按继续，按住 1 秒立即中止)");
+                        }
+                    }
+                    _ => {}
+                }
+                thread::sleep(Duration::from_millis(50));
+            }
+        })
+        .map(|_| ())
+        .map_err(|e| format!("无法启动暂停热键线程: {}", e))
+}
@@ -0,0 +1,880 @@
+// src/capture.rs
+use image::RgbaImage;
+use screenshots::Screen;
+use serde::Deserialize;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::window::{self, Origin, WindowHandle};
+
+use windows::core::{ComInterface, IInspectable};
+use windows::Graphics::Capture::{Direct3D11CaptureFramePool, GraphicsCaptureItem, GraphicsCaptureSession};
+use windows::Graphics::DirectX::Direct3D11::IDirect3DDevice;
+use windows::Graphics::DirectX::DirectXPixelFormat;
+use std::ffi::c_void;
+use windows::Graphics::SizeInt32;
+use windows::Win32::Foundation::{BOOL, HMODULE, HWND, LPARAM, POINT, RECT};
+use windows::Win32::Graphics::Dwm::{DwmGetWindowAttribute, DWMWA_EXTENDED_FRAME_BOUNDS};
+use windows::Win32::Graphics::Direct3D::D3D_DRIVER_TYPE_HARDWARE;
+use windows::Win32::Graphics::Direct3D11::{
+    D3D11CreateDevice, ID3D11Device, ID3D11DeviceContext, ID3D11Resource, ID3D11Texture2D, D3D11_BOX,
+    D3D11_CPU_ACCESS_READ, D3D11_CREATE_DEVICE_BGRA_SUPPORT, D3D11_MAPPED_SUBRESOURCE, D3D11_MAP_READ,
+    D3D11_SDK_VERSION, D3D11_TEXTURE2D_DESC, D3D11_USAGE_STAGING,
+};
+use windows::Win32::Graphics::Dxgi::{
+    IDXGIDevice, IDXGIOutput1, IDXGIOutputDuplication, IDXGIResource, DXGI_ERROR_ACCESS_LOST,
+    DXGI_ERROR_WAIT_TIMEOUT, DXGI_OUTDUPL_FRAME_INFO, DXGI_OUTDUPL_MOVE_RECT, DXGI_OUTPUT_DESC,
+};
+use windows::Win32::Graphics::Gdi::{
+    EnumDisplayMonitors, GetMonitorInfoW, MonitorFromPoint, HDC, HMONITOR, MONITORINFO, MONITOR_DEFAULTTOPRIMARY,
+};
+use windows::Win32::System::WinRT::Direct3D11::{CreateDirect3D11DeviceFromDXGIDevice, IDirect3DDxgiInterfaceAccess};
+use windows::Win32::System::WinRT::Graphics::Capture::IGraphicsCaptureItemInterop;
+use windows::Win32::UI::WindowsAndMessaging::{GetSystemMetrics, MONITORINFOF_PRIMARY, SM_CXSCREEN, SM_CYSCREEN};
+
+// ==========================================
+// 1. 通用接口 (Trait)
+// ==========================================
+pub trait CaptureBackend: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// 截取主屏幕上的矩形区域 (屏幕坐标)，返回 RGBA 图像
+    fn capture_area(&self, x: i32, y: i32, w: u32, h: u32) -> Result<RgbaImage, String>;
+
+    /// 坐标系的尺寸 (宽, 高)；默认为主屏幕分辨率，ui_map 的 base_resolution 据此缩放坐标
+    fn frame_size(&self) -> Option<(u32, u32)> {
+        let (w, h) = unsafe { (GetSystemMetrics(SM_CXSCREEN), GetSystemMetrics(SM_CYSCREEN)) };
+        (w > 0 && h > 0).then_some((w as u32, h as u32))
+    }
+
+    /// 坐标 (0, 0) 在虚拟桌面上的位置；默认为主显示器左上角
+    fn origin(&self) -> (i32, i32) {
+        (0, 0)
+    }
+}
+
+// ==========================================
+// 2. 配置
+// ==========================================
+#[derive(Deserialize, Debug, Clone)]
+pub struct CaptureConfig {
+    /// 首选后端: "gdi" / "dxgi" / "wgc" / "sim"
+    #[serde(default = "default_backend")]
+    pub backend: String,
+    /// 首选后端不可用时依次尝试
+    #[serde(default = "default_fallback")]
+    pub fallback: Vec<String>,
+    /// sim 后端使用的静态截图
+    #[serde(default = "default_sim_image")]
+    pub sim_image: String,
+    /// 截取的显示器序号 (0 为主显示器，其余按系统枚举顺序)；缺省时为主显示器，指定 window 时为窗口所在的显示器
+    #[serde(default)]
+    pub monitor: Option<usize>,
+    /// 只截取该窗口的客户区，坐标按客户区解释: "标题[#序号]" 或 "class:类名[#序号]"
+    #[serde(default)]
+    pub window: Option<String>,
+}
+
+fn default_backend() -> String { "gdi".to_string() }
+fn default_fallback() -> Vec<String> { vec!["dxgi".to_string(), "gdi".to_string()] }
+fn default_sim_image() -> String { "debug_screenshot.png".to_string() }
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self {
+            backend: default_backend(),
+            fallback: default_fallback(),
+            sim_image: default_sim_image(),
+            monitor: None,
+            window: None,
+        }
+    }
+}
+
+// ==========================================
+// 3. 显示器
+// ==========================================
+
+/// 显示器在虚拟桌面上的位置与尺寸
+#[derive(Debug, Clone, Copy)]
+pub struct Monitor {
+    pub handle: isize,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+unsafe extern "system" fn enum_monitor_proc(monitor: HMONITOR, _: HDC, _: *mut RECT, lparam: LPARAM) -> BOOL {
+    let out = &mut *(lparam.0 as *mut Vec<(Monitor, bool)>);
+    let mut info = MONITORINFO { cbSize: std::mem::size_of::<MONITORINFO>() as u32, ..Default::default() };
+    if GetMonitorInfoW(monitor, &mut info).as_bool() {
+        let r = info.rcMonitor;
+        let m = Monitor {
+            handle: monitor.0,
+            x: r.left,
+            y: r.top,
+            width: (r.right - r.left).max(0) as u32,
+            height: (r.bottom - r.top).max(0) as u32,
+        };
+        out.push((m, info.dwFlags & MONITORINFOF_PRIMARY != 0));
+    }
+    BOOL(1)
+}
+
+/// 枚举所有显示器，主显示器排在第 0 位，其余保持系统枚举顺序
+pub fn monitors() -> Vec<Monitor> {
+    let mut all: Vec<(Monitor, bool)> = Vec::new();
+    unsafe {
+        let _ = EnumDisplayMonitors(HDC::default(), None, Some(enum_monitor_proc), LPARAM(&mut all as *mut _ as isize));
+    }
+    all.sort_by_key(|(_, primary)| !primary);
+    all.into_iter().map(|(m, _)| m).collect()
+}
+
+/// 包含虚拟桌面坐标 (x, y) 的显示器序号
+fn monitor_index_at(x: i32, y: i32) -> Option<usize> {
+    monitors()
+        .iter()
+        .position(|m| x >= m.x && y >= m.y && x < m.x + m.width as i32 && y < m.y + m.height as i32)
+}
+
+// ==========================================
+// 4. GDI 后端 (screenshots crate)
+// ==========================================
+/// monitor 为 None 时截取主显示器
+#[derive(Default)]
+pub struct GdiCapture {
+    monitor: Option<Monitor>,
+}
+
+impl GdiCapture {
+    pub fn new(monitor: Option<Monitor>) -> Self {
+        Self { monitor }
+    }
+}
+
+impl CaptureBackend for GdiCapture {
+    fn name(&self) -> &'static str { "gdi" }
+
+    fn capture_area(&self, x: i32, y: i32, w: u32, h: u32) -> Result<RgbaImage, String> {
+        let screens = Screen::all().map_err(|e| format!("枚举屏幕失败: {}", e))?;
+        let screen = match self.monitor {
+            Some(m) => screens.iter().find(|s| s.display_info.x == m.x && s.display_info.y == m.y),
+            None => screens.iter().find(|s| s.display_info.is_primary).or(screens.first()),
+        }
+        .ok_or("未检测到显示器")?;
+        let captured = screen.capture_area(x, y, w, h).map_err(|e| format!("GDI 截图失败: {}", e))?;
+        // screenshots 使用 image 0.24，这里转换为本项目的 image 0.25
+        RgbaImage::from_raw(captured.width(), captured.height(), captured.into_raw())
+            .ok_or_else(|| "截图数据长度异常".to_string())
+    }
+
+    fn frame_size(&self) -> Option<(u32, u32)> {
+        monitor_size(self.monitor)
+    }
+
+    fn origin(&self) -> (i32, i32) {
+        monitor_origin(self.monitor)
+    }
+}
+
+fn monitor_size(monitor: Option<Monitor>) -> Option<(u32, u32)> {
+    match monitor {
+        Some(m) => Some((m.width, m.height)),
+        None => monitors().first().map(|m| (m.width, m.height)),
+    }
+}
+
+fn monitor_origin(monitor: Option<Monitor>) -> (i32, i32) {
+    monitor.map_or((0, 0), |m| (m.x, m.y))
+}
+
+// ==========================================
+// 5. D3D11 公共辅助
+// ==========================================
+
+/// CPU 侧缓存的整帧 (RGBA)
+struct FrameCache {
+    data: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
+impl FrameCache {
+    fn crop(&self, x: i32, y: i32, w: u32, h: u32) -> Result<RgbaImage, String> {
+        if self.width == 0 || self.height == 0 {
+            return Err("尚未获取到任何帧".to_string());
+        }
+        let x0 = (x.max(0) as u32).min(self.width - 1);
+        let y0 = (y.max(0) as u32).min(self.height - 1);
+        let cw = w.max(1).min(self.width - x0);
+        let ch = h.max(1).min(self.height - y0);
+
+        let mut out = Vec::with_capacity((cw * ch * 4) as usize);
+        for row in y0..y0 + ch {
+            let start = ((row * self.width + x0) * 4) as usize;
+            out.extend_from_slice(&self.data[start..start + (cw * 4) as usize]);
+        }
+        RgbaImage::from_raw(cw, ch, out).ok_or_else(|| "裁剪失败".to_string())
+    }
+
+    /// 把映射出的 BGRA 纹理中 rect 范围内的像素转换为 RGBA 写入缓存 (rect 已裁剪到帧内)
+    fn write_bgra(&mut self, src: &[u8], pitch: usize, rect: RECT) {
+        let (x0, x1) = (rect.left as usize, rect.right as usize);
+        let w = self.width as usize;
+        for row in rect.top as usize..rect.bottom as usize {
+            let src_row = &src[row * pitch + x0 * 4..row * pitch + x1 * 4];
+            let dst_row = &mut self.data[(row * w + x0) * 4..(row * w + x1) * 4];
+            for (d, s) in dst_row.chunks_exact_mut(4).zip(src_row.chunks_exact(4)) {
+                d[0] = s[2];
+                d[1] = s[1];
+                d[2] = s[0];
+                d[3] = 255;
+            }
+        }
+    }
+}
+
+fn create_d3d_device() -> Result<(ID3D11Device, ID3D11DeviceContext), String> {
+    let mut device: Option<ID3D11Device> = None;
+    let mut context: Option<ID3D11DeviceContext> = None;
+    unsafe {
+        D3D11CreateDevice(
+            None,
+            D3D_DRIVER_TYPE_HARDWARE,
+            HMODULE::default(),
+            D3D11_CREATE_DEVICE_BGRA_SUPPORT,
+            None,
+            D3D11_SDK_VERSION,
+            Some(&mut device),
+            None,
+            Some(&mut context),
+        )
+    }
+    .map_err(|e| format!("D3D11 设备创建失败: {}", e))?;
+    match (device, context) {
+        (Some(d), Some(c)) => Ok((d, c)),
+        _ => Err("D3D11 设备创建失败".to_string()),
+    }
+}
+
+/// 将 GPU 纹理拷贝到 staging 纹理并读回 CPU (BGRA -> RGBA)
+fn read_texture(
+    device: &ID3D11Device,
+    context: &ID3D11DeviceContext,
+    texture: &ID3D11Texture2D,
+    cache: &mut FrameCache,
+) -> Result<(), String> {
+    unsafe {
+        let mut desc = D3D11_TEXTURE2D_DESC::default();
+        texture.GetDesc(&mut desc);
+        desc.Usage = D3D11_USAGE_STAGING;
+        desc.BindFlags = 0;
+        desc.CPUAccessFlags = D3D11_CPU_ACCESS_READ.0 as u32;
+        desc.MiscFlags = 0;
+        desc.MipLevels = 1;
+        desc.ArraySize = 1;
+
+        let mut staging: Option<ID3D11Texture2D> = None;
+        device
+            .CreateTexture2D(&desc, None, Some(&mut staging))
+            .map_err(|e| format!("staging 纹理创建失败: {}", e))?;
+        let staging = staging.ok_or("staging 纹理创建失败")?;
+
+        let src: ID3D11Resource = texture.cast().map_err(|e| e.to_string())?;
+        let dst: ID3D11Resource = staging.cast().map_err(|e| e.to_string())?;
+        context.CopyResource(&dst, &src);
+
+        let mut mapped = D3D11_MAPPED_SUBRESOURCE::default();
+        context
+            .Map(&dst, 0, D3D11_MAP_READ, 0, Some(&mut mapped))
+            .map_err(|e| format!("纹理映射失败: {}", e))?;
+
+        let (w, h) = (desc.Width, desc.Height);
+        let pitch = mapped.RowPitch as usize;
+        let src_bytes = std::slice::from_raw_parts(mapped.pData as *const u8, pitch * h as usize);
+
+        cache.width = w;
+        cache.height = h;
+        cache.data.resize((w * h * 4) as usize, 0);
+        cache.write_bgra(src_bytes, pitch, RECT { left: 0, top: 0, right: w as i32, bottom: h as i32 });
+
+        context.Unmap(&dst, 0);
+    }
+    Ok(())
+}
+
+// ==========================================
+// 6. DXGI Desktop Duplication 后端
+// ==========================================
+struct DxgiState {
+    device: ID3D11Device,
+    context: ID3D11DeviceContext,
+    output: IDXGIOutput1,
+    duplication: IDXGIOutputDuplication,
+    /// 常驻的 CPU 可读纹理，尺寸与桌面一致；每帧只把脏区域拷进来
+    staging: Option<ID3D11Texture2D>,
+    /// 下一帧必须整帧读取 (首帧、桌面尺寸变化、复制会话重建后)
+    full: bool,
+    /// 帧元数据 (移动区域 / 脏矩形) 的缓冲区，按需扩容
+    move_rects: Vec<DXGI_OUTDUPL_MOVE_RECT>,
+    dirty_rects: Vec<RECT>,
+    cache: FrameCache,
+}
+
+pub struct DxgiCapture {
+    state: Mutex<DxgiState>,
+    monitor: Option<Monitor>,
+}
+
+// COM 对象只在 Mutex 保护下访问
+unsafe impl Send for DxgiCapture {}
+unsafe impl Sync for DxgiCapture {}
+
+impl DxgiCapture {
+    /// 截取 monitor (None 为显卡的第一个输出，通常即主显示器)
+    pub fn new(monitor: Option<Monitor>) -> Result<Self, String> {
+        let (device, context) = create_d3d_device()?;
+        let (output, duplication) = unsafe {
+            let dxgi_device: IDXGIDevice = device.cast().map_err(|e| e.to_string())?;
+            let adapter = dxgi_device.GetAdapter().map_err(|e| format!("获取显卡失败: {}", e))?;
+            let output = match monitor {
+                None => adapter.EnumOutputs(0).map_err(|e| format!("获取显示输出失败: {}", e))?,
+                // 只能复制与 D3D 设备同一块显卡上的输出
+                Some(m) => (0..)
+                    .map_while(|i| adapter.EnumOutputs(i).ok())
+                    .find(|o| {
+                        let mut desc = DXGI_OUTPUT_DESC::default();
+                        o.GetDesc(&mut desc).is_ok() && desc.Monitor.0 == m.handle
+                    })
+                    .ok_or("该显示器不在默认显卡上，DXGI 无法截取 (可改用 wgc / gdi)")?,
+            };
+            let output1: IDXGIOutput1 = output.cast().map_err(|e| e.to_string())?;
+            let duplication = output1
+                .DuplicateOutput(&device)
+                .map_err(|e| format!("DuplicateOutput 失败: {}", e))?;
+            (output1, duplication)
+        };
+        Ok(Self {
+            state: Mutex::new(DxgiState {
+                device,
+                context,
+                output,
+                duplication,
+                staging: None,
+                full: true,
+                move_rects: Vec::new(),
+                dirty_rects: Vec::new(),
+                cache: FrameCache { data: Vec::new(), width: 0, height: 0 },
+            }),
+            monitor,
+        })
+    }
+}
+
+impl DxgiState {
+    /// 获取最新帧；画面无变化 (超时) 时保留上一帧缓存
+    fn refresh(&mut self, timeout_ms: u32) -> Result<(), String> {
+        unsafe {
+            let mut info = DXGI_OUTDUPL_FRAME_INFO::default();
+            let mut resource: Option<IDXGIResource> = None;
+            match self.duplication.AcquireNextFrame(timeout_ms, &mut info, &mut resource) {
+                Ok(()) => {}
+                Err(e) if e.code() == DXGI_ERROR_WAIT_TIMEOUT => return Ok(()),
+                // 分辨率切换、UAC 安全桌面、全屏独占切换后复制会话失效，需重新建立
+                Err(e) if e.code() == DXGI_ERROR_ACCESS_LOST => {
+                    self.duplication = self
+                        .output
+                        .DuplicateOutput(&self.device)
+                        .map_err(|e| format!("重建 DuplicateOutput 失败: {}", e))?;
+                    self.full = true;
+                    return if self.cache.width > 0 { Ok(()) } else { Err("DXGI 复制会话已重建，等待新帧".to_string()) };
+                }
+                Err(e) => return Err(format!("AcquireNextFrame 失败: {}", e)),
+            }
+
+            // LastPresentTime 为 0 表示只有鼠标指针变化，画面内容没变
+            let result = match resource {
+                Some(res) if info.LastPresentTime != 0 || self.full => match res.cast::<ID3D11Texture2D>() {
+                    Ok(tex) => self.update(&tex, &info),
+                    Err(e) => Err(e.to_string()),
+                },
+                _ => Ok(()),
+            };
+            let _ = self.duplication.ReleaseFrame();
+            result
+        }
+    }
+
+    /// 把本帧变化的区域读回 CPU 缓存；整帧读取只发生在首帧与尺寸变化时
+    unsafe fn update(&mut self, texture: &ID3D11Texture2D, info: &DXGI_OUTDUPL_FRAME_INFO) -> Result<(), String> {
+        let mut desc = D3D11_TEXTURE2D_DESC::default();
+        texture.GetDesc(&mut desc);
+        let (w, h) = (desc.Width, desc.Height);
+        if self.staging.is_none() || self.cache.width != w || self.cache.height != h {
+            desc.Usage = D3D11_USAGE_STAGING;
+            desc.BindFlags = 0;
+            desc.CPUAccessFlags = D3D11_CPU_ACCESS_READ.0 as u32;
+            desc.MiscFlags = 0;
+            desc.MipLevels = 1;
+            desc.ArraySize = 1;
+            let mut staging: Option<ID3D11Texture2D> = None;
+            self.device
+                .CreateTexture2D(&desc, None, Some(&mut staging))
+                .map_err(|e| format!("staging 纹理创建失败: {}", e))?;
+            self.staging = Some(staging.ok_or("staging 纹理创建失败")?);
+            self.cache.width = w;
+            self.cache.height = h;
+            self.cache.data.resize((w * h * 4) as usize, 0);
+            self.full = true;
+        }
+
+        let full = RECT { left: 0, top: 0, right: w as i32, bottom: h as i32 };
+        let rects = if self.full { vec![full] } else { self.changed_rects(info, full)? };
+        if rects.is_empty() {
+            return Ok(());
+        }
+
+        let staging = self.staging.as_ref().ok_or("staging 纹理创建失败")?;
+        let src: ID3D11Resource = texture.cast().map_err(|e| e.to_string())?;
+        let dst: ID3D11Resource = staging.cast().map_err(|e| e.to_string())?;
+        for r in &rects {
+            let region = D3D11_BOX {
+                left: r.left as u32,
+                top: r.top as u32,
+                front: 0,
+                right: r.right as u32,
+                bottom: r.bottom as u32,
+                back: 1,
+            };
+            self.context
+                .CopySubresourceRegion(&dst, 0, r.left as u32, r.top as u32, 0, &src, 0, Some(&region));
+        }
+
+        let mut mapped = D3D11_MAPPED_SUBRESOURCE::default();
+        self.context
+            .Map(&dst, 0, D3D11_MAP_READ, 0, Some(&mut mapped))
+            .map_err(|e| format!("纹理映射失败: {}", e))?;
+        let pitch = mapped.RowPitch as usize;
+        let src_bytes = std::slice::from_raw_parts(mapped.pData as *const u8, pitch * h as usize);
+        for r in rects {
+            self.cache.write_bgra(src_bytes, pitch, r);
+        }
+        self.context.Unmap(&dst, 0);
+        self.full = false;
+        Ok(())
+    }
+
+    /// 本帧的脏矩形与移动区域的目标矩形 (移动后的内容已在桌面纹理中，按脏区域重新读取即可)
+    unsafe fn changed_rects(&mut self, info: &DXGI_OUTDUPL_FRAME_INFO, bounds: RECT) -> Result<Vec<RECT>, String> {
+        let size = info.TotalMetadataBufferSize as usize;
+        if size == 0 {
+            return Ok(Vec::new());
+        }
+        // 两类元数据共用 TotalMetadataBufferSize，各自按最坏情况分配
+        self.move_rects.resize(size / std::mem::size_of::<DXGI_OUTDUPL_MOVE_RECT>() + 1, Default::default());
+        self.dirty_rects.resize(size / std::mem::size_of::<RECT>() + 1, Default::default());
+        let mut rects = Vec::new();
+
+        let mut used = 0u32;
+        self.duplication
+            .GetFrameMoveRects(
+                std::mem::size_of_val(self.move_rects.as_slice()) as u32,
+                self.move_rects.as_mut_ptr(),
+                &mut used,
+            )
+            .map_err(|e| format!("GetFrameMoveRects 失败: {}", e))?;
+        let count = used as usize / std::mem::size_of::<DXGI_OUTDUPL_MOVE_RECT>();
+        rects.extend(self.move_rects[..count].iter().map(|m| m.DestinationRect));
+
+        self.duplication
+            .GetFrameDirtyRects(
+                std::mem::size_of_val(self.dirty_rects.as_slice()) as u32,
+                self.dirty_rects.as_mut_ptr(),
+                &mut used,
+            )
+            .map_err(|e| format!("GetFrameDirtyRects 失败: {}", e))?;
+        let count = used as usize / std::mem::size_of::<RECT>();
+        rects.extend_from_slice(&self.dirty_rects[..count]);
+
+        Ok(rects
+            .into_iter()
+            .map(|r| RECT {
+                left: r.left.clamp(0, bounds.right),
+                top: r.top.clamp(0, bounds.bottom),
+                right: r.right.clamp(0, bounds.right),
+                bottom: r.bottom.clamp(0, bounds.bottom),
+            })
+            .filter(|r| r.right > r.left && r.bottom > r.top)
+            .collect())
+    }
+}
+
+impl CaptureBackend for DxgiCapture {
+    fn name(&self) -> &'static str { "dxgi" }
+
+    fn capture_area(&self, x: i32, y: i32, w: u32, h: u32) -> Result<RgbaImage, String> {
+        let mut state = self.state.lock().map_err(|_| "DXGI 状态锁中毒".to_string())?;
+        // 首帧需要等待桌面合成器产出画面；之后不等待，画面没变化时直接使用缓存
+        let timeout = if state.cache.width == 0 { 500 } else { 0 };
+        state.refresh(timeout)?;
+        state.cache.crop(x, y, w, h)
+    }
+
+    fn frame_size(&self) -> Option<(u32, u32)> {
+        monitor_size(self.monitor)
+    }
+
+    fn origin(&self) -> (i32, i32) {
+        monitor_origin(self.monitor)
+    }
+}
+
+// ==========================================
+// 7. Windows.Graphics.Capture 后端
+// ==========================================
+struct WgcState {
+    device: ID3D11Device,
+    context: ID3D11DeviceContext,
+    d3d_device: IDirect3DDevice,
+    frame_pool: Direct3D11CaptureFramePool,
+    /// 帧池当前的缓冲区尺寸；窗口改变大小后按新尺寸重建
+    size: SizeInt32,
+    _session: GraphicsCaptureSession,
+    cache: FrameCache,
+}
+
+pub struct WgcCapture {
+    state: Mutex<WgcState>,
+    monitor: Option<Monitor>,
+    /// 绑定窗口时直接捕获该窗口的画面 (被遮挡或在其他显示器上也能截到)，坐标按客户区解释
+    window: Option<WindowHandle>,
+}
+
+unsafe impl Send for WgcCapture {}
+unsafe impl Sync for WgcCapture {}
+
+impl WgcCapture {
+    /// 捕获 monitor (None 为主显示器)
+    pub fn new(monitor: Option<Monitor>) -> Result<Self, String> {
+        let item = unsafe {
+            let interop = windows::core::factory::<GraphicsCaptureItem, IGraphicsCaptureItemInterop>()
+                .map_err(|e| format!("WGC 不可用: {}", e))?;
+            let handle = match monitor {
+                Some(m) => HMONITOR(m.handle),
+                None => MonitorFromPoint(POINT { x: 0, y: 0 }, MONITOR_DEFAULTTOPRIMARY),
+            };
+            interop
+                .CreateForMonitor::<_, GraphicsCaptureItem>(handle)
+                .map_err(|e| format!("CreateForMonitor 失败: {}", e))?
+        };
+        Self::from_item(item, monitor, None)
+    }
+
+    /// 捕获单个窗口；窗口被其他窗口覆盖时画面依然正确 (最小化时保留最后一帧)
+    pub fn for_window(hwnd: WindowHandle) -> Result<Self, String> {
+        let item = unsafe {
+            let interop = windows::core::factory::<GraphicsCaptureItem, IGraphicsCaptureItemInterop>()
+                .map_err(|e| format!("WGC 不可用: {}", e))?;
+            interop
+                .CreateForWindow::<_, GraphicsCaptureItem>(HWND(hwnd))
+                .map_err(|e| format!("CreateForWindow 失败: {}", e))?
+        };
+        Self::from_item(item, None, Some(hwnd))
+    }
+
+    fn from_item(item: GraphicsCaptureItem, monitor: Option<Monitor>, window: Option<WindowHandle>) -> Result<Self, String> {
+        let (device, context) = create_d3d_device()?;
+        let d3d_device: IDirect3DDevice = unsafe {
+            let dxgi_device: IDXGIDevice = device.cast().map_err(|e| e.to_string())?;
+            let inspectable: IInspectable = CreateDirect3D11DeviceFromDXGIDevice(&dxgi_device)
+                .map_err(|e| format!("WinRT 设备创建失败: {}", e))?;
+            inspectable.cast().map_err(|e| e.to_string())?
+        };
+
+        let size = item.Size().map_err(|e| e.to_string())?;
+        let frame_pool = Direct3D11CaptureFramePool::CreateFreeThreaded(
+            &d3d_device,
+            DirectXPixelFormat::B8G8R8A8UIntNormalized,
+            2,
+            size,
+        )
+        .map_err(|e| format!("帧池创建失败: {}", e))?;
+        let session = frame_pool
+            .CreateCaptureSession(&item)
+            .map_err(|e| format!("捕获会话创建失败: {}", e))?;
+        // 不把鼠标光标画进画面 (Windows 10 2004 起支持，旧系统忽略)
+        let _ = session.SetIsCursorCaptureEnabled(false);
+        session.StartCapture().map_err(|e| format!("启动捕获失败: {}", e))?;
+
+        Ok(Self {
+            state: Mutex::new(WgcState {
+                device,
+                context,
+                d3d_device,
+                frame_pool,
+                size,
+                _session: session,
+                cache: FrameCache { data: Vec::new(), width: 0, height: 0 },
+            }),
+            monitor,
+            window,
+        })
+    }
+}
+
+impl WgcState {
+    /// 取出帧池中最新的一帧；没有新帧时保留上一帧缓存
+    fn refresh(&mut self, timeout: Duration) -> Result<(), String> {
+        let start = Instant::now();
+        let mut latest = None;
+        loop {
+            // 清空积压，只保留最新帧
+            while let Ok(frame) = self.frame_pool.TryGetNextFrame() {
+                latest = Some(frame);
+            }
+            if latest.is_some() || self.cache.width > 0 || start.elapsed() >= timeout {
+                break;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        let frame = match latest {
+            Some(f) => f,
+            None if self.cache.width > 0 => return Ok(()),
+            None => return Err("WGC 等待首帧超时".to_string()),
+        };
+
+        // 捕获目标的尺寸变了 (窗口缩放)，下一帧起使用新尺寸的缓冲区
+        if let Ok(content) = frame.ContentSize() {
+            if content != self.size {
+                self.frame_pool
+                    .Recreate(&self.d3d_device, DirectXPixelFormat::B8G8R8A8UIntNormalized, 2, content)
+                    .map_err(|e| format!("帧池重建失败: {}", e))?;
+                self.size = content;
+            }
+        }
+
+        let surface = frame.Surface().map_err(|e| e.to_string())?;
+        let texture: ID3D11Texture2D = unsafe {
+            let access: IDirect3DDxgiInterfaceAccess = surface.cast().map_err(|e| e.to_string())?;
+            access.GetInterface().map_err(|e| e.to_string())?
+        };
+        let result = read_texture(&self.device, &self.context, &texture, &mut self.cache);
+        let _ = frame.Close();
+        result
+    }
+}
+
+/// 窗口画面包含标题栏与边框 (范围为 DWM 的可见边界)，返回客户区左上角在画面中的位置
+fn client_offset_in_frame(hwnd: WindowHandle) -> Option<(i32, i32)> {
+    let (cx, cy) = window::client_origin(hwnd)?;
+    let mut bounds = RECT::default();
+    unsafe {
+        DwmGetWindowAttribute(
+            HWND(hwnd),
+            DWMWA_EXTENDED_FRAME_BOUNDS,
+            &mut bounds as *mut RECT as *mut c_void,
+            std::mem::size_of::<RECT>() as u32,
+        )
+        .ok()?;
+    }
+    Some(((cx - bounds.left).max(0), (cy - bounds.top).max(0)))
+}
+
+impl CaptureBackend for WgcCapture {
+    fn name(&self) -> &'static str {
+        if self.window.is_some() { "wgc-window" } else { "wgc" }
+    }
+
+    fn capture_area(&self, x: i32, y: i32, w: u32, h: u32) -> Result<RgbaImage, String> {
+        let (dx, dy) = match self.window {
+            Some(hwnd) => client_offset_in_frame(hwnd).ok_or("目标窗口已关闭或不可用")?,
+            None => (0, 0),
+        };
+        let mut state = self.state.lock().map_err(|_| "WGC 状态锁中毒".to_string())?;
+        state.refresh(Duration::from_millis(500))?;
+        state.cache.crop(dx + x, dy + y, w, h)
+    }
+
+    fn frame_size(&self) -> Option<(u32, u32)> {
+        match self.window {
+            Some(hwnd) => window::client_size(hwnd),
+            None => monitor_size(self.monitor),
+        }
+    }
+
+    fn origin(&self) -> (i32, i32) {
+        match self.window {
+            Some(hwnd) => window::client_origin(hwnd).unwrap_or((0, 0)),
+            None => monitor_origin(self.monitor),
+        }
+    }
+}
+
+// ==========================================
+// 8. 模拟后端 (静态截图，用于离线调试)
+// ==========================================
+pub struct SimulatedCapture {
+    frame: RgbaImage,
+}
+
+impl SimulatedCapture {
+    pub fn new(path: &str) -> Result<Self, String> {
+        let img = image::open(path).map_err(|e| format!("无法加载模拟截图 {}: {}", path, e))?;
+        Ok(Self { frame: img.to_rgba8() })
+    }
+}
+
+impl CaptureBackend for SimulatedCapture {
+    fn name(&self) -> &'static str { "sim" }
+
+    fn capture_area(&self, x: i32, y: i32, w: u32, h: u32) -> Result<RgbaImage, String> {
+        let (fw, fh) = self.frame.dimensions();
+        if fw == 0 || fh == 0 {
+            return Err("模拟截图为空".to_string());
+        }
+        let x0 = (x.max(0) as u32).min(fw - 1);
+        let y0 = (y.max(0) as u32).min(fh - 1);
+        let cw = w.max(1).min(fw - x0);
+        let ch = h.max(1).min(fh - y0);
+        Ok(image::imageops::crop_imm(&self.frame, x0, y0, cw, ch).to_image())
+    }
+
+    fn frame_size(&self) -> Option<(u32, u32)> {
+        Some(self.frame.dimensions())
+    }
+}
+
+// ==========================================
+// 9. 窗口绑定 (多开时每个实例只看自己的窗口)
+// ==========================================
+/// 从显示器画面上裁剪窗口客户区所在的矩形：坐标按窗口客户区解释，每次截图前重新读取窗口位置 (窗口可被拖动)
+/// 截到的是屏幕上该位置的内容而不是窗口本身，窗口被遮挡时会截到遮挡物；捕获窗口本身见 WgcCapture::for_window
+pub struct ClientAreaCrop {
+    hwnd: WindowHandle,
+    inner: Box<dyn CaptureBackend>,
+}
+
+impl ClientAreaCrop {
+    pub fn new(hwnd: WindowHandle, inner: Box<dyn CaptureBackend>) -> Self {
+        Self { hwnd, inner }
+    }
+}
+
+impl CaptureBackend for ClientAreaCrop {
+    fn name(&self) -> &'static str { self.inner.name() }
+
+    fn capture_area(&self, x: i32, y: i32, w: u32, h: u32) -> Result<RgbaImage, String> {
+        let (ox, oy) = window::client_origin(self.hwnd).ok_or("目标窗口已关闭或不可用")?;
+        // 内层后端的坐标相对于其所截显示器的左上角
+        let (mx, my) = self.inner.origin();
+        self.inner.capture_area(ox - mx + x, oy - my + y, w, h)
+    }
+
+    fn frame_size(&self) -> Option<(u32, u32)> {
+        window::client_size(self.hwnd)
+    }
+
+    fn origin(&self) -> (i32, i32) {
+        window::client_origin(self.hwnd).unwrap_or((0, 0))
+    }
+}
+
+// ==========================================
+// 10. 工厂函数 (带自动回退)
+// ==========================================
+fn create_single(name: &str, cfg: &CaptureConfig, monitor: Option<Monitor>) -> Result<Box<dyn CaptureBackend>, String> {
+    let backend: Box<dyn CaptureBackend> = match name.to_lowercase().as_str() {
+        "gdi" => Box::new(GdiCapture::new(monitor)),
+        "dxgi" => Box::new(DxgiCapture::new(monitor)?),
+        "wgc" => Box::new(WgcCapture::new(monitor)?),
+        "sim" => Box::new(SimulatedCapture::new(&cfg.sim_image)?),
+        other => return Err(format!("未知的截图后端: {}", other)),
+    };
+    // 试截 1 像素，确认后端真正可用
+    backend.capture_area(0, 0, 1, 1)?;
+    Ok(backend)
+}
+
+/// 按序号取显示器；序号越界时提示并使用主显示器
+fn resolve_monitor(index: Option<usize>) -> Option<Monitor> {
+    let index = index?;
+    let all = monitors();
+    match all.get(index) {
+        Some(m) => {
+            println!("🖥️ 截取显示器 #{} ({}x{} @ {},{})", index, m.width, m.height, m.x, m.y);
+            Some(*m)
+        }
+        None => {
+            println!("⚠️ 显示器 #{} 不存在 (共 {} 个)，使用主显示器", index, all.len());
+            None
+        }
+    }
+}
+
+/// 在 monitor 上按 backend -> fallback 的顺序创建第一个可用的截图后端，全部失败时使用 GDI
+fn create_on(cfg: &CaptureConfig, monitor: Option<Monitor>) -> Box<dyn CaptureBackend> {
+    let mut candidates = vec![cfg.backend.clone()];
+    for f in &cfg.fallback {
+        if !candidates.contains(f) {
+            candidates.push(f.clone());
+        }
+    }
+
+    for name in &candidates {
+        match create_single(name, cfg, monitor) {
+            Ok(b) => {
+                println!("📷 截图后端: [{}]", b.name());
+                return b;
+            }
+            Err(e) => println!("⚠️ 截图后端 [{}] 不可用: {}", name, e),
+        }
+    }
+    println!("⚠️ 所有截图后端均不可用，回退到 [gdi]");
+    Box::new(GdiCapture::new(monitor))
+}
+
+/// 按 [capture] 的 backend / fallback / monitor 创建截图后端 (坐标相对于所截显示器)
+pub fn create_backend(cfg: &CaptureConfig) -> Box<dyn CaptureBackend> {
+    create_on(cfg, resolve_monitor(cfg.monitor))
+}
+
+/// 只截取 hwnd 客户区的后端
+/// backend 为 wgc 时直接捕获窗口本身 (被遮挡、在后台时也能识别)；其余后端从窗口所在的显示器上裁剪
+pub fn create_window_backend(cfg: &CaptureConfig, hwnd: WindowHandle) -> Box<dyn CaptureBackend> {
+    if cfg.backend.eq_ignore_ascii_case("wgc") {
+        match WgcCapture::for_window(hwnd).and_then(|b| b.capture_area(0, 0, 1, 1).map(|_| b)) {
+            Ok(b) => {
+                println!("📷 截图后端: [{}]", b.name());
+                return Box::new(b);
+            }
+            Err(e) => println!("⚠️ 截图后端 [wgc-window] 不可用: {}", e),
+        }
+    }
+    let index = cfg.monitor.or_else(|| {
+        let (x, y) = window::client_origin(hwnd)?;
+        monitor_index_at(x, y)
+    });
+    println!("📷 从显示器画面裁剪窗口客户区 (窗口被遮挡时会截到遮挡物，后台识别请使用 backend = \"wgc\")");
+    Box::new(ClientAreaCrop::new(hwnd, create_on(cfg, resolve_monitor(index))))
+}
+
+/// 按 [capture] 创建截图后端，并返回截图坐标系的原点 (点击坐标据此换算为屏幕坐标)
+/// 指定 window 时坐标按窗口客户区解释；找不到窗口时退回整屏截图
+pub fn create_target(cfg: &CaptureConfig) -> (Box<dyn CaptureBackend>, Origin) {
+    if let Some(spec) = cfg.window.as_deref().filter(|s| !s.is_empty()) {
+        match window::find_by_spec(spec) {
+            Ok(hwnd) => {
+                println!("🪟 截图目标窗口: {} (0x{:X})", spec, hwnd);
+                return (create_window_backend(cfg, hwnd), Origin::Window(hwnd));
+            }
+            Err(e) => println!("⚠️ {}，改为截取整个显示器", e),
+        }
+    }
+    let backend = create_backend(cfg);
+    let (x, y) = backend.origin();
+    (backend, Origin::Fixed(x, y))
+}
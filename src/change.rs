@@ -0,0 +1,43 @@
+// src/change.rs
+// 画面变化检测：把截图切成小块求平均亮度，比较两次截图的块签名即可判断画面是否变化
+use image::RgbaImage;
+
+/// 分块边长 (px)；块越小越敏感，文字区域里换一个字也能察觉
+const TILE: u32 = 12;
+/// 每个方向最多的块数，大区域的签名长度保持有限
+const MAX_TILES: u32 = 32;
+/// 单块平均亮度变化超过此值视为画面变化；低于此值的差异按压缩噪声、抖动处理
+const TILE_TOLERANCE: u8 = 6;
+
+/// 一张截图的分块亮度签名
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+    width: u32,
+    height: u32,
+    tiles: Vec<u8>,
+}
+
+impl Signature {
+    pub fn of(img: &RgbaImage) -> Self {
+        let (width, height) = img.dimensions();
+        let cols = width.div_ceil(TILE).clamp(1, MAX_TILES);
+        let rows = height.div_ceil(TILE).clamp(1, MAX_TILES);
+        let mut sums = vec![(0u64, 0u64); (cols * rows) as usize];
+        for (x, y, p) in img.enumerate_pixels() {
+            let i = ((y * rows / height.max(1)) * cols + x * cols / width.max(1)) as usize;
+            // 整数近似的 Rec.601 亮度
+            let luma = (p[0] as u64 * 299 + p[1] as u64 * 587 + p[2] as u64 * 114) / 1000;
+            sums[i].0 += luma;
+            sums[i].1 += 1;
+        }
+        let tiles = sums.into_iter().map(|(s, n)| (s / n.max(1)) as u8).collect();
+        Self { width, height, tiles }
+    }
+
+    /// 尺寸不同，或任意一块的平均亮度变化超过容差
+    pub fn changed(&self, other: &Signature) -> bool {
+        self.width != other.width
+            || self.height != other.height
+            || self.tiles.iter().zip(&other.tiles).any(|(a, b)| a.abs_diff(*b) > TILE_TOLERANCE)
+    }
+}
@@ -0,0 +1,156 @@
+// src/checkpoint.rs
+use crate::stats::DEFAULT_STATE_DB;
+use chrono::{DateTime, Local, TimeZone};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::time::Duration;
+
+// ==========================================
+// 1. 存储层 (与运行统计共用 SQLite 状态库)
+// ==========================================
+
+/// 一条进度检查点：每个 (owner, handler) 只保留最新一条
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    pub owner: String,
+    pub handler: String,
+    pub scene_id: String,
+    /// 处理器自定义的进度数据 (JSON)
+    pub data: String,
+    pub updated_at: DateTime<Local>,
+}
+
+impl Checkpoint {
+    pub fn age(&self) -> Duration {
+        (Local::now() - self.updated_at).to_std().unwrap_or_default()
+    }
+}
+
+pub struct CheckpointStore {
+    conn: Connection,
+}
+
+impl CheckpointStore {
+    pub fn open(path: &str) -> Result<Self, String> {
+        let conn = Connection::open(path).map_err(|e| format!("无法打开状态库 {}: {}", path, e))?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS checkpoints (
+                owner      TEXT    NOT NULL,
+                handler    TEXT    NOT NULL,
+                scene_id   TEXT    NOT NULL,
+                data       TEXT    NOT NULL,
+                updated_at INTEGER NOT NULL,
+                PRIMARY KEY (owner, handler)
+            );",
+        )
+        .map_err(|e| format!("检查点表初始化失败: {}", e))?;
+        Ok(Self { conn })
+    }
+
+    pub fn save(&self, owner: &str, handler: &str, scene_id: &str, data: &str) -> Result<(), String> {
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO checkpoints (owner, handler, scene_id, data, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![owner, handler, scene_id, data, Local::now().timestamp()],
+            )
+            .map_err(|e| format!("写入检查点失败: {}", e))?;
+        Ok(())
+    }
+
+    pub fn load(&self, owner: &str, handler: &str) -> Result<Option<Checkpoint>, String> {
+        self.conn
+            .query_row(
+                "SELECT owner, handler, scene_id, data, updated_at FROM checkpoints
+                 WHERE owner = ?1 AND handler = ?2",
+                params![owner, handler],
+                |row| {
+                    let ts: i64 = row.get(4)?;
+                    Ok(Checkpoint {
+                        owner: row.get(0)?,
+                        handler: row.get(1)?,
+                        scene_id: row.get(2)?,
+                        data: row.get(3)?,
+                        updated_at: Local.timestamp_opt(ts, 0).single().unwrap_or_else(Local::now),
+                    })
+                },
+            )
+            .optional()
+            .map_err(|e| format!("读取检查点失败: {}", e))
+    }
+
+    pub fn clear(&self, owner: &str, handler: &str) -> Result<(), String> {
+        self.conn
+            .execute("DELETE FROM checkpoints WHERE owner = ?1 AND handler = ?2", params![owner, handler])
+            .map_err(|e| format!("删除检查点失败: {}", e))?;
+        Ok(())
+    }
+}
+
+// ==========================================
+// 2. 处理器侧句柄
+// ==========================================
+
+/// 交给单个处理器使用的检查点句柄；状态库不可用时所有操作静默跳过
+pub struct TaskCheckpoint {
+    store: Option<CheckpointStore>,
+    owner: String,
+    handler: String,
+    scene_id: String,
+}
+
+impl TaskCheckpoint {
+    pub fn open(owner: &str, handler: &str, scene_id: &str) -> Self {
+        let store = match CheckpointStore::open(DEFAULT_STATE_DB) {
+            Ok(s) => Some(s),
+            Err(e) => {
+                println!("⚠️ 检查点不可用，本次任务中断后将从头开始 ({})", e);
+                None
+            }
+        };
+        Self {
+            store,
+            owner: owner.to_string(),
+            handler: handler.to_string(),
+            scene_id: scene_id.to_string(),
+        }
+    }
+
+    /// 不记录进度 (处理器被单独调用时使用)
+    pub fn disabled() -> Self {
+        Self { store: None, owner: String::new(), handler: String::new(), scene_id: String::new() }
+    }
+
+    /// 读取同一场景、未过期的检查点；场景不符或已过期的检查点视为无效
+    pub fn load<T: DeserializeOwned>(&self, max_age: Duration) -> Option<T> {
+        let cp = self.store.as_ref()?.load(&self.owner, &self.handler).ok()??;
+        if cp.scene_id != self.scene_id || cp.age() > max_age {
+            return None;
+        }
+        serde_json::from_str(&cp.data).ok()
+    }
+
+    pub fn save<T: Serialize>(&self, data: &T) {
+        let Some(store) = &self.store else { return };
+        let result = serde_json::to_string(data)
+            .map_err(|e| e.to_string())
+            .and_then(|json| store.save(&self.owner, &self.handler, &self.scene_id, &json));
+        if let Err(e) = result {
+            println!("⚠️ {}", e);
+        }
+    }
+
+    pub fn clear(&self) {
+        if let Some(store) = &self.store {
+            if let Err(e) = store.clear(&self.owner, &self.handler) {
+                println!("⚠️ {}", e);
+            }
+        }
+    }
+}
+
+/// 查询某个 owner 下尚未完成的检查点 (用于启动时恢复)
+pub fn pending(owner: &str, handler: &str) -> Option<Checkpoint> {
+    CheckpointStore::open(DEFAULT_STATE_DB).ok()?.load(owner, handler).ok()?
+}
@@ -0,0 +1,36 @@
+// src/clipboard.rs
+use windows::Win32::Foundation::{GlobalFree, HANDLE, HWND};
+use windows::Win32::System::DataExchange::{CloseClipboard, EmptyClipboard, OpenClipboard, SetClipboardData};
+use windows::Win32::System::Memory::{GlobalAlloc, GlobalLock, GlobalUnlock, GMEM_MOVEABLE};
+
+/// 剪贴板格式: UTF-16 文本
+const CF_UNICODETEXT: u32 = 13;
+
+/// 把文本写入系统剪贴板 (覆盖原有内容)，供 Ctrl+V 粘贴输入中文等 HID 键码打不出的字符
+pub fn set_text(text: &str) -> Result<(), String> {
+    let wide: Vec<u16> = text.encode_utf16().chain(std::iter::once(0)).collect();
+    unsafe {
+        OpenClipboard(HWND(0)).map_err(|e| format!("无法打开剪贴板: {}", e))?;
+        let result = write_locked(&wide);
+        let _ = CloseClipboard();
+        result
+    }
+}
+
+/// 须在 OpenClipboard 之后调用；成功后内存归剪贴板所有，失败时自行释放
+unsafe fn write_locked(wide: &[u16]) -> Result<(), String> {
+    EmptyClipboard().map_err(|e| format!("清空剪贴板失败: {}", e))?;
+    let mem = GlobalAlloc(GMEM_MOVEABLE, std::mem::size_of_val(wide)).map_err(|e| format!("分配剪贴板内存失败: {}", e))?;
+    let ptr = GlobalLock(mem) as *mut u16;
+    if ptr.is_null() {
+        let _ = GlobalFree(mem);
+        return Err("锁定剪贴板内存失败".to_string());
+    }
+    std::ptr::copy_nonoverlapping(wide.as_ptr(), ptr, wide.len());
+    let _ = GlobalUnlock(mem);
+    if let Err(e) = SetClipboardData(CF_UNICODETEXT, HANDLE(mem.0 as isize)) {
+        let _ = GlobalFree(mem);
+        return Err(format!("写入剪贴板失败: {}", e));
+    }
+    Ok(())
+}
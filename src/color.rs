@@ -0,0 +1,66 @@
+// src/color.rs
+// 颜色取样：像素 / 区域平均色与容差比较，供颜色锚点与业务模块的简单判断 (血条比例、按钮是否可点)
+use image::RgbaImage;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Rgb {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    /// 解析 "#RRGGBB" 或 "RRGGBB"
+    pub fn from_hex(hex_str: &str) -> Option<Self> {
+        match hex::decode(hex_str.trim().trim_start_matches('#')).ok()?.as_slice() {
+            [r, g, b] => Some(Self::new(*r, *g, *b)),
+            _ => None,
+        }
+    }
+
+    /// 三个通道差值之和
+    pub fn distance(&self, other: &Rgb) -> u16 {
+        self.r.abs_diff(other.r) as u16 + self.g.abs_diff(other.g) as u16 + self.b.abs_diff(other.b) as u16
+    }
+
+    /// 平均每个通道相差不超过 tolerance (与 ui_map 颜色锚点的 tol 含义相同)
+    pub fn matches(&self, other: &Rgb, tolerance: u8) -> bool {
+        self.distance(other) <= tolerance as u16 * 3
+    }
+
+    /// 图像左上角像素；空图像为 None
+    pub fn first_pixel(img: &RgbaImage) -> Option<Self> {
+        img.pixels().next().map(|p| Self::new(p[0], p[1], p[2]))
+    }
+
+    /// 图像全部像素的平均色；空图像为 None
+    pub fn average(img: &RgbaImage) -> Option<Self> {
+        let n = img.pixels().len() as u64;
+        if n == 0 {
+            return None;
+        }
+        let (r, g, b) = img.pixels().fold((0u64, 0u64, 0u64), |(r, g, b), p| (r + p[0] as u64, g + p[1] as u64, b + p[2] as u64));
+        Some(Self::new((r / n) as u8, (g / n) as u8, (b / n) as u8))
+    }
+
+    /// 图像中与本颜色相差在容差内的像素比例 (0~1)，如血条中红色像素的占比
+    pub fn ratio_in(&self, img: &RgbaImage, tolerance: u8) -> f32 {
+        let n = img.pixels().len();
+        if n == 0 {
+            return 0.0;
+        }
+        let hits = img.pixels().filter(|p| self.matches(&Self::new(p[0], p[1], p[2]), tolerance)).count();
+        hits as f32 / n as f32
+    }
+}
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02X}{:02X}{:02X}", self.r, self.g, self.b)
+    }
+}
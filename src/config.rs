@@ -0,0 +1,77 @@
+// src/config.rs
+use crate::actor::FailoverConfig;
+use crate::capture::CaptureConfig;
+use crate::detector::DetectorConfig;
+use crate::dump::DumpConfig;
+use crate::ocr::OcrConfig;
+use crate::hardware::{HardwareConfig, HeartbeatConfig, ThrottleConfig};
+use crate::identity::IdentityConfig;
+use crate::keymap::KeyboardConfig;
+use crate::profile::HumanConfig;
+use crate::instances::InstanceConfig;
+use crate::sync::SyncConfig;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+/// 程序全局配置 (config.toml)，所有字段均可缺省
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct AppConfig {
+    #[serde(default)]
+    pub capture: CaptureConfig,
+    /// 文字识别后端 ([ocr])
+    #[serde(default)]
+    pub ocr: OcrConfig,
+    /// 动态实体目标检测 ([detector])
+    #[serde(default)]
+    pub detector: DetectorConfig,
+    #[serde(default)]
+    pub sync: SyncConfig,
+    /// 自制串口固件参数 (波特率、帧间隔、超时)
+    #[serde(default)]
+    pub hardware: HardwareConfig,
+    /// 后台心跳间隔与失联判定 ([heartbeat])
+    #[serde(default)]
+    pub heartbeat: HeartbeatConfig,
+    /// 驱动层限速与指令间随机间隔 ([throttle])
+    #[serde(default)]
+    pub throttle: ThrottleConfig,
+    /// 运行中切换驱动后端的热键与离线自动切换 ([failover])
+    #[serde(default)]
+    pub failover: FailoverConfig,
+    /// 目标机器的键盘布局 ([keyboard])
+    #[serde(default)]
+    pub keyboard: KeyboardConfig,
+    /// 拟人档案选择 ([human])
+    #[serde(default)]
+    pub human: HumanConfig,
+    /// 设备身份轮换 ([identity])
+    #[serde(default)]
+    pub identity: IdentityConfig,
+    /// 导航失败时保存截图与识别报告 ([debug_dump])
+    #[serde(default)]
+    pub debug_dump: DumpConfig,
+    /// 多开实例 ([[instances]])，配合 --instances 使用
+    #[serde(default)]
+    pub instances: Vec<InstanceConfig>,
+}
+
+impl AppConfig {
+    /// 读取配置文件；文件不存在或解析失败时使用默认值
+    pub fn load(path: &str) -> Self {
+        if !Path::new(path).exists() {
+            return Self::default();
+        }
+        match fs::read_to_string(path).map(|c| toml::from_str::<AppConfig>(&c)) {
+            Ok(Ok(cfg)) => cfg,
+            Ok(Err(e)) => {
+                println!("⚠️ 配置文件 {} 解析失败，使用默认配置: {}", path, e);
+                Self::default()
+            }
+            Err(e) => {
+                println!("⚠️ 无法读取配置文件 {}，使用默认配置: {}", path, e);
+                Self::default()
+            }
+        }
+    }
+}
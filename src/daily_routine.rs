@@ -1,10 +1,28 @@
 // src/daily_routine.rs
+use crate::checkpoint::TaskCheckpoint;
+use crate::hardware::{DriverError, MouseButtons};
 use crate::human::HumanDriver;
-use crate::nav::NavEngine;
+use crate::nav::{NavEngine, TextClickOptions};
+use chrono::{Local, Timelike};
+use serde::{Deserialize, Serialize};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 
+/// 领取后按空格跳过奖励弹窗的最多次数
+const CLAIM_SKIP_ATTEMPTS: usize = 3;
+
+/// 单个槽位本轮的处理结果
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SlotOutcome {
+    /// 无需操作 (已完成/未知状态)
+    Idle,
+    /// 领取了奖励
+    Claimed,
+    /// 点击了刷新
+    Refreshed,
+}
+
 /// 定义单个任务槽位的配置
 struct TaskSlot {
     index: usize,
@@ -14,10 +32,20 @@ struct TaskSlot {
     refresh_pos: (u16, u16),
 }
 
+/// 日活进度检查点：已完成的轮次与已消耗的刷新次数 (仅当天有效)
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+struct DailyCheckpoint {
+    round: u32,
+    refresh_count: u32,
+}
+
 pub struct DailyRoutineApp {
     driver: Arc<Mutex<HumanDriver>>,
     nav: Arc<NavEngine>,
     slots: Vec<TaskSlot>,
+    checkpoint: TaskCheckpoint,
+    /// 任务页面的界面 id；设置后每轮开始前确认仍停留在该页面
+    scene: Option<String>,
 }
 
 impl DailyRoutineApp {
@@ -46,26 +74,70 @@ impl DailyRoutineApp {
             },
         ];
 
-        Self { driver, nav, slots }
+        Self { driver, nav, slots, checkpoint: TaskCheckpoint::disabled(), scene: None }
+    }
+
+    /// 启用进度检查点 (中断后继续本日的轮次与刷新预算)
+    pub fn set_checkpoint(&mut self, checkpoint: TaskCheckpoint) {
+        self.checkpoint = checkpoint;
+    }
+
+    /// 设置任务页面的界面 id (ui_map.toml 中 handler = "daily" 的界面)
+    pub fn set_scene(&mut self, id: &str) {
+        self.scene = Some(id.to_string());
+    }
+
+    /// 是否已离开任务页面 (被弹窗挡住或回到了其他界面)；页面没有锚点、无法确认时按仍在页面处理
+    /// (任务页面与上级的赛季任务页共用标签栏，不能用「识别到了别的界面」来判断)
+    fn left_task_page(&self) -> bool {
+        let Some(id) = &self.scene else { return false };
+        if self.nav.check_scene(id) != Some(false) {
+            return false;
+        }
+        match self.nav.detect_current_scene() {
+            Some((current, _)) => println!("⚠️ [Daily] 当前界面为 [{}]，不在任务页面 [{}]", current, id),
+            None => println!("⚠️ [Daily] 任务页面 [{}] 的锚点未命中", id),
+        }
+        true
     }
 
-    /// 执行日活逻辑主入口
-    pub fn run(&self) {
+    /// 执行日活逻辑主入口，返回本次消耗的刷新次数；输入设备失败时中止 (检查点保留)
+    pub fn run(&self) -> Result<u32, DriverError> {
         println!("📅 [Daily] 开始执行日活任务逻辑...");
         
         // 最大轮次，防止无限刷新把钱刷光了
         let max_rounds = 10; 
 
-        for round in 1..=max_rounds {
+        // 每日任务零点重置，检查点只在当天有效
+        let today = Duration::from_secs(Local::now().num_seconds_from_midnight() as u64);
+        let resumed = self.checkpoint.load::<DailyCheckpoint>(today).unwrap_or_default();
+        if resumed.round > 0 {
+            println!(
+                "♻️ [Daily] 从检查点继续: 已完成 {} 轮，已刷新 {} 次",
+                resumed.round, resumed.refresh_count
+            );
+        }
+        let mut refresh_count = resumed.refresh_count;
+
+        for round in (resumed.round + 1)..=max_rounds {
             println!("\n🔄 [Daily] 第 {}/{} 轮扫描...", round, max_rounds);
+            if self.left_task_page() {
+                // 检查点保留，回到任务页面后从本轮继续
+                println!("⚠️ [Daily] 已离开任务页面，停止本次日活");
+                return Ok(refresh_count);
+            }
             
             let mut need_retry = false;
             
             // 遍历 4 个任务槽
             for slot in &self.slots {
-                let processed = self.process_slot(slot);
-                if processed {
-                    need_retry = true;
+                match self.process_slot(slot)? {
+                    SlotOutcome::Claimed => need_retry = true,
+                    SlotOutcome::Refreshed => {
+                        need_retry = true;
+                        refresh_count += 1;
+                    }
+                    SlotOutcome::Idle => {}
                 }
                 // 槽位间稍微停顿，看起来更像人
                 thread::sleep(Duration::from_millis(500)); 
@@ -75,19 +147,22 @@ impl DailyRoutineApp {
                 println!("✅ [Daily] 所有任务已完成或已领取！");
                 break;
             }
+            self.checkpoint.save(&DailyCheckpoint { round, refresh_count });
 
             // 如果本轮有操作（领取或刷新），等待界面动画刷新后继续
             println!("⏳ 等待任务列表刷新 (2秒)...");
-            thread::sleep(Duration::from_secs(2));
+            if let Ok(mut d) = self.driver.lock() {
+                d.idle_humanly(Duration::from_secs(2))?;
+            }
         }
 
-        println!("🏁 [Daily] 日活流程结束。");
+        self.checkpoint.clear();
+        println!("🏁 [Daily] 日活流程结束 (刷新 {} 次)。", refresh_count);
+        Ok(refresh_count)
     }
 
-    /// 处理单个槽位，返回 true 表示进行了操作（需要进入下一轮检查）
-// src/daily_routine.rs
-
-    fn process_slot(&self, slot: &TaskSlot) -> bool {
+    /// 处理单个槽位，返回非 Idle 表示进行了操作（需要进入下一轮检查）
+    fn process_slot(&self, slot: &TaskSlot) -> Result<SlotOutcome, DriverError> {
         // 1. OCR 识别状态
         let text = self.nav.ocr_area(slot.status_rect);
         // 去除空格和换行，防止 OCR 识别出 "已 完 成" 导致匹配失败
@@ -103,27 +178,30 @@ impl DailyRoutineApp {
         // ⚠️ 必须放在最前面！因为 "已领取" 包含 "领取" 字样
         if clean_text.contains("已完成") || clean_text.contains("已领取") {
             println!("      -> ✅ 任务已结束，跳过。");
-            return false; // 不做操作
+            return Ok(SlotOutcome::Idle); // 不做操作
         }
 
         // 2. 【可领取】
         if clean_text.contains("领取") {
             println!("      -> 🎉 发现可领取奖励，执行领取流程...");
+            // A. 按文字定位领取按钮 (布局偏移几个像素也能点中)，定位失败时退回点击整个状态区域
+            if !self.nav.find_text_and_click(slot.status_rect, "领取", &TextClickOptions::default())? {
+                if let Ok(mut d) = self.driver.lock() {
+                    d.click_in_rect_humanly(slot.status_rect, MouseButtons::LEFT)?;
+                }
+            }
             if let Ok(mut d) = self.driver.lock() {
-                // A. 点击状态文字中心 (即领取按钮)
-                let cx = (slot.status_rect[0] + slot.status_rect[2]) / 2;
-                let cy = (slot.status_rect[1] + slot.status_rect[3]) / 2;
-                d.move_to_humanly(cx as u16, cy as u16, 0.5);
-                d.click_humanly(true, false, 0);
-
-                // B. 处理奖励弹窗 (按空格跳过)
+                // B. 处理奖励弹窗：按空格跳过，直到槽位显示 "已领取" (弹窗确实关闭)
                 println!("      -> ⏳ 等待弹窗并按空格跳过...");
                 thread::sleep(Duration::from_millis(1000)); // 等待动画
-                d.key_click(' '); 
-                thread::sleep(Duration::from_millis(1000));
-                d.key_click(' '); // 连按两次防止漏掉
+                for _ in 0..CLAIM_SKIP_ATTEMPTS {
+                    d.key_click(' ')?;
+                    if self.nav.wait_for_text(slot.status_rect, "已领取", Duration::from_millis(1500)).is_ok() {
+                        break;
+                    }
+                }
             }
-            return true; // 做了操作，需要重试扫描
+            return Ok(SlotOutcome::Claimed); // 做了操作，需要重试扫描
         }
 
         // 3. 【未完成】需要刷新
@@ -131,22 +209,22 @@ impl DailyRoutineApp {
             println!("      -> ⚠️ 任务未完成，点击刷新 ({}, {})...", slot.refresh_pos.0, slot.refresh_pos.1);
             if let Ok(mut d) = self.driver.lock() {
                 // 点击对应的刷新按钮
-                d.move_to_humanly(slot.refresh_pos.0, slot.refresh_pos.1, 0.5);
-                d.click_humanly(true, false, 0);
+                d.move_to_humanly(slot.refresh_pos.0, slot.refresh_pos.1, 0.5)?;
+                d.click_humanly(MouseButtons::LEFT, 0)?;
                 
                 // 刷新后的短暂冷却
                 thread::sleep(Duration::from_millis(500));
             }
-            return true; // 做了操作，需要重试扫描
+            return Ok(SlotOutcome::Refreshed); // 做了操作，需要重试扫描
         }
         
         // 4. 【兜底】识别为空或其他未知状态
         if clean_text.is_empty() {
              println!("      -> ⚪ 识别为空 (可能是图标/过暗)，暂跳过");
-             return false;
+             return Ok(SlotOutcome::Idle);
         }
 
         println!("      -> ❓ 未知状态，跳过");
-        false
+        Ok(SlotOutcome::Idle)
     }
 }
\ No newline at end of file
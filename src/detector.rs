@@ -0,0 +1,108 @@
+// src/detector.rs
+// 目标检测：在截图上识别位置不断变化的实体 (敌人、掉落物、防御塔)，返回带标签的框；
+// 模板匹配只适合固定外观的静态元素，跟不上移动中的敌人，这类实体交给 YOLO 类检测模型
+use image::RgbaImage;
+use serde::Deserialize;
+use std::sync::Arc;
+
+// ==========================================
+// 1. 检测结果与后端接口
+// ==========================================
+
+/// 一个检测到的实体；rect 为 [x1, y1, x2, y2]，相对送入检测的图像
+#[derive(Debug, Clone, PartialEq)]
+pub struct Detection {
+    pub label: String,
+    pub score: f32,
+    pub rect: [i32; 4],
+}
+
+impl Detection {
+    /// 框中心点，点击 / 瞄准时使用
+    pub fn center(&self) -> (i32, i32) {
+        ((self.rect[0] + self.rect[2]) / 2, (self.rect[1] + self.rect[3]) / 2)
+    }
+}
+
+/// 目标检测后端；NavEngine 负责截图并把结果换算回截图坐标，后端只处理单张图像
+pub trait ObjectDetector: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// 返回置信度不低于配置阈值、已去除重叠框的全部检测结果
+    fn detect(&self, img: &RgbaImage) -> Result<Vec<Detection>, String>;
+}
+
+// ==========================================
+// 2. 配置 (config.toml 中的 [detector])
+// ==========================================
+#[derive(Deserialize, Debug, Clone)]
+pub struct DetectorConfig {
+    /// 是否启用 (需以 --features detector 编译)
+    #[serde(default)]
+    pub enabled: bool,
+    /// YOLO 模型 (YOLOv5 / YOLOv8 导出的 ONNX，输入 1×3×N×N)
+    #[serde(default = "default_model")]
+    pub model: String,
+    /// 类别名，按模型训练时的类别顺序；未列出的类别显示为 "class_序号"
+    #[serde(default)]
+    pub labels: Vec<String>,
+    /// ONNX Runtime 动态库文件名或路径
+    #[serde(default = "default_onnxruntime")]
+    pub onnxruntime: String,
+    /// 模型输入边长 (导出时的 imgsz)
+    #[serde(default = "default_input_size")]
+    pub input_size: u32,
+    /// 置信度阈值
+    #[serde(default = "default_confidence")]
+    pub confidence: f32,
+    /// 同类框重叠 (IoU) 超过此值时只保留得分最高的
+    #[serde(default = "default_iou")]
+    pub iou: f32,
+}
+
+fn default_model() -> String { "models/yolo.onnx".to_string() }
+fn default_onnxruntime() -> String { "onnxruntime.dll".to_string() }
+fn default_input_size() -> u32 { 640 }
+fn default_confidence() -> f32 { 0.4 }
+fn default_iou() -> f32 { 0.45 }
+
+impl Default for DetectorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            model: default_model(),
+            labels: Vec::new(),
+            onnxruntime: default_onnxruntime(),
+            input_size: default_input_size(),
+            confidence: default_confidence(),
+            iou: default_iou(),
+        }
+    }
+}
+
+// ==========================================
+// 3. 工厂
+// ==========================================
+
+/// 按配置创建检测后端；未启用或不可用时为 None (依赖检测的逻辑应退回到 OCR / 模板判断)
+pub fn create_detector(cfg: &DetectorConfig) -> Option<Arc<dyn ObjectDetector>> {
+    if !cfg.enabled {
+        return None;
+    }
+    #[cfg(feature = "detector")]
+    match crate::yolo::YoloDetector::new(cfg) {
+        Ok(d) => {
+            println!("🎯 目标检测: [yolo] ({})", cfg.model);
+            Some(Arc::new(d))
+        }
+        Err(e) => {
+            println!("⚠️ 目标检测 [yolo] 不可用: {}", e);
+            None
+        }
+    }
+    #[cfg(not(feature = "detector"))]
+    {
+        println!("⚠️ 当前构建未包含目标检测，请使用 --features detector 重新编译");
+        None
+    }
+}
@@ -0,0 +1,97 @@
+// src/dump.rs
+// 识别失败现场转储：导航失败时把整屏截图、各锚点裁剪图与识别报告 (期望值、OCR 结果) 存入带时间戳的目录
+use crate::nav::RecognitionReport;
+use image::RgbaImage;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 失败转储配置 (config.toml 中的 [debug_dump])
+#[derive(Deserialize, Debug, Clone)]
+pub struct DumpConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// 转储根目录，每次失败在其下新建一个 "时间戳_目标" 子目录
+    #[serde(default = "default_dir")]
+    pub dir: String,
+    /// 最多保留的转储数，超出时删除最旧的
+    #[serde(default = "default_keep")]
+    pub keep: usize,
+}
+
+fn default_dir() -> String { "debug".to_string() }
+fn default_keep() -> usize { 20 }
+
+impl Default for DumpConfig {
+    fn default() -> Self {
+        Self { enabled: false, dir: default_dir(), keep: default_keep() }
+    }
+}
+
+/// 界面 ID、目标名中不适合做文件名的字符替换为 '_'
+fn file_safe(name: &str) -> String {
+    name.chars().map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' }).collect()
+}
+
+/// 从整屏截图中裁剪锚点区域 (截图坐标)；区域完全在截图外时返回 None
+fn crop(screen: &RgbaImage, rect: [i32; 4]) -> Option<RgbaImage> {
+    let (w, h) = screen.dimensions();
+    let x1 = rect[0].clamp(0, w as i32) as u32;
+    let y1 = rect[1].clamp(0, h as i32) as u32;
+    let x2 = rect[2].clamp(0, w as i32) as u32;
+    let y2 = rect[3].clamp(0, h as i32) as u32;
+    (x2 > x1 && y2 > y1).then(|| image::imageops::crop_imm(screen, x1, y1, x2 - x1, y2 - y1).to_image())
+}
+
+/// 写入一次失败转储，返回转储目录：
+/// report.txt (目标、失败原因与各锚点的期望 / 实际值)、screen.png (整屏)、
+/// 以及每个锚点的裁剪图 "界面_序号_类型.png" (序号与 report.txt 中该界面的锚点顺序一致)
+pub fn write(cfg: &DumpConfig, target: &str, report: &RecognitionReport, screen: Option<&RgbaImage>) -> Result<PathBuf, String> {
+    let now = chrono::Local::now();
+    let dir = Path::new(&cfg.dir).join(format!("{}_{}", now.format("%Y%m%d_%H%M%S_%3f"), file_safe(target)));
+    fs::create_dir_all(&dir).map_err(|e| format!("创建转储目录 {} 失败: {}", dir.display(), e))?;
+
+    let text = format!("目标: {}\n时间: {}\n{}\n", target, now.format("%Y-%m-%d %H:%M:%S%.3f"), report);
+    fs::write(dir.join("report.txt"), text).map_err(|e| format!("写入识别报告失败: {}", e))?;
+
+    if let Some(screen) = screen {
+        screen.save(dir.join("screen.png")).map_err(|e| format!("保存截图失败: {}", e))?;
+        for scene in &report.scenes {
+            for (i, anchor) in scene.anchors.iter().enumerate() {
+                if let Some(img) = crop(screen, anchor.rect) {
+                    let name = format!("{}_{:02}_{}.png", file_safe(&scene.id), i, anchor.kind);
+                    img.save(dir.join(name)).map_err(|e| format!("保存锚点裁剪图失败: {}", e))?;
+                }
+            }
+        }
+    }
+
+    prune(Path::new(&cfg.dir), cfg.keep);
+    Ok(dir)
+}
+
+/// 目录名是否为 write 生成的 "%Y%m%d_%H%M%S_%3f_目标" 格式
+fn is_dump_dir(name: &str) -> bool {
+    const PATTERN: &[u8] = b"dddddddd_dddddd_ddd_";
+    let bytes = name.as_bytes();
+    bytes.len() >= PATTERN.len()
+        && PATTERN.iter().zip(bytes).all(|(p, b)| if *p == b'd' { b.is_ascii_digit() } else { b == p })
+}
+
+/// 只保留最新的 keep 个转储目录；转储根目录下不是转储产生的目录 (名称不符合时间戳格式) 不会被删除
+fn prune(root: &Path, keep: usize) {
+    let mut dirs: Vec<PathBuf> = match fs::read_dir(root) {
+        Ok(rd) => rd
+            .flatten()
+            .filter(|e| e.file_name().to_str().is_some_and(is_dump_dir))
+            .map(|e| e.path())
+            .filter(|p| p.is_dir())
+            .collect(),
+        Err(_) => return,
+    };
+    // 目录名以时间戳开头，字典序即时间序
+    dirs.sort();
+    while dirs.len() > keep.max(1) {
+        let _ = fs::remove_dir_all(dirs.remove(0));
+    }
+}
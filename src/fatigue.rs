@@ -0,0 +1,130 @@
+// src/fatigue.rs
+use rand::Rng;
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+
+// ==========================================
+// 1. 配置 (拟人档案中的 [档案名.fatigue])
+// ==========================================
+
+/// 疲劳与休息：连续运行越久，延迟越长、失误越多，并每隔一段时间离开几分钟
+#[derive(Deserialize, Debug, Clone)]
+pub struct FatigueConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// 每运行一小时 (不含休息) 延迟与失误率增加的比例
+    #[serde(default = "default_ramp_per_hour")]
+    pub ramp_per_hour: f32,
+    /// 疲劳倍率上限
+    #[serde(default = "default_max_factor")]
+    pub max_factor: f32,
+    /// 休息一次后疲劳时长恢复的比例 (0.5 表示抵消一半)
+    #[serde(default = "default_recovery")]
+    pub recovery: f32,
+    /// 两次休息之间的间隔 [最小, 最大] (分钟)
+    #[serde(default = "default_break_every_min")]
+    pub break_every_min: [u64; 2],
+    /// 每次休息的时长 [最小, 最大] (分钟)
+    #[serde(default = "default_break_len_min")]
+    pub break_len_min: [u64; 2],
+}
+
+fn default_ramp_per_hour() -> f32 { 0.08 }
+fn default_max_factor() -> f32 { 1.6 }
+fn default_recovery() -> f32 { 0.5 }
+fn default_break_every_min() -> [u64; 2] { [60, 90] }
+fn default_break_len_min() -> [u64; 2] { [3, 8] }
+
+impl Default for FatigueConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ramp_per_hour: default_ramp_per_hour(),
+            max_factor: default_max_factor(),
+            recovery: default_recovery(),
+            break_every_min: default_break_every_min(),
+            break_len_min: default_break_len_min(),
+        }
+    }
+}
+
+// ==========================================
+// 2. 会话状态
+// ==========================================
+
+/// 休息开始 / 结束时通知监听者 (main.rs 据此打印「休息中」)
+#[derive(Debug, Clone)]
+pub enum BreakEvent {
+    Started { duration: Duration },
+    Ended,
+}
+
+pub type BreakListener = Box<dyn Fn(&BreakEvent) + Send>;
+
+/// 分钟区间 -> 随机时长 (精确到秒)
+fn minutes(rng: &mut impl Rng, range: [u64; 2]) -> Duration {
+    let (lo, hi) = (range[0] * 60, range[1].max(range[0]) * 60);
+    Duration::from_secs(rng.gen_range(lo..=hi))
+}
+
+pub struct Session {
+    /// 计入疲劳的运行时长的起点 (休息会把它往后推)
+    tired_since: Instant,
+    next_break: Instant,
+    break_started: Option<Instant>,
+    listener: Option<BreakListener>,
+}
+
+impl Session {
+    pub fn new(cfg: &FatigueConfig, rng: &mut impl Rng) -> Self {
+        let now = Instant::now();
+        Self { tired_since: now, next_break: now + minutes(rng, cfg.break_every_min), break_started: None, listener: None }
+    }
+
+    /// 从头计时 (切换档案后按新档案的间隔排定第一次休息)
+    pub fn restart(&mut self, cfg: &FatigueConfig, rng: &mut impl Rng) {
+        let now = Instant::now();
+        self.tired_since = now;
+        self.next_break = now + minutes(rng, cfg.break_every_min);
+        self.break_started = None;
+    }
+
+    pub fn set_listener(&mut self, listener: BreakListener) {
+        self.listener = Some(listener);
+    }
+
+    /// 当前疲劳倍率 (>= 1)；未启用时恒为 1
+    pub fn factor(&self, cfg: &FatigueConfig) -> f32 {
+        if !cfg.enabled {
+            return 1.0;
+        }
+        let hours = self.tired_since.elapsed().as_secs_f32() / 3600.0;
+        (1.0 + cfg.ramp_per_hour * hours).min(cfg.max_factor.max(1.0))
+    }
+
+    /// 到了休息时间则返回本次休息时长，并通知监听者休息开始
+    pub fn begin_break(&mut self, cfg: &FatigueConfig, rng: &mut impl Rng) -> Option<Duration> {
+        if !cfg.enabled || Instant::now() < self.next_break {
+            return None;
+        }
+        let duration = minutes(rng, cfg.break_len_min);
+        self.break_started = Some(Instant::now());
+        if let Some(listener) = &self.listener {
+            listener(&BreakEvent::Started { duration });
+        }
+        Some(duration)
+    }
+
+    /// 休息结束：部分恢复疲劳，排定下一次休息
+    pub fn end_break(&mut self, cfg: &FatigueConfig, rng: &mut impl Rng) {
+        let now = Instant::now();
+        // 休息本身不计入疲劳
+        let worked = self.break_started.take().unwrap_or(now).duration_since(self.tired_since);
+        let tired = worked.mul_f32(1.0 - cfg.recovery.clamp(0.0, 1.0));
+        self.tired_since = now.checked_sub(tired).unwrap_or(now);
+        self.next_break = now + minutes(rng, cfg.break_every_min);
+        if let Some(listener) = &self.listener {
+            listener(&BreakEvent::Ended);
+        }
+    }
+}
@@ -0,0 +1,347 @@
+// src/gamepad.rs
+// 通过 ViGEmBus 虚拟 Xbox 360 手柄输入 (需安装 ViGEmBus 并将 ViGEmClient.dll 放在程序目录)
+use crate::hardware::{chord_report, DriverCaps, DriverError, DriverFeatures, DriverInfo, InputDriver, MouseButtons};
+use std::ffi::c_void;
+use std::thread;
+use std::time::Duration;
+use windows::core::{s, w};
+use windows::Win32::System::LibraryLoader::{GetProcAddress, LoadLibraryW};
+
+// ==========================================
+// 1. ViGEmClient.dll 接口 (运行时加载)
+// ==========================================
+
+#[repr(C)]
+#[derive(Default, Clone, Copy, PartialEq)]
+struct XusbReport {
+    buttons: u16,
+    left_trigger: u8,
+    right_trigger: u8,
+    thumb_lx: i16,
+    thumb_ly: i16,
+    thumb_rx: i16,
+    thumb_ry: i16,
+}
+
+type AllocFn = unsafe extern "C" fn() -> *mut c_void;
+type FreeFn = unsafe extern "C" fn(*mut c_void);
+type ConnectFn = unsafe extern "C" fn(*mut c_void) -> u32;
+type TargetAddFn = unsafe extern "C" fn(*mut c_void, *mut c_void) -> u32;
+type UpdateFn = unsafe extern "C" fn(*mut c_void, *mut c_void, XusbReport) -> u32;
+
+const VIGEM_ERROR_NONE: u32 = 0x2000_0000;
+
+/// Xbox 360 手柄按键位
+pub mod buttons {
+    pub const DPAD_UP: u16 = 0x0001;
+    pub const DPAD_DOWN: u16 = 0x0002;
+    pub const DPAD_LEFT: u16 = 0x0004;
+    pub const DPAD_RIGHT: u16 = 0x0008;
+    pub const START: u16 = 0x0010;
+    pub const BACK: u16 = 0x0020;
+    pub const LEFT_SHOULDER: u16 = 0x0100;
+    pub const RIGHT_SHOULDER: u16 = 0x0200;
+    pub const A: u16 = 0x1000;
+    pub const B: u16 = 0x2000;
+    pub const X: u16 = 0x4000;
+    pub const Y: u16 = 0x8000;
+}
+
+struct Api {
+    client: *mut c_void,
+    target: *mut c_void,
+    update: UpdateFn,
+    remove: TargetAddFn,
+    target_free: FreeFn,
+    disconnect: FreeFn,
+    free: FreeFn,
+}
+
+impl Api {
+    fn load() -> Result<Self, String> {
+        unsafe {
+            let module =
+                LoadLibraryW(w!("ViGEmClient.dll")).map_err(|e| format!("无法加载 ViGEmClient.dll: {}", e))?;
+            let proc = |name| GetProcAddress(module, name).ok_or("ViGEmClient.dll 缺少必要的导出函数".to_string());
+            type Farproc = unsafe extern "system" fn() -> isize;
+            let alloc = std::mem::transmute::<Farproc, AllocFn>(proc(s!("vigem_alloc"))?);
+            let free = std::mem::transmute::<Farproc, FreeFn>(proc(s!("vigem_free"))?);
+            let connect = std::mem::transmute::<Farproc, ConnectFn>(proc(s!("vigem_connect"))?);
+            let disconnect = std::mem::transmute::<Farproc, FreeFn>(proc(s!("vigem_disconnect"))?);
+            let target_alloc = std::mem::transmute::<Farproc, AllocFn>(proc(s!("vigem_target_x360_alloc"))?);
+            let target_free = std::mem::transmute::<Farproc, FreeFn>(proc(s!("vigem_target_free"))?);
+            let add = std::mem::transmute::<Farproc, TargetAddFn>(proc(s!("vigem_target_add"))?);
+            let remove = std::mem::transmute::<Farproc, TargetAddFn>(proc(s!("vigem_target_remove"))?);
+            let update = std::mem::transmute::<Farproc, UpdateFn>(proc(s!("vigem_target_x360_update"))?);
+
+            let client = alloc();
+            if client.is_null() {
+                return Err("vigem_alloc 失败".to_string());
+            }
+            let err = connect(client);
+            if err != VIGEM_ERROR_NONE {
+                free(client);
+                return Err(format!("无法连接 ViGEmBus (错误 0x{:08X})，请确认驱动已安装", err));
+            }
+            let target = target_alloc();
+            if target.is_null() {
+                disconnect(client);
+                free(client);
+                return Err("vigem_target_x360_alloc 失败".to_string());
+            }
+            let err = add(client, target);
+            if err != VIGEM_ERROR_NONE {
+                target_free(target);
+                disconnect(client);
+                free(client);
+                return Err(format!("无法插入虚拟手柄 (错误 0x{:08X})", err));
+            }
+            Ok(Self { client, target, update, remove, target_free, disconnect, free })
+        }
+    }
+
+    fn send(&self, report: XusbReport) -> Result<(), DriverError> {
+        let err = unsafe { (self.update)(self.client, self.target, report) };
+        if err == VIGEM_ERROR_NONE {
+            Ok(())
+        } else {
+            Err(DriverError::Io(format!("虚拟手柄状态更新失败 (错误 0x{:08X})", err)))
+        }
+    }
+}
+
+impl Drop for Api {
+    fn drop(&mut self) {
+        unsafe {
+            (self.remove)(self.client, self.target);
+            (self.target_free)(self.target);
+            (self.disconnect)(self.client);
+            (self.free)(self.client);
+        }
+    }
+}
+
+// ==========================================
+// 2. 键鼠 -> 手柄映射
+// ==========================================
+
+/// 单次按键的按住/间隔时长
+const PRESS_HOLD: Duration = Duration::from_millis(60);
+const PRESS_GAP: Duration = Duration::from_millis(60);
+/// 滚轮每格对应的右摇杆拨动时长
+const WHEEL_PULSE: Duration = Duration::from_millis(80);
+const DEFAULT_DPAD_STEP: (i32, i32) = (200, 120);
+
+/// HID 键码 -> 手柄按键
+fn hid_to_button(hid: u8) -> Option<u16> {
+    let b = match hid {
+        0x28 | 0x2C => buttons::A, // Enter / Space
+        0x29 => buttons::B,        // Esc
+        0x2B => buttons::BACK,     // Tab
+        0x4F => buttons::DPAD_RIGHT,
+        0x50 => buttons::DPAD_LEFT,
+        0x51 => buttons::DPAD_DOWN,
+        0x52 => buttons::DPAD_UP,
+        0x14 => buttons::LEFT_SHOULDER,  // Q
+        0x08 => buttons::RIGHT_SHOULDER, // E
+        0x15 => buttons::X,              // R
+        0x09 => buttons::Y,              // F
+        0x43 => buttons::START,          // F10
+        _ => return None,
+    };
+    Some(b)
+}
+
+/// 点击的处理方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ClickMode {
+    /// 按鼠标位移折算方向键步数，移动焦点后确认
+    Dpad { step: (i32, i32) },
+    /// 直接在当前焦点上确认
+    Direct,
+}
+
+/// 解析 "gamepad" 之后的参数: "" / "WxH" / "direct"
+fn parse_mode(spec: &str) -> Result<ClickMode, String> {
+    let spec = spec.trim();
+    if spec.is_empty() {
+        return Ok(ClickMode::Dpad { step: DEFAULT_DPAD_STEP });
+    }
+    if spec.eq_ignore_ascii_case("direct") {
+        return Ok(ClickMode::Direct);
+    }
+    let (w, h) = spec.split_once(['x', 'X']).ok_or_else(|| format!("无效的手柄参数: {} (WxH 或 direct)", spec))?;
+    let w: i32 = w.trim().parse().map_err(|_| format!("无效的方向键步长: {}", spec))?;
+    let h: i32 = h.trim().parse().map_err(|_| format!("无效的方向键步长: {}", spec))?;
+    if w <= 0 || h <= 0 {
+        return Err(format!("方向键步长必须为正数: {}", spec));
+    }
+    Ok(ClickMode::Dpad { step: (w, h) })
+}
+
+// ==========================================
+// 3. 驱动
+// ==========================================
+
+/// 虚拟手柄驱动：鼠标只移动虚拟光标，点击折算为方向键导航 + A 确认，常用键映射到手柄按键
+pub struct GamepadDriver {
+    api: Api,
+    mode: ClickMode,
+    report: XusbReport,
+    /// 虚拟光标 (HumanDriver 以为的鼠标位置)
+    cursor: (i32, i32),
+    /// 推测的界面焦点位置，方向键每走一步移动一个步长
+    focus: (i32, i32),
+    screen_w: u16,
+    screen_h: u16,
+    /// 当前由键盘映射按住的手柄按键
+    held_from_keys: u16,
+}
+
+// ViGEm 句柄只在驱动线程中使用 (见 actor.rs)
+unsafe impl Send for GamepadDriver {}
+
+impl GamepadDriver {
+    /// spec 为 --port gamepad[:WxH|:direct] 冒号之后的部分
+    pub fn new(spec: &str, screen_w: u16, screen_h: u16) -> Result<Self, String> {
+        let mode = parse_mode(spec)?;
+        let api = Api::load()?;
+        let center = (screen_w as i32 / 2, screen_h as i32 / 2);
+        println!("🎮 [Gamepad] 虚拟 Xbox 360 手柄已插入 (点击映射: {:?})", mode);
+        Ok(Self {
+            api,
+            mode,
+            report: XusbReport::default(),
+            cursor: center,
+            focus: center,
+            screen_w,
+            screen_h,
+            held_from_keys: 0,
+        })
+    }
+
+    /// 重置推测的焦点位置 (例如界面切换后焦点回到默认按钮)
+    pub fn reset_focus(&mut self, x: i32, y: i32) {
+        self.focus = (x, y);
+    }
+
+    fn update(&mut self, report: XusbReport) -> Result<(), DriverError> {
+        self.report = report;
+        self.api.send(report)
+    }
+
+    /// 短按一次 (保留键盘映射按住的按键)
+    pub fn tap(&mut self, button: u16) -> Result<(), DriverError> {
+        let base = self.report;
+        self.update(XusbReport { buttons: base.buttons | button, ..base })?;
+        thread::sleep(PRESS_HOLD);
+        self.update(base)?;
+        thread::sleep(PRESS_GAP);
+        Ok(())
+    }
+
+    /// 设置摇杆 (左/右) 偏移，范围 -32768 ~ 32767，y 轴向上为正
+    pub fn set_stick(&mut self, right: bool, x: i16, y: i16) -> Result<(), DriverError> {
+        let mut report = self.report;
+        if right {
+            (report.thumb_rx, report.thumb_ry) = (x, y);
+        } else {
+            (report.thumb_lx, report.thumb_ly) = (x, y);
+        }
+        self.update(report)
+    }
+
+    /// 从焦点位置按方向键走到光标位置
+    fn navigate_to_cursor(&mut self, step: (i32, i32)) -> Result<(), DriverError> {
+        let nx = ((self.cursor.0 - self.focus.0) as f32 / step.0 as f32).round() as i32;
+        let ny = ((self.cursor.1 - self.focus.1) as f32 / step.1 as f32).round() as i32;
+        let horizontal = if nx > 0 { buttons::DPAD_RIGHT } else { buttons::DPAD_LEFT };
+        let vertical = if ny > 0 { buttons::DPAD_DOWN } else { buttons::DPAD_UP };
+        for _ in 0..ny.abs() {
+            self.tap(vertical)?;
+        }
+        for _ in 0..nx.abs() {
+            self.tap(horizontal)?;
+        }
+        self.focus = (self.focus.0 + nx * step.0, self.focus.1 + ny * step.1);
+        Ok(())
+    }
+
+    fn set_held(&mut self, keycodes: &[u8]) -> Result<(), DriverError> {
+        let held = keycodes.iter().filter_map(|k| hid_to_button(*k)).fold(0, |b, x| b | x);
+        let buttons = self.report.buttons & !self.held_from_keys | held;
+        self.held_from_keys = held;
+        self.update(XusbReport { buttons, ..self.report })
+    }
+}
+
+impl InputDriver for GamepadDriver {
+    fn info(&self) -> DriverInfo {
+        DriverInfo::named("ViGEm 虚拟手柄", DriverFeatures::KEYBOARD_REPORT | DriverFeatures::HWHEEL)
+    }
+
+    fn capabilities(&self) -> DriverCaps {
+        DriverCaps { identity: false, ..Default::default() }
+    }
+
+    fn heartbeat(&mut self) -> Result<(), DriverError> { Ok(()) }
+    fn switch_identity(&mut self, _index: u8) -> Result<(), DriverError> { Ok(()) }
+
+    fn mouse_abs(&mut self, x: u16, y: u16) -> Result<(), DriverError> {
+        self.cursor = (x as i32, y as i32);
+        Ok(())
+    }
+
+    fn mouse_move(&mut self, dx: i32, dy: i32, wheel: i8) -> Result<(), DriverError> {
+        self.cursor = (
+            (self.cursor.0 + dx).clamp(0, self.screen_w as i32 - 1),
+            (self.cursor.1 + dy).clamp(0, self.screen_h as i32 - 1),
+        );
+        if wheel != 0 {
+            // 滚轮向上 (正) 对应右摇杆向上
+            let y = if wheel > 0 { i16::MAX } else { i16::MIN };
+            self.set_stick(true, 0, y)?;
+            thread::sleep(WHEEL_PULSE * wheel.unsigned_abs() as u32);
+            self.set_stick(true, 0, 0)?;
+        }
+        Ok(())
+    }
+
+    fn mouse_hscroll(&mut self, delta: i8) -> Result<(), DriverError> {
+        let x = if delta > 0 { i16::MAX } else { i16::MIN };
+        self.set_stick(true, x, 0)?;
+        thread::sleep(WHEEL_PULSE * delta.unsigned_abs() as u32);
+        self.set_stick(true, 0, 0)
+    }
+
+    fn mouse_down(&mut self, pressed: MouseButtons) -> Result<(), DriverError> {
+        if pressed.contains(MouseButtons::LEFT) {
+            if let ClickMode::Dpad { step } = self.mode {
+                self.navigate_to_cursor(step)?;
+            }
+            self.update(XusbReport { buttons: self.report.buttons | buttons::A, ..self.report })?;
+        } else if pressed.contains(MouseButtons::RIGHT) {
+            self.update(XusbReport { buttons: self.report.buttons | buttons::B, ..self.report })?;
+        }
+        Ok(())
+    }
+
+    fn mouse_up(&mut self) -> Result<(), DriverError> {
+        self.update(XusbReport { buttons: self.report.buttons & !(buttons::A | buttons::B) | self.held_from_keys, ..self.report })
+    }
+
+    fn key_down(&mut self, keycode: u8, _modifier: u8) -> Result<(), DriverError> {
+        let held = self.held_from_keys | hid_to_button(keycode).unwrap_or(0);
+        let buttons = self.report.buttons | held;
+        self.held_from_keys = held;
+        self.update(XusbReport { buttons, ..self.report })
+    }
+
+    fn key_up(&mut self) -> Result<(), DriverError> {
+        self.set_held(&[])
+    }
+
+    fn keys_down(&mut self, keycodes: &[u8], _modifier: u8) -> Result<(), DriverError> {
+        self.set_held(&chord_report(keycodes))
+    }
+}
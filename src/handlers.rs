@@ -0,0 +1,259 @@
+// src/handlers.rs
+use crate::cancel;
+use crate::checkpoint::{self, TaskCheckpoint};
+use crate::daily_routine::DailyRoutineApp;
+use crate::hardware::DriverError;
+use crate::human::HumanDriver;
+use crate::nav::{NavEngine, NavOptions, NavResult};
+use crate::stats::{RunRecord, StatsStore};
+use crate::tower_defense::TowerDefenseApp;
+use chrono::{DateTime, Local};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// 主循环中单次导航的最长时间，超时后执行界面重置
+const NAV_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// 处理器执行完毕后的汇报
+#[derive(Debug, Clone)]
+pub struct HandlerReport {
+    pub scene_id: String,
+    pub handler: String,
+    pub started_at: DateTime<Local>,
+    pub duration_secs: f64,
+    pub success: bool,
+    pub waves: i32,
+    pub refresh_count: u32,
+}
+
+impl HandlerReport {
+    pub fn to_record(&self) -> RunRecord {
+        RunRecord {
+            map: self.scene_id.clone(),
+            handler: self.handler.clone(),
+            started_at: self.started_at,
+            duration_secs: self.duration_secs,
+            success: self.success,
+            waves: self.waves,
+            refresh_count: self.refresh_count,
+        }
+    }
+}
+
+/// 已注册的处理器代号 (ui_map.toml 中的 handler)；NavEngine::validate 据此检查地图
+pub const HANDLERS: &[&str] = &["daily", "td"];
+
+const TRAPS_FILE: &str = "traps_config.json";
+
+/// 塔防场景对应的 (地图文件, 策略文件)
+fn td_files(scene_id: &str) -> (String, String) {
+    (format!("{}地图.json", scene_id), format!("{}策略.json", scene_id))
+}
+
+/// 根据处理器代号启动对应的业务模块 (未知代号按 "td" 处理)
+/// owner 标识调用方 (主控 / 实例名 / 流水线)，用于隔离各自的检查点
+pub fn run_handler(
+    handler_key: &str,
+    scene_id: &str,
+    owner: &str,
+    driver: &Arc<Mutex<HumanDriver>>,
+    engine: &Arc<NavEngine>,
+) -> HandlerReport {
+    let started_at = Local::now();
+    let timer = Instant::now();
+
+    // (处理器名, 是否成功, 推进波次, 刷新次数)
+    let (handler_name, success, waves, refresh_count) = match handler_key {
+        "daily" => {
+            println!("📅 [路由] 检测到 'daily' 标记，启动日活模块...");
+            let mut app = DailyRoutineApp::new(Arc::clone(driver), Arc::clone(engine));
+            app.set_checkpoint(TaskCheckpoint::open(owner, "daily", scene_id));
+            app.set_scene(scene_id);
+            match app.run() {
+                Ok(refreshes) => ("daily", true, 0, refreshes),
+                Err(e) => {
+                    println!("❌ [路由] 日活因输入设备故障中止: {}", e);
+                    ("daily", false, 0, 0)
+                }
+            }
+        }
+        _ => {
+            println!("🏰 [路由] 启动塔防模块 (Handler: {})...", handler_key);
+            let mut td_app = TowerDefenseApp::new(Arc::clone(driver), Arc::clone(engine));
+            td_app.set_checkpoint(TaskCheckpoint::open(owner, "td", scene_id));
+
+            let (map_file, strategy_file) = td_files(scene_id);
+
+            println!("📂 加载配置: {} | {}", map_file, strategy_file);
+            let result = td_app.run(&map_file, &strategy_file, TRAPS_FILE);
+            let waves = td_app.last_confirmed_wave();
+            if let Err(e) = &result {
+                println!("❌ [路由] 塔防因输入设备故障中止 (第 {} 波): {}", waves, e);
+            }
+            ("td", result.is_ok() && waves > 0, waves, 0)
+        }
+    };
+
+    HandlerReport {
+        scene_id: scene_id.to_string(),
+        handler: handler_name.to_string(),
+        started_at,
+        duration_secs: timer.elapsed().as_secs_f64(),
+        success,
+        waves,
+        refresh_count,
+    }
+}
+
+/// 恢复 owner 名下中断的塔防战斗 (程序崩溃或弹窗恢复后调用)
+/// 检查点无效或游戏已不在该局战斗中时返回 None，检查点保留到下次正常开局时清除
+pub fn resume_pending(
+    owner: &str,
+    driver: &Arc<Mutex<HumanDriver>>,
+    engine: &Arc<NavEngine>,
+) -> Option<HandlerReport> {
+    let cp = checkpoint::pending(owner, "td")?;
+    println!(
+        "💾 [{}] 发现未完成的塔防任务 [{}] ({} 分钟前)，尝试恢复...",
+        owner,
+        cp.scene_id,
+        cp.age().as_secs() / 60
+    );
+
+    let started_at = Local::now();
+    let timer = Instant::now();
+    let mut td_app = TowerDefenseApp::new(Arc::clone(driver), Arc::clone(engine));
+    td_app.set_checkpoint(TaskCheckpoint::open(owner, "td", &cp.scene_id));
+
+    let (map_file, strategy_file) = td_files(&cp.scene_id);
+    let result = td_app.resume(&map_file, &strategy_file, TRAPS_FILE);
+    if let Ok(false) = result {
+        return None;
+    }
+
+    let waves = td_app.last_confirmed_wave();
+    if let Err(e) = &result {
+        println!("❌ [{}] 恢复的对局因输入设备故障中止: {}", owner, e);
+    }
+    Some(HandlerReport {
+        scene_id: cp.scene_id,
+        handler: "td".to_string(),
+        started_at,
+        duration_secs: timer.elapsed().as_secs_f64(),
+        success: result.is_ok() && waves > 0,
+        waves,
+        refresh_count: 0,
+    })
+}
+
+/// 任务之间的等待：光标保持自然的小动作 (见 HumanDriver::idle_humanly)
+pub fn idle(driver: &Arc<Mutex<HumanDriver>>, duration: Duration) {
+    let result = match driver.lock() {
+        Ok(mut human) => human.idle_humanly(duration),
+        Err(_) => {
+            thread::sleep(duration);
+            Ok(())
+        }
+    };
+    if let Err(e) = result {
+        println!("⚠️ 空闲动作失败: {}", e);
+    }
+}
+
+/// 导航失败后的界面重置 (ESC + 空格)
+pub fn reset_interface(driver: &Arc<Mutex<HumanDriver>>) {
+    if let Ok(mut human) = driver.lock() {
+        if let Err(e) = human.exclusive(reset_keys) {
+            println!("❌ 界面重置失败: {}", e);
+        }
+    }
+}
+
+fn reset_keys(human: &mut HumanDriver) -> Result<(), DriverError> {
+    human.key_hold('\u{1B}', 100)?;
+
+    human.raw(|dev| dev.key_down(0x29, 0))?;
+    thread::sleep(Duration::from_millis(100));
+    human.raw(|dev| dev.key_up())?;
+
+    thread::sleep(Duration::from_millis(100));
+    human.raw(|dev| dev.key_down(0x2C, 0))?; // 空格键扫描码
+    thread::sleep(Duration::from_millis(100));
+    human.raw(|dev| dev.key_up())
+}
+
+/// 自动化主循环：导航 -> 处理器 -> 记录统计，直到 keep_running 返回 false
+pub fn run_loop(
+    label: &str,
+    target: &str,
+    driver: &Arc<Mutex<HumanDriver>>,
+    engine: &Arc<NavEngine>,
+    stats: Option<&StatsStore>,
+    keep_running: impl Fn() -> bool,
+) {
+    let record = |report: &HandlerReport| {
+        if let Some(store) = stats {
+            if let Err(e) = store.record_run(&report.to_record()) {
+                println!("⚠️ {}", e);
+            }
+        }
+    };
+
+    // 启动时先尝试接上次中断的战斗
+    let mut try_resume = true;
+
+    while keep_running() {
+        if std::mem::take(&mut try_resume) {
+            if let Some(report) = resume_pending(label, driver, engine) {
+                record(&report);
+                println!("🎉 [{}] 恢复的对局已结束，5秒后重新开始循环...", label);
+                idle(driver, Duration::from_secs(5));
+                continue;
+            }
+        }
+
+        // 长时间运行时按拟人档案定时休息 (只在两局之间)
+        if let Ok(mut human) = driver.lock() {
+            human.take_break_if_due();
+        }
+
+        println!("\n🔄 [{}] 正在导航至: {}...", label, target);
+
+        // 卡在某个界面时限时放弃，交给下面的重置流程；中止热键同样生效
+        let options = NavOptions {
+            max_duration: Some(NAV_TIMEOUT),
+            cancel: driver.lock().map(|h| h.cancel_token()).unwrap_or_else(|_| cancel::global()),
+            ..NavOptions::default()
+        };
+        match engine.navigate_with(target, options) {
+            NavResult::Handover(scene_id, handler_opt) => {
+                println!("⚔️ [{}] 导航成功: [{}]", label, scene_id);
+
+                let handler_key = handler_opt.as_deref().unwrap_or("td");
+                let report = run_handler(handler_key, &scene_id, label, driver, engine);
+                record(&report);
+
+                println!("🎉 [{}] 本局任务结束，5秒后重新开始循环...", label);
+                idle(driver, Duration::from_secs(5));
+            }
+
+            NavResult::Failed(report) => {
+                println!("❌ [{}] 导航失败，执行重置操作 (ESC)...\n{}", label, report);
+
+                reset_interface(driver);
+
+                println!("⏳ 等待界面重置 (3秒)...");
+                thread::sleep(Duration::from_secs(3));
+
+                // 重置后可能回到了被弹窗打断的战斗中
+                try_resume = true;
+            }
+
+            NavResult::Success => {
+                println!("✅ [{}] 导航到达终点，等待重置...", label);
+                idle(driver, Duration::from_secs(5));
+            }
+        }
+    }
+}
@@ -1,26 +1,357 @@
+use crate::ble::BleUart;
+use crate::clipboard;
+use crate::keymap;
+use crate::overlay;
+use crate::remote::RemoteDriver;
+use bitflags::bitflags;
 use byteorder::{LittleEndian, WriteBytesExt};
 // ✨ Added Axis to imports
 use enigo::{
     Direction, Enigo, Key, Keyboard, Mouse, Settings, Coordinate,
     Button, Axis 
 };
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 use serialport::SerialPort;
-use std::io::Write;
+use std::io::{Read, Write};
+use std::net::UdpSocket;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use windows::Win32::Foundation::POINT;
+use windows::Win32::UI::WindowsAndMessaging::GetCursorPos;
 
 // ==========================================
 // 1. Common Interface (Trait)
 // ==========================================
-pub trait InputDriver: Send + Sync {
-    fn heartbeat(&mut self);
-    fn mouse_abs(&mut self, x: u16, y: u16);
-    fn mouse_move(&mut self, dx: i32, dy: i32, wheel: i8);
-    fn mouse_down(&mut self, left: bool, right: bool);
-    fn mouse_up(&mut self);
-    fn key_down(&mut self, keycode: u8, modifier: u8);
-    fn key_up(&mut self);
-    fn switch_identity(&mut self, index: u8);
+
+/// 驱动连接状态变化
+#[derive(Debug, Clone, PartialEq)]
+pub enum DriverEvent {
+    Disconnected(String),
+    Reconnected,
+}
+
+pub type DriverEventCallback = Box<dyn Fn(&DriverEvent) + Send + Sync>;
+
+/// 驱动发送失败的原因 (可序列化，远程代理据此回传错误)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DriverError {
+    /// 设备已断开 (正在后台重连)
+    Disconnected(String),
+    /// 底层 I/O 写入失败
+    Io(String),
+    /// 设备未确认 (超时或校验失败)
+    NoAck(String),
+    /// 当前驱动不支持该操作
+    Unsupported(String),
+    /// 拟人动作被暂停热键 / 超时中止 (见 cancel.rs)，按住的输入已松开
+    Cancelled(String),
+}
+
+impl std::fmt::Display for DriverError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DriverError::Disconnected(m) => write!(f, "设备已断开: {}", m),
+            DriverError::Io(m) => write!(f, "写入失败: {}", m),
+            DriverError::NoAck(m) => write!(f, "设备未确认: {}", m),
+            DriverError::Unsupported(m) => write!(f, "驱动不支持: {}", m),
+            DriverError::Cancelled(m) => write!(f, "已取消: {}", m),
+        }
+    }
+}
+
+bitflags! {
+    /// 鼠标按键位掩码 (与 HID 鼠标报告的按键字节一致)
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub struct MouseButtons: u8 {
+        const LEFT = 0x01;
+        const RIGHT = 0x02;
+        const MIDDLE = 0x04;
+        /// 侧键 "后退"
+        const X1 = 0x08;
+        /// 侧键 "前进"
+        const X2 = 0x10;
+    }
+}
+
+impl MouseButtons {
+    /// 兼容旧的 (left, right) 参数写法
+    pub fn from_pair(left: bool, right: bool) -> Self {
+        let mut b = Self::empty();
+        b.set(Self::LEFT, left);
+        b.set(Self::RIGHT, right);
+        b
+    }
+}
+
+bitflags! {
+    /// 驱动/固件支持的能力
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub struct DriverFeatures: u8 {
+        /// 确认重传协议
+        const ACKED = 0x01;
+        /// 完整键盘报告 (组合键)
+        const KEYBOARD_REPORT = 0x02;
+        /// 水平滚轮
+        const HWHEEL = 0x04;
+        /// 多设备身份切换
+        const IDENTITY = 0x08;
+    }
+}
+
+/// 驱动与固件信息 (启动时打印，并用于拒绝不兼容的固件)
+#[derive(Debug, Clone, Default)]
+pub struct DriverInfo {
+    pub name: String,
+    /// 固件版本 (major, minor, patch)；旧固件或非固件驱动为 None
+    pub firmware: Option<(u8, u8, u8)>,
+    pub features: DriverFeatures,
+    /// 最大报告速率 (Hz)，未知时为 None
+    pub max_report_rate: Option<u16>,
+}
+
+/// 本程序支持的固件主版本号
+pub const SUPPORTED_FIRMWARE_MAJOR: u8 = 1;
+
+impl DriverInfo {
+    pub fn named(name: &str, features: DriverFeatures) -> Self {
+        Self { name: name.to_string(), features, ..Default::default() }
+    }
+
+    /// 固件主版本不符时拒绝运行；旧固件 (不应答版本查询) 视为兼容
+    pub fn check_compatible(&self) -> Result<(), String> {
+        match self.firmware {
+            Some((major, minor, patch)) if major != SUPPORTED_FIRMWARE_MAJOR => Err(format!(
+                "{} 固件版本 v{}.{}.{} 不兼容 (需要 v{}.x)，请升级固件或程序",
+                self.name, major, minor, patch, SUPPORTED_FIRMWARE_MAJOR
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl std::fmt::Display for DriverInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some((major, minor, patch)) = self.firmware {
+            write!(f, " | 固件 v{}.{}.{}", major, minor, patch)?;
+        }
+        write!(f, " | 能力 {:?}", self.features)?;
+        if let Some(rate) = self.max_report_rate {
+            write!(f, " | 最大 {} Hz", rate)?;
+        }
+        Ok(())
+    }
+}
+
+/// 驱动行为能力：上层 (HumanDriver / NavEngine) 据此调整输入方式，而不是假设每个后端都像串口盒子
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct DriverCaps {
+    /// 支持绝对坐标；否则应以相对位移 (mouse_move) 逼近目标
+    pub absolute_mouse: bool,
+    /// 单条指令可携带的最大滚轮格数
+    pub wheel_step: u8,
+    /// 支持水平滚动
+    pub hscroll: bool,
+    /// 可同时按住的普通键数
+    pub max_keys_down: usize,
+    /// 支持设备身份切换
+    pub identity: bool,
+    /// 支持 type_text 直接输入任意 Unicode 文本 (中文等)
+    #[serde(default)]
+    pub text_input: bool,
+    /// 直接驱动本机的系统光标，GetCursorPos 能读回真实位置 (软件注入类后端)
+    #[serde(default)]
+    pub os_cursor: bool,
+}
+
+impl Default for DriverCaps {
+    /// 自制串口固件的能力
+    fn default() -> Self {
+        Self {
+            absolute_mouse: true,
+            wheel_step: i8::MAX as u8,
+            hscroll: true,
+            max_keys_down: MAX_CHORD_KEYS,
+            identity: true,
+            text_input: false,
+            os_cursor: false,
+        }
+    }
+}
+
+/// 驱动由驱动线程独占 (见 actor.rs)，因此只要求 Send
+pub trait InputDriver: Send {
+    fn heartbeat(&mut self) -> Result<(), DriverError>;
+    fn mouse_abs(&mut self, x: u16, y: u16) -> Result<(), DriverError>;
+    fn mouse_move(&mut self, dx: i32, dy: i32, wheel: i8) -> Result<(), DriverError>;
+    fn mouse_down(&mut self, buttons: MouseButtons) -> Result<(), DriverError>;
+    fn mouse_up(&mut self) -> Result<(), DriverError>;
+    fn key_down(&mut self, keycode: u8, modifier: u8) -> Result<(), DriverError>;
+    fn key_up(&mut self) -> Result<(), DriverError>;
+    /// 以完整 HID 报告的方式设置当前按住的键 (最多 6 个，多余的忽略)：
+    /// 不在列表中的键会被松开，空列表等同于只按住修饰键
+    fn keys_down(&mut self, keycodes: &[u8], modifier: u8) -> Result<(), DriverError>;
+    fn switch_identity(&mut self, index: u8) -> Result<(), DriverError>;
+
+    /// 水平滚动 (正数向右)，不支持的驱动返回 Unsupported
+    fn mouse_hscroll(&mut self, _delta: i8) -> Result<(), DriverError> {
+        Err(DriverError::Unsupported("水平滚动".to_string()))
+    }
+
+    /// 直接输入一段 Unicode 文本 (不经过 HID 键码与键盘布局，可输入中文)；
+    /// 只有运行在目标机器上的后端能做到，其他驱动返回 Unsupported
+    fn type_text(&mut self, _text: &str) -> Result<(), DriverError> {
+        Err(DriverError::Unsupported("Unicode 文本输入".to_string()))
+    }
+
+    /// 等待已提交的指令全部送达设备 (异步驱动使用)
+    fn flush(&mut self) -> Result<(), DriverError> {
+        Ok(())
+    }
+
+    /// 尚未送达设备的指令数
+    fn pending(&self) -> usize {
+        0
+    }
+
+    /// 设备当前是否在线 (断线重连期间为 false)
+    fn is_online(&self) -> bool {
+        true
+    }
+
+    /// 注册连接状态回调 (断开 / 重连成功)
+    fn set_event_callback(&mut self, _callback: DriverEventCallback) {}
+
+    /// 向改版固件发送自定义帧 (LED、抖动模式等厂商指令)；只有自制串口固件支持，其他驱动忽略
+    fn send_custom(&mut self, _event_type: u8, _payload: [u8; 6], _delay: u16) -> Result<(), DriverError> {
+        Ok(())
+    }
+
+    /// 逐帧延迟统计 (需开启 [hardware] metrics)，不支持的驱动返回 None
+    fn latency_metrics(&self) -> Option<LatencyMetrics> {
+        None
+    }
+
+    /// 驱动名称、固件版本与能力
+    fn info(&self) -> DriverInfo {
+        DriverInfo::named("未知驱动", DriverFeatures::empty())
+    }
+
+    /// 行为能力 (绝对坐标、滚轮粒度、同时按键数等)
+    fn capabilities(&self) -> DriverCaps {
+        DriverCaps::default()
+    }
+}
+
+/// HID 键盘报告最多同时携带的普通键数
+pub const MAX_CHORD_KEYS: usize = 6;
+
+/// 去重、去零并截断到 6 个键，组成 HID 报告的键码数组
+pub fn chord_report(keycodes: &[u8]) -> [u8; MAX_CHORD_KEYS] {
+    let mut report = [0u8; MAX_CHORD_KEYS];
+    let mut n = 0;
+    for &k in keycodes {
+        if k != 0 && !report[..n].contains(&k) && n < MAX_CHORD_KEYS {
+            report[n] = k;
+            n += 1;
+        }
+    }
+    report
+}
+
+/// 延迟直方图各桶的上界 (µs)，超过最后一个上界的计入溢出桶
+const LATENCY_BUCKETS_US: [u64; 10] = [50, 100, 250, 500, 1_000, 2_000, 4_000, 8_000, 16_000, 50_000];
+
+/// 单个阶段的延迟直方图
+#[derive(Debug, Clone, Default)]
+pub struct LatencyHistogram {
+    counts: [u64; LATENCY_BUCKETS_US.len() + 1],
+    total_us: u64,
+    max_us: u64,
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, elapsed: Duration) {
+        let us = elapsed.as_micros() as u64;
+        let bucket = LATENCY_BUCKETS_US.iter().position(|&b| us <= b).unwrap_or(LATENCY_BUCKETS_US.len());
+        self.counts[bucket] += 1;
+        self.total_us += us;
+        self.max_us = self.max_us.max(us);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    pub fn mean_us(&self) -> u64 {
+        self.total_us.checked_div(self.count()).unwrap_or(0)
+    }
+
+    pub fn max_us(&self) -> u64 {
+        self.max_us
+    }
+
+    /// 第 p (0~100) 百分位所在桶的上界 (µs)；落在溢出桶时返回最大值
+    pub fn percentile_us(&self, p: f64) -> u64 {
+        let target = (self.count() as f64 * p / 100.0).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (i, &n) in self.counts.iter().enumerate() {
+            seen += n;
+            if seen >= target {
+                return LATENCY_BUCKETS_US.get(i).copied().unwrap_or(self.max_us);
+            }
+        }
+        0
+    }
+}
+
+impl std::fmt::Display for LatencyHistogram {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let total = self.count();
+        writeln!(
+            f,
+            "{} 次 | 平均 {}µs | p50 ≤{}µs | p99 ≤{}µs | 最大 {}µs",
+            total,
+            self.mean_us(),
+            self.percentile_us(50.0),
+            self.percentile_us(99.0),
+            self.max_us
+        )?;
+        let mut lower = 0;
+        for (i, &n) in self.counts.iter().enumerate() {
+            let label = match LATENCY_BUCKETS_US.get(i) {
+                Some(upper) => format!("{:>6}~{:<6}µs", lower, upper),
+                None => format!("{:>6}µs 以上   ", lower),
+            };
+            if n > 0 {
+                let bar = "#".repeat(((n * 40).div_ceil(total)) as usize);
+                writeln!(f, "      {} {:>6} {}", label, n, bar)?;
+            }
+            lower = LATENCY_BUCKETS_US.get(i).copied().unwrap_or(lower);
+        }
+        Ok(())
+    }
+}
+
+/// 每帧各阶段耗时：排队 (入队到写入线程取出)、组帧、串口写入 (含等待确认与重传)、帧间节流
+#[derive(Debug, Clone, Default)]
+pub struct LatencyMetrics {
+    pub queue: LatencyHistogram,
+    pub serialize: LatencyHistogram,
+    pub write: LatencyHistogram,
+    pub pacing: LatencyHistogram,
+}
+
+impl std::fmt::Display for LatencyMetrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "  排队: {}", self.queue)?;
+        write!(f, "  组帧: {}", self.serialize)?;
+        write!(f, "  写入: {}", self.write)?;
+        write!(f, "  节流: {}", self.pacing)
+    }
 }
 
 // ==========================================
@@ -28,70 +359,669 @@ pub trait InputDriver: Send + Sync {
 // ==========================================
 const FRAME_HEAD: u8 = 0xAA;
 const FRAME_TAIL: u8 = 0x55;
+/// 带确认协议的帧在事件类型上置最高位
+const FRAME_ACKED_FLAG: u8 = 0x80;
+/// 自定义帧可用的事件类型；0x01 ~ 0x0F 留给内置帧类型 (目前用到 0x06)
+const CUSTOM_EVENT_TYPES: std::ops::RangeInclusive<u8> = 0x10..=0x7F;
+const ACK_MARK: u8 = 0xAC;
+/// 版本应答: AA AD major minor patch features rate_lo rate_hi 55
+const INFO_MARK: u8 = 0xAD;
+const INFO_TIMEOUT: Duration = Duration::from_millis(200);
+const RECONNECT_BACKOFF_MIN: Duration = Duration::from_millis(500);
+const RECONNECT_BACKOFF_MAX: Duration = Duration::from_secs(30);
 
-#[repr(u8)]
+/// 自制固件串口参数 (config.toml 的 [hardware]，也可用命令行覆盖)
+#[derive(Deserialize, Debug, Clone)]
+pub struct HardwareConfig {
+    #[serde(default = "default_baud_rate")]
+    pub baud_rate: u32,
+    /// 写入线程中两帧之间的最小间隔 (ms)，0 表示不限速
+    #[serde(default = "default_frame_pacing_ms")]
+    pub frame_pacing_ms: u64,
+    /// 串口读超时 (ms)
+    #[serde(default = "default_read_timeout_ms")]
+    pub read_timeout_ms: u64,
+    /// 等待确认帧的超时 (ms)，超时后重传
+    #[serde(default = "default_ack_timeout_ms")]
+    pub ack_timeout_ms: u64,
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    /// 统计每帧的排队、组帧、写入与节流耗时 (--test latency 时自动开启)
+    #[serde(default)]
+    pub metrics: bool,
+    /// 绝对坐标校准；未配置时使用 calibrate 子命令保存在状态库中的结果
+    #[serde(default)]
+    pub calibration: Option<AbsCalibration>,
+}
+
+fn default_baud_rate() -> u32 { 115200 }
+fn default_frame_pacing_ms() -> u64 { 4 }
+fn default_read_timeout_ms() -> u64 { 10 }
+fn default_ack_timeout_ms() -> u64 { 50 }
+fn default_max_retries() -> u32 { 3 }
+
+impl Default for HardwareConfig {
+    fn default() -> Self {
+        Self {
+            baud_rate: default_baud_rate(),
+            frame_pacing_ms: default_frame_pacing_ms(),
+            read_timeout_ms: default_read_timeout_ms(),
+            ack_timeout_ms: default_ack_timeout_ms(),
+            max_retries: default_max_retries(),
+            metrics: false,
+            calibration: None,
+        }
+    }
+}
+
+/// 校准样本: (发送坐标, 系统读回的实际坐标)
+pub type CalibrationSample = ((f32, f32), (f32, f32));
+
+/// 绝对坐标的逐轴线性校准：实际发送坐标 = 目标坐标 * scale + offset
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct AbsCalibration {
+    pub x_scale: f32,
+    pub x_offset: f32,
+    pub y_scale: f32,
+    pub y_offset: f32,
+}
+
+impl Default for AbsCalibration {
+    fn default() -> Self {
+        Self { x_scale: 1.0, x_offset: 0.0, y_scale: 1.0, y_offset: 0.0 }
+    }
+}
+
+impl AbsCalibration {
+    pub fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        (x * self.x_scale + self.x_offset, y * self.y_scale + self.y_offset)
+    }
+
+    /// 由 (发送坐标, 系统读回坐标) 样本拟合：每轴最小二乘求 实际 = a * 发送 + b，再取逆映射
+    pub fn fit(samples: &[CalibrationSample]) -> Result<Self, String> {
+        fn axis(points: &[(f32, f32)]) -> Result<(f32, f32), String> {
+            let n = points.len() as f32;
+            let mean_s = points.iter().map(|p| p.0).sum::<f32>() / n;
+            let mean_a = points.iter().map(|p| p.1).sum::<f32>() / n;
+            let cov: f32 = points.iter().map(|p| (p.0 - mean_s) * (p.1 - mean_a)).sum();
+            let var: f32 = points.iter().map(|p| (p.0 - mean_s).powi(2)).sum();
+            if var <= f32::EPSILON {
+                return Err("校准样本点过少或重合".to_string());
+            }
+            let a = cov / var;
+            if a.abs() < 0.1 {
+                return Err("光标几乎没有随指令移动，请确认驱动支持绝对坐标且未被其他程序接管".to_string());
+            }
+            let b = mean_a - a * mean_s;
+            Ok((1.0 / a, -b / a))
+        }
+        let xs: Vec<(f32, f32)> = samples.iter().map(|(s, a)| (s.0, a.0)).collect();
+        let ys: Vec<(f32, f32)> = samples.iter().map(|(s, a)| (s.1, a.1)).collect();
+        let (x_scale, x_offset) = axis(&xs)?;
+        let (y_scale, y_offset) = axis(&ys)?;
+        Ok(Self { x_scale, x_offset, y_scale, y_offset })
+    }
+}
+
+#[derive(Clone, Copy)]
 enum EventType {
-    Keyboard = 0x01,
-    MouseRel = 0x02,
-    MouseAbs = 0x03,
-    System = 0x04,
+    Keyboard,
+    MouseRel,
+    MouseAbs,
+    System,
+    /// 完整键盘报告: b = [modifier, k1..k5]，delay 字段低字节承载 k6
+    KeyboardReport,
+    /// 水平滚轮: b[0] = delta (i8)
+    MouseHWheel,
+    /// 改版固件的自定义指令 (send_custom)，原样透传
+    Custom(u8),
+}
+
+impl EventType {
+    fn code(self) -> u8 {
+        match self {
+            EventType::Keyboard => 0x01,
+            EventType::MouseRel => 0x02,
+            EventType::MouseAbs => 0x03,
+            EventType::System => 0x04,
+            EventType::KeyboardReport => 0x05,
+            EventType::MouseHWheel => 0x06,
+            EventType::Custom(code) => code,
+        }
+    }
 }
 
 #[repr(u8)]
 enum SystemCmd {
     SetId = 0x10,
+    /// 查询固件版本与能力，设备以版本应答代替确认帧
+    Version = 0x20,
     Heartbeat = 0xFF,
 }
 
+/// CRC-8 (多项式 0x07)
+fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |mut crc, b| {
+        crc ^= b;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 { (crc << 1) ^ 0x07 } else { crc << 1 };
+        }
+        crc
+    })
+}
+
+/// 旧格式 (11 字节): AA | type | b[6] | delay(LE) | 55
+/// 确认格式 (13 字节): AA | type|0x80 | seq | b[6] | delay(LE) | crc8 | 55，设备回 AA AC seq status 55
+fn build_frame(acked: bool, seq: u8, event_type: EventType, b: [u8; 6], delay_ms: u16) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(13);
+    frame.push(FRAME_HEAD);
+    if acked {
+        frame.push(event_type.code() | FRAME_ACKED_FLAG);
+        frame.push(seq);
+    } else {
+        frame.push(event_type.code());
+    }
+    frame.extend_from_slice(&b);
+    frame.write_u16::<LittleEndian>(delay_ms).unwrap();
+    if acked {
+        let crc = crc8(&frame[1..]);
+        frame.push(crc);
+    }
+    frame.push(FRAME_TAIL);
+    frame
+}
+
+/// 写入队列容量，队列满时调用方阻塞 (背压)
+const WRITER_QUEUE_CAPACITY: usize = 256;
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(2);
+/// 离线时写入线程检查重连的周期
+const RECONNECT_POLL: Duration = Duration::from_millis(100);
+
+enum WriterCmd {
+    /// 最后一项为入队时间，用于统计排队延迟
+    Frame(EventType, [u8; 6], u16, Instant),
+    /// 队列中此前的帧全部写出后回复
+    Flush(mpsc::Sender<()>),
+}
+
+/// 调用方与写入线程共享的状态
+struct LinkShared {
+    online: AtomicBool,
+    pending: AtomicUsize,
+    /// 重试耗尽仍失败的帧数
+    failed_frames: AtomicU32,
+    /// 写入线程中最近一次失败，下一次调用时返回给调用方
+    last_error: Mutex<Option<DriverError>>,
+    on_event: Mutex<Option<DriverEventCallback>>,
+    /// 未开启统计时为 None
+    metrics: Option<Mutex<LatencyMetrics>>,
+}
+
+impl LinkShared {
+    fn record(&self, f: impl FnOnce(&mut LatencyMetrics)) {
+        if let Some(m) = &self.metrics {
+            if let Ok(mut m) = m.lock() {
+                f(&mut m);
+            }
+        }
+    }
+
+    fn take_error(&self) -> Option<DriverError> {
+        self.last_error.lock().ok()?.take()
+    }
+
+    fn emit(&self, event: DriverEvent) {
+        if let Ok(cb) = self.on_event.lock() {
+            if let Some(cb) = cb.as_ref() {
+                cb(&event);
+            }
+        }
+    }
+}
+
+/// 帧协议的底层字节通道：USB 串口或 BLE UART，读取超时返回错误
+pub trait FrameTransport: Read + Write + Send {
+    /// 丢弃已收到但尚未读取的数据
+    fn clear_input(&mut self);
+}
+
+impl FrameTransport for Box<dyn SerialPort> {
+    fn clear_input(&mut self) {
+        let _ = self.clear(serialport::ClearBuffer::Input);
+    }
+}
+
+/// 自制固件的连接方式
+#[derive(Debug, Clone)]
+enum Transport {
+    /// USB 串口，参数为端口名
+    Serial(String),
+    /// BLE UART (Nordic UART Service)，参数为设备名或蓝牙地址
+    Ble(String),
+}
+
+impl Transport {
+    fn open(&self, config: &HardwareConfig) -> Result<Box<dyn FrameTransport>, String> {
+        match self {
+            Transport::Serial(port_name) => serialport::new(port_name, config.baud_rate)
+                .timeout(Duration::from_millis(config.read_timeout_ms))
+                .open()
+                .map(|port| Box::new(port) as Box<dyn FrameTransport>)
+                .map_err(|e| format!("无法打开串口 {}: {}", port_name, e)),
+            Transport::Ble(device) => {
+                let uart = BleUart::connect(device, Duration::from_millis(config.read_timeout_ms))?;
+                Ok(Box::new(uart))
+            }
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            Transport::Serial(name) | Transport::Ble(name) => name,
+        }
+    }
+
+    /// 日志前缀
+    fn label(&self) -> &'static str {
+        match self {
+            Transport::Serial(_) => "串口",
+            Transport::Ble(_) => "BLE",
+        }
+    }
+}
+
+/// 帧链路：由写入线程独占，负责组帧、确认重传与断线重连
+struct SerialLink {
+    port: Box<dyn FrameTransport>,
+    transport: Transport,
+    port_name: String,
+    config: HardwareConfig,
+    /// 固件是否支持确认协议 (握手时自动判断)
+    acked: bool,
+    seq: u8,
+
+    // 断线重连 (指数退避)
+    next_reconnect: Instant,
+    backoff: Duration,
+    shared: Arc<LinkShared>,
+}
+
+impl SerialLink {
+    fn online(&self) -> bool {
+        self.shared.online.load(Ordering::SeqCst)
+    }
+
+    /// 写入失败：标记离线并安排重连
+    fn mark_offline(&mut self, reason: &str) {
+        if self.shared.online.swap(false, Ordering::SeqCst) {
+            self.backoff = RECONNECT_BACKOFF_MIN;
+            self.next_reconnect = Instant::now() + self.backoff;
+            println!("🔌 [{}] {} 已断开: {}", self.transport.label(), self.port_name, reason);
+            self.shared.emit(DriverEvent::Disconnected(reason.to_string()));
+        }
+    }
+
+    /// 到达退避时间后尝试重新打开串口，失败则退避时间翻倍
+    fn try_reconnect(&mut self) -> bool {
+        if Instant::now() < self.next_reconnect {
+            return false;
+        }
+        match self.transport.open(&self.config) {
+            Ok(port) => {
+                self.port = port;
+                self.backoff = RECONNECT_BACKOFF_MIN;
+                self.shared.online.store(true, Ordering::SeqCst);
+                println!("🔌 [{}] {} 已重新连接", self.transport.label(), self.port_name);
+                self.shared.emit(DriverEvent::Reconnected);
+                true
+            }
+            Err(_) => {
+                self.backoff = (self.backoff * 2).min(RECONNECT_BACKOFF_MAX);
+                self.next_reconnect = Instant::now() + self.backoff;
+                false
+            }
+        }
+    }
+
+    /// 写入线程主循环：按 frame_pacing_ms 节奏写出队列中的帧，空闲且离线时尝试重连
+    fn run(mut self, rx: mpsc::Receiver<WriterCmd>) {
+        let pacing = Duration::from_millis(self.config.frame_pacing_ms);
+        loop {
+            match rx.recv_timeout(RECONNECT_POLL) {
+                Ok(WriterCmd::Frame(event_type, b, delay_ms, queued_at)) => {
+                    self.shared.record(|m| m.queue.record(queued_at.elapsed()));
+                    self.send_raw(event_type, b, delay_ms);
+                    self.shared.pending.fetch_sub(1, Ordering::SeqCst);
+                    if !pacing.is_zero() {
+                        let t = Instant::now();
+                        thread::sleep(pacing);
+                        self.shared.record(|m| m.pacing.record(t.elapsed()));
+                    }
+                }
+                Ok(WriterCmd::Flush(done)) => {
+                    let _ = done.send(());
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    if !self.online() {
+                        self.try_reconnect();
+                    }
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+        }
+    }
+
+    fn send_raw(&mut self, event_type: EventType, b: [u8; 6], delay_ms: u16) {
+        if let Err(e) = self.send_frame(event_type, b, delay_ms) {
+            self.shared.failed_frames.fetch_add(1, Ordering::SeqCst);
+            // 写入失败视为断线，后续帧将在退避重连后恢复
+            if let DriverError::Io(reason) = &e {
+                let reason = reason.clone();
+                self.mark_offline(&reason);
+            }
+            if let Ok(mut last) = self.shared.last_error.lock() {
+                *last = Some(e);
+            }
+        }
+    }
+
+    fn send_frame(&mut self, event_type: EventType, b: [u8; 6], delay_ms: u16) -> Result<(), DriverError> {
+        if !self.online() && !self.try_reconnect() {
+            return Err(DriverError::Disconnected(self.port_name.clone()));
+        }
+        if self.acked {
+            self.seq = self.seq.wrapping_add(1);
+            self.send_acked(event_type, b, delay_ms, self.config.max_retries)
+        } else {
+            let t = Instant::now();
+            let frame = build_frame(false, 0, event_type, b, delay_ms);
+            self.shared.record(|m| m.serialize.record(t.elapsed()));
+            let t = Instant::now();
+            let result = self
+                .port
+                .write_all(&frame)
+                .and_then(|_| self.port.flush())
+                .map_err(|e| DriverError::Io(e.to_string()));
+            self.shared.record(|m| m.write.record(t.elapsed()));
+            result
+        }
+    }
+
+    /// 发送并等待确认，超时或校验失败时重传，最多 retries 次
+    fn send_acked(&mut self, event_type: EventType, b: [u8; 6], delay_ms: u16, retries: u32) -> Result<(), DriverError> {
+        let seq = self.seq;
+        let t = Instant::now();
+        let frame = build_frame(true, seq, event_type, b, delay_ms);
+        self.shared.record(|m| m.serialize.record(t.elapsed()));
+        let t = Instant::now();
+        let result = self.write_acked(seq, &frame, retries);
+        self.shared.record(|m| m.write.record(t.elapsed()));
+        result
+    }
+
+    fn write_acked(&mut self, seq: u8, frame: &[u8], retries: u32) -> Result<(), DriverError> {
+        let mut reason = String::new();
+        for _ in 0..=retries {
+            self.port.clear_input();
+            // 写入失败通常意味着设备已拔出，不再重传
+            self.port
+                .write_all(frame)
+                .and_then(|_| self.port.flush())
+                .map_err(|e| DriverError::Io(e.to_string()))?;
+            match self.read_ack(seq) {
+                Some(0) => return Ok(()),
+                Some(status) => reason = format!("设备校验失败 (状态 0x{:02X})", status),
+                None => reason = "等待确认超时".to_string(),
+            }
+        }
+        Err(DriverError::NoAck(format!("帧 #{} 已重传 {} 次: {}", seq, retries, reason)))
+    }
+
+    /// 发送版本查询帧并解析应答；旧固件不应答时返回 None
+    fn query_info(&mut self) -> Option<([u8; 3], DriverFeatures, u16)> {
+        self.seq = self.seq.wrapping_add(1);
+        let mut b = [0u8; 6];
+        b[0] = SystemCmd::Version as u8;
+        let frame = build_frame(self.acked, self.seq, EventType::System, b, 0);
+        self.port.clear_input();
+        self.port.write_all(&frame).and_then(|_| self.port.flush()).ok()?;
+
+        let deadline = Instant::now() + INFO_TIMEOUT;
+        let mut buf: Vec<u8> = Vec::with_capacity(32);
+        let mut chunk = [0u8; 16];
+        while Instant::now() < deadline {
+            if let Ok(n) = self.port.read(&mut chunk) {
+                buf.extend_from_slice(&chunk[..n]);
+            }
+            for w in buf.windows(9) {
+                if w[0] == FRAME_HEAD && w[1] == INFO_MARK && w[8] == FRAME_TAIL {
+                    let rate = u16::from_le_bytes([w[6], w[7]]);
+                    return Some(([w[2], w[3], w[4]], DriverFeatures::from_bits_truncate(w[5]), rate));
+                }
+            }
+        }
+        None
+    }
+
+    /// 读取 AA AC seq status 55，返回 status；超时返回 None
+    fn read_ack(&mut self, seq: u8) -> Option<u8> {
+        let deadline = Instant::now() + Duration::from_millis(self.config.ack_timeout_ms);
+        let mut buf: Vec<u8> = Vec::with_capacity(16);
+        let mut chunk = [0u8; 16];
+        while Instant::now() < deadline {
+            if let Ok(n) = self.port.read(&mut chunk) {
+                buf.extend_from_slice(&chunk[..n]);
+            }
+            for w in buf.windows(5) {
+                if w[0] == FRAME_HEAD && w[1] == ACK_MARK && w[2] == seq && w[4] == FRAME_TAIL {
+                    return Some(w[3]);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// 自制固件驱动 (USB 串口或 BLE)：调用方只负责入队，写入与节奏控制在后台写入线程中完成
 pub struct HardwareDriver {
-    port: Box<dyn SerialPort>,
+    tx: mpsc::SyncSender<WriterCmd>,
+    shared: Arc<LinkShared>,
+    port_name: String,
+    info: DriverInfo,
+    calibration: AbsCalibration,
     pub screen_w: u16,
     pub screen_h: u16,
 }
 
 impl HardwareDriver {
-    pub fn new(port_name: &str, baud_rate: u32, screen_w: u16, screen_h: u16) -> Result<Self, String> {
-        let port = serialport::new(port_name, baud_rate)
-            .timeout(Duration::from_millis(100))
-            .open()
-            .map_err(|e| format!("无法打开串口 {}: {}", port_name, e))?;
+    pub fn new(port_name: &str, config: &HardwareConfig, screen_w: u16, screen_h: u16) -> Result<Self, String> {
+        Self::open(Transport::Serial(port_name.to_string()), config, screen_w, screen_h)
+    }
 
-        Ok(Self { port, screen_w, screen_h })
+    /// 通过 BLE UART 连接的盒子 (需先在系统蓝牙设置中配对)；device 为设备名或 AA:BB:CC:DD:EE:FF 形式的地址
+    pub fn new_ble(device: &str, config: &HardwareConfig, screen_w: u16, screen_h: u16) -> Result<Self, String> {
+        Self::open(Transport::Ble(device.to_string()), config, screen_w, screen_h)
     }
 
-    fn send_raw(&mut self, event_type: EventType, b: [u8; 6], delay_ms: u16) {
-        let mut frame = Vec::with_capacity(11);
-        frame.push(FRAME_HEAD);
-        frame.push(event_type as u8);
-        frame.extend_from_slice(&b);
-        frame.write_u16::<LittleEndian>(delay_ms).unwrap();
-        frame.push(FRAME_TAIL);
+    fn open(transport: Transport, config: &HardwareConfig, screen_w: u16, screen_h: u16) -> Result<Self, String> {
+        let port = transport.open(config)?;
+        let port_name = transport.name().to_string();
+        let label = transport.label();
+        let shared = Arc::new(LinkShared {
+            online: AtomicBool::new(true),
+            pending: AtomicUsize::new(0),
+            failed_frames: AtomicU32::new(0),
+            last_error: Mutex::new(None),
+            on_event: Mutex::new(None),
+            metrics: config.metrics.then(|| Mutex::new(LatencyMetrics::default())),
+        });
+
+        let mut link = SerialLink {
+            port,
+            transport: transport.clone(),
+            port_name: port_name.clone(),
+            config: config.clone(),
+            acked: true,
+            seq: 0,
+            next_reconnect: Instant::now(),
+            backoff: RECONNECT_BACKOFF_MIN,
+            shared: Arc::clone(&shared),
+        };
+
+        // 握手：发送一帧确认格式的心跳，无应答则按旧固件处理
+        let mut b = [0u8; 6];
+        b[0] = SystemCmd::Heartbeat as u8;
+        if link.send_acked(EventType::System, b, 0, 0).is_err() {
+            println!("⚠️ [{}] 固件未应答确认协议，使用旧帧格式 (无重传)", label);
+            link.acked = false;
+        }
+
+        // 版本查询：旧固件不应答，只记录握手得到的能力
+        let name = match transport {
+            Transport::Serial(_) => "自制串口固件",
+            Transport::Ble(_) => "自制固件 (BLE)",
+        };
+        let mut info = DriverInfo::named(name, DriverFeatures::empty());
+        match link.query_info() {
+            Some(([major, minor, patch], features, rate)) => {
+                info.firmware = Some((major, minor, patch));
+                info.features = features;
+                info.max_report_rate = (rate > 0).then_some(rate);
+            }
+            None => println!("⚠️ [{}] 固件未应答版本查询 (旧固件)，能力按握手结果推断", label),
+        }
+        info.features.set(DriverFeatures::ACKED, link.acked);
+
+        let (tx, rx) = mpsc::sync_channel(WRITER_QUEUE_CAPACITY);
+        thread::Builder::new()
+            .name("serial-writer".to_string())
+            .spawn(move || link.run(rx))
+            .map_err(|e| format!("无法启动串口写入线程: {}", e))?;
 
-        let _ = self.port.write_all(&frame);
-        let _ = self.port.flush();
-        thread::sleep(Duration::from_millis(4));
+        let calibration = config.calibration.unwrap_or_default();
+        Ok(Self { tx, shared, port_name, info, calibration, screen_w, screen_h })
+    }
+
+    /// 探测该串口上是否为本项目固件：发送心跳帧，期望收到以帧头开始的回包
+    pub fn probe(port_name: &str, baud_rate: u32) -> bool {
+        let Ok(mut port) = serialport::new(port_name, baud_rate)
+            .timeout(Duration::from_millis(200))
+            .open()
+        else {
+            return false;
+        };
+        let _ = port.clear(serialport::ClearBuffer::Input);
+        let mut b = [0u8; 6];
+        b[0] = SystemCmd::Heartbeat as u8;
+        let frame = build_frame(true, 0, EventType::System, b, 0);
+        if port.write_all(&frame).and_then(|_| port.flush()).is_err() {
+            return false;
+        }
+        let mut buf = [0u8; 16];
+        matches!(port.read(&mut buf), Ok(n) if n > 0 && buf[0] == FRAME_HEAD)
+    }
+
+    /// 重试耗尽仍失败的帧数
+    pub fn failed_frames(&self) -> u32 {
+        self.shared.failed_frames.load(Ordering::SeqCst)
     }
-}
 
-unsafe impl Sync for HardwareDriver {}
+    /// 入队一帧；写入线程中此前发生的失败在入队之后返回。
+    /// 即使之前出错或已离线也照常入队 (写入线程会先尝试重连)，
+    /// 否则上一帧的失败会连带吞掉随后的 mouse_up / key_up，导致按键卡住
+    fn send_raw(&mut self, event_type: EventType, b: [u8; 6], delay_ms: u16) -> Result<(), DriverError> {
+        self.shared.pending.fetch_add(1, Ordering::SeqCst);
+        self.tx.send(WriterCmd::Frame(event_type, b, delay_ms, Instant::now())).map_err(|_| {
+            self.shared.pending.fetch_sub(1, Ordering::SeqCst);
+            DriverError::Io("串口写入线程已退出".to_string())
+        })?;
+        if let Some(e) = self.shared.take_error() {
+            return Err(e);
+        }
+        if !self.shared.online.load(Ordering::SeqCst) {
+            return Err(DriverError::Disconnected(self.port_name.clone()));
+        }
+        Ok(())
+    }
+}
 
 impl InputDriver for HardwareDriver {
-    fn heartbeat(&mut self) {
+    fn info(&self) -> DriverInfo {
+        self.info.clone()
+    }
+
+    /// 固件只模拟 HID 键盘，主机上的输入法 / Unicode 字符无法经由键码送达
+    fn type_text(&mut self, text: &str) -> Result<(), DriverError> {
+        Err(DriverError::Unsupported(format!(
+            "自制固件只能发送 HID 键码，无法输入 \"{}\" (中文请改用 SOFT 或 window: 驱动)",
+            text
+        )))
+    }
+
+    fn latency_metrics(&self) -> Option<LatencyMetrics> {
+        self.shared.metrics.as_ref()?.lock().ok().map(|m| m.clone())
+    }
+
+    /// 按固件应答版本查询时声明的能力；未应答的旧固件只按最基本的能力处理
+    /// (单键、无水平滚轮；身份切换保持原有的默认行为)
+    fn capabilities(&self) -> DriverCaps {
+        let mut caps = DriverCaps::default();
+        let f = self.info.features;
+        caps.hscroll = f.contains(DriverFeatures::HWHEEL);
+        if !f.contains(DriverFeatures::KEYBOARD_REPORT) {
+            caps.max_keys_down = 1;
+        }
+        if self.info.firmware.is_some() {
+            caps.identity = f.contains(DriverFeatures::IDENTITY);
+        }
+        caps
+    }
+
+    fn is_online(&self) -> bool {
+        self.shared.online.load(Ordering::SeqCst)
+    }
+
+    fn set_event_callback(&mut self, callback: DriverEventCallback) {
+        if let Ok(mut cb) = self.shared.on_event.lock() {
+            *cb = Some(callback);
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), DriverError> {
+        let (done_tx, done_rx) = mpsc::channel();
+        self.tx
+            .send(WriterCmd::Flush(done_tx))
+            .map_err(|_| DriverError::Io("串口写入线程已退出".to_string()))?;
+        done_rx
+            .recv_timeout(FLUSH_TIMEOUT)
+            .map_err(|_| DriverError::Io(format!("等待队列写出超时 (剩余 {} 帧)", self.pending())))?;
+        match self.shared.take_error() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    fn pending(&self) -> usize {
+        self.shared.pending.load(Ordering::SeqCst)
+    }
+
+    fn heartbeat(&mut self) -> Result<(), DriverError> {
         let mut b = [0u8; 6];
         b[0] = SystemCmd::Heartbeat as u8;
-        self.send_raw(EventType::System, b, 0);
+        self.send_raw(EventType::System, b, 0)
     }
 
-    fn switch_identity(&mut self, index: u8) {
+    fn switch_identity(&mut self, index: u8) -> Result<(), DriverError> {
         let mut b = [0u8; 6];
         b[0] = SystemCmd::SetId as u8;
         b[1] = index;
-        self.send_raw(EventType::System, b, 0);
+        self.send_raw(EventType::System, b, 0)
     }
 
-    fn mouse_abs(&mut self, x: u16, y: u16) {
-        let tx = ((x as f32 / self.screen_w as f32) * 32767.0) as u16;
-        let ty = ((y as f32 / self.screen_h as f32) * 32767.0) as u16;
+    fn mouse_abs(&mut self, x: u16, y: u16) -> Result<(), DriverError> {
+        let (cx, cy) = self.calibration.apply(x as f32, y as f32);
+        let tx = ((cx / self.screen_w as f32) * 32767.0).round().max(0.0) as u16;
+        let ty = ((cy / self.screen_h as f32) * 32767.0).round().max(0.0) as u16;
         let tx = tx.clamp(10, 32757);
         let ty = ty.clamp(10, 32757);
 
@@ -100,12 +1030,12 @@ impl InputDriver for HardwareDriver {
         b[3] = ((tx >> 8) & 0xFF) as u8;
         b[4] = (ty & 0xFF) as u8;
         b[5] = ((ty >> 8) & 0xFF) as u8;
-        self.send_raw(EventType::MouseAbs, b, 0);
+        self.send_raw(EventType::MouseAbs, b, 0)
     }
 
-    fn mouse_move(&mut self, dx: i32, dy: i32, wheel: i8) {
+    fn mouse_move(&mut self, dx: i32, dy: i32, wheel: i8) -> Result<(), DriverError> {
         if wheel != 0 {
-            self.send_raw(EventType::MouseRel, [0, wheel as u8, 0, 0, 0, 0], 0);
+            self.send_raw(EventType::MouseRel, [0, wheel as u8, 0, 0, 0, 0], 0)?;
         }
         let max_step = 127;
         let mut cur_dx = dx;
@@ -118,30 +1048,54 @@ impl InputDriver for HardwareDriver {
             let bx = (step_x as i16).to_le_bytes();
             let by = (step_y as i16).to_le_bytes();
             
-            self.send_raw(EventType::MouseRel, [0, 0, bx[0], bx[1], by[0], by[1]], 0);
+            self.send_raw(EventType::MouseRel, [0, 0, bx[0], bx[1], by[0], by[1]], 0)?;
             
             cur_dx -= step_x;
             cur_dy -= step_y;
         }
+        Ok(())
+    }
+
+    fn mouse_down(&mut self, buttons: MouseButtons) -> Result<(), DriverError> {
+        self.send_raw(EventType::MouseRel, [buttons.bits(), 0, 0, 0, 0, 0], 0)
+    }
+
+    fn mouse_up(&mut self) -> Result<(), DriverError> {
+        self.send_raw(EventType::MouseRel, [0, 0, 0, 0, 0, 0], 0)
+    }
+
+    fn key_down(&mut self, keycode: u8, modifier: u8) -> Result<(), DriverError> {
+        self.send_raw(EventType::Keyboard, [keycode, 0x00, modifier, 0, 0, 0], 0)
     }
 
-    fn mouse_down(&mut self, left: bool, right: bool) {
-        let mut mask = 0;
-        if left { mask |= 0x01; }
-        if right { mask |= 0x02; }
-        self.send_raw(EventType::MouseRel, [mask, 0, 0, 0, 0, 0], 0);
+    fn key_up(&mut self) -> Result<(), DriverError> {
+        self.send_raw(EventType::Keyboard, [0, 0x80, 0, 0, 0, 0], 0)
     }
 
-    fn mouse_up(&mut self) {
-        self.send_raw(EventType::MouseRel, [0, 0, 0, 0, 0, 0], 0);
+    /// 固件不支持完整键盘报告时退化为单键帧，只按住第一个键
+    fn keys_down(&mut self, keycodes: &[u8], modifier: u8) -> Result<(), DriverError> {
+        let k = chord_report(keycodes);
+        if !self.info.features.contains(DriverFeatures::KEYBOARD_REPORT) {
+            return if k[0] == 0 && modifier == 0 { self.key_up() } else { self.key_down(k[0], modifier) };
+        }
+        self.send_raw(EventType::KeyboardReport, [modifier, k[0], k[1], k[2], k[3], k[4]], k[5] as u16)
     }
 
-    fn key_down(&mut self, keycode: u8, modifier: u8) {
-        self.send_raw(EventType::Keyboard, [keycode, 0x00, modifier, 0, 0, 0], 0);
+    fn mouse_hscroll(&mut self, delta: i8) -> Result<(), DriverError> {
+        self.send_raw(EventType::MouseHWheel, [delta as u8, 0, 0, 0, 0, 0], 0)
     }
 
-    fn key_up(&mut self) {
-        self.send_raw(EventType::Keyboard, [0, 0x80, 0, 0, 0, 0], 0);
+    /// 自定义帧与普通帧走同一队列、同样的确认与重传；不能占用内置帧类型，最高位留给确认标志
+    fn send_custom(&mut self, event_type: u8, payload: [u8; 6], delay: u16) -> Result<(), DriverError> {
+        if !CUSTOM_EVENT_TYPES.contains(&event_type) {
+            return Err(DriverError::Unsupported(format!(
+                "自定义帧类型 0x{:02X} (可用范围 0x{:02X} ~ 0x{:02X})",
+                event_type,
+                CUSTOM_EVENT_TYPES.start(),
+                CUSTOM_EVENT_TYPES.end()
+            )));
+        }
+        self.send_raw(EventType::Custom(event_type), payload, delay)
     }
 }
 
@@ -152,10 +1106,31 @@ pub struct SoftwareDriver {
     enigo: Enigo,
     pub screen_w: u16,
     pub screen_h: u16,
-    last_key: Option<Key>,
+    /// 当前按住的普通键与修饰键，key_up 时一并释放
+    held_keys: Vec<Key>,
+    held_modifiers: Vec<Key>,
+    held_buttons: MouseButtons,
 }
 
-unsafe impl Sync for SoftwareDriver {}
+const ENIGO_BUTTONS: [(MouseButtons, Button); 5] = [
+    (MouseButtons::LEFT, Button::Left),
+    (MouseButtons::RIGHT, Button::Right),
+    (MouseButtons::MIDDLE, Button::Middle),
+    (MouseButtons::X1, Button::Back),
+    (MouseButtons::X2, Button::Forward),
+];
+
+/// HID 修饰键字节的各位 (LCtrl, LShift, LAlt, LGui, RCtrl, RShift, RAlt, RGui)
+const HID_MODIFIER_KEYS: [Key; 8] = [
+    Key::LControl,
+    Key::LShift,
+    Key::LMenu,
+    Key::LWin,
+    Key::RControl,
+    Key::RShift,
+    Key::RMenu,
+    Key::RWin,
+];
 
 impl SoftwareDriver {
     pub fn new(screen_w: u16, screen_h: u16) -> Self {
@@ -163,11 +1138,64 @@ impl SoftwareDriver {
             enigo: Enigo::new(&Settings::default()).unwrap(),
             screen_w,
             screen_h,
-            last_key: None,
+            held_keys: Vec::new(),
+            held_modifiers: Vec::new(),
+            held_buttons: MouseButtons::empty(),
+        }
+    }
+
+    /// 与当前按键状态做差：先松开不再需要的键，再按下新增的键 (修饰键先按后松)
+    fn set_held(&mut self, keys: Vec<Key>, modifier: u8) -> Result<(), DriverError> {
+        let mods: Vec<Key> = HID_MODIFIER_KEYS
+            .iter()
+            .enumerate()
+            .filter(|(bit, _)| modifier & (1 << bit) != 0)
+            .map(|(_, k)| *k)
+            .collect();
+
+        // 某个键松开失败时继续松开其余的键，返回第一个错误
+        let mut released = Ok(());
+        for key in self.held_keys.clone().into_iter().rev() {
+            if !keys.contains(&key) {
+                self.held_keys.retain(|k| *k != key);
+                released = released.and(self.enigo.key(key, Direction::Release).map_err(io_err));
+            }
+        }
+        for key in self.held_modifiers.clone().into_iter().rev() {
+            if !mods.contains(&key) {
+                self.held_modifiers.retain(|k| *k != key);
+                released = released.and(self.enigo.key(key, Direction::Release).map_err(io_err));
+            }
+        }
+        released?;
+        for key in mods {
+            if !self.held_modifiers.contains(&key) {
+                self.enigo.key(key, Direction::Press).map_err(io_err)?;
+                self.held_modifiers.push(key);
+            }
         }
+        for key in keys {
+            if !self.held_keys.contains(&key) {
+                self.enigo.key(key, Direction::Press).map_err(io_err)?;
+                self.held_keys.push(key);
+            }
+        }
+        Ok(())
+    }
+
+    /// 写入剪贴板后按 Ctrl+V (会覆盖剪贴板原有内容)
+    fn paste(&mut self, text: &str) -> Result<(), DriverError> {
+        clipboard::set_text(text).map_err(DriverError::Io)?;
+        // 按下 Ctrl+V 后松开所有键，与 key_up 的效果一致
+        self.set_held(vec![Key::Unicode('v')], 0x01)?;
+        self.set_held(Vec::new(), 0)
     }
 
     fn hid_to_enigo(&self, hid: u8) -> Option<Key> {
+        // 字符键按目标机器的布局取该位置的字符，enigo 再按系统布局找到对应的按键
+        if let Some(c) = keymap::current().char_for(hid) {
+            return Some(Key::Unicode(c));
+        }
         match hid {
             0x04..=0x1D => { 
                 let c = (b'a' + (hid - 0x04)) as char;
@@ -189,85 +1217,1264 @@ impl SoftwareDriver {
             0x31 => Some(Key::Unicode('\\')),
             0x33 => Some(Key::Unicode(';')),
             0x34 => Some(Key::Unicode('\'')),
+            0x35 => Some(Key::Unicode('`')),
             0x36 => Some(Key::Unicode(',')),
             0x37 => Some(Key::Unicode('.')),
             0x38 => Some(Key::Unicode('/')),
-            0xE0 => Some(Key::Control),
-            0xE1 => Some(Key::Shift),
-            0xE2 => Some(Key::Alt),
+            0x39 => Some(Key::CapsLock),
+
+            // 功能键 F1~F12 (0x3A~0x45)，F13~F24 (0x68~0x73)
+            0x3A => Some(Key::F1),
+            0x3B => Some(Key::F2),
+            0x3C => Some(Key::F3),
+            0x3D => Some(Key::F4),
+            0x3E => Some(Key::F5),
+            0x3F => Some(Key::F6),
+            0x40 => Some(Key::F7),
+            0x41 => Some(Key::F8),
+            0x42 => Some(Key::F9),
+            0x43 => Some(Key::F10),
+            0x44 => Some(Key::F11),
+            0x45 => Some(Key::F12),
+            0x68 => Some(Key::F13),
+            0x69 => Some(Key::F14),
+            0x6A => Some(Key::F15),
+            0x6B => Some(Key::F16),
+            0x6C => Some(Key::F17),
+            0x6D => Some(Key::F18),
+            0x6E => Some(Key::F19),
+            0x6F => Some(Key::F20),
+            0x70 => Some(Key::F21),
+            0x71 => Some(Key::F22),
+            0x72 => Some(Key::F23),
+            0x73 => Some(Key::F24),
+
+            // 编辑 / 导航区
+            0x46 => Some(Key::PrintScr),
+            0x47 => Some(Key::Scroll),
+            0x48 => Some(Key::Pause),
+            0x49 => Some(Key::Insert),
+            0x4A => Some(Key::Home),
+            0x4B => Some(Key::PageUp),
+            0x4C => Some(Key::Delete),
+            0x4D => Some(Key::End),
+            0x4E => Some(Key::PageDown),
+            0x4F => Some(Key::RightArrow),
+            0x50 => Some(Key::LeftArrow),
+            0x51 => Some(Key::DownArrow),
+            0x52 => Some(Key::UpArrow),
+
+            // 小键盘
+            0x53 => Some(Key::Numlock),
+            0x54 => Some(Key::Divide),
+            0x55 => Some(Key::Multiply),
+            0x56 => Some(Key::Subtract),
+            0x57 => Some(Key::Add),
+            0x58 => Some(Key::Return),
+            0x59 => Some(Key::Numpad1),
+            0x5A => Some(Key::Numpad2),
+            0x5B => Some(Key::Numpad3),
+            0x5C => Some(Key::Numpad4),
+            0x5D => Some(Key::Numpad5),
+            0x5E => Some(Key::Numpad6),
+            0x5F => Some(Key::Numpad7),
+            0x60 => Some(Key::Numpad8),
+            0x61 => Some(Key::Numpad9),
+            0x62 => Some(Key::Numpad0),
+            0x63 => Some(Key::Decimal),
+            0x65 => Some(Key::Apps),
+
+            // 修饰键本身作为普通键码发送时
+            0xE0 => Some(Key::LControl),
+            0xE1 => Some(Key::LShift),
+            0xE2 => Some(Key::LMenu),
+            0xE3 => Some(Key::LWin),
+            0xE4 => Some(Key::RControl),
+            0xE5 => Some(Key::RShift),
+            0xE6 => Some(Key::RMenu),
+            0xE7 => Some(Key::RWin),
             _ => None,
         }
     }
 }
 
 impl InputDriver for SoftwareDriver {
-    fn heartbeat(&mut self) {}
-    fn switch_identity(&mut self, _index: u8) {}
+    fn info(&self) -> DriverInfo {
+        DriverInfo::named("软件模拟 (enigo)", DriverFeatures::KEYBOARD_REPORT | DriverFeatures::HWHEEL)
+    }
+
+    fn capabilities(&self) -> DriverCaps {
+        DriverCaps { identity: false, text_input: true, os_cursor: true, ..Default::default() }
+    }
+
+    fn heartbeat(&mut self) -> Result<(), DriverError> { Ok(()) }
+    fn switch_identity(&mut self, _index: u8) -> Result<(), DriverError> { Ok(()) }
+
+    /// 逐字符注入 Unicode 键盘事件 (绕过输入法)；[keyboard] paste_text 开启或注入失败时改为剪贴板粘贴
+    fn type_text(&mut self, text: &str) -> Result<(), DriverError> {
+        if !keymap::paste_text() {
+            let mut chars = text.char_indices();
+            for (i, ch) in chars.by_ref() {
+                let mut buf = [0u8; 4];
+                if let Err(e) = self.enigo.text(ch.encode_utf8(&mut buf)) {
+                    println!("⚠️ [Software] Unicode 注入失败 ({})，剩余文本改用剪贴板粘贴", e);
+                    return self.paste(&text[i..]);
+                }
+            }
+            return Ok(());
+        }
+        self.paste(text)
+    }
 
-    fn mouse_abs(&mut self, x: u16, y: u16) {
-        let _ = self.enigo.move_mouse(x as i32, y as i32, Coordinate::Abs);
+    fn mouse_abs(&mut self, x: u16, y: u16) -> Result<(), DriverError> {
+        self.enigo.move_mouse(x as i32, y as i32, Coordinate::Abs).map_err(io_err)
     }
 
-    fn mouse_move(&mut self, dx: i32, dy: i32, wheel: i8) {
-        let _ = self.enigo.move_mouse(dx, dy, Coordinate::Rel);
+    fn mouse_move(&mut self, dx: i32, dy: i32, wheel: i8) -> Result<(), DriverError> {
+        self.enigo.move_mouse(dx, dy, Coordinate::Rel).map_err(io_err)?;
         if wheel != 0 {
             // ✨ Corrected scroll usage
-            let _ = self.enigo.scroll(-wheel as i32, Axis::Vertical);
+            self.enigo.scroll(-wheel as i32, Axis::Vertical).map_err(io_err)?;
         }
+        Ok(())
     }
 
-    fn mouse_down(&mut self, left: bool, right: bool) {
-        if left { let _ = self.enigo.button(Button::Left, Direction::Press); }
-        if right { let _ = self.enigo.button(Button::Right, Direction::Press); }
+    fn mouse_hscroll(&mut self, delta: i8) -> Result<(), DriverError> {
+        self.enigo.scroll(delta as i32, Axis::Horizontal).map_err(io_err)
     }
 
-    fn mouse_up(&mut self) {
-        let _ = self.enigo.button(Button::Left, Direction::Release);
-        let _ = self.enigo.button(Button::Right, Direction::Release);
+    fn mouse_down(&mut self, buttons: MouseButtons) -> Result<(), DriverError> {
+        for (flag, button) in ENIGO_BUTTONS {
+            if buttons.contains(flag) {
+                self.enigo.button(button, Direction::Press).map_err(io_err)?;
+                self.held_buttons |= flag;
+            }
+        }
+        Ok(())
     }
 
-    fn key_down(&mut self, keycode: u8, modifier: u8) {
-        if (modifier & 0x02) != 0 || (modifier & 0x20) != 0 {
-            let _ = self.enigo.key(Key::Shift, Direction::Press);
+    fn mouse_up(&mut self) -> Result<(), DriverError> {
+        // 左右键始终释放 (与旧行为一致)，其余按键只释放按下过的
+        // 某个键松开失败时继续松开其余的键，返回第一个错误
+        let held = std::mem::take(&mut self.held_buttons) | MouseButtons::LEFT | MouseButtons::RIGHT;
+        let mut released = Ok(());
+        for (flag, button) in ENIGO_BUTTONS {
+            if held.contains(flag) {
+                released = released.and(self.enigo.button(button, Direction::Release).map_err(io_err));
+            }
         }
+        released
+    }
 
+    fn key_down(&mut self, keycode: u8, modifier: u8) -> Result<(), DriverError> {
+        let mut keys: Vec<Key> = self.held_keys.clone();
         if let Some(key) = self.hid_to_enigo(keycode) {
-            let _ = self.enigo.key(key, Direction::Press);
-            self.last_key = Some(key);
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+        self.set_held(keys, modifier)
+    }
+
+    fn key_up(&mut self) -> Result<(), DriverError> {
+        self.set_held(Vec::new(), 0)
+    }
+
+    fn keys_down(&mut self, keycodes: &[u8], modifier: u8) -> Result<(), DriverError> {
+        let keys = chord_report(keycodes).iter().filter_map(|k| self.hid_to_enigo(*k)).collect();
+        self.set_held(keys, modifier)
+    }
+}
+
+/// enigo 的模拟失败统一视为 I/O 错误
+fn io_err(e: enigo::InputError) -> DriverError {
+    DriverError::Io(e.to_string())
+}
+
+// ==========================================
+// 4. KMBox Net Driver (UDP)
+// ==========================================
+const KMNET_CMD_CONNECT: u32 = 0xaf3c_2828;
+const KMNET_CMD_MOUSE_MOVE: u32 = 0xaede_7345;
+const KMNET_CMD_MOUSE_LEFT: u32 = 0x9823_ae8d;
+const KMNET_CMD_MOUSE_MIDDLE: u32 = 0x97a3_ae8d;
+const KMNET_CMD_MOUSE_RIGHT: u32 = 0x238d_8212;
+const KMNET_CMD_MOUSE_WHEEL: u32 = 0xffee_ad38;
+const KMNET_CMD_KEYBOARD_ALL: u32 = 0x123c_2c2f;
+
+/// 盒子上显示的 UUID 写在地址后面: "192.168.2.188:16896@A1B2C3D4"
+pub struct KmboxNetDriver {
+    socket: UdpSocket,
+    mac: u32,
+    index: u32,
+    /// soft_mouse_t 中持续生效的按键状态
+    buttons: i32,
+    /// soft_keyboard_t: ctrl 修饰键 + 最多 10 个按键
+    kb_ctrl: u8,
+    kb_keys: [u8; 10],
+    pub screen_w: u16,
+    pub screen_h: u16,
+}
+
+impl KmboxNetDriver {
+    pub fn new(addr: &str, screen_w: u16, screen_h: u16) -> Result<Self, String> {
+        let (host, uuid) = match addr.split_once('@') {
+            Some((h, u)) => (h, Some(u)),
+            None => (addr, None),
+        };
+        let mac = match uuid {
+            Some(u) => u32::from_str_radix(u.trim(), 16).map_err(|_| format!("无效的 KMBox UUID: {}", u))?,
+            None => {
+                println!("⚠️ [KMBox] 地址中未提供 UUID (ip:port@UUID)，部分固件会拒绝连接");
+                0
+            }
+        };
+
+        let socket = UdpSocket::bind("0.0.0.0:0").map_err(|e| format!("无法创建 UDP 套接字: {}", e))?;
+        socket.connect(host).map_err(|e| format!("无法连接 KMBox {}: {}", host, e))?;
+        socket
+            .set_read_timeout(Some(Duration::from_millis(100)))
+            .map_err(|e| e.to_string())?;
+
+        let mut drv = Self {
+            socket,
+            mac,
+            index: 0,
+            buttons: 0,
+            kb_ctrl: 0,
+            kb_keys: [0; 10],
+            screen_w,
+            screen_h,
+        };
+        if let Err(e) = drv.send_cmd(KMNET_CMD_CONNECT, &[]) {
+            return Err(format!("KMBox {} {} (请检查 IP/端口/UUID)", host, e));
+        }
+        println!("🌐 [KMBox] 已连接 {}", host);
+        Ok(drv)
+    }
+
+    /// 发送一条命令：16 字节包头 (mac, rand, index, cmd) + 负载，等待盒子应答
+    fn send_cmd(&mut self, cmd: u32, payload: &[u8]) -> Result<(), DriverError> {
+        self.index = self.index.wrapping_add(1);
+        let mut frame = Vec::with_capacity(16 + payload.len());
+        frame.write_u32::<LittleEndian>(self.mac).unwrap();
+        frame.write_u32::<LittleEndian>(rand::random::<u32>()).unwrap();
+        frame.write_u32::<LittleEndian>(self.index).unwrap();
+        frame.write_u32::<LittleEndian>(cmd).unwrap();
+        frame.extend_from_slice(payload);
+
+        self.socket.send(&frame).map_err(|e| DriverError::Io(e.to_string()))?;
+        // 盒子会回显包头作为应答
+        let mut buf = [0u8; 1024];
+        match self.socket.recv(&mut buf) {
+            Ok(n) if n >= 16 => Ok(()),
+            _ => Err(DriverError::NoAck(format!("KMBox 命令 {:#x} 无应答", cmd))),
+        }
+    }
+
+    /// soft_mouse_t { button, x, y, wheel, point[10] }
+    fn send_mouse(&mut self, cmd: u32, x: i32, y: i32, wheel: i32) -> Result<(), DriverError> {
+        let mut payload = Vec::with_capacity(56);
+        for v in [self.buttons, x, y, wheel] {
+            payload.write_i32::<LittleEndian>(v).unwrap();
+        }
+        payload.resize(56, 0);
+        self.send_cmd(cmd, &payload)
+    }
+
+    /// 为状态发生变化的按键下发对应命令；侧键没有独立命令，随 button 字段经移动命令下发
+    fn send_buttons(&mut self, changed: MouseButtons) -> Result<(), DriverError> {
+        if changed.contains(MouseButtons::LEFT) {
+            self.send_mouse(KMNET_CMD_MOUSE_LEFT, 0, 0, 0)?;
+        }
+        if changed.contains(MouseButtons::RIGHT) {
+            self.send_mouse(KMNET_CMD_MOUSE_RIGHT, 0, 0, 0)?;
+        }
+        if changed.contains(MouseButtons::MIDDLE) {
+            self.send_mouse(KMNET_CMD_MOUSE_MIDDLE, 0, 0, 0)?;
+        }
+        if changed.intersects(MouseButtons::X1 | MouseButtons::X2) {
+            self.send_mouse(KMNET_CMD_MOUSE_MOVE, 0, 0, 0)?;
+        }
+        Ok(())
+    }
+
+    fn send_keyboard(&mut self) -> Result<(), DriverError> {
+        let mut payload = vec![self.kb_ctrl, 0];
+        payload.extend_from_slice(&self.kb_keys);
+        self.send_cmd(KMNET_CMD_KEYBOARD_ALL, &payload)
+    }
+}
+
+impl InputDriver for KmboxNetDriver {
+    fn info(&self) -> DriverInfo {
+        DriverInfo::named("KMBox Net", DriverFeatures::ACKED | DriverFeatures::KEYBOARD_REPORT)
+    }
+
+    /// mouse_abs 依赖本机光标位置换算，KMBox 接在另一台电脑上时并不准确，上层应直接发相对位移
+    fn capabilities(&self) -> DriverCaps {
+        DriverCaps { absolute_mouse: false, hscroll: false, identity: false, ..Default::default() }
+    }
+
+    fn heartbeat(&mut self) -> Result<(), DriverError> { Ok(()) }
+
+    fn switch_identity(&mut self, _index: u8) -> Result<(), DriverError> { Ok(()) }
+
+    /// KMBox 只支持相对移动：读取当前光标位置后发送差值
+    fn mouse_abs(&mut self, x: u16, y: u16) -> Result<(), DriverError> {
+        let mut cur = POINT::default();
+        unsafe { GetCursorPos(&mut cur) }.map_err(|e| DriverError::Io(format!("读取光标位置失败: {}", e)))?;
+        let tx = (x as i32).min(self.screen_w as i32 - 1);
+        let ty = (y as i32).min(self.screen_h as i32 - 1);
+        let (dx, dy) = (tx - cur.x, ty - cur.y);
+        if dx != 0 || dy != 0 {
+            self.mouse_move(dx, dy, 0)?;
+        }
+        Ok(())
+    }
+
+    fn mouse_move(&mut self, dx: i32, dy: i32, wheel: i8) -> Result<(), DriverError> {
+        if dx != 0 || dy != 0 {
+            self.send_mouse(KMNET_CMD_MOUSE_MOVE, dx, dy, 0)?;
+        }
+        if wheel != 0 {
+            self.send_mouse(KMNET_CMD_MOUSE_WHEEL, 0, 0, wheel as i32)?;
+        }
+        Ok(())
+    }
+
+    fn mouse_down(&mut self, buttons: MouseButtons) -> Result<(), DriverError> {
+        self.buttons |= buttons.bits() as i32;
+        self.send_buttons(buttons)
+    }
+
+    fn mouse_up(&mut self) -> Result<(), DriverError> {
+        let old = MouseButtons::from_bits_truncate(self.buttons as u8);
+        self.buttons = 0;
+        self.send_buttons(old)
+    }
+
+    fn key_down(&mut self, keycode: u8, modifier: u8) -> Result<(), DriverError> {
+        self.kb_ctrl = modifier;
+        if !self.kb_keys.contains(&keycode) {
+            if let Some(slot) = self.kb_keys.iter_mut().find(|k| **k == 0) {
+                *slot = keycode;
+            }
+        }
+        self.send_keyboard()
+    }
+
+    fn key_up(&mut self) -> Result<(), DriverError> {
+        self.kb_ctrl = 0;
+        self.kb_keys = [0; 10];
+        self.send_keyboard()
+    }
+
+    fn keys_down(&mut self, keycodes: &[u8], modifier: u8) -> Result<(), DriverError> {
+        self.kb_ctrl = modifier;
+        self.kb_keys = [0; 10];
+        self.kb_keys[..MAX_CHORD_KEYS].copy_from_slice(&chord_report(keycodes));
+        self.send_keyboard()
+    }
+}
+
+// ==========================================
+// 5. CH9329 Driver (标准串口转 HID 芯片)
+// ==========================================
+const CH9329_HEAD: [u8; 2] = [0x57, 0xAB];
+const CH9329_ADDR: u8 = 0x00;
+const CH9329_CMD_GET_INFO: u8 = 0x01;
+const CH9329_CMD_KB_GENERAL: u8 = 0x02;
+const CH9329_CMD_MS_ABS: u8 = 0x04;
+const CH9329_CMD_MS_REL: u8 = 0x05;
+/// CH9329 出厂默认波特率
+pub const CH9329_DEFAULT_BAUD: u32 = 9600;
+
+pub struct Ch9329Driver {
+    port: Box<dyn SerialPort>,
+    /// 当前按下的鼠标键 (bit0 左, bit1 右)，移动时需要一并发送
+    buttons: u8,
+    modifier: u8,
+    keys: [u8; 6],
+    pub screen_w: u16,
+    pub screen_h: u16,
+}
+
+impl Ch9329Driver {
+    pub fn new(port_name: &str, baud_rate: u32, screen_w: u16, screen_h: u16) -> Result<Self, String> {
+        let port = serialport::new(port_name, baud_rate)
+            .timeout(Duration::from_millis(100))
+            .open()
+            .map_err(|e| format!("无法打开串口 {}: {}", port_name, e))?;
+
+        let mut drv = Self { port, buttons: 0, modifier: 0, keys: [0; 6], screen_w, screen_h };
+        if !drv.query_info() {
+            println!("⚠️ [CH9329] 芯片未应答 GET_INFO，请确认波特率 (默认 {})", CH9329_DEFAULT_BAUD);
         }
+        Ok(drv)
+    }
+
+    /// 探测该串口上是否为 CH9329 (GET_INFO 有正确应答)
+    pub fn probe(port_name: &str, baud_rate: u32) -> bool {
+        match serialport::new(port_name, baud_rate).timeout(Duration::from_millis(200)).open() {
+            Ok(port) => {
+                let mut drv = Self { port, buttons: 0, modifier: 0, keys: [0; 6], screen_w: 1, screen_h: 1 };
+                drv.query_info()
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// 帧格式: 57 AB | ADDR | CMD | LEN | DATA | SUM (前面所有字节累加和)
+    fn send_cmd(&mut self, cmd: u8, data: &[u8]) -> Result<(), DriverError> {
+        let mut frame = Vec::with_capacity(6 + data.len());
+        frame.extend_from_slice(&CH9329_HEAD);
+        frame.push(CH9329_ADDR);
+        frame.push(cmd);
+        frame.push(data.len() as u8);
+        frame.extend_from_slice(data);
+        let sum = frame.iter().fold(0u8, |acc, b| acc.wrapping_add(*b));
+        frame.push(sum);
+
+        // 芯片对每条命令都会回包，这里丢弃积压的应答
+        self.port.clear_input();
+        self.port
+            .write_all(&frame)
+            .and_then(|_| self.port.flush())
+            .map_err(|e| DriverError::Io(e.to_string()))
     }
 
-    fn key_up(&mut self) {
-        if let Some(key) = self.last_key {
-            let _ = self.enigo.key(key, Direction::Release);
-            self.last_key = None;
+    fn query_info(&mut self) -> bool {
+        if self.send_cmd(CH9329_CMD_GET_INFO, &[]).is_err() {
+            return false;
         }
-        let _ = self.enigo.key(Key::Shift, Direction::Release);
+        thread::sleep(Duration::from_millis(50));
+        let mut buf = [0u8; 16];
+        matches!(self.port.read(&mut buf), Ok(n) if n >= 5 && buf[..2] == CH9329_HEAD && buf[3] == (CH9329_CMD_GET_INFO | 0x80))
+    }
+
+    fn send_keyboard(&mut self) -> Result<(), DriverError> {
+        let mut data = [0u8; 8];
+        data[0] = self.modifier;
+        data[2..].copy_from_slice(&self.keys);
+        self.send_cmd(CH9329_CMD_KB_GENERAL, &data)
+    }
+
+    fn send_rel(&mut self, dx: i8, dy: i8, wheel: i8) -> Result<(), DriverError> {
+        self.send_cmd(CH9329_CMD_MS_REL, &[0x01, self.buttons, dx as u8, dy as u8, wheel as u8])
+    }
+}
+
+impl InputDriver for Ch9329Driver {
+    fn info(&self) -> DriverInfo {
+        DriverInfo::named("CH9329", DriverFeatures::ACKED | DriverFeatures::KEYBOARD_REPORT)
+    }
+
+    fn capabilities(&self) -> DriverCaps {
+        DriverCaps { hscroll: false, identity: false, ..Default::default() }
+    }
+
+    fn heartbeat(&mut self) -> Result<(), DriverError> { Ok(()) }
+
+    fn switch_identity(&mut self, _index: u8) -> Result<(), DriverError> { Ok(()) }
+
+    fn mouse_abs(&mut self, x: u16, y: u16) -> Result<(), DriverError> {
+        // 绝对坐标范围 0~4095
+        let tx = ((x as f32 / self.screen_w as f32) * 4095.0).clamp(0.0, 4095.0) as u16;
+        let ty = ((y as f32 / self.screen_h as f32) * 4095.0).clamp(0.0, 4095.0) as u16;
+        let [xl, xh] = tx.to_le_bytes();
+        let [yl, yh] = ty.to_le_bytes();
+        self.send_cmd(CH9329_CMD_MS_ABS, &[0x02, self.buttons, xl, xh, yl, yh, 0])
+    }
+
+    fn mouse_move(&mut self, dx: i32, dy: i32, wheel: i8) -> Result<(), DriverError> {
+        if wheel != 0 {
+            self.send_rel(0, 0, wheel)?;
+        }
+        let max_step = 127;
+        let mut cur_dx = dx;
+        let mut cur_dy = dy;
+        while cur_dx != 0 || cur_dy != 0 {
+            let step_x = cur_dx.clamp(-max_step, max_step);
+            let step_y = cur_dy.clamp(-max_step, max_step);
+            self.send_rel(step_x as i8, step_y as i8, 0)?;
+            cur_dx -= step_x;
+            cur_dy -= step_y;
+        }
+        Ok(())
+    }
+
+    fn mouse_down(&mut self, buttons: MouseButtons) -> Result<(), DriverError> {
+        // 按键字节 bit0~2 为左/右/中键，bit3~4 为侧键
+        self.buttons |= buttons.bits();
+        self.send_rel(0, 0, 0)
+    }
+
+    fn mouse_up(&mut self) -> Result<(), DriverError> {
+        self.buttons = 0;
+        self.send_rel(0, 0, 0)
+    }
+
+    fn key_down(&mut self, keycode: u8, modifier: u8) -> Result<(), DriverError> {
+        self.modifier = modifier;
+        if !self.keys.contains(&keycode) {
+            if let Some(slot) = self.keys.iter_mut().find(|k| **k == 0) {
+                *slot = keycode;
+            }
+        }
+        self.send_keyboard()
+    }
+
+    fn key_up(&mut self) -> Result<(), DriverError> {
+        self.modifier = 0;
+        self.keys = [0; 6];
+        self.send_keyboard()
+    }
+
+    fn keys_down(&mut self, keycodes: &[u8], modifier: u8) -> Result<(), DriverError> {
+        self.modifier = modifier;
+        self.keys = chord_report(keycodes);
+        self.send_keyboard()
     }
 }
 
 // ==========================================
-// 4. Factory Function
+// 6. Mirror / Log / DryRun Driver (调试用驱动)
 // ==========================================
+
+/// 同时向两个后端发送指令：返回值以主驱动为准，副驱动的失败只打印警告
+pub struct MirrorDriver {
+    primary: Box<dyn InputDriver>,
+    secondary: Box<dyn InputDriver>,
+    /// 副驱动连续失败期间只提示一次
+    secondary_failing: bool,
+}
+
+impl MirrorDriver {
+    pub fn new(primary: Box<dyn InputDriver>, secondary: Box<dyn InputDriver>) -> Self {
+        Self { primary, secondary, secondary_failing: false }
+    }
+
+    fn both(&mut self, f: impl Fn(&mut dyn InputDriver) -> Result<(), DriverError>) -> Result<(), DriverError> {
+        let result = f(self.primary.as_mut());
+        match f(self.secondary.as_mut()) {
+            Ok(()) => self.secondary_failing = false,
+            Err(e) => {
+                if !self.secondary_failing {
+                    println!("⚠️ [Mirror] 副驱动失败 (不影响主驱动): {}", e);
+                }
+                self.secondary_failing = true;
+            }
+        }
+        result
+    }
+}
+
+impl InputDriver for MirrorDriver {
+    fn heartbeat(&mut self) -> Result<(), DriverError> { self.both(|d| d.heartbeat()) }
+    fn mouse_abs(&mut self, x: u16, y: u16) -> Result<(), DriverError> { self.both(|d| d.mouse_abs(x, y)) }
+    fn mouse_move(&mut self, dx: i32, dy: i32, wheel: i8) -> Result<(), DriverError> {
+        self.both(|d| d.mouse_move(dx, dy, wheel))
+    }
+    fn mouse_down(&mut self, buttons: MouseButtons) -> Result<(), DriverError> { self.both(|d| d.mouse_down(buttons)) }
+    fn mouse_up(&mut self) -> Result<(), DriverError> { self.both(|d| d.mouse_up()) }
+    fn key_down(&mut self, keycode: u8, modifier: u8) -> Result<(), DriverError> {
+        self.both(|d| d.key_down(keycode, modifier))
+    }
+    fn key_up(&mut self) -> Result<(), DriverError> { self.both(|d| d.key_up()) }
+    fn keys_down(&mut self, keycodes: &[u8], modifier: u8) -> Result<(), DriverError> {
+        self.both(|d| d.keys_down(keycodes, modifier))
+    }
+    fn switch_identity(&mut self, index: u8) -> Result<(), DriverError> { self.both(|d| d.switch_identity(index)) }
+    fn mouse_hscroll(&mut self, delta: i8) -> Result<(), DriverError> { self.both(|d| d.mouse_hscroll(delta)) }
+    fn type_text(&mut self, text: &str) -> Result<(), DriverError> { self.both(|d| d.type_text(text)) }
+    fn send_custom(&mut self, event_type: u8, payload: [u8; 6], delay: u16) -> Result<(), DriverError> {
+        self.both(|d| d.send_custom(event_type, payload, delay))
+    }
+    fn flush(&mut self) -> Result<(), DriverError> { self.both(|d| d.flush()) }
+    fn pending(&self) -> usize { self.primary.pending() }
+    fn is_online(&self) -> bool { self.primary.is_online() }
+    fn info(&self) -> DriverInfo { self.primary.info() }
+    fn latency_metrics(&self) -> Option<LatencyMetrics> { self.primary.latency_metrics() }
+    fn capabilities(&self) -> DriverCaps { self.primary.capabilities() }
+
+    /// 连接状态只跟随主驱动
+    fn set_event_callback(&mut self, callback: DriverEventCallback) {
+        self.primary.set_event_callback(callback);
+    }
+}
+
+/// 不产生任何输入，只把收到的指令连同时间戳打印到控制台
+pub struct LogDriver {
+    started: Instant,
+}
+
+impl LogDriver {
+    pub fn new() -> Self {
+        Self { started: Instant::now() }
+    }
+
+    fn log(&self, msg: std::fmt::Arguments) -> Result<(), DriverError> {
+        println!("📝 [Log +{:>9.3}s] {}", self.started.elapsed().as_secs_f64(), msg);
+        Ok(())
+    }
+}
+
+impl Default for LogDriver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InputDriver for LogDriver {
+    fn info(&self) -> DriverInfo {
+        DriverInfo::named("指令日志", DriverFeatures::all())
+    }
+
+    fn capabilities(&self) -> DriverCaps {
+        DriverCaps { text_input: true, ..Default::default() }
+    }
+
+    /// 心跳每秒一次，不记录
+    fn heartbeat(&mut self) -> Result<(), DriverError> { Ok(()) }
+    fn mouse_abs(&mut self, x: u16, y: u16) -> Result<(), DriverError> { self.log(format_args!("mouse_abs({}, {})", x, y)) }
+    fn mouse_move(&mut self, dx: i32, dy: i32, wheel: i8) -> Result<(), DriverError> {
+        self.log(format_args!("mouse_move({}, {}, wheel={})", dx, dy, wheel))
+    }
+    fn mouse_down(&mut self, buttons: MouseButtons) -> Result<(), DriverError> {
+        self.log(format_args!("mouse_down({:?})", buttons))
+    }
+    fn mouse_up(&mut self) -> Result<(), DriverError> { self.log(format_args!("mouse_up()")) }
+    fn key_down(&mut self, keycode: u8, modifier: u8) -> Result<(), DriverError> {
+        self.log(format_args!("key_down(0x{:02X}, mod=0x{:02X})", keycode, modifier))
+    }
+    fn key_up(&mut self) -> Result<(), DriverError> { self.log(format_args!("key_up()")) }
+    fn keys_down(&mut self, keycodes: &[u8], modifier: u8) -> Result<(), DriverError> {
+        self.log(format_args!("keys_down({:02X?}, mod=0x{:02X})", keycodes, modifier))
+    }
+    fn switch_identity(&mut self, index: u8) -> Result<(), DriverError> {
+        self.log(format_args!("switch_identity({})", index))
+    }
+    fn mouse_hscroll(&mut self, delta: i8) -> Result<(), DriverError> { self.log(format_args!("mouse_hscroll({})", delta)) }
+    fn type_text(&mut self, text: &str) -> Result<(), DriverError> { self.log(format_args!("type_text({:?})", text)) }
+    fn send_custom(&mut self, event_type: u8, payload: [u8; 6], delay: u16) -> Result<(), DriverError> {
+        self.log(format_args!("send_custom(0x{:02X}, {:02X?}, delay={})", event_type, payload, delay))
+    }
+}
+
+/// 不产生任何真实输入，只在屏幕覆盖层上画出光标十字线与点击标记，
+/// 用于对照实际游戏画面校验新的 ui_map.toml / 策略坐标
+pub struct DryRunDriver {
+    x: i32,
+    y: i32,
+    screen_w: u16,
+    screen_h: u16,
+}
+
+impl DryRunDriver {
+    pub fn new(screen_w: u16, screen_h: u16) -> Self {
+        let (x, y) = (screen_w as i32 / 2, screen_h as i32 / 2);
+        overlay::show(screen_w, screen_h);
+        overlay::set_cursor(x, y);
+        println!("🧪 [DryRun] 试运行模式：不会发送任何键鼠输入");
+        Self { x, y, screen_w, screen_h }
+    }
+
+    fn move_cursor(&mut self, x: i32, y: i32) {
+        self.x = x.clamp(0, self.screen_w as i32 - 1);
+        self.y = y.clamp(0, self.screen_h as i32 - 1);
+        overlay::set_cursor(self.x, self.y);
+    }
+}
+
+impl InputDriver for DryRunDriver {
+    fn info(&self) -> DriverInfo {
+        DriverInfo::named("试运行 (DryRun)", DriverFeatures::all())
+    }
+
+    fn capabilities(&self) -> DriverCaps {
+        DriverCaps { text_input: true, ..Default::default() }
+    }
+
+    fn heartbeat(&mut self) -> Result<(), DriverError> { Ok(()) }
+
+    fn mouse_abs(&mut self, x: u16, y: u16) -> Result<(), DriverError> {
+        self.move_cursor(x as i32, y as i32);
+        Ok(())
+    }
+
+    fn mouse_move(&mut self, dx: i32, dy: i32, wheel: i8) -> Result<(), DriverError> {
+        self.move_cursor(self.x + dx, self.y + dy);
+        if wheel != 0 {
+            overlay::set_label(format!("滚轮 {:+}", wheel));
+        }
+        Ok(())
+    }
+
+    fn mouse_down(&mut self, buttons: MouseButtons) -> Result<(), DriverError> {
+        println!("🧪 [DryRun] 点击 {:?} @ ({}, {})", buttons, self.x, self.y);
+        overlay::add_click(self.x, self.y, buttons);
+        Ok(())
+    }
+
+    fn mouse_up(&mut self) -> Result<(), DriverError> { Ok(()) }
+
+    fn key_down(&mut self, keycode: u8, modifier: u8) -> Result<(), DriverError> {
+        self.keys_down(&[keycode], modifier)
+    }
+
+    fn key_up(&mut self) -> Result<(), DriverError> { Ok(()) }
+
+    fn keys_down(&mut self, keycodes: &[u8], modifier: u8) -> Result<(), DriverError> {
+        let text = format!("按键 {:02X?} mod=0x{:02X}", keycodes, modifier);
+        println!("🧪 [DryRun] {}", text);
+        overlay::set_label(text);
+        Ok(())
+    }
+
+    fn switch_identity(&mut self, index: u8) -> Result<(), DriverError> {
+        println!("🧪 [DryRun] 切换身份 -> {}", index);
+        Ok(())
+    }
+
+    fn mouse_hscroll(&mut self, delta: i8) -> Result<(), DriverError> {
+        overlay::set_label(format!("水平滚动 {:+}", delta));
+        Ok(())
+    }
+
+    fn type_text(&mut self, text: &str) -> Result<(), DriverError> {
+        println!("🧪 [DryRun] 输入文本 {:?}", text);
+        overlay::set_label(format!("输入 \"{}\"", text));
+        Ok(())
+    }
+}
+
+// ==========================================
+// 7. Port Discovery
+// ==========================================
+
+/// 枚举串口，USB 串口排在前面
+fn available_ports() -> Result<Vec<serialport::SerialPortInfo>, String> {
+    let mut ports = serialport::available_ports().map_err(|e| format!("枚举串口失败: {}", e))?;
+    ports.sort_by_key(|p| !matches!(p.port_type, serialport::SerialPortType::UsbPort(_)));
+    Ok(ports)
+}
+
+/// 依次尝试自定义固件与 CH9329 的握手，返回应答的协议
+fn probe_port(port_name: &str, baud_rate: u32) -> Option<DriverType> {
+    if HardwareDriver::probe(port_name, baud_rate) {
+        Some(DriverType::Hardware)
+    } else if Ch9329Driver::probe(port_name, CH9329_DEFAULT_BAUD) {
+        Some(DriverType::Ch9329)
+    } else {
+        None
+    }
+}
+
+/// 枚举所有串口并逐个握手，返回第一个应答预期协议的设备 (USB 串口优先)
+pub fn detect_port(baud_rate: u32) -> Result<(DriverType, String), String> {
+    let ports = available_ports()?;
+    if ports.is_empty() {
+        return Err("未发现任何串口设备".to_string());
+    }
+
+    for p in &ports {
+        println!("🔌 [探测] {} ...", p.port_name);
+        match probe_port(&p.port_name, baud_rate) {
+            Some(DriverType::Ch9329) => {
+                println!("✅ [探测] {} 是 CH9329 模块", p.port_name);
+                return Ok((DriverType::Ch9329, p.port_name.clone()));
+            }
+            Some(t) => {
+                println!("✅ [探测] {} 应答了自定义固件协议", p.port_name);
+                return Ok((t, p.port_name.clone()));
+            }
+            None => {}
+        }
+    }
+    let names: Vec<&str> = ports.iter().map(|p| p.port_name.as_str()).collect();
+    Err(format!("已探测 {:?}，均未应答", names))
+}
+
+/// 一个串口的枚举结果 (list_ports)
+#[derive(Debug, Clone)]
+pub struct PortEntry {
+    pub name: String,
+    /// USB 串口的 (VID, PID)，蓝牙 / PCI / 虚拟串口为 None
+    pub usb_id: Option<(u16, u16)>,
+    pub manufacturer: Option<String>,
+    pub product: Option<String>,
+    pub serial_number: Option<String>,
+    /// 应答握手的协议；未探测、被占用或无应答时为 None
+    pub responds: Option<DriverType>,
+}
+
+impl std::fmt::Display for PortEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:<8}", self.name)?;
+        match self.usb_id {
+            Some((vid, pid)) => write!(f, " {:04X}:{:04X}", vid, pid)?,
+            None => write!(f, " {:<9}", "-")?,
+        }
+        let desc: Vec<&str> = [&self.manufacturer, &self.product].iter().filter_map(|s| s.as_deref()).collect();
+        write!(f, "  {}", if desc.is_empty() { "(无描述)".to_string() } else { desc.join(" / ") })?;
+        if let Some(sn) = &self.serial_number {
+            write!(f, "  SN={}", sn)?;
+        }
+        match self.responds {
+            Some(DriverType::Ch9329) => write!(f, "  ✅ CH9329"),
+            Some(_) => write!(f, "  ✅ NZM 固件"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// 列出所有串口及其 USB 描述信息；probe 为 true 时逐个握手，标出应答 NZM 固件或 CH9329 协议的端口
+/// 已被其他程序 (包括本程序的驱动) 打开的串口无法握手
+pub fn list_ports(baud_rate: u32, probe: bool) -> Result<Vec<PortEntry>, String> {
+    Ok(available_ports()?
+        .into_iter()
+        .map(|p| {
+            let (usb_id, manufacturer, product, serial_number) = match p.port_type {
+                serialport::SerialPortType::UsbPort(usb) => {
+                    (Some((usb.vid, usb.pid)), usb.manufacturer, usb.product, usb.serial_number)
+                }
+                serialport::SerialPortType::BluetoothPort => (None, None, Some("蓝牙串口".to_string()), None),
+                _ => (None, None, None, None),
+            };
+            let responds = if probe { probe_port(&p.port_name, baud_rate) } else { None };
+            PortEntry { name: p.port_name, usb_id, manufacturer, product, serial_number, responds }
+        })
+        .collect())
+}
+
+// ==========================================
+// 8. Factory Function
+// ==========================================
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum DriverType {
     Hardware,
     Software,
+    /// 网络盒子，port 参数为 "ip:port" 或 "ip:port@UUID"
+    KmboxNet,
+    /// 标准 CH9329 串口转 HID 模块
+    Ch9329,
+    /// 试运行：不发送输入，仅在覆盖层上显示动作落点
+    DryRun,
+    /// 经 TCP 转发到另一台机器上的 nzm_agent，port 参数为 "host:port" 或 "host:port@TOKEN"
+    Remote,
+    /// 经 BLE UART 连接的自制固件盒子，port 参数为设备名或蓝牙地址
+    Ble,
+    /// 向指定窗口投递输入 (游戏可在后台)，port 参数为 "标题[#序号][@focus]"
+    Window,
+    /// Interception 内核过滤驱动 (需以 --features interception 编译)
+    Interception,
+    /// ViGEm 虚拟手柄 (需以 --features vigem 编译)，port 参数为点击映射方式: "" / "WxH" / "direct"
+    Gamepad,
 }
 
 pub fn create_driver(
     t: DriverType, 
     port: &str, 
+    hw_config: &HardwareConfig,
     screen_w: u16, 
     screen_h: u16
 ) -> Result<Box<dyn InputDriver>, String> {
     match t {
         DriverType::Hardware => {
-            let drv = HardwareDriver::new(port, 115200, screen_w, screen_h)?;
+            let drv = HardwareDriver::new(port, hw_config, screen_w, screen_h)?;
+            Ok(Box::new(drv))
+        }
+        DriverType::Ble => {
+            let drv = HardwareDriver::new_ble(port, hw_config, screen_w, screen_h)?;
             Ok(Box::new(drv))
         }
         DriverType::Software => {
             let drv = SoftwareDriver::new(screen_w, screen_h);
             Ok(Box::new(drv))
         }
+        DriverType::KmboxNet => {
+            let drv = KmboxNetDriver::new(port, screen_w, screen_h)?;
+            Ok(Box::new(drv))
+        }
+        DriverType::Ch9329 => {
+            let drv = Ch9329Driver::new(port, CH9329_DEFAULT_BAUD, screen_w, screen_h)?;
+            Ok(Box::new(drv))
+        }
+        DriverType::DryRun => Ok(Box::new(DryRunDriver::new(screen_w, screen_h))),
+        DriverType::Window => Ok(Box::new(crate::sendinput::SendInputDriver::new(port, screen_w, screen_h)?)),
+        DriverType::Remote => Ok(Box::new(RemoteDriver::new(port)?)),
+        #[cfg(feature = "interception")]
+        DriverType::Interception => Ok(Box::new(crate::interception::InterceptionDriver::new(screen_w, screen_h)?)),
+        #[cfg(not(feature = "interception"))]
+        DriverType::Interception => Err("当前构建未包含 Interception 后端，请使用 --features interception 重新编译".to_string()),
+        #[cfg(feature = "vigem")]
+        DriverType::Gamepad => Ok(Box::new(crate::gamepad::GamepadDriver::new(port, screen_w, screen_h)?)),
+        #[cfg(not(feature = "vigem"))]
+        DriverType::Gamepad => Err("当前构建未包含虚拟手柄后端，请使用 --features vigem 重新编译".to_string()),
     }
-}
\ No newline at end of file
+}
+
+// ==========================================
+// 9. Heartbeat Monitor
+// ==========================================
+
+/// 后台心跳参数 (config.toml 的 [heartbeat])
+#[derive(Deserialize, Debug, Clone)]
+pub struct HeartbeatConfig {
+    /// 心跳间隔 (ms)，0 表示不发送心跳
+    #[serde(default = "default_heartbeat_interval_ms")]
+    pub interval_ms: u64,
+    /// 连续失败多少次视为设备失联
+    #[serde(default = "default_heartbeat_max_failures")]
+    pub max_failures: u32,
+}
+
+fn default_heartbeat_interval_ms() -> u64 { 1000 }
+fn default_heartbeat_max_failures() -> u32 { 3 }
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        Self {
+            interval_ms: default_heartbeat_interval_ms(),
+            max_failures: default_heartbeat_max_failures(),
+        }
+    }
+}
+
+/// 离线期间每失败多少次心跳打印一次提示
+const HEARTBEAT_LOG_EVERY: u32 = 60;
+
+/// 在后台线程中定时发送心跳：连续 max_failures 次失败时回调 Disconnected，之后首次成功时回调 Reconnected
+/// 驱动自己报告的 Disconnected 错误不计数 (驱动已通过自身的事件回调通知过)
+pub fn spawn_heartbeat(
+    mut driver: Box<dyn InputDriver>,
+    config: &HeartbeatConfig,
+    on_event: DriverEventCallback,
+) -> Result<(), String> {
+    if config.interval_ms == 0 {
+        return Ok(());
+    }
+    let interval = Duration::from_millis(config.interval_ms);
+    let max_failures = config.max_failures.max(1);
+    thread::Builder::new()
+        .name("heartbeat".to_string())
+        .spawn(move || {
+            let mut failures = 0u32;
+            loop {
+                match driver.heartbeat() {
+                    Ok(()) => {
+                        if failures >= max_failures {
+                            println!("✅ [心跳] 设备恢复在线 (此前连续失败 {} 次)", failures);
+                            on_event(&DriverEvent::Reconnected);
+                        } else if failures > 0 {
+                            println!("✅ [心跳] 恢复正常 (此前失败 {} 次)", failures);
+                        }
+                        failures = 0;
+                    }
+                    Err(DriverError::Disconnected(_)) => {}
+                    Err(e) => {
+                        failures = failures.saturating_add(1);
+                        // 只打印首次失败与离线转换，离线期间每 HEARTBEAT_LOG_EVERY 次提示一次，避免每拍刷屏
+                        if failures == 1 {
+                            println!("⚠️ [心跳] 失败: {}", e);
+                        }
+                        if failures == max_failures {
+                            println!("🔌 [心跳] 连续 {} 次失败，设备离线: {}", failures, e);
+                            on_event(&DriverEvent::Disconnected(format!("连续 {} 次心跳失败: {}", failures, e)));
+                        } else if failures > max_failures && (failures - max_failures).is_multiple_of(HEARTBEAT_LOG_EVERY) {
+                            println!("🔌 [心跳] 设备仍离线 (已连续失败 {} 次): {}", failures, e);
+                        }
+                    }
+                }
+                thread::sleep(interval);
+            }
+        })
+        .map(|_| ())
+        .map_err(|e| format!("无法启动心跳线程: {}", e))
+}
+
+// ==========================================
+// 10. Throttle Driver (限速 + 指令间随机间隔)
+// ==========================================
+
+/// 驱动层限速参数 (config.toml 的 [throttle])
+#[derive(Deserialize, Debug, Clone)]
+pub struct ThrottleConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// 每秒最多转发的输入指令数，0 表示不限速
+    #[serde(default = "default_throttle_max_per_sec")]
+    pub max_per_sec: u32,
+    /// 允许的突发指令数 (令牌桶容量)
+    #[serde(default = "default_throttle_burst")]
+    pub burst: u32,
+    /// 每条指令前随机等待的时间范围 (微秒)，两者都为 0 表示不加抖动
+    #[serde(default = "default_throttle_jitter_min_us")]
+    pub jitter_min_us: u64,
+    #[serde(default = "default_throttle_jitter_max_us")]
+    pub jitter_max_us: u64,
+}
+
+fn default_throttle_max_per_sec() -> u32 { 250 }
+fn default_throttle_burst() -> u32 { 20 }
+fn default_throttle_jitter_min_us() -> u64 { 200 }
+fn default_throttle_jitter_max_us() -> u64 { 1500 }
+
+impl Default for ThrottleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_per_sec: default_throttle_max_per_sec(),
+            burst: default_throttle_burst(),
+            jitter_min_us: default_throttle_jitter_min_us(),
+            jitter_max_us: default_throttle_jitter_max_us(),
+        }
+    }
+}
+
+/// 超出速率时的汇总提示间隔
+const THROTTLE_REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 包在真实驱动外层：超出 max_per_sec 的输入指令阻塞等待 (不丢弃，避免光标位置错乱)，
+/// 每条指令前再随机等待一小段时间；心跳、flush 与状态查询不受影响
+pub struct ThrottleDriver {
+    inner: Box<dyn InputDriver>,
+    /// 令牌间隔，None 表示不限速
+    interval: Option<Duration>,
+    burst: f64,
+    tokens: f64,
+    last_refill: Instant,
+    jitter_us: (u64, u64),
+    /// 上次汇总以来被延后的指令数与累计等待时间
+    delayed: u32,
+    waited: Duration,
+    last_report: Instant,
+}
+
+impl ThrottleDriver {
+    pub fn new(inner: Box<dyn InputDriver>, config: &ThrottleConfig) -> Self {
+        let burst = config.burst.max(1) as f64;
+        let lo = config.jitter_min_us.min(config.jitter_max_us);
+        let hi = config.jitter_min_us.max(config.jitter_max_us);
+        Self {
+            inner,
+            interval: (config.max_per_sec > 0).then(|| Duration::from_secs_f64(1.0 / config.max_per_sec as f64)),
+            burst,
+            tokens: burst,
+            last_refill: Instant::now(),
+            jitter_us: (lo, hi),
+            delayed: 0,
+            waited: Duration::ZERO,
+            last_report: Instant::now(),
+        }
+    }
+
+    /// 取一个令牌 (不足时等待) 并加上随机间隔，然后转发
+    fn paced(
+        &mut self,
+        f: impl FnOnce(&mut dyn InputDriver) -> Result<(), DriverError>,
+    ) -> Result<(), DriverError> {
+        if let Some(interval) = self.interval {
+            let now = Instant::now();
+            let refill = now.duration_since(self.last_refill).as_secs_f64() / interval.as_secs_f64();
+            self.tokens = (self.tokens + refill).min(self.burst);
+            self.last_refill = now;
+            if self.tokens < 1.0 {
+                let wait = interval.mul_f64(1.0 - self.tokens);
+                thread::sleep(wait);
+                self.tokens = 1.0;
+                self.last_refill = Instant::now();
+                self.delayed += 1;
+                self.waited += wait;
+            }
+            self.tokens -= 1.0;
+            if self.delayed > 0 && self.last_report.elapsed() >= THROTTLE_REPORT_INTERVAL {
+                println!(
+                    "⏱️ [限速] 最近 {:.0}s 内 {} 条指令超出速率被延后 (共等待 {}ms)，上层可能在刷屏发送",
+                    self.last_report.elapsed().as_secs_f64(),
+                    self.delayed,
+                    self.waited.as_millis()
+                );
+                self.delayed = 0;
+                self.waited = Duration::ZERO;
+                self.last_report = Instant::now();
+            }
+        }
+        let (lo, hi) = self.jitter_us;
+        if hi > 0 {
+            let us = if lo == hi { lo } else { rand::thread_rng().gen_range(lo..=hi) };
+            thread::sleep(Duration::from_micros(us));
+        }
+        f(self.inner.as_mut())
+    }
+}
+
+impl InputDriver for ThrottleDriver {
+    fn heartbeat(&mut self) -> Result<(), DriverError> { self.inner.heartbeat() }
+    fn mouse_abs(&mut self, x: u16, y: u16) -> Result<(), DriverError> { self.paced(|d| d.mouse_abs(x, y)) }
+    fn mouse_move(&mut self, dx: i32, dy: i32, wheel: i8) -> Result<(), DriverError> {
+        self.paced(|d| d.mouse_move(dx, dy, wheel))
+    }
+    fn mouse_down(&mut self, buttons: MouseButtons) -> Result<(), DriverError> { self.paced(|d| d.mouse_down(buttons)) }
+    fn mouse_up(&mut self) -> Result<(), DriverError> { self.paced(|d| d.mouse_up()) }
+    fn key_down(&mut self, keycode: u8, modifier: u8) -> Result<(), DriverError> {
+        self.paced(|d| d.key_down(keycode, modifier))
+    }
+    fn key_up(&mut self) -> Result<(), DriverError> { self.paced(|d| d.key_up()) }
+    fn keys_down(&mut self, keycodes: &[u8], modifier: u8) -> Result<(), DriverError> {
+        self.paced(|d| d.keys_down(keycodes, modifier))
+    }
+    fn switch_identity(&mut self, index: u8) -> Result<(), DriverError> { self.inner.switch_identity(index) }
+    fn mouse_hscroll(&mut self, delta: i8) -> Result<(), DriverError> { self.paced(|d| d.mouse_hscroll(delta)) }
+    fn type_text(&mut self, text: &str) -> Result<(), DriverError> { self.paced(|d| d.type_text(text)) }
+    fn send_custom(&mut self, event_type: u8, payload: [u8; 6], delay: u16) -> Result<(), DriverError> {
+        self.paced(|d| d.send_custom(event_type, payload, delay))
+    }
+    fn flush(&mut self) -> Result<(), DriverError> { self.inner.flush() }
+    fn pending(&self) -> usize { self.inner.pending() }
+    fn is_online(&self) -> bool { self.inner.is_online() }
+    fn set_event_callback(&mut self, callback: DriverEventCallback) { self.inner.set_event_callback(callback) }
+    fn info(&self) -> DriverInfo { self.inner.info() }
+    fn latency_metrics(&self) -> Option<LatencyMetrics> { self.inner.latency_metrics() }
+    fn capabilities(&self) -> DriverCaps { self.inner.capabilities() }
+}
+
+// ==========================================
+// 11. Input State Tracker (异常退出时释放按键)
+// ==========================================
+
+/// 当前在硬件侧处于按下状态的键盘键与鼠标键
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HeldInput {
+    pub keys: Vec<u8>,
+    pub modifier: u8,
+    pub buttons: MouseButtons,
+}
+
+impl HeldInput {
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty() && self.modifier == 0 && self.buttons.is_empty()
+    }
+}
+
+/// 进程内唯一的按键状态，由 TrackedDriver 维护；panic 或 Ctrl+C 时据此松开按键
+static HELD: Mutex<HeldInput> = Mutex::new(HeldInput { keys: Vec::new(), modifier: 0, buttons: MouseButtons::empty() });
+
+fn held() -> std::sync::MutexGuard<'static, HeldInput> {
+    // 持锁线程 panic 不影响状态本身，继续使用
+    HELD.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// 当前按住的键与鼠标键
+pub fn held_input() -> HeldInput {
+    held().clone()
+}
+
+/// 对当前按住的键与鼠标键发送 key_up / mouse_up；没有按住任何键时不发送
+pub fn release_held(driver: &mut dyn InputDriver) -> Result<(), DriverError> {
+    let state = held_input();
+    if state.is_empty() {
+        return Ok(());
+    }
+    println!("🧯 释放仍按住的输入: 键 {:02X?} 修饰 0x{:02X} 鼠标 {:?}", state.keys, state.modifier, state.buttons);
+    if !state.keys.is_empty() || state.modifier != 0 {
+        driver.key_up()?;
+    }
+    if !state.buttons.is_empty() {
+        driver.mouse_up()?;
+    }
+    driver.flush()?;
+    *held() = HeldInput::default();
+    Ok(())
+}
+
+/// 记录经过它的按下 / 松开指令 (由驱动线程自动包在最外层，见 actor.rs)
+pub struct TrackedDriver {
+    inner: Box<dyn InputDriver>,
+}
+
+impl TrackedDriver {
+    pub fn new(inner: Box<dyn InputDriver>) -> Self {
+        Self { inner }
+    }
+
+    /// 按下在发送前就记入状态：异步驱动可能已把帧送出却返回之前积压的错误，
+    /// 宁可多松开一次，也不能漏掉真正按下的键
+    fn press(update: impl FnOnce(&mut HeldInput), send: impl FnOnce() -> Result<(), DriverError>) -> Result<(), DriverError> {
+        update(&mut held());
+        send()
+    }
+
+    /// 松开成功后才清除状态；失败时保留，退出时会再松开一次
+    fn release(result: Result<(), DriverError>, update: impl FnOnce(&mut HeldInput)) -> Result<(), DriverError> {
+        if result.is_ok() {
+            update(&mut held());
+        }
+        result
+    }
+}
+
+impl InputDriver for TrackedDriver {
+    fn heartbeat(&mut self) -> Result<(), DriverError> { self.inner.heartbeat() }
+    fn mouse_abs(&mut self, x: u16, y: u16) -> Result<(), DriverError> { self.inner.mouse_abs(x, y) }
+    fn mouse_move(&mut self, dx: i32, dy: i32, wheel: i8) -> Result<(), DriverError> {
+        self.inner.mouse_move(dx, dy, wheel)
+    }
+    fn mouse_down(&mut self, buttons: MouseButtons) -> Result<(), DriverError> {
+        Self::press(|h| h.buttons |= buttons, || self.inner.mouse_down(buttons))
+    }
+    /// mouse_up / key_up 松开全部按键，因此清空对应的状态
+    fn mouse_up(&mut self) -> Result<(), DriverError> {
+        Self::release(self.inner.mouse_up(), |h| h.buttons = MouseButtons::empty())
+    }
+    fn key_down(&mut self, keycode: u8, modifier: u8) -> Result<(), DriverError> {
+        Self::press(
+            |h| {
+                if keycode != 0 && !h.keys.contains(&keycode) {
+                    h.keys.push(keycode);
+                }
+                h.modifier |= modifier;
+            },
+            || self.inner.key_down(keycode, modifier),
+        )
+    }
+    fn key_up(&mut self) -> Result<(), DriverError> {
+        Self::release(self.inner.key_up(), |h| {
+            h.keys.clear();
+            h.modifier = 0;
+        })
+    }
+    /// 完整键盘报告：先把新按下的键并入状态，发送成功后再去掉报告中已松开的键
+    fn keys_down(&mut self, keycodes: &[u8], modifier: u8) -> Result<(), DriverError> {
+        let report: Vec<u8> = keycodes.iter().copied().filter(|k| *k != 0).collect();
+        let result = Self::press(
+            |h| {
+                for k in &report {
+                    if !h.keys.contains(k) {
+                        h.keys.push(*k);
+                    }
+                }
+                h.modifier |= modifier;
+            },
+            || self.inner.keys_down(keycodes, modifier),
+        );
+        Self::release(result, |h| {
+            h.keys.retain(|k| report.contains(k));
+            h.modifier = modifier;
+        })
+    }
+    fn switch_identity(&mut self, index: u8) -> Result<(), DriverError> { self.inner.switch_identity(index) }
+    fn mouse_hscroll(&mut self, delta: i8) -> Result<(), DriverError> { self.inner.mouse_hscroll(delta) }
+    fn type_text(&mut self, text: &str) -> Result<(), DriverError> { self.inner.type_text(text) }
+    fn send_custom(&mut self, event_type: u8, payload: [u8; 6], delay: u16) -> Result<(), DriverError> {
+        self.inner.send_custom(event_type, payload, delay)
+    }
+    fn flush(&mut self) -> Result<(), DriverError> { self.inner.flush() }
+    fn pending(&self) -> usize { self.inner.pending() }
+    fn is_online(&self) -> bool { self.inner.is_online() }
+    fn set_event_callback(&mut self, callback: DriverEventCallback) { self.inner.set_event_callback(callback) }
+    fn info(&self) -> DriverInfo { self.inner.info() }
+    fn latency_metrics(&self) -> Option<LatencyMetrics> { self.inner.latency_metrics() }
+    fn capabilities(&self) -> DriverCaps { self.inner.capabilities() }
+}
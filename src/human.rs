@@ -1,189 +1,1185 @@
 // src/human.rs
-use crate::hardware::InputDriver;
+use crate::actor::DriverHandle;
+use crate::calibration;
+use crate::cancel::{self, CancellationToken};
+use crate::fatigue::{BreakListener, Session};
+use crate::hardware::{self, DriverCaps, DriverError, InputDriver, MouseButtons};
+use crate::keymap::{self, Modifier};
+use crate::profile::{self, HumanProfile, MsRange};
+use crate::trajectory::{self, Stroke};
+use crate::window::{self, Origin, WindowHandle};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
-use rand::Rng;
+use std::time::{Duration, Instant};
+use windows::Win32::UI::WindowsAndMessaging::{GetSystemMetrics, SM_CXSCREEN, SM_CYSCREEN};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use rand_distr::{Normal, Distribution};
+use serde::Deserialize;
+
+/// HID 退格键
+const HID_BACKSPACE: u8 = 0x2A;
+/// 拖拽前移动到起点的时长 (秒)
+const DRAG_APPROACH_SEC: f32 = 0.4;
+
+/// 光标外部移动 (用户碰了鼠标) 超过该距离时打印提示
+const DRIFT_NOTICE_PX: f32 = 50.0;
+
+/// 每段轨迹开始前如何让内部记录的光标位置与真实光标一致
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CursorSync {
+    /// 驱动直接操作本机光标时用 os，能发绝对坐标时用 anchor，否则用 os
+    Auto,
+    /// 用 GetCursorPos 读回系统光标的真实位置，作为轨迹起点
+    Os,
+    /// 先用 mouse_abs 把真实光标拉回记录的位置，再开始轨迹 (硬件盒子读不回光标时)
+    Anchor,
+    /// 不校正 (旧行为)
+    Off,
+}
+
+impl CursorSync {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s.trim().to_lowercase().as_str() {
+            "auto" | "" => Ok(Self::Auto),
+            "os" => Ok(Self::Os),
+            "anchor" => Ok(Self::Anchor),
+            "off" => Ok(Self::Off),
+            other => Err(format!("未知的光标同步方式: {} (可用 auto / os / anchor / off)", other)),
+        }
+    }
+}
 
 pub struct HumanDriver {
-    // ✨ 核心修改：使用 Box<dyn InputDriver> 来存储多态驱动
-    pub device: Arc<Mutex<Box<dyn InputDriver>>>,
+    /// 驱动线程句柄 (多个 HumanDriver 可共享同一个驱动)
+    pub device: DriverHandle,
     pub cur_x: f32,
     pub cur_y: f32,
+    /// 全部随机参数 (速度、抖动、按住时长、打字节奏、反应延迟)
+    profile: HumanProfile,
+    /// 所有随机决策 (轨迹、抖动、延迟) 共用的随机数发生器；固定种子后可逐次复现
+    rng: StdRng,
+    /// 疲劳倍率与休息计时
+    session: Session,
+    /// 暂停 / 中止开关 (默认为进程内共享的 cancel::global())
+    cancel: CancellationToken,
+    /// with_deadline 设置的截止时间，超过后进行中的动作以 Cancelled 结束
+    deadline: Option<Instant>,
+    cursor_sync: CursorSync,
+    /// 多开模式下绑定的实例 (坐标换算 + 输入闸门)
+    binding: Option<InstanceBinding>,
+    in_gate: bool,
+    /// 正在 exclusive 内执行动作 (可能按住键鼠、占用闸门)，此时检查点不因暂停等待
+    in_action: bool,
+    /// 未绑定实例时目标坐标的原点；None 表示目标坐标即屏幕坐标
+    origin: Option<Origin>,
+}
+
+/// HumanDriver 的构造器：在默认档案 (或 profile 指定的档案) 上覆盖个别参数
+/// 覆盖项在 build 时最后生效，与调用顺序无关
+pub struct HumanDriverBuilder {
+    device: DriverHandle,
+    start: (u16, u16),
+    profile: HumanProfile,
+    seed: Option<u64>,
+    cursor_sync: CursorSync,
+    cancel: Option<CancellationToken>,
+    break_listener: Option<BreakListener>,
+    speed: Option<f32>,
+    jitter_px: Option<f32>,
+    click_hold_ms: Option<MsRange>,
+    typing_wpm_scale: Option<f32>,
+    origin: Option<Origin>,
+}
+
+impl HumanDriverBuilder {
+    /// 初始光标位置 (屏幕坐标)，缺省为 (0, 0)
+    pub fn start(mut self, x: u16, y: u16) -> Self {
+        self.start = (x, y);
+        self
+    }
+
+    pub fn profile(mut self, profile: HumanProfile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn cursor_sync(mut self, mode: CursorSync) -> Self {
+        self.cursor_sync = mode;
+        self
+    }
+
+    pub fn cancel_token(mut self, token: CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    pub fn break_listener(mut self, listener: BreakListener) -> Self {
+        self.break_listener = Some(listener);
+        self
+    }
+
+    /// 移动时长倍率 (>1 更慢)
+    pub fn speed(mut self, speed: f32) -> Self {
+        self.speed = Some(speed);
+        self
+    }
+
+    /// 移动终点的随机偏移 (px)
+    pub fn jitter(mut self, px: f32) -> Self {
+        self.jitter_px = Some(px);
+        self
+    }
+
+    pub fn click_hold_ms(mut self, range: MsRange) -> Self {
+        self.click_hold_ms = Some(range);
+        self
+    }
+
+    /// 打字速度倍率
+    pub fn typing_speed(mut self, scale: f32) -> Self {
+        self.typing_wpm_scale = Some(scale);
+        self
+    }
+
+    /// 目标坐标的原点 (副显示器左上角或窗口客户区)，与截图后端的坐标系保持一致
+    pub fn origin(mut self, origin: Origin) -> Self {
+        self.origin = Some(origin);
+        self
+    }
+
+    pub fn build(self) -> HumanDriver {
+        let mut profile = self.profile;
+        if let Some(v) = self.speed {
+            profile.speed = v;
+        }
+        if let Some(v) = self.jitter_px {
+            profile.target_jitter_px = v;
+        }
+        if let Some(v) = self.click_hold_ms {
+            profile.click_hold_ms = v;
+        }
+        if let Some(v) = self.typing_wpm_scale {
+            profile.typing_wpm_scale = v;
+        }
+
+        let mut human = HumanDriver::new(self.device, self.start.0, self.start.1);
+        human.set_profile(profile);
+        if let Some(seed) = self.seed {
+            human.set_seed(seed);
+        }
+        human.set_cursor_sync(self.cursor_sync);
+        if let Some(token) = self.cancel {
+            human.set_cancel_token(token);
+        }
+        if let Some(listener) = self.break_listener {
+            human.set_break_listener(listener);
+        }
+        human.origin = self.origin;
+        human
+    }
+}
+
+// ==========================================
+// 0. 多开实例绑定
+// ==========================================
+
+/// 多个实例共享的输入闸门：同一时刻只允许一个实例操作键鼠，
+/// 并记录物理光标的真实位置，供切换实例时同步
+pub struct InputGate {
+    state: Mutex<GateState>,
+}
+
+struct GateState {
+    active: Option<usize>,
+    cursor: (f32, f32),
+}
+
+impl InputGate {
+    pub fn new(start_x: f32, start_y: f32) -> Self {
+        Self { state: Mutex::new(GateState { active: None, cursor: (start_x, start_y) }) }
+    }
+}
+
+/// 单个实例的绑定信息
+pub struct InstanceBinding {
+    pub index: usize,
+    pub hwnd: WindowHandle,
+    /// 硬件支持时切换到的设备身份
+    pub identity: Option<u8>,
+    pub gate: Arc<InputGate>,
 }
 
 impl HumanDriver {
     /// 初始化拟人化驱动器
-    // ✨ 核心修改：参数类型同步更新
-    pub fn new(device: Arc<Mutex<Box<dyn InputDriver>>>, start_x: u16, start_y: u16) -> Self {
+    pub fn new(device: DriverHandle, start_x: u16, start_y: u16) -> Self {
+        let profile = HumanProfile::default();
+        let mut rng = StdRng::from_entropy();
+        let session = Session::new(&profile.fatigue, &mut rng);
         Self {
             device,
             cur_x: start_x as f32,
             cur_y: start_y as f32,
+            profile,
+            rng,
+            session,
+            cancel: cancel::global(),
+            deadline: None,
+            cursor_sync: CursorSync::Auto,
+            binding: None,
+            in_gate: false,
+            in_action: false,
+            origin: None,
+        }
+    }
+
+    pub fn builder(device: DriverHandle) -> HumanDriverBuilder {
+        HumanDriverBuilder {
+            device,
+            start: (0, 0),
+            profile: HumanProfile::default(),
+            seed: None,
+            cursor_sync: CursorSync::Auto,
+            cancel: None,
+            break_listener: None,
+            speed: None,
+            jitter_px: None,
+            click_hold_ms: None,
+            typing_wpm_scale: None,
+            origin: None,
+        }
+    }
+
+    /// 底层驱动的能力；每次向驱动线程查询，运行中切换后端 (DriverHandle::swap) 后立即生效
+    pub fn caps(&self) -> DriverCaps {
+        self.device.capabilities()
+    }
+
+    /// 切换拟人档案 (默认 normal)
+    pub fn set_profile(&mut self, profile: HumanProfile) {
+        self.profile = profile;
+        self.session.restart(&self.profile.fatigue, &mut self.rng);
+    }
+
+    pub fn profile(&self) -> &HumanProfile {
+        &self.profile
+    }
+
+    // 运行中调整个别参数 (例如战斗中加快、菜单里放慢)；之后 set_profile 会整体覆盖这些调整
+
+    pub fn set_speed(&mut self, speed: f32) {
+        self.profile.speed = speed;
+    }
+
+    pub fn set_jitter(&mut self, px: f32) {
+        self.profile.target_jitter_px = px;
+    }
+
+    pub fn set_click_hold_ms(&mut self, range: MsRange) {
+        self.profile.click_hold_ms = range;
+    }
+
+    pub fn set_typing_speed(&mut self, scale: f32) {
+        self.profile.typing_wpm_scale = scale;
+    }
+
+    /// 固定随机种子：相同种子、相同调用序列下轨迹与延迟完全一致
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+        self.session.restart(&self.profile.fatigue, &mut self.rng);
+    }
+
+    /// 休息开始 / 结束时的回调 (用于打印日志)
+    pub fn set_break_listener(&mut self, listener: BreakListener) {
+        self.session.set_listener(listener);
+    }
+
+    /// 当前疲劳倍率：延迟、落点抖动与打错字的概率均按此放大 (未启用疲劳时为 1)
+    pub fn fatigue(&self) -> f32 {
+        self.session.factor(&self.profile.fatigue)
+    }
+
+    /// 到了休息时间则离开一段时间 (期间不发送任何输入)；应在两个任务之间调用
+    /// 返回是否休息过
+    pub fn take_break_if_due(&mut self) -> bool {
+        let Some(duration) = self.session.begin_break(&self.profile.fatigue, &mut self.rng) else { return false };
+        thread::sleep(duration);
+        self.session.end_break(&self.profile.fatigue, &mut self.rng);
+        true
+    }
+
+    pub fn set_cursor_sync(&mut self, mode: CursorSync) {
+        self.cursor_sync = mode;
+    }
+
+    /// 替换暂停 / 中止开关
+    pub fn set_cancel_token(&mut self, token: CancellationToken) {
+        self.cancel = token;
+    }
+
+    pub fn cancel_token(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+
+    /// 限时执行一组动作：超过 limit 时进行中的移动 / 打字在下一个检查点停下并松开所有按键，返回 Cancelled
+    pub fn with_deadline<R>(&mut self, limit: Duration, f: impl FnOnce(&mut Self) -> Result<R, DriverError>) -> Result<R, DriverError> {
+        let outer = self.deadline;
+        let mine = Instant::now() + limit;
+        self.deadline = Some(outer.map_or(mine, |o| o.min(mine)));
+        let result = f(self);
+        self.deadline = outer;
+        result
+    }
+
+    /// 检查点：已中止或超时时返回 Cancelled；暂停时在此等待，
+    /// 但动作进行中 (exclusive 内) 不等待，暂停在当前动作结束、按键松开并交出闸门后生效
+    fn checkpoint(&self) -> Result<(), DriverError> {
+        if self.in_action {
+            self.cancel.check_cancelled(self.deadline)
+        } else {
+            self.cancel.check(self.deadline)
+        }
+    }
+
+    /// 可被暂停 / 中止打断的等待
+    fn wait(&self, duration: Duration) -> Result<(), DriverError> {
+        const SLICE: Duration = Duration::from_millis(50);
+        let end = Instant::now() + duration;
+        loop {
+            self.checkpoint()?;
+            let left = end.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Ok(());
+            }
+            thread::sleep(left.min(SLICE));
+        }
+    }
+
+    /// 在区间内取一个时长并等待 (可被打断)
+    fn wait_range(&mut self, range: MsRange) -> Result<(), DriverError> {
+        let ms = self.draw(range);
+        self.wait(Duration::from_millis(ms))
+    }
+
+    /// 在区间内取一个毫秒数 (使用本驱动的随机数发生器)，并按疲劳程度放大
+    fn draw(&mut self, range: MsRange) -> u64 {
+        let ms = profile::sample(&mut self.rng, range);
+        (ms as f32 * self.fatigue()) as u64
+    }
+
+    /// 动作前的反应延迟 (不占用输入闸门，可被暂停 / 中止打断)
+    fn react(&mut self) -> Result<(), DriverError> {
+        self.wait_range(self.profile.reaction_ms)
+    }
+
+    /// 绑定到某个游戏窗口：之后的移动坐标均为该窗口的客户区坐标
+    pub fn bind_instance(&mut self, binding: InstanceBinding) {
+        self.binding = Some(binding);
+    }
+
+    /// 在输入闸门内执行一组连续动作 (期间其他实例不会插入输入)；未绑定实例时直接执行
+    /// 动作被暂停热键 / 超时中止时，先松开所有仍按住的键与鼠标键再返回
+    pub fn exclusive<R>(&mut self, f: impl FnOnce(&mut Self) -> Result<R, DriverError>) -> Result<R, DriverError> {
+        // 暂停时不占用闸门
+        self.checkpoint()?;
+        let gate = match &self.binding {
+            Some(b) if !self.in_gate => Arc::clone(&b.gate),
+            _ => {
+                let result = self.run_action(f);
+                return self.release_if_cancelled(result);
+            }
+        };
+        let mut state = gate.state.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = self.activate(&mut state) {
+            // 切换失败时不占用闸门，下次操作重新切换
+            state.active = None;
+            return Err(e);
+        }
+
+        self.in_gate = true;
+        // 交出闸门前等待本实例的指令全部写出，避免与下一个实例的输入交错
+        let result = self.run_action(f);
+        let result = self.release_if_cancelled(result).and_then(|r| self.raw(|dev| dev.flush()).map(|_| r));
+        self.in_gate = false;
+
+        state.cursor = (self.cur_x, self.cur_y);
+        result
+    }
+
+    fn run_action<R>(&mut self, f: impl FnOnce(&mut Self) -> Result<R, DriverError>) -> Result<R, DriverError> {
+        let outer = std::mem::replace(&mut self.in_action, true);
+        let result = f(self);
+        self.in_action = outer;
+        result
+    }
+
+    fn release_if_cancelled<R>(&self, result: Result<R, DriverError>) -> Result<R, DriverError> {
+        if let Err(DriverError::Cancelled(_)) = &result {
+            // 嵌套调用时内外层都会走到这里，release_held 在没有按住任何输入时什么也不做
+            if let Err(e) = self.raw(|dev| hardware::release_held(dev)) {
+                println!("⚠️ 释放按键失败: {}", e);
+            }
+        }
+        result
+    }
+
+    /// 直接操作底层设备 (不经过拟人化处理)；每条调用单独投递给驱动线程
+    pub fn raw<R>(&self, f: impl FnOnce(&mut dyn InputDriver) -> Result<R, DriverError>) -> Result<R, DriverError> {
+        let mut dev = self.device.clone();
+        f(&mut dev)
+    }
+
+    /// 切换到本实例：前置窗口、切换设备身份，并把光标移回本实例上次的位置
+    /// (保证「移动 -> 点击」之间被其他实例插队时仍点在原处)
+    fn activate(&mut self, state: &mut GateState) -> Result<(), DriverError> {
+        let Some(b) = &self.binding else { return Ok(()) };
+        let resume = (self.cur_x, self.cur_y);
+        self.cur_x = state.cursor.0;
+        self.cur_y = state.cursor.1;
+        if state.active != Some(b.index) {
+            let had_other = state.active.is_some();
+            if !window::focus(b.hwnd) {
+                println!("⚠️ [实例 {}] 无法将窗口切到前台", b.index);
+            }
+            // 不支持身份切换的驱动只切窗口
+            if let Some(id) = b.identity.filter(|_| self.caps().identity) {
+                self.raw(|dev| dev.switch_identity(id))?;
+            }
+            state.active = Some(b.index);
+            thread::sleep(Duration::from_millis(self.rng.gen_range(80..150)));
+            if had_other && resume != state.cursor {
+                self.glide(resume, 0.3, 0.0)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 窗口客户区 / 显示器坐标 -> 屏幕坐标
+    fn to_screen(&self, x: u16, y: u16) -> (f32, f32) {
+        let origin = match &self.binding {
+            Some(b) => window::client_origin(b.hwnd),
+            None => self.origin.and_then(|o| o.resolve()),
         }
+        .unwrap_or((0, 0));
+        ((x as i32 + origin.0) as f32, (y as i32 + origin.1) as f32)
     }
 
     // ==========================================
     // 1. 基础输入原子操作 (原子层)
     // ==========================================
 
-    /// 内部辅助：字符转 HID 键码
+    /// 该字符能否通过 key_click / type_humanly 输入
+    pub fn can_type(&self, ch: char) -> bool {
+        keymap::current().key_for(ch).is_some()
+    }
+
+    /// 内部辅助：字符转 HID 键码 (按目标机器的键盘布局)
     fn char_to_keycode(&self, ch: char) -> u8 {
-        match ch.to_ascii_lowercase() {
-            'a'..='z' => ch.to_ascii_lowercase() as u8 - b'a' + 0x04,
-            '1'..='9' => ch as u8 - b'1' + 0x1E,
-            '0' => 0x27,
-            ' ' => 0x2C,
-            _ => 0,
-        }
+        keymap::current().key_for(ch).map_or(0, |(code, _)| code)
     }
 
     /// 🔥 【键盘长按】
     /// 允许指定按下的毫秒数。如果是 0，则执行一次极短的点击。
-    pub fn key_hold(&mut self, ch: char, ms: u64) {
-        let keycode = self.char_to_keycode(ch);
-        if keycode != 0 {
-            if let Ok(mut dev) = self.device.lock() {
-                dev.key_down(keycode, 0);
-            }
-            
+    pub fn key_hold(&mut self, ch: char, ms: u64) -> Result<(), DriverError> {
+        self.exclusive(|d| d.key_hold_inner(ch, ms))
+    }
+
+    fn key_hold_inner(&mut self, ch: char, ms: u64) -> Result<(), DriverError> {
+        // 大写字母与符号按布局附带 Shift / AltGr
+        if let Some((keycode, modifier)) = keymap::current().key_for(ch) {
             // 如果 ms 为 0，模拟一个非常短的物理接触
-            let hold_time = if ms > 0 { ms } else { rand::thread_rng().gen_range(20..45) };
-            thread::sleep(Duration::from_millis(hold_time));
+            let hold_time = if ms > 0 { ms } else { self.draw(self.profile.key_min_hold_ms) };
+            let held = self
+                .raw(|dev| dev.key_down(keycode, modifier))
+                .and_then(|_| self.wait(Duration::from_millis(hold_time)));
 
-            if let Ok(mut dev) = self.device.lock() {
-                dev.key_up();
-            }
+            // 按下或等待失败也要尝试松开，避免按键卡住
+            let released = self.raw(|dev| dev.key_up());
+            return held.and(released);
         }
+        Ok(())
+    }
+
+    /// 🔥 【组合键长按】
+    /// 同时按住多个字符键 (最多 6 个，受驱动能力限制) 与修饰键，例如 WASD 移动中释放技能、Ctrl+Shift+X
+    pub fn chord_hold(&mut self, keys: &[char], modifier: u8, ms: u64) -> Result<(), DriverError> {
+        let mut codes: Vec<u8> = keys.iter().map(|c| self.char_to_keycode(*c)).filter(|k| *k != 0).collect();
+        let max_keys = self.caps().max_keys_down;
+        if codes.len() > max_keys {
+            println!("⚠️ 当前驱动最多同时按住 {} 个键，组合键已截断", max_keys);
+            codes.truncate(max_keys);
+        }
+        self.react()?;
+        self.exclusive(|d| {
+            let hold_time = if ms > 0 { ms } else { d.draw(d.profile.chord_hold_ms) };
+            let held = d.raw(|dev| dev.keys_down(&codes, modifier)).and_then(|_| d.wait(Duration::from_millis(hold_time)));
+            let released = d.raw(|dev| dev.key_up());
+            held.and(released)
+        })
+    }
+
+    /// 【拟人化组合键】
+    /// 依次按下修饰键 (先于主键 combo_lead_ms)，点按 ch，松开 ch 后再按相反顺序逐个松开修饰键，
+    /// 例如 key_combo_humanly(&[Modifier::Shift], '1') 触发绑定在 Shift+1 上的技能
+    /// 驱动不支持完整键盘报告时退化为修饰键与主键同时按下
+    pub fn key_combo_humanly(&mut self, modifiers: &[Modifier], ch: char) -> Result<(), DriverError> {
+        let Some((keycode, implied)) = keymap::current().key_for(ch) else {
+            return Err(DriverError::Unsupported(format!("当前键盘布局无法输入字符 {:?}", ch)));
+        };
+        let all = modifiers.iter().fold(implied, |m, k| m | k.bit());
+        self.react()?;
+        if self.caps().max_keys_down <= 1 {
+            let hold = self.draw(self.profile.key_tap_ms);
+            return self.exclusive(|d| {
+                let held = d.raw(|dev| dev.key_down(keycode, all)).and_then(|_| d.wait(Duration::from_millis(hold)));
+                let released = d.raw(|dev| dev.key_up());
+                held.and(released)
+            });
+        }
+        self.exclusive(|d| {
+            let mut held = 0u8;
+            let pressed = modifiers.iter().enumerate().try_for_each(|(i, m)| {
+                if i > 0 {
+                    d.wait_range(d.profile.combo_stagger_ms)?;
+                }
+                held |= m.bit();
+                d.raw(|dev| dev.keys_down(&[], held))
+            });
+
+            let hold = d.draw(d.profile.key_tap_ms);
+            let result = pressed.and_then(|_| {
+                d.wait_range(d.profile.combo_lead_ms)?;
+                d.raw(|dev| dev.keys_down(&[keycode], all))?;
+                d.wait(Duration::from_millis(hold))?;
+                d.raw(|dev| dev.keys_down(&[], held))
+            });
+            // 修饰键或主键失败也要逐个松开修饰键，最后的 key_up 兜底松开全部按键
+            let released = modifiers.iter().rev().try_for_each(|m| {
+                d.wait_range(d.profile.combo_stagger_ms)?;
+                held &= !m.bit();
+                d.raw(|dev| dev.keys_down(&[], held))
+            });
+            let up = d.raw(|dev| dev.key_up());
+            result.and(released).and(up)
+        })
     }
 
     /// 【拟人化按键点击】 (短按)
-    pub fn key_click(&mut self, ch: char) {
-        // 模拟真实按键点击通常在 30-70ms 之间
-        let jitter = rand::thread_rng().gen_range(35..70);
-        self.key_hold(ch, jitter);
+    pub fn key_click(&mut self, ch: char) -> Result<(), DriverError> {
+        // 模拟真实按键点击通常在 30-70ms 之间 (key_tap_ms)
+        self.react()?;
+        let hold = self.draw(self.profile.key_tap_ms);
+        self.key_hold(ch, hold)
     }
 
     /// 🔥 【模拟鼠标滚轮】
     /// delta: 120 的倍数，正数为向上滚，负数为向下滚
-    pub fn mouse_scroll(&mut self, delta: i32) {
-        if let Ok(mut dev) = self.device.lock() {
-            // 在 lib.rs 中 mouse_move 的第三个参数通常对应滚轮字节
-            dev.mouse_move(0, 0, delta as i8);
-        }
+    pub fn mouse_scroll(&mut self, delta: i32) -> Result<(), DriverError> {
+        // mouse_move 的第三个参数对应滚轮字节；超出驱动单次上限时拆成多条
+        let step = self.caps().wheel_step.max(1) as i32;
+        self.exclusive(|d| {
+            let mut rest = delta;
+            while rest != 0 {
+                let chunk = rest.clamp(-step, step);
+                d.raw(|dev| dev.mouse_move(0, 0, chunk as i8))?;
+                rest -= chunk;
+                if rest != 0 {
+                    d.wait_range(d.profile.scroll_gap_ms)?;
+                }
+            }
+            Ok(())
+        })?;
         // 滚轮后稍微停顿符合人体工程学
-        thread::sleep(Duration::from_millis(100));
+        self.wait_range(self.profile.scroll_settle_ms)?;
+        Ok(())
+    }
+
+    /// 【拟人化滚动】
+    /// 与 mouse_scroll 等间隔地发完所有格不同，按连拨分组：每组内先加速后减速，组间停顿 (手指回位)；
+    /// 滚到头时偶尔多拨一格再拨回来。amount 为格数，负数向下
+    pub fn scroll_humanly(&mut self, amount: i32) -> Result<(), DriverError> {
+        if amount == 0 {
+            return Ok(());
+        }
+        self.react()?;
+        self.exclusive(|d| {
+            let dir: i8 = if amount > 0 { 1 } else { -1 };
+            let mut rest = amount.unsigned_abs();
+            while rest > 0 {
+                let [lo, hi] = d.profile.scroll_burst_ticks;
+                let burst = d.rng.gen_range(lo.max(1)..=hi.max(lo).max(1)).min(rest);
+                d.scroll_burst(dir, burst)?;
+                rest -= burst;
+                if rest > 0 {
+                    d.wait_range(d.profile.scroll_regrip_ms)?;
+                }
+            }
+            if d.rng.gen::<f32>() < d.profile.scroll_overshoot_chance {
+                d.raw(|dev| dev.mouse_move(0, 0, dir))?;
+                // 看到滚过了再拨回来
+                d.wait_range(d.profile.scroll_regrip_ms)?;
+                d.raw(|dev| dev.mouse_move(0, 0, -dir))?;
+            }
+            Ok(())
+        })?;
+        self.wait_range(self.profile.scroll_settle_ms)?;
+        Ok(())
+    }
+
+    /// 一次连拨 ticks 格：间隔在两端取 scroll_tick_ms 上限、中段取下限，按正弦过渡并带 ±15% 抖动
+    fn scroll_burst(&mut self, dir: i8, ticks: u32) -> Result<(), DriverError> {
+        let [fast, slow] = self.profile.scroll_tick_ms;
+        for i in 0..ticks {
+            self.raw(|dev| dev.mouse_move(0, 0, dir))?;
+            if i + 1 < ticks {
+                let u = (i as f32 + 0.5) / (ticks - 1).max(1) as f32;
+                let base = slow as f32 - (slow as f32 - fast as f32) * (u.min(1.0) * std::f32::consts::PI).sin();
+                let ms = base * self.rng.gen_range(0.85..1.15) * self.fatigue();
+                self.wait(Duration::from_millis(ms.max(1.0) as u64))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 【模拟水平滚动】
+    /// delta: 正数向右，负数向左 (用于横向滚动的商店/任务列表)
+    /// 驱动不支持水平滚轮时改用 Shift + 垂直滚轮 (Windows 下多数界面的等效操作)
+    pub fn mouse_hscroll(&mut self, delta: i32) -> Result<(), DriverError> {
+        let delta = delta.clamp(-(i8::MAX as i32), i8::MAX as i32) as i8;
+        if self.caps().hscroll {
+            self.exclusive(|d| d.raw(|dev| dev.mouse_hscroll(delta)))?;
+        } else {
+            self.exclusive(|d| {
+                let scrolled = d.raw(|dev| dev.keys_down(&[], 0x02)).and_then(|_| {
+                    d.wait_range(d.profile.scroll_gap_ms)?;
+                    // 向下滚等效于向右
+                    d.raw(|dev| dev.mouse_move(0, 0, -delta))?;
+                    d.wait_range(d.profile.scroll_gap_ms)
+                });
+                let released = d.raw(|dev| dev.key_up());
+                scrolled.and(released)
+            })?;
+        }
+        self.wait_range(self.profile.scroll_settle_ms)?;
+        Ok(())
     }
 
     /// 🔥 【相对移动】
     /// 用于在当前位置基础上进行微调或防掉线微动
-    pub fn move_relative(&mut self, dx: i32, dy: i32) {
-        if let Ok(mut dev) = self.device.lock() {
-            dev.mouse_move(dx, dy, 0);
-        }
-        self.cur_x += dx as f32;
-        self.cur_y += dy as f32;
+    pub fn move_relative(&mut self, dx: i32, dy: i32) -> Result<(), DriverError> {
+        self.exclusive(|d| {
+            d.raw(|dev| dev.mouse_move(dx, dy, 0))?;
+            d.cur_x += dx as f32;
+            d.cur_y += dy as f32;
+            Ok(())
+        })
     }
 
     // ==========================================
     // 2. 高级拟人化行为 (行为层)
     // ==========================================
 
+    /// 【拟人化空闲】
+    /// 代替等待时的 thread::sleep：光标不再纹丝不动，而是偶尔小幅晃动、滚一格滚轮或挪到屏幕边缘歇着
+    /// 绑定实例 (多开) 时只等待，避免为了晃动抢占输入闸门、把窗口切到前台
+    pub fn idle_humanly(&mut self, duration: Duration) -> Result<(), DriverError> {
+        let deadline = Instant::now() + duration;
+        if self.binding.is_some() {
+            self.wait(duration)?;
+            return Ok(());
+        }
+        loop {
+            let pause = Duration::from_millis(self.draw(self.profile.idle_pause_ms));
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left <= pause {
+                self.wait(left)?;
+                return Ok(());
+            }
+            self.wait(pause)?;
+
+            let roll = self.rng.gen::<f32>();
+            if roll < self.profile.idle_scroll_chance {
+                let delta = if self.rng.gen_bool(0.5) { 1 } else { -1 };
+                self.mouse_scroll(delta)?;
+            } else if roll < self.profile.idle_scroll_chance + self.profile.idle_edge_chance {
+                let end = self.edge_point();
+                let dur = self.rng.gen_range(0.4..0.8) * self.profile.speed;
+                self.exclusive(|d| d.glide(end, dur, 0.0))?;
+            } else {
+                let r = self.profile.idle_drift_px.max(0.0) * self.rng.gen_range(0.3..1.0);
+                let angle = self.rng.gen_range(0.0..std::f32::consts::TAU);
+                let end = (self.cur_x + r * angle.cos(), self.cur_y + r * angle.sin());
+                let dur = self.rng.gen_range(0.15..0.4);
+                self.exclusive(|d| d.glide(end, dur, 0.0))?;
+            }
+        }
+    }
+
+    /// 屏幕右侧或下方边缘附近的一点 (人在等待时常把鼠标推到画面外侧)
+    fn edge_point(&mut self) -> (f32, f32) {
+        let (w, h) = unsafe { (GetSystemMetrics(SM_CXSCREEN) as f32, GetSystemMetrics(SM_CYSCREEN) as f32) };
+        let margin = self.rng.gen_range(20.0..80.0);
+        if self.rng.gen_bool(0.5) {
+            (w - margin, self.rng.gen_range(h * 0.3..h * 0.8))
+        } else {
+            (self.rng.gen_range(w * 0.3..w * 0.8), h - margin)
+        }
+    }
+
     /// 【高级拟人移动】
-    pub fn move_to_humanly(&mut self, target_x: u16, target_y: u16, duration_sec: f32) {
-        let mut rng = rand::thread_rng();
+    /// 绑定实例时 target 为窗口客户区坐标
+    pub fn move_to_humanly(&mut self, target_x: u16, target_y: u16, duration_sec: f32) -> Result<(), DriverError> {
+        self.react()?;
+        self.exclusive(|d| d.move_to_humanly_inner(target_x, target_y, duration_sec))
+    }
+
+    fn move_to_humanly_inner(&mut self, target_x: u16, target_y: u16, duration_sec: f32) -> Result<(), DriverError> {
+        let end = self.aim(target_x, target_y);
+        self.glide(end, duration_sec * self.profile.speed, 0.0)
+    }
+
+    /// 目标点 (客户区坐标) -> 叠加落点抖动后的屏幕坐标
+    fn aim(&mut self, target_x: u16, target_y: u16) -> (f32, f32) {
+        let (tx, ty) = self.to_screen(target_x, target_y);
+        let j = self.profile.target_jitter_px * self.fatigue();
+        if j > 0.0 { (tx + self.rng.gen_range(-j..j), ty + self.rng.gen_range(-j..j)) } else { (tx, ty) }
+    }
+
+    /// 按档案中的轨迹模型从当前位置滑动到屏幕坐标 end；中途失败时光标停在最后一次成功的位置
+    /// sag 为拖拽时轨迹中段的下垂量 (px)，普通移动为 0
+    /// 驱动不支持绝对坐标时逐步发送取整后的相对位移
+    fn glide(&mut self, end: (f32, f32), duration_sec: f32, sag: f32) -> Result<(), DriverError> {
+        self.resync()?;
         let start = (self.cur_x, self.cur_y);
-        
-        let end = (
-            target_x as f32 + rng.gen_range(-2.0..2.0),
-            target_y as f32 + rng.gen_range(-2.0..2.0)
-        );
-
-        let ctrl1 = (
-            start.0 + (end.0 - start.0) * 0.2 + rng.gen_range(-40.0..40.0),
-            start.1 + (end.1 - start.1) * 0.2 + rng.gen_range(-40.0..40.0)
-        );
-        let ctrl2 = (
-            start.0 + (end.0 - start.0) * 0.8 + rng.gen_range(-20.0..60.0),
-            start.1 + (end.1 - start.1) * 0.8 + rng.gen_range(-20.0..60.0)
-        );
-
-        let steps = (duration_sec * 80.0) as u32; 
-        let interval = Duration::from_secs_f32(duration_sec / steps as f32);
-
-        for i in 0..=steps {
-            let t_linear = i as f32 / steps as f32;
-            let t_eased = Self::ease_in_out_cubic(t_linear);
-            let (px, py) = Self::bezier_cubic(t_eased, start, ctrl1, ctrl2, end);
-            
-            if let Ok(mut dev) = self.device.lock() {
-                dev.mouse_abs(px as u16, py as u16);
-            }
-            thread::sleep(interval);
+        let mut strokes = trajectory::plan(&self.profile.motion, start, end, duration_sec, &mut self.rng);
+        trajectory::sag(&mut strokes, start, end, sag);
+        self.play(strokes, end)
+    }
+
+    /// 轨迹开始前校正 cur_x / cur_y (见 CursorSync)，避免用户碰过鼠标后相对位移的误差越积越大
+    fn resync(&mut self) -> Result<(), DriverError> {
+        let caps = self.caps();
+        let mode = match self.cursor_sync {
+            CursorSync::Auto if caps.os_cursor || !caps.absolute_mouse => CursorSync::Os,
+            CursorSync::Auto => CursorSync::Anchor,
+            m => m,
+        };
+        match mode {
+            CursorSync::Os => {
+                // 读不到时 (无桌面会话等) 沿用记录的位置
+                if let Ok((x, y)) = calibration::cursor_pos() {
+                    let drift = (x - self.cur_x).hypot(y - self.cur_y);
+                    if drift > DRIFT_NOTICE_PX {
+                        println!("🖱️ 光标被外部移动了 {:.0}px，已按系统光标重新同步", drift);
+                    }
+                    self.cur_x = x;
+                    self.cur_y = y;
+                }
+            }
+            CursorSync::Anchor if caps.absolute_mouse => {
+                let (x, y) = (self.cur_x.max(0.0) as u16, self.cur_y.max(0.0) as u16);
+                self.raw(|dev| dev.mouse_abs(x, y))?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// 逐段发送规划好的轨迹，最后把光标记在 end
+    fn play(&mut self, strokes: Vec<Stroke>, end: (f32, f32)) -> Result<(), DriverError> {
+        let absolute = self.caps().absolute_mouse;
+        for stroke in strokes {
+            let interval = Duration::from_secs_f32(stroke.duration_sec / stroke.points.len() as f32);
+            for (px, py) in stroke.points {
+                if absolute {
+                    self.raw(|dev| dev.mouse_abs(px.max(0.0) as u16, py.max(0.0) as u16))?;
+                } else {
+                    let dx = px.round() as i32 - self.cur_x.round() as i32;
+                    let dy = py.round() as i32 - self.cur_y.round() as i32;
+                    if dx != 0 || dy != 0 {
+                        self.raw(|dev| dev.mouse_move(dx, dy, 0))?;
+                    }
+                }
+                self.cur_x = px;
+                self.cur_y = py;
+                self.wait(interval)?;
+            }
+            self.wait(Duration::from_millis(stroke.pause_ms))?;
         }
 
         self.cur_x = end.0;
         self.cur_y = end.1;
+        Ok(())
     }
 
     /// 【拟人化鼠标点击】
     /// 增加 hold_ms 参数以支持长按点击（如蓄力）
-    pub fn click_humanly(&mut self, left: bool, right: bool, hold_ms: u64) {
-        let mut rng = rand::thread_rng();
-        if let Ok(mut dev) = self.device.lock() {
-            dev.mouse_down(left, right);
-            
-            let sleep_time = if hold_ms > 0 { hold_ms } else { rng.gen_range(30..75) };
-            thread::sleep(Duration::from_millis(sleep_time));
-            
-            dev.mouse_up();
-        }
+    pub fn click_humanly(&mut self, buttons: MouseButtons, hold_ms: u64) -> Result<(), DriverError> {
+        self.react()?;
+        self.exclusive(|d| d.click_humanly_inner(buttons, hold_ms))
     }
 
-    pub fn double_click_humanly(&mut self, left: bool, right: bool, interval_ms: u64) {
-         self.click_humanly(left, right, 0);
-         
-         // 为了保持拟人化，我们在传入的基准时间上增加 0~20ms 的随机波动
-         // 如果你想要绝对精确，去掉 jitter 即可
-         let jitter = rand::thread_rng().gen_range(0..20);
-         let final_delay = interval_ms + jitter;
+    fn click_humanly_inner(&mut self, buttons: MouseButtons, hold_ms: u64) -> Result<(), DriverError> {
+        let sleep_time = if hold_ms > 0 { hold_ms } else { self.draw(self.profile.click_hold_ms) };
+        let held = self.raw(|dev| dev.mouse_down(buttons)).and_then(|_| self.wait(Duration::from_millis(sleep_time)));
+        // 按下或等待失败也要尝试松开，避免按键卡住
+        let released = self.raw(|dev| dev.mouse_up());
+        held.and(released)
+    }
 
-         std::thread::sleep(Duration::from_millis(final_delay));
-         
-         self.click_humanly(left, right, 0);
+    /// 【识别后反应点击】
+    /// 用于「识别到界面元素后立即点击」的场景：先按档案的 recognition 分布停顿一次人类反应时间
+    /// (取代 move_to_humanly 的 reaction_ms)，再移动并点击
+    pub fn react_then_click(&mut self, x: u16, y: u16, buttons: MouseButtons, duration_sec: f32) -> Result<(), DriverError> {
+        let ms = (self.profile.recognition.sample(&mut self.rng) as f32 * self.fatigue()) as u64;
+        self.wait(Duration::from_millis(ms))?;
+        self.exclusive(|d| d.move_to_humanly_inner(x, y, duration_sec))?;
+        // 已经停顿过识别反应时间，点击前不再叠加 reaction_ms
+        self.exclusive(|d| d.click_humanly_inner(buttons, 0))
+    }
+
+    /// 【矩形内拟人点击】
+    /// rect 为 [x1, y1, x2, y2] (与 OCR 区域同一坐标系)；落点取自以偏心瞄点为中心的二维高斯分布并夹紧在矩形内，
+    /// 避免每次都点在同一个像素上
+    pub fn click_in_rect_humanly(&mut self, rect: [i32; 4], buttons: MouseButtons) -> Result<(), DriverError> {
+        let (x, y) = self.point_in_rect(rect);
+        self.move_to_humanly(x, y, 0.5)?;
+        self.click_humanly(buttons, 0)
+    }
+
+    /// 在矩形内按档案的 click_bias / click_spread 采样一个点
+    pub fn point_in_rect(&mut self, rect: [i32; 4]) -> (u16, u16) {
+        let (x1, y1) = (rect[0].min(rect[2]) as f32, rect[1].min(rect[3]) as f32);
+        let (x2, y2) = (rect[0].max(rect[2]) as f32, rect[1].max(rect[3]) as f32);
+        let (w, h) = (x2 - x1, y2 - y1);
+        let [bx, by] = self.profile.click_bias;
+        let spread = self.profile.click_spread.max(0.0);
+        let mut axis = |lo: f32, len: f32, bias: f32| {
+            let mean = lo + len * (0.5 + bias);
+            let v = Normal::new(mean, len * spread).map_or(mean, |n| n.sample(&mut self.rng));
+            v.clamp(lo, lo + (len - 1.0).max(0.0)).max(0.0) as u16
+        };
+        (axis(x1, w, bx), axis(y1, h, by))
+    }
+
+    /// 【拟人化双击】
+    /// 两次点击的间隔取自档案的 double_click_gap_ms (需小于系统双击时间，默认 500ms)
+    pub fn double_click_humanly(&mut self, buttons: MouseButtons) -> Result<(), DriverError> {
+        self.react()?;
+        self.exclusive(|d| {
+            d.click_humanly_inner(buttons, 0)?;
+            let gap = d.draw(d.profile.double_click_gap_ms);
+            d.wait(Duration::from_millis(gap))?;
+            d.click_humanly_inner(buttons, 0)
+        })
+    }
+
+    /// 【拟人化长按】
+    /// 按住约 ms 毫秒 (按档案的 long_press_jitter 上下浮动)，用于开宝箱、技能蓄力等需要长按的控件
+    pub fn long_press_humanly(&mut self, buttons: MouseButtons, ms: u64) -> Result<(), DriverError> {
+        let j = self.profile.long_press_jitter.clamp(0.0, 0.9);
+        let hold = if j > 0.0 { (ms as f32 * self.rng.gen_range(1.0 - j..=1.0 + j)) as u64 } else { ms };
+        self.react()?;
+        self.exclusive(|d| d.click_humanly_inner(buttons, hold.max(1)))
+    }
+
+    /// 【拟人化拖拽】
+    /// 移动到 from 按下 buttons，按住沿带下垂的拟人轨迹拖到 to，停稳后松开 (如拖动卡牌放置陷阱、中键拖动镜头)
+    /// 坐标含义同 move_to_humanly
+    pub fn drag_humanly(
+        &mut self,
+        buttons: MouseButtons,
+        from: (u16, u16),
+        to: (u16, u16),
+        duration_sec: f32,
+    ) -> Result<(), DriverError> {
+        self.react()?;
+        self.exclusive(|d| {
+            d.move_to_humanly_inner(from.0, from.1, DRAG_APPROACH_SEC)?;
+            let press = d.draw(d.profile.drag_press_ms);
+            let moved = d.raw(|dev| dev.mouse_down(buttons)).and_then(|_| {
+                d.wait(Duration::from_millis(press))?;
+                let end = d.aim(to.0, to.1);
+                let sag = d.profile.drag_sag_px * d.rng.gen_range(0.5..=1.0);
+                d.glide(end, duration_sec * d.profile.speed, sag)?;
+
+                // 停稳后再松开，避免目标把「移动中松开」当作取消
+                let settle = d.draw(d.profile.drag_release_ms);
+                d.wait(Duration::from_millis(settle))
+            });
+            // 按下、移动失败或等待被取消也要尝试松开按键，避免按键卡住
+            let released = d.raw(|dev| dev.mouse_up());
+            moved.and(released)
+        })
+    }
+
+    /// 【沿路径拟人移动】
+    /// 从当前位置出发依次经过 points (客户区坐标)，拐角自然倒圆并减速，速度有快有慢；
+    /// 用于手势类界面操作，total_time 为整条路径的时长 (秒)
+    pub fn move_along_humanly(&mut self, points: &[(u16, u16)], total_time: f32) -> Result<(), DriverError> {
+        self.react()?;
+        self.exclusive(|d| d.move_along_inner(points, total_time))
+    }
+
+    fn move_along_inner(&mut self, points: &[(u16, u16)], total_time: f32) -> Result<(), DriverError> {
+        let Some((&last, via)) = points.split_last() else { return Ok(()) };
+        self.resync()?;
+        let mut waypoints = vec![(self.cur_x, self.cur_y)];
+        waypoints.extend(via.iter().map(|&(x, y)| self.to_screen(x, y)));
+        // 只有终点带落点抖动，途经点按原样经过
+        let end = self.aim(last.0, last.1);
+        waypoints.push(end);
+
+        let duration_sec = total_time * self.profile.speed;
+        let points = trajectory::polyline(&waypoints, self.profile.path_corner_px, duration_sec, &mut self.rng);
+        self.play(vec![Stroke { points, duration_sec, pause_ms: 0 }], end)
+    }
+
+    /// 【沿路径拖拽】
+    /// 移动到 points 的首点按下，沿其余各点移动后停稳松开；用于框选一组单位或绘制手势
+    pub fn drag_along_humanly(&mut self, buttons: MouseButtons, points: &[(u16, u16)], total_time: f32) -> Result<(), DriverError> {
+        let Some((&first, rest)) = points.split_first() else { return Ok(()) };
+        self.react()?;
+        self.exclusive(|d| {
+            d.move_to_humanly_inner(first.0, first.1, DRAG_APPROACH_SEC)?;
+            let press = d.draw(d.profile.drag_press_ms);
+            let moved = d.raw(|dev| dev.mouse_down(buttons)).and_then(|_| {
+                d.wait(Duration::from_millis(press))?;
+                d.move_along_inner(rest, total_time)?;
+                let settle = d.draw(d.profile.drag_release_ms);
+                d.wait(Duration::from_millis(settle))
+            });
+            let released = d.raw(|dev| dev.mouse_up());
+            moved.and(released)
+        })
+    }
+
+    /// 【拟人化镜头平移】
+    /// 按住 buttons (中键 / 右键，视游戏设置) 分段发送总量为 (dx, dy) 的相对位移后松开，
+    /// 用于超出一屏的地图在放置陷阱前拖动战场
+    /// 平移期间游戏通常锁定光标并在松开后复位，因此不更新 cur_x / cur_y
+    pub fn pan_humanly(&mut self, buttons: MouseButtons, dx: i32, dy: i32, duration_sec: f32) -> Result<(), DriverError> {
+        self.react()?;
+        self.exclusive(|d| {
+            let press = d.draw(d.profile.drag_press_ms);
+            let moved = d.raw(|dev| dev.mouse_down(buttons)).and_then(|_| {
+                d.wait(Duration::from_millis(press))?;
+                d.pan_inner(dx, dy, duration_sec * d.profile.speed)?;
+                let settle = d.draw(d.profile.drag_release_ms);
+                d.wait(Duration::from_millis(settle))
+            });
+            let released = d.raw(|dev| dev.mouse_up());
+            moved.and(released)
+        })
+    }
+
+    /// 沿拟人轨迹 (以原点为起点) 逐段发送相对位移，保证各段之和恰为 (dx, dy)
+    fn pan_inner(&mut self, dx: i32, dy: i32, duration_sec: f32) -> Result<(), DriverError> {
+        let strokes = trajectory::plan(&self.profile.motion, (0.0, 0.0), (dx as f32, dy as f32), duration_sec, &mut self.rng);
+        let mut sent = (0, 0);
+        for stroke in strokes {
+            let interval = Duration::from_secs_f32(stroke.duration_sec / stroke.points.len() as f32);
+            for (px, py) in stroke.points {
+                let step = (px.round() as i32 - sent.0, py.round() as i32 - sent.1);
+                if step != (0, 0) {
+                    self.raw(|dev| dev.mouse_move(step.0, step.1, 0))?;
+                    sent = (sent.0 + step.0, sent.1 + step.1);
+                }
+                self.wait(interval)?;
+            }
+            self.wait(Duration::from_millis(stroke.pause_ms))?;
+        }
+        Ok(())
+    }
+
+    /// 误按 ch 旁边的一个键 (保持 ch 的修饰键)，停顿片刻后退格删掉
+    fn typo(&mut self, ch: char) -> Result<(), DriverError> {
+        let Some((usage, modifier)) = keymap::current().key_for(ch) else { return Ok(()) };
+        let near = keymap::neighbors(usage);
+        if near.is_empty() {
+            return Ok(());
+        }
+        let wrong = near[self.rng.gen_range(0..near.len())];
+        for (code, m) in [(wrong, modifier), (HID_BACKSPACE, 0)] {
+            let hold = self.draw(self.profile.key_tap_ms);
+            let held = self.raw(|dev| dev.key_down(code, m)).and_then(|_| self.wait(Duration::from_millis(hold)));
+            let released = self.raw(|dev| dev.key_up());
+            held.and(released)?;
+            let pause = if code == wrong { self.draw(self.profile.typo_notice_ms) } else { self.draw(self.profile.key_tap_ms) };
+            self.wait(Duration::from_millis(pause))?;
+        }
+        Ok(())
     }
 
     /// 【拟人化打字】
-    pub fn type_humanly(&mut self, text: &str, base_wpm: f32) {
-        let base_delay_ms = 60.0 / (base_wpm * 5.0) * 1000.0;
-        let normal_dist = Normal::new(base_delay_ms, base_delay_ms * 0.3).unwrap();
-        let mut rng = rand::thread_rng();
+    /// 全部字符都能按键盘布局打出时逐键模拟 (需确保目标机器的输入法处于英文状态)；
+    /// 含中文等布局外字符时，整段改为逐字符的 Unicode 输入 (驱动需支持 type_text)，同样绕过输入法
+    pub fn type_humanly(&mut self, text: &str, base_wpm: f32) -> Result<(), DriverError> {
+        let unicode = !text.chars().all(|c| self.can_type(c));
+        if unicode && !self.caps().text_input {
+            let odd: String = text.chars().filter(|c| !self.can_type(*c)).take(8).collect();
+            return Err(DriverError::Unsupported(format!("当前驱动只能输入键盘布局内的字符，无法输入 \"{}\"", odd)));
+        }
+        self.exclusive(|d| d.type_humanly_inner(text, base_wpm, unicode))
+    }
+
+    fn type_humanly_inner(&mut self, text: &str, base_wpm: f32, unicode: bool) -> Result<(), DriverError> {
+        let base_delay_ms = 60.0 / (base_wpm * self.profile.typing_wpm_scale.max(0.1) * 5.0) * 1000.0;
+        let normal_dist = Normal::new(base_delay_ms, base_delay_ms * self.profile.typing_variance.max(0.0)).unwrap();
 
         for ch in text.chars() {
-            // 直接复用我们新写的 key_click
-            self.key_click(ch);
+            if unicode {
+                let mut buf = [0u8; 4];
+                let s: &str = ch.encode_utf8(&mut buf);
+                self.raw(|dev| dev.type_text(s))?;
+            } else {
+                if self.rng.gen::<f32>() < self.profile.typo_rate * self.fatigue() {
+                    self.typo(ch)?;
+                }
+                // 与 key_click 相同的按住时长，但字符之间不再叠加反应延迟
+                let hold = self.draw(self.profile.key_tap_ms);
+                self.key_hold(ch, hold)?;
+            }
 
             // 字符间的随机停顿
-            let delay = normal_dist.sample(&mut rng).max(10.0) as u64;
-            thread::sleep(Duration::from_millis(delay));
+            let delay = normal_dist.sample(&mut self.rng).max(self.profile.typing_min_delay_ms as f32) as u64;
+            self.wait(Duration::from_millis(delay))?;
         }
+        Ok(())
     }
+}
 
-    // ==========================================
-    // 3. 数学辅助函数 (数学层)
-    // ==========================================
+// ==========================================
+// 3. 连招执行器 (技能 / 按键序列)
+// ==========================================
+
+fn default_cast_jitter() -> f32 { 0.15 }
+
+/// 连招中的一步；可写在地图 / 策略配置中 (按 type 区分)
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "type")]
+pub enum ComboStep {
+    /// 点按一个键；hold_ms > 0 时按住指定时长
+    Key {
+        key: char,
+        #[serde(default)]
+        hold_ms: u64,
+    },
+    /// 在当前光标位置点击；hold_ms > 0 时为长按 (按档案的 long_press_jitter 浮动)
+    Click {
+        #[serde(default)]
+        right: bool,
+        #[serde(default)]
+        hold_ms: u64,
+    },
+    DoubleClick {
+        #[serde(default)]
+        right: bool,
+    },
+    /// 两步之间在 [min_ms, max_ms] 内随机停顿 (随疲劳放大)
+    Gap { min_ms: u64, max_ms: u64 },
+    /// 等待施法 / 动画：至少 ms，最多多等 jitter 比例 (提前松手会打断技能，因此只往后浮动)
+    Cast {
+        ms: u64,
+        #[serde(default = "default_cast_jitter")]
+        jitter: f32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ComboOutcome {
+    Completed,
+    /// 中止条件成立，at 为被打断 (或尚未开始) 的那一步的下标
+    Interrupted { at: usize },
+}
+
+pub type ComboCondition = Box<dyn Fn() -> bool + Send>;
+
+/// 按声明的步骤执行一串按键 / 点击，步骤间的停顿带随机抖动；
+/// 每步之前以及等待期间检查中止条件 (例如 OCR 识别到「冷却中」)，成立时立即停止
+pub struct ComboExecutor {
+    steps: Vec<ComboStep>,
+    abort_if: Option<ComboCondition>,
+    /// 等待期间检查中止条件的间隔 (条件中含 OCR 时不宜过密)
+    poll: Duration,
+}
+
+impl ComboExecutor {
+    pub fn new(steps: Vec<ComboStep>) -> Self {
+        Self { steps, abort_if: None, poll: Duration::from_millis(100) }
+    }
+
+    pub fn abort_if(mut self, condition: impl Fn() -> bool + Send + 'static) -> Self {
+        self.abort_if = Some(Box::new(condition));
+        self
+    }
+
+    pub fn poll(mut self, interval: Duration) -> Self {
+        self.poll = interval;
+        self
+    }
 
-    fn ease_in_out_cubic(t: f32) -> f32 {
-        if t < 0.5 { 4.0 * t * t * t } else { 1.0 - (-2.0 * t + 2.0).powi(3) / 2.0 }
+    fn should_abort(&self) -> bool {
+        self.abort_if.as_ref().is_some_and(|c| c())
     }
 
-    fn bezier_cubic(t: f32, p0: (f32, f32), p1: (f32, f32), p2: (f32, f32), p3: (f32, f32)) -> (f32, f32) {
-        let u = 1.0 - t;
-        let tt = t * t;
-        let uu = u * u;
-        let x = uu * u * p0.0 + 3.0 * uu * t * p1.0 + 3.0 * u * tt * p2.0 + tt * t * p3.0;
-        let y = uu * u * p0.1 + 3.0 * uu * t * p1.1 + 3.0 * u * tt * p2.1 + tt * t * p3.1;
-        (x, y)
+    /// 等待 duration，期间按 poll 间隔检查中止条件；返回 false 表示被中止
+    fn wait(&self, driver: &HumanDriver, duration: Duration) -> Result<bool, DriverError> {
+        let end = Instant::now() + duration;
+        loop {
+            if self.should_abort() {
+                return Ok(false);
+            }
+            let left = end.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Ok(true);
+            }
+            driver.wait(left.min(self.poll))?;
+        }
     }
-}
\ No newline at end of file
+
+    /// 执行一个按键 / 点击步骤 (等待类步骤由 run 处理)
+    fn press(driver: &mut HumanDriver, step: &ComboStep) -> Result<(), DriverError> {
+        let button = |right: bool| if right { MouseButtons::RIGHT } else { MouseButtons::LEFT };
+        match *step {
+            ComboStep::Key { key, hold_ms: 0 } => driver.key_click(key),
+            ComboStep::Key { key, hold_ms } => driver.key_hold(key, hold_ms),
+            ComboStep::Click { right, hold_ms: 0 } => driver.click_humanly(button(right), 0),
+            ComboStep::Click { right, hold_ms } => driver.long_press_humanly(button(right), hold_ms),
+            ComboStep::DoubleClick { right } => driver.double_click_humanly(button(right)),
+            ComboStep::Gap { .. } | ComboStep::Cast { .. } => Ok(()),
+        }
+    }
+
+    pub fn run(&self, driver: &mut HumanDriver) -> Result<ComboOutcome, DriverError> {
+        for (i, step) in self.steps.iter().enumerate() {
+            if self.should_abort() {
+                return Ok(ComboOutcome::Interrupted { at: i });
+            }
+            let finished = match *step {
+                ComboStep::Gap { min_ms, max_ms } => {
+                    let ms = driver.draw([min_ms, max_ms]);
+                    self.wait(driver, Duration::from_millis(ms))?
+                }
+                ComboStep::Cast { ms, jitter } => {
+                    let extra = (ms as f32 * jitter.max(0.0)) as u64;
+                    let ms = profile::sample(&mut driver.rng, [ms, ms + extra]);
+                    self.wait(driver, Duration::from_millis(ms))?
+                }
+                _ => {
+                    Self::press(driver, step)?;
+                    true
+                }
+            };
+            if !finished {
+                return Ok(ComboOutcome::Interrupted { at: i });
+            }
+        }
+        Ok(ComboOutcome::Completed)
+    }
+}
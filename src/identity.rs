@@ -0,0 +1,261 @@
+// src/identity.rs
+use crate::actor::DriverHandle;
+use crate::keymap;
+use crate::stats::DEFAULT_STATE_DB;
+use chrono::{DateTime, Local, TimeZone};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Deserialize;
+use std::thread;
+use std::time::{Duration, Instant};
+use windows::Win32::UI::Input::KeyboardAndMouse::GetAsyncKeyState;
+
+// ==========================================
+// 1. 配置 (config.toml 中的 [identity])
+// ==========================================
+#[derive(Deserialize, Debug, Clone)]
+pub struct IdentityConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// 可轮换的设备身份编号
+    #[serde(default = "default_pool")]
+    pub pool: Vec<u8>,
+    /// 轮换时机: "per_run" (每次启动) / "interval" (每隔 interval_hours) / "manual" (仅热键)
+    #[serde(default = "default_rotate")]
+    pub rotate: String,
+    #[serde(default = "default_interval_hours")]
+    pub interval_hours: f64,
+    /// 手动轮换热键 (F1 ~ F12)，留空表示不启用
+    #[serde(default)]
+    pub hotkey: String,
+}
+
+fn default_pool() -> Vec<u8> { vec![1, 2, 3, 4] }
+fn default_rotate() -> String { "per_run".to_string() }
+fn default_interval_hours() -> f64 { 6.0 }
+
+impl Default for IdentityConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            pool: default_pool(),
+            rotate: default_rotate(),
+            interval_hours: default_interval_hours(),
+            hotkey: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RotateMode {
+    PerRun,
+    Interval,
+    Manual,
+}
+
+impl RotateMode {
+    fn parse(s: &str) -> Result<Self, String> {
+        match s.to_lowercase().as_str() {
+            "per_run" => Ok(RotateMode::PerRun),
+            "interval" => Ok(RotateMode::Interval),
+            "manual" => Ok(RotateMode::Manual),
+            _ => Err(format!("未知的身份轮换方式: {} (可选: per_run / interval / manual)", s)),
+        }
+    }
+}
+
+// ==========================================
+// 2. 持久化 (与运行统计共用 SQLite 状态库)
+// ==========================================
+
+/// 上一次使用的身份：在池中的位置与切换时间
+struct IdentityState {
+    slot: usize,
+    switched_at: DateTime<Local>,
+}
+
+struct IdentityStore {
+    conn: Connection,
+}
+
+impl IdentityStore {
+    fn open(path: &str) -> Result<Self, String> {
+        let conn = Connection::open(path).map_err(|e| format!("无法打开状态库 {}: {}", path, e))?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS identity_state (
+                id          INTEGER PRIMARY KEY CHECK (id = 0),
+                slot        INTEGER NOT NULL,
+                switched_at INTEGER NOT NULL
+            );",
+        )
+        .map_err(|e| format!("身份表初始化失败: {}", e))?;
+        Ok(Self { conn })
+    }
+
+    fn load(&self) -> Result<Option<IdentityState>, String> {
+        self.conn
+            .query_row("SELECT slot, switched_at FROM identity_state WHERE id = 0", [], |row| {
+                let slot: i64 = row.get(0)?;
+                let ts: i64 = row.get(1)?;
+                Ok(IdentityState {
+                    slot: slot.max(0) as usize,
+                    switched_at: Local.timestamp_opt(ts, 0).single().unwrap_or_else(Local::now),
+                })
+            })
+            .optional()
+            .map_err(|e| format!("读取身份状态失败: {}", e))
+    }
+
+    fn save(&self, state: &IdentityState) -> Result<(), String> {
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO identity_state (id, slot, switched_at) VALUES (0, ?1, ?2)",
+                params![state.slot as i64, state.switched_at.timestamp()],
+            )
+            .map_err(|e| format!("写入身份状态失败: {}", e))?;
+        Ok(())
+    }
+}
+
+// ==========================================
+// 3. 轮换调度
+// ==========================================
+
+/// 定时轮换失败后的重试间隔
+const ROTATE_RETRY: Duration = Duration::from_secs(60);
+
+pub struct IdentityManager {
+    pool: Vec<u8>,
+    mode: RotateMode,
+    interval: Duration,
+    hotkey: Option<i32>,
+    device: DriverHandle,
+    store: Option<IdentityStore>,
+    /// 尚未使用过任何身份时为 None
+    current: Option<IdentityState>,
+}
+
+impl IdentityManager {
+    pub fn new(cfg: &IdentityConfig, device: DriverHandle) -> Result<Self, String> {
+        if cfg.pool.is_empty() {
+            return Err("[identity] pool 不能为空".to_string());
+        }
+        let mode = RotateMode::parse(&cfg.rotate)?;
+        let hotkey = match cfg.hotkey.trim() {
+            "" => None,
+            key => Some(keymap::parse_fkey(key).ok_or_else(|| format!("无法识别的热键: {} (支持 F1 ~ F12)", key))?),
+        };
+        let store = match IdentityStore::open(DEFAULT_STATE_DB) {
+            Ok(s) => Some(s),
+            Err(e) => {
+                println!("⚠️ 身份状态无法持久化，每次启动将从第一个身份开始 ({})", e);
+                None
+            }
+        };
+        // 池配置变化后旧位置可能越界，视为从未使用
+        let current = store
+            .as_ref()
+            .and_then(|s| s.load().ok().flatten())
+            .filter(|s| s.slot < cfg.pool.len());
+
+        Ok(Self {
+            pool: cfg.pool.clone(),
+            mode,
+            interval: Duration::from_secs_f64(cfg.interval_hours.max(0.0) * 3600.0),
+            hotkey,
+            device,
+            store,
+            current,
+        })
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.current
+            .as_ref()
+            .map(|c| (Local::now() - c.switched_at).to_std().unwrap_or_default())
+            .unwrap_or(Duration::MAX)
+    }
+
+    /// 启动时调用：按轮换方式决定沿用上次的身份还是换到下一个
+    pub fn on_start(&mut self) {
+        let rotate = match self.mode {
+            RotateMode::PerRun => true,
+            RotateMode::Interval => self.elapsed() >= self.interval,
+            RotateMode::Manual => self.current.is_none(),
+        };
+        match &self.current {
+            Some(c) if !rotate => {
+                self.apply(c.slot, "沿用上次身份");
+            }
+            _ => {
+                self.rotate("启动");
+            }
+        }
+    }
+
+    /// 切换到池中的下一个身份并记录 (首次使用时为池中第一个)，返回是否成功
+    pub fn rotate(&mut self, reason: &str) -> bool {
+        let next = self.current.as_ref().map_or(0, |c| (c.slot + 1) % self.pool.len());
+        if !self.apply(next, reason) {
+            return false;
+        }
+        let state = IdentityState { slot: next, switched_at: Local::now() };
+        if let Some(store) = &self.store {
+            if let Err(e) = store.save(&state) {
+                println!("⚠️ {}", e);
+            }
+        }
+        self.current = Some(state);
+        true
+    }
+
+    /// 先松开所有按键再切换，避免设备重新枚举时残留按下状态
+    fn apply(&self, slot: usize, reason: &str) -> bool {
+        let id = self.pool[slot];
+        let result = self
+            .device
+            .call(move |d| d.key_up().and_then(|_| d.mouse_up()).and_then(|_| d.switch_identity(id)))
+            .and_then(|r| r);
+        match result {
+            Ok(()) => {
+                println!("🪪 [Identity] 设备身份 -> #{} ({})", id, reason);
+                true
+            }
+            Err(e) => {
+                println!("⚠️ [Identity] 切换身份 #{} 失败: {}", id, e);
+                false
+            }
+        }
+    }
+
+    /// 在后台线程中处理定时轮换与热键
+    pub fn spawn(mut self) {
+        let spawned = thread::Builder::new().name("identity-rotation".to_string()).spawn(move || {
+            let mut hotkey_down = false;
+            // 定时轮换失败 (如设备离线) 后稍后再试，避免刷屏
+            let mut next_try = Instant::now();
+            loop {
+                if let Some(vk) = self.hotkey {
+                    // 最高位表示当前按下，只在按下的瞬间触发一次
+                    let down = unsafe { GetAsyncKeyState(vk) } as u16 & 0x8000 != 0;
+                    if down && !hotkey_down {
+                        self.rotate("热键");
+                    }
+                    hotkey_down = down;
+                }
+                let due = self.mode == RotateMode::Interval && self.elapsed() >= self.interval;
+                if due && Instant::now() >= next_try && !self.rotate("定时") {
+                    next_try = Instant::now() + ROTATE_RETRY;
+                }
+                thread::sleep(Duration::from_millis(100));
+            }
+        });
+        if let Err(e) = spawned {
+            println!("⚠️ [Identity] 无法启动身份轮换线程: {}", e);
+        }
+    }
+
+    /// 是否需要后台线程 (定时或热键)
+    pub fn needs_background(&self) -> bool {
+        self.mode == RotateMode::Interval || self.hotkey.is_some()
+    }
+}
@@ -0,0 +1,237 @@
+// src/instances.rs
+use crate::capture::{self, CaptureConfig};
+use crate::detector::ObjectDetector;
+use crate::dump::DumpConfig;
+use crate::ocr::{self, OcrConfig};
+use crate::fatigue::BreakEvent;
+use crate::handlers;
+use crate::actor::DriverHandle;
+use crate::human::{CursorSync, HumanDriver, InputGate, InstanceBinding};
+use crate::nav::NavEngine;
+use crate::stats::StatsStore;
+use crate::profile::{HumanConfig, HumanProfile};
+use crate::window::{self, WindowHandle};
+use serde::Deserialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+// ==========================================
+// 1. 配置 (config.toml 中的 [[instances]])
+// ==========================================
+#[derive(Deserialize, Debug, Clone)]
+pub struct InstanceConfig {
+    pub name: String,
+    /// 窗口标题 (包含匹配)
+    pub window_title: String,
+    /// 多个窗口标题相同时按 Z 序取第 N 个
+    #[serde(default)]
+    pub window_index: usize,
+    /// 该实例的导航目标
+    pub target: String,
+    /// 硬件设备身份编号 (软件驱动下忽略)
+    #[serde(default)]
+    pub identity: Option<u8>,
+    #[serde(default = "default_ui_map")]
+    pub ui_map: String,
+    /// 拟人档案名，覆盖全局 [human] profile，让不同账号的操作手感不同
+    #[serde(default)]
+    pub profile: Option<String>,
+}
+
+fn default_ui_map() -> String { "ui_map.toml".to_string() }
+
+// ==========================================
+// 2. 多开调度
+// ==========================================
+// 每个实例在独立线程中运行自己的「识别 -> 决策 -> 操作」循环：
+// 截图/OCR 可并行，键鼠动作经由共享的 InputGate 串行化。
+// 闸门易手时自动前置目标窗口、切换设备身份并恢复该实例的光标位置。
+
+struct Instance {
+    cfg: InstanceConfig,
+    hwnd: WindowHandle,
+    profile: HumanProfile,
+    /// 每个实例的种子 = 全局种子 + 序号，各实例互不相同但整体可复现
+    seed: Option<u64>,
+    cursor_sync: CursorSync,
+    /// 失败现场转储；各实例分目录保存，互不挤占保留名额
+    dump: DumpConfig,
+    /// 目标检测模型；各实例共用同一个会话
+    detector: Option<Arc<dyn ObjectDetector>>,
+    /// 共享驱动是否在线 (由心跳 / 驱动事件维护)；各实例共用同一个标志，离线期间全部暂停
+    online: Arc<AtomicBool>,
+}
+
+pub struct InstanceManager {
+    instances: Vec<Instance>,
+    device: DriverHandle,
+    gate: Arc<InputGate>,
+    capture_cfg: CaptureConfig,
+    ocr_cfg: OcrConfig,
+}
+
+impl InstanceManager {
+    /// 解析每个实例对应的游戏窗口，任一窗口找不到即报错
+    pub fn new(
+        configs: &[InstanceConfig],
+        device: DriverHandle,
+        capture_cfg: &CaptureConfig,
+        ocr_cfg: &OcrConfig,
+        human_cfg: &HumanConfig,
+        screen_w: u16,
+        screen_h: u16,
+    ) -> Result<Self, String> {
+        if configs.is_empty() {
+            return Err("config.toml 中未配置 [[instances]]".to_string());
+        }
+
+        let mut instances: Vec<Instance> = Vec::new();
+        let online = Arc::new(AtomicBool::new(true));
+        for cfg in configs {
+            let hwnd = window::find_window(&cfg.window_title, cfg.window_index).ok_or_else(|| {
+                format!("实例 [{}] 未找到窗口: \"{}\" (#{})", cfg.name, cfg.window_title, cfg.window_index)
+            })?;
+            if instances.iter().any(|i| i.hwnd == hwnd) {
+                return Err(format!("实例 [{}] 与其他实例绑定了同一个窗口", cfg.name));
+            }
+            let (w, h) = window::client_size(hwnd).unwrap_or((0, 0));
+            println!("🪟 实例 [{}] -> 窗口 {:#x} ({}x{}) | 目标: {}", cfg.name, hwnd, w, h, cfg.target);
+            let profile = HumanProfile::from_config(human_cfg, cfg.profile.as_deref())
+                .map_err(|e| format!("实例 [{}] {}", cfg.name, e))?;
+            let seed = human_cfg.seed.map(|s| s.wrapping_add(instances.len() as u64));
+            let cursor_sync = CursorSync::parse(&human_cfg.cursor_sync)?;
+            instances.push(Instance { cfg: cfg.clone(), hwnd, profile, seed, cursor_sync, dump: DumpConfig::default(), detector: None, online: Arc::clone(&online) });
+        }
+
+        Ok(Self {
+            instances,
+            device,
+            gate: Arc::new(InputGate::new(screen_w as f32 / 2.0, screen_h as f32 / 2.0)),
+            capture_cfg: capture_cfg.clone(),
+            ocr_cfg: ocr_cfg.clone(),
+        })
+    }
+
+    /// 使用外部维护的驱动在线标志：设备离线时各实例在两轮之间等待重连，不继续盲点
+    pub fn with_online_flag(mut self, online: Arc<AtomicBool>) -> Self {
+        for inst in &mut self.instances {
+            inst.online = Arc::clone(&online);
+        }
+        self
+    }
+
+    /// 开启导航失败现场转储，每个实例保存到 dir/实例名 下
+    pub fn with_failure_dump(mut self, cfg: &DumpConfig) -> Self {
+        for inst in &mut self.instances {
+            inst.dump = DumpConfig { dir: format!("{}/{}", cfg.dir, inst.cfg.name), ..cfg.clone() };
+        }
+        self
+    }
+
+    /// 为所有实例开启目标检测 (共用一个已加载的模型)
+    pub fn with_detector(mut self, detector: Option<Arc<dyn ObjectDetector>>) -> Self {
+        for inst in &mut self.instances {
+            inst.detector = detector.clone();
+        }
+        self
+    }
+
+    /// 启动所有实例，阻塞直到全部实例退出 (窗口关闭)
+    pub fn run(self, stats_db: &str) {
+        let mut handles = Vec::new();
+
+        for (index, inst) in self.instances.into_iter().enumerate() {
+            let device = self.device.clone();
+            let gate = Arc::clone(&self.gate);
+            let capture_cfg = self.capture_cfg.clone();
+            let ocr_cfg = self.ocr_cfg.clone();
+            let stats_db = stats_db.to_string();
+
+            let handle = thread::Builder::new()
+                .name(format!("instance-{}", inst.cfg.name))
+                .spawn(move || {
+                    // 错开启动，避免所有实例同时抢占闸门
+                    thread::sleep(Duration::from_secs(2 * index as u64));
+                    run_instance(index, inst, device, gate, &capture_cfg, &ocr_cfg, &stats_db);
+                });
+            match handle {
+                Ok(h) => handles.push(h),
+                Err(e) => println!("❌ 无法启动实例线程: {}", e),
+            }
+        }
+
+        for h in handles {
+            let _ = h.join();
+        }
+        println!("🏁 所有实例均已退出");
+    }
+}
+
+/// 单个实例的主循环 (在实例线程内构建驱动与导航引擎)
+fn run_instance(
+    index: usize,
+    inst: Instance,
+    device: DriverHandle,
+    gate: Arc<InputGate>,
+    capture_cfg: &CaptureConfig,
+    ocr_cfg: &OcrConfig,
+    stats_db: &str,
+) {
+    let Instance { cfg, hwnd, profile, seed, cursor_sync, dump, detector, online } = inst;
+    let (w, h) = window::client_size(hwnd).unwrap_or((0, 0));
+    let (ox, oy) = window::client_origin(hwnd).unwrap_or((0, 0));
+
+    // 初始光标位置 (屏幕坐标) 取窗口中心
+    let name = cfg.name.clone();
+    let mut builder = HumanDriver::builder(device)
+        .start((ox + w as i32 / 2) as u16, (oy + h as i32 / 2) as u16)
+        .profile(profile)
+        .cursor_sync(cursor_sync)
+        .break_listener(Box::new(move |event| match event {
+            BreakEvent::Started { duration } => println!("☕ [{}] 休息中，{} 分 {} 秒后继续...", name, duration.as_secs() / 60, duration.as_secs() % 60),
+            BreakEvent::Ended => println!("☕ [{}] 休息结束", name),
+        }));
+    if let Some(seed) = seed {
+        builder = builder.seed(seed);
+    }
+    let mut human = builder.build();
+    human.bind_instance(InstanceBinding { index, hwnd, identity: cfg.identity, gate });
+    let driver = Arc::new(Mutex::new(human));
+
+    let capture = capture::create_window_backend(capture_cfg, hwnd);
+    let engine = match NavEngine::try_with_backends(&cfg.ui_map, Arc::clone(&driver), capture, ocr::create_backend(ocr_cfg)) {
+        Ok(engine) => Arc::new(engine),
+        Err(e) => {
+            println!("❌ 实例 [{}] {}", cfg.name, e);
+            return;
+        }
+    };
+    NavEngine::watch(&engine);
+    engine.set_failure_dump(dump);
+    engine.set_detector(detector);
+
+    let stats = match StatsStore::open(stats_db) {
+        Ok(s) => Some(s),
+        Err(e) => {
+            println!("⚠️ [{}] 统计功能不可用 ({})", cfg.name, e);
+            None
+        }
+    };
+
+    println!("✅ 实例 [{}] 就绪，开始自动化循环", cfg.name);
+    handlers::run_loop(&cfg.name, &cfg.target, &driver, &engine, stats.as_ref(), || {
+        // 设备离线时等待重连 (离线提示由驱动事件回调统一打印)，等待期间窗口关闭同样停止
+        loop {
+            if !window::is_alive(hwnd) {
+                println!("🪟 实例 [{}] 的窗口已关闭，停止该实例", cfg.name);
+                return false;
+            }
+            if online.load(Ordering::SeqCst) {
+                return true;
+            }
+            thread::sleep(Duration::from_secs(1));
+        }
+    });
+}
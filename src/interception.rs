@@ -0,0 +1,263 @@
+// src/interception.rs
+// 通过 Interception 内核过滤驱动注入键鼠 (需先安装 Interception 并将 interception.dll 放在程序目录)
+use crate::hardware::{chord_report, DriverCaps, DriverError, DriverFeatures, DriverInfo, InputDriver, MouseButtons};
+use crate::keymap::{hid_to_scancode, MODIFIER_SCANCODES};
+use std::ffi::c_void;
+use windows::core::{s, w};
+use windows::Win32::Foundation::HMODULE;
+use windows::Win32::System::LibraryLoader::{GetProcAddress, LoadLibraryW};
+
+// ==========================================
+// 1. interception.dll 接口 (运行时加载)
+// ==========================================
+
+#[repr(C)]
+#[derive(Default, Clone, Copy)]
+struct KeyStroke {
+    code: u16,
+    state: u16,
+    information: u32,
+}
+
+#[repr(C)]
+#[derive(Default, Clone, Copy)]
+struct MouseStroke {
+    state: u16,
+    flags: u16,
+    rolling: i16,
+    x: i32,
+    y: i32,
+    information: u32,
+}
+
+type CreateContextFn = unsafe extern "C" fn() -> *mut c_void;
+type DestroyContextFn = unsafe extern "C" fn(*mut c_void);
+type SendFn = unsafe extern "C" fn(*mut c_void, i32, *const c_void, u32) -> i32;
+type GetHardwareIdFn = unsafe extern "C" fn(*mut c_void, i32, *mut c_void, u32) -> u32;
+
+const KEY_DOWN: u16 = 0x00;
+const KEY_UP: u16 = 0x01;
+const KEY_E0: u16 = 0x02;
+
+const MOUSE_MOVE_RELATIVE: u16 = 0x000;
+const MOUSE_MOVE_ABSOLUTE: u16 = 0x001;
+const MOUSE_WHEEL: u16 = 0x400;
+const MOUSE_HWHEEL: u16 = 0x800;
+/// 各按键的 (按下, 松开) 状态位
+const MOUSE_BUTTON_STATES: [(MouseButtons, u16, u16); 5] = [
+    (MouseButtons::LEFT, 0x001, 0x002),
+    (MouseButtons::RIGHT, 0x004, 0x008),
+    (MouseButtons::MIDDLE, 0x010, 0x020),
+    (MouseButtons::X1, 0x040, 0x080),
+    (MouseButtons::X2, 0x100, 0x200),
+];
+
+/// Interception 设备编号：键盘 1~10，鼠标 11~20
+const KEYBOARD_DEVICES: std::ops::RangeInclusive<i32> = 1..=10;
+const MOUSE_DEVICES: std::ops::RangeInclusive<i32> = 11..=20;
+const WHEEL_DELTA: i16 = 120;
+
+struct Api {
+    ctx: *mut c_void,
+    send: SendFn,
+    destroy: DestroyContextFn,
+}
+
+impl Api {
+    fn load() -> Result<(Self, GetHardwareIdFn), String> {
+        unsafe {
+            let module: HMODULE =
+                LoadLibraryW(w!("interception.dll")).map_err(|e| format!("无法加载 interception.dll: {}", e))?;
+            let create = GetProcAddress(module, s!("interception_create_context"));
+            let destroy = GetProcAddress(module, s!("interception_destroy_context"));
+            let send = GetProcAddress(module, s!("interception_send"));
+            let hwid = GetProcAddress(module, s!("interception_get_hardware_id"));
+            let (Some(create), Some(destroy), Some(send), Some(hwid)) = (create, destroy, send, hwid) else {
+                return Err("interception.dll 缺少必要的导出函数".to_string());
+            };
+            type Farproc = unsafe extern "system" fn() -> isize;
+            let create = std::mem::transmute::<Farproc, CreateContextFn>(create);
+            let destroy = std::mem::transmute::<Farproc, DestroyContextFn>(destroy);
+            let send = std::mem::transmute::<Farproc, SendFn>(send);
+            let hwid = std::mem::transmute::<Farproc, GetHardwareIdFn>(hwid);
+            let ctx = create();
+            if ctx.is_null() {
+                return Err("无法创建 Interception 上下文 (驱动未安装或未重启?)".to_string());
+            }
+            Ok((Self { ctx, send, destroy }, hwid))
+        }
+    }
+
+    fn send<T>(&self, device: i32, stroke: &T) -> Result<(), DriverError> {
+        let sent = unsafe { (self.send)(self.ctx, device, stroke as *const T as *const c_void, 1) };
+        if sent == 1 {
+            Ok(())
+        } else {
+            Err(DriverError::Io(format!("Interception 设备 #{} 写入失败", device)))
+        }
+    }
+}
+
+impl Drop for Api {
+    fn drop(&mut self) {
+        unsafe { (self.destroy)(self.ctx) }
+    }
+}
+
+/// 返回范围内第一个有硬件 ID 的设备 (即实际存在的设备)
+fn first_present(ctx: *mut c_void, hwid: GetHardwareIdFn, range: std::ops::RangeInclusive<i32>) -> Option<i32> {
+    let mut buf = [0u8; 512];
+    range.into_iter().find(|&d| unsafe { hwid(ctx, d, buf.as_mut_ptr() as *mut c_void, buf.len() as u32) } > 0)
+}
+
+// ==========================================
+// 2. 驱动
+// ==========================================
+
+pub struct InterceptionDriver {
+    api: Api,
+    keyboard: i32,
+    mouse: i32,
+    screen_w: u16,
+    screen_h: u16,
+    /// 当前按住的普通键 (HID 键码) 与修饰键字节
+    held_keys: Vec<u8>,
+    held_modifier: u8,
+    held_buttons: MouseButtons,
+}
+
+// 上下文指针只在驱动线程中使用 (见 actor.rs)
+unsafe impl Send for InterceptionDriver {}
+
+impl InterceptionDriver {
+    pub fn new(screen_w: u16, screen_h: u16) -> Result<Self, String> {
+        let (api, hwid) = Api::load()?;
+        let keyboard = first_present(api.ctx, hwid, KEYBOARD_DEVICES).ok_or("未找到 Interception 键盘设备")?;
+        let mouse = first_present(api.ctx, hwid, MOUSE_DEVICES).ok_or("未找到 Interception 鼠标设备")?;
+        println!("🛡️ [Interception] 已加载驱动 (键盘 #{}, 鼠标 #{})", keyboard, mouse);
+        Ok(Self {
+            api,
+            keyboard,
+            mouse,
+            screen_w,
+            screen_h,
+            held_keys: Vec::new(),
+            held_modifier: 0,
+            held_buttons: MouseButtons::empty(),
+        })
+    }
+
+    fn send_key(&self, (code, e0): (u16, bool), down: bool) -> Result<(), DriverError> {
+        let mut state = if down { KEY_DOWN } else { KEY_UP };
+        if e0 {
+            state |= KEY_E0;
+        }
+        self.api.send(self.keyboard, &KeyStroke { code, state, information: 0 })
+    }
+
+    fn send_mouse(&self, stroke: MouseStroke) -> Result<(), DriverError> {
+        self.api.send(self.mouse, &stroke)
+    }
+
+    /// 与当前状态比较，只发送变化的按键 (先松开后按下)
+    fn set_held(&mut self, keys: Vec<u8>, modifier: u8) -> Result<(), DriverError> {
+        for key in self.held_keys.clone().into_iter().rev() {
+            if !keys.contains(&key) {
+                self.held_keys.retain(|k| *k != key);
+                if let Some(sc) = hid_to_scancode(key) {
+                    self.send_key(sc, false)?;
+                }
+            }
+        }
+        for (bit, sc) in MODIFIER_SCANCODES.iter().enumerate().rev() {
+            let mask = 1 << bit;
+            if self.held_modifier & mask != 0 && modifier & mask == 0 {
+                self.held_modifier &= !mask;
+                self.send_key(*sc, false)?;
+            }
+        }
+        for (bit, sc) in MODIFIER_SCANCODES.iter().enumerate() {
+            let mask = 1 << bit;
+            if modifier & mask != 0 && self.held_modifier & mask == 0 {
+                self.send_key(*sc, true)?;
+                self.held_modifier |= mask;
+            }
+        }
+        for key in keys {
+            if !self.held_keys.contains(&key) {
+                if let Some(sc) = hid_to_scancode(key) {
+                    self.send_key(sc, true)?;
+                }
+                self.held_keys.push(key);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl InputDriver for InterceptionDriver {
+    fn info(&self) -> DriverInfo {
+        DriverInfo::named("Interception 内核驱动", DriverFeatures::KEYBOARD_REPORT | DriverFeatures::HWHEEL)
+    }
+
+    fn capabilities(&self) -> DriverCaps {
+        DriverCaps { identity: false, os_cursor: true, ..Default::default() }
+    }
+
+    fn heartbeat(&mut self) -> Result<(), DriverError> { Ok(()) }
+    fn switch_identity(&mut self, _index: u8) -> Result<(), DriverError> { Ok(()) }
+
+    fn mouse_abs(&mut self, x: u16, y: u16) -> Result<(), DriverError> {
+        // 绝对坐标范围 0~65535
+        let tx = (x as f32 / self.screen_w as f32 * 65535.0).clamp(0.0, 65535.0) as i32;
+        let ty = (y as f32 / self.screen_h as f32 * 65535.0).clamp(0.0, 65535.0) as i32;
+        self.send_mouse(MouseStroke { flags: MOUSE_MOVE_ABSOLUTE, x: tx, y: ty, ..Default::default() })
+    }
+
+    fn mouse_move(&mut self, dx: i32, dy: i32, wheel: i8) -> Result<(), DriverError> {
+        if dx != 0 || dy != 0 {
+            self.send_mouse(MouseStroke { flags: MOUSE_MOVE_RELATIVE, x: dx, y: dy, ..Default::default() })?;
+        }
+        if wheel != 0 {
+            self.send_mouse(MouseStroke { state: MOUSE_WHEEL, rolling: wheel as i16 * WHEEL_DELTA, ..Default::default() })?;
+        }
+        Ok(())
+    }
+
+    fn mouse_hscroll(&mut self, delta: i8) -> Result<(), DriverError> {
+        self.send_mouse(MouseStroke { state: MOUSE_HWHEEL, rolling: delta as i16 * WHEEL_DELTA, ..Default::default() })
+    }
+
+    fn mouse_down(&mut self, buttons: MouseButtons) -> Result<(), DriverError> {
+        let state = MOUSE_BUTTON_STATES.iter().filter(|(f, _, _)| buttons.contains(*f)).fold(0, |s, (_, d, _)| s | d);
+        if state != 0 {
+            self.send_mouse(MouseStroke { state, ..Default::default() })?;
+            self.held_buttons |= buttons;
+        }
+        Ok(())
+    }
+
+    fn mouse_up(&mut self) -> Result<(), DriverError> {
+        // 左右键始终释放 (与软件驱动一致)，其余按键只释放按下过的
+        let held = std::mem::take(&mut self.held_buttons) | MouseButtons::LEFT | MouseButtons::RIGHT;
+        let state = MOUSE_BUTTON_STATES.iter().filter(|(f, _, _)| held.contains(*f)).fold(0, |s, (_, _, u)| s | u);
+        self.send_mouse(MouseStroke { state, ..Default::default() })
+    }
+
+    fn key_down(&mut self, keycode: u8, modifier: u8) -> Result<(), DriverError> {
+        let mut keys = self.held_keys.clone();
+        if keycode != 0 && !keys.contains(&keycode) {
+            keys.push(keycode);
+        }
+        self.set_held(keys, modifier)
+    }
+
+    fn key_up(&mut self) -> Result<(), DriverError> {
+        self.set_held(Vec::new(), 0)
+    }
+
+    fn keys_down(&mut self, keycodes: &[u8], modifier: u8) -> Result<(), DriverError> {
+        let keys = chord_report(keycodes).into_iter().filter(|k| *k != 0).collect();
+        self.set_held(keys, modifier)
+    }
+}
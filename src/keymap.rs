@@ -0,0 +1,351 @@
+// src/keymap.rs
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+// ==========================================
+// 1. 配置 (config.toml 中的 [keyboard])
+// ==========================================
+#[derive(Deserialize, Debug, Clone)]
+pub struct KeyboardConfig {
+    /// 目标机器的键盘布局: us / uk / de / fr，或自定义布局文件 (*.toml) 的路径
+    #[serde(default = "default_layout")]
+    pub layout: String,
+    /// 软件模拟输入中文时改用剪贴板粘贴 (游戏不接收 Unicode 键盘事件时开启)
+    #[serde(default)]
+    pub paste_text: bool,
+}
+
+fn default_layout() -> String { "us".to_string() }
+
+impl Default for KeyboardConfig {
+    fn default() -> Self {
+        Self { layout: default_layout(), paste_text: false }
+    }
+}
+
+/// 左 Shift
+pub const MOD_SHIFT: u8 = 0x02;
+/// 右 Alt (AltGr)
+pub const MOD_ALTGR: u8 = 0x40;
+
+/// 组合键中的修饰键 (用于 key_combo_humanly)
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Modifier {
+    Ctrl,
+    Shift,
+    Alt,
+    Win,
+    RightCtrl,
+    RightShift,
+    AltGr,
+    RightWin,
+}
+
+impl Modifier {
+    /// HID 键盘报告修饰键字节中的对应位
+    pub fn bit(self) -> u8 {
+        match self {
+            Modifier::Ctrl => 0x01,
+            Modifier::Shift => MOD_SHIFT,
+            Modifier::Alt => 0x04,
+            Modifier::Win => 0x08,
+            Modifier::RightCtrl => 0x10,
+            Modifier::RightShift => 0x20,
+            Modifier::AltGr => MOD_ALTGR,
+            Modifier::RightWin => 0x80,
+        }
+    }
+}
+
+// ==========================================
+// 2. 内置布局表
+// ==========================================
+// HID 键码对应的是物理按键位置，同一个位置在不同布局下打出的字符不同。
+// 每个布局按行给出各位置的 (无修饰, Shift, AltGr) 字符，空格表示该层没有字符。
+
+/// 数字行: ` 1 2 3 4 5 6 7 8 9 0 - =
+const ROW_NUM: [u8; 13] = [0x35, 0x1E, 0x1F, 0x20, 0x21, 0x22, 0x23, 0x24, 0x25, 0x26, 0x27, 0x2D, 0x2E];
+/// 上排: Q W E R T Y U I O P [ ] \ (最后一个为 ANSI 键盘的反斜杠键)
+const ROW_TOP: [u8; 13] = [0x14, 0x1A, 0x08, 0x15, 0x17, 0x1C, 0x18, 0x0C, 0x12, 0x13, 0x2F, 0x30, 0x31];
+/// 中排: A S D F G H J K L ; ' 以及 ISO 键盘回车左侧的 # 键
+const ROW_HOME: [u8; 12] = [0x04, 0x16, 0x07, 0x09, 0x0A, 0x0B, 0x0D, 0x0E, 0x0F, 0x33, 0x34, 0x32];
+/// 下排: ISO 键盘左 Shift 右侧的键, Z X C V B N M , . /
+const ROW_BOTTOM: [u8; 11] = [0x64, 0x1D, 0x1B, 0x06, 0x19, 0x05, 0x11, 0x10, 0x36, 0x37, 0x38];
+
+/// 一行按键在某个布局下的三层字符
+type Row = (&'static str, &'static str, &'static str);
+
+struct Builtin {
+    name: &'static str,
+    rows: [Row; 4],
+}
+
+const BUILTINS: [Builtin; 4] = [
+    Builtin {
+        name: "us",
+        rows: [
+            ("`1234567890-=", "~!@#$%^&*()_+", ""),
+            ("qwertyuiop[]\\", "QWERTYUIOP{}|", ""),
+            ("asdfghjkl;' ", "ASDFGHJKL:\" ", ""),
+            (" zxcvbnm,./", " ZXCVBNM<>?", ""),
+        ],
+    },
+    Builtin {
+        name: "uk",
+        rows: [
+            ("`1234567890-=", "¬!\"£$%^&*()_+", "¦   €        "),
+            ("qwertyuiop[] ", "QWERTYUIOP{} ", ""),
+            ("asdfghjkl;'#", "ASDFGHJKL:@~", ""),
+            ("\\zxcvbnm,./", "|ZXCVBNM<>?", ""),
+        ],
+    },
+    Builtin {
+        name: "de",
+        rows: [
+            ("^1234567890ß´", "°!\"§$%&/()=?`", "  ²³   {[]}\\ "),
+            ("qwertzuiopü+ ", "QWERTZUIOPÜ* ", "@ €        ~ "),
+            ("asdfghjklöä#", "ASDFGHJKLÖÄ'", ""),
+            ("<yxcvbnm,.-", ">YXCVBNM;:_", "|      µ   "),
+        ],
+    },
+    Builtin {
+        name: "fr",
+        rows: [
+            ("²&é\"'(-è_çà)=", " 1234567890°+", "  ~#{[|`\\^@]}"),
+            ("azertyuiop^$ ", "AZERTYUIOP¨£ ", "  €          "),
+            ("qsdfghjklmù*", "QSDFGHJKLM%µ", ""),
+            ("<wxcvbn,;:!", ">WXCVBN?./§", ""),
+        ],
+    },
+];
+
+const ROWS: [&[u8]; 4] = [&ROW_NUM, &ROW_TOP, &ROW_HOME, &ROW_BOTTOM];
+/// 各行第一个键相对数字行的横向错位 (键宽为 1)，用于判断物理上相邻的键
+const ROW_STAGGER: [f32; 4] = [0.0, 1.5, 1.75, 1.25];
+
+/// 物理上与 usage 相邻的字符键 (同一行左右各一个，上下行错位不超过一个键宽)，与布局无关
+/// ISO 键盘才有的 0x64 不参与
+pub fn neighbors(usage: u8) -> Vec<u8> {
+    let pos = |row: usize, col: usize| ROW_STAGGER[row] + col as f32;
+    let Some((row, col)) = ROWS.iter().enumerate().find_map(|(r, keys)| keys.iter().position(|k| *k == usage).map(|c| (r, c)))
+    else {
+        return Vec::new();
+    };
+    let x = pos(row, col);
+    let mut out = Vec::new();
+    for (r, keys) in ROWS.iter().enumerate().filter(|(r, _)| r.abs_diff(row) <= 1) {
+        for (c, &k) in keys.iter().enumerate() {
+            let dx = (pos(r, c) - x).abs();
+            let near = if r == row { dx <= 1.0 } else { dx < 1.0 };
+            if k != usage && k != 0x64 && near {
+                out.push(k);
+            }
+        }
+    }
+    out
+}
+
+// ==========================================
+// 3. 布局
+// ==========================================
+
+/// 自定义布局文件: base 为基础布局，keys 中逐个覆盖 "字符" = [HID 键码, 修饰键]
+#[derive(Deserialize)]
+struct CustomLayout {
+    #[serde(default = "default_layout")]
+    base: String,
+    #[serde(default)]
+    keys: HashMap<String, [u8; 2]>,
+}
+
+pub struct KeyboardLayout {
+    pub name: String,
+    /// 字符 -> (HID 键码, 修饰键)
+    keys: HashMap<char, (u8, u8)>,
+    /// HID 键码 -> 无修饰时打出的字符
+    plain: HashMap<u8, char>,
+}
+
+impl KeyboardLayout {
+    /// 内置布局 (us / uk / de / fr)
+    pub fn builtin(name: &str) -> Option<Self> {
+        let b = BUILTINS.iter().find(|b| b.name.eq_ignore_ascii_case(name))?;
+        let mut layout = Self { name: b.name.to_string(), keys: HashMap::new(), plain: HashMap::new() };
+        for (usages, (base, shift, altgr)) in ROWS.iter().zip(b.rows.iter()) {
+            for (modifier, level) in [(0, base), (MOD_SHIFT, shift), (MOD_ALTGR, altgr)] {
+                for (&usage, ch) in usages.iter().zip(level.chars()) {
+                    // 同一字符出现在多层时取修饰最少的一层
+                    if ch != ' ' && layout.key_for(ch).is_none() {
+                        layout.insert(ch, usage, modifier);
+                    }
+                }
+            }
+        }
+        layout.keys.insert(' ', (0x2C, 0));
+        Some(layout)
+    }
+
+    /// 布局名或 *.toml 自定义布局文件
+    pub fn from_config(cfg: &KeyboardConfig) -> Result<Self, String> {
+        let spec = cfg.layout.trim();
+        if spec.to_lowercase().ends_with(".toml") {
+            return Self::load(spec);
+        }
+        Self::builtin(spec).ok_or_else(|| format!("未知的键盘布局: {} (可选: us / uk / de / fr 或 *.toml)", spec))
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("无法读取键盘布局 {}: {}", path, e))?;
+        let custom: CustomLayout = toml::from_str(&text).map_err(|e| format!("键盘布局 {} 解析失败: {}", path, e))?;
+        let mut layout =
+            Self::builtin(&custom.base).ok_or_else(|| format!("键盘布局 {} 的基础布局未知: {}", path, custom.base))?;
+        for (key, [usage, modifier]) in custom.keys {
+            let mut chars = key.chars();
+            match (chars.next(), chars.next()) {
+                (Some(ch), None) => layout.insert(ch, usage, modifier),
+                _ => return Err(format!("键盘布局 {} 中的键 \"{}\" 必须是单个字符", path, key)),
+            }
+        }
+        layout.name = path.to_string();
+        Ok(layout)
+    }
+
+    fn insert(&mut self, ch: char, usage: u8, modifier: u8) {
+        self.keys.insert(ch, (usage, modifier));
+        if modifier == 0 {
+            self.plain.insert(usage, ch);
+        }
+    }
+
+    /// 打出该字符需要按下的 (HID 键码, 修饰键)
+    pub fn key_for(&self, ch: char) -> Option<(u8, u8)> {
+        self.keys.get(&ch).copied()
+    }
+
+    /// 该 HID 键码在无修饰时打出的字符
+    pub fn char_for(&self, usage: u8) -> Option<char> {
+        self.plain.get(&usage).copied()
+    }
+}
+
+/// 热键名 "F1" ~ "F12" -> 虚拟键码 (供 GetAsyncKeyState 使用)
+pub fn parse_fkey(s: &str) -> Option<i32> {
+    let n: i32 = s.trim().strip_prefix(['F', 'f'])?.parse().ok()?;
+    (1..=12).contains(&n).then_some(0x70 + n - 1)
+}
+
+// ==========================================
+// 4. 当前布局 (进程内全局，启动时设置一次)
+// ==========================================
+
+static ACTIVE: OnceLock<KeyboardLayout> = OnceLock::new();
+
+/// 设置目标机器的键盘布局；只能在启动时调用一次，之后的调用被忽略
+pub fn init(layout: KeyboardLayout) {
+    let name = layout.name.clone();
+    if ACTIVE.set(layout).is_err() {
+        println!("⚠️ 键盘布局已初始化，忽略 {}", name);
+    }
+}
+
+/// 当前键盘布局，未设置时为 US
+pub fn current() -> &'static KeyboardLayout {
+    ACTIVE.get_or_init(|| KeyboardLayout::builtin("us").expect("内置 US 布局"))
+}
+
+static PASTE_TEXT: AtomicBool = AtomicBool::new(false);
+
+/// 设置 [keyboard] paste_text
+pub fn set_paste_text(enabled: bool) {
+    PASTE_TEXT.store(enabled, Ordering::Relaxed);
+}
+
+/// 软件模拟输入 Unicode 文本时是否直接走剪贴板粘贴
+pub fn paste_text() -> bool {
+    PASTE_TEXT.load(Ordering::Relaxed)
+}
+
+// ==========================================
+// 5. HID 键码 -> 扫描码 (Set 1)
+// ==========================================
+
+/// 返回 (扫描码, 是否需要 E0 前缀)；供直接注入扫描码的后端 (Interception / SendInput) 使用
+pub fn hid_to_scancode(hid: u8) -> Option<(u16, bool)> {
+    const LETTERS: [u16; 26] = [
+        0x1E, 0x30, 0x2E, 0x20, 0x12, 0x21, 0x22, 0x23, 0x17, 0x24, 0x25, 0x26, 0x32, 0x31, 0x18, 0x19, 0x10,
+        0x13, 0x1F, 0x14, 0x16, 0x2F, 0x11, 0x2D, 0x15, 0x2C,
+    ];
+    let code = match hid {
+        0x04..=0x1D => (LETTERS[(hid - 0x04) as usize], false),
+        // 1..9, 0
+        0x1E..=0x27 => ((hid - 0x1E) as u16 + 0x02, false),
+        0x28 => (0x1C, false), // Enter
+        0x29 => (0x01, false), // Esc
+        0x2A => (0x0E, false), // Backspace
+        0x2B => (0x0F, false), // Tab
+        0x2C => (0x39, false), // Space
+        0x2D => (0x0C, false),
+        0x2E => (0x0D, false),
+        0x2F => (0x1A, false),
+        0x30 => (0x1B, false),
+        0x31 => (0x2B, false),
+        0x33 => (0x27, false),
+        0x34 => (0x28, false),
+        0x35 => (0x29, false),
+        0x36 => (0x33, false),
+        0x37 => (0x34, false),
+        0x38 => (0x35, false),
+        0x39 => (0x3A, false), // CapsLock
+        // F1..F10
+        0x3A..=0x43 => ((hid - 0x3A) as u16 + 0x3B, false),
+        0x44 => (0x57, false), // F11
+        0x45 => (0x58, false), // F12
+        0x46 => (0x37, true),  // PrintScreen
+        0x47 => (0x46, false), // ScrollLock
+        0x49 => (0x52, true),  // Insert
+        0x4A => (0x47, true),  // Home
+        0x4B => (0x49, true),  // PageUp
+        0x4C => (0x53, true),  // Delete
+        0x4D => (0x4F, true),  // End
+        0x4E => (0x51, true),  // PageDown
+        0x4F => (0x4D, true),  // Right
+        0x50 => (0x4B, true),  // Left
+        0x51 => (0x50, true),  // Down
+        0x52 => (0x48, true),  // Up
+        0x53 => (0x45, false), // NumLock
+        0x54 => (0x35, true),  // KP /
+        0x55 => (0x37, false), // KP *
+        0x56 => (0x4A, false), // KP -
+        0x57 => (0x4E, false), // KP +
+        0x58 => (0x1C, true),  // KP Enter
+        0x59 => (0x4F, false), // KP 1
+        0x5A => (0x50, false),
+        0x5B => (0x51, false),
+        0x5C => (0x4B, false),
+        0x5D => (0x4C, false),
+        0x5E => (0x4D, false),
+        0x5F => (0x47, false),
+        0x60 => (0x48, false),
+        0x61 => (0x49, false), // KP 9
+        0x62 => (0x52, false), // KP 0
+        0x63 => (0x53, false), // KP .
+        0x65 => (0x5D, true),  // Apps
+        _ => return None,
+    };
+    Some(code)
+}
+
+/// HID 修饰键字节的各位 (LCtrl, LShift, LAlt, LGui, RCtrl, RShift, RAlt, RGui)
+pub const MODIFIER_SCANCODES: [(u16, bool); 8] = [
+    (0x1D, false),
+    (0x2A, false),
+    (0x38, false),
+    (0x5B, true),
+    (0x1D, true),
+    (0x36, false),
+    (0x38, true),
+    (0x5C, true),
+];
@@ -0,0 +1,300 @@
+// src/learn.rs
+use crate::profile::{HumanProfile, MsRange};
+use crate::recorder::RawEvent;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+// ==========================================
+// 1. 参数
+// ==========================================
+
+/// 相邻两次光标移动间隔超过此值即视为一段新的移动
+const STROKE_GAP_MS: u64 = 80;
+/// 短于此距离的移动 (微调) 不参与轨迹拟合
+const MIN_STROKE_PX: f32 = 50.0;
+/// 某项参数的样本少于此数时保留 normal 档的值
+const MIN_FIT_SAMPLES: usize = 8;
+/// click_in_rect_humanly 等常用调用的名义移动时长，speed 倍率以它为基准
+const REFERENCE_MOVE_SEC: f32 = 0.5;
+/// 经验值：贝塞尔控制点偏移幅度为 spread 时，轨迹偏离弦线的最大距离中位数约为 0.33 * spread
+const DEVIATION_PER_SPREAD: f32 = 0.33;
+/// 沿移动方向超出终点该比例以上才算冲过目标
+const OVERSHOOT_MIN_RATIO: f32 = 0.02;
+/// 按住超过此值的按键 (移动键、长按) 不计入 key_tap_ms
+const MAX_TAP_MS: u64 = 300;
+/// 两次按键间隔超过此值视为打字中断，不计入节奏
+const MAX_TYPING_GAP_MS: u64 = 1000;
+/// 同一键连续两次点击的间隔 (松开到按下) 小于此值视为双击
+const DOUBLE_CLICK_MS: u64 = 500;
+/// 滚轮相邻两格间隔小于此值属于同一次连拨
+const SCROLL_BURST_GAP_MS: u64 = 100;
+/// 两次连拨之间超过此值视为停下看内容，而不是手指回位
+const MAX_REGRIP_MS: u64 = 1000;
+
+// ==========================================
+// 2. 拟合
+// ==========================================
+
+/// 从真实操作中拟合出的参数；None 表示样本不足，沿用 normal 档
+#[derive(Debug, Clone, Default)]
+pub struct LearnedProfile {
+    pub strokes: usize,
+    pub clicks: usize,
+    pub keys: usize,
+    pub wheel_ticks: usize,
+    pub speed: Option<f32>,
+    pub bezier_spread: Option<f32>,
+    pub overshoot_chance: Option<f32>,
+    pub overshoot_ratio: Option<f32>,
+    pub click_hold_ms: Option<MsRange>,
+    pub double_click_gap_ms: Option<MsRange>,
+    pub key_tap_ms: Option<MsRange>,
+    pub typing_variance: Option<f32>,
+    pub scroll_burst_ticks: Option<[u32; 2]>,
+    pub scroll_tick_ms: Option<MsRange>,
+    pub scroll_regrip_ms: Option<MsRange>,
+}
+
+fn sorted(values: &[f64]) -> Vec<f64> {
+    let mut v = values.to_vec();
+    v.sort_by(|a, b| a.total_cmp(b));
+    v
+}
+
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    sorted[((sorted.len() - 1) as f64 * p).round() as usize]
+}
+
+fn median(values: &[f64]) -> Option<f64> {
+    (values.len() >= MIN_FIT_SAMPLES).then(|| percentile(&sorted(values), 0.5))
+}
+
+/// 取 P10 ~ P90 作为档案中的 [最小, 最大] 区间，去掉偶发的极端值
+fn range(values: &[f64]) -> Option<MsRange> {
+    if values.len() < MIN_FIT_SAMPLES {
+        return None;
+    }
+    let v = sorted(values);
+    Some([percentile(&v, 0.1).round() as u64, percentile(&v, 0.9).round() as u64])
+}
+
+/// 一段移动：时长、偏离弦线的最大距离、沿移动方向冲过终点的比例
+struct Stroke {
+    duration_ms: f64,
+    length: f32,
+    deviation: f32,
+    overshoot: f32,
+}
+
+fn fit_stroke(points: &[(u64, f32, f32)]) -> Option<Stroke> {
+    let (&(t0, x0, y0), &(t1, x1, y1)) = (points.first()?, points.last()?);
+    let (dx, dy) = (x1 - x0, y1 - y0);
+    let length = dx.hypot(dy);
+    if points.len() < 5 || length < MIN_STROKE_PX || t1 <= t0 {
+        return None;
+    }
+    let (ux, uy) = (dx / length, dy / length);
+    let (mut deviation, mut furthest) = (0.0f32, 0.0f32);
+    for &(_, x, y) in points {
+        let (px, py) = (x - x0, y - y0);
+        deviation = deviation.max((px * uy - py * ux).abs());
+        furthest = furthest.max(px * ux + py * uy);
+    }
+    Some(Stroke { duration_ms: (t1 - t0) as f64, length, deviation, overshoot: furthest / length - 1.0 })
+}
+
+/// 从 recorder::capture(.., moves = true) 采集的原始事件拟合个人习惯
+pub fn fit(events: &[RawEvent]) -> LearnedProfile {
+    let mut learned = LearnedProfile::default();
+
+    // 轨迹
+    let mut strokes = Vec::new();
+    let mut current: Vec<(u64, f32, f32)> = Vec::new();
+    for e in events {
+        match *e {
+            RawEvent::MouseMove { x, y, t } => {
+                if current.last().is_some_and(|&(last, _, _)| t.saturating_sub(last) > STROKE_GAP_MS) {
+                    strokes.extend(fit_stroke(&current));
+                    current.clear();
+                }
+                current.push((t, x as f32, y as f32));
+            }
+            // 按下鼠标即结束一段移动 (拖拽另算一段)
+            RawEvent::MouseDown { .. } | RawEvent::MouseUp { .. } => {
+                strokes.extend(fit_stroke(&current));
+                current.clear();
+            }
+            _ => {}
+        }
+    }
+    strokes.extend(fit_stroke(&current));
+    learned.strokes = strokes.len();
+
+    let durations: Vec<f64> = strokes.iter().map(|s| s.duration_ms).collect();
+    learned.speed = median(&durations).map(|ms| (ms as f32 / 1000.0 / REFERENCE_MOVE_SEC).clamp(0.3, 3.0));
+    let deviations: Vec<f64> = strokes.iter().map(|s| s.deviation as f64).collect();
+    learned.bezier_spread = median(&deviations).map(|d| (d as f32 / DEVIATION_PER_SPREAD).clamp(0.0, 200.0));
+
+    let min_distance = HumanProfile::default().motion.overshoot_min_distance;
+    let long: Vec<&Stroke> = strokes.iter().filter(|s| s.length >= min_distance).collect();
+    if long.len() >= MIN_FIT_SAMPLES {
+        let over: Vec<f64> = long.iter().filter(|s| s.overshoot > OVERSHOOT_MIN_RATIO).map(|s| s.overshoot as f64).collect();
+        learned.overshoot_chance = Some(over.len() as f32 / long.len() as f32);
+        if !over.is_empty() {
+            learned.overshoot_ratio = Some((percentile(&sorted(&over), 0.9) as f32).min(0.3));
+        }
+    }
+
+    // 点击
+    let (mut holds, mut double_gaps) = (Vec::new(), Vec::new());
+    let mut last_up: Option<(bool, u64)> = None;
+    for (i, e) in events.iter().enumerate() {
+        let RawEvent::MouseDown { right, t, .. } = *e else { continue };
+        if let Some((r, up)) = last_up {
+            if r == right && t.saturating_sub(up) < DOUBLE_CLICK_MS {
+                double_gaps.push(t.saturating_sub(up) as f64);
+            }
+        }
+        let up = events[i + 1..].iter().find_map(|n| match *n {
+            RawEvent::MouseUp { right: r, t } if r == right => Some(t),
+            _ => None,
+        });
+        if let Some(up) = up {
+            holds.push(up.saturating_sub(t) as f64);
+            last_up = Some((right, up));
+        }
+    }
+    learned.clicks = holds.len();
+    learned.click_hold_ms = range(&holds);
+    learned.double_click_gap_ms = range(&double_gaps);
+
+    // 按键
+    let (mut taps, mut gaps) = (Vec::new(), Vec::new());
+    let mut last_press: Option<u64> = None;
+    let mut down: Vec<(u32, u64)> = Vec::new();
+    for e in events {
+        match *e {
+            // 按住时的系统重复 KeyDown 不算新的按键
+            RawEvent::KeyDown { vk, t } if !down.iter().any(|&(v, _)| v == vk) => {
+                down.push((vk, t));
+                if let Some(prev) = last_press.replace(t) {
+                    if t.saturating_sub(prev) <= MAX_TYPING_GAP_MS {
+                        gaps.push(t.saturating_sub(prev) as f64);
+                    }
+                }
+            }
+            RawEvent::KeyUp { vk, t } => {
+                if let Some(i) = down.iter().position(|&(v, _)| v == vk) {
+                    let held = t.saturating_sub(down.swap_remove(i).1);
+                    if held <= MAX_TAP_MS {
+                        taps.push(held as f64);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    learned.keys = taps.len();
+    learned.key_tap_ms = range(&taps);
+    if gaps.len() >= MIN_FIT_SAMPLES {
+        let mean = gaps.iter().sum::<f64>() / gaps.len() as f64;
+        let std = (gaps.iter().map(|g| (g - mean).powi(2)).sum::<f64>() / gaps.len() as f64).sqrt();
+        learned.typing_variance = Some(((std / mean) as f32).clamp(0.05, 1.0));
+    }
+
+    // 滚轮 (钩子每格报告一次)
+    let ticks: Vec<u64> = events
+        .iter()
+        .filter_map(|e| match *e {
+            RawEvent::Wheel { delta, t } => Some(std::iter::repeat_n(t, delta.unsigned_abs() as usize)),
+            _ => None,
+        })
+        .flatten()
+        .collect();
+    learned.wheel_ticks = ticks.len();
+    let (mut tick_gaps, mut regrips, mut bursts) = (Vec::new(), Vec::new(), Vec::new());
+    let mut burst = 1u32;
+    for w in ticks.windows(2) {
+        let gap = w[1] - w[0];
+        if gap < SCROLL_BURST_GAP_MS {
+            tick_gaps.push(gap as f64);
+            burst += 1;
+        } else {
+            bursts.push(burst as f64);
+            burst = 1;
+            if gap <= MAX_REGRIP_MS {
+                regrips.push(gap as f64);
+            }
+        }
+    }
+    if !ticks.is_empty() {
+        bursts.push(burst as f64);
+    }
+    learned.scroll_tick_ms = range(&tick_gaps);
+    learned.scroll_regrip_ms = range(&regrips);
+    learned.scroll_burst_ticks = range(&bursts).map(|[lo, hi]| [lo.max(1) as u32, hi.max(1) as u32]);
+
+    learned
+}
+
+// ==========================================
+// 3. 输出
+// ==========================================
+
+impl LearnedProfile {
+    /// 生成 human_profile.toml 中的一个 [name] 表，只写出拟合成功的字段
+    pub fn to_toml(&self, name: &str) -> String {
+        let mut out = format!(
+            "\n# 由 learn 从真实操作拟合 ({} 段移动 / {} 次点击 / {} 次按键 / {} 格滚轮)\n[{}]\n",
+            self.strokes, self.clicks, self.keys, self.wheel_ticks, name
+        );
+        let ms = |v: MsRange| format!("[{}, {}]", v[0], v[1]);
+        let mut line = |key: &str, value: Option<String>| {
+            if let Some(v) = value {
+                out.push_str(&format!("{} = {}\n", key, v));
+            }
+        };
+        line("speed", self.speed.map(|v| format!("{:.2}", v)));
+        line("click_hold_ms", self.click_hold_ms.map(ms));
+        line("double_click_gap_ms", self.double_click_gap_ms.map(ms));
+        line("key_tap_ms", self.key_tap_ms.map(ms));
+        line("typing_variance", self.typing_variance.map(|v| format!("{:.2}", v)));
+        line("scroll_burst_ticks", self.scroll_burst_ticks.map(|v| format!("[{}, {}]", v[0], v[1])));
+        line("scroll_tick_ms", self.scroll_tick_ms.map(ms));
+        line("scroll_regrip_ms", self.scroll_regrip_ms.map(ms));
+
+        let mut motion = Vec::new();
+        if let Some(s) = self.bezier_spread {
+            motion.push(format!("path = {{ type = \"Bezier\", spread = {:.1} }}", s));
+        }
+        if let Some(c) = self.overshoot_chance {
+            motion.push(format!("overshoot_chance = {:.2}", c));
+        }
+        if let Some(r) = self.overshoot_ratio {
+            motion.push(format!("overshoot_ratio = {:.3}", r));
+        }
+        if !motion.is_empty() {
+            out.push_str(&format!("motion = {{ {} }}\n", motion.join(", ")));
+        }
+        out
+    }
+}
+
+/// 把档案追加到档案文件末尾 (保留原有内容与注释)；已有同名档案时拒绝覆盖
+pub fn save(path: &str, name: &str, learned: &LearnedProfile) -> Result<(), String> {
+    if Path::new(path).exists() && HumanProfile::load(path)?.contains_key(name) {
+        return Err(format!("{} 中已有档案 [{}]，请换一个名字或先删除", path, name));
+    }
+    let block = learned.to_toml(name);
+    // 写入前确认生成的片段能被正常解析
+    toml::from_str::<std::collections::HashMap<String, HumanProfile>>(&block)
+        .map_err(|e| format!("生成的档案无法解析: {}", e))?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("无法写入拟人档案 {}: {}", path, e))?;
+    file.write_all(block.as_bytes()).map_err(|e| format!("无法写入拟人档案 {}: {}", path, e))?;
+    Ok(())
+}
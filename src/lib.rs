@@ -1,7 +1,57 @@
 // src/lib.rs
 
 pub mod hardware;      // 新增：底层驱动
+pub mod actor;         // 驱动线程与句柄
+pub mod ble;           // BLE UART 传输
+pub mod window;        // 窗口查找与坐标
+pub mod clipboard;     // 剪贴板 (中文文本粘贴)
+pub mod capture;       // 截图后端
+pub mod ocr;           // 文字识别后端
+pub mod ocr_cache;     // OCR 结果缓存
+#[cfg(feature = "tesseract")]
+pub mod tesseract;     // Tesseract OCR 后端
+#[cfg(any(feature = "paddle", feature = "detector"))]
+mod onnx;              // ONNX Runtime 运行时加载
+#[cfg(feature = "paddle")]
+pub mod paddle;        // PP-OCR (ONNX Runtime) 后端
+pub mod detector;      // 动态实体目标检测
+#[cfg(feature = "detector")]
+pub mod yolo;          // YOLO (ONNX Runtime) 检测后端
+pub mod config;        // 全局配置
 pub mod human;         // 拟人化层
+pub mod trajectory;    // 移动轨迹生成
+pub mod profile;       // 拟人参数档案
+pub mod fatigue;       // 疲劳与休息调度
+pub mod cancel;        // 拟人动作的暂停 / 中止
+pub mod keymap;        // 键盘布局
 pub mod nav;           // 视觉导航层
+pub mod template;      // 图像模板匹配
+pub mod change;        // 画面变化检测
+pub mod color;         // 像素与区域颜色取样
+pub mod logic;         // 场景锚点组合逻辑表达式
+pub mod params;        // 参数化场景展开
+pub mod numeric;       // 游戏数字解析
+pub mod dump;          // 识别失败现场转储
 pub mod tower_defense; // 业务逻辑层
-pub mod daily_routine; // 日常任务层
\ No newline at end of file
+pub mod daily_routine; // 日常任务层
+pub mod stats;         // 运行统计
+pub mod checkpoint;    // 任务检查点
+pub mod handlers;      // 处理器路由
+pub mod pipeline;      // 声明式流水线
+pub mod recorder;      // 宏录制与回放
+pub mod learn;         // 从真实操作拟合拟人档案
+pub mod trace;         // 驱动指令轨迹
+pub mod audit;         // 拟人输出的统计自检
+pub mod overlay;       // 试运行覆盖层
+pub mod calibration;   // 绝对坐标校准
+pub mod selftest;      // 驱动自检 (--test driver)
+pub mod remote;        // 远程键鼠代理
+pub mod sendinput;     // 窗口定向输入 (PostMessage / SendInput)
+#[cfg(feature = "interception")]
+pub mod interception;  // Interception 内核驱动后端
+#[cfg(feature = "vigem")]
+pub mod gamepad;       // ViGEm 虚拟手柄后端
+pub mod secrets;       // 加密凭据库
+pub mod sync;          // 远程配置同步
+pub mod instances;     // 多开实例调度
+pub mod identity;      // 设备身份轮换
\ No newline at end of file
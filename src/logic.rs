@@ -0,0 +1,237 @@
+// src/logic.rs
+// 场景锚点的组合逻辑：把 logic = "(text.0 AND color.1) OR NOT image.0" 解析为表达式树
+// 锚点按类型分别编号 (text.0 是第一个文字锚点，color.1 是第二个颜色锚点)，NOT 优先级最高，其次 AND，最后 OR
+// 运算符也可以写作 ! / && / ||，关键字不区分大小写
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// 锚点引用：(类型 "text" / "color" / "image", 该类型中的序号)
+    Anchor(&'static str, usize),
+    Not(Box<Expr>),
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Word(String),
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = src.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            '(' => { tokens.push(Token::Open); chars.next(); }
+            ')' => { tokens.push(Token::Close); chars.next(); }
+            '!' => { tokens.push(Token::Word("not".to_string())); chars.next(); }
+            '&' | '|' => {
+                chars.next();
+                if chars.next_if_eq(&c).is_none() {
+                    return Err(format!("无法识别的运算符 '{}' (应为 {}{})", c, c, c));
+                }
+                tokens.push(Token::Word(if c == '&' { "and" } else { "or" }.to_string()));
+            }
+            c if c.is_whitespace() => { chars.next(); }
+            c if c.is_ascii_alphanumeric() || c == '_' || c == '.' => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek().filter(|c| c.is_ascii_alphanumeric() || **c == '_' || **c == '.') {
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+            other => return Err(format!("无法识别的字符 '{}'", other)),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.tokens.get(self.pos), Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword))
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut items = vec![self.and()?];
+        while self.peek_keyword("or") {
+            self.pos += 1;
+            items.push(self.and()?);
+        }
+        Ok(if items.len() == 1 { items.remove(0) } else { Expr::Or(items) })
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut items = vec![self.unary()?];
+        while self.peek_keyword("and") {
+            self.pos += 1;
+            items.push(self.unary()?);
+        }
+        Ok(if items.len() == 1 { items.remove(0) } else { Expr::And(items) })
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.peek_keyword("not") {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        match self.tokens.get(self.pos).cloned() {
+            Some(Token::Open) => {
+                self.pos += 1;
+                let inner = self.or()?;
+                if self.tokens.get(self.pos) != Some(&Token::Close) {
+                    return Err("缺少右括号".to_string());
+                }
+                self.pos += 1;
+                Ok(inner)
+            }
+            Some(Token::Word(w)) => {
+                self.pos += 1;
+                anchor_ref(&w)
+            }
+            Some(Token::Close) => Err("多余的右括号".to_string()),
+            None => Err("表达式不完整".to_string()),
+        }
+    }
+}
+
+/// 解析 "text.0" 形式的锚点引用
+fn anchor_ref(word: &str) -> Result<Expr, String> {
+    let (kind, index) = word.split_once('.').ok_or_else(|| format!("无效的锚点引用 \"{}\" (应为 text.0 / color.0 / image.0)", word))?;
+    let kind = match kind.to_lowercase().as_str() {
+        "text" => "text",
+        "color" => "color",
+        "image" => "image",
+        _ => return Err(format!("未知的锚点类型 \"{}\"", kind)),
+    };
+    let index = index.parse().map_err(|_| format!("无效的锚点序号 \"{}\"", word))?;
+    Ok(Expr::Anchor(kind, index))
+}
+
+impl Expr {
+    pub fn parse(src: &str) -> Result<Self, String> {
+        let mut parser = Parser { tokens: tokenize(src)?, pos: 0 };
+        let expr = parser.or()?;
+        if parser.pos < parser.tokens.len() {
+            return Err(format!("表达式在第 {} 个记号处多出内容", parser.pos + 1));
+        }
+        Ok(expr)
+    }
+
+    /// 部分锚点尚未检查时求值：lookup 返回 None 表示未知；结果已能确定 (如 AND 中已有一项不命中) 时返回 Some
+    pub fn eval_partial(&self, lookup: &impl Fn(&str, usize) -> Option<bool>) -> Option<bool> {
+        // decisive 为能直接确定结果的取值：AND 遇到 false、OR 遇到 true
+        let combine = |items: &[Expr], decisive: bool| {
+            let mut unknown = false;
+            for e in items {
+                match e.eval_partial(lookup) {
+                    Some(v) if v == decisive => return Some(decisive),
+                    Some(_) => {}
+                    None => unknown = true,
+                }
+            }
+            (!unknown).then_some(!decisive)
+        };
+        match self {
+            Expr::Anchor(kind, index) => lookup(kind, *index),
+            Expr::Not(inner) => inner.eval_partial(lookup).map(|v| !v),
+            Expr::And(items) => combine(items, false),
+            Expr::Or(items) => combine(items, true),
+        }
+    }
+
+    /// 表达式引用的全部锚点，用于载入地图时检查序号是否越界
+    pub fn anchors(&self) -> Vec<(&'static str, usize)> {
+        match self {
+            Expr::Anchor(kind, index) => vec![(*kind, *index)],
+            Expr::Not(inner) => inner.anchors(),
+            Expr::And(items) | Expr::Or(items) => items.iter().flat_map(Expr::anchors).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 命中的锚点集合中查找；不在 known 中的锚点视为尚未检查
+    fn eval(src: &str, hits: &[(&str, usize)], known: &[(&str, usize)]) -> Option<bool> {
+        let expr = Expr::parse(src).unwrap();
+        expr.eval_partial(&|kind, index| known.contains(&(kind, index)).then(|| hits.contains(&(kind, index))))
+    }
+
+    const ALL: [(&str, usize); 4] = [("text", 0), ("text", 1), ("color", 0), ("image", 0)];
+
+    #[test]
+    fn precedence() {
+        // NOT > AND > OR
+        assert_eq!(
+            Expr::parse("text.0 OR text.1 AND NOT color.0").unwrap(),
+            Expr::Or(vec![
+                Expr::Anchor("text", 0),
+                Expr::And(vec![Expr::Anchor("text", 1), Expr::Not(Box::new(Expr::Anchor("color", 0)))]),
+            ])
+        );
+        assert_eq!(eval("text.0 OR text.1 AND color.0", &[("text", 0)], &ALL), Some(true));
+        assert_eq!(eval("text.1 AND color.0 OR text.0", &[("text", 1)], &ALL), Some(false));
+        assert_eq!(eval("NOT text.0 AND color.0", &[("color", 0)], &ALL), Some(true));
+    }
+
+    #[test]
+    fn symbolic_operators() {
+        assert_eq!(Expr::parse("!text.0 && color.0 || image.0"), Expr::parse("NOT text.0 AND color.0 OR image.0"));
+        assert_eq!(Expr::parse("text.0 and not color.0"), Expr::parse("text.0 AND NOT color.0"));
+        assert_eq!(eval("!!text.0", &[("text", 0)], &ALL), Some(true));
+        assert_eq!(eval("text.0 && !color.0", &[("text", 0), ("color", 0)], &ALL), Some(false));
+        assert_eq!(eval("text.1 || image.0", &[("image", 0)], &ALL), Some(true));
+    }
+
+    #[test]
+    fn parentheses() {
+        assert_eq!(
+            Expr::parse("(text.0 OR text.1) AND color.0").unwrap(),
+            Expr::And(vec![Expr::Or(vec![Expr::Anchor("text", 0), Expr::Anchor("text", 1)]), Expr::Anchor("color", 0)])
+        );
+        assert_eq!(eval("(text.0 OR text.1) AND color.0", &[("text", 1)], &ALL), Some(false));
+        assert_eq!(eval("(text.0 OR text.1) AND color.0", &[("text", 1), ("color", 0)], &ALL), Some(true));
+        assert_eq!(eval("NOT (text.0 AND color.0)", &[("text", 0)], &ALL), Some(true));
+        assert_eq!(Expr::parse("((text.0))").unwrap(), Expr::Anchor("text", 0));
+    }
+
+    #[test]
+    fn partial_evaluation() {
+        // 已能确定结果时不需要等待其余锚点
+        assert_eq!(eval("text.0 AND color.0", &[], &[("text", 0)]), Some(false));
+        assert_eq!(eval("text.0 OR color.0", &[("text", 0)], &[("text", 0)]), Some(true));
+        assert_eq!(eval("text.0 AND color.0", &[("text", 0)], &[("text", 0)]), None);
+        assert_eq!(eval("NOT color.0", &[], &[]), None);
+    }
+
+    #[test]
+    fn unknown_identifiers() {
+        assert!(Expr::parse("button.0").unwrap_err().contains("未知的锚点类型"));
+        assert!(Expr::parse("text").unwrap_err().contains("无效的锚点引用"));
+        assert!(Expr::parse("text.x").unwrap_err().contains("无效的锚点序号"));
+        assert!(Expr::parse("text.0 XOR color.0").unwrap_err().contains("多出内容"));
+        assert_eq!(Expr::parse("TEXT.2 or Color.1").unwrap().anchors(), vec![("text", 2), ("color", 1)]);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(Expr::parse("").unwrap_err().contains("表达式不完整"));
+        assert!(Expr::parse("text.0 AND").unwrap_err().contains("表达式不完整"));
+        assert!(Expr::parse("(text.0 OR color.0").unwrap_err().contains("缺少右括号"));
+        assert!(Expr::parse("text.0)").unwrap_err().contains("多出内容"));
+        assert!(Expr::parse(")").unwrap_err().contains("多余的右括号"));
+        assert!(Expr::parse("text.0 & color.0").unwrap_err().contains("无法识别的运算符"));
+        assert!(Expr::parse("text.0 | color.0").unwrap_err().contains("无法识别的运算符"));
+        assert!(Expr::parse("text.0 + color.0").unwrap_err().contains("无法识别的字符"));
+    }
+}
@@ -1,11 +1,34 @@
 // src/main.rs
-use clap::Parser;
-use nzm_cmd::daily_routine::DailyRoutineApp;
-use nzm_cmd::hardware::{create_driver, DriverType, InputDriver};
-use nzm_cmd::human::HumanDriver;
-use nzm_cmd::nav::{NavEngine, NavResult};
-use nzm_cmd::tower_defense::TowerDefenseApp;
-use screenshots::Screen;
+use clap::{Parser, Subcommand};
+use nzm_cmd::actor::{self, DriverHandle, PrimaryDriver};
+use nzm_cmd::audit;
+use nzm_cmd::calibration;
+use nzm_cmd::cancel;
+use nzm_cmd::capture;
+use nzm_cmd::config::AppConfig;
+use nzm_cmd::detector;
+use nzm_cmd::fatigue::BreakEvent;
+use nzm_cmd::handlers;
+use nzm_cmd::hardware::{
+    self, create_driver, DriverError, DriverEvent, DriverEventCallback, DriverType, HardwareConfig, InputDriver,
+    LogDriver, MirrorDriver, MouseButtons, ThrottleDriver,
+};
+use nzm_cmd::human::{CursorSync, HumanDriver};
+use nzm_cmd::identity::IdentityManager;
+use nzm_cmd::learn;
+use nzm_cmd::keymap::{self, KeyboardLayout, Modifier};
+use nzm_cmd::instances::InstanceManager;
+use nzm_cmd::nav::NavEngine;
+use nzm_cmd::ocr;
+use nzm_cmd::pipeline::PipelineEngine;
+use nzm_cmd::profile::HumanProfile;
+use nzm_cmd::recorder;
+use nzm_cmd::selftest;
+use nzm_cmd::secrets::{SecretStore, DEFAULT_SECRETS_FILE};
+use nzm_cmd::stats::{self, GroupBy, StatsStore};
+use nzm_cmd::sync;
+use nzm_cmd::trace::{RecordingDriver, ReplayDriver};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
@@ -16,201 +39,832 @@ struct Args {
     #[arg(short, long, default_value = "COM3")]
     port: String,
 
+    /// 自制固件串口波特率 (覆盖 config.toml 的 [hardware] baud_rate)
+    #[arg(long)]
+    baud: Option<u32>,
+
+    /// 两帧之间的最小间隔 (ms)，0 表示不限速 (覆盖 [hardware] frame_pacing_ms)
+    #[arg(long)]
+    frame_pacing: Option<u64>,
+
     #[arg(short, long, default_value = "空间站普通")]
     target: String,
 
     #[arg(long)]
     test: Option<String>,
+
+    /// 执行流水线 (对应 pipelines.toml 中的 name)，执行完毕后退出
+    #[arg(long)]
+    pipeline: Option<String>,
+
+    #[arg(long, default_value = "pipelines.toml")]
+    pipelines_file: String,
+
+    /// 按 config.toml 的 [[instances]] 同时驱动多个游戏窗口
+    #[arg(long)]
+    instances: bool,
+
+    /// 把所有指令同时镜像到第二个驱动 (调试固件用)：log 表示仅打印，其余写法同 --port
+    #[arg(long)]
+    mirror: Option<String>,
+
+    /// 把发往驱动的每条指令写入 JSONL 轨迹文件 (可用 replay 子命令复现)
+    #[arg(long)]
+    trace: Option<String>,
+
+    /// 拟人化随机种子 (覆盖 [human] seed)；用启动时打印的种子重跑可复现相同的轨迹与延迟
+    #[arg(long)]
+    seed: Option<u64>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// 查询运行统计 (例如: stats --since 7d --by map)
+    Stats {
+        /// 时间跨度: 7d / 12h / 30m，缺省为全部记录
+        #[arg(long)]
+        since: Option<String>,
+        /// 分组维度: map / handler / day
+        #[arg(long, default_value = "map")]
+        by: String,
+        /// 导出格式 (目前支持 csv)
+        #[arg(long)]
+        export: Option<String>,
+        /// 导出文件路径
+        #[arg(short, long, default_value = "stats_export.csv")]
+        output: String,
+    },
+    /// 录制真实键鼠操作并生成可回放的宏脚本 (按 F12 提前结束)
+    Record {
+        /// 录制时长 (秒)
+        #[arg(short, long, default_value_t = 60)]
+        duration: u64,
+        #[arg(short, long, default_value = "macro.json")]
+        output: String,
+        /// 同时保存为驱动指令轨迹 (JSONL，可用 replay 按原始节奏回放)
+        #[arg(long)]
+        trace: Option<String>,
+    },
+    /// 管理加密保存的游戏账号 (DPAPI)
+    Accounts {
+        #[command(subcommand)]
+        action: AccountAction,
+    },
+    /// 从远端拉取地图/策略配置 (见 config.toml 的 [sync])
+    Sync {
+        /// 回滚到上一次同步前的备份
+        #[arg(long)]
+        rollback: bool,
+    },
+    /// 录制真实的鼠标键盘操作 (按 F12 提前结束)，拟合轨迹、点击、按键与滚轮习惯，追加为个人拟人档案
+    Learn {
+        /// 录制时长 (秒)
+        #[arg(short, long, default_value_t = 300)]
+        duration: u64,
+        /// 保存的档案名 (写入 [human] profile_file)
+        #[arg(short, long, default_value = "learned")]
+        name: String,
+    },
+    /// 检查地图文件 (跳转目标、重复 id、坐标越界、处理器等)，有错误时以非零状态退出
+    Validate {
+        #[arg(default_value = "ui_map.toml")]
+        map: String,
+    },
+    /// 把地图的界面 / 跳转关系导出为 Graphviz DOT，用于检查可达性与死路
+    Graph {
+        #[arg(default_value = "ui_map.toml")]
+        map: String,
+        /// 输出文件
+        #[arg(short, long, default_value = "ui_map.dot")]
+        out: String,
+    },
+    /// 回放宏脚本 (使用 -p 指定的驱动)
+    Play {
+        file: String,
+    },
+    /// 校准自制固件的绝对坐标 (移动到已知点并读回系统光标位置)，结果按分辨率保存到状态库
+    Calibrate,
+    /// 按原始时间间隔回放 --trace 录下的驱动指令轨迹 (使用 -p 指定的驱动)
+    Replay {
+        file: String,
+        /// 回放倍速
+        #[arg(long, default_value_t = 1.0)]
+        speed: f32,
+    },
+    /// 统计拟人输出的分布 (点击间隔、轨迹弯曲度、速度曲线、按住时长)，标出过于规律的指标
+    /// 指定 --trace 录下的轨迹文件则分析该文件，否则用当前档案在内存中模拟一段操作 (不发送真实输入)
+    Audit {
+        file: Option<String>,
+        /// 模拟的轮数 (每轮一次点击，间或输入文字与滚动)
+        #[arg(long, default_value_t = 40)]
+        rounds: usize,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum AccountAction {
+    /// 添加或覆盖账号 (密码从终端读取，不回显)
+    Add {
+        name: String,
+        #[arg(short, long)]
+        username: String,
+    },
+    /// 删除账号
+    Remove { name: String },
+    /// 列出已保存的账号
+    List,
+}
+
+const STATS_DB: &str = stats::DEFAULT_STATE_DB;
+const CONFIG_FILE: &str = "config.toml";
+
 fn main() {
     let args = Args::parse();
 
+    match &args.command {
+        Some(Command::Stats { since, by, export, output }) => {
+            run_stats_command(since.as_deref(), by, export.as_deref(), output);
+            return;
+        }
+        Some(Command::Accounts { action }) => {
+            run_accounts_command(action);
+            return;
+        }
+        Some(Command::Sync { rollback }) => {
+            run_sync_command(&AppConfig::load(CONFIG_FILE), *rollback);
+            return;
+        }
+        Some(Command::Record { duration, output, trace }) => {
+            run_record_command(*duration, output, trace.as_deref());
+            return;
+        }
+        Some(Command::Learn { duration, name }) => {
+            run_learn_command(&AppConfig::load(CONFIG_FILE), *duration, name);
+            return;
+        }
+        Some(Command::Validate { map }) => {
+            if !run_validate_command(map) {
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Command::Graph { map, out }) => {
+            match NavEngine::export_map_graph(map, out) {
+                Ok(n) => println!("✅ 已导出 {} 个界面到 {} (dot -Tsvg {} -o ui_map.svg)", n, out, out),
+                Err(e) => {
+                    println!("❌ {}", e);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        _ => {}
+    }
+
     println!("========================================");
     println!("🚀 NZM_CMD 智能控制中心");
     println!("📍 端口: {}", args.port);
     if let Some(t) = &args.test {
         println!("🔧 模式: 测试 ({})", t);
+    } else if let Some(p) = &args.pipeline {
+        println!("🧵 流水线: {}", p);
+    } else if args.instances {
+        println!("🪟 模式: 多开");
     } else {
         println!("🎯 目标: {}", args.target);
     }
     println!("========================================");
 
     let (sw, sh) = (1920, 1080);
+    let mut app_config = AppConfig::load(CONFIG_FILE);
+    let seed = args.seed.or(app_config.human.seed).unwrap_or_else(rand::random);
+    app_config.human.seed = Some(seed);
+    println!("🎲 随机种子: {} (复现本次运行: --seed {})", seed, seed);
+    keymap::set_paste_text(app_config.keyboard.paste_text);
+    match KeyboardLayout::from_config(&app_config.keyboard) {
+        Ok(layout) => {
+            println!("⌨️ 键盘布局: {}", layout.name);
+            keymap::init(layout);
+        }
+        Err(e) => println!("⚠️ {}，使用 US 布局", e),
+    }
 
-    let driver_type = if args.port.to_uppercase() == "SOFT" {
-        DriverType::Software
-    } else {
-        DriverType::Hardware
-    };
+    if let Some(Command::Audit { file, rounds }) = &args.command {
+        run_audit_command(&app_config, file.as_deref(), *rounds, seed, sw, sh);
+        return;
+    }
+
+    if app_config.sync.enabled {
+        run_sync_command(&app_config, false);
+    }
+
+    let mut hw_config = app_config.hardware.clone();
+    if let Some(baud) = args.baud {
+        hw_config.baud_rate = baud;
+    }
+    if let Some(pacing) = args.frame_pacing {
+        hw_config.frame_pacing_ms = pacing;
+    }
+    if args.test.as_deref() == Some("latency") {
+        hw_config.metrics = true;
+    }
+    // 必须在打开驱动之前探测，否则 --port 指定的串口已被占用
+    if args.test.as_deref() == Some("ports") {
+        run_list_ports(hw_config.baud_rate);
+        return;
+    }
+    // 校准时必须使用未校正的原始映射
+    let calibrating = matches!(args.command, Some(Command::Calibrate));
+    if calibrating {
+        hw_config.calibration = None;
+    } else if hw_config.calibration.is_none() {
+        match calibration::load(STATS_DB, sw, sh) {
+            Ok(Some(cal)) => {
+                println!("🎯 已加载 {}x{} 的绝对坐标校准", sw, sh);
+                hw_config.calibration = Some(cal);
+            }
+            Ok(None) => {}
+            Err(e) => println!("⚠️ {}", e),
+        }
+    }
 
-    let driver_box: Box<dyn InputDriver> = match create_driver(driver_type, &args.port, sw, sh) {
+    let (driver_type, port) = resolve_port(&args.port, &hw_config);
+
+    let mut driver_box: Box<dyn InputDriver> = match create_driver(driver_type, &port, &hw_config, sw, sh) {
         Ok(d) => d,
         Err(e) => {
             println!("⚠️ 警告: 无法初始化驱动 ({})", e);
             println!("⚠️ 尝试回退到 [软件模拟模式]...");
-            create_driver(DriverType::Software, "", sw, sh).unwrap()
+            create_driver(DriverType::Software, "", &hw_config, sw, sh).unwrap()
+        }
+    };
+
+    // 启动前检查固件版本，避免运行到一半才因协议不符而失败
+    let driver_info = driver_box.info();
+    println!("🔧 驱动: {}", driver_info);
+    println!("🔧 驱动能力: {:?}", driver_box.capabilities());
+    if let Err(e) = driver_info.check_compatible() {
+        println!("❌ {}", e);
+        return;
+    }
+
+    if let Some(spec) = &args.mirror {
+        driver_box = match create_mirror(spec, &hw_config, sw, sh) {
+            Ok(secondary) => {
+                println!("🪞 指令镜像已开启 -> {}", spec);
+                Box::new(MirrorDriver::new(driver_box, secondary))
+            }
+            Err(e) => {
+                println!("⚠️ 无法初始化镜像驱动 ({})，仅使用主驱动", e);
+                driver_box
+            }
+        };
+    }
+
+    if app_config.throttle.enabled {
+        let t = &app_config.throttle;
+        println!("⏱️ 驱动限速已开启: 每秒最多 {} 条，指令间隔 {}~{}us", t.max_per_sec, t.jitter_min_us, t.jitter_max_us);
+        driver_box = Box::new(ThrottleDriver::new(driver_box, t));
+    }
+
+    if let Some(path) = &args.trace {
+        driver_box = match RecordingDriver::new(driver_box, path) {
+            Ok(recording) => {
+                println!("📼 指令轨迹写入 -> {}", path);
+                Box::new(recording)
+            }
+            Err(e) => {
+                println!("⚠️ {}", e);
+                return;
+            }
+        };
+    }
+
+    // 驱动交给独立线程，之后各模块都通过句柄投递指令
+    let mut device = match DriverHandle::spawn(driver_box) {
+        Ok(d) => d,
+        Err(e) => {
+            println!("❌ {}", e);
+            return;
         }
     };
+    // panic 或 Ctrl+C 退出前松开仍按住的键 (例如 key_hold 途中)
+    actor::install_release_hooks();
 
-    let driver_arc: Arc<Mutex<Box<dyn InputDriver>>> = Arc::new(Mutex::new(driver_box));
+    // 设备断开时暂停导航，重连后继续 (回调挂在驱动线程上，热切换后端后依然有效)
+    let driver_online = Arc::new(AtomicBool::new(true));
+    device.set_event_callback(online_callback(Arc::clone(&driver_online)));
 
-    let hb = Arc::clone(&driver_arc);
-    thread::spawn(move || loop {
-        if let Ok(mut d) = hb.lock() {
-            d.heartbeat();
+    // 心跳连续失败同样视为离线，主循环暂停而不是继续盲点
+    if let Err(e) = hardware::spawn_heartbeat(
+        Box::new(device.clone()),
+        &app_config.heartbeat,
+        online_callback(Arc::clone(&driver_online)),
+    ) {
+        println!("⚠️ {}", e);
+    }
+
+    if !calibrating {
+        let primary = PrimaryDriver { driver_type, port: port.clone(), config: hw_config.clone(), screen_w: sw, screen_h: sh };
+        if let Err(e) = actor::spawn_failover(device.clone(), &app_config.failover, primary, Arc::clone(&driver_online)) {
+            println!("⚠️ 故障切换未启用: {}", e);
         }
-        thread::sleep(Duration::from_secs(1));
-    });
+    }
 
-    let human_driver = Arc::new(Mutex::new(HumanDriver::new(
-        Arc::clone(&driver_arc),
-        sw / 2,
-        sh / 2,
-    )));
+    if calibrating {
+        run_calibration(&mut device.clone(), driver_type, sw, sh);
+        return;
+    }
 
-    let engine = Arc::new(NavEngine::new("ui_map.toml", Arc::clone(&human_driver)));
+    // 多开模式下每个实例使用 [[instances]] 中固定的身份，不做轮换
+    if app_config.identity.enabled && !args.instances {
+        match IdentityManager::new(&app_config.identity, device.clone()) {
+            Ok(mut manager) => {
+                manager.on_start();
+                if manager.needs_background() {
+                    manager.spawn();
+                }
+            }
+            Err(e) => println!("⚠️ 身份轮换未启用: {}", e),
+        }
+    }
+
+    // 所有 HumanDriver (含多开实例) 默认共用 cancel::global()，一个热键即可全部暂停
+    let pause_key = app_config.human.pause_hotkey.trim();
+    if !pause_key.is_empty() {
+        match cancel::spawn_pause_hotkey(cancel::global(), pause_key) {
+            Ok(()) => println!("⏸️ 按 {} 暂停 / 继续所有拟人动作", pause_key),
+            Err(e) => println!("⚠️ {}", e),
+        }
+    }
+
+    if args.instances {
+        match InstanceManager::new(&app_config.instances, device.clone(), &app_config.capture, &app_config.ocr, &app_config.human, sw, sh) {
+            Ok(manager) => {
+                let manager = manager
+                    .with_failure_dump(&app_config.debug_dump)
+                    .with_detector(detector::create_detector(&app_config.detector))
+                    .with_online_flag(Arc::clone(&driver_online));
+                println!("✅ 多开就绪，5秒后启动 {} 个实例...", app_config.instances.len());
+                thread::sleep(Duration::from_secs(5));
+                manager.run(STATS_DB);
+            }
+            Err(e) => println!("❌ {}", e),
+        }
+        return;
+    }
+
+    let (capture_backend, origin) = capture::create_target(&app_config.capture);
+    let mut builder = HumanDriver::builder(device.clone())
+        .start(sw / 2, sh / 2)
+        .seed(seed)
+        .origin(origin)
+        .break_listener(Box::new(|event| match event {
+            BreakEvent::Started { duration } => println!("☕ [疲劳] 休息中，{} 分 {} 秒后继续...", duration.as_secs() / 60, duration.as_secs() % 60),
+            BreakEvent::Ended => println!("☕ [疲劳] 休息结束，继续运行"),
+        }));
+    match HumanProfile::from_config(&app_config.human, None) {
+        Ok(profile) => builder = builder.profile(profile),
+        Err(e) => println!("⚠️ {}，使用 normal 档案", e),
+    }
+    match CursorSync::parse(&app_config.human.cursor_sync) {
+        Ok(mode) => builder = builder.cursor_sync(mode),
+        Err(e) => println!("⚠️ {}，使用 auto", e),
+    }
+    let human = builder.build();
+    let human_driver = Arc::new(Mutex::new(human));
+
+    let engine = match NavEngine::try_with_backends(
+        "ui_map.toml",
+        Arc::clone(&human_driver),
+        capture_backend,
+        ocr::create_backend(&app_config.ocr),
+    ) {
+        Ok(engine) => Arc::new(engine),
+        Err(e) => {
+            println!("❌ {}", e);
+            return;
+        }
+    };
+    NavEngine::watch(&engine);
+    engine.set_failure_dump(app_config.debug_dump.clone());
+    engine.set_detector(detector::create_detector(&app_config.detector));
+
+    if let Some(Command::Play { file }) = &args.command {
+        match recorder::load_script(file) {
+            Ok(actions) => {
+                println!("⏳ 5秒后开始回放 [{}]...", file);
+                thread::sleep(Duration::from_secs(5));
+                if let Err(e) = recorder::play_script(&human_driver, &actions) {
+                    println!("❌ [Recorder] 回放中止: {}", e);
+                }
+            }
+            Err(e) => println!("❌ {}", e),
+        }
+        return;
+    }
+
+    if let Some(Command::Replay { file, speed }) = &args.command {
+        match ReplayDriver::load(file) {
+            Ok(replay) => {
+                println!(
+                    "⏳ 5秒后开始回放轨迹 [{}] ({} 条指令，录制于 {}，{}x)...",
+                    file,
+                    replay.len(),
+                    replay.started_at().unwrap_or("未知"),
+                    speed
+                );
+                thread::sleep(Duration::from_secs(5));
+                match replay.run(&mut device.clone(), *speed) {
+                    Ok(()) => println!("✅ [Trace] 轨迹回放完成"),
+                    Err(e) => println!("❌ [Trace] 回放中止: {}", e),
+                }
+            }
+            Err(e) => println!("❌ {}", e),
+        }
+        return;
+    }
 
     if let Some(mode) = args.test.as_deref() {
         println!("⏳ 5秒后开始执行 [{}] 测试...", mode);
         thread::sleep(Duration::from_secs(5));
         match mode {
             "input" => run_input_test(human_driver),
-            "screen" => run_screen_test(),
+            "screen" => run_screen_test(engine, sw, sh),
             "ocr" => run_ocr_test(engine),
             "scroll" => run_scroll_test(human_driver),
             "combo" => run_combo_test(human_driver), // ✨ 新增这一行
+            "latency" => run_latency_test(human_driver, &device),
+            "driver" => run_driver_test(&device, sw, sh),
             _ => println!("❌ 未知测试模式"),
         }
         return;
     }
 
+    let stats_store = match StatsStore::open(STATS_DB) {
+        Ok(s) => Some(s),
+        Err(e) => {
+            println!("⚠️ 警告: 统计功能不可用 ({})", e);
+            None
+        }
+    };
+
+    if let Some(name) = args.pipeline.as_deref() {
+        let pipelines = match PipelineEngine::load(
+            &args.pipelines_file,
+            Arc::clone(&human_driver),
+            Arc::clone(&engine),
+        ) {
+            Ok(p) => p,
+            Err(e) => {
+                println!("❌ {}", e);
+                return;
+            }
+        };
+        println!("✅ 引擎就绪，5秒后开始执行流水线 [{}]...", name);
+        thread::sleep(Duration::from_secs(5));
+        if let Err(e) = pipelines.run(name, stats_store.as_ref()) {
+            println!("❌ [Pipeline] 中止: {}", e);
+            println!("   可用流水线: {:?}", pipelines.names());
+        }
+        return;
+    }
+
     println!("✅ 引擎就绪，5秒后开始自动化循环...");
     thread::sleep(Duration::from_secs(5));
 
-    loop {
-        println!("\n🔄 [主控] 正在导航至: {}...", args.target);
-
-        let nav_result = engine.navigate(&args.target);
-
-        match nav_result {
-            NavResult::Handover(scene_id, handler_opt) => {
-                println!("⚔️ [主控] 导航成功: [{}]", scene_id);
-
-                let handler_key = handler_opt.as_deref().unwrap_or("td");
-
-                match handler_key {
-                    "daily" => {
-                        println!("📅 [路由] 检测到 'daily' 标记，启动日活模块...");
-                        let app =
-                            DailyRoutineApp::new(Arc::clone(&human_driver), Arc::clone(&engine));
-                        app.run();
-                    }
-                    "td" | _ => {
-                        println!("🏰 [路由] 启动塔防模块 (Handler: {})...", handler_key);
-                        let mut td_app =
-                            TowerDefenseApp::new(Arc::clone(&human_driver), Arc::clone(&engine));
-
-                        let map_file = format!("{}地图.json", scene_id);
-                        let strategy_file = format!("{}策略.json", scene_id);
-                        let traps_file = "traps_config.json";
-
-                        println!("📂 加载配置: {} | {}", map_file, strategy_file);
-                        td_app.run(&map_file, &strategy_file, traps_file);
-                    }
-                }
+    handlers::run_loop("主控", &args.target, &human_driver, &engine, stats_store.as_ref(), || {
+        while !driver_online.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_secs(1));
+        }
+        true
+    });
+}
 
-                println!("🎉 本局任务结束，5秒后重新开始循环...");
-                thread::sleep(Duration::from_secs(5));
+/// 分析轨迹文件的拟人输出分布；未指定文件时用当前档案在内存中模拟 rounds 轮操作
+fn run_audit_command(app_config: &AppConfig, file: Option<&str>, rounds: usize, seed: u64, sw: u16, sh: u16) {
+    let entries = match file {
+        Some(path) => match ReplayDriver::load(path) {
+            Ok(replay) => {
+                println!("🔍 [Audit] 分析轨迹 [{}] ({} 条指令)", path, replay.len());
+                replay.entries().to_vec()
             }
+            Err(e) => return println!("❌ {}", e),
+        },
+        None => {
+            let profile = HumanProfile::from_config(&app_config.human, None).unwrap_or_else(|e| {
+                println!("⚠️ {}，使用 normal 档案", e);
+                HumanProfile::default()
+            });
+            println!("🔍 [Audit] 以当前档案模拟 {} 轮操作 (约 {} 秒，不发送真实输入)...", rounds, rounds * 2);
+            match audit::simulate(profile, seed, rounds, sw, sh) {
+                Ok(entries) => entries,
+                Err(e) => return println!("❌ [Audit] {}", e),
+            }
+        }
+    };
+    println!("{}", audit::analyze(&entries));
+}
 
-            NavResult::Failed => {
-                println!("❌ [主控] 导航失败，执行重置操作 (ESC)...");
-
-                if let Ok(mut human) = human_driver.lock() {
-                    human.key_hold('\u{1B}', 100);
-
-                    if let Ok(mut dev) = human.device.lock() {
-                        dev.key_down(0x29, 0);
-                    }
-                    thread::sleep(Duration::from_millis(100));
-                    if let Ok(mut dev) = human.device.lock() {
-                        dev.key_up();
-                    }
-
-                    thread::sleep(Duration::from_millis(100));
-                    if let Ok(mut dev) = human.device.lock() {
-                        dev.key_down(0x2C, 0); // 空格键扫描码
-                    }
-                    thread::sleep(Duration::from_millis(100));
-                    if let Ok(mut dev) = human.device.lock() {
-                        dev.key_up(); 
-                    }
-                }
+/// 枚举串口并逐个握手，列出应答本项目固件的端口
+fn run_list_ports(baud_rate: u32) {
+    println!("🔌 正在枚举串口并握手 (波特率 {})...", baud_rate);
+    match hardware::list_ports(baud_rate, true) {
+        Ok(ports) if ports.is_empty() => println!("⚠️ 未发现任何串口设备"),
+        Ok(ports) => {
+            for p in &ports {
+                println!("   {}", p);
+            }
+            let found = ports.iter().filter(|p| p.responds.is_some()).count();
+            println!("✅ 共 {} 个串口，{} 个应答了握手", ports.len(), found);
+        }
+        Err(e) => println!("❌ {}", e),
+    }
+}
 
-                println!("⏳ 等待界面重置 (3秒)...");
-                thread::sleep(Duration::from_secs(3));
+/// 设备离线 / 重连时切换 online 标志 (驱动事件与心跳共用)
+fn online_callback(online: Arc<AtomicBool>) -> DriverEventCallback {
+    Box::new(move |event| match event {
+        DriverEvent::Disconnected(reason) => {
+            println!("⏸️ [主控] 设备离线 ({})，暂停自动化，等待重连...", reason);
+            online.store(false, Ordering::SeqCst);
+        }
+        DriverEvent::Reconnected => {
+            println!("▶️ [主控] 设备已重连，继续自动化");
+            online.store(true, Ordering::SeqCst);
+        }
+    })
+}
+
+/// 创建 --mirror 指定的副驱动: log 为仅打印，其余写法同 --port
+fn create_mirror(spec: &str, hw_config: &HardwareConfig, sw: u16, sh: u16) -> Result<Box<dyn InputDriver>, String> {
+    if spec.eq_ignore_ascii_case("log") {
+        return Ok(Box::new(LogDriver::new()));
+    }
+    let (driver_type, port) = resolve_port(spec, hw_config);
+    create_driver(driver_type, &port, hw_config, sw, sh)
+}
+
+/// 根据 --port 的写法选择驱动: auto / SOFT / DRYRUN / interception / gamepad[:WxH] / remote:host:port[@TOKEN] / ip:port[@UUID] / ble:NAME / window:TITLE[#N][@focus] / ch9329:COMx / COMx
+fn resolve_port(port: &str, hw_config: &HardwareConfig) -> (DriverType, String) {
+    if port.eq_ignore_ascii_case("auto") {
+        return match hardware::detect_port(hw_config.baud_rate) {
+            Ok(found) => found,
+            Err(e) => {
+                println!("⚠️ 自动探测串口失败: {}", e);
+                (DriverType::Software, String::new())
             }
+        };
+    }
+    if port.eq_ignore_ascii_case("SOFT") {
+        (DriverType::Software, port.to_string())
+    } else if port.eq_ignore_ascii_case("DRYRUN") {
+        (DriverType::DryRun, port.to_string())
+    } else if port.eq_ignore_ascii_case("interception") {
+        (DriverType::Interception, port.to_string())
+    } else if port.eq_ignore_ascii_case("gamepad") {
+        (DriverType::Gamepad, String::new())
+    } else if let Some(rest) = port.strip_prefix("gamepad:").or_else(|| port.strip_prefix("GAMEPAD:")) {
+        (DriverType::Gamepad, rest.to_string())
+    } else if let Some(rest) = port.strip_prefix("remote:").or_else(|| port.strip_prefix("REMOTE:")) {
+        (DriverType::Remote, rest.to_string())
+    } else if let Some(rest) = port.strip_prefix("window:").or_else(|| port.strip_prefix("WINDOW:")) {
+        (DriverType::Window, rest.to_string())
+    } else if let Some(rest) = port.strip_prefix("ble:").or_else(|| port.strip_prefix("BLE:")) {
+        (DriverType::Ble, rest.to_string())
+    } else if let Some(rest) = port.strip_prefix("ch9329:").or_else(|| port.strip_prefix("CH9329:")) {
+        (DriverType::Ch9329, rest.to_string())
+    } else if port.contains('.') {
+        // 形如 192.168.2.188:16896 的地址视为 KMBox Net
+        (DriverType::KmboxNet, port.to_string())
+    } else {
+        (DriverType::Hardware, port.to_string())
+    }
+}
 
-            NavResult::Success => {
-                println!("✅ [主控] 导航到达终点，等待重置...");
-                thread::sleep(Duration::from_secs(5));
+fn run_stats_command(since: Option<&str>, by: &str, export: Option<&str>, output: &str) {
+    let since_time = match since.map(stats::parse_since).transpose() {
+        Ok(t) => t,
+        Err(e) => {
+            println!("❌ {}", e);
+            return;
+        }
+    };
+    let store = match StatsStore::open(STATS_DB) {
+        Ok(s) => s,
+        Err(e) => {
+            println!("❌ {}", e);
+            return;
+        }
+    };
+
+    if let Some(fmt) = export {
+        if !fmt.eq_ignore_ascii_case("csv") {
+            println!("❌ 不支持的导出格式: {} (目前仅支持 csv)", fmt);
+            return;
+        }
+        match store.export_csv(since_time, output) {
+            Ok(n) => println!("✅ 已导出 {} 条记录至: {}", n, output),
+            Err(e) => println!("❌ {}", e),
+        }
+        return;
+    }
+
+    let group = match GroupBy::parse(by) {
+        Ok(g) => g,
+        Err(e) => {
+            println!("❌ {}", e);
+            return;
+        }
+    };
+    match store.query(since_time, group) {
+        Ok(rows) if rows.is_empty() => println!("📭 暂无统计记录"),
+        Ok(rows) => stats::print_table(&rows, group),
+        Err(e) => println!("❌ {}", e),
+    }
+}
+
+fn run_accounts_command(action: &AccountAction) {
+    let mut store = match SecretStore::open(DEFAULT_SECRETS_FILE) {
+        Ok(s) => s,
+        Err(e) => {
+            println!("❌ {}", e);
+            return;
+        }
+    };
+    match action {
+        AccountAction::Add { name, username } => {
+            let password = match rpassword::prompt_password(format!("🔑 请输入账号 [{}] 的密码: ", name)) {
+                Ok(p) => p,
+                Err(e) => {
+                    println!("❌ 读取密码失败: {}", e);
+                    return;
+                }
+            };
+            match store.add(name, username, &password) {
+                Ok(()) => println!("✅ 账号 [{}] 已加密保存至 {}", name, DEFAULT_SECRETS_FILE),
+                Err(e) => println!("❌ {}", e),
+            }
+        }
+        AccountAction::Remove { name } => match store.remove(name) {
+            Ok(true) => println!("🗑️ 账号 [{}] 已删除", name),
+            Ok(false) => println!("⚠️ 未找到账号: {}", name),
+            Err(e) => println!("❌ {}", e),
+        },
+        AccountAction::List => {
+            let accounts = store.list();
+            if accounts.is_empty() {
+                println!("📭 尚未保存任何账号");
+            }
+            for (name, username) in accounts {
+                println!("   - {} ({})", name, username);
+            }
+        }
+    }
+}
+
+/// 打印地图的全部问题；没有错误 (只有提示或没有问题) 时返回 true
+fn run_validate_command(map: &str) -> bool {
+    let problems = NavEngine::validate(map, handlers::HANDLERS);
+    let errors = problems.iter().filter(|p| !p.is_warning()).count();
+    for p in &problems {
+        println!("{} {}", if p.is_warning() { "⚠️" } else { "❌" }, p);
+    }
+    if errors == 0 {
+        println!("✅ {} 检查通过 ({} 条提示)", map, problems.len());
+    } else {
+        println!("❌ {} 共 {} 个错误、{} 条提示", map, errors, problems.len() - errors);
+    }
+    errors == 0
+}
+
+fn run_sync_command(config: &AppConfig, rollback: bool) {
+    if rollback {
+        match sync::rollback() {
+            Ok(files) => println!("⏪ [Sync] 已回滚 {} 个文件: {:?}", files.len(), files),
+            Err(e) => println!("❌ [Sync] {}", e),
+        }
+        return;
+    }
+    match sync::sync(&config.sync) {
+        Ok(report) => println!(
+            "✅ [Sync] 版本 {} | 更新 {} | 未变 {} | 备份 {:?}",
+            report.version,
+            report.updated.len(),
+            report.unchanged,
+            report.backup
+        ),
+        Err(e) => println!("⚠️ [Sync] 同步失败，继续使用本地配置: {}", e),
+    }
+}
+
+fn run_record_command(duration: u64, output: &str, trace: Option<&str>) {
+    let events = match recorder::capture(Duration::from_secs(duration), false) {
+        Ok(events) => events,
+        Err(e) => {
+            println!("❌ {}", e);
+            return;
+        }
+    };
+    let actions = recorder::compile_events(&events);
+    match recorder::save_script(output, &actions) {
+        Ok(()) => println!("💾 宏脚本已保存: {} ({} 步)", output, actions.len()),
+        Err(e) => println!("❌ {}", e),
+    }
+    if let Some(path) = trace {
+        let entries = recorder::compile_trace(&events);
+        match recorder::save_trace(path, &entries) {
+            Ok(()) => println!("💾 驱动指令轨迹已保存: {} ({} 条)", path, entries.len()),
+            Err(e) => println!("❌ {}", e),
+        }
+    }
+}
+
+fn run_learn_command(app_config: &AppConfig, duration: u64, name: &str) {
+    println!("🖱️ [Learn] 请像平时一样操作游戏 (点按钮、打字、滚动列表)，样本越多拟合越准");
+    let events = match recorder::capture(Duration::from_secs(duration), true) {
+        Ok(events) => events,
+        Err(e) => return println!("❌ {}", e),
+    };
+    let learned = learn::fit(&events);
+    println!("{}", learned.to_toml(name).trim());
+    let path = &app_config.human.profile_file;
+    match learn::save(path, name, &learned) {
+        Ok(()) => println!("💾 档案 [{}] 已追加到 {}，在 config.toml 的 [human] 中设置 profile = \"{}\" 启用", name, path, name),
+        Err(e) => println!("❌ {}", e),
+    }
+}
+
+fn run_calibration(driver: &mut dyn InputDriver, driver_type: DriverType, sw: u16, sh: u16) {
+    if driver_type != DriverType::Hardware {
+        println!("⚠️ 校准结果只作用于自制串口固件，当前驱动仅做测量");
+    }
+    println!("⏳ 3秒后开始校准，期间请勿移动鼠标...");
+    thread::sleep(Duration::from_secs(3));
+    match calibration::run(driver, sw, sh) {
+        Ok(cal) => {
+            println!("🎯 [校准] {:?}", cal);
+            match calibration::save(STATS_DB, sw, sh, &cal) {
+                Ok(()) => println!("💾 校准结果已保存，之后启动自动生效"),
+                Err(e) => println!("❌ {}", e),
             }
         }
+        Err(e) => println!("❌ [校准] {}", e),
     }
 }
 
 fn run_input_test(driver: Arc<Mutex<HumanDriver>>) {
     println!("Testing Mouse & Keyboard...");
-    if let Ok(mut d) = driver.lock() {
+    let result = lock_human(&driver).and_then(|mut d| {
         println!("-> 移动鼠标 (矩形轨迹)");
         let start_x = 500;
         let start_y = 500;
-        d.move_to_humanly(start_x, start_y, 0.5);
-        d.move_to_humanly(start_x + 300, start_y, 0.5);
-        d.move_to_humanly(start_x + 300, start_y + 300, 0.5);
-        d.move_to_humanly(start_x, start_y + 300, 0.5);
-        d.move_to_humanly(start_x, start_y, 0.5);
+        d.move_to_humanly(start_x, start_y, 0.5)?;
+        d.move_to_humanly(start_x + 300, start_y, 0.5)?;
+        d.move_to_humanly(start_x + 300, start_y + 300, 0.5)?;
+        d.move_to_humanly(start_x, start_y + 300, 0.5)?;
+        d.move_to_humanly(start_x, start_y, 0.5)?;
 
         println!("-> 执行点击 (Click)");
-        d.click_humanly(true, false, 0);
+        d.click_humanly(MouseButtons::LEFT, 0)?;
         thread::sleep(Duration::from_millis(500));
 
         println!("-> 模拟键盘输入 'hello 123'");
-        d.type_humanly("hello 123", 60.0);
-    }
-    println!("Done.");
+        d.type_humanly("hello 123", 60.0)?;
+        thread::sleep(Duration::from_millis(500));
+
+        println!("-> 组合键 Shift+1");
+        d.key_combo_humanly(&[Modifier::Shift], '1')
+    });
+    report_test(result);
 }
 
-fn run_screen_test() {
-    println!("Testing Screen Capture...");
+fn run_screen_test(engine: Arc<NavEngine>, sw: u16, sh: u16) {
+    println!("Testing Screen Capture ({})...", engine.capture_backend_name());
     let start = Instant::now();
-    let screens = Screen::all().unwrap_or_default();
-
-    if let Some(screen) = screens.first() {
-        println!(
-            "-> 检测到屏幕: {}x{}",
-            screen.display_info.width, screen.display_info.height
-        );
-        match screen.capture() {
-            Ok(image) => {
-                let path = "debug_screenshot.png";
-                image.save(path).unwrap();
-                println!(
-                    "✅ 截图成功! 已保存至: {} (耗时 {}ms)",
-                    path,
-                    start.elapsed().as_millis()
-                );
-            }
-            Err(e) => println!("❌ 截图失败: {}", e),
+
+    // 截取整个截图坐标系 (所选显示器或窗口客户区)
+    let (w, h) = engine.frame_size().unwrap_or((sw as u32, sh as u32));
+    match engine.capture_area([0, 0, w as i32, h as i32]) {
+        Ok(image) => {
+            println!("-> 截图尺寸: {}x{}", image.width(), image.height());
+            let path = "debug_screenshot.png";
+            image.save(path).unwrap();
+            println!(
+                "✅ 截图成功! 已保存至: {} (耗时 {}ms)",
+                path,
+                start.elapsed().as_millis()
+            );
         }
-    } else {
-        println!("❌ 未检测到显示器");
+        Err(e) => println!("❌ 截图失败: {}", e),
     }
 }
 
 fn run_ocr_test(engine: Arc<NavEngine>) {
-    println!("Testing OCR Function...");
+    println!("Testing OCR Function ({})...", engine.ocr_backend_name());
     let rect = [100, 100, 500, 200];
     println!("-> 正在识别区域: {:?}", rect);
     let start = Instant::now();
@@ -228,16 +882,30 @@ fn run_ocr_test(engine: Arc<NavEngine>) {
 
 fn run_scroll_test(driver: Arc<Mutex<HumanDriver>>) {
     println!("Testing Mouse Scroll...");
-    if let Ok(mut d) = driver.lock() {
+    let result = lock_human(&driver).and_then(|mut d| {
         println!("-> 向下滚动 5 格 (Scroll Down)");
-        d.mouse_scroll(-5);
+        d.mouse_scroll(-5)?;
 
         thread::sleep(Duration::from_secs(2));
 
         println!("-> 向上滚动 5 格 (Scroll Up)");
-        d.mouse_scroll(5);
-    }
-    println!("Done.");
+        d.mouse_scroll(5)?;
+
+        thread::sleep(Duration::from_secs(2));
+
+        println!("-> 拟人滚动: 向下 15 格 / 向上 15 格 (分组连拨)");
+        d.scroll_humanly(-15)?;
+        thread::sleep(Duration::from_secs(1));
+        d.scroll_humanly(15)?;
+
+        thread::sleep(Duration::from_secs(2));
+
+        println!("-> 向右 / 向左滚动 5 格 (Horizontal)");
+        d.mouse_hscroll(5)?;
+        thread::sleep(Duration::from_secs(1));
+        d.mouse_hscroll(-5)
+    });
+    report_test(result);
 }
 
 // ✨ 新增 Combo 测试函数
@@ -253,32 +921,24 @@ fn run_combo_test(driver: Arc<Mutex<HumanDriver>>) {
 
     loop {
         // 锁定 HumanDriver 以获取访问权限
-        if let Ok(mut human) = driver.lock() {
+        let result = lock_human(&driver).and_then(|mut human| {
             // 1. 鼠标左键两下
             // (click_humanly 内部会有几十毫秒的 hold time)
-            human.click_humanly(true, false, 50);
+            human.click_humanly(MouseButtons::LEFT, 50)?;
             thread::sleep(delay);
-            human.click_humanly(true, false, 0);
+            human.click_humanly(MouseButtons::LEFT, 0)?;
             thread::sleep(delay);
 
             // 2. 按 b, 按 5
-            if let Ok(mut dev) = human.device.lock() {
-                dev.key_down(key_b, 0);
-            }
+            human.raw(|dev| dev.key_down(key_b, 0))?;
             thread::sleep(delay);
-            if let Ok(mut dev) = human.device.lock() {
-                dev.key_down(key_5, 0);
-            }
+            human.raw(|dev| dev.key_down(key_5, 0))?;
             thread::sleep(delay);
 
             // 3. 松 b, 松 5
-            if let Ok(mut dev) = human.device.lock() {
-                dev.key_up(); // 释放 (通常是释放所有或最后一个)
-            }
+            human.raw(|dev| dev.key_up())?; // 释放 (通常是释放所有或最后一个)
             thread::sleep(delay);
-            if let Ok(mut dev) = human.device.lock() {
-                dev.key_up(); // 再次释放以防万一
-            }
+            human.raw(|dev| dev.key_up())?; // 再次释放以防万一
             thread::sleep(delay);
             thread::sleep(delay);
             thread::sleep(delay);
@@ -302,29 +962,21 @@ fn run_combo_test(driver: Arc<Mutex<HumanDriver>>) {
             thread::sleep(delay);
             thread::sleep(delay);
             // 4. 鼠标左键两下
-            human.click_humanly(true, false, 0);
+            human.click_humanly(MouseButtons::LEFT, 0)?;
             thread::sleep(delay);
-            human.click_humanly(true, false, 0);
+            human.click_humanly(MouseButtons::LEFT, 0)?;
             thread::sleep(delay);
 
             // 5. 按 b, 按 4
-            if let Ok(mut dev) = human.device.lock() {
-                dev.key_down(key_b, 0);
-            }
+            human.raw(|dev| dev.key_down(key_b, 0))?;
             thread::sleep(delay);
-            if let Ok(mut dev) = human.device.lock() {
-                dev.key_down(key_4, 0);
-            }
+            human.raw(|dev| dev.key_down(key_4, 0))?;
             thread::sleep(delay);
 
             // 6. 松 b, 松 4
-            if let Ok(mut dev) = human.device.lock() {
-                dev.key_up();
-            }
+            human.raw(|dev| dev.key_up())?;
             thread::sleep(delay);
-            if let Ok(mut dev) = human.device.lock() {
-                dev.key_up();
-            }
+            human.raw(|dev| dev.key_up())?;
             thread::sleep(delay);
             thread::sleep(delay);
             thread::sleep(delay);
@@ -352,7 +1004,53 @@ fn run_combo_test(driver: Arc<Mutex<HumanDriver>>) {
             thread::sleep(delay);
             thread::sleep(delay);
             thread::sleep(delay);
+            Ok(())
+        });
+        if let Err(e) = result {
+            println!("❌ 测试中止: {}", e);
+            break;
         }
         // 循环继续
     }
 }
+
+fn lock_human(driver: &Arc<Mutex<HumanDriver>>) -> Result<std::sync::MutexGuard<'_, HumanDriver>, DriverError> {
+    driver.lock().map_err(|_| DriverError::Io("驱动锁中毒".to_string()))
+}
+
+/// 连续拟人移动若干秒后打印驱动各阶段的延迟直方图
+fn run_latency_test(driver: Arc<Mutex<HumanDriver>>, device: &DriverHandle) {
+    println!("Testing driver latency...");
+    let result = lock_human(&driver).and_then(|mut d| {
+        println!("-> 往返移动鼠标 (10 轮)");
+        for i in 0..10u16 {
+            let offset = (i % 2) * 400;
+            d.move_to_humanly(500 + offset, 400 + offset / 2, 0.4)?;
+        }
+        d.raw(|dev| dev.flush())
+    });
+    report_test(result);
+    match device.latency_metrics() {
+        Some(m) => println!("⏱️ 驱动延迟统计:\n{}", m),
+        None => println!("⚠️ 当前驱动未提供延迟统计 (仅自制串口固件支持)"),
+    }
+}
+
+/// 逐一发送每种事件并报告每一步的结果，用于验收新的盒子 / 固件
+fn run_driver_test(device: &DriverHandle, sw: u16, sh: u16) {
+    println!("Testing driver (每种事件各一次，会真实点击与按键)...");
+    let report = selftest::run(&mut device.clone(), sw, sh);
+    println!("{}", report);
+    if report.failed() > 0 {
+        println!("❌ 驱动自检有 {} 步失败", report.failed());
+    } else {
+        println!("✅ 驱动自检完成");
+    }
+}
+
+fn report_test(result: Result<(), DriverError>) {
+    match result {
+        Ok(()) => println!("Done."),
+        Err(e) => println!("❌ 测试中止: {}", e),
+    }
+}
@@ -1,20 +1,30 @@
 // src/nav.rs
+use crate::cancel::{self, CancellationToken};
+use crate::capture::{CaptureBackend, GdiCapture};
+use crate::change::Signature;
+use crate::color::Rgb;
+use crate::detector::{Detection, ObjectDetector};
+use crate::dump::{self, DumpConfig};
+use crate::hardware::{DriverError, MouseButtons};
 use crate::human::HumanDriver;
+use crate::logic::Expr;
+use crate::numeric;
+use crate::params;
+use crate::ocr::{OcrBackend, OcrLine, WindowsOcr};
+use crate::ocr_cache::{self, OcrCache};
+use crate::template::Template;
+use regex::Regex;
 use serde::Deserialize;
-use std::collections::{HashMap, VecDeque};
-use std::sync::{Arc, Mutex};
-use std::sync::atomic::AtomicUsize;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex, RwLock};
 use std::thread;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
+use std::fmt;
 use std::fs;
-use std::path::Path;
-use std::io::Cursor;
+use std::path::{Path, PathBuf};
 
-use screenshots::Screen;
-use windows::Media::Ocr::OcrEngine;
-use windows::Globalization::Language;
-use windows::Graphics::Imaging::BitmapDecoder;
-use windows::Storage::Streams::{DataWriter, InMemoryRandomAccessStream};
 
 // ==========================================
 // 0. 结果枚举
@@ -24,36 +34,382 @@ pub enum NavResult {
     Success,
     // ✨ 修改：Handover 携带 (场景ID, 处理器代号)
     Handover(String, Option<String>),
-    Failed,
+    /// 携带失败原因与最后一次识别的逐锚点结果
+    Failed(RecognitionReport),
+}
+
+/// 界面 id (ui_map.toml 中 [[scenes]] 的 id)
+pub type SceneId = String;
+
+/// 单个锚点的检查结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnchorReport {
+    /// "text" / "color" / "image"
+    pub kind: &'static str,
+    /// 实际检查的区域 (截图坐标)
+    pub rect: [i32; 4],
+    /// 期望的文字 (或正则)、颜色或模板
+    pub expected: String,
+    /// 实际识别到的文字、颜色或模板匹配得分
+    pub observed: String,
+    /// OCR 置信度或模板匹配得分 (0~1)；后端不提供时为 None
+    pub confidence: Option<f32>,
+    pub passed: bool,
+}
+
+/// 单个界面的识别结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneReport {
+    pub id: String,
+    /// 是否满足该界面的 logic (and / or)
+    pub passed: bool,
+    pub anchors: Vec<AnchorReport>,
+}
+
+impl AnchorReport {
+    /// 反向锚点取反命中结果
+    fn negated(mut self, negate: bool) -> Self {
+        if negate {
+            self.passed = !self.passed;
+            self.expected = format!("不含 {}", self.expected);
+        }
+        self
+    }
+}
+
+impl SceneReport {
+    /// 识别得分：通过时为命中的锚点数，否则为 0
+    pub fn score(&self) -> usize {
+        if self.passed { self.anchors.iter().filter(|a| a.passed).count() } else { 0 }
+    }
+}
+
+/// 一次识别 (或一次失败的导航) 的诊断信息
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RecognitionReport {
+    pub reason: String,
+    pub scenes: Vec<SceneReport>,
+}
+
+impl fmt::Display for RecognitionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "原因: {}", self.reason)?;
+        for scene in &self.scenes {
+            let hits = scene.anchors.iter().filter(|a| a.passed).count();
+            write!(f, "\n  [{}] {} ({}/{})", scene.id, if scene.passed { "✅" } else { "❌" }, hits, scene.anchors.len())?;
+            for a in &scene.anchors {
+                write!(
+                    f,
+                    "\n    {} {} {:?} 期望 \"{}\" 实际 \"{}\"",
+                    if a.passed { "✅" } else { "❌" },
+                    a.kind,
+                    a.rect,
+                    a.expected,
+                    a.observed
+                )?;
+                if let Some(c) = a.confidence {
+                    write!(f, " (置信度 {:.2})", c)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// find_text_and_click 的选项
+#[derive(Debug, Clone, Copy)]
+pub struct TextClickOptions {
+    /// 在此时间内反复识别，等待文字出现；为 0 时只识别一次
+    pub timeout: Duration,
+    /// 点击前先停顿一次人类反应时间
+    pub react: bool,
+    pub button: MouseButtons,
+}
+
+impl Default for TextClickOptions {
+    fn default() -> Self {
+        Self { timeout: Duration::ZERO, react: false, button: MouseButtons::LEFT }
+    }
+}
+
+/// navigate_with 的步数、时间限制与取消开关
+#[derive(Clone)]
+pub struct NavOptions {
+    /// 最多执行的跳转次数 (含重试与重新规划后的跳转)；None 表示不限
+    pub max_steps: Option<usize>,
+    /// 整次导航的最长时间；None 表示不限
+    pub max_duration: Option<Duration>,
+    /// 中止后在下一次跳转或识别前返回 Failed；暂停时原地等待
+    pub cancel: CancellationToken,
+}
+
+impl Default for NavOptions {
+    fn default() -> Self {
+        Self { max_steps: None, max_duration: None, cancel: cancel::global() }
+    }
+}
+
+/// 一次导航已消耗的步数与时间
+struct Budget<'a> {
+    options: &'a NavOptions,
+    started: Instant,
+    steps: usize,
+}
+
+impl Budget<'_> {
+    /// 在执行下一步之前检查是否已取消或超出限制
+    fn check(&self) -> Result<(), String> {
+        let deadline = self.deadline();
+        if let Err(e) = self.options.cancel.check(deadline) {
+            return Err(match deadline {
+                Some(d) if Instant::now() >= d => format!("导航超时 ({}s)", self.options.max_duration.unwrap_or_default().as_secs()),
+                _ => format!("导航已取消 ({})", e),
+            });
+        }
+        match self.options.max_steps {
+            Some(max) if self.steps >= max => Err(format!("超过最大跳转次数 {}", max)),
+            _ => Ok(()),
+        }
+    }
+
+    /// max_duration 对应的截止时间；进行中的点击 / 动作到点即中止
+    fn deadline(&self) -> Option<Instant> {
+        self.options.max_duration.map(|d| self.started + d)
+    }
+}
+
+/// 导航过程中的事件，通过 NavEngine::add_observer 注册的观察者接收
+#[derive(Debug)]
+pub enum NavEvent<'a> {
+    /// 识别到当前界面 (扫描或等待进入目标界面)
+    SceneRecognized { id: &'a str, score: usize },
+    /// 扫描后无法识别当前界面
+    RecognitionFailed { report: &'a RecognitionReport },
+    /// 执行完一次跳转的点击与动作 (attempt 从 1 开始，重试时递增)
+    TransitionExecuted { from: &'a str, to: &'a str, attempt: u32 },
+    /// 关闭了一个打断界面
+    InterruptDismissed { id: &'a str },
+    /// 一次实际执行的 OCR (命中缓存的不算)；rect 为截图坐标
+    OcrCompleted { rect: [i32; 4], text: &'a str, confidence: Option<f32>, duration: Duration },
+    /// navigate 以 Failed 结束
+    NavigationFailed { target: &'a str, report: &'a RecognitionReport },
+}
+
+/// 导航事件观察者：用于日志、统计或覆盖层显示，无需修改 NavEngine；
+/// 回调在导航线程上同步执行，应尽快返回
+pub trait NavObserver: Send + Sync {
+    fn on_event(&self, event: &NavEvent);
+}
+
+impl<F: Fn(&NavEvent) + Send + Sync> NavObserver for F {
+    fn on_event(&self, event: &NavEvent) {
+        self(event)
+    }
+}
+
+/// ui_map.toml 的载入与校验问题
+#[derive(Debug, Clone, PartialEq)]
+pub enum NavConfigError {
+    /// 无法读取地图文件
+    Io { path: String, message: String },
+    /// TOML 语法错误或字段类型不符
+    Parse { path: String, message: String },
+    /// 两个界面使用了同一个 id (后出现的覆盖先出现的)；first / second 为各自所在的文件
+    DuplicateScene { id: String, first: String, second: String },
+    /// 参数化场景的 params 无效 (取值表不存在或为空)
+    InvalidParams { file: String, message: String },
+    /// 被 include 的文件声明了与主地图不同的 base_resolution
+    ResolutionConflict { file: String, expected: [u32; 2], found: [u32; 2] },
+    /// 跳转目标界面未定义
+    UnknownTarget { scene: String, target: String },
+    /// 跳转的 expect 界面未定义
+    UnknownExpect { scene: String, target: String, expect: String },
+    /// 锚点区域宽高不为正，或区域 / 坐标超出 base_resolution；what 为 "text.0"、"到 [x] 的跳转" 等
+    OutOfScreen { scene: String, what: String, coords: Vec<i32> },
+    /// handler 不是已注册的处理器 (只在 NavEngine::validate 中检查)
+    MissingHandler { scene: String, handler: String },
+    InvalidRegex { scene: String, message: String },
+    InvalidLogic { scene: String, message: String },
+    /// 跳转既没有 coords 也没有 actions
+    EmptyTransition { scene: String, target: String },
+    UnknownKey { scene: String, context: String, key: String },
+    /// 打断界面缺少锚点或 dismiss 动作
+    InvalidDismiss(String),
+    /// 图像锚点的模板无法加载
+    Template { file: String, message: String },
+}
+
+impl NavConfigError {
+    /// 只提示、不阻止载入与热重载的问题：跳转目标未定义 (地图可以先画一半)
+    pub fn is_warning(&self) -> bool {
+        matches!(self, NavConfigError::UnknownTarget { .. })
+    }
+}
+
+impl fmt::Display for NavConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NavConfigError::Io { path, message } => write!(f, "无法读取 {}: {}", path, message),
+            NavConfigError::Parse { path, message } => write!(f, "{} 解析错误: {}", path, message),
+            NavConfigError::DuplicateScene { id, first, second } if first == second => write!(f, "场景 id [{}] 在 {} 中重复定义", id, first),
+            NavConfigError::DuplicateScene { id, first, second } => write!(f, "场景 id [{}] 同时定义在 {} 与 {} 中", id, first, second),
+            NavConfigError::InvalidParams { file, message } => write!(f, "{} 的参数化场景无效: {}", file, message),
+            NavConfigError::ResolutionConflict { file, expected, found } => {
+                write!(f, "{} 的 base_resolution {:?} 与主地图的 {:?} 不一致", file, found, expected)
+            }
+            NavConfigError::UnknownTarget { scene, target } => write!(f, "场景 [{}] 的跳转目标 [{}] 未定义", scene, target),
+            NavConfigError::UnknownExpect { scene, target, expect } => {
+                write!(f, "场景 [{}] 到 [{}] 的跳转的 expect [{}] 不存在", scene, target, expect)
+            }
+            NavConfigError::OutOfScreen { scene, what, coords } => write!(f, "场景 [{}] 的 {} 坐标 {:?} 无效或超出画面", scene, what, coords),
+            NavConfigError::MissingHandler { scene, handler } => write!(f, "场景 [{}] 的处理器 \"{}\" 不存在", scene, handler),
+            NavConfigError::InvalidRegex { scene, message } => write!(f, "场景 [{}] 的 val_regex 无效: {}", scene, message),
+            NavConfigError::InvalidLogic { scene, message } => write!(f, "场景 [{}] 的 logic 无效: {}", scene, message),
+            NavConfigError::EmptyTransition { scene, target } => {
+                write!(f, "场景 [{}] 到 [{}] 的跳转既没有 coords 也没有 actions", scene, target)
+            }
+            NavConfigError::UnknownKey { scene, context, key } => write!(f, "场景 [{}] 的 {} 使用了无法识别的按键 \"{}\"", scene, context, key),
+            NavConfigError::InvalidDismiss(id) => write!(f, "打断界面 [{}] 需要锚点与至少一个 dismiss 动作", id),
+            // Template::load 的错误信息已包含文件路径
+            NavConfigError::Template { message, .. } => write!(f, "{}", message),
+        }
+    }
 }
 
 // ==========================================
 // 1. TOML 配置数据结构
 // ==========================================
 #[derive(Deserialize, Debug, Clone)]
-struct TomlRoot { scenes: Vec<Scene> }
+struct TomlRoot {
+    /// 制作地图时的分辨率 (宽, 高)；设置后所有坐标按实际截图尺寸等比缩放
+    #[serde(default)]
+    base_resolution: Option<[u32; 2]>,
+    /// 一并载入的其他地图文件 (路径相对本文件)：文件、目录 (其中全部 *.toml) 或文件名中带 * 的通配，
+    /// 如 include = ["lobby.toml", "td/*.toml", "packs/"]
+    #[serde(default)]
+    include: Vec<String>,
+    /// 参数化场景引用的取值表 ([params.chapter])，见 params.rs
+    #[serde(default)]
+    params: toml::Table,
+    /// 先按原始 TOML 读入，展开 params 后再逐个解析为 Scene
+    #[serde(default)]
+    scenes: Vec<toml::Value>,
+}
 
 #[derive(Deserialize, Debug, Clone)]
 struct Scene {
     id: String,
+    /// "and" (默认，全部命中) / "or" (任一命中) / 组合表达式，如 "(text.0 AND color.1) OR text.2"
     #[serde(default)] logic: String,
+    /// logic 为组合表达式时的解析结果，载入地图时填充
+    #[serde(skip)]
+    condition: Option<Expr>,
     #[serde(default)] anchors: Option<Anchors>,
     #[serde(default)] transitions: Option<Vec<Transition>>,
     // ✨ 新增：处理该界面的函数代号 (例如 "daily", "td")
     #[serde(default)]
     handler: Option<String>,
+    /// 打断界面 (断线、公告、升级弹窗)：每次识别前检查，命中后执行这些动作关闭，再继续原来的导航
+    #[serde(default)]
+    dismiss: Option<Vec<TransitionAction>>,
 }
 
 #[derive(Deserialize, Debug, Clone, Default)]
 struct Anchors {
     text: Option<Vec<TextAnchor>>,
     color: Option<Vec<ColorAnchor>>,
+    image: Option<Vec<ImageAnchor>>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
 struct TextAnchor {
+    /// 锚点名称，供 NavEngine::read_value 引用
+    #[serde(default)]
+    name: Option<String>,
     rect: [i32; 4],
+    #[serde(default)]
     val: String,
+    /// 正则匹配 (优先于 val 与 match)；其中的捕获组可通过 NavEngine::read_value 读取
+    #[serde(default)]
+    val_regex: Option<String>,
+    /// val_regex 编译结果，加载场景时填充；编译失败的锚点永不命中
+    #[serde(skip)]
+    regex: Option<Regex>,
+    /// 匹配方式，缺省为 contains
+    #[serde(default, rename = "match")]
+    mode: TextMatch,
+    /// fuzzy 模式允许的最大编辑距离 (按字符计)；缺省为 val 长度的 1/4，至少 1
+    #[serde(default)]
+    distance: Option<usize>,
+    /// 反向锚点：识别结果不满足上述条件时才算命中 (如 "返回" 按钮不存在)
+    #[serde(default)]
+    negate: bool,
+    /// OCR 语言 (BCP-47，如 "en" 用于纯数字 / 英文计数器)；缺省使用后端的默认语言
+    #[serde(default)]
+    lang: Option<String>,
+}
+
+/// 文字锚点的匹配方式
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum TextMatch {
+    /// 某一次识别结果与 val 完全相同
+    Exact,
+    /// 识别结果中包含 val
+    #[default]
+    Contains,
+    /// 识别结果中存在与 val 编辑距离不超过阈值的片段 (容忍个别错字、漏字)
+    Fuzzy,
+}
+
+impl TextAnchor {
+    fn max_distance(&self) -> usize {
+        self.distance.unwrap_or((self.val.chars().count() / 4).max(1))
+    }
+
+    fn matches(&self, output: &str) -> bool {
+        if self.val_regex.is_some() {
+            return self.captures(output).is_some();
+        }
+        match self.mode {
+            // 多次曝光的结果以空格拼接，逐段比较
+            TextMatch::Exact => output.split(' ').any(|part| part == self.val),
+            TextMatch::Contains => output.contains(&self.val),
+            TextMatch::Fuzzy => output
+                .split(' ')
+                .any(|part| substring_distance(part, &self.val) <= self.max_distance()),
+        }
+    }
+
+    /// 用 val_regex 逐段匹配识别结果，返回第一处命中的捕获组 (没有捕获组时返回整个匹配)
+    fn captures(&self, output: &str) -> Option<Vec<String>> {
+        let re = self.regex.as_ref()?;
+        let caps = output.split(' ').find_map(|part| re.captures(part))?;
+        let groups: Vec<String> = caps.iter().skip(1).map(|m| m.map_or(String::new(), |m| m.as_str().to_string())).collect();
+        if groups.is_empty() {
+            Some(vec![caps[0].to_string()])
+        } else {
+            Some(groups)
+        }
+    }
+}
+
+/// pattern 与 text 中最相近的子串之间的编辑距离 (Sellers 近似子串匹配：子串可从任意位置开始、结束)
+fn substring_distance(text: &str, pattern: &str) -> usize {
+    let pattern: Vec<char> = pattern.chars().collect();
+    // prev[i] = pattern 前 i 个字符与 "以当前位置结尾的某个子串" 的最小编辑距离
+    let mut prev: Vec<usize> = (0..=pattern.len()).collect();
+    let mut best = pattern.len();
+    for c in text.chars() {
+        let mut cur = vec![0; pattern.len() + 1];
+        for i in 1..=pattern.len() {
+            let substitute = prev[i - 1] + usize::from(pattern[i - 1] != c);
+            cur[i] = substitute.min(prev[i] + 1).min(cur[i - 1] + 1);
+        }
+        best = best.min(cur[pattern.len()]);
+        prev = cur;
+    }
+    best
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -61,106 +417,371 @@ struct ColorAnchor {
     pos: [i32; 2],
     val: String,
     tol: u8,
+    /// 反向锚点：颜色不在容差内时才算命中 (如加载转圈已消失)
+    #[serde(default)]
+    negate: bool,
 }
 
+/// 图像模板锚点：在 rect 区域内搜索 file (相对 ui_map.toml 所在目录) 的截图，
+/// 归一化互相关得分不低于 threshold 即命中；适合没有文字、背景会动的图标
+#[derive(Deserialize, Debug, Clone)]
+struct ImageAnchor {
+    rect: [i32; 4],
+    file: String,
+    #[serde(default = "default_threshold")]
+    threshold: f32,
+    /// 反向锚点：区域内找不到该模板时才算命中
+    #[serde(default)]
+    negate: bool,
+}
+
+fn default_threshold() -> f32 { 0.9 }
+
 #[derive(Deserialize, Debug, Clone)]
 struct Transition {
     target: String,
-    coords: [i32; 2],
+    /// 点击坐标；只靠按键等动作跳转时可省略
+    #[serde(default)]
+    coords: Option<[i32; 2]>,
+    /// 点击 coords 之后依次执行的动作
+    #[serde(default)]
+    actions: Vec<TransitionAction>,
     #[serde(default = "default_delay")]
     post_delay: u64,
+    /// 点击前先停顿一次人类反应时间 (见 HumanDriver::react_then_click)，而不是识别到界面后立刻移动
+    #[serde(default)]
+    react: bool,
+    /// 路径规划时这一跳的代价 (毫秒)；缺省为 post_delay 加一次点击的耗时，
+    /// 可调大以避开加载慢或不稳定的跳转
+    #[serde(default)]
+    cost: Option<u64>,
+    /// 点击后没有进入 expect 界面时的重新点击次数 (仍停留在原界面时才会重点)
+    #[serde(default = "default_retries")]
+    retries: u32,
+    /// 每次点击后等待 expect 界面出现的时间 (毫秒)；缺省为 post_delay，至少 2000
+    #[serde(default)]
+    verify_timeout: Option<u64>,
+    /// 用于确认本跳成功的界面，缺省为 target (如点击后先弹出的确认框)
+    #[serde(default)]
+    expect: Option<String>,
+}
+
+impl Transition {
+    /// 缺省代价：post_delay、每个键鼠动作一次点击的耗时与 wait 动作的总时长
+    fn cost(&self) -> u64 {
+        self.cost.unwrap_or_else(|| {
+            let inputs = self.coords.is_some() as u64 + self.actions.iter().filter(|a| !matches!(a, TransitionAction::Wait { .. })).count() as u64;
+            let waits: u64 = self.actions.iter().map(|a| if let TransitionAction::Wait { ms } = a { *ms } else { 0 }).sum();
+            self.post_delay + CLICK_COST_MS * inputs.max(1) + waits
+        })
+    }
+}
+
+/// 跳转动作 (坐标为地图坐标)，如 `actions = [{ type = "key", key = "esc" }, { type = "wait", ms = 500 }]`
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum TransitionAction {
+    Click {
+        pos: [i32; 2],
+        #[serde(default)]
+        react: bool,
+    },
+    /// 单个字符键，或命名键 esc / enter / tab / backspace / space
+    Key {
+        key: String,
+        #[serde(default)]
+        hold_ms: u64,
+    },
+    /// 滚轮 amount 格 (负数向下)；指定 pos 时先移动到该处
+    Scroll {
+        #[serde(default)]
+        pos: Option<[i32; 2]>,
+        amount: i32,
+    },
+    /// 按住左键从 from 拖到 to
+    Drag {
+        from: [i32; 2],
+        to: [i32; 2],
+        #[serde(default = "default_drag_ms")]
+        ms: u64,
+    },
+    Wait {
+        ms: u64,
+    },
+}
+
+fn default_drag_ms() -> u64 { 600 }
+
+impl TransitionAction {
+    /// 简短描述，用于界面图导出时的边标签
+    fn describe(&self) -> String {
+        match self {
+            TransitionAction::Click { pos, .. } => format!("点击 ({}, {})", pos[0], pos[1]),
+            TransitionAction::Key { key, hold_ms: 0 } => format!("按键 {}", key),
+            TransitionAction::Key { key, hold_ms } => format!("按住 {} {}ms", key, hold_ms),
+            TransitionAction::Scroll { amount, .. } => format!("滚轮 {}", amount),
+            TransitionAction::Drag { from, to, .. } => format!("拖动 ({}, {})→({}, {})", from[0], from[1], to[0], to[1]),
+            TransitionAction::Wait { ms } => format!("等待 {}ms", ms),
+        }
+    }
+}
+
+/// 要按下的键：可打印字符走键盘布局，其余直接使用 HID 键码
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum KeyStroke {
+    Char(char),
+    Code(u8),
+}
+
+/// 动作列表中无法识别的按键名
+fn unknown_keys(actions: &[TransitionAction]) -> impl Iterator<Item = &str> {
+    actions.iter().filter_map(|a| match a {
+        TransitionAction::Key { key, .. } if parse_key(key).is_none() => Some(key.as_str()),
+        _ => None,
+    })
+}
+
+fn parse_key(name: &str) -> Option<KeyStroke> {
+    match name.to_lowercase().as_str() {
+        "esc" | "escape" => Some(KeyStroke::Code(0x29)),
+        "enter" | "return" => Some(KeyStroke::Code(0x28)),
+        "tab" => Some(KeyStroke::Code(0x2B)),
+        "backspace" => Some(KeyStroke::Code(0x2A)),
+        "space" => Some(KeyStroke::Char(' ')),
+        _ => {
+            let mut chars = name.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Some(KeyStroke::Char(c)),
+                _ => None,
+            }
+        }
+    }
 }
 
 fn default_delay() -> u64 { 500 }
+fn default_retries() -> u32 { 1 }
+
+/// 某一跳重试后仍没有进入预期界面
+struct Stall {
+    from: String,
+    target: String,
+    expect: String,
+    attempts: u32,
+}
+
+impl Stall {
+    fn describe(&self) -> String {
+        format!("跳转 [{}] -> [{}] 点击 {} 次后仍未进入 [{}]", self.from, self.target, self.attempts, self.expect)
+    }
+}
+
+/// 未指定 cost 时，一次跳转点击 (反应 + 移动 + 点击 + 确认界面) 的估计耗时
+const CLICK_COST_MS: u64 = 800;
+/// 中途进入了意料之外的界面时，重新定位并规划路径的最多次数
+const MAX_REPLANS: usize = 3;
+
+/// 一次识别前连续处理打断界面的最多次数 (防止关不掉的弹窗卡死导航)
+const MAX_INTERRUPTS: usize = 3;
+/// 关闭打断界面后等待画面恢复的时间
+const INTERRUPT_SETTLE: Duration = Duration::from_millis(800);
+
+/// 监视线程检查引擎是否已释放的间隔
+const WATCH_IDLE: Duration = Duration::from_secs(1);
+/// 收到文件变化后再等一会儿合并后续事件 (编辑器保存时常常先删除再写入)
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// wait_for_scene / wait_for_text 的轮询间隔 (画面未变化时不会重复识别，见 ocr_cache)
+const WAIT_POLL: Duration = Duration::from_millis(200);
+
+/// 单次跳转点击 (反应 + 移动 + 点击) 的最长时间；超时的动作停在原处并松开按键，本次导航失败
+const CLICK_TIMEOUT: Duration = Duration::from_secs(8);
+
+/// 单次点击 / 动作的时限：不超过 CLICK_TIMEOUT，也不越过导航的截止时间
+fn action_limit(deadline: Option<Instant>) -> Duration {
+    deadline.map_or(CLICK_TIMEOUT, |d| CLICK_TIMEOUT.min(d.saturating_duration_since(Instant::now())))
+}
+
+/// find_image 尝试的模板缩放比例 (相对 base_resolution 换算出的尺度)：UI 缩放设置不同、元素带缩放动画时仍能找到
+const FIND_SCALES: [f32; 5] = [0.8, 0.9, 1.0, 1.1, 1.25];
+
+/// 同一界面的锚点最多由几个线程并行检查 (每个文字锚点要跑三次 OCR，是识别耗时的大头)
+const ANCHOR_WORKERS: usize = 4;
 
 // ==========================================
 // 2. 接口层 (OCR 与 多重图像预处理)
 // ==========================================
 struct GameInterface {
     driver: Arc<Mutex<HumanDriver>>,
-    ocr_engine: Option<OcrEngine>,
-    screenshot_count: AtomicUsize, 
+    ocr: Box<dyn OcrBackend>,
+    capture: Box<dyn CaptureBackend>,
+    /// ui_map 的 base_resolution；None 表示坐标即截图坐标 (热重载时随场景图一起替换)
+    base: RwLock<Option<(u32, u32)>>,
+    /// 本轮识别预先截好的锚点区域 (截图坐标, 图像)；锚点检查优先从这里裁剪，不再逐个截图
+    snapshot: Mutex<Vec<([i32; 4], image::RgbaImage)>>,
+    /// 最近的 OCR 结果 (按区域与画面内容)；画面没变或回到见过的内容时直接复用，不再重复识别
+    ocr_cache: Mutex<OcrCache>,
+    /// 每个 (区域, 模板) 上次的画面签名与最高匹配得分
+    match_cache: Mutex<MatchCache>,
+    observers: RwLock<Vec<Arc<dyn NavObserver>>>,
 }
 
-unsafe impl Send for GameInterface {}
-unsafe impl Sync for GameInterface {}
+/// 待检查的锚点 (evaluate_scene 分给检查线程的单位)
+enum AnchorJob<'a> {
+    Text(&'a TextAnchor),
+    Color(&'a ColorAnchor),
+    Image(&'a ImageAnchor),
+}
+
+type MatchCache = HashMap<([i32; 4], String), (Signature, Option<f32>)>;
+type FoundTemplates = HashMap<PathBuf, (Option<SystemTime>, Arc<Template>)>;
+
+/// 两个锚点区域合并后的外接矩形面积不超过二者面积之和的这个倍数时，合并为一次截图
+const MERGE_SLACK: f64 = 1.5;
+
+/// 把锚点矩形合并为尽量少、又不会多截太多空白的截图区域；每个输入矩形都被某个输出区域完整包含
+fn merge_regions(mut rects: Vec<[i32; 4]>) -> Vec<[i32; 4]> {
+    let area = |r: &[i32; 4]| ((r[2] - r[0]).max(1) as f64) * ((r[3] - r[1]).max(1) as f64);
+    loop {
+        let mut merged = None;
+        'search: for i in 0..rects.len() {
+            for j in i + 1..rects.len() {
+                let (a, b) = (rects[i], rects[j]);
+                let union = [a[0].min(b[0]), a[1].min(b[1]), a[2].max(b[2]), a[3].max(b[3])];
+                if area(&union) <= (area(&a) + area(&b)) * MERGE_SLACK {
+                    merged = Some((i, j, union));
+                    break 'search;
+                }
+            }
+        }
+        match merged {
+            Some((i, j, union)) => {
+                rects[i] = union;
+                rects.swap_remove(j);
+            }
+            None => return rects,
+        }
+    }
+}
 
 impl GameInterface {
-    fn new(driver: Arc<Mutex<HumanDriver>>) -> Self {
-        println!("🚀 初始化 Windows OCR...");
-        let engine = match Language::CreateLanguage(&windows::core::HSTRING::from("zh-Hans")) {
-            Ok(lang) => match OcrEngine::TryCreateFromLanguage(&lang) {
-                Ok(e) => Some(e),
-                Err(_) => OcrEngine::TryCreateFromUserProfileLanguages().ok()
-            },
-            Err(_) => OcrEngine::TryCreateFromUserProfileLanguages().ok(),
-        };
-        Self { 
-            driver, 
-            ocr_engine: engine,
-            screenshot_count: AtomicUsize::new(0), 
+    fn new(driver: Arc<Mutex<HumanDriver>>, capture: Box<dyn CaptureBackend>, ocr: Box<dyn OcrBackend>) -> Self {
+        Self {
+            driver,
+            ocr,
+            capture,
+            base: RwLock::new(None),
+            snapshot: Mutex::new(Vec::new()),
+            ocr_cache: Mutex::new(OcrCache::new(ocr_cache::DEFAULT_CAPACITY)),
+            match_cache: Mutex::new(HashMap::new()),
+            observers: RwLock::new(Vec::new()),
         }
     }
 
-    /// 调用底层 Windows OCR 识别单张图像
-    fn run_windows_ocr(&self, dynamic_img: image::DynamicImage) -> String {
-        if self.ocr_engine.is_none() { return String::new(); }
-        let engine = self.ocr_engine.as_ref().unwrap();
+    fn emit(&self, event: NavEvent) {
+        if let Ok(observers) = self.observers.read() {
+            for o in observers.iter() {
+                o.on_event(&event);
+            }
+        }
+    }
 
-        let mut png_buffer = Cursor::new(Vec::new());
-        if dynamic_img.write_to(&mut png_buffer, image::ImageFormat::Png).is_err() { return String::new(); }
-        let png_bytes = png_buffer.into_inner();
+    /// 截取屏幕区域：完整落在本轮快照内时直接裁剪，否则实时截图
+    fn grab(&self, x: i32, y: i32, w: u32, h: u32) -> Result<image::RgbaImage, String> {
+        if let Ok(regions) = self.snapshot.lock() {
+            for (r, img) in regions.iter() {
+                let (rw, rh) = img.dimensions();
+                if x >= r[0] && y >= r[1] && x + w as i32 <= r[0] + rw as i32 && y + h as i32 <= r[1] + rh as i32 {
+                    return Ok(image::imageops::crop_imm(img, (x - r[0]) as u32, (y - r[1]) as u32, w, h).to_image());
+                }
+            }
+        }
+        self.capture.capture_area(x, y, w, h)
+    }
 
-        let stream = InMemoryRandomAccessStream::new().unwrap();
-        let writer = DataWriter::CreateDataWriter(&stream).unwrap();
-        if writer.WriteBytes(&png_bytes).is_err() { return String::new(); }
-        if writer.StoreAsync().unwrap().get().is_err() { return String::new(); }
-        if writer.FlushAsync().unwrap().get().is_err() { return String::new(); }
-        if writer.DetachStream().is_err() { return String::new(); }
-        if stream.Seek(0).is_err() { return String::new(); }
+    /// 一次性截取这些区域 (截图坐标) 作为本轮快照；截图失败的区域留给锚点检查时实时截取
+    fn take_snapshot(&self, rects: Vec<[i32; 4]>) {
+        let regions = merge_regions(rects)
+            .into_iter()
+            .filter_map(|r| {
+                let (w, h) = ((r[2] - r[0]).max(1) as u32, (r[3] - r[1]).max(1) as u32);
+                self.capture.capture_area(r[0], r[1], w, h).ok().map(|img| (r, img))
+            })
+            .collect();
+        if let Ok(mut snapshot) = self.snapshot.lock() {
+            *snapshot = regions;
+        }
+    }
 
-        let decoder = match BitmapDecoder::CreateAsync(&stream) {
-             Ok(op) => match op.get() { Ok(d) => d, Err(_) => return String::new() },
-             Err(_) => return String::new(),
-        };
-        let software_bitmap = match decoder.GetSoftwareBitmapAsync() {
-             Ok(op) => match op.get() { Ok(b) => b, Err(_) => return String::new() },
-             Err(_) => return String::new(),
-        };
-        let result = match engine.RecognizeAsync(&software_bitmap) {
-             Ok(op) => match op.get() { Ok(res) => res, Err(_) => return String::new() },
-             Err(_) => return String::new(),
-        };
-        
-        let mut full_text = String::new();
-        if let Ok(lines) = result.Lines() {
-            for line in lines {
-                if let Ok(text) = line.Text() { full_text.push_str(&text.to_string()); }
-            }
+    fn clear_snapshot(&self) {
+        if let Ok(mut snapshot) = self.snapshot.lock() {
+            snapshot.clear();
+        }
+    }
+
+    /// 地图坐标到截图坐标的缩放比例 (x, y)；每次读取窗口尺寸，窗口改变大小后仍然正确
+    fn scale(&self) -> (f64, f64) {
+        let base = self.base.read().ok().and_then(|b| *b);
+        match (base, self.capture.frame_size()) {
+            (Some((bw, bh)), Some((w, h))) if bw > 0 && bh > 0 => (w as f64 / bw as f64, h as f64 / bh as f64),
+            _ => (1.0, 1.0),
+        }
+    }
+
+    fn to_screen_point(&self, p: [i32; 2]) -> [i32; 2] {
+        let (sx, sy) = self.scale();
+        [(p[0] as f64 * sx).round() as i32, (p[1] as f64 * sy).round() as i32]
+    }
+
+    fn to_screen_rect(&self, r: [i32; 4]) -> [i32; 4] {
+        let [x1, y1] = self.to_screen_point([r[0], r[1]]);
+        let [x2, y2] = self.to_screen_point([r[2], r[3]]);
+        [x1, y1, x2, y2]
+    }
+
+    /// 调用 OCR 后端识别单张图像；识别失败视为没有文字
+    fn run_ocr(&self, img: image::DynamicImage, lang: Option<&str>) -> (String, Option<f32>) {
+        match lang {
+            Some(lang) => self.ocr.recognize_lang(&img, lang),
+            None => self.ocr.recognize_scored(&img),
         }
-        full_text.replace(|c: char| c.is_whitespace(), "")
+        .unwrap_or_default()
     }
 
     pub fn get_text_from_area(&self, rect: [i32; 4]) -> String {
+        self.read_area(rect, None).0
+    }
+
+    /// 识别区域文字 (lang 为 None 时使用后端默认语言)，同时返回 OCR 置信度
+    fn read_area(&self, rect: [i32; 4], lang: Option<&str>) -> (String, Option<f32>) {
          let x = rect[0]; 
          let y = rect[1];
          let w = (rect[2] - rect[0]).max(1);
          let h = (rect[3] - rect[1]).max(1);
          
-         let screens = Screen::all().unwrap_or_default();
-         let screen = match screens.first() { Some(s) => s, None => return String::new() };
-         
-         let captured_data = match screen.capture_area(x, y, w as u32, h as u32) {
+         let rgba_img = match self.grab(x, y, w as u32, h as u32) {
              Ok(img) => img,
-             Err(_) => return String::new(),
+             Err(_) => return (String::new(), None),
          };
+         let hash = ocr_cache::content_hash(&rgba_img);
+         if let Some(hit) = self.ocr_cache.lock().ok().and_then(|mut c| c.get(rect, lang, hash)) {
+             return hit;
+         }
+         let started = Instant::now();
+         let (text, confidence) = self.recognize_area(rgba_img, lang);
+         self.emit(NavEvent::OcrCompleted { rect, text: &text, confidence, duration: started.elapsed() });
+         if let Ok(mut cache) = self.ocr_cache.lock() { cache.insert(rect, lang, hash, text.clone(), confidence); }
+         (text, confidence)
+    }
+
+    /// 置信度取三种预处理中最高的一次
+    fn recognize_area(&self, rgba_img: image::RgbaImage, lang: Option<&str>) -> (String, Option<f32>) {
+         let (w, h) = rgba_img.dimensions();
 
          // 1. 基础转换
-         let rgba_img = image::RgbaImage::from_raw(captured_data.width(), captured_data.height(), captured_data.into_raw()).unwrap();
          let dynamic_img = image::DynamicImage::ImageRgba8(rgba_img);
 
          // 2. 🔥 2倍放大：Lanczos3 采样能有效平滑艺术字边缘
-         let scaled_img = dynamic_img.resize(w as u32 * 2, h as u32 * 2, image::imageops::FilterType::Lanczos3);
+         let scaled_img = dynamic_img.resize(w * 2, h * 2, image::imageops::FilterType::Lanczos3);
          
          // 3. 🔥 多重曝光 OCR 策略
          let mut results = Vec::new();
@@ -168,209 +789,1415 @@ impl GameInterface {
          // 策略 A: 强二值化 (阈值 200)
          let mut luma_high = scaled_img.grayscale().into_luma8();
          for pixel in luma_high.pixels_mut() { pixel[0] = if pixel[0] > 200 { 255 } else { 0 }; }
-         results.push(self.run_windows_ocr(image::DynamicImage::ImageLuma8(luma_high)));
+         results.push(self.run_ocr(image::DynamicImage::ImageLuma8(luma_high), lang));
 
          // 策略 B: 中等二值化 (阈值 140)
          let mut luma_mid = scaled_img.grayscale().into_luma8();
          for pixel in luma_mid.pixels_mut() { pixel[0] = if pixel[0] > 140 { 255 } else { 0 }; }
-         results.push(self.run_windows_ocr(image::DynamicImage::ImageLuma8(luma_mid)));
+         results.push(self.run_ocr(image::DynamicImage::ImageLuma8(luma_mid), lang));
 
          // 策略 C: 原色缩放图
-         results.push(self.run_windows_ocr(scaled_img.clone()));
+         results.push(self.run_ocr(scaled_img.clone(), lang));
 
          // 4. 合并所有识别到的文本块
-         let final_text = results.join(" ");
-         final_text
+         let confidence = results.iter().filter_map(|r| r.1).reduce(f32::max);
+         let texts: Vec<String> = results.into_iter().map(|r| r.0).collect();
+         (texts.join(" "), confidence)
+    }
+
+    /// 用偏向数字的预处理识别区域文字 (截图坐标)
+    fn read_digits(&self, rect: [i32; 4]) -> String {
+        let (w, h) = ((rect[2] - rect[0]).max(1) as u32, (rect[3] - rect[1]).max(1) as u32);
+        let Ok(img) = self.grab(rect[0], rect[1], w, h) else { return String::new() };
+        let started = Instant::now();
+        let (text, confidence) = self.run_ocr(image::DynamicImage::ImageLuma8(numeric::prepare_digits(&img)), None);
+        self.emit(NavEvent::OcrCompleted { rect, text: &text, confidence, duration: started.elapsed() });
+        text
     }
 
-    fn check_text_anchor(&self, rect: [i32; 4], expected: &str) -> bool {
-        let output = self.get_text_from_area(rect);
-        output.contains(expected)
+    fn check_text_anchor(&self, anchor: &TextAnchor) -> AnchorReport {
+        let rect = self.to_screen_rect(anchor.rect);
+        let (text, confidence) = self.read_area(rect, anchor.lang.as_deref());
+        AnchorReport {
+            kind: "text",
+            rect,
+            expected: anchor.val_regex.clone().unwrap_or_else(|| anchor.val.clone()),
+            passed: anchor.matches(&text),
+            observed: text,
+            confidence,
+        }
     }
 
     pub fn debug_ocr_file(&self, file_path: &str, expected_contain: &str) {
         println!("📂 [本地测试] 加载: {}", file_path);
         if !Path::new(file_path).exists() { return; }
         let dynamic_img = image::open(file_path).expect("加载失败");
-        let output = self.run_windows_ocr(dynamic_img);
-        println!("📝 结果: [{}] | 期望: [{}] -> {}", output, expected_contain, output.contains(expected_contain));
+        let (output, confidence) = self.run_ocr(dynamic_img, None);
+        println!("📝 结果: [{}] | 期望: [{}] -> {} (置信度: {:?})", output, expected_contain, output.contains(expected_contain), confidence);
+    }
+
+    fn check_color_anchor(&self, pos: [i32; 2], expected_hex: &str, tolerance: u8) -> AnchorReport {
+        let [x, y] = self.to_screen_point(pos);
+        let mut report = AnchorReport {
+            kind: "color",
+            rect: [x, y, x + 1, y + 1],
+            expected: format!("{} ±{}", expected_hex, tolerance),
+            observed: "截图失败".to_string(),
+            confidence: None,
+            passed: false,
+        };
+        let Some(actual) = self.grab(x, y, 1, 1).ok().and_then(|img| Rgb::first_pixel(&img)) else { return report };
+        // 无法解析的颜色按黑色比较
+        let expected = Rgb::from_hex(expected_hex).unwrap_or_default();
+        report.observed = actual.to_string();
+        report.passed = actual.matches(&expected, tolerance);
+        report
+    }
+
+    fn check_image_anchor(&self, rect: [i32; 4], file: &str, template: &Template, threshold: f32) -> AnchorReport {
+        let (tw, th) = template.size();
+        // 区域比模板还小时按模板大小截取，避免配置时框得太紧导致永远匹配不上
+        let w = ((rect[2] - rect[0]).max(0) as u32).max(tw);
+        let h = ((rect[3] - rect[1]).max(0) as u32).max(th);
+        let [x1, y1, x2, y2] = self.to_screen_rect([rect[0], rect[1], rect[0] + w as i32, rect[1] + h as i32]);
+        let report = |observed: String, score: Option<f32>| AnchorReport {
+            kind: "image",
+            rect: [x1, y1, x2, y2],
+            expected: format!("{} ≥{:.2}", file, threshold),
+            observed,
+            confidence: score,
+            passed: score.is_some_and(|s| s >= threshold),
+        };
+        let scored = |score: Option<f32>| report(score.map_or("无匹配".to_string(), |s| format!("{:.3}", s)), score);
+        let image = match self.grab(x1, y1, (x2 - x1).max(1) as u32, (y2 - y1).max(1) as u32) {
+            Ok(img) => img,
+            Err(_) => return report("截图失败".to_string(), None),
+        };
+        let key = (rect, file.to_string());
+        let signature = Signature::of(&image);
+        if let Some((last, score)) = self.match_cache.lock().ok().and_then(|c| c.get(&key).cloned()) {
+            if !last.changed(&signature) { return scored(score); }
+        }
+        let mut gray = image::DynamicImage::ImageRgba8(image).into_luma8();
+        // 模板是在 base_resolution 下裁的，先把截图缩回同一尺度再匹配
+        if gray.dimensions() != (w, h) {
+            gray = image::imageops::resize(&gray, w, h, image::imageops::FilterType::Triangle);
+        }
+        let score = template.best_match(&gray).map(|m| m.score);
+        if let Ok(mut cache) = self.match_cache.lock() { cache.insert(key, (signature, score)); }
+        scored(score)
+    }
+
+    /// 执行一次跳转：先点击 coords (如有)，再依次执行 actions；deadline 为导航的截止时间
+    fn perform_transition(&self, step: &Transition, deadline: Option<Instant>) -> Result<(), DriverError> {
+        if let Some(pos) = step.coords {
+            self.perform_click(pos, step.react, deadline)?;
+        }
+        for action in &step.actions {
+            self.perform_action(action, deadline)?;
+        }
+        Ok(())
     }
 
-    fn check_color_anchor(&self, pos: [i32; 2], expected_hex: &str, tolerance: u8) -> bool {
-        let x = pos[0]; let y = pos[1];
-        let screens = Screen::all().unwrap_or_default();
-        let screen = match screens.first() { Some(s) => s, None => return false };
-        let image = match screen.capture_area(x, y, 1, 1) { Ok(img) => img, Err(_) => return false };
-        let data = image.as_raw();
-        if data.len() < 3 { return false; }
-        let (r, g, b) = (data[0], data[1], data[2]);
-        let expected_rgb = hex::decode(expected_hex.trim_start_matches('#')).unwrap_or(vec![0,0,0]);
-        let diff = (r as i16 - expected_rgb[0] as i16).abs() + (g as i16 - expected_rgb[1] as i16).abs() + (b as i16 - expected_rgb[2] as i16).abs();
-        diff <= (tolerance as i16 * 3)
+    fn perform_action(&self, action: &TransitionAction, deadline: Option<Instant>) -> Result<(), DriverError> {
+        let point = |p: [i32; 2]| {
+            let [x, y] = self.to_screen_point(p);
+            (x as u16, y as u16)
+        };
+        match action {
+            TransitionAction::Click { pos, react } => return self.perform_click(*pos, *react, deadline),
+            TransitionAction::Wait { ms } => {
+                thread::sleep(Duration::from_millis(*ms));
+                return Ok(());
+            }
+            _ => {}
+        }
+        let Ok(mut bot) = self.driver.lock() else { return Ok(()) };
+        bot.with_deadline(action_limit(deadline), |bot| match action {
+            TransitionAction::Key { key, hold_ms } => match parse_key(key) {
+                Some(KeyStroke::Char(c)) if *hold_ms > 0 => bot.key_hold(c, *hold_ms),
+                Some(KeyStroke::Char(c)) => bot.key_click(c),
+                Some(KeyStroke::Code(code)) => {
+                    bot.raw(|dev| dev.key_down(code, 0))?;
+                    thread::sleep(Duration::from_millis((*hold_ms).max(30)));
+                    bot.raw(|dev| dev.key_up())
+                }
+                None => {
+                    println!("⚠️ [Nav] 无法识别的按键 \"{}\"，已跳过", key);
+                    Ok(())
+                }
+            },
+            TransitionAction::Scroll { pos, amount } => {
+                if let Some(p) = pos {
+                    let (x, y) = point(*p);
+                    bot.move_to_humanly(x, y, 0.6)?;
+                }
+                bot.scroll_humanly(*amount)
+            }
+            TransitionAction::Drag { from, to, ms } => {
+                bot.drag_humanly(MouseButtons::LEFT, point(*from), point(*to), *ms as f32 / 1000.0)
+            }
+            TransitionAction::Click { .. } | TransitionAction::Wait { .. } => Ok(()),
+        })
     }
 
-    fn perform_click(&self, x: i32, y: i32) {
+    fn perform_click(&self, pos: [i32; 2], react: bool, deadline: Option<Instant>) -> Result<(), DriverError> {
+        let [x, y] = self.to_screen_point(pos);
         if let Ok(mut bot) = self.driver.lock() {
-            bot.move_to_humanly(x as u16, y as u16, 0.6);
-            bot.click_humanly(true, false, 0); 
+            // 只能相对移动的驱动每步位移受指针加速影响，放慢滑动让单步更小、落点更准
+            let duration = if bot.caps().absolute_mouse { 0.6 } else { 1.0 };
+            bot.with_deadline(action_limit(deadline), |bot| {
+                if react {
+                    return bot.react_then_click(x as u16, y as u16, MouseButtons::LEFT, duration);
+                }
+                bot.move_to_humanly(x as u16, y as u16, duration)?;
+                bot.click_humanly(MouseButtons::LEFT, 0)
+            })?;
         }
+        Ok(())
     }
 }
 
 // ==========================================
 // 3. 导航引擎
 // ==========================================
-pub struct NavEngine {
+/// 从 ui_map.toml 载入的场景图；热重载时整体替换
+struct SceneGraph {
     scenes: HashMap<String, Scene>,
+    /// 图像锚点的模板 (按 file 字段索引)，载入地图时一次性加载
+    templates: HashMap<String, Template>,
+    base: Option<(u32, u32)>,
+    /// 载入时读取的所有文件与目录，热重载监视这些路径
+    sources: Vec<PathBuf>,
+}
+
+/// 展开一条 include：目录取其中全部 *.toml，文件名带 * 时按通配匹配同目录文件，其余按单个文件处理；结果按文件名排序
+fn expand_include(dir: &Path, pattern: &str) -> Result<(Vec<PathBuf>, Option<PathBuf>), NavConfigError> {
+    let target = dir.join(pattern);
+    let (folder, name) = if target.is_dir() {
+        (target, "*.toml".to_string())
+    } else {
+        match target.file_name().map(|n| n.to_string_lossy().to_string()).filter(|n| n.contains('*')) {
+            Some(name) => (target.parent().unwrap_or(Path::new("")).to_path_buf(), name),
+            None => return Ok((vec![target], None)),
+        }
+    };
+    let entries = fs::read_dir(if folder.as_os_str().is_empty() { Path::new(".") } else { &folder })
+        .map_err(|e| NavConfigError::Io { path: folder.display().to_string(), message: e.to_string() })?;
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|e| e.path().is_file() && wildcard_match(&name, &e.file_name().to_string_lossy()))
+        .map(|e| folder.join(e.file_name()))
+        .collect();
+    files.sort();
+    Ok((files, Some(folder)))
+}
+
+/// 只支持 * (任意长度) 的文件名通配
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = name.strip_prefix(first) else { return false };
+    let parts: Vec<&str> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        if i == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    rest.is_empty()
+}
+
+/// 读取一个地图文件及其 include (递归)；已读过的文件跳过，防止循环 include。
+/// 读取或展开 include 失败记入 problems，只有 path 本身读取失败时返回 Err
+fn read_fragments(
+    path: &Path,
+    fragments: &mut Vec<(PathBuf, TomlRoot)>,
+    sources: &mut Vec<PathBuf>,
+    problems: &mut Vec<NavConfigError>,
+) -> Result<(), NavConfigError> {
+    let key = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    if fragments.iter().any(|(p, _)| fs::canonicalize(p).unwrap_or_else(|_| p.clone()) == key) {
+        return Ok(());
+    }
+    let display = path.display().to_string();
+    let content = fs::read_to_string(path).map_err(|e| NavConfigError::Io { path: display.clone(), message: e.to_string() })?;
+    let root: TomlRoot = toml::from_str(&content).map_err(|e| NavConfigError::Parse { path: display, message: e.to_string() })?;
+    let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+    let includes = root.include.clone();
+    sources.push(path.to_path_buf());
+    fragments.push((path.to_path_buf(), root));
+    for pattern in &includes {
+        match expand_include(&dir, pattern) {
+            Ok((files, folder)) => {
+                sources.extend(folder);
+                for file in files {
+                    if let Err(e) = read_fragments(&file, fragments, sources, problems) {
+                        problems.push(e);
+                    }
+                }
+            }
+            Err(e) => problems.push(e),
+        }
+    }
+    Ok(())
+}
+
+/// logic 不是 "and" / "or" 时按组合表达式解析，并检查引用的锚点序号是否存在
+/// 热重载监视的路径统一为绝对路径，与 notify 事件中的路径比较
+fn watch_sources(sources: &[PathBuf]) -> Vec<PathBuf> {
+    sources.iter().map(|p| std::path::absolute(p).unwrap_or_else(|_| p.clone())).collect()
+}
+
+/// 事件路径是否涉及载入过的文件：文件本身、通配目录中的文件，或包含它们的目录被删除 / 改名
+fn affects(sources: &[PathBuf], path: &Path) -> bool {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    sources.iter().any(|s| *s == path || path.parent() == Some(s.as_path()) || s.starts_with(&path))
+}
+
+/// 监视所有文件所在的目录 (文件被删除后重新创建也能收到事件) 与通配目录本身，
+/// 不再需要的目录取消监视
+fn rewatch(watcher: &mut notify::RecommendedWatcher, dirs: &mut HashSet<PathBuf>, sources: &[PathBuf]) {
+    use notify::{RecursiveMode, Watcher};
+    let mut wanted = HashSet::new();
+    for s in sources {
+        if s.is_dir() {
+            wanted.insert(s.clone());
+        }
+        if let Some(parent) = s.parent().filter(|p| p.is_dir()) {
+            wanted.insert(parent.to_path_buf());
+        }
+    }
+    for dir in dirs.difference(&wanted) {
+        let _ = watcher.unwatch(dir);
+    }
+    dirs.retain(|d| wanted.contains(d));
+    for dir in wanted {
+        if dirs.contains(&dir) {
+            continue;
+        }
+        match watcher.watch(&dir, RecursiveMode::NonRecursive) {
+            Ok(()) => {
+                dirs.insert(dir);
+            }
+            Err(e) => println!("⚠️ [Nav] 无法监视目录 {}: {}", dir.display(), e),
+        }
+    }
+}
+
+fn parse_condition(scene: &mut Scene) -> Result<(), String> {
+    if matches!(scene.logic.trim().to_lowercase().as_str(), "" | "and" | "or") {
+        return Ok(());
+    }
+    let expr = Expr::parse(&scene.logic)?;
+    let anchors = scene.anchors.clone().unwrap_or_default();
+    for (kind, index) in expr.anchors() {
+        let count = match kind {
+            "text" => anchors.text.as_ref().map_or(0, Vec::len),
+            "color" => anchors.color.as_ref().map_or(0, Vec::len),
+            _ => anchors.image.as_ref().map_or(0, Vec::len),
+        };
+        if index >= count {
+            return Err(format!("引用了不存在的锚点 {}.{} (该类型共 {} 个)", kind, index, count));
+        }
+    }
+    scene.condition = Some(expr);
+    Ok(())
+}
+
+/// 检查界面中所有锚点区域与点击坐标：区域宽高须为正；设置了 base_resolution 时还须落在画面内
+fn check_bounds(scene: &Scene, base: Option<[u32; 2]>) -> Vec<NavConfigError> {
+    let mut problems = Vec::new();
+    let inside = |x: i32, y: i32| base.is_none_or(|[w, h]| x >= 0 && y >= 0 && x <= w as i32 && y <= h as i32);
+    let mut rect = |what: String, r: [i32; 4]| {
+        if r[2] <= r[0] || r[3] <= r[1] || !inside(r[0], r[1]) || !inside(r[2], r[3]) {
+            problems.push(NavConfigError::OutOfScreen { scene: scene.id.clone(), what, coords: r.to_vec() });
+        }
+    };
+    let anchors = scene.anchors.clone().unwrap_or_default();
+    for (i, a) in anchors.text.iter().flatten().enumerate() {
+        rect(format!("text.{}", i), a.rect);
+    }
+    for (i, a) in anchors.image.iter().flatten().enumerate() {
+        rect(format!("image.{}", i), a.rect);
+    }
+    let mut points: Vec<(String, [i32; 2])> = anchors.color.iter().flatten().enumerate().map(|(i, a)| (format!("color.{}", i), a.pos)).collect();
+    for t in scene.transitions.iter().flatten() {
+        let what = format!("到 [{}] 的跳转", t.target);
+        points.extend(t.coords.map(|c| (what.clone(), c)));
+        points.extend(action_points(&t.actions).map(|p| (what.clone(), p)));
+    }
+    points.extend(action_points(scene.dismiss.as_deref().unwrap_or_default()).map(|p| ("dismiss".to_string(), p)));
+    for (what, p) in points.into_iter().filter(|(_, p)| !inside(p[0], p[1])) {
+        problems.push(NavConfigError::OutOfScreen { scene: scene.id.clone(), what, coords: p.to_vec() });
+    }
+    problems
+}
+
+/// 动作列表中所有的坐标
+fn action_points(actions: &[TransitionAction]) -> impl Iterator<Item = [i32; 2]> + '_ {
+    actions.iter().flat_map(|a| match a {
+        TransitionAction::Click { pos, .. } => vec![*pos],
+        TransitionAction::Scroll { pos, .. } => pos.iter().copied().collect(),
+        TransitionAction::Drag { from, to, .. } => vec![*from, *to],
+        TransitionAction::Key { .. } | TransitionAction::Wait { .. } => Vec::new(),
+    })
+}
+
+impl SceneGraph {
+    /// 读取并解析地图；无法读取或解析时返回 Err，
+    /// 其余问题 (正则无效、模板加载失败、坐标越界等) 随场景图一起返回，由调用方决定是否接受 (见 NavConfigError::is_warning)
+    /// file_path 为目录时载入其中全部 *.toml (地图包)
+    fn load(file_path: &str) -> Result<(Self, Vec<NavConfigError>), NavConfigError> {
+        let mut problems = Vec::new();
+        let mut fragments = Vec::new();
+        let mut sources = Vec::new();
+        let path = Path::new(file_path);
+        if path.is_dir() {
+            let (files, folder) = expand_include(path, "")?;
+            if files.is_empty() {
+                return Err(NavConfigError::Io { path: file_path.to_string(), message: "目录中没有 *.toml 地图文件".to_string() });
+            }
+            sources.extend(folder);
+            for file in files {
+                read_fragments(&file, &mut fragments, &mut sources, &mut problems)?;
+            }
+        } else {
+            read_fragments(path, &mut fragments, &mut sources, &mut problems)?;
+        }
+
+        // 坐标按 base_resolution 解释，各文件必须一致 (以第一个声明的为准)
+        let mut resolution = None;
+        for (file, root) in &fragments {
+            match (resolution, root.base_resolution) {
+                (None, found) => resolution = found,
+                (Some(expected), Some(found)) if expected != found => {
+                    problems.push(NavConfigError::ResolutionConflict { file: file.display().to_string(), expected, found });
+                }
+                _ => {}
+            }
+        }
+
+        // 取值表在所有文件间共享
+        let tables: toml::Table = fragments.iter().flat_map(|(_, root)| root.params.clone()).collect();
+        let mut map = HashMap::new();
+        let mut origin: HashMap<String, String> = HashMap::new();
+        for (file, root) in fragments {
+            let dir = file.parent().unwrap_or(Path::new("")).to_path_buf();
+            let file = file.display().to_string();
+            let mut scenes = Vec::new();
+            for (i, raw) in root.scenes.iter().enumerate() {
+                let expanded = match params::expand_scene(raw, &tables) {
+                    Ok(expanded) => expanded,
+                    Err(message) => {
+                        problems.push(NavConfigError::InvalidParams { file: file.clone(), message: format!("第 {} 个场景: {}", i + 1, message) });
+                        continue;
+                    }
+                };
+                for value in expanded {
+                    match value.try_into::<Scene>() {
+                        Ok(scene) => scenes.push(scene),
+                        Err(e) => problems.push(NavConfigError::Parse { path: file.clone(), message: format!("第 {} 个场景: {}", i + 1, e) }),
+                    }
+                }
+            }
+            for mut s in scenes {
+                // 模板路径相对声明它的文件
+                for anchor in s.anchors.iter_mut().filter_map(|a| a.image.as_mut()).flatten() {
+                    anchor.file = dir.join(&anchor.file).to_string_lossy().to_string();
+                }
+                for anchor in s.anchors.iter_mut().filter_map(|a| a.text.as_mut()).flatten() {
+                    let Some(pattern) = &anchor.val_regex else { continue };
+                    match Regex::new(pattern) {
+                        Ok(re) => anchor.regex = Some(re),
+                        Err(e) => problems.push(NavConfigError::InvalidRegex { scene: s.id.clone(), message: e.to_string() }),
+                    }
+                }
+                if let Err(e) = parse_condition(&mut s) {
+                    problems.push(NavConfigError::InvalidLogic { scene: s.id.clone(), message: e });
+                    // 无效的表达式按永不命中处理
+                    s.condition = Some(Expr::Or(Vec::new()));
+                }
+                problems.extend(check_bounds(&s, resolution));
+                // 重复的 id 以后出现的为准
+                if let Some(first) = origin.insert(s.id.clone(), file.clone()) {
+                    problems.push(NavConfigError::DuplicateScene { id: s.id.clone(), first, second: file.clone() });
+                }
+                map.insert(s.id.clone(), s);
+            }
+        }
+        for s in map.values() {
+            for t in s.transitions.iter().flatten() {
+                // 指向尚未定义的界面的跳转只提示，不算错误 (地图可以先画一半)
+                if !map.contains_key(&t.target) {
+                    problems.push(NavConfigError::UnknownTarget { scene: s.id.clone(), target: t.target.clone() });
+                }
+                if let Some(expect) = t.expect.as_ref().filter(|e| !map.contains_key(*e)) {
+                    problems.push(NavConfigError::UnknownExpect { scene: s.id.clone(), target: t.target.clone(), expect: expect.clone() });
+                }
+                if t.coords.is_none() && t.actions.is_empty() {
+                    problems.push(NavConfigError::EmptyTransition { scene: s.id.clone(), target: t.target.clone() });
+                }
+                for key in unknown_keys(&t.actions) {
+                    problems.push(NavConfigError::UnknownKey { scene: s.id.clone(), context: format!("到 [{}] 的跳转", t.target), key: key.to_string() });
+                }
+            }
+            if let Some(dismiss) = &s.dismiss {
+                if dismiss.is_empty() || s.anchors.is_none() {
+                    problems.push(NavConfigError::InvalidDismiss(s.id.clone()));
+                }
+                for key in unknown_keys(dismiss) {
+                    problems.push(NavConfigError::UnknownKey { scene: s.id.clone(), context: "dismiss".to_string(), key: key.to_string() });
+                }
+            }
+        }
+        let mut templates = HashMap::new();
+        for anchor in map.values().filter_map(|s| s.anchors.as_ref()).filter_map(|a| a.image.as_ref()).flatten() {
+            if templates.contains_key(&anchor.file) { continue; }
+            match Template::load(&anchor.file) {
+                Ok(t) => { templates.insert(anchor.file.clone(), t); }
+                // 加载失败的锚点视为不匹配
+                Err(e) => problems.push(NavConfigError::Template { file: anchor.file.clone(), message: e }),
+            }
+        }
+        let graph = Self { scenes: map, templates, base: resolution.map(|[w, h]| (w, h)), sources };
+        Ok((graph, problems))
+    }
+
+    /// 导出为 Graphviz DOT：节点为界面，边为跳转 (标注点击坐标、动作与代价)。
+    /// 处理器界面为蓝色、打断界面为黄色、没有出边的死路为浅红色、未定义的跳转目标为红色虚线框，
+    /// 没有锚点 (无法识别) 的界面为虚线框；没有入边与没有出边的界面另列在文件开头的注释中
+    fn to_dot(&self) -> String {
+        let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"));
+        let mut ids: Vec<&String> = self.scenes.keys().collect();
+        ids.sort();
+        let targets: HashSet<&str> =
+            self.scenes.values().flat_map(|s| s.transitions.iter().flatten()).map(|t| t.target.as_str()).collect();
+        let dead_end = |s: &Scene| s.handler.is_none() && s.dismiss.is_none() && s.transitions.as_ref().is_none_or(|t| t.is_empty());
+        let unreached: Vec<&str> = ids
+            .iter()
+            .filter(|id| self.scenes[id.as_str()].dismiss.is_none() && !targets.contains(id.as_str()))
+            .map(|id| id.as_str())
+            .collect();
+        let dead: Vec<&str> = ids.iter().filter(|id| dead_end(&self.scenes[id.as_str()])).map(|id| id.as_str()).collect();
+
+        let mut out = String::from("// 由 NavEngine::export_graph 生成，可用 dot -Tsvg 渲染\n");
+        out += &format!("// 没有入边 (只能作为起点): {}\n", unreached.join(", "));
+        out += &format!("// 没有出边 (死路): {}\n", dead.join(", "));
+        out += "digraph ui_map {\n    rankdir=LR;\n    node [shape=box, style=rounded, fontname=\"Microsoft YaHei\"];\n";
+        out += "    edge [fontname=\"Microsoft YaHei\", fontsize=10];\n\n";
+
+        for id in &ids {
+            let s = &self.scenes[id.as_str()];
+            let mut label = id.to_string();
+            let fill = if let Some(handler) = &s.handler {
+                label += &format!("\n[{}]", handler);
+                Some("lightblue")
+            } else if s.dismiss.is_some() {
+                label += "\n(打断界面)";
+                Some("lightyellow")
+            } else {
+                dead_end(s).then_some("mistyrose")
+            };
+            let mut style = vec!["rounded"];
+            style.extend(fill.map(|_| "filled"));
+            if s.anchors.is_none() {
+                style.push("dashed");
+            }
+            let mut attrs = vec![format!("label={}", quote(&label)), format!("style={}", quote(&style.join(",")))];
+            attrs.extend(fill.map(|c| format!("fillcolor={}", c)));
+            out += &format!("    {} [{}];\n", quote(id), attrs.join(", "));
+        }
+
+        let mut missing: Vec<&str> = targets.iter().copied().filter(|t| !self.scenes.contains_key(*t)).collect();
+        missing.sort();
+        for t in &missing {
+            out += &format!("    {} [label={}, color=red, fontcolor=red, style=\"rounded,dashed\"];\n", quote(t), quote(&format!("{}\n(未定义)", t)));
+        }
+
+        out += "\n";
+        for id in &ids {
+            for t in self.scenes[id.as_str()].transitions.iter().flatten() {
+                let mut lines: Vec<String> = t.coords.iter().map(|c| format!("点击 ({}, {})", c[0], c[1])).collect();
+                lines.extend(t.actions.iter().map(TransitionAction::describe));
+                if let Some(expect) = t.expect.as_ref().filter(|e| **e != t.target) {
+                    lines.push(format!("确认 {}", expect));
+                }
+                lines.push(format!("{}ms", t.cost()));
+                out += &format!("    {} -> {} [label={}];\n", quote(id), quote(&t.target), quote(&lines.join("\n")));
+            }
+        }
+        out += "}\n";
+        out
+    }
+}
+
+fn write_dot(graph: &SceneGraph, path: &Path) -> Result<(), String> {
+    fs::write(path, graph.to_dot()).map_err(|e| format!("写入界面图 {} 失败: {}", path.display(), e))
+}
+
+pub struct NavEngine {
+    map_path: String,
+    graph: RwLock<Arc<SceneGraph>>,
     interface: GameInterface,
+    /// 导航失败时的现场转储 ([debug_dump])
+    dump: RwLock<DumpConfig>,
+    /// find_image 用过的模板 (路径 -> (文件修改时间, 模板))；文件更新后自动重新加载
+    found_templates: Mutex<FoundTemplates>,
+    /// 动态实体检测 ([detector])；未启用时为 None
+    detector: RwLock<Option<Arc<dyn ObjectDetector>>>,
 }
 
 impl NavEngine {
     pub fn new(file_path: &str, driver: Arc<Mutex<HumanDriver>>) -> Self {
-        let content = fs::read_to_string(file_path).expect("无法读取 TOML");
-        let root: TomlRoot = toml::from_str(&content).expect("TOML 解析错误");
-        let mut map = HashMap::new();
-        for s in root.scenes { map.insert(s.id.clone(), s); }
-        Self { scenes: map, interface: GameInterface::new(driver) }
+        Self::with_capture(file_path, driver, Box::new(GdiCapture::default()))
+    }
+
+    /// 使用指定的截图后端创建导航引擎 (OCR 使用 Windows OCR)
+    pub fn with_capture(file_path: &str, driver: Arc<Mutex<HumanDriver>>, capture: Box<dyn CaptureBackend>) -> Self {
+        Self::with_backends(file_path, driver, capture, Box::new(WindowsOcr::new()))
+    }
+
+    /// 使用指定的截图与 OCR 后端创建导航引擎；地图无法读取或解析时 panic，需要自行处理错误时用 try_with_backends
+    pub fn with_backends(
+        file_path: &str,
+        driver: Arc<Mutex<HumanDriver>>,
+        capture: Box<dyn CaptureBackend>,
+        ocr: Box<dyn OcrBackend>,
+    ) -> Self {
+        Self::try_with_backends(file_path, driver, capture, ocr).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// 同 with_backends；地图无法读取或解析时返回错误，其余问题打印后照常载入 (有问题的锚点永不命中)
+    pub fn try_with_backends(
+        file_path: &str,
+        driver: Arc<Mutex<HumanDriver>>,
+        capture: Box<dyn CaptureBackend>,
+        ocr: Box<dyn OcrBackend>,
+    ) -> Result<Self, NavConfigError> {
+        let (graph, problems) = SceneGraph::load(file_path)?;
+        for p in &problems {
+            println!("⚠️ [Nav] {}", p);
+        }
+        let interface = GameInterface::new(driver, capture, ocr);
+        *interface.base.write().unwrap() = graph.base;
+        Ok(Self { map_path: file_path.to_string(), graph: RwLock::new(Arc::new(graph)), interface,
+            dump: RwLock::new(DumpConfig::default()),
+            found_templates: Mutex::new(HashMap::new()),
+            detector: RwLock::new(None),
+        })
+    }
+
+    /// 只检查地图文件、不创建引擎 (CLI 的 validate 子命令)；handlers 为已注册的处理器代号。
+    /// 返回全部问题，为空表示地图没有问题；is_warning 的问题不影响载入
+    pub fn validate(file_path: &str, handlers: &[&str]) -> Vec<NavConfigError> {
+        let (graph, mut problems) = match SceneGraph::load(file_path) {
+            Ok(loaded) => loaded,
+            Err(e) => return vec![e],
+        };
+        let mut ids: Vec<&String> = graph.scenes.keys().collect();
+        ids.sort();
+        for id in ids {
+            if let Some(handler) = graph.scenes[id].handler.as_ref().filter(|h| !handlers.contains(&h.as_str())) {
+                problems.push(NavConfigError::MissingHandler { scene: id.clone(), handler: handler.clone() });
+            }
+        }
+        problems
+    }
+
+    /// 把当前场景图导出为 Graphviz DOT 文件，检查可达性与死路 (dot -Tsvg ui_map.dot -o ui_map.svg)
+    pub fn export_graph(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        write_dot(&self.graph(), path.as_ref())
+    }
+
+    /// 不创建引擎，直接把地图文件导出为 DOT (CLI 的 graph 子命令)；返回导出的界面数
+    pub fn export_map_graph(file_path: &str, path: impl AsRef<Path>) -> Result<usize, String> {
+        let (graph, _) = SceneGraph::load(file_path).map_err(|e| e.to_string())?;
+        write_dot(&graph, path.as_ref())?;
+        Ok(graph.scenes.len())
+    }
+
+    /// 注册导航事件观察者
+    pub fn add_observer(&self, observer: Arc<dyn NavObserver>) {
+        if let Ok(mut observers) = self.interface.observers.write() {
+            observers.push(observer);
+        }
+    }
+
+    /// 设置导航失败时的现场转储 (enabled = false 时关闭)
+    pub fn set_failure_dump(&self, cfg: DumpConfig) {
+        if let Ok(mut dump) = self.dump.write() {
+            *dump = cfg;
+        }
+    }
+
+    /// 设置目标检测后端 (多个引擎可共用同一个模型)；None 表示关闭检测
+    pub fn set_detector(&self, detector: Option<Arc<dyn ObjectDetector>>) {
+        if let Ok(mut current) = self.detector.write() {
+            *current = detector;
+        }
+    }
+
+    /// 保存失败现场：整屏截图、各锚点裁剪图与识别报告
+    fn dump_failure(&self, target_id: &str, report: &RecognitionReport) {
+        let Some(cfg) = self.dump.read().ok().filter(|c| c.enabled).map(|c| c.clone()) else { return };
+        let screen = self.interface.capture.frame_size().and_then(|(w, h)| self.interface.capture.capture_area(0, 0, w, h).ok());
+        match dump::write(&cfg, target_id, report, screen.as_ref()) {
+            Ok(dir) => println!("🗂️ [Nav] 失败现场已保存到 {}", dir.display()),
+            Err(e) => println!("⚠️ [Nav] 保存失败现场出错: {}", e),
+        }
+    }
+
+    /// 当前场景图；热重载只替换 Arc，正在进行的识别与导航继续使用旧图直到结束
+    fn graph(&self) -> Arc<SceneGraph> {
+        Arc::clone(&self.graph.read().unwrap())
+    }
+
+    /// 重新载入地图文件；有任何问题 (提示类除外) 时拒绝新文件并保留当前场景图，返回新图的场景数
+    pub fn reload(&self) -> Result<usize, String> {
+        let (graph, problems) = SceneGraph::load(&self.map_path).map_err(|e| e.to_string())?;
+        let (warnings, errors): (Vec<_>, Vec<_>) = problems.into_iter().partition(NavConfigError::is_warning);
+        if !errors.is_empty() {
+            return Err(errors.iter().map(ToString::to_string).collect::<Vec<_>>().join("；"));
+        }
+        for w in &warnings {
+            println!("⚠️ [Nav] {}", w);
+        }
+        let count = graph.scenes.len();
+        let mut current = self.graph.write().map_err(|_| "场景图状态异常".to_string())?;
+        *self.interface.base.write().map_err(|_| "场景图状态异常".to_string())? = graph.base;
+        *current = Arc::new(graph);
+        drop(current);
+        // 模板文件可能已更换，旧的匹配结果不再可信
+        if let Ok(mut cache) = self.interface.match_cache.lock() { cache.clear(); }
+        Ok(count)
+    }
+
+    /// 后台监视地图文件 (notify)，修改、删除或新增后自动重载；引擎释放后线程自动退出。
+    /// 重载失败时提示原因并继续监视上一次成功载入的文件列表，文件恢复后再次重载
+    pub fn watch(engine: &Arc<Self>) {
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = match notify::recommended_watcher(tx) {
+            Ok(w) => w,
+            Err(e) => return println!("⚠️ [Nav] 无法监视 {}: {}", engine.map_path, e),
+        };
+        let weak = Arc::downgrade(engine);
+        let path = engine.map_path.clone();
+        let mut sources = watch_sources(&engine.graph().sources);
+        let mut dirs = HashSet::new();
+        rewatch(&mut watcher, &mut dirs, &sources);
+
+        let spawned = thread::Builder::new().name("ui-map-watch".to_string()).spawn(move || loop {
+            match rx.recv_timeout(WATCH_IDLE) {
+                Ok(Ok(event)) if event.paths.iter().any(|p| affects(&sources, p)) => {}
+                Ok(_) => continue,
+                Err(mpsc::RecvTimeoutError::Timeout) if weak.strong_count() > 0 => continue,
+                Err(_) => return,
+            }
+            thread::sleep(WATCH_DEBOUNCE);
+            while rx.try_recv().is_ok() {}
+
+            let Some(engine) = weak.upgrade() else { return };
+            match engine.reload() {
+                Ok(count) => println!("🔄 [Nav] 已重新载入 {} ({} 个界面)", path, count),
+                Err(e) => println!("⚠️ [Nav] {} 未生效，继续使用旧地图: {}", path, e),
+            }
+            // 文件列表按当前 (重载成功则为新的，否则为上一次成功载入的) 场景图重建
+            sources = watch_sources(&engine.graph().sources);
+            rewatch(&mut watcher, &mut dirs, &sources);
+        });
+        if let Err(e) = spawned {
+            println!("⚠️ [Nav] 无法监视 {}: {}", engine.map_path, e);
+        }
+    }
+
+    /// 截取屏幕区域 (经由当前截图后端)
+    pub fn capture_area(&self, rect: [i32; 4]) -> Result<image::RgbaImage, String> {
+        let w = (rect[2] - rect[0]).max(1) as u32;
+        let h = (rect[3] - rect[1]).max(1) as u32;
+        self.interface.capture.capture_area(rect[0], rect[1], w, h)
+    }
+
+    /// 截图坐标系的尺寸 (显示器或窗口客户区)
+    pub fn frame_size(&self) -> Option<(u32, u32)> {
+        self.interface.capture.frame_size()
+    }
+
+    pub fn capture_backend_name(&self) -> &'static str {
+        self.interface.capture.name()
+    }
+
+    pub fn ocr_backend_name(&self) -> &'static str {
+        self.interface.ocr.name()
     }
 
     pub fn test_ocr_on_file(&self, filename: &str, expected: &str) {
         self.interface.debug_ocr_file(filename, expected);
     }
 
+    /// 将按 base_resolution 编写的坐标换算为截图坐标 (未设置 base_resolution 时原样返回)；
+    /// capture_area / ocr_area 接收的是截图坐标，业务模块的固定坐标可先经此换算
+    pub fn scale_rect(&self, rect: [i32; 4]) -> [i32; 4] {
+        self.interface.to_screen_rect(rect)
+    }
+
+    pub fn scale_point(&self, pos: [i32; 2]) -> [i32; 2] {
+        self.interface.to_screen_point(pos)
+    }
+
     pub fn ocr_area(&self, rect: [i32; 4]) -> String {
         self.interface.get_text_from_area(rect)
     }
 
+    /// 按指定语言识别区域文字 (如 "en" 读取纯数字计数器)，其余同 ocr_area
+    pub fn ocr_area_lang(&self, rect: [i32; 4], lang: &str) -> String {
+        self.interface.read_area(rect, Some(lang)).0
+    }
+
+    /// 当前画面上 (x, y) (截图坐标) 的颜色；截图失败时为 None
+    pub fn get_pixel(&self, x: i32, y: i32) -> Option<Rgb> {
+        self.interface.grab(x, y, 1, 1).ok().and_then(|img| Rgb::first_pixel(&img))
+    }
+
+    /// 区域 rect (截图坐标) 的平均色，如按钮置灰 / 高亮的判断
+    pub fn average_color(&self, rect: [i32; 4]) -> Option<Rgb> {
+        self.capture_area(rect).ok().and_then(|img| Rgb::average(&img))
+    }
+
+    /// 区域 rect (截图坐标) 内与 color 相差在容差内的像素比例 (0~1)，如血条中红色的占比；截图失败时为 0
+    pub fn color_ratio(&self, rect: [i32; 4], color: Rgb, tolerance: u8) -> f32 {
+        self.capture_area(rect).map_or(0.0, |img| color.ratio_in(&img, tolerance))
+    }
+
+    /// 在整个画面上搜索模板图片，返回得分不低于 threshold 的最佳位置 (截图坐标)；用于位置不固定的元素
+    pub fn find_image(&self, template: &Path, threshold: f32) -> Option<[i32; 4]> {
+        let (w, h) = self.frame_size()?;
+        self.find_image_in([0, 0, w as i32, h as i32], template, threshold)
+    }
+
+    /// 在区域 region (截图坐标) 内多尺度搜索模板图片；模板按 base_resolution 下的尺寸裁取
+    pub fn find_image_in(&self, region: [i32; 4], template: &Path, threshold: f32) -> Option<[i32; 4]> {
+        let template = match self.load_found_template(template) {
+            Ok(t) => t,
+            Err(e) => {
+                println!("⚠️ [Nav] {}", e);
+                return None;
+            }
+        };
+        let gray = image::DynamicImage::ImageRgba8(self.capture_area(region).ok()?).into_luma8();
+        let (sx, sy) = self.interface.scale();
+        let expected = ((sx + sy) / 2.0) as f32;
+        let (m, (tw, th)) = FIND_SCALES
+            .iter()
+            .filter_map(|k| template.scaled(expected * k))
+            .filter_map(|t| t.search(&gray).map(|m| (m, t.size())))
+            .max_by(|a, b| a.0.score.total_cmp(&b.0.score))?;
+        let (x, y) = (region[0] + m.x as i32, region[1] + m.y as i32);
+        (m.score >= threshold).then_some([x, y, x + tw as i32, y + th as i32])
+    }
+
+    /// 是否已配置目标检测；未配置时 detect_objects 总是返回空
+    pub fn has_detector(&self) -> bool {
+        self.detector.read().map(|d| d.is_some()).unwrap_or(false)
+    }
+
+    /// 在区域 region (截图坐标) 内检测动态实体 (敌人、掉落物等)，返回的框为截图坐标；
+    /// 未配置检测或截图 / 推理失败时为空
+    pub fn detect_objects(&self, region: [i32; 4]) -> Vec<Detection> {
+        let Some(detector) = self.detector.read().ok().and_then(|d| d.clone()) else { return Vec::new() };
+        let img = match self.capture_area(region) {
+            Ok(img) => img,
+            Err(e) => {
+                println!("⚠️ [Nav] 目标检测截图失败: {}", e);
+                return Vec::new();
+            }
+        };
+        match detector.detect(&img) {
+            Ok(found) => found
+                .into_iter()
+                .map(|d| {
+                    let r = d.rect;
+                    Detection { rect: [r[0] + region[0], r[1] + region[1], r[2] + region[0], r[3] + region[1]], ..d }
+                })
+                .collect(),
+            Err(e) => {
+                println!("⚠️ [Nav] 目标检测 [{}] 失败: {}", detector.name(), e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// 在整个画面上检测动态实体 (截图坐标)
+    pub fn detect_all(&self) -> Vec<Detection> {
+        let Some((w, h)) = self.frame_size() else { return Vec::new() };
+        self.detect_objects([0, 0, w as i32, h as i32])
+    }
+
+    fn load_found_template(&self, path: &Path) -> Result<Arc<Template>, String> {
+        let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+        let mut cache = self.found_templates.lock().map_err(|_| "模板缓存状态异常".to_string())?;
+        if let Some((cached, template)) = cache.get(path) {
+            if *cached == modified {
+                return Ok(Arc::clone(template));
+            }
+        }
+        let template = Arc::new(Template::load(&path.to_string_lossy())?);
+        cache.insert(path.to_path_buf(), (modified, Arc::clone(&template)));
+        Ok(template)
+    }
+
+    /// 读取区域 rect (截图坐标) 内的数字 ("12,345" / "1.2万" / "3/5" 取分子)；
+    /// 先用偏向数字的预处理识别，解析不出再退回常规 OCR
+    pub fn ocr_number(&self, rect: [i32; 4]) -> Option<i64> {
+        numeric::parse_number(&self.interface.read_digits(rect)).or_else(|| numeric::parse_number(&self.ocr_area(rect)))
+    }
+
+    /// 读取区域 rect (截图坐标) 内 "3/5" 形式的进度，返回 (分子, 分母)
+    pub fn ocr_fraction(&self, rect: [i32; 4]) -> Option<(i64, i64)> {
+        numeric::parse_fraction(&self.interface.read_digits(rect)).or_else(|| numeric::parse_fraction(&self.ocr_area(rect)))
+    }
+
+    /// 识别区域 rect (截图坐标) 内的文字行与词，返回的区域同为截图坐标；用于列表解析与按文字定位
+    pub fn ocr_lines(&self, rect: [i32; 4]) -> Vec<OcrLine> {
+        let (w, h) = ((rect[2] - rect[0]).max(1) as u32, (rect[3] - rect[1]).max(1) as u32);
+        let Ok(img) = self.interface.grab(rect[0], rect[1], w, h) else { return Vec::new() };
+        // 与 ocr_area 一样放大 2 倍再识别，结果坐标缩回原尺度
+        let scaled = image::DynamicImage::ImageRgba8(img).resize(w * 2, h * 2, image::imageops::FilterType::Lanczos3);
+        let to_capture = |r: [i32; 4]| [rect[0] + r[0] / 2, rect[1] + r[1] / 2, rect[0] + (r[2] + 1) / 2, rect[1] + (r[3] + 1) / 2];
+        let mut lines = self.interface.ocr.recognize_lines(&scaled).unwrap_or_default();
+        for line in &mut lines {
+            line.rect = to_capture(line.rect);
+            for word in &mut line.words {
+                word.rect = to_capture(word.rect);
+            }
+        }
+        lines
+    }
+
+    /// 在区域 rect (截图坐标) 内查找文字 needle，返回其所在区域 (截图坐标)
+    pub fn find_text(&self, rect: [i32; 4], needle: &str) -> Option<[i32; 4]> {
+        self.ocr_lines(rect).iter().find_map(|line| line.locate(needle))
+    }
+
+    /// 在区域 region (截图坐标) 内识别并定位文字 needle，在其外接矩形内拟人点击 (落点按拟人档案散布)；
+    /// 在 options.timeout 内都没找到时返回 Ok(false)
+    pub fn find_text_and_click(&self, region: [i32; 4], needle: &str, options: &TextClickOptions) -> Result<bool, DriverError> {
+        let mut target = None;
+        let found = self.wait_until(options.timeout, WAIT_POLL, || {
+            target = self.find_text(region, needle);
+            target.is_some()
+        });
+        let (Ok(_), Some(target)) = (found, target) else {
+            println!("⚠️ [Nav] 区域 {:?} 内未找到 \"{}\"", region, needle);
+            return Ok(false);
+        };
+        println!("👆 点击文字 \"{}\" {:?}", needle, target);
+        if let Ok(mut bot) = self.interface.driver.lock() {
+            bot.with_deadline(CLICK_TIMEOUT, |bot| {
+                let (x, y) = bot.point_in_rect(target);
+                if options.react {
+                    return bot.react_then_click(x, y, options.button, 0.5);
+                }
+                bot.move_to_humanly(x, y, 0.5)?;
+                bot.click_humanly(options.button, 0)
+            })?;
+        }
+        Ok(true)
+    }
+
+    /// 识别场景 scene 中名为 anchor 的文字锚点区域，并用其 val_regex 提取捕获组
+    /// 例如 `val_regex = "剩余次数[:：]\\s*(\\d+)/3"` 返回 ["2"]；未找到锚点或未匹配时返回 None
+    pub fn read_value(&self, scene: &str, anchor: &str) -> Option<Vec<String>> {
+        let graph = self.graph();
+        let a = graph
+            .scenes
+            .get(scene)?
+            .anchors
+            .as_ref()?
+            .text
+            .as_ref()?
+            .iter()
+            .find(|t| t.name.as_deref() == Some(anchor))?;
+        if a.regex.is_none() {
+            println!("⚠️ [Nav] 锚点 [{}/{}] 没有可用的 val_regex", scene, anchor);
+            return None;
+        }
+        a.captures(&self.interface.read_area(self.interface.to_screen_rect(a.rect), a.lang.as_deref()).0)
+    }
+
+    /// 等待区域 rect (地图坐标) 的画面发生变化，每 100ms 截一次小图比较分块签名；
+    /// 在 timeout 内变化返回 true。适合等待加载、动画结束，而不必反复跑完整的识别流程
+    pub fn wait_for_change(&self, rect: [i32; 4], timeout: Duration) -> bool {
+        let [x1, y1, x2, y2] = self.interface.to_screen_rect(rect);
+        let (w, h) = ((x2 - x1).max(1) as u32, (y2 - y1).max(1) as u32);
+        let grab = || self.interface.capture.capture_area(x1, y1, w, h).ok().map(|img| Signature::of(&img));
+        let start = Instant::now();
+        let Some(first) = grab() else { return false };
+        while start.elapsed() < timeout {
+            thread::sleep(Duration::from_millis(100));
+            if grab().is_some_and(|s| s.changed(&first)) {
+                return true;
+            }
+        }
+        false
+    }
+
     fn get_match_score(&self, target_id: &str) -> usize {
-        if let Some(scene) = self.scenes.get(target_id) {
-            if scene.anchors.is_none() { return 0; }
-            let anchors = scene.anchors.as_ref().unwrap();
-            let mut score = 0;
-            let mut total_checks = 0;
-            if let Some(texts) = &anchors.text {
-                for t in texts {
-                    total_checks += 1;
-                    if self.interface.check_text_anchor(t.rect, &t.val) { score += 1; }
-                }
-            }
-            if let Some(colors) = &anchors.color {
-                for c in colors {
-                    total_checks += 1;
-                    if self.interface.check_color_anchor(c.pos, &c.val, c.tol) { score += 1; }
-                }
-            }
-            let passed = match scene.logic.to_lowercase().as_str() {
-                "or" => score > 0,              
-                _ => score == total_checks && total_checks > 0, 
-            };
-            if passed { return score; }
+        self.evaluate_scene(target_id).map_or(0, |r| r.score())
+    }
+
+    /// 检查界面的锚点；没有该界面或没有锚点时为 None。
+    /// 结果已能确定 (and 已有锚点不命中、or 已有锚点命中) 时停止检查其余锚点，未检查的锚点记为不命中，
+    /// 因此得分取决于检查先后，只适合判断是否通过；需要比较得分时用 evaluate_scene_with(id, false)
+    fn evaluate_scene(&self, target_id: &str) -> Option<SceneReport> {
+        self.evaluate_scene_with(target_id, true)
+    }
+
+    /// early_stop 为 false 时检查全部锚点 (失败诊断需要完整的逐锚点结果，候选界面比较得分时也需要)
+    fn evaluate_scene_with(&self, target_id: &str, early_stop: bool) -> Option<SceneReport> {
+        let graph = self.graph();
+        let scene = graph.scenes.get(target_id)?;
+        let anchors = scene.anchors.as_ref()?;
+        // 结果依次为文字、颜色、图像锚点 (与 logic 表达式中的编号一致)
+        let mut jobs: Vec<AnchorJob> = Vec::new();
+        jobs.extend(anchors.text.iter().flatten().map(AnchorJob::Text));
+        jobs.extend(anchors.color.iter().flatten().map(AnchorJob::Color));
+        jobs.extend(anchors.image.iter().flatten().map(AnchorJob::Image));
+        let (texts, colors) = (anchors.text.as_ref().map_or(0, Vec::len), anchors.color.as_ref().map_or(0, Vec::len));
+        let decide = |results: &[Option<AnchorReport>]| -> Option<bool> {
+            let known: Vec<Option<bool>> = results.iter().map(|r| r.as_ref().map(|a| a.passed)).collect();
+            match (&scene.condition, scene.logic.to_lowercase().as_str()) {
+                (Some(expr), _) => expr.eval_partial(&|kind, index| {
+                    let offset = match kind {
+                        "text" => 0,
+                        "color" => texts,
+                        _ => texts + colors,
+                    };
+                    known.get(offset + index).copied().unwrap_or(Some(false))
+                }),
+                (None, "or") if known.contains(&Some(true)) => Some(true),
+                (None, "or") => known.iter().all(Option::is_some).then_some(false),
+                _ if known.contains(&Some(false)) || known.is_empty() => Some(false),
+                _ => known.iter().all(Option::is_some).then_some(true),
+            }
+        };
+
+        // 先检查便宜的颜色、图像锚点，它们能确定结果时就不必再跑 OCR
+        let mut order: Vec<usize> = (0..jobs.len()).collect();
+        order.sort_by_key(|&i| matches!(jobs[i], AnchorJob::Text(_)));
+        let results: Mutex<Vec<Option<AnchorReport>>> = Mutex::new(vec![None; jobs.len()]);
+        let (next, decided) = (AtomicUsize::new(0), AtomicBool::new(false));
+        let work = || loop {
+            if early_stop && decided.load(Ordering::Relaxed) {
+                break;
+            }
+            let Some(&i) = order.get(next.fetch_add(1, Ordering::Relaxed)) else { break };
+            let report = self.check_anchor(&graph, &jobs[i]);
+            let Ok(mut results) = results.lock() else { break };
+            results[i] = Some(report);
+            if early_stop && decide(&results).is_some() {
+                decided.store(true, Ordering::Relaxed);
+            }
+        };
+        let workers = ANCHOR_WORKERS.min(jobs.len());
+        if workers > 1 {
+            thread::scope(|s| {
+                for _ in 0..workers {
+                    s.spawn(work);
+                }
+            });
+        } else {
+            work();
+        }
+
+        let results = results.into_inner().unwrap_or_default();
+        let passed = decide(&results).unwrap_or(false);
+        let reports = results
+            .into_iter()
+            .zip(&jobs)
+            .map(|(r, job)| r.unwrap_or_else(|| self.skipped_anchor(job)))
+            .collect();
+        Some(SceneReport { id: target_id.to_string(), passed, anchors: reports })
+    }
+
+    fn check_anchor(&self, graph: &SceneGraph, job: &AnchorJob) -> AnchorReport {
+        match *job {
+            AnchorJob::Text(t) => self.interface.check_text_anchor(t).negated(t.negate),
+            AnchorJob::Color(c) => self.interface.check_color_anchor(c.pos, &c.val, c.tol).negated(c.negate),
+            AnchorJob::Image(i) => match graph.templates.get(&i.file) {
+                Some(t) => self.interface.check_image_anchor(i.rect, &i.file, t, i.threshold).negated(i.negate),
+                // 模板缺失时无法判断，反向锚点同样不算命中
+                None => AnchorReport {
+                    kind: "image",
+                    rect: self.interface.to_screen_rect(i.rect),
+                    expected: i.file.clone(),
+                    observed: "模板未加载".to_string(),
+                    confidence: None,
+                    passed: false,
+                },
+            },
         }
-        0
+    }
+
+    /// 界面结果已确定、未实际检查的锚点
+    fn skipped_anchor(&self, job: &AnchorJob) -> AnchorReport {
+        let (kind, rect, expected) = match *job {
+            AnchorJob::Text(t) => ("text", self.interface.to_screen_rect(t.rect), t.val_regex.clone().unwrap_or_else(|| t.val.clone())),
+            AnchorJob::Color(c) => {
+                let [x, y] = self.interface.to_screen_point(c.pos);
+                ("color", [x, y, x + 1, y + 1], format!("{} ±{}", c.val, c.tol))
+            }
+            AnchorJob::Image(i) => ("image", self.interface.to_screen_rect(i.rect), format!("{} ≥{:.2}", i.file, i.threshold)),
+        };
+        AnchorReport { kind, rect, expected, observed: "未检查 (结果已确定)".to_string(), confidence: None, passed: false }
+    }
+
+    /// 在同一份快照上重新检查这些界面，作为失败时的诊断信息
+    fn diagnose(&self, reason: String, ids: &[&str]) -> RecognitionReport {
+        let scenes = self.with_snapshot(ids, || ids.iter().filter_map(|id| self.evaluate_scene_with(id, false)).collect());
+        RecognitionReport { reason, scenes }
+    }
+
+    /// 场景全部锚点的截图区域 (截图坐标)；图像锚点与 check_image_anchor 一样至少取模板大小
+    fn anchor_regions(&self, id: &str) -> Vec<[i32; 4]> {
+        let graph = self.graph();
+        let Some(anchors) = graph.scenes.get(id).and_then(|s| s.anchors.as_ref()) else { return Vec::new() };
+        let mut rects: Vec<[i32; 4]> = Vec::new();
+        rects.extend(anchors.text.iter().flatten().map(|t| t.rect));
+        rects.extend(anchors.color.iter().flatten().map(|c| [c.pos[0], c.pos[1], c.pos[0] + 1, c.pos[1] + 1]));
+        for i in anchors.image.iter().flatten() {
+            let (tw, th) = graph.templates.get(&i.file).map_or((0, 0), |t| t.size());
+            let r = i.rect;
+            rects.push([r[0], r[1], r[0] + (r[2] - r[0]).max(tw as i32), r[1] + (r[3] - r[1]).max(th as i32)]);
+        }
+        rects.into_iter().map(|r| self.interface.to_screen_rect(r)).collect()
+    }
+
+    /// 先把这些场景的锚点区域一次截好，再在同一帧上逐个评分 (只截需要的区域，而不是逐个锚点截图)
+    fn with_snapshot<R>(&self, ids: &[&str], f: impl FnOnce() -> R) -> R {
+        self.interface.take_snapshot(ids.iter().flat_map(|id| self.anchor_regions(id)).collect());
+        let result = f();
+        self.interface.clear_snapshot();
+        result
     }
 
     pub fn identify_current_scene(&self, hint: Option<&str>) -> Option<String> {
+        self.identify_with_report(hint).0
+    }
+
+    /// 检查打断界面 (带 dismiss 的界面)，命中则执行关闭动作；返回关闭的次数
+    pub fn handle_interrupts(&self) -> usize {
+        let graph = self.graph();
+        let ids: Vec<&str> = graph.scenes.values().filter(|s| s.dismiss.is_some()).map(|s| s.id.as_str()).collect();
+        let mut handled = 0;
+        while !ids.is_empty() && handled < MAX_INTERRUPTS {
+            let Some(id) = self.with_snapshot(&ids, || ids.iter().copied().find(|id| self.get_match_score(id) > 0)) else { break };
+            println!("🧹 检测到打断界面 [{}]，执行关闭动作", id);
+            for action in graph.scenes[id].dismiss.iter().flatten() {
+                if let Err(e) = self.interface.perform_action(action, None) {
+                    println!("⚠️ [Nav] 关闭 [{}] 失败: {}", id, e);
+                    return handled;
+                }
+            }
+            handled += 1;
+            self.interface.emit(NavEvent::InterruptDismissed { id });
+            thread::sleep(INTERRUPT_SETTLE);
+        }
+        handled
+    }
+
+    /// 识别当前界面，同时返回所有候选界面的逐锚点结果 (命中预期目标时只含该界面)；
+    /// 识别前先关闭打断界面，打断界面本身不会作为识别结果
+    pub fn identify_with_report(&self, hint: Option<&str>) -> (Option<String>, RecognitionReport) {
+        self.handle_interrupts();
         println!("👀 扫描当前界面...");
+        let mut report = RecognitionReport::default();
         if let Some(target_id) = hint {
-            if self.get_match_score(target_id) > 0 {
-                println!("✅ 命中预期目标: [{}]", target_id);
-                return Some(target_id.to_string());
+            if let Some(scene) = self.with_snapshot(&[target_id], || self.evaluate_scene(target_id)) {
+                let hit = scene.passed;
+                report.scenes.push(scene);
+                if hit {
+                    println!("✅ 命中预期目标: [{}]", target_id);
+                    let score = report.scenes[0].score();
+                    self.interface.emit(NavEvent::SceneRecognized { id: target_id, score });
+                    return (Some(target_id.to_string()), report);
+                }
             }
         }
-        let mut best_match: Option<String> = None;
-        let mut max_score = 0;
-        for (id, _) in &self.scenes {
-            if let Some(h) = hint { if h == id { continue; } }
-            let score = self.get_match_score(id);
-            if score > 0 && score > max_score {
-                max_score = score;
-                best_match = Some(id.clone());
+        let graph = self.graph();
+        let candidates: Vec<&str> = graph
+            .scenes
+            .values()
+            .filter(|s| s.dismiss.is_none() && hint != Some(s.id.as_str()))
+            .map(|s| s.id.as_str())
+            .collect();
+        let (best_match, scenes) = self.best_scene(&candidates);
+        report.scenes.extend(scenes);
+        match &best_match {
+            Some((id, score)) => {
+                println!("✅ 定位: [{}] (得分: {})", id, score);
+                self.interface.emit(NavEvent::SceneRecognized { id, score: *score });
+            }
+            None => {
+                report.reason = "无法识别当前界面".to_string();
+                self.interface.emit(NavEvent::RecognitionFailed { report: &report });
             }
         }
-        if let Some(id) = &best_match { println!("✅ 定位: [{}] (得分: {})", id, max_score); }
-        best_match
+        (best_match.map(|(id, _)| id), report)
+    }
+
+    /// 在同一帧上给候选界面逐个评分，返回得分最高的 (界面, 得分) 与全部候选的检查结果；
+    /// 每个候选都检查全部锚点，得分不受提前停止影响
+    fn best_scene(&self, candidates: &[&str]) -> (Option<(SceneId, usize)>, Vec<SceneReport>) {
+        let mut best: Option<(SceneId, usize)> = None;
+        let mut reports = Vec::new();
+        self.with_snapshot(candidates, || {
+            for id in candidates {
+                let Some(scene) = self.evaluate_scene_with(id, false) else { continue };
+                let score = scene.score();
+                if score > 0 && best.as_ref().is_none_or(|(_, max)| score > *max) {
+                    best = Some((id.to_string(), score));
+                }
+                reports.push(scene);
+            }
+        });
+        (best, reports)
+    }
+
+    /// 只识别、不操作："我现在在哪个界面"。与 identify_with_report 不同，不会关闭打断界面，
+    /// 打断界面 (升级弹窗、结算弹窗等) 同样可能作为结果返回；没有界面命中时返回 None
+    pub fn detect_current_scene(&self) -> Option<(SceneId, RecognitionReport)> {
+        let graph = self.graph();
+        let candidates: Vec<&str> = graph.scenes.keys().map(String::as_str).collect();
+        let (best, scenes) = self.best_scene(&candidates);
+        let (id, score) = best?;
+        self.interface.emit(NavEvent::SceneRecognized { id: &id, score });
+        Some((id, RecognitionReport { reason: String::new(), scenes }))
+    }
+
+    /// 当前是否处于界面 id (只检查该界面的锚点，不操作)；界面不存在或没有锚点、无法确认时返回 None
+    pub fn check_scene(&self, id: &str) -> Option<bool> {
+        self.with_snapshot(&[id], || self.evaluate_scene(id)).map(|r| r.passed)
     }
 
-    fn wait_for_scene(&self, target_id: &str, timeout_ms: u64) -> bool {
+    /// 每隔 poll 检查一次 cond，直到其返回 true (返回已等待的时间) 或超过 timeout
+    pub fn wait_until(&self, timeout: Duration, poll: Duration, mut cond: impl FnMut() -> bool) -> Result<Duration, String> {
         let start = Instant::now();
-        println!("    👀 确认进入 [{}]...", target_id);
-        while start.elapsed().as_millis() < timeout_ms as u128 {
-            if self.get_match_score(target_id) > 0 {
-                println!("    ✅ 确认到达 (耗时 {}ms)", start.elapsed().as_millis());
-                return true;
+        loop {
+            if cond() {
+                return Ok(start.elapsed());
             }
-            thread::sleep(Duration::from_millis(200));
+            if start.elapsed() >= timeout {
+                return Err(format!("等待超时 ({}ms)", timeout.as_millis()));
+            }
+            thread::sleep(poll.min(timeout.saturating_sub(start.elapsed())));
         }
-        println!("    ⚠️ 等待超时 [{}]", target_id);
-        false
     }
 
-    pub fn navigate(&self, target_id: &str) -> NavResult {
-        let start_id = match self.identify_current_scene(None) {
-            Some(id) => id,
-            None => { println!("❌ 无法定位起点"); return NavResult::Failed; }
+    /// 等待进入界面 id，返回已等待的时间
+    pub fn wait_for_scene(&self, id: &str, timeout: Duration) -> Result<Duration, String> {
+        println!("    👀 确认进入 [{}]...", id);
+        // 没进入目标时顺带检查打断界面，点击后弹出的升级、公告窗口会被自动关闭
+        let mut score = 0;
+        let arrived = || {
+            score = self.with_snapshot(&[id], || self.get_match_score(id));
+            if score == 0 && self.handle_interrupts() > 0 {
+                score = self.with_snapshot(&[id], || self.get_match_score(id));
+            }
+            score > 0
         };
-        if start_id == target_id {
-            println!("✅ 已在目标位置");
-            return NavResult::Success;
-        }
-        println!("🤖 规划路径: [{}] -> [{}]", start_id, target_id);
-        let path = match self.find_path(&start_id, target_id) {
-            Some(p) => p,
-            None => { println!("❌ 无路可走"); return NavResult::Failed; }
+        match self.wait_until(timeout, WAIT_POLL, arrived) {
+            Ok(waited) => {
+                println!("    ✅ 确认到达 (耗时 {}ms)", waited.as_millis());
+                self.interface.emit(NavEvent::SceneRecognized { id, score });
+                Ok(waited)
+            }
+            Err(e) => {
+                println!("    ⚠️ 等待超时 [{}]", id);
+                Err(format!("未进入 [{}]: {}", id, e))
+            }
+        }
+    }
+
+    /// 等待区域 rect (与 ocr_area 相同的截图坐标) 的识别结果包含 needle
+    pub fn wait_for_text(&self, rect: [i32; 4], needle: &str, timeout: Duration) -> Result<Duration, String> {
+        self.wait_until(timeout, WAIT_POLL, || self.ocr_area(rect).contains(needle))
+            .map_err(|e| format!("区域 {:?} 未出现 \"{}\": {}", rect, needle, e))
+    }
+
+    /// 等待区域 rect 的识别结果不再包含 needle (弹窗、提示消失)
+    pub fn wait_for_text_gone(&self, rect: [i32; 4], needle: &str, timeout: Duration) -> Result<Duration, String> {
+        self.wait_until(timeout, WAIT_POLL, || !self.ocr_area(rect).contains(needle))
+            .map_err(|e| format!("区域 {:?} 的 \"{}\" 未消失: {}", rect, needle, e))
+    }
+
+    pub fn navigate(&self, target_id: &str) -> NavResult {
+        self.navigate_with(target_id, NavOptions::default())
+    }
+
+    /// 导航到参数化场景，如 navigate_template("stage_select_{n}", &[("n", "3")]) 前往 stage_select_3
+    pub fn navigate_template(&self, template_id: &str, params: &[(&str, &str)]) -> NavResult {
+        self.navigate(&params::fill(template_id, params))
+    }
+
+    /// 带步数、时间限制与取消开关的导航；超出限制或被取消时返回 Failed (报告中注明原因)
+    pub fn navigate_with(&self, target_id: &str, options: NavOptions) -> NavResult {
+        let mut budget = Budget { options: &options, started: Instant::now(), steps: 0 };
+        let result = self.navigate_inner(target_id, &mut budget);
+        if let NavResult::Failed(report) = &result {
+            self.interface.emit(NavEvent::NavigationFailed { target: target_id, report });
+            // 手动中止不是识别问题，不留现场
+            if !options.cancel.is_aborted() {
+                self.dump_failure(target_id, report);
+            }
+        }
+        result
+    }
+
+    fn navigate_inner(&self, target_id: &str, budget: &mut Budget) -> NavResult {
+        if let Err(reason) = budget.check() {
+            println!("❌ {}", reason);
+            return NavResult::Failed(RecognitionReport { reason, scenes: Vec::new() });
+        }
+        let mut start_id = match self.identify_with_report(None) {
+            (Some(id), _) => id,
+            (None, mut report) => {
+                println!("❌ 无法定位起点");
+                report.reason = "无法定位起点".to_string();
+                return NavResult::Failed(report);
+            }
         };
+        let mut replans = 0;
+        loop {
+            if start_id == target_id {
+                println!("✅ 已在目标位置");
+                return NavResult::Success;
+            }
+            let path = match self.find_path(&start_id, target_id) {
+                Some(p) => p,
+                None => {
+                    let reason = format!("无路可走: [{}] -> [{}]", start_id, target_id);
+                    println!("❌ {}", reason);
+                    return NavResult::Failed(RecognitionReport { reason, scenes: Vec::new() });
+                }
+            };
+            let route: Vec<&str> = path.iter().map(|t| t.target.as_str()).collect();
+            println!("🤖 规划路径: [{}] -> {}", start_id, route.iter().map(|id| format!("[{}]", id)).collect::<Vec<_>>().join(" -> "));
+
+            match self.follow(&start_id, &path, budget) {
+                Ok(result) => return result,
+                Err(stall) => {
+                    // 没进入预期界面 (弹窗、误触、网络延迟)：从实际所在界面重新规划
+                    replans += 1;
+                    if replans > MAX_REPLANS {
+                        println!("❌ 导航中断: 多次未能进入 [{}]", stall.expect);
+                        let reason = format!("重新规划 {} 次后仍失败，{}", MAX_REPLANS, stall.describe());
+                        return NavResult::Failed(self.diagnose(reason, &[&stall.expect, &stall.from]));
+                    }
+                    match self.identify_with_report(None) {
+                        (Some(id), _) => {
+                            if let Err(reason) = budget.check() {
+                                println!("❌ {}", reason);
+                                return NavResult::Failed(RecognitionReport { reason, scenes: Vec::new() });
+                            }
+                            println!("🔁 {}，当前在 [{}]，重新规划 ({}/{})", stall.describe(), id, replans, MAX_REPLANS);
+                            start_id = id;
+                        }
+                        (None, mut report) => {
+                            println!("❌ 导航中断: 未能进入 [{}]，且无法识别当前界面", stall.expect);
+                            report.reason = format!("{}，且无法识别当前界面", stall.describe());
+                            return NavResult::Failed(report);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// 按路径逐跳点击；某一跳重试后仍没能进入预期界面时返回 Err，由 navigate 重新规划
+    fn follow(&self, start: &str, path: &[Transition], budget: &mut Budget) -> Result<NavResult, Stall> {
+        let mut from = start.to_string();
         for (i, step) in path.iter().enumerate() {
             println!("\n➡️  [步骤 {}/{}] 点击 -> [{}]", i+1, path.len(), step.target);
-            self.interface.perform_click(step.coords[0], step.coords[1]);
-            
+            let expect = step.expect.as_deref().unwrap_or(&step.target);
+            let timeout = Duration::from_millis(step.verify_timeout.unwrap_or(step.post_delay.max(2000)));
             // ✨ 核心修改：检查是否需要移交控制权
-            // 如果 TOML 里写了 handler = "xxx"，或者它是无锚点的虚拟节点，则移交
-            let (should_handover, handler_name) = if let Some(s) = self.scenes.get(&step.target) {
-                // 如果有 handler 字段，或者没有锚点，都视为需要移交
-                (s.handler.is_some() || s.anchors.is_none(), s.handler.clone())
-            } else { 
-                (false, None) 
-            };
-
-            if should_handover {
+            // 如果 TOML 里写了 handler = "xxx"，或者它是无锚点的虚拟节点，则移交 (指定了 expect 时先确认再移交)
+            let handover = self.is_handover(&step.target);
+            let mut attempts = 0;
+            loop {
+                if let Err(reason) = budget.check() {
+                    println!("❌ 导航中断: {}", reason);
+                    return Ok(NavResult::Failed(self.diagnose(reason, &[&from, expect])));
+                }
+                attempts += 1;
+                budget.steps += 1;
+                if let Err(e) = self.interface.perform_transition(step, budget.deadline()) {
+                    println!("❌ 导航中断: 点击失败 ({})", e);
+                    let reason = format!("点击 [{}] 失败: {}", step.target, e);
+                    return Ok(NavResult::Failed(RecognitionReport { reason, scenes: Vec::new() }));
+                }
+                self.interface.emit(NavEvent::TransitionExecuted { from: &from, to: &step.target, attempt: attempts });
+                if (handover && step.expect.is_none()) || self.wait_for_scene(expect, timeout).is_ok() {
+                    break;
+                }
+                // 只有仍停在原界面 (点击被吞) 时才重点；已经去了别处就交给 navigate 重新规划
+                let stalled = attempts <= step.retries && self.with_snapshot(&[&from], || self.get_match_score(&from)) > 0;
+                if !stalled {
+                    return Err(Stall { from, target: step.target.clone(), expect: expect.to_string(), attempts });
+                }
+                println!("🔁 仍停留在 [{}]，重新点击 ({}/{})", from, attempts, step.retries);
+            }
+            if handover {
+                let handler_name = self.graph().scenes.get(&step.target).and_then(|s| s.handler.clone());
                 println!("🚀 到达托管节点 [{}]，触发处理器: {:?}", step.target, handler_name);
                 thread::sleep(Duration::from_millis(step.post_delay));
                 // 将 handler 名称一并返回给 main
-                return NavResult::Handover(step.target.clone(), handler_name);
-            }
-
-            let timeout = if step.post_delay < 2000 { 2000 } else { step.post_delay };
-            if !self.wait_for_scene(&step.target, timeout) {
-                println!("❌ 导航中断: 未能进入 [{}]", step.target);
-                return NavResult::Failed;
+                return Ok(NavResult::Handover(step.target.clone(), handler_name));
             }
             thread::sleep(Duration::from_millis(300));
+            from = step.target.clone();
         }
         println!("✅ 导航完成");
-        NavResult::Success
+        Ok(NavResult::Success)
+    }
+
+    /// 有 handler 或没有锚点 (无法确认是否到达) 的界面：到达后把控制权交给处理器
+    fn is_handover(&self, id: &str) -> bool {
+        self.graph().scenes.get(id).is_some_and(|s| s.handler.is_some() || s.anchors.is_none())
     }
 
+    /// 在界面图上按跳转代价求最短路径 (Dijkstra)；托管节点只能作为终点，不会被当作中转
     fn find_path(&self, start: &str, target: &str) -> Option<Vec<Transition>> {
         if start == target { return Some(vec![]); }
-        let mut queue = VecDeque::from([start.to_string()]);
-        let mut came_from: HashMap<String, (String, Transition)> = HashMap::new();
-        let mut visited = vec![start.to_string()];
-        while let Some(curr) = queue.pop_front() {
+        let graph = self.graph();
+        let mut dist: HashMap<&str, u64> = HashMap::from([(start, 0)]);
+        let mut came_from: HashMap<&str, (&str, &Transition)> = HashMap::new();
+        let mut heap = BinaryHeap::from([Reverse((0u64, start))]);
+        while let Some(Reverse((d, curr))) = heap.pop() {
             if curr == target {
                 let mut path = vec![];
-                let mut p = target.to_string();
-                while p != start {
-                    if let Some((prev, trans)) = came_from.get(&p) { path.push(trans.clone()); p = prev.clone(); }
+                let mut p = target;
+                while let Some(&(prev, trans)) = came_from.get(p) {
+                    path.push(trans.clone());
+                    p = prev;
                 }
-                path.reverse(); return Some(path);
+                path.reverse();
+                return Some(path);
             }
-            if let Some(scene) = self.scenes.get(&curr) {
-                if let Some(trans) = &scene.transitions {
-                    for t in trans {
-                        if !visited.contains(&t.target) {
-                            visited.push(t.target.clone()); queue.push_back(t.target.clone()); came_from.insert(t.target.clone(), (curr.clone(), t.clone()));
-                        }
-                    }
+            if d > dist.get(curr).copied().unwrap_or(u64::MAX) || (curr != start && self.is_handover(curr)) {
+                continue;
+            }
+            let Some(trans) = graph.scenes.get(curr).and_then(|s| s.transitions.as_ref()) else { continue };
+            for t in trans {
+                let next = d + t.cost();
+                if next < dist.get(t.target.as_str()).copied().unwrap_or(u64::MAX) {
+                    dist.insert(&t.target, next);
+                    came_from.insert(&t.target, (curr, t));
+                    heap.push(Reverse((next, &t.target)));
                 }
             }
         }
@@ -0,0 +1,189 @@
+// src/numeric.rs
+// 游戏数字解析：修正 OCR 常见的字形混淆 (O→0, l→1 ...)，并解析 "12,345" / "1.2万" / "3/5" 等写法
+use image::{GrayImage, Luma, RgbaImage};
+
+/// 数字区域的放大倍数；数字笔画细，放大比普通文字更多
+const DIGIT_SCALE: u32 = 3;
+/// 二值化后四周补的白边 (px)，数字贴边时识别率明显下降
+const DIGIT_PADDING: u32 = 16;
+
+/// OCR 常把数字认成的字母与符号；只在以真实数字为主的数字串内替换，普通单词不受影响
+fn confusable(c: char) -> Option<char> {
+    match c {
+        'O' | 'o' | 'D' | 'Q' => Some('0'),
+        'l' | 'I' | 'i' | '|' | '!' => Some('1'),
+        'Z' | 'z' => Some('2'),
+        'S' | 's' => Some('5'),
+        'b' | 'G' => Some('6'),
+        'B' => Some('8'),
+        'g' | 'q' => Some('9'),
+        _ => None,
+    }
+}
+
+/// 数字末尾的数量级单位
+fn unit(c: char) -> Option<f64> {
+    match c {
+        'k' | 'K' => Some(1e3),
+        '万' | 'w' | 'W' => Some(1e4),
+        'M' => Some(1e6),
+        '亿' => Some(1e8),
+        _ => None,
+    }
+}
+
+/// 全角数字与标点转半角，去掉空白 (OCR 常在数字之间插入空格)
+fn normalize(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| match c {
+            '０'..='９' => char::from_u32(c as u32 - '０' as u32 + '0' as u32).unwrap_or(c),
+            '，' => ',',
+            '．' | '。' => '.',
+            '／' => '/',
+            _ => c,
+        })
+        .collect()
+}
+
+/// 片段中真实数字多于易混淆字符时才按数字修正 ("1O0" 是 100，"Zb3" / "Boss1" 不是)
+fn mostly_digits(token: &str) -> bool {
+    let digits = token.chars().filter(char::is_ascii_digit).count();
+    let confused = token.chars().filter(|c| unit(*c).is_none() && confusable(*c).is_some()).count();
+    digits > confused
+}
+
+/// 切出所有可能是数字的片段 (至少含一个真实数字)，并完成字形修正；
+/// 不以数字为主的片段不做替换，易混淆字符当作分隔符，只保留其中的真实数字
+fn numeric_tokens(text: &str) -> Vec<String> {
+    let candidate = |c: char| c.is_ascii_digit() || confusable(c).is_some() || unit(c).is_some() || matches!(c, ',' | '.' | '/');
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    for c in normalize(text).chars().chain(std::iter::once(' ')) {
+        if candidate(c) {
+            current.push(c);
+            continue;
+        }
+        if current.chars().any(|c| c.is_ascii_digit()) {
+            if mostly_digits(&current) {
+                tokens.push(current.chars().map(|c| if unit(c).is_some() { c } else { confusable(c).unwrap_or(c) }).collect());
+            } else {
+                let parts = current.split(|c: char| unit(c).is_none() && confusable(c).is_some());
+                tokens.extend(parts.filter(|p| p.chars().any(|c| c.is_ascii_digit())).map(str::to_string));
+            }
+        }
+        current.clear();
+    }
+    tokens
+}
+
+/// 解析单个数值 "12,345" / "1.2万" / "3k"；千分位可能被识别成逗号或点
+/// 没有单位的小数 ("1.5") 多半是误识别，返回错误而不是四舍五入成整数
+fn parse_value(token: &str) -> Result<i64, String> {
+    let invalid = || format!("\"{}\" 不是有效的数字", token);
+    let trimmed = token.trim_matches(|c| c == ',' || c == '.');
+    let (body, scale) = match trimmed.chars().last() {
+        Some(last) if unit(last).is_some() => (&trimmed[..trimmed.len() - last.len_utf8()], unit(last)),
+        _ => (trimmed, None),
+    };
+    let body = body.trim_matches(|c| c == ',' || c == '.');
+    if body.is_empty() || !body.chars().all(|c| c.is_ascii_digit() || c == ',' || c == '.') {
+        return Err(invalid());
+    }
+    let groups: Vec<&str> = body.split('.').collect();
+    // 没有单位时，点号后都是 3 位一组视为千分位 ("1.234" 多半是 "1,234" 的误识别)
+    let thousands = scale.is_none() && groups.len() > 1 && groups[1..].iter().all(|g| g.replace(',', "").len() == 3);
+    let digits: String = if thousands || groups.len() > 2 {
+        body.chars().filter(char::is_ascii_digit).collect()
+    } else {
+        body.chars().filter(|c| *c != ',').collect()
+    };
+    let value: f64 = digits.parse().map_err(|_| invalid())?;
+    match scale {
+        Some(scale) => Ok((value * scale).round() as i64),
+        None if value.fract() != 0.0 => Err(format!("\"{}\" 含小数但没有单位 (万 / k ...)，无法确定是否误识别", token)),
+        None => Ok(value as i64),
+    }
+}
+
+/// 解析失败的片段打印提示后跳过，继续尝试后面的片段
+fn accept(token: &str, value: Result<i64, String>) -> Option<i64> {
+    value.map_err(|e| println!("⚠️ [Numeric] 已忽略 \"{}\": {}", token, e)).ok()
+}
+
+/// 从 OCR 文本中解析第一个数字；"3/5" 这类进度取分子，需要分母时用 parse_fraction
+pub fn parse_number(text: &str) -> Option<i64> {
+    numeric_tokens(text).iter().find_map(|token| accept(token, parse_value(token.split('/').next()?)))
+}
+
+/// 解析 "3/5" 形式的进度 / 次数，返回 (分子, 分母)
+pub fn parse_fraction(text: &str) -> Option<(i64, i64)> {
+    numeric_tokens(text).iter().find_map(|token| {
+        let (num, den) = token.split_once('/')?;
+        Some((accept(token, parse_value(num))?, accept(token, parse_value(den.split('/').next()?))?))
+    })
+}
+
+/// 偏向数字的预处理：放大、按区域内明暗中点二值化、统一为白底黑字并补白边
+pub fn prepare_digits(img: &RgbaImage) -> GrayImage {
+    let gray = image::DynamicImage::ImageRgba8(img.clone()).into_luma8();
+    let (w, h) = gray.dimensions();
+    let scaled = image::imageops::resize(&gray, w * DIGIT_SCALE, h * DIGIT_SCALE, image::imageops::FilterType::Lanczos3);
+    let (min, max) = scaled.pixels().fold((255u8, 0u8), |(lo, hi), p| (lo.min(p[0]), hi.max(p[0])));
+    let threshold = ((min as u16 + max as u16) / 2) as u8;
+    let bright = scaled.pixels().filter(|p| p[0] > threshold).count();
+    // 数字笔画占少数像素：亮像素过半说明是亮底暗字，否则是暗底亮字 (游戏 HUD 常见) 需要反相
+    let light_background = bright * 2 >= scaled.pixels().len();
+    let (sw, sh) = scaled.dimensions();
+    let mut out = GrayImage::from_pixel(sw + DIGIT_PADDING * 2, sh + DIGIT_PADDING * 2, Luma([255]));
+    for (x, y, p) in scaled.enumerate_pixels() {
+        let ink = (p[0] > threshold) != light_background;
+        out.put_pixel(x + DIGIT_PADDING, y + DIGIT_PADDING, Luma([if ink { 0 } else { 255 }]));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn confusables_fixed_in_digit_tokens() {
+        assert_eq!(parse_number("1O0"), Some(100));
+        assert_eq!(parse_number("金币: 1,2O5"), Some(1205));
+        assert_eq!(parse_number("l23"), Some(123));
+        assert_eq!(parse_fraction("3/S"), None);
+        assert_eq!(parse_fraction("3/5"), Some((3, 5)));
+    }
+
+    #[test]
+    fn confusables_kept_in_word_tokens() {
+        // 字母为主的片段不按数字修正，只取其中的真实数字
+        assert_eq!(parse_number("Boss1"), Some(1));
+        assert_eq!(parse_number("Zb3"), Some(3));
+        assert_eq!(parse_number("SOS2"), Some(2));
+        assert_eq!(parse_number("Boss"), None);
+        assert_eq!(numeric_tokens("Zb3:q12"), vec!["3".to_string(), "912".to_string()]);
+    }
+
+    #[test]
+    fn units_and_separators() {
+        assert_eq!(parse_number("12,345"), Some(12345));
+        assert_eq!(parse_number("1.234"), Some(1234));
+        assert_eq!(parse_number("1.2万"), Some(12000));
+        assert_eq!(parse_number("3k"), Some(3000));
+        assert_eq!(parse_number("１２，３４５"), Some(12345));
+    }
+
+    #[test]
+    fn unscaled_fractions_rejected() {
+        assert!(parse_value("1.5").is_err());
+        assert!(parse_value("2.50").is_err());
+        assert_eq!(parse_value("1.5万"), Ok(15000));
+        assert_eq!(parse_value("1.0"), Ok(1));
+        assert_eq!(parse_number("1.5"), None);
+        // 被拒绝的片段跳过，继续解析后面的数字
+        assert_eq!(parse_number("1.5倍 42"), Some(42));
+        assert_eq!(parse_fraction("1.5/3"), None);
+    }
+}
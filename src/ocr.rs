@@ -0,0 +1,291 @@
+// src/ocr.rs
+use image::DynamicImage;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::sync::Mutex;
+
+use windows::Globalization::Language;
+use windows::Graphics::Imaging::BitmapDecoder;
+use windows::Media::Ocr::{OcrEngine, OcrResult};
+use windows::Storage::Streams::{DataWriter, InMemoryRandomAccessStream};
+
+// ==========================================
+// 1. 后端接口
+// ==========================================
+
+/// 一个识别出的词 (中文通常是单字或短语)；rect 为 [x1, y1, x2, y2]，相对送入识别的图像
+#[derive(Debug, Clone, PartialEq)]
+pub struct OcrWord {
+    pub text: String,
+    pub rect: [i32; 4],
+    pub confidence: Option<f32>,
+}
+
+/// 一行文字；text 为各词拼接 (已去掉空白)，rect 为各词区域的并集
+#[derive(Debug, Clone, PartialEq)]
+pub struct OcrLine {
+    pub text: String,
+    pub rect: [i32; 4],
+    pub words: Vec<OcrWord>,
+}
+
+impl OcrLine {
+    pub fn from_words(words: Vec<OcrWord>) -> Self {
+        let text = words.iter().map(|w| w.text.as_str()).collect::<String>().replace(|c: char| c.is_whitespace(), "");
+        let rect = union(words.iter().map(|w| w.rect));
+        Self { text, rect, words }
+    }
+
+    /// needle 在本行中所占的区域 (覆盖 needle 的词的并集)；本行不含 needle 时为 None
+    pub fn locate(&self, needle: &str) -> Option<[i32; 4]> {
+        let needle: String = needle.chars().filter(|c| !c.is_whitespace()).collect();
+        if needle.is_empty() {
+            return None;
+        }
+        // 每个词在拼接文本中的字节范围
+        let mut spans = Vec::new();
+        let mut offset = 0;
+        for w in &self.words {
+            let len: usize = w.text.chars().filter(|c| !c.is_whitespace()).map(char::len_utf8).sum();
+            spans.push((offset, offset + len, w.rect));
+            offset += len;
+        }
+        let start = self.text.find(&needle)?;
+        let end = start + needle.len();
+        let covered = spans.into_iter().filter(|&(a, b, _)| a < end && b > start).map(|(_, _, r)| r);
+        Some(union(covered)).filter(|r| r[2] > r[0])
+    }
+}
+
+/// 多个区域的外接矩形；没有区域时为全 0
+fn union(rects: impl Iterator<Item = [i32; 4]>) -> [i32; 4] {
+    rects
+        .reduce(|a, b| [a[0].min(b[0]), a[1].min(b[1]), a[2].max(b[2]), a[3].max(b[3])])
+        .unwrap_or([0; 4])
+}
+
+/// 文字识别后端；NavEngine 负责截图与预处理 (放大、二值化)，后端只识别单张图像
+pub trait OcrBackend: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// 返回识别出的全部文字 (已去掉空白)
+    fn recognize(&self, img: &DynamicImage) -> Result<String, String>;
+
+    /// 识别文字并给出置信度 (0~1)；后端不提供置信度时为 None (如 Windows OCR)
+    fn recognize_scored(&self, img: &DynamicImage) -> Result<(String, Option<f32>), String> {
+        self.recognize(img).map(|text| (text, None))
+    }
+
+    /// 按指定语言识别 (BCP-47 标签，如 "en" / "zh-Hans")；不支持按语言切换的后端忽略 lang
+    fn recognize_lang(&self, img: &DynamicImage, _lang: &str) -> Result<(String, Option<f32>), String> {
+        self.recognize_scored(img)
+    }
+
+    /// 按行、词返回识别结果与位置；不提供版面信息的后端把整张图作为一个词
+    fn recognize_lines(&self, img: &DynamicImage) -> Result<Vec<OcrLine>, String> {
+        let (text, confidence) = self.recognize_scored(img)?;
+        if text.is_empty() {
+            return Ok(Vec::new());
+        }
+        let rect = [0, 0, img.width() as i32, img.height() as i32];
+        Ok(vec![OcrLine::from_words(vec![OcrWord { text, rect, confidence }])])
+    }
+}
+
+// ==========================================
+// 2. 配置 (config.toml 中的 [ocr])
+// ==========================================
+#[derive(Deserialize, Debug, Clone)]
+pub struct OcrConfig {
+    /// "windows" (默认，系统自带) / "tesseract" (需以 --features tesseract 编译) / "paddle" (需以 --features paddle 编译)
+    #[serde(default = "default_backend")]
+    pub backend: String,
+    /// Tesseract 语言包，如 "chi_sim" 或 "chi_sim+eng"
+    #[serde(default = "default_language")]
+    pub language: String,
+    /// Tesseract 语言包 (*.traineddata) 所在目录
+    #[serde(default = "default_tessdata")]
+    pub tessdata: String,
+    /// Tesseract 动态库文件名或路径
+    #[serde(default = "default_library")]
+    pub library: String,
+    /// Tesseract 页面分割模式 (7 = 单行文字，6 = 文字块)
+    #[serde(default = "default_psm")]
+    pub psm: i32,
+    /// PP-OCR 模型目录 (det.onnx / rec.onnx / ppocr_keys_v1.txt)
+    #[serde(default = "default_models")]
+    pub models: String,
+    /// ONNX Runtime 动态库文件名或路径
+    #[serde(default = "default_onnxruntime")]
+    pub onnxruntime: String,
+}
+
+fn default_backend() -> String { "windows".to_string() }
+fn default_language() -> String { "chi_sim".to_string() }
+fn default_tessdata() -> String { "tessdata".to_string() }
+fn default_library() -> String { "libtesseract-5.dll".to_string() }
+fn default_psm() -> i32 { 7 }
+fn default_models() -> String { "models".to_string() }
+fn default_onnxruntime() -> String { "onnxruntime.dll".to_string() }
+
+impl Default for OcrConfig {
+    fn default() -> Self {
+        Self {
+            backend: default_backend(),
+            language: default_language(),
+            tessdata: default_tessdata(),
+            library: default_library(),
+            psm: default_psm(),
+            models: default_models(),
+            onnxruntime: default_onnxruntime(),
+        }
+    }
+}
+
+// ==========================================
+// 3. Windows.Media.Ocr 后端
+// ==========================================
+pub struct WindowsOcr {
+    engine: Option<OcrEngine>,
+    /// 按语言标签懒加载的引擎；None 表示系统未安装该语言的 OCR 包
+    by_lang: Mutex<HashMap<String, Option<OcrEngine>>>,
+}
+
+// OcrEngine 是 agile 的 WinRT 对象，可跨线程调用
+unsafe impl Send for WindowsOcr {}
+unsafe impl Sync for WindowsOcr {}
+
+impl WindowsOcr {
+    pub fn new() -> Self {
+        println!("🚀 初始化 Windows OCR...");
+        let engine = match Language::CreateLanguage(&windows::core::HSTRING::from("zh-Hans")) {
+            Ok(lang) => match OcrEngine::TryCreateFromLanguage(&lang) {
+                Ok(e) => Some(e),
+                Err(_) => OcrEngine::TryCreateFromUserProfileLanguages().ok()
+            },
+            Err(_) => OcrEngine::TryCreateFromUserProfileLanguages().ok(),
+        };
+        Self { engine, by_lang: Mutex::new(HashMap::new()) }
+    }
+
+    /// 指定语言的引擎，首次使用时创建；未安装该语言时提示一次并回退到默认引擎
+    fn engine_for(&self, lang: &str) -> Option<OcrEngine> {
+        let mut engines = self.by_lang.lock().ok()?;
+        let engine = engines.entry(lang.to_string()).or_insert_with(|| {
+            let created = Language::CreateLanguage(&windows::core::HSTRING::from(lang))
+                .and_then(|l| OcrEngine::TryCreateFromLanguage(&l))
+                .ok();
+            match &created {
+                Some(_) => println!("🔤 Windows OCR: 已创建 [{}] 引擎", lang),
+                None => println!("⚠️ Windows OCR 不支持语言 [{}] (未安装对应语言包?)，使用默认引擎", lang),
+            }
+            created
+        });
+        engine.clone().or_else(|| self.engine.clone())
+    }
+}
+
+impl Default for WindowsOcr {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OcrBackend for WindowsOcr {
+    fn name(&self) -> &'static str { "windows" }
+
+    fn recognize(&self, img: &DynamicImage) -> Result<String, String> {
+        Ok(flatten(&self.run(self.engine.as_ref(), img)?))
+    }
+
+    fn recognize_lang(&self, img: &DynamicImage, lang: &str) -> Result<(String, Option<f32>), String> {
+        Ok((flatten(&self.run(self.engine_for(lang).as_ref(), img)?), None))
+    }
+
+    /// Windows OCR 自带行、词的外接矩形 (不提供置信度)
+    fn recognize_lines(&self, img: &DynamicImage) -> Result<Vec<OcrLine>, String> {
+        let result = self.run(self.engine.as_ref(), img)?;
+        let mut out = Vec::new();
+        for line in result.Lines().map_err(|e| e.to_string())? {
+            let mut words = Vec::new();
+            for word in line.Words().map_err(|e| e.to_string())? {
+                let (Ok(text), Ok(r)) = (word.Text(), word.BoundingRect()) else { continue };
+                let rect = [r.X.floor() as i32, r.Y.floor() as i32, (r.X + r.Width).ceil() as i32, (r.Y + r.Height).ceil() as i32];
+                words.push(OcrWord { text: text.to_string(), rect, confidence: None });
+            }
+            if !words.is_empty() {
+                out.push(OcrLine::from_words(words));
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// 所有行拼接为一个字符串并去掉空白
+fn flatten(result: &OcrResult) -> String {
+    let mut full_text = String::new();
+    if let Ok(lines) = result.Lines() {
+        for line in lines {
+            if let Ok(text) = line.Text() { full_text.push_str(&text.to_string()); }
+        }
+    }
+    full_text.replace(|c: char| c.is_whitespace(), "")
+}
+
+impl WindowsOcr {
+    fn run(&self, engine: Option<&OcrEngine>, img: &DynamicImage) -> Result<OcrResult, String> {
+        let engine = engine.ok_or("Windows OCR 不可用 (未安装中文语言包?)")?;
+        let err = |e: windows::core::Error| e.to_string();
+
+        let mut png_buffer = Cursor::new(Vec::new());
+        img.write_to(&mut png_buffer, image::ImageFormat::Png).map_err(|e| e.to_string())?;
+        let png_bytes = png_buffer.into_inner();
+
+        let stream = InMemoryRandomAccessStream::new().map_err(err)?;
+        let writer = DataWriter::CreateDataWriter(&stream).map_err(err)?;
+        writer.WriteBytes(&png_bytes).map_err(err)?;
+        writer.StoreAsync().and_then(|op| op.get()).map_err(err)?;
+        writer.FlushAsync().and_then(|op| op.get()).map_err(err)?;
+        writer.DetachStream().map_err(err)?;
+        stream.Seek(0).map_err(err)?;
+
+        let decoder = BitmapDecoder::CreateAsync(&stream).and_then(|op| op.get()).map_err(err)?;
+        let software_bitmap = decoder.GetSoftwareBitmapAsync().and_then(|op| op.get()).map_err(err)?;
+        engine.RecognizeAsync(&software_bitmap).and_then(|op| op.get()).map_err(err)
+    }
+}
+
+// ==========================================
+// 4. 工厂
+// ==========================================
+
+/// 按配置创建 OCR 后端；不可用时回退到 Windows OCR
+pub fn create_backend(cfg: &OcrConfig) -> Box<dyn OcrBackend> {
+    match cfg.backend.to_lowercase().as_str() {
+        "windows" | "" => {}
+        #[cfg(feature = "tesseract")]
+        "tesseract" => match crate::tesseract::TesseractOcr::new(cfg) {
+            Ok(t) => {
+                println!("🔤 OCR 后端: [tesseract] ({})", cfg.language);
+                return Box::new(t);
+            }
+            Err(e) => println!("⚠️ OCR 后端 [tesseract] 不可用: {}", e),
+        },
+        #[cfg(not(feature = "tesseract"))]
+        "tesseract" => println!("⚠️ 当前构建未包含 Tesseract 后端，请使用 --features tesseract 重新编译"),
+        #[cfg(feature = "paddle")]
+        "paddle" => match crate::paddle::PaddleOcr::new(cfg) {
+            Ok(p) => {
+                println!("🔤 OCR 后端: [paddle] ({})", cfg.models);
+                return Box::new(p);
+            }
+            Err(e) => println!("⚠️ OCR 后端 [paddle] 不可用: {}", e),
+        },
+        #[cfg(not(feature = "paddle"))]
+        "paddle" => println!("⚠️ 当前构建未包含 PaddleOCR 后端，请使用 --features paddle 重新编译"),
+        other => println!("⚠️ 未知的 OCR 后端: {} (可用 windows / tesseract / paddle)", other),
+    }
+    println!("🔤 OCR 后端: [windows]");
+    Box::new(WindowsOcr::new())
+}
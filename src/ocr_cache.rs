@@ -0,0 +1,56 @@
+// src/ocr_cache.rs
+// OCR 结果缓存：按 (区域, 语言, 画面内容哈希) 保存最近的识别结果，画面回到见过的内容时不再重复识别
+// (如每秒轮询一次的 "战斗中" / "准备中" 状态标签，WinRT OCR 每次约 100 ~ 300ms)
+use image::RgbaImage;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// 缺省容量；每条只保存区域坐标、哈希与文字，占用很小
+pub const DEFAULT_CAPACITY: usize = 64;
+
+/// 截图的像素哈希 (含尺寸)；内容逐像素相同时才相等
+pub fn content_hash(img: &RgbaImage) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    img.dimensions().hash(&mut hasher);
+    img.as_raw().hash(&mut hasher);
+    hasher.finish()
+}
+
+struct Entry {
+    rect: [i32; 4],
+    lang: Option<String>,
+    hash: u64,
+    text: String,
+    confidence: Option<f32>,
+}
+
+/// 最近最少使用淘汰的识别结果缓存；条目按使用时间排列，最近使用的在末尾
+pub struct OcrCache {
+    capacity: usize,
+    entries: Vec<Entry>,
+}
+
+impl OcrCache {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity: capacity.max(1), entries: Vec::new() }
+    }
+
+    /// 查找区域 rect 的缓存结果；只复用像素完全相同的条目 (数字只变了一位时签名仍可能相近，不能按签名复用)
+    pub fn get(&mut self, rect: [i32; 4], lang: Option<&str>, hash: u64) -> Option<(String, Option<f32>)> {
+        let index = self.entries.iter().position(|e| e.rect == rect && e.lang.as_deref() == lang && e.hash == hash)?;
+        let entry = self.entries.remove(index);
+        let result = (entry.text.clone(), entry.confidence);
+        self.entries.push(entry);
+        Some(result)
+    }
+
+    /// 记录一次识别结果，超出容量时淘汰最久未使用的条目
+    pub fn insert(&mut self, rect: [i32; 4], lang: Option<&str>, hash: u64, text: String, confidence: Option<f32>) {
+        self.entries.retain(|e| !(e.rect == rect && e.lang.as_deref() == lang && e.hash == hash));
+        self.entries.push(Entry { rect, lang: lang.map(str::to_string), hash, text, confidence });
+        if self.entries.len() > self.capacity {
+            let excess = self.entries.len() - self.capacity;
+            self.entries.drain(..excess);
+        }
+    }
+}
@@ -0,0 +1,239 @@
+// src/onnx.rs
+// ONNX Runtime C API 的最小封装 (运行时加载 onnxruntime.dll)，供 PP-OCR 与目标检测后端共用
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::path::Path;
+use windows::core::{s, HSTRING};
+use windows::Win32::System::LibraryLoader::{GetProcAddress, LoadLibraryW};
+
+/// 请求的 API 版本 (onnxruntime 1.11 及以上均提供)
+const ORT_API_VERSION: u32 = 11;
+
+// OrtApi 是一张函数指针表，以下为用到的字段下标 (onnxruntime_c_api.h 中 struct OrtApi 的声明顺序，只追加不改动)
+const FN_GET_ERROR_MESSAGE: usize = 2;
+const FN_CREATE_ENV: usize = 3;
+const FN_CREATE_SESSION: usize = 7;
+const FN_RUN: usize = 9;
+const FN_CREATE_SESSION_OPTIONS: usize = 10;
+const FN_SESSION_GET_INPUT_NAME: usize = 36;
+const FN_SESSION_GET_OUTPUT_NAME: usize = 37;
+const FN_CREATE_TENSOR_WITH_DATA: usize = 49;
+const FN_GET_TENSOR_MUTABLE_DATA: usize = 51;
+const FN_GET_DIMENSIONS_COUNT: usize = 61;
+const FN_GET_DIMENSIONS: usize = 62;
+const FN_GET_TENSOR_TYPE_AND_SHAPE: usize = 65;
+const FN_CREATE_CPU_MEMORY_INFO: usize = 69;
+const FN_ALLOCATOR_FREE: usize = 76;
+const FN_GET_ALLOCATOR_WITH_DEFAULT_OPTIONS: usize = 78;
+const FN_RELEASE_ENV: usize = 92;
+const FN_RELEASE_STATUS: usize = 93;
+const FN_RELEASE_MEMORY_INFO: usize = 94;
+const FN_RELEASE_SESSION: usize = 95;
+const FN_RELEASE_VALUE: usize = 96;
+const FN_RELEASE_TENSOR_TYPE_AND_SHAPE_INFO: usize = 99;
+const FN_RELEASE_SESSION_OPTIONS: usize = 100;
+
+/// ORT_LOGGING_LEVEL_ERROR
+const LOG_LEVEL_ERROR: i32 = 3;
+/// OrtArenaAllocator / OrtMemTypeDefault
+const ARENA_ALLOCATOR: i32 = 1;
+const MEM_TYPE_DEFAULT: i32 = 0;
+/// ONNX_TENSOR_ELEMENT_DATA_TYPE_FLOAT
+const ELEMENT_FLOAT: i32 = 1;
+
+type Handle = *mut c_void;
+type Status = *mut c_void;
+
+#[repr(C)]
+struct OrtApiBase {
+    get_api: unsafe extern "system" fn(u32) -> *const *const c_void,
+    get_version_string: unsafe extern "system" fn() -> *const c_char,
+}
+
+type GetApiBaseFn = unsafe extern "system" fn() -> *const OrtApiBase;
+type GetErrorMessageFn = unsafe extern "system" fn(Status) -> *const c_char;
+type CreateEnvFn = unsafe extern "system" fn(i32, *const c_char, *mut Handle) -> Status;
+type CreateSessionOptionsFn = unsafe extern "system" fn(*mut Handle) -> Status;
+type CreateSessionFn = unsafe extern "system" fn(Handle, *const u16, Handle, *mut Handle) -> Status;
+type SessionGetNameFn = unsafe extern "system" fn(Handle, usize, Handle, *mut *mut c_char) -> Status;
+type GetAllocatorFn = unsafe extern "system" fn(*mut Handle) -> Status;
+type AllocatorFreeFn = unsafe extern "system" fn(Handle, *mut c_void) -> Status;
+type CreateCpuMemoryInfoFn = unsafe extern "system" fn(i32, i32, *mut Handle) -> Status;
+type CreateTensorFn = unsafe extern "system" fn(Handle, *mut c_void, usize, *const i64, usize, i32, *mut Handle) -> Status;
+type RunFn = unsafe extern "system" fn(
+    Handle, *const c_void, *const *const c_char, *const Handle, usize, *const *const c_char, usize, *mut Handle,
+) -> Status;
+type GetTensorDataFn = unsafe extern "system" fn(Handle, *mut *mut c_void) -> Status;
+type GetTypeAndShapeFn = unsafe extern "system" fn(Handle, *mut Handle) -> Status;
+type GetDimensionsCountFn = unsafe extern "system" fn(Handle, *mut usize) -> Status;
+type GetDimensionsFn = unsafe extern "system" fn(Handle, *mut i64, usize) -> Status;
+type ReleaseFn = unsafe extern "system" fn(Handle);
+
+/// 已加载的 onnxruntime 运行时 (一个 OrtEnv + CPU 内存描述)
+pub(crate) struct Runtime {
+    api: *const *const c_void,
+    env: Handle,
+    memory: Handle,
+}
+
+/// 单个模型的推理会话，记录其第一个输入 / 输出的名称
+pub(crate) struct Session {
+    handle: Handle,
+    input: CString,
+    output: CString,
+}
+
+impl Runtime {
+    pub(crate) fn load(library: &str) -> Result<Self, String> {
+        unsafe {
+            let module = LoadLibraryW(&HSTRING::from(library)).map_err(|e| format!("无法加载 {}: {}", library, e))?;
+            let get_base = GetProcAddress(module, s!("OrtGetApiBase"))
+                .ok_or_else(|| format!("{} 缺少 OrtGetApiBase 导出", library))?;
+            type Farproc = unsafe extern "system" fn() -> isize;
+            let base = std::mem::transmute::<Farproc, GetApiBaseFn>(get_base)();
+            if base.is_null() {
+                return Err("OrtGetApiBase 返回空指针".to_string());
+            }
+            let api = ((*base).get_api)(ORT_API_VERSION);
+            if api.is_null() {
+                let version = CStr::from_ptr(((*base).get_version_string)()).to_string_lossy();
+                return Err(format!("onnxruntime {} 版本过旧 (需要 1.11 及以上)", version));
+            }
+
+            let mut rt = Self { api, env: std::ptr::null_mut(), memory: std::ptr::null_mut() };
+            let log_id = CString::new("nzm_onnx").unwrap();
+            let (mut env, mut memory): (Handle, Handle) = (std::ptr::null_mut(), std::ptr::null_mut());
+            rt.check(rt.func::<CreateEnvFn>(FN_CREATE_ENV)(LOG_LEVEL_ERROR, log_id.as_ptr(), &mut env))?;
+            rt.env = env;
+            rt.check(rt.func::<CreateCpuMemoryInfoFn>(FN_CREATE_CPU_MEMORY_INFO)(
+                ARENA_ALLOCATOR,
+                MEM_TYPE_DEFAULT,
+                &mut memory,
+            ))?;
+            rt.memory = memory;
+            Ok(rt)
+        }
+    }
+
+    /// 从函数表中取出第 index 个函数指针
+    unsafe fn func<F: Copy>(&self, index: usize) -> F {
+        std::mem::transmute_copy::<*const c_void, F>(&*self.api.add(index))
+    }
+
+    /// 非空的 OrtStatus 表示失败：取出错误信息并释放
+    unsafe fn check(&self, status: Status) -> Result<(), String> {
+        if status.is_null() {
+            return Ok(());
+        }
+        let message = CStr::from_ptr(self.func::<GetErrorMessageFn>(FN_GET_ERROR_MESSAGE)(status))
+            .to_string_lossy()
+            .into_owned();
+        self.func::<ReleaseFn>(FN_RELEASE_STATUS)(status);
+        Err(message)
+    }
+
+    pub(crate) fn open(&self, path: &Path) -> Result<Session, String> {
+        if !path.exists() {
+            return Err(format!("找不到模型文件 {}", path.display()));
+        }
+        unsafe {
+            let mut options: Handle = std::ptr::null_mut();
+            self.check(self.func::<CreateSessionOptionsFn>(FN_CREATE_SESSION_OPTIONS)(&mut options))?;
+            let mut handle: Handle = std::ptr::null_mut();
+            let wide = HSTRING::from(path.as_os_str());
+            let created = self.check(self.func::<CreateSessionFn>(FN_CREATE_SESSION)(
+                self.env,
+                wide.as_ptr(),
+                options,
+                &mut handle,
+            ));
+            self.func::<ReleaseFn>(FN_RELEASE_SESSION_OPTIONS)(options);
+            created.map_err(|e| format!("无法加载模型 {}: {}", path.display(), e))?;
+
+            let names = (self.name(handle, FN_SESSION_GET_INPUT_NAME), self.name(handle, FN_SESSION_GET_OUTPUT_NAME));
+            match names {
+                (Ok(input), Ok(output)) => Ok(Session { handle, input, output }),
+                (Err(e), _) | (_, Err(e)) => {
+                    self.func::<ReleaseFn>(FN_RELEASE_SESSION)(handle);
+                    Err(e)
+                }
+            }
+        }
+    }
+
+    /// 读取会话第 0 个输入或输出的名称 (名称由默认分配器分配，复制后归还)
+    unsafe fn name(&self, session: Handle, which: usize) -> Result<CString, String> {
+        let mut allocator: Handle = std::ptr::null_mut();
+        self.check(self.func::<GetAllocatorFn>(FN_GET_ALLOCATOR_WITH_DEFAULT_OPTIONS)(&mut allocator))?;
+        let mut raw: *mut c_char = std::ptr::null_mut();
+        self.check(self.func::<SessionGetNameFn>(which)(session, 0, allocator, &mut raw))?;
+        let name = CStr::from_ptr(raw).to_owned();
+        self.check(self.func::<AllocatorFreeFn>(FN_ALLOCATOR_FREE)(allocator, raw as *mut c_void))?;
+        Ok(name)
+    }
+
+    /// 以 float 张量执行一次推理，返回第一个输出的形状与数据
+    pub(crate) fn run(&self, session: &Session, data: &mut [f32], shape: &[i64]) -> Result<(Vec<i64>, Vec<f32>), String> {
+        unsafe {
+            let mut input: Handle = std::ptr::null_mut();
+            self.check(self.func::<CreateTensorFn>(FN_CREATE_TENSOR_WITH_DATA)(
+                self.memory,
+                data.as_mut_ptr() as *mut c_void,
+                std::mem::size_of_val(data),
+                shape.as_ptr(),
+                shape.len(),
+                ELEMENT_FLOAT,
+                &mut input,
+            ))?;
+            let mut output: Handle = std::ptr::null_mut();
+            let ran = self.check(self.func::<RunFn>(FN_RUN)(
+                session.handle,
+                std::ptr::null(),
+                &session.input.as_ptr(),
+                &input,
+                1,
+                &session.output.as_ptr(),
+                1,
+                &mut output,
+            ));
+            self.func::<ReleaseFn>(FN_RELEASE_VALUE)(input);
+            ran?;
+            let result = self.read_output(output);
+            self.func::<ReleaseFn>(FN_RELEASE_VALUE)(output);
+            result
+        }
+    }
+
+    unsafe fn read_output(&self, value: Handle) -> Result<(Vec<i64>, Vec<f32>), String> {
+        let mut info: Handle = std::ptr::null_mut();
+        self.check(self.func::<GetTypeAndShapeFn>(FN_GET_TENSOR_TYPE_AND_SHAPE)(value, &mut info))?;
+        let mut count = 0usize;
+        let mut dims = Vec::new();
+        let shape = self
+            .check(self.func::<GetDimensionsCountFn>(FN_GET_DIMENSIONS_COUNT)(info, &mut count))
+            .and_then(|_| {
+                dims = vec![0i64; count];
+                self.check(self.func::<GetDimensionsFn>(FN_GET_DIMENSIONS)(info, dims.as_mut_ptr(), count))
+            });
+        self.func::<ReleaseFn>(FN_RELEASE_TENSOR_TYPE_AND_SHAPE_INFO)(info);
+        shape?;
+
+        let len = dims.iter().map(|&d| d.max(0) as usize).product::<usize>();
+        let mut ptr: *mut c_void = std::ptr::null_mut();
+        self.check(self.func::<GetTensorDataFn>(FN_GET_TENSOR_MUTABLE_DATA)(value, &mut ptr))?;
+        let values = std::slice::from_raw_parts(ptr as *const f32, len).to_vec();
+        Ok((dims, values))
+    }
+
+    pub(crate) fn close(&self, session: &Session) {
+        unsafe { self.func::<ReleaseFn>(FN_RELEASE_SESSION)(session.handle) }
+    }
+}
+
+impl Drop for Runtime {
+    fn drop(&mut self) {
+        unsafe {
+            self.func::<ReleaseFn>(FN_RELEASE_MEMORY_INFO)(self.memory);
+            self.func::<ReleaseFn>(FN_RELEASE_ENV)(self.env);
+        }
+    }
+}
@@ -0,0 +1,216 @@
+// src/overlay.rs
+use crate::hardware::MouseButtons;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use windows::core::w;
+use windows::Win32::Foundation::{COLORREF, HWND, LPARAM, LRESULT, WPARAM};
+use windows::Win32::Graphics::Gdi::{
+    BeginPaint, CreatePen, DeleteObject, Ellipse, EndPaint, GetStockObject, InvalidateRect, LineTo, MoveToEx,
+    SelectObject, SetBkMode, SetTextColor, TextOutW, BLACK_BRUSH, HBRUSH, NULL_BRUSH, PAINTSTRUCT, PS_SOLID,
+    TRANSPARENT,
+};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW, PostQuitMessage, RegisterClassW,
+    SetLayeredWindowAttributes, SetTimer, SetWindowDisplayAffinity, TranslateMessage, LWA_ALPHA, LWA_COLORKEY, MSG,
+    WDA_EXCLUDEFROMCAPTURE, WM_DESTROY, WM_PAINT, WM_TIMER, WNDCLASSW, WS_EX_LAYERED, WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW, WS_EX_TOPMOST, WS_EX_TRANSPARENT,
+    WS_POPUP, WS_VISIBLE,
+};
+
+// ==========================================
+// 1. 共享绘制状态
+// ==========================================
+// 窗口过程没有上下文参数，只能通过全局状态传递数据
+
+/// 点击标记保留时长
+const MARK_TTL: Duration = Duration::from_millis(1500);
+/// 重绘间隔 (约 30 FPS)
+const REPAINT_MS: u32 = 33;
+/// 颜色键：纯黑像素完全透明
+const COLOR_KEY: COLORREF = COLORREF(0);
+
+struct ClickMark {
+    x: i32,
+    y: i32,
+    buttons: MouseButtons,
+    at: Instant,
+}
+
+struct OverlayState {
+    cursor: (i32, i32),
+    marks: Vec<ClickMark>,
+    /// 左上角状态栏 (最近一次按键等)
+    label: String,
+}
+
+static STATE: Mutex<OverlayState> = Mutex::new(OverlayState { cursor: (0, 0), marks: Vec::new(), label: String::new() });
+static STARTED: AtomicBool = AtomicBool::new(false);
+
+// ==========================================
+// 2. 对外接口
+// ==========================================
+
+/// 启动覆盖顶层窗口 (只启动一次)；窗口点击穿透，不抢焦点
+pub fn show(screen_w: u16, screen_h: u16) {
+    if STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let spawned = thread::Builder::new().name("dry-run-overlay".to_string()).spawn(move || {
+        if let Err(e) = run_window(screen_w as i32, screen_h as i32) {
+            println!("⚠️ [Overlay] 覆盖层创建失败，仅输出日志: {}", e);
+        }
+    });
+    if let Err(e) = spawned {
+        println!("⚠️ [Overlay] 无法启动覆盖层线程: {}", e);
+    }
+}
+
+pub fn set_cursor(x: i32, y: i32) {
+    if let Ok(mut s) = STATE.lock() {
+        s.cursor = (x, y);
+    }
+}
+
+pub fn add_click(x: i32, y: i32, buttons: MouseButtons) {
+    if let Ok(mut s) = STATE.lock() {
+        s.marks.push(ClickMark { x, y, buttons, at: Instant::now() });
+    }
+}
+
+pub fn set_label(text: String) {
+    if let Ok(mut s) = STATE.lock() {
+        s.label = text;
+    }
+}
+
+// ==========================================
+// 3. Win32 窗口与 GDI 绘制
+// ==========================================
+
+fn run_window(w: i32, h: i32) -> Result<(), String> {
+    unsafe {
+        let instance = GetModuleHandleW(None).map_err(|e| e.to_string())?;
+        let class_name = w!("NzmDryRunOverlay");
+        let wc = WNDCLASSW {
+            lpfnWndProc: Some(wnd_proc),
+            hInstance: instance.into(),
+            lpszClassName: class_name,
+            hbrBackground: HBRUSH(GetStockObject(BLACK_BRUSH).0),
+            ..Default::default()
+        };
+        if RegisterClassW(&wc) == 0 {
+            return Err("RegisterClassW 失败".to_string());
+        }
+
+        let hwnd = CreateWindowExW(
+            WS_EX_LAYERED | WS_EX_TRANSPARENT | WS_EX_TOPMOST | WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE,
+            class_name,
+            w!("NZM DryRun"),
+            WS_POPUP | WS_VISIBLE,
+            0,
+            0,
+            w,
+            h,
+            None,
+            None,
+            instance,
+            None,
+        );
+        if hwnd.0 == 0 {
+            return Err("CreateWindowExW 失败".to_string());
+        }
+        SetLayeredWindowAttributes(hwnd, COLOR_KEY, 200, LWA_COLORKEY | LWA_ALPHA).map_err(|e| e.to_string())?;
+        // 覆盖层不能出现在截图中，否则标记会被当作界面内容识别 (需要 Windows 10 2004 及以上)
+        if let Err(e) = SetWindowDisplayAffinity(hwnd, WDA_EXCLUDEFROMCAPTURE) {
+            println!("⚠️ [Overlay] 无法将覆盖层排除出截图 ({})，识别结果可能受标记干扰", e);
+        }
+        SetTimer(hwnd, 1, REPAINT_MS, None);
+
+        let mut msg = MSG::default();
+        while GetMessageW(&mut msg, None, 0, 0).as_bool() {
+            TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+    }
+    Ok(())
+}
+
+unsafe extern "system" fn wnd_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    match msg {
+        WM_TIMER => {
+            InvalidateRect(hwnd, None, true);
+            LRESULT(0)
+        }
+        WM_PAINT => {
+            paint(hwnd);
+            LRESULT(0)
+        }
+        WM_DESTROY => {
+            PostQuitMessage(0);
+            LRESULT(0)
+        }
+        _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+    }
+}
+
+/// COLORREF 为 0x00BBGGRR
+const fn rgb(r: u8, g: u8, b: u8) -> COLORREF {
+    COLORREF(r as u32 | (g as u32) << 8 | (b as u32) << 16)
+}
+
+fn mark_color(buttons: MouseButtons) -> COLORREF {
+    if buttons.contains(MouseButtons::LEFT) {
+        rgb(255, 64, 64)
+    } else if buttons.contains(MouseButtons::RIGHT) {
+        rgb(64, 160, 255)
+    } else {
+        rgb(255, 220, 0)
+    }
+}
+
+unsafe fn paint(hwnd: HWND) {
+    let mut ps = PAINTSTRUCT::default();
+    let hdc = BeginPaint(hwnd, &mut ps);
+
+    let Ok(mut state) = STATE.lock() else {
+        EndPaint(hwnd, &ps);
+        return;
+    };
+    state.marks.retain(|m| m.at.elapsed() < MARK_TTL);
+
+    SelectObject(hdc, GetStockObject(NULL_BRUSH));
+
+    // 点击标记：随时间扩散的圆圈
+    for mark in &state.marks {
+        let pen = CreatePen(PS_SOLID, 3, mark_color(mark.buttons));
+        let old = SelectObject(hdc, pen);
+        let r = 8 + (mark.at.elapsed().as_millis() as i32 / 50);
+        Ellipse(hdc, mark.x - r, mark.y - r, mark.x + r, mark.y + r);
+        SelectObject(hdc, old);
+        DeleteObject(pen);
+    }
+
+    // 当前光标十字线
+    let (cx, cy) = state.cursor;
+    let pen = CreatePen(PS_SOLID, 2, rgb(0, 255, 128));
+    let old = SelectObject(hdc, pen);
+    MoveToEx(hdc, cx - 20, cy, None);
+    LineTo(hdc, cx + 21, cy);
+    MoveToEx(hdc, cx, cy - 20, None);
+    LineTo(hdc, cx, cy + 21);
+    SelectObject(hdc, old);
+    DeleteObject(pen);
+
+    // 状态栏
+    let text: Vec<u16> = format!("DRY RUN  ({}, {})  {}", cx, cy, state.label).encode_utf16().collect();
+    SetBkMode(hdc, TRANSPARENT);
+    SetTextColor(hdc, rgb(255, 255, 255));
+    TextOutW(hdc, 12, 12, &text);
+
+    drop(state);
+    EndPaint(hwnd, &ps);
+}
+
@@ -0,0 +1,292 @@
+// src/paddle.rs
+// 通过 ONNX Runtime 运行 PP-OCRv4 检测 + 识别模型 (需将 onnxruntime.dll 放在程序目录，模型放在 [ocr] models 目录)
+// 模型目录结构: det.onnx (文字检测) / rec.onnx (文字识别) / ppocr_keys_v1.txt (识别字典，每行一个字符)
+use crate::ocr::{OcrBackend, OcrConfig, OcrLine, OcrWord};
+use crate::onnx::{Runtime, Session};
+use image::{imageops::FilterType, DynamicImage, RgbImage};
+use std::path::Path;
+
+// ==========================================
+// 1. 文字检测 (DB)
+// ==========================================
+
+/// 检测输入的最长边上限 (与 PaddleOCR det_limit_side_len 一致)
+const DET_MAX_SIDE: u32 = 960;
+/// 概率图二值化阈值
+const DET_THRESH: f32 = 0.3;
+/// 文字框内平均概率低于此值视为误检
+const DET_BOX_THRESH: f32 = 0.6;
+/// 文字框外扩比例 (DB 预测的是收缩后的文字核心区域)
+const DET_UNCLIP_RATIO: f32 = 1.5;
+
+const DET_MEAN: [f32; 3] = [0.485, 0.456, 0.406];
+const DET_STD: [f32; 3] = [0.229, 0.224, 0.225];
+
+/// 文字框 (原图坐标，右下角不含)
+#[derive(Debug, Clone, Copy)]
+struct TextBox {
+    x1: u32,
+    y1: u32,
+    x2: u32,
+    y2: u32,
+}
+
+/// 将 HWC 的 RGB 图像转换为 NCHW 张量，逐通道做 (v / 255 - mean) / std
+fn to_tensor(img: &RgbImage, mean: [f32; 3], std: [f32; 3]) -> Vec<f32> {
+    let (w, h) = img.dimensions();
+    let plane = (w * h) as usize;
+    let mut data = vec![0f32; plane * 3];
+    for (i, p) in img.pixels().enumerate() {
+        for c in 0..3 {
+            data[c * plane + i] = (p[c] as f32 / 255.0 - mean[c]) / std[c];
+        }
+    }
+    data
+}
+
+/// 检测输入尺寸：等比缩放到最长边不超过 DET_MAX_SIDE，再取整到 32 的倍数
+fn det_size(w: u32, h: u32) -> (u32, u32) {
+    let scale = (DET_MAX_SIDE as f32 / w.max(h) as f32).min(1.0);
+    let round = |v: u32| (((v as f32 * scale) / 32.0).round() as u32 * 32).max(32);
+    (round(w), round(h))
+}
+
+/// 对概率图做连通域分析，返回过滤并外扩后的文字框 (概率图坐标)
+fn boxes_from_map(prob: &[f32], w: usize, h: usize) -> Vec<TextBox> {
+    let mut visited = vec![false; w * h];
+    let mut boxes = Vec::new();
+    let mut stack = Vec::new();
+    for start in 0..w * h {
+        if visited[start] || prob[start] <= DET_THRESH {
+            continue;
+        }
+        visited[start] = true;
+        stack.push(start);
+        let (mut x1, mut y1, mut x2, mut y2) = (w, h, 0, 0);
+        let (mut sum, mut count) = (0f32, 0usize);
+        while let Some(i) = stack.pop() {
+            let (x, y) = (i % w, i / w);
+            x1 = x1.min(x);
+            y1 = y1.min(y);
+            x2 = x2.max(x);
+            y2 = y2.max(y);
+            sum += prob[i];
+            count += 1;
+            let neighbors = [
+                (x > 0).then(|| i - 1),
+                (x + 1 < w).then(|| i + 1),
+                (y > 0).then(|| i - w),
+                (y + 1 < h).then(|| i + w),
+            ];
+            for n in neighbors.into_iter().flatten() {
+                if !visited[n] && prob[n] > DET_THRESH {
+                    visited[n] = true;
+                    stack.push(n);
+                }
+            }
+        }
+
+        let (bw, bh) = ((x2 - x1 + 1) as f32, (y2 - y1 + 1) as f32);
+        if bw.min(bh) < 3.0 || sum / (count as f32) < DET_BOX_THRESH {
+            continue;
+        }
+        // DB 的 unclip：按 面积 × 比例 / 周长 向外扩展
+        let d = bw * bh * DET_UNCLIP_RATIO / (2.0 * (bw + bh));
+        boxes.push(TextBox {
+            x1: (x1 as f32 - d).max(0.0) as u32,
+            y1: (y1 as f32 - d).max(0.0) as u32,
+            x2: ((x2 + 1) as f32 + d).min(w as f32) as u32,
+            y2: ((y2 + 1) as f32 + d).min(h as f32) as u32,
+        });
+    }
+    boxes
+}
+
+/// 按阅读顺序排列文字框：先按行 (纵向中心落在同一行高度内)，行内从左到右
+fn reading_order(mut boxes: Vec<TextBox>) -> Vec<TextBox> {
+    boxes.sort_by_key(|b| b.y1 + b.y2);
+    let mut lines: Vec<Vec<TextBox>> = Vec::new();
+    for b in boxes {
+        let center = (b.y1 + b.y2) / 2;
+        match lines.last_mut() {
+            Some(line) if center >= line[0].y1 && center < line[0].y2 => line.push(b),
+            _ => lines.push(vec![b]),
+        }
+    }
+    lines
+        .into_iter()
+        .flat_map(|mut line| {
+            line.sort_by_key(|b| b.x1);
+            line
+        })
+        .collect()
+}
+
+// ==========================================
+// 2. 文字识别 (CTC)
+// ==========================================
+
+/// 识别模型的输入高度 (PP-OCRv4 rec 为 3×48×W)
+const REC_HEIGHT: u32 = 48;
+/// 识别输入宽度上限，防止超长文字行占用过多内存
+const REC_MAX_WIDTH: u32 = 2048;
+
+/// 贪心 CTC 解码：逐帧取最大概率类别，去掉空白 (0) 与连续重复；
+/// 同时返回输出字符的平均概率作为置信度 (没有输出字符时为 0)
+fn ctc_decode(probs: &[f32], steps: usize, classes: usize, dict: &[String]) -> (String, f32) {
+    let mut text = String::new();
+    let (mut sum, mut count) = (0.0f32, 0usize);
+    let mut last = 0usize;
+    for t in 0..steps {
+        let frame = &probs[t * classes..(t + 1) * classes];
+        let (best, p) = frame
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(i, p)| (i, *p))
+            .unwrap_or((0, 0.0));
+        if best != 0 && best != last {
+            if let Some(ch) = dict.get(best - 1) {
+                text.push_str(ch);
+                sum += p;
+                count += 1;
+            }
+        }
+        last = best;
+    }
+    (text, if count > 0 { sum / count as f32 } else { 0.0 })
+}
+
+// ==========================================
+// 3. 后端
+// ==========================================
+
+pub struct PaddleOcr {
+    runtime: Runtime,
+    det: Session,
+    rec: Session,
+    dict: Vec<String>,
+}
+
+// OrtSession::Run 允许多线程并发调用，会话创建后不再修改
+unsafe impl Send for PaddleOcr {}
+unsafe impl Sync for PaddleOcr {}
+
+impl PaddleOcr {
+    pub fn new(cfg: &OcrConfig) -> Result<Self, String> {
+        let dir = Path::new(&cfg.models);
+        let keys = dir.join("ppocr_keys_v1.txt");
+        let mut dict: Vec<String> = std::fs::read_to_string(&keys)
+            .map_err(|e| format!("无法读取识别字典 {}: {}", keys.display(), e))?
+            .lines()
+            .map(|l| l.trim_end_matches('\r').to_string())
+            .collect();
+        // PaddleOCR 训练时启用了 use_space_char，字典末尾隐含一个空格
+        dict.push(" ".to_string());
+
+        let runtime = Runtime::load(&cfg.onnxruntime)?;
+        let det = runtime.open(&dir.join("det.onnx"))?;
+        let rec = match runtime.open(&dir.join("rec.onnx")) {
+            Ok(rec) => rec,
+            Err(e) => {
+                runtime.close(&det);
+                return Err(e);
+            }
+        };
+        Ok(Self { runtime, det, rec, dict })
+    }
+
+    fn detect(&self, img: &RgbImage) -> Result<Vec<TextBox>, String> {
+        let (w, h) = img.dimensions();
+        let (dw, dh) = det_size(w, h);
+        let resized = image::imageops::resize(img, dw, dh, FilterType::Triangle);
+        let mut data = to_tensor(&resized, DET_MEAN, DET_STD);
+        let (shape, prob) = self.runtime.run(&self.det, &mut data, &[1, 3, dh as i64, dw as i64])?;
+        // 输出为 1×1×H×W 的文字概率图
+        let (mh, mw) = match shape.as_slice() {
+            [.., mh, mw] => (*mh as usize, *mw as usize),
+            _ => return Err(format!("检测模型输出形状异常: {:?}", shape)),
+        };
+        let (sx, sy) = (w as f32 / mw as f32, h as f32 / mh as f32);
+        let boxes = boxes_from_map(&prob, mw, mh)
+            .into_iter()
+            .map(|b| TextBox {
+                x1: (b.x1 as f32 * sx) as u32,
+                y1: (b.y1 as f32 * sy) as u32,
+                x2: ((b.x2 as f32 * sx).ceil() as u32).min(w),
+                y2: ((b.y2 as f32 * sy).ceil() as u32).min(h),
+            })
+            .filter(|b| b.x2 > b.x1 && b.y2 > b.y1)
+            .collect();
+        Ok(reading_order(boxes))
+    }
+
+    fn read_line(&self, line: &RgbImage) -> Result<(String, f32), String> {
+        let (w, h) = line.dimensions();
+        let width = ((REC_HEIGHT as f32 * w as f32 / h as f32).ceil() as u32).clamp(REC_HEIGHT / 3, REC_MAX_WIDTH);
+        let resized = image::imageops::resize(line, width, REC_HEIGHT, FilterType::Triangle);
+        let mut data = to_tensor(&resized, [0.5; 3], [0.5; 3]);
+        let (shape, probs) = self.runtime.run(&self.rec, &mut data, &[1, 3, REC_HEIGHT as i64, width as i64])?;
+        // 输出为 1×T×C 的逐帧字符概率
+        match shape.as_slice() {
+            [_, steps, classes] => Ok(ctc_decode(&probs, *steps as usize, *classes as usize, &self.dict)),
+            _ => Err(format!("识别模型输出形状异常: {:?}", shape)),
+        }
+    }
+}
+
+impl Drop for PaddleOcr {
+    fn drop(&mut self) {
+        self.runtime.close(&self.det);
+        self.runtime.close(&self.rec);
+    }
+}
+
+impl OcrBackend for PaddleOcr {
+    fn name(&self) -> &'static str { "paddle" }
+
+    fn recognize(&self, img: &DynamicImage) -> Result<String, String> {
+        self.recognize_scored(img).map(|(text, _)| text)
+    }
+
+    /// 置信度取各文字行的平均值
+    fn recognize_scored(&self, img: &DynamicImage) -> Result<(String, Option<f32>), String> {
+        let rgb = img.to_rgb8();
+        let boxes = self.detect(&rgb)?;
+        // 截图区域通常就是一行文字，检测不到文字框时整张图直接送识别
+        if boxes.is_empty() {
+            let (text, confidence) = self.read_line(&rgb)?;
+            return Ok((text.replace(|c: char| c.is_whitespace(), ""), Some(confidence)));
+        }
+        let (mut text, mut sum) = (String::new(), 0.0f32);
+        let lines = boxes.len();
+        for b in boxes {
+            let crop = image::imageops::crop_imm(&rgb, b.x1, b.y1, b.x2 - b.x1, b.y2 - b.y1).to_image();
+            let (line, confidence) = self.read_line(&crop)?;
+            text.push_str(&line);
+            sum += confidence;
+        }
+        Ok((text.replace(|c: char| c.is_whitespace(), ""), Some(sum / lines as f32)))
+    }
+
+    /// 每个检测框作为一行 (整行作为一个词)；检测不到文字框时与 recognize 一样整张图作为一行
+    fn recognize_lines(&self, img: &DynamicImage) -> Result<Vec<OcrLine>, String> {
+        let rgb = img.to_rgb8();
+        let mut boxes = self.detect(&rgb)?;
+        if boxes.is_empty() {
+            boxes.push(TextBox { x1: 0, y1: 0, x2: rgb.width(), y2: rgb.height() });
+        }
+        let mut lines = Vec::new();
+        for b in boxes {
+            let crop = image::imageops::crop_imm(&rgb, b.x1, b.y1, b.x2 - b.x1, b.y2 - b.y1).to_image();
+            let (text, confidence) = self.read_line(&crop)?;
+            let text = text.replace(|c: char| c.is_whitespace(), "");
+            if text.is_empty() {
+                continue;
+            }
+            let rect = [b.x1 as i32, b.y1 as i32, b.x2 as i32, b.y2 as i32];
+            lines.push(OcrLine::from_words(vec![OcrWord { text, rect, confidence: Some(confidence) }]));
+        }
+        Ok(lines)
+    }
+}
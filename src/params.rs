@@ -0,0 +1,110 @@
+// src/params.rs
+// 参数化场景：带 params 的场景 / 跳转按参数取值表展开为多份，字符串中的 {名称} 替换为对应的值
+//
+// [params.chapter]                       # 取值表：每个取值附带若干命名数值 (如该章节按钮的坐标)
+// 1 = { x = 320, y = 540 }
+// 2 = { x = 520, y = 540 }
+//
+// [[scenes]]
+// id = "stage_select_{n}"
+// params = { n = "chapter" }             # 引用取值表，也可直接内联 { 1 = { ... }, 2 = { ... } }
+use std::collections::BTreeMap;
+use toml::{Table, Value};
+
+/// 一组参数取值：参数名 -> 取值，以及所选行中的命名数值
+type Bindings = BTreeMap<String, Value>;
+
+/// 取值表的键为整数时按整数代入，"{n}" 单独出现在数值字段中也能得到整数
+fn key_value(key: &str) -> Value {
+    key.parse::<i64>().map(Value::Integer).unwrap_or_else(|_| Value::String(key.to_string()))
+}
+
+fn display(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// 替换字符串中的 {名称}：整个字符串就是一个占位符时保留原类型 (用于坐标等数值字段)，否则按文本拼接；
+/// 未定义的名称原样保留 (如正则里的 \d{2})
+fn substitute(value: &Value, bindings: &Bindings) -> Value {
+    match value {
+        Value::String(s) => {
+            if let Some(v) = s.strip_prefix('{').and_then(|r| r.strip_suffix('}')).and_then(|name| bindings.get(name)) {
+                return v.clone();
+            }
+            let mut out = s.clone();
+            for (name, v) in bindings {
+                out = out.replace(&format!("{{{}}}", name), &display(v));
+            }
+            Value::String(out)
+        }
+        Value::Array(items) => Value::Array(items.iter().map(|v| substitute(v, bindings)).collect()),
+        Value::Table(table) => Value::Table(table.iter().map(|(k, v)| (k.clone(), substitute(v, bindings))).collect()),
+        other => other.clone(),
+    }
+}
+
+/// 解析 params：每个参数对应一张取值表 (内联或引用 tables 中的表)，返回全部取值组合
+fn combinations(params: &Table, tables: &Table) -> Result<Vec<Bindings>, String> {
+    let mut result = vec![Bindings::new()];
+    for (name, spec) in params {
+        let table = match spec {
+            Value::String(table_name) => tables
+                .get(table_name)
+                .and_then(Value::as_table)
+                .ok_or_else(|| format!("参数 {} 引用的取值表 [params.{}] 不存在", name, table_name))?,
+            Value::Table(inline) => inline,
+            _ => return Err(format!("参数 {} 应为取值表或取值表名", name)),
+        };
+        if table.is_empty() {
+            return Err(format!("参数 {} 的取值表为空", name));
+        }
+        let mut next = Vec::new();
+        for bindings in &result {
+            for (key, row) in table {
+                let mut b = bindings.clone();
+                b.insert(name.clone(), key_value(key));
+                if let Some(row) = row.as_table() {
+                    b.extend(row.iter().map(|(k, v)| (k.clone(), v.clone())));
+                }
+                next.push(b);
+            }
+        }
+        result = next;
+    }
+    Ok(result)
+}
+
+/// 按 params 展开一个表 (场景或跳转)：每个取值组合得到一份去掉 params 的副本；没有 params 时原样返回
+fn expand_one(value: &Value, tables: &Table) -> Result<Vec<Value>, String> {
+    let Some(params) = value.get("params") else { return Ok(vec![value.clone()]) };
+    let params = params.as_table().ok_or("params 应为表")?;
+    let mut template = value.clone();
+    if let Some(t) = template.as_table_mut() {
+        t.remove("params");
+    }
+    Ok(combinations(params, tables)?.iter().map(|b| substitute(&template, b)).collect())
+}
+
+/// 展开一个场景：先按场景自身的 params 展开，再展开其中带 params 的跳转
+pub fn expand_scene(scene: &Value, tables: &Table) -> Result<Vec<Value>, String> {
+    let mut scenes = expand_one(scene, tables)?;
+    for s in &mut scenes {
+        let Some(Value::Array(transitions)) = s.get("transitions") else { continue };
+        let mut expanded = Vec::new();
+        for t in transitions {
+            expanded.extend(expand_one(t, tables)?);
+        }
+        if let Some(t) = s.as_table_mut() {
+            t.insert("transitions".to_string(), Value::Array(expanded));
+        }
+    }
+    Ok(scenes)
+}
+
+/// 用参数填充场景 id 模板，如 fill("stage_select_{n}", &[("n", "3")]) == "stage_select_3"
+pub fn fill(template: &str, params: &[(&str, &str)]) -> String {
+    params.iter().fold(template.to_string(), |id, (name, value)| id.replace(&format!("{{{}}}", name), value))
+}
@@ -0,0 +1,283 @@
+// src/pipeline.rs
+use crate::checkpoint::TaskCheckpoint;
+use crate::handlers::{self, HandlerReport};
+use crate::hardware::{DriverError, MouseButtons};
+use crate::human::HumanDriver;
+use crate::nav::{NavEngine, NavResult};
+use crate::secrets::{SecretStore, DEFAULT_SECRETS_FILE};
+use crate::stats::StatsStore;
+use crate::trace;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+// ==========================================
+// 1. TOML 配置数据结构
+// ==========================================
+#[derive(Deserialize, Debug, Clone)]
+struct PipelineRoot {
+    pipelines: Vec<Pipeline>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Pipeline {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub steps: Vec<PipelineStep>,
+}
+
+/// 流水线中的单个步骤
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PipelineStep {
+    /// 导航至指定场景
+    Navigate {
+        target: String,
+        #[serde(default = "default_retries")]
+        retries: u32,
+    },
+    /// 在当前场景启动处理器 ("daily" / "td")，repeat > 1 时每轮前重新导航
+    Handler {
+        name: String,
+        #[serde(default = "default_repeat")]
+        repeat: u32,
+    },
+    /// 固定等待
+    Wait { ms: u64 },
+    /// 使用凭据库 (secrets.json) 中的账号登录
+    Login {
+        account: String,
+        username_pos: [i32; 2],
+        password_pos: [i32; 2],
+        submit_pos: [i32; 2],
+        #[serde(default = "default_login_delay")]
+        post_delay: u64,
+    },
+    /// 输出阶段汇总
+    Notify {
+        #[serde(default)]
+        message: String,
+    },
+}
+
+fn default_retries() -> u32 { 3 }
+fn default_repeat() -> u32 { 1 }
+fn default_login_delay() -> u64 { 5000 }
+
+/// 流水线进度检查点：正在执行的步骤及该步骤已完成的轮数
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+struct PipelineProgress {
+    step: usize,
+    round: u32,
+}
+
+const PIPELINE_CHECKPOINT_MAX_AGE: Duration = Duration::from_secs(12 * 3600);
+
+// ==========================================
+// 2. 流水线引擎
+// ==========================================
+pub struct PipelineEngine {
+    pipelines: HashMap<String, Pipeline>,
+    driver: Arc<Mutex<HumanDriver>>,
+    engine: Arc<NavEngine>,
+}
+
+impl PipelineEngine {
+    pub fn load(path: &str, driver: Arc<Mutex<HumanDriver>>, engine: Arc<NavEngine>) -> Result<Self, String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("无法读取 {}: {}", path, e))?;
+        let root: PipelineRoot = toml::from_str(&content).map_err(|e| format!("{} 解析错误: {}", path, e))?;
+
+        let mut pipelines = HashMap::new();
+        for p in root.pipelines {
+            if pipelines.contains_key(&p.name) {
+                return Err(format!("流水线名称重复: {}", p.name));
+            }
+            pipelines.insert(p.name.clone(), p);
+        }
+        Ok(Self { pipelines, driver, engine })
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.pipelines.keys().map(|k| k.as_str()).collect()
+    }
+
+    /// 执行指定流水线，返回沿途所有处理器的汇报
+    pub fn run(&self, name: &str, stats: Option<&StatsStore>) -> Result<Vec<HandlerReport>, String> {
+        let pipeline = self
+            .pipelines
+            .get(name)
+            .ok_or_else(|| format!("未找到流水线: {}", name))?;
+
+        println!("🧵 [Pipeline] 开始执行 [{}] ({} 步)", pipeline.name, pipeline.steps.len());
+        if !pipeline.description.is_empty() {
+            println!("   📝 {}", pipeline.description);
+        }
+
+        let mut reports: Vec<HandlerReport> = Vec::new();
+        let mut last_target: Option<(String, u32)> = None;
+        let mut current_scene: Option<String> = None;
+
+        let owner = format!("pipeline:{}", pipeline.name);
+        let progress = TaskCheckpoint::open(&owner, "pipeline", &pipeline.name);
+        let record = |report: &HandlerReport| {
+            if let Some(store) = stats {
+                if let Err(e) = store.record_run(&report.to_record()) {
+                    println!("⚠️ {}", e);
+                }
+            }
+        };
+
+        // 上次中断的进度：先接上未完成的战斗，再重新导航确认场景，然后从中断的步骤继续
+        let mut start = PipelineProgress::default();
+        if let Some(p) = progress.load::<PipelineProgress>(PIPELINE_CHECKPOINT_MAX_AGE) {
+            if p.step < pipeline.steps.len() {
+                println!("♻️ [Pipeline] 从检查点继续: 步骤 {}/{} (已完成 {} 轮)", p.step + 1, pipeline.steps.len(), p.round);
+                start = p;
+                if let Some(report) = handlers::resume_pending(&owner, &self.driver, &self.engine) {
+                    record(&report);
+                    reports.push(report);
+                    if matches!(pipeline.steps[start.step], PipelineStep::Handler { .. }) {
+                        start.round += 1;
+                    }
+                }
+                let prev_nav = pipeline.steps[..start.step].iter().rev().find_map(|s| match s {
+                    PipelineStep::Navigate { target, retries } => Some((target.clone(), *retries)),
+                    _ => None,
+                });
+                if let Some((target, retries)) = prev_nav {
+                    current_scene = Some(self.navigate_with_retry(&target, retries)?);
+                    last_target = Some((target, retries));
+                }
+            }
+        }
+
+        for (i, step) in pipeline.steps.iter().enumerate().skip(start.step) {
+            let done_rounds = if i == start.step { start.round } else { 0 };
+            progress.save(&PipelineProgress { step: i, round: done_rounds });
+
+            println!("\n🧵 [Pipeline] 步骤 {}/{}: {:?}", i + 1, pipeline.steps.len(), step);
+            match step {
+                PipelineStep::Navigate { target, retries } => {
+                    let scene = self.navigate_with_retry(target, *retries)?;
+                    current_scene = Some(scene);
+                    last_target = Some((target.clone(), *retries));
+                }
+                PipelineStep::Handler { name, repeat } => {
+                    for round in (done_rounds + 1)..=(*repeat).max(1) {
+                        if round > 1 {
+                            if let Some((target, retries)) = &last_target {
+                                current_scene = Some(self.navigate_with_retry(target, *retries)?);
+                            }
+                        }
+                        let scene = current_scene
+                            .as_deref()
+                            .ok_or_else(|| format!("处理器 [{}] 之前没有导航步骤", name))?;
+
+                        println!("🔁 [Pipeline] 处理器 [{}] 第 {}/{} 轮", name, round, repeat);
+                        let report = handlers::run_handler(name, scene, &owner, &self.driver, &self.engine);
+                        record(&report);
+                        reports.push(report);
+                        progress.save(&PipelineProgress { step: i, round });
+                        thread::sleep(Duration::from_secs(5));
+                    }
+                }
+                PipelineStep::Wait { ms } => {
+                    thread::sleep(Duration::from_millis(*ms));
+                }
+                PipelineStep::Login { account, username_pos, password_pos, submit_pos, post_delay } => {
+                    self.login(account, *username_pos, *password_pos, *submit_pos)?;
+                    thread::sleep(Duration::from_millis(*post_delay));
+                }
+                PipelineStep::Notify { message } => {
+                    print_summary(message, &reports);
+                }
+            }
+        }
+
+        progress.clear();
+        println!("🏁 [Pipeline] [{}] 执行完毕", pipeline.name);
+        Ok(reports)
+    }
+
+    fn login(&self, account: &str, user_pos: [i32; 2], pass_pos: [i32; 2], submit_pos: [i32; 2]) -> Result<(), String> {
+        let cred = SecretStore::open(DEFAULT_SECRETS_FILE)?.get(account)?;
+        println!("🔐 [Pipeline] 登录账号 [{}] ({})", account, cred.username);
+
+        let mut d = self.driver.lock().map_err(|_| "驱动锁中毒".to_string())?;
+        if let Some(bad) = cred.username.chars().chain(cred.password.chars()).find(|c| !d.can_type(*c)) {
+            return Err(format!("账号 [{}] 含有无法通过键盘输入的字符: {:?}", account, bad));
+        }
+
+        let input = |d: &mut HumanDriver| -> Result<(), DriverError> {
+            d.move_to_humanly(user_pos[0] as u16, user_pos[1] as u16, 0.5)?;
+            d.click_humanly(MouseButtons::LEFT, 0)?;
+            thread::sleep(Duration::from_millis(300));
+            d.type_humanly(&cred.username, 60.0)?;
+
+            thread::sleep(Duration::from_millis(400));
+            d.move_to_humanly(pass_pos[0] as u16, pass_pos[1] as u16, 0.5)?;
+            d.click_humanly(MouseButtons::LEFT, 0)?;
+            thread::sleep(Duration::from_millis(300));
+            d.type_humanly(&cred.password, 60.0)?;
+
+            thread::sleep(Duration::from_millis(400));
+            d.move_to_humanly(submit_pos[0] as u16, submit_pos[1] as u16, 0.5)?;
+            d.click_humanly(MouseButtons::LEFT, 0)
+        };
+        // 开着 --trace 时轨迹是明文 JSONL，账号密码的按键不能写进去
+        let _redact = trace::redact();
+        input(&mut d).map_err(|e| format!("登录账号 [{}] 时输入失败: {}", account, e))
+    }
+
+    /// 导航到目标，失败时执行界面重置并重试，返回最终所在的场景 ID
+    fn navigate_with_retry(&self, target: &str, retries: u32) -> Result<String, String> {
+        for attempt in 0..=retries {
+            if attempt > 0 {
+                println!("🔄 [Pipeline] 第 {}/{} 次重试导航至 [{}]", attempt, retries, target);
+            }
+            match self.engine.navigate(target) {
+                NavResult::Handover(scene_id, _) => return Ok(scene_id),
+                NavResult::Success => return Ok(target.to_string()),
+                NavResult::Failed(report) => {
+                    println!("❌ [Pipeline] 导航至 [{}] 失败\n{}", target, report);
+                    handlers::reset_interface(&self.driver);
+                    thread::sleep(Duration::from_secs(3));
+                }
+            }
+        }
+        Err(format!("导航至 [{}] 失败 (已重试 {} 次)", target, retries))
+    }
+}
+
+fn print_summary(message: &str, reports: &[HandlerReport]) {
+    let total_secs: f64 = reports.iter().map(|r| r.duration_secs).sum();
+    let wins = reports.iter().filter(|r| r.success).count();
+    let refreshes: u32 = reports.iter().map(|r| r.refresh_count).sum();
+
+    println!("========================================");
+    if !message.is_empty() {
+        println!("📣 {}", message);
+    }
+    println!(
+        "📊 已执行 {} 个任务 | 成功 {} | 刷新 {} 次 | 总耗时 {:.0}s",
+        reports.len(),
+        wins,
+        refreshes,
+        total_secs
+    );
+    for r in reports {
+        println!(
+            "   - [{}] {} | {} | 波次 {} | {:.0}s",
+            r.handler,
+            r.scene_id,
+            if r.success { "✅" } else { "❌" },
+            r.waves,
+            r.duration_secs
+        );
+    }
+    println!("========================================");
+}
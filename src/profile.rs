@@ -0,0 +1,299 @@
+// src/profile.rs
+use crate::fatigue::FatigueConfig;
+use crate::trajectory::{MotionConfig, PathModel};
+use rand::Rng;
+use rand_distr::{Distribution, Exp, Normal};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+// ==========================================
+// 1. 配置 (config.toml 中的 [human])
+// ==========================================
+#[derive(Deserialize, Debug, Clone)]
+pub struct HumanConfig {
+    /// 使用的档案名: 内置 normal / careful / fast / sloppy，或 profile_file 中定义的档案
+    #[serde(default = "default_profile")]
+    pub profile: String,
+    #[serde(default = "default_profile_file")]
+    pub profile_file: String,
+    /// 固定随机种子 (--seed 可覆盖)；缺省时每次启动随机生成并打印，便于复现问题
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// 暂停 / 继续所有拟人动作的热键 (F1 ~ F12)，进行中的移动停在原处；留空表示不启用
+    #[serde(default)]
+    pub pause_hotkey: String,
+    /// 每次移动前如何校正内部记录的光标位置: auto / os / anchor / off (见 human::CursorSync)
+    #[serde(default = "default_cursor_sync")]
+    pub cursor_sync: String,
+}
+
+fn default_profile() -> String { "normal".to_string() }
+fn default_profile_file() -> String { "human_profile.toml".to_string() }
+fn default_cursor_sync() -> String { "auto".to_string() }
+
+impl Default for HumanConfig {
+    fn default() -> Self {
+        Self { profile: default_profile(), profile_file: default_profile_file(), seed: None, pause_hotkey: String::new(), cursor_sync: default_cursor_sync() }
+    }
+}
+
+// ==========================================
+// 2. 拟人参数档案
+// ==========================================
+
+/// 毫秒区间 [最小, 最大]，在其中均匀取值
+pub type MsRange = [u64; 2];
+
+/// 在区间内随机取一个毫秒数；区间为空时取下限
+pub fn sample(rng: &mut impl Rng, range: MsRange) -> u64 {
+    if range[1] > range[0] { rng.gen_range(range[0]..=range[1]) } else { range[0] }
+}
+
+/// 识别到目标到开始移动之间的反应时间，服从 ex-Gaussian 分布
+/// (正态部分 mu / sigma 加上均值为 tau 的指数拖尾，与人的简单反应时分布形状一致)，结果夹紧在 [min_ms, max_ms]
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct ReactionTime {
+    pub mu_ms: f32,
+    pub sigma_ms: f32,
+    pub tau_ms: f32,
+    pub min_ms: u64,
+    pub max_ms: u64,
+}
+
+impl Default for ReactionTime {
+    fn default() -> Self {
+        Self { mu_ms: 200.0, sigma_ms: 25.0, tau_ms: 50.0, min_ms: 180, max_ms: 350 }
+    }
+}
+
+impl ReactionTime {
+    pub fn sample(&self, rng: &mut impl Rng) -> u64 {
+        let normal = Normal::new(self.mu_ms, self.sigma_ms.max(0.0)).map_or(self.mu_ms, |n| n.sample(rng));
+        let tail = if self.tau_ms > 0.0 { Exp::new(1.0 / self.tau_ms).map_or(0.0, |e| e.sample(rng)) } else { 0.0 };
+        ((normal + tail).max(0.0) as u64).clamp(self.min_ms, self.max_ms.max(self.min_ms))
+    }
+}
+
+/// HumanDriver 的全部随机参数；档案中缺省的字段取 normal 档的值
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct HumanProfile {
+    /// 移动时长倍率 (>1 更慢)
+    pub speed: f32,
+    /// 移动终点的随机偏移 (px)
+    pub target_jitter_px: f32,
+    /// 轨迹模型与冲过目标
+    pub motion: MotionConfig,
+    /// click_in_rect_humanly 的瞄点偏离矩形中心的比例 [横, 纵] (习惯性偏右下为正)
+    pub click_bias: [f32; 2],
+    /// click_in_rect_humanly 的落点标准差占矩形宽 / 高的比例
+    pub click_spread: f32,
+    /// 每次移动 / 点击 / 按键前的反应延迟
+    pub reaction_ms: MsRange,
+    /// react_then_click 中「看到目标」到「开始移动」的反应时间
+    pub recognition: ReactionTime,
+    /// 未指定时长时鼠标点击的按住时间
+    pub click_hold_ms: MsRange,
+    /// 双击两次点击之间的间隔
+    pub double_click_gap_ms: MsRange,
+    /// 长按时长的浮动比例 (0.1 表示 ±10%)
+    pub long_press_jitter: f32,
+    /// 拖拽时按下后、松开前的停顿
+    pub drag_press_ms: MsRange,
+    pub drag_release_ms: MsRange,
+    /// 按住拖拽时轨迹中段的最大下垂 (px)
+    pub drag_sag_px: f32,
+    /// move_along_humanly 在拐角处倒圆的半径 (px)
+    pub path_corner_px: f32,
+    /// key_click 的按住时间
+    pub key_tap_ms: MsRange,
+    /// key_hold(ms = 0) 的极短接触时间
+    pub key_min_hold_ms: MsRange,
+    /// chord_hold(ms = 0) 的按住时间
+    pub chord_hold_ms: MsRange,
+    /// key_combo_humanly 中修饰键先于主键按下的时间
+    pub combo_lead_ms: MsRange,
+    /// key_combo_humanly 中多个修饰键依次按下 / 松开的间隔
+    pub combo_stagger_ms: MsRange,
+    /// 拆分滚轮 / Shift+滚轮时每步之间的停顿
+    pub scroll_gap_ms: MsRange,
+    /// 滚轮后的停顿
+    pub scroll_settle_ms: MsRange,
+    /// scroll_humanly 一次连拨的格数
+    pub scroll_burst_ticks: [u32; 2],
+    /// 连拨中相邻两格的间隔 [最快 (中段), 最慢 (起止)]
+    pub scroll_tick_ms: MsRange,
+    /// 两次连拨之间手指回位的停顿
+    pub scroll_regrip_ms: MsRange,
+    /// 滚到头后多拨一格再拨回的概率
+    pub scroll_overshoot_chance: f32,
+    /// 打字速度倍率 (作用于 type_humanly 的 base_wpm)
+    pub typing_wpm_scale: f32,
+    /// 字符间隔的标准差占平均间隔的比例
+    pub typing_variance: f32,
+    /// 字符间隔下限
+    pub typing_min_delay_ms: u64,
+    /// 打字时误按相邻键再退格改正的概率 (每个字符，0 表示从不)
+    pub typo_rate: f32,
+    /// 误按后多久发现并按退格
+    pub typo_notice_ms: MsRange,
+    /// idle_humanly 每次小幅晃动的最大距离 (px)
+    pub idle_drift_px: f32,
+    /// idle_humanly 两次小动作之间静止的时间
+    pub idle_pause_ms: MsRange,
+    /// idle_humanly 每次动作改为滚一格滚轮的概率
+    pub idle_scroll_chance: f32,
+    /// idle_humanly 每次动作改为把光标挪到屏幕边缘歇着的概率
+    pub idle_edge_chance: f32,
+    /// 长时间运行的疲劳与定时休息 (默认关闭)
+    pub fatigue: FatigueConfig,
+}
+
+impl Default for HumanProfile {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            target_jitter_px: 2.0,
+            motion: MotionConfig::default(),
+            click_bias: [0.04, 0.06],
+            click_spread: 0.18,
+            reaction_ms: [0, 0],
+            recognition: ReactionTime::default(),
+            click_hold_ms: [30, 75],
+            double_click_gap_ms: [150, 170],
+            long_press_jitter: 0.1,
+            drag_press_ms: [40, 80],
+            drag_release_ms: [30, 60],
+            drag_sag_px: 6.0,
+            path_corner_px: 24.0,
+            key_tap_ms: [35, 70],
+            key_min_hold_ms: [20, 45],
+            chord_hold_ms: [35, 70],
+            combo_lead_ms: [40, 120],
+            combo_stagger_ms: [10, 35],
+            scroll_gap_ms: [15, 30],
+            scroll_settle_ms: [100, 100],
+            scroll_burst_ticks: [3, 6],
+            scroll_tick_ms: [12, 45],
+            scroll_regrip_ms: [120, 260],
+            scroll_overshoot_chance: 0.15,
+            typing_wpm_scale: 1.0,
+            typing_variance: 0.3,
+            typing_min_delay_ms: 10,
+            typo_rate: 0.0,
+            typo_notice_ms: [180, 450],
+            idle_drift_px: 6.0,
+            idle_pause_ms: [600, 2500],
+            idle_scroll_chance: 0.05,
+            idle_edge_chance: 0.08,
+            fatigue: FatigueConfig::default(),
+        }
+    }
+}
+
+impl HumanProfile {
+    /// 内置档案: normal (默认) / careful (慢而准) / fast (快而直) / sloppy (手抖、常冲过目标)
+    pub fn preset(name: &str) -> Option<Self> {
+        let normal = Self::default();
+        Some(match name {
+            "normal" => normal,
+            "careful" => Self {
+                speed: 1.4,
+                target_jitter_px: 1.0,
+                click_spread: 0.12,
+                drag_sag_px: 3.0,
+                path_corner_px: 12.0,
+                reaction_ms: [120, 300],
+                recognition: ReactionTime { mu_ms: 260.0, sigma_ms: 30.0, tau_ms: 60.0, min_ms: 220, max_ms: 450 },
+                click_hold_ms: [60, 110],
+                double_click_gap_ms: [160, 200],
+                long_press_jitter: 0.05,
+                key_tap_ms: [50, 90],
+                scroll_settle_ms: [150, 250],
+                scroll_burst_ticks: [2, 4],
+                scroll_overshoot_chance: 0.05,
+                typing_wpm_scale: 0.7,
+                typing_variance: 0.2,
+                typo_rate: 0.005,
+                typo_notice_ms: [120, 250],
+                idle_drift_px: 3.0,
+                idle_pause_ms: [1000, 4000],
+                ..normal
+            },
+            "fast" => Self {
+                speed: 0.7,
+                target_jitter_px: 3.0,
+                motion: MotionConfig {
+                    path: PathModel::WindMouse { gravity: 14.0, wind: 2.0, max_step: 25.0, target_area: 10.0 },
+                    overshoot_chance: 0.15,
+                    ..MotionConfig::default()
+                },
+                reaction_ms: [0, 60],
+                recognition: ReactionTime { mu_ms: 190.0, sigma_ms: 20.0, tau_ms: 30.0, min_ms: 150, max_ms: 280 },
+                click_hold_ms: [20, 50],
+                double_click_gap_ms: [90, 120],
+                drag_press_ms: [25, 50],
+                drag_release_ms: [20, 40],
+                key_tap_ms: [25, 50],
+                scroll_gap_ms: [10, 20],
+                scroll_settle_ms: [50, 80],
+                scroll_burst_ticks: [4, 8],
+                scroll_tick_ms: [8, 30],
+                scroll_regrip_ms: [80, 160],
+                typing_wpm_scale: 1.4,
+                typo_rate: 0.02,
+                ..normal
+            },
+            "sloppy" => Self {
+                speed: 0.9,
+                target_jitter_px: 6.0,
+                motion: MotionConfig {
+                    path: PathModel::WindMouse { gravity: 7.0, wind: 6.0, max_step: 18.0, target_area: 16.0 },
+                    overshoot_chance: 0.5,
+                    overshoot_min_distance: 150.0,
+                    overshoot_ratio: 0.1,
+                    settle_ms: 90,
+                },
+                click_bias: [0.1, 0.12],
+                click_spread: 0.28,
+                drag_sag_px: 12.0,
+                path_corner_px: 40.0,
+                reaction_ms: [40, 200],
+                recognition: ReactionTime { tau_ms: 90.0, max_ms: 500, ..ReactionTime::default() },
+                click_hold_ms: [25, 120],
+                double_click_gap_ms: [110, 230],
+                long_press_jitter: 0.2,
+                key_tap_ms: [30, 100],
+                typing_variance: 0.5,
+                scroll_overshoot_chance: 0.35,
+                typo_rate: 0.04,
+                typo_notice_ms: [250, 700],
+                idle_drift_px: 12.0,
+                idle_pause_ms: [300, 1500],
+                idle_scroll_chance: 0.1,
+                ..normal
+            },
+            _ => return None,
+        })
+    }
+
+    /// 按名称选择档案：profile_file 中的同名档案优先，其次是内置档案
+    pub fn from_config(cfg: &HumanConfig, name: Option<&str>) -> Result<Self, String> {
+        let name = name.unwrap_or(&cfg.profile);
+        if Path::new(&cfg.profile_file).exists() {
+            if let Some(profile) = Self::load(&cfg.profile_file)?.remove(name) {
+                return Ok(profile);
+            }
+        }
+        Self::preset(name).ok_or_else(|| format!("未知的拟人档案: {} (内置 normal / careful / fast / sloppy)", name))
+    }
+
+    /// 读取档案文件，每个 [档案名] 表为一个档案
+    pub fn load(path: &str) -> Result<HashMap<String, Self>, String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("无法读取拟人档案 {}: {}", path, e))?;
+        toml::from_str(&content).map_err(|e| format!("拟人档案 {} 解析失败: {}", path, e))
+    }
+}
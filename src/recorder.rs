@@ -0,0 +1,434 @@
+// src/recorder.rs
+use crate::hardware::{DriverError, MouseButtons};
+use crate::human::HumanDriver;
+use crate::keymap::Modifier;
+use crate::trace::{TraceCommand, TraceEntry};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use windows::Win32::Foundation::{HINSTANCE, LPARAM, LRESULT, WPARAM};
+use windows::Win32::UI::WindowsAndMessaging::{
+    CallNextHookEx, DispatchMessageW, PeekMessageW, SetWindowsHookExW, TranslateMessage,
+    UnhookWindowsHookEx, HHOOK, KBDLLHOOKSTRUCT, MSG, MSLLHOOKSTRUCT, PM_REMOVE, WH_KEYBOARD_LL,
+    WH_MOUSE_LL, WM_KEYDOWN, WM_KEYUP, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MOUSEMOVE, WM_MOUSEWHEEL,
+    WM_RBUTTONDOWN, WM_RBUTTONUP, WM_SYSKEYDOWN, WM_SYSKEYUP,
+};
+
+// ==========================================
+// 1. 宏脚本协议
+// ==========================================
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type")]
+pub enum MacroAction {
+    Move {
+        x: u16,
+        y: u16,
+    },
+    Click {
+        #[serde(default)]
+        left: bool,
+        #[serde(default)]
+        right: bool,
+        #[serde(default)]
+        hold_ms: u64,
+    },
+    /// 可打印字符键 (a-z / 0-9 / 空格)
+    Key {
+        char: char,
+        #[serde(default)]
+        hold_ms: u64,
+    },
+    /// 其它按键，直接使用 HID 键码 (如 ESC = 0x29)
+    KeyCode {
+        code: u8,
+        #[serde(default)]
+        hold_ms: u64,
+    },
+    Scroll {
+        delta: i32,
+    },
+    Wait {
+        ms: u64,
+    },
+}
+
+// ==========================================
+// 2. 低级钩子采集
+// ==========================================
+/// 钩子采集到的原始事件 (屏幕坐标，t 为距录制开始的毫秒数)
+#[derive(Debug, Clone, Copy)]
+pub enum RawEvent {
+    /// 仅在 capture(.., moves = true) 时采集
+    MouseMove { x: i32, y: i32, t: u64 },
+    MouseDown { right: bool, x: i32, y: i32, t: u64 },
+    MouseUp { right: bool, t: u64 },
+    Wheel { delta: i32, t: u64 },
+    KeyDown { vk: u32, t: u64 },
+    KeyUp { vk: u32, t: u64 },
+}
+
+const LLMHF_INJECTED: u32 = 0x01;
+const LLKHF_INJECTED: u32 = 0x10;
+const VK_F12: u32 = 0x7B;
+
+// 钩子回调没有上下文参数，只能通过全局状态传递数据
+static EVENTS: Mutex<Vec<RawEvent>> = Mutex::new(Vec::new());
+static START: Mutex<Option<Instant>> = Mutex::new(None);
+static STOP: AtomicBool = AtomicBool::new(false);
+static CAPTURE_MOVES: AtomicBool = AtomicBool::new(false);
+
+fn now_ms() -> u64 {
+    START
+        .lock()
+        .ok()
+        .and_then(|s| s.map(|t| t.elapsed().as_millis() as u64))
+        .unwrap_or(0)
+}
+
+fn push_event(e: RawEvent) {
+    if let Ok(mut events) = EVENTS.lock() {
+        events.push(e);
+    }
+}
+
+unsafe extern "system" fn mouse_proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code >= 0 {
+        let info = &*(lparam.0 as *const MSLLHOOKSTRUCT);
+        // 忽略程序自身注入的输入 (例如软件驱动回放)
+        if info.flags & LLMHF_INJECTED == 0 {
+            let t = now_ms();
+            let (x, y) = (info.pt.x, info.pt.y);
+            match wparam.0 as u32 {
+                WM_MOUSEMOVE if CAPTURE_MOVES.load(Ordering::Relaxed) => push_event(RawEvent::MouseMove { x, y, t }),
+                WM_LBUTTONDOWN => push_event(RawEvent::MouseDown { right: false, x, y, t }),
+                WM_RBUTTONDOWN => push_event(RawEvent::MouseDown { right: true, x, y, t }),
+                WM_LBUTTONUP => push_event(RawEvent::MouseUp { right: false, t }),
+                WM_RBUTTONUP => push_event(RawEvent::MouseUp { right: true, t }),
+                WM_MOUSEWHEEL => {
+                    let delta = (info.mouseData >> 16) as u16 as i16 as i32;
+                    push_event(RawEvent::Wheel { delta: delta / 120, t });
+                }
+                _ => {}
+            }
+        }
+    }
+    CallNextHookEx(HHOOK::default(), code, wparam, lparam)
+}
+
+unsafe extern "system" fn keyboard_proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code >= 0 {
+        let info = &*(lparam.0 as *const KBDLLHOOKSTRUCT);
+        if info.flags.0 & LLKHF_INJECTED == 0 {
+            let t = now_ms();
+            match wparam.0 as u32 {
+                WM_KEYDOWN | WM_SYSKEYDOWN => {
+                    if info.vkCode == VK_F12 {
+                        STOP.store(true, Ordering::SeqCst);
+                    } else {
+                        push_event(RawEvent::KeyDown { vk: info.vkCode, t });
+                    }
+                }
+                WM_KEYUP | WM_SYSKEYUP if info.vkCode != VK_F12 => {
+                    push_event(RawEvent::KeyUp { vk: info.vkCode, t });
+                }
+                _ => {}
+            }
+        }
+    }
+    CallNextHookEx(HHOOK::default(), code, wparam, lparam)
+}
+
+/// 录制真实键鼠操作，持续 duration 或直到按下 F12
+pub fn record(duration: Duration) -> Result<Vec<MacroAction>, String> {
+    capture(duration, false).map(|events| compile_events(&events))
+}
+
+/// 采集原始事件，持续 duration 或直到按下 F12；moves 为 true 时同时记录每一次光标移动 (数据量大)
+pub fn capture(duration: Duration, moves: bool) -> Result<Vec<RawEvent>, String> {
+    CAPTURE_MOVES.store(moves, Ordering::SeqCst);
+    if let Ok(mut events) = EVENTS.lock() {
+        events.clear();
+    }
+    if let Ok(mut start) = START.lock() {
+        *start = Some(Instant::now());
+    }
+    STOP.store(false, Ordering::SeqCst);
+
+    let mouse_hook = unsafe { SetWindowsHookExW(WH_MOUSE_LL, Some(mouse_proc), HINSTANCE::default(), 0) }
+        .map_err(|e| format!("鼠标钩子安装失败: {}", e))?;
+    let kb_hook = match unsafe { SetWindowsHookExW(WH_KEYBOARD_LL, Some(keyboard_proc), HINSTANCE::default(), 0) } {
+        Ok(h) => h,
+        Err(e) => {
+            unsafe {
+                let _ = UnhookWindowsHookEx(mouse_hook);
+            }
+            return Err(format!("键盘钩子安装失败: {}", e));
+        }
+    };
+
+    println!("🔴 [Recorder] 开始录制 ({} 秒，按 F12 提前结束)...", duration.as_secs());
+    let deadline = Instant::now() + duration;
+    // 低级钩子要求安装线程持续泵消息
+    let mut msg = MSG::default();
+    while Instant::now() < deadline && !STOP.load(Ordering::SeqCst) {
+        unsafe {
+            while PeekMessageW(&mut msg, None, 0, 0, PM_REMOVE).as_bool() {
+                TranslateMessage(&msg);
+                DispatchMessageW(&msg);
+            }
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+
+    unsafe {
+        let _ = UnhookWindowsHookEx(mouse_hook);
+        let _ = UnhookWindowsHookEx(kb_hook);
+    }
+
+    let events = EVENTS.lock().map(|e| e.clone()).unwrap_or_default();
+    println!("⏹️ [Recorder] 录制结束，共捕获 {} 个原始事件", events.len());
+    Ok(events)
+}
+
+// ==========================================
+// 3. 原始事件 -> 拟人化动作
+// ==========================================
+
+/// 虚拟键码 -> 宏动作 (可打印字符优先)
+fn vk_to_action(vk: u32, hold_ms: u64) -> Option<MacroAction> {
+    match vk {
+        0x41..=0x5A => Some(MacroAction::Key { char: (b'a' + (vk - 0x41) as u8) as char, hold_ms }),
+        0x30..=0x39 => Some(MacroAction::Key { char: (b'0' + (vk - 0x30) as u8) as char, hold_ms }),
+        0x20 => Some(MacroAction::Key { char: ' ', hold_ms }),
+        0x0D => Some(MacroAction::KeyCode { code: 0x28, hold_ms }), // Enter
+        0x1B => Some(MacroAction::KeyCode { code: 0x29, hold_ms }), // ESC
+        0x08 => Some(MacroAction::KeyCode { code: 0x2A, hold_ms }), // Backspace
+        0x09 => Some(MacroAction::KeyCode { code: 0x2B, hold_ms }), // Tab
+        _ => None,
+    }
+}
+
+pub fn compile_events(events: &[RawEvent]) -> Vec<MacroAction> {
+    let mut actions = Vec::new();
+    let mut last_t = 0u64;
+
+    let push_wait = |actions: &mut Vec<MacroAction>, t: u64, last_t: &mut u64| {
+        let gap = t.saturating_sub(*last_t);
+        // 过短的间隔交给拟人层自身的随机延迟
+        if gap >= 50 {
+            actions.push(MacroAction::Wait { ms: gap });
+        }
+        *last_t = t;
+    };
+
+    for (i, e) in events.iter().enumerate() {
+        match *e {
+            RawEvent::MouseDown { right, x, y, t } => {
+                let up_t = events[i + 1..].iter().find_map(|n| match *n {
+                    RawEvent::MouseUp { right: r, t } if r == right => Some(t),
+                    _ => None,
+                });
+                push_wait(&mut actions, t, &mut last_t);
+                actions.push(MacroAction::Move { x: x.max(0) as u16, y: y.max(0) as u16 });
+                actions.push(MacroAction::Click {
+                    left: !right,
+                    right,
+                    hold_ms: up_t.map(|u| u.saturating_sub(t)).unwrap_or(0),
+                });
+            }
+            RawEvent::Wheel { delta, t } => {
+                push_wait(&mut actions, t, &mut last_t);
+                // 合并连续滚动
+                if let Some(MacroAction::Scroll { delta: d }) = actions.last_mut() {
+                    *d += delta;
+                } else {
+                    actions.push(MacroAction::Scroll { delta });
+                }
+            }
+            RawEvent::KeyDown { vk, t } => {
+                // 按住时系统会重复发送 KeyDown，仅处理首次按下
+                let repeated = events[..i].iter().rev().find_map(|p| match *p {
+                    RawEvent::KeyDown { vk: v, .. } if v == vk => Some(true),
+                    RawEvent::KeyUp { vk: v, .. } if v == vk => Some(false),
+                    _ => None,
+                });
+                if repeated == Some(true) {
+                    continue;
+                }
+                let up_t = events[i + 1..].iter().find_map(|n| match *n {
+                    RawEvent::KeyUp { vk: v, t } if v == vk => Some(t),
+                    _ => None,
+                });
+                if let Some(action) = vk_to_action(vk, up_t.map(|u| u.saturating_sub(t)).unwrap_or(0)) {
+                    push_wait(&mut actions, t, &mut last_t);
+                    actions.push(action);
+                }
+            }
+            RawEvent::MouseMove { .. } | RawEvent::MouseUp { .. } | RawEvent::KeyUp { .. } => {}
+        }
+    }
+    actions
+}
+
+// ==========================================
+// 4. 原始事件 -> 驱动指令轨迹
+// ==========================================
+
+/// 虚拟键码 -> HID 修饰键位 (低级钩子报告区分左右的 VK_LSHIFT 等，不区分的按左侧处理)
+fn vk_to_modifier(vk: u32) -> Option<u8> {
+    let m = match vk {
+        0xA2 | 0x11 => Modifier::Ctrl,
+        0xA0 | 0x10 => Modifier::Shift,
+        0xA4 | 0x12 => Modifier::Alt,
+        0x5B => Modifier::Win,
+        0xA3 => Modifier::RightCtrl,
+        0xA1 => Modifier::RightShift,
+        0xA5 => Modifier::AltGr,
+        0x5C => Modifier::RightWin,
+        _ => return None,
+    };
+    Some(m.bit())
+}
+
+/// 虚拟键码 -> HID 键码 (字母、数字、常用编辑键、F1 ~ F11 与方向键)
+fn vk_to_hid(vk: u32) -> Option<u8> {
+    match vk {
+        0x41..=0x5A => Some(0x04 + (vk - 0x41) as u8),
+        0x31..=0x39 => Some(0x1E + (vk - 0x31) as u8),
+        0x30 => Some(0x27),
+        0x0D => Some(0x28), // Enter
+        0x1B => Some(0x29), // ESC
+        0x08 => Some(0x2A), // Backspace
+        0x09 => Some(0x2B), // Tab
+        0x20 => Some(0x2C), // 空格
+        0x70..=0x7A => Some(0x3A + (vk - 0x70) as u8), // F12 用于结束录制
+        0x27 => Some(0x4F), // →
+        0x25 => Some(0x50), // ←
+        0x28 => Some(0x51), // ↓
+        0x26 => Some(0x52), // ↑
+        _ => None,
+    }
+}
+
+/// 把原始事件转为驱动指令轨迹 (与 --trace 录下的格式相同，可用 replay 按原始节奏回放)：
+/// 按下鼠标前先把光标移到按下位置，键盘每次变化都发送完整的按住状态
+pub fn compile_trace(events: &[RawEvent]) -> Vec<TraceEntry> {
+    let mut entries = vec![TraceEntry {
+        t_ms: 0,
+        cmd: TraceCommand::Start { at: Local::now().format("%Y-%m-%d %H:%M:%S%.3f").to_string() },
+    }];
+    let mut push = |t_ms: u64, cmd: TraceCommand| entries.push(TraceEntry { t_ms, cmd });
+    let abs = |x: i32, y: i32| TraceCommand::MouseAbs { x: x.clamp(0, u16::MAX as i32) as u16, y: y.clamp(0, u16::MAX as i32) as u16 };
+    let mut keys: Vec<u8> = Vec::new();
+    let mut modifier = 0u8;
+
+    for e in events {
+        match *e {
+            RawEvent::MouseMove { x, y, t } => push(t, abs(x, y)),
+            RawEvent::MouseDown { right, x, y, t } => {
+                push(t, abs(x, y));
+                push(t, TraceCommand::MouseDown { buttons: MouseButtons::from_pair(!right, right).bits() });
+            }
+            RawEvent::MouseUp { t, .. } => push(t, TraceCommand::MouseUp),
+            RawEvent::Wheel { delta, t } => {
+                push(t, TraceCommand::MouseMove { dx: 0, dy: 0, wheel: delta.clamp(-(i8::MAX as i32), i8::MAX as i32) as i8 })
+            }
+            RawEvent::KeyDown { vk, t } | RawEvent::KeyUp { vk, t } => {
+                let down = matches!(e, RawEvent::KeyDown { .. });
+                let (held_keys, held_mods) = (keys.clone(), modifier);
+                if let Some(bit) = vk_to_modifier(vk) {
+                    modifier = if down { modifier | bit } else { modifier & !bit };
+                } else if let Some(code) = vk_to_hid(vk) {
+                    // 按住时系统会重复发送 KeyDown，状态不变时不记录
+                    keys.retain(|k| *k != code);
+                    if down {
+                        keys.push(code);
+                    }
+                }
+                if keys == held_keys && modifier == held_mods {
+                    continue;
+                }
+                if keys.is_empty() && modifier == 0 {
+                    push(t, TraceCommand::KeyUp);
+                } else {
+                    push(t, TraceCommand::KeysDown { keycodes: keys.clone(), modifier });
+                }
+            }
+        }
+    }
+    entries
+}
+
+// ==========================================
+// 5. 保存 / 加载 / 回放
+// ==========================================
+pub fn save_script(path: &str, actions: &[MacroAction]) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(actions).map_err(|e| e.to_string())?;
+    fs::write(path, json).map_err(|e| format!("无法写入 {}: {}", path, e))
+}
+
+/// 以 JSONL (每行一条指令) 保存轨迹
+pub fn save_trace(path: &str, entries: &[TraceEntry]) -> Result<(), String> {
+    let mut out = String::new();
+    for entry in entries {
+        out.push_str(&serde_json::to_string(entry).map_err(|e| e.to_string())?);
+        out.push('\n');
+    }
+    fs::write(path, out).map_err(|e| format!("无法写入 {}: {}", path, e))
+}
+
+pub fn load_script(path: &str) -> Result<Vec<MacroAction>, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("无法读取 {}: {}", path, e))?;
+    serde_json::from_str(&content).map_err(|e| format!("{} 解析失败: {}", path, e))
+}
+
+/// 通过拟人化驱动回放宏脚本；输入设备失败时中止回放
+pub fn play_script(driver: &Arc<Mutex<HumanDriver>>, actions: &[MacroAction]) -> Result<(), DriverError> {
+    println!("▶️ [Recorder] 开始回放 ({} 步)", actions.len());
+    for action in actions {
+        match action {
+            MacroAction::Wait { ms } => thread::sleep(Duration::from_millis(*ms)),
+            _ => {
+                if let Ok(mut d) = driver.lock() {
+                    match action {
+                        MacroAction::Move { x, y } => {
+                            // 根据距离估算拟人移动时长
+                            let dist = ((*x as f32 - d.cur_x).powi(2) + (*y as f32 - d.cur_y).powi(2)).sqrt();
+                            let secs = (0.25 + dist / 2500.0).min(0.9);
+                            d.move_to_humanly(*x, *y, secs)?;
+                        }
+                        MacroAction::Click { left, right, hold_ms } => {
+                            let buttons = MouseButtons::from_pair(*left, *right);
+                            if *hold_ms > 0 {
+                                d.long_press_humanly(buttons, *hold_ms)?;
+                            } else {
+                                d.click_humanly(buttons, 0)?;
+                            }
+                        }
+                        MacroAction::Key { char, hold_ms } => {
+                            if *hold_ms > 0 {
+                                d.key_hold(*char, *hold_ms)?;
+                            } else {
+                                d.key_click(*char)?;
+                            }
+                        }
+                        MacroAction::KeyCode { code, hold_ms } => {
+                            d.raw(|dev| dev.key_down(*code, 0))?;
+                            thread::sleep(Duration::from_millis((*hold_ms).max(30)));
+                            d.raw(|dev| dev.key_up())?;
+                        }
+                        MacroAction::Scroll { delta } => d.mouse_scroll(*delta)?,
+                        MacroAction::Wait { .. } => {}
+                    }
+                }
+            }
+        }
+    }
+    println!("✅ [Recorder] 回放完成");
+    Ok(())
+}
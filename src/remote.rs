@@ -0,0 +1,401 @@
+// src/remote.rs
+use crate::hardware::{DriverCaps, DriverError, DriverEvent, DriverEventCallback, DriverFeatures, DriverInfo, InputDriver, MouseButtons};
+use crate::trace::TraceCommand;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::{Duration, Instant};
+
+// ==========================================
+// 1. 线路协议 (TCP 上的 JSON 行)
+// ==========================================
+// 连接建立后代理先发 Hello{nonce}，控制端回 Auth{digest = HMAC(token, nonce)}，
+// 代理回 Welcome{info, caps, proof}，proof 证明代理同样持有口令；之后每条请求对应一条 Reply。
+// 指令体复用轨迹格式 (TraceCommand)。
+//
+// 握手之后每行消息末尾附带 "\t<mac>"：mac = HMAC-SHA256(会话密钥, 方向 + 序号 + 消息体)，
+// 会话密钥由口令与本次连接的 nonce 派生。序号两端各自累加、不在线路上传输，
+// 因此被篡改、伪造、重放、调换顺序或删掉的消息都会导致校验失败并断开连接。
+//
+// 威胁模型：防的是同一网段上能看到并改写 TCP 流量的第三方 (ARP 欺骗、公共 Wi-Fi 等)，
+// 不知道口令就无法通过认证，也无法在会话中注入或改动指令。线路不加密：
+// 指令内容 (包括 type_text 输入的文字) 对窃听者可见，跨越不可信网络时仍应走 VPN / SSH 隧道。
+// 口令强度即安全强度，窃听者可以用截获的握手离线猜测弱口令。
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "op")]
+enum Request {
+    Auth { digest: String },
+    Input(TraceCommand),
+    Ping,
+    Flush,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "op")]
+enum Response {
+    Hello { nonce: String },
+    /// 认证通过，附带代理端驱动信息与能力 (旧代理不带 caps)
+    Welcome {
+        info: String,
+        #[serde(default)]
+        caps: Option<DriverCaps>,
+        proof: String,
+    },
+    Reply {
+        #[serde(default)]
+        error: Option<DriverError>,
+    },
+}
+
+/// 远程代理默认监听端口
+pub const DEFAULT_AGENT_PORT: u16 = 7878;
+const IO_TIMEOUT: Duration = Duration::from_secs(2);
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+/// 代理端超过该时间未收到任何消息 (控制端每秒一次心跳) 即视为控制端失联
+const AGENT_IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 区分各处 HMAC 用途的标签，同一口令算出的值不能互相顶替
+const AUTH_LABEL: &[u8] = b"nzm-auth";
+const PROOF_LABEL: &[u8] = b"nzm-proof";
+const SESSION_LABEL: &[u8] = b"nzm-session";
+/// 消息方向 (参与 MAC 计算，防止把一个方向的消息反射回另一个方向)
+const TO_AGENT: &[u8] = b"c2a";
+const TO_CONTROLLER: &[u8] = b"a2c";
+
+/// HMAC-SHA256 (RFC 2104)，parts 依次拼接为消息
+fn hmac_sha256(key: &[u8], parts: &[&[u8]]) -> [u8; 32] {
+    const BLOCK: usize = 64;
+    let mut block = [0u8; BLOCK];
+    if key.len() > BLOCK {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let mut inner = Sha256::new();
+    inner.update(block.map(|b| b ^ 0x36));
+    for part in parts {
+        inner.update(part);
+    }
+    let mut outer = Sha256::new();
+    outer.update(block.map(|b| b ^ 0x5c));
+    outer.update(inner.finalize());
+    outer.finalize().into()
+}
+
+/// 控制端的认证应答
+fn auth_digest(token: &str, nonce: &str) -> String {
+    hex::encode(hmac_sha256(token.as_bytes(), &[AUTH_LABEL, nonce.as_bytes()]))
+}
+
+/// 代理端的认证证明 (与 auth_digest 用途不同，窃听者不能把一个当作另一个)
+fn agent_proof(token: &str, nonce: &str) -> String {
+    hex::encode(hmac_sha256(token.as_bytes(), &[PROOF_LABEL, nonce.as_bytes()]))
+}
+
+fn session_key(token: &str, nonce: &str) -> [u8; 32] {
+    hmac_sha256(token.as_bytes(), &[SESSION_LABEL, nonce.as_bytes()])
+}
+
+/// 定长比较 (耗时与第一个不同字节的位置无关)，避免按响应时间逐字节猜出摘要
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// 一条 TCP 连接的两端读写；认证通过后 (key 非空) 每条消息都带 MAC
+struct Channel {
+    writer: TcpStream,
+    reader: BufReader<TcpStream>,
+    key: Option<[u8; 32]>,
+    /// 本端发出 / 收到的消息方向标签
+    outgoing: &'static [u8],
+    incoming: &'static [u8],
+    sent: u64,
+    received: u64,
+}
+
+impl Channel {
+    fn new(stream: TcpStream, outgoing: &'static [u8], incoming: &'static [u8]) -> Result<Self, String> {
+        let writer = stream.try_clone().map_err(|e| e.to_string())?;
+        Ok(Self { writer, reader: BufReader::new(stream), key: None, outgoing, incoming, sent: 0, received: 0 })
+    }
+
+    fn mac(key: &[u8; 32], direction: &[u8], seq: u64, body: &str) -> String {
+        hex::encode(hmac_sha256(key, &[direction, &seq.to_be_bytes(), body.as_bytes()]))
+    }
+
+    fn send<T: Serialize>(&mut self, msg: &T) -> Result<(), String> {
+        let mut line = serde_json::to_string(msg).map_err(|e| e.to_string())?;
+        if let Some(key) = &self.key {
+            // serde_json 会转义制表符，消息体里不会出现分隔符
+            let mac = Self::mac(key, self.outgoing, self.sent, &line);
+            self.sent += 1;
+            line.push('\t');
+            line.push_str(&mac);
+        }
+        line.push('\n');
+        self.writer.write_all(line.as_bytes()).map_err(|e| e.to_string())
+    }
+
+    /// 读取一条消息；对端正常关闭连接时返回 None
+    fn recv<T: for<'de> Deserialize<'de>>(&mut self) -> Result<Option<T>, String> {
+        let mut line = String::new();
+        match self.reader.read_line(&mut line) {
+            Ok(0) => return Ok(None),
+            Ok(_) => {}
+            Err(e) => return Err(e.to_string()),
+        }
+        let line = line.trim_end_matches(['\r', '\n']);
+        let body = match &self.key {
+            None => line,
+            Some(key) => {
+                let Some((body, mac)) = line.rsplit_once('\t') else {
+                    return Err("消息缺少校验码".to_string());
+                };
+                if !constant_time_eq(mac.as_bytes(), Self::mac(key, self.incoming, self.received, body).as_bytes()) {
+                    return Err("消息校验失败 (被篡改、重放或口令不一致)".to_string());
+                }
+                self.received += 1;
+                body
+            }
+        };
+        serde_json::from_str(body).map(Some).map_err(|e| format!("无法解析消息: {}", e))
+    }
+
+    /// 控制端调用时连接必须保持，关闭视为错误
+    fn expect<T: for<'de> Deserialize<'de>>(&mut self) -> Result<T, String> {
+        self.recv()?.ok_or_else(|| "连接已关闭".to_string())
+    }
+}
+
+// ==========================================
+// 2. 控制端驱动
+// ==========================================
+
+/// 把 InputDriver 调用经 TCP 转发给另一台机器上的 nzm_agent
+/// 地址写法: host:port 或 host:port@TOKEN
+pub struct RemoteDriver {
+    addr: String,
+    token: String,
+    conn: Option<Channel>,
+    remote_info: String,
+    remote_caps: DriverCaps,
+    last_attempt: Instant,
+    on_event: Option<DriverEventCallback>,
+}
+
+impl RemoteDriver {
+    pub fn new(spec: &str) -> Result<Self, String> {
+        let (addr, token) = match spec.split_once('@') {
+            Some((a, t)) => (a.to_string(), t.to_string()),
+            None => (spec.to_string(), String::new()),
+        };
+        let (conn, remote_info, remote_caps) = Self::connect(&addr, &token)?;
+        println!("🌐 [Remote] 已连接代理 {} -> {}", addr, remote_info);
+        Ok(Self { addr, token, conn: Some(conn), remote_info, remote_caps, last_attempt: Instant::now(), on_event: None })
+    }
+
+    fn connect(addr: &str, token: &str) -> Result<(Channel, String, DriverCaps), String> {
+        let stream = TcpStream::connect(addr).map_err(|e| format!("无法连接远程代理 {}: {}", addr, e))?;
+        stream.set_nodelay(true).map_err(|e| e.to_string())?;
+        stream.set_read_timeout(Some(IO_TIMEOUT)).map_err(|e| e.to_string())?;
+        stream.set_write_timeout(Some(IO_TIMEOUT)).map_err(|e| e.to_string())?;
+        let mut channel = Channel::new(stream, TO_AGENT, TO_CONTROLLER)?;
+
+        let Response::Hello { nonce } = channel.expect()? else {
+            return Err("远程代理握手失败: 未收到 Hello".to_string());
+        };
+        channel.send(&Request::Auth { digest: auth_digest(token, &nonce) })?;
+        match channel.expect()? {
+            // 对方不知道口令就算不出 proof：拒绝连接到冒充的代理
+            Response::Welcome { proof, .. } if !constant_time_eq(proof.as_bytes(), agent_proof(token, &nonce).as_bytes()) => {
+                Err("远程代理握手失败: 代理口令校验失败".to_string())
+            }
+            Response::Welcome { info, caps, .. } => {
+                channel.key = Some(session_key(token, &nonce));
+                Ok((channel, info, caps.unwrap_or_default()))
+            }
+            Response::Reply { error: Some(e) } => Err(format!("远程代理拒绝连接: {}", e)),
+            _ => Err("远程代理握手失败: 应答异常".to_string()),
+        }
+    }
+
+    fn emit(&self, event: DriverEvent) {
+        if let Some(cb) = &self.on_event {
+            cb(&event);
+        }
+    }
+
+    /// 断线后每隔 RECONNECT_INTERVAL 尝试重连一次
+    fn ensure_connected(&mut self) -> Result<(), DriverError> {
+        if self.conn.is_some() {
+            return Ok(());
+        }
+        if self.last_attempt.elapsed() < RECONNECT_INTERVAL {
+            return Err(DriverError::Disconnected(self.addr.clone()));
+        }
+        self.last_attempt = Instant::now();
+        match Self::connect(&self.addr, &self.token) {
+            Ok((conn, info, caps)) => {
+                println!("🌐 [Remote] 已重新连接代理 {}", self.addr);
+                self.conn = Some(conn);
+                self.remote_info = info;
+                self.remote_caps = caps;
+                self.emit(DriverEvent::Reconnected);
+                Ok(())
+            }
+            Err(_) => Err(DriverError::Disconnected(self.addr.clone())),
+        }
+    }
+
+    fn call(&mut self, req: Request) -> Result<(), DriverError> {
+        self.ensure_connected()?;
+        let Some(conn) = self.conn.as_mut() else {
+            return Err(DriverError::Disconnected(self.addr.clone()));
+        };
+        let result = conn.send(&req).and_then(|_| conn.expect::<Response>());
+        match result {
+            Ok(Response::Reply { error: None }) => Ok(()),
+            Ok(Response::Reply { error: Some(e) }) => Err(e),
+            Ok(_) => Err(DriverError::Io("远程代理应答异常".to_string())),
+            Err(reason) => {
+                // 连接不可用：丢弃并通知上层，后续调用自动重连
+                self.conn = None;
+                self.last_attempt = Instant::now();
+                println!("🔌 [Remote] 与代理 {} 的连接已断开: {}", self.addr, reason);
+                self.emit(DriverEvent::Disconnected(reason.clone()));
+                Err(DriverError::Disconnected(reason))
+            }
+        }
+    }
+
+    fn input(&mut self, cmd: TraceCommand) -> Result<(), DriverError> {
+        self.call(Request::Input(cmd))
+    }
+}
+
+impl InputDriver for RemoteDriver {
+    fn info(&self) -> DriverInfo {
+        DriverInfo::named(
+            &format!("远程代理 {} -> {}", self.addr, self.remote_info),
+            DriverFeatures::KEYBOARD_REPORT | DriverFeatures::HWHEEL | DriverFeatures::IDENTITY,
+        )
+    }
+
+    /// 远端的系统光标在另一台机器上，本机 GetCursorPos 读不到
+    fn capabilities(&self) -> DriverCaps {
+        DriverCaps { os_cursor: false, ..self.remote_caps }
+    }
+
+    fn is_online(&self) -> bool {
+        self.conn.is_some()
+    }
+
+    fn set_event_callback(&mut self, callback: DriverEventCallback) {
+        self.on_event = Some(callback);
+    }
+
+    fn heartbeat(&mut self) -> Result<(), DriverError> { self.call(Request::Ping) }
+    fn flush(&mut self) -> Result<(), DriverError> { self.call(Request::Flush) }
+    fn mouse_abs(&mut self, x: u16, y: u16) -> Result<(), DriverError> { self.input(TraceCommand::MouseAbs { x, y }) }
+    fn mouse_move(&mut self, dx: i32, dy: i32, wheel: i8) -> Result<(), DriverError> {
+        self.input(TraceCommand::MouseMove { dx, dy, wheel })
+    }
+    fn mouse_down(&mut self, buttons: MouseButtons) -> Result<(), DriverError> {
+        self.input(TraceCommand::MouseDown { buttons: buttons.bits() })
+    }
+    fn mouse_up(&mut self) -> Result<(), DriverError> { self.input(TraceCommand::MouseUp) }
+    fn key_down(&mut self, keycode: u8, modifier: u8) -> Result<(), DriverError> {
+        self.input(TraceCommand::KeyDown { keycode, modifier })
+    }
+    fn key_up(&mut self) -> Result<(), DriverError> { self.input(TraceCommand::KeyUp) }
+    fn keys_down(&mut self, keycodes: &[u8], modifier: u8) -> Result<(), DriverError> {
+        self.input(TraceCommand::KeysDown { keycodes: keycodes.to_vec(), modifier })
+    }
+    fn switch_identity(&mut self, index: u8) -> Result<(), DriverError> {
+        self.input(TraceCommand::SwitchIdentity { index })
+    }
+    fn mouse_hscroll(&mut self, delta: i8) -> Result<(), DriverError> { self.input(TraceCommand::MouseHScroll { delta }) }
+    fn type_text(&mut self, text: &str) -> Result<(), DriverError> {
+        self.input(TraceCommand::TypeText { text: text.to_string() })
+    }
+    fn send_custom(&mut self, event_type: u8, payload: [u8; 6], delay: u16) -> Result<(), DriverError> {
+        self.input(TraceCommand::Custom { event_type, payload, delay })
+    }
+}
+
+// ==========================================
+// 3. 代理端 (nzm_agent)
+// ==========================================
+
+/// 监听 listen 地址，依次服务每个控制端连接 (同一时间只接受一个)
+pub fn serve(listen: &str, token: &str, driver: &mut dyn InputDriver) -> Result<(), String> {
+    // 没有口令时任何能访问该端口的机器都可以控制本机键鼠，直接拒绝启动
+    if token.is_empty() {
+        return Err("未设置连接口令 (--token)，拒绝启动".to_string());
+    }
+    let listener = TcpListener::bind(listen).map_err(|e| format!("无法监听 {}: {}", listen, e))?;
+    println!("🌐 [Agent] 正在监听 {} (驱动: {})", listen, driver.info());
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(s) => s,
+            Err(e) => {
+                println!("⚠️ [Agent] 接受连接失败: {}", e);
+                continue;
+            }
+        };
+        let peer = stream.peer_addr().map(|a| a.to_string()).unwrap_or_default();
+        println!("🔗 [Agent] 控制端已连接: {}", peer);
+        match handle_client(stream, token, driver) {
+            Ok(()) => println!("👋 [Agent] 控制端 {} 已断开", peer),
+            Err(e) => println!("⚠️ [Agent] 控制端 {} 会话结束: {}", peer, e),
+        }
+        // 控制端中途断开时不能让按键保持按下
+        let _ = driver.key_up();
+        let _ = driver.mouse_up();
+    }
+    Ok(())
+}
+
+fn handle_client(stream: TcpStream, token: &str, driver: &mut dyn InputDriver) -> Result<(), String> {
+    stream.set_nodelay(true).map_err(|e| e.to_string())?;
+    stream.set_read_timeout(Some(AGENT_IDLE_TIMEOUT)).map_err(|e| e.to_string())?;
+    let mut channel = Channel::new(stream, TO_CONTROLLER, TO_AGENT)?;
+
+    // nonce 每个连接都不同，会话密钥随之不同，上一次会话截获的消息无法重放
+    let mut nonce = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let nonce = hex::encode(nonce);
+    channel.send(&Response::Hello { nonce: nonce.clone() })?;
+
+    match channel.expect()? {
+        Request::Auth { digest } if constant_time_eq(digest.as_bytes(), auth_digest(token, &nonce).as_bytes()) => {
+            let welcome = Response::Welcome {
+                info: driver.info().to_string(),
+                caps: Some(driver.capabilities()),
+                proof: agent_proof(token, &nonce),
+            };
+            channel.send(&welcome)?;
+            channel.key = Some(session_key(token, &nonce));
+        }
+        _ => {
+            let error = Some(DriverError::Io("口令错误".to_string()));
+            let _ = channel.send(&Response::Reply { error });
+            return Err("口令校验失败".to_string());
+        }
+    }
+
+    loop {
+        let result = match channel.recv::<Request>()? {
+            Some(Request::Input(cmd)) => cmd.apply(driver),
+            Some(Request::Ping) => driver.heartbeat(),
+            Some(Request::Flush) => driver.flush(),
+            Some(Request::Auth { .. }) => Ok(()),
+            None => return Ok(()),
+        };
+        channel.send(&Response::Reply { error: result.err() })?;
+    }
+}
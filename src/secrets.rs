@@ -0,0 +1,136 @@
+// src/secrets.rs
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use windows::Win32::Foundation::{LocalFree, HLOCAL};
+use windows::Win32::Security::Cryptography::{
+    CryptProtectData, CryptUnprotectData, CRYPTPROTECT_UI_FORBIDDEN, CRYPT_INTEGER_BLOB,
+};
+
+pub const DEFAULT_SECRETS_FILE: &str = "secrets.json";
+
+// ==========================================
+// 1. 数据结构
+// ==========================================
+
+/// 解密后的账号凭据 (仅存在于内存)
+#[derive(Clone)]
+pub struct Credential {
+    pub username: String,
+    pub password: String,
+}
+
+// 避免密码被日志意外打印
+impl std::fmt::Debug for Credential {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Credential")
+            .field("username", &self.username)
+            .field("password", &"******")
+            .finish()
+    }
+}
+
+/// 落盘格式：密码经 DPAPI 加密后以 hex 保存，只有当前 Windows 用户能解密
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct StoredAccount {
+    username: String,
+    password_enc: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct SecretsFile {
+    #[serde(default)]
+    accounts: BTreeMap<String, StoredAccount>,
+}
+
+// ==========================================
+// 2. DPAPI 封装
+// ==========================================
+fn dpapi_protect(plain: &[u8]) -> Result<Vec<u8>, String> {
+    let input = CRYPT_INTEGER_BLOB { cbData: plain.len() as u32, pbData: plain.as_ptr() as *mut u8 };
+    let mut output = CRYPT_INTEGER_BLOB::default();
+    unsafe {
+        CryptProtectData(&input, None, None, None, None, CRYPTPROTECT_UI_FORBIDDEN, &mut output)
+            .map_err(|e| format!("DPAPI 加密失败: {}", e))?;
+        let data = std::slice::from_raw_parts(output.pbData, output.cbData as usize).to_vec();
+        let _ = LocalFree(HLOCAL(output.pbData as _));
+        Ok(data)
+    }
+}
+
+fn dpapi_unprotect(cipher: &[u8]) -> Result<Vec<u8>, String> {
+    let input = CRYPT_INTEGER_BLOB { cbData: cipher.len() as u32, pbData: cipher.as_ptr() as *mut u8 };
+    let mut output = CRYPT_INTEGER_BLOB::default();
+    unsafe {
+        CryptUnprotectData(&input, None, None, None, None, CRYPTPROTECT_UI_FORBIDDEN, &mut output)
+            .map_err(|e| format!("DPAPI 解密失败 (是否换了 Windows 用户?): {}", e))?;
+        let data = std::slice::from_raw_parts(output.pbData, output.cbData as usize).to_vec();
+        let _ = LocalFree(HLOCAL(output.pbData as _));
+        Ok(data)
+    }
+}
+
+// ==========================================
+// 3. 凭据库
+// ==========================================
+pub struct SecretStore {
+    path: String,
+    data: SecretsFile,
+}
+
+impl SecretStore {
+    pub fn open(path: &str) -> Result<Self, String> {
+        let data = if Path::new(path).exists() {
+            let content = fs::read_to_string(path).map_err(|e| format!("无法读取 {}: {}", path, e))?;
+            serde_json::from_str(&content).map_err(|e| format!("{} 解析失败: {}", path, e))?
+        } else {
+            SecretsFile::default()
+        };
+        Ok(Self { path: path.to_string(), data })
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(&self.data).map_err(|e| e.to_string())?;
+        fs::write(&self.path, json).map_err(|e| format!("无法写入 {}: {}", self.path, e))
+    }
+
+    pub fn add(&mut self, name: &str, username: &str, password: &str) -> Result<(), String> {
+        let enc = dpapi_protect(password.as_bytes())?;
+        self.data.accounts.insert(
+            name.to_string(),
+            StoredAccount { username: username.to_string(), password_enc: hex::encode(enc) },
+        );
+        self.save()
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<bool, String> {
+        let existed = self.data.accounts.remove(name).is_some();
+        if existed {
+            self.save()?;
+        }
+        Ok(existed)
+    }
+
+    /// 账号名与用户名列表 (不解密密码)
+    pub fn list(&self) -> Vec<(String, String)> {
+        self.data
+            .accounts
+            .iter()
+            .map(|(k, v)| (k.clone(), v.username.clone()))
+            .collect()
+    }
+
+    pub fn get(&self, name: &str) -> Result<Credential, String> {
+        let stored = self
+            .data
+            .accounts
+            .get(name)
+            .ok_or_else(|| format!("未找到账号: {}", name))?;
+        let cipher = hex::decode(&stored.password_enc).map_err(|e| format!("账号 {} 数据损坏: {}", name, e))?;
+        let plain = dpapi_unprotect(&cipher)?;
+        let password = String::from_utf8(plain).map_err(|_| format!("账号 {} 密码解码失败", name))?;
+        Ok(Credential { username: stored.username.clone(), password })
+    }
+}
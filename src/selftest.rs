@@ -0,0 +1,223 @@
+// src/selftest.rs
+use crate::calibration::cursor_pos;
+use crate::hardware::{DriverError, InputDriver, MouseButtons};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// ==========================================
+// 1. 步骤结果
+// ==========================================
+
+pub enum StepOutcome {
+    Pass,
+    /// 指令发送成功，但读回的光标位置与预期不符等
+    Warn(String),
+    Fail(DriverError),
+    /// 驱动声明不支持该能力
+    Skip(&'static str),
+}
+
+pub struct StepResult {
+    pub name: String,
+    pub outcome: StepOutcome,
+    pub elapsed: Duration,
+}
+
+impl std::fmt::Display for StepResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ms = self.elapsed.as_secs_f64() * 1000.0;
+        match &self.outcome {
+            StepOutcome::Pass => write!(f, "✅ {:<24} {:>7.1}ms", self.name, ms),
+            StepOutcome::Warn(msg) => write!(f, "⚠️ {:<24} {:>7.1}ms  {}", self.name, ms, msg),
+            StepOutcome::Fail(e) => write!(f, "❌ {:<24} {:>7.1}ms  {}", self.name, ms, e),
+            StepOutcome::Skip(why) => write!(f, "⏭️ {:<24} {:>9}  {}", self.name, "-", why),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct SelfTestReport {
+    pub steps: Vec<StepResult>,
+}
+
+impl SelfTestReport {
+    fn count(&self, f: impl Fn(&StepOutcome) -> bool) -> usize {
+        self.steps.iter().filter(|s| f(&s.outcome)).count()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.count(|o| matches!(o, StepOutcome::Fail(_)))
+    }
+}
+
+impl std::fmt::Display for SelfTestReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for s in &self.steps {
+            writeln!(f, "  {}", s)?;
+        }
+        write!(
+            f,
+            "通过 {} / 警告 {} / 失败 {} / 跳过 {}",
+            self.count(|o| matches!(o, StepOutcome::Pass)),
+            self.count(|o| matches!(o, StepOutcome::Warn(_))),
+            self.failed(),
+            self.count(|o| matches!(o, StepOutcome::Skip(_)))
+        )
+    }
+}
+
+// ==========================================
+// 2. 测试脚本
+// ==========================================
+
+/// 两步之间的间隔，便于肉眼观察
+const STEP_GAP: Duration = Duration::from_millis(250);
+/// 读回光标位置前等待系统处理输入
+const SETTLE: Duration = Duration::from_millis(150);
+/// 光标位置的允许偏差 (px)
+const TOLERANCE: f32 = 8.0;
+/// 角落测试点离屏幕边缘的距离
+const INSET: u16 = 50;
+
+/// 各类按键各取一个: (名称, HID 键码, 修饰键)
+const KEYS: [(&str, u8, u8); 7] = [
+    ("字母 a", 0x04, 0),
+    ("数字 1", 0x1E, 0),
+    ("功能键 F2", 0x3B, 0),
+    ("方向键 →", 0x4F, 0),
+    ("编辑键 Backspace", 0x2A, 0),
+    ("小键盘 1", 0x59, 0),
+    ("修饰键 Shift+a", 0x04, 0x02),
+];
+
+/// 鼠标键: (名称, 按键)
+const BUTTONS: [(&str, MouseButtons); 5] = [
+    ("左键", MouseButtons::LEFT),
+    ("右键", MouseButtons::RIGHT),
+    ("中键", MouseButtons::MIDDLE),
+    ("侧键 X1", MouseButtons::X1),
+    ("侧键 X2", MouseButtons::X2),
+];
+
+struct Runner<'a> {
+    driver: &'a mut dyn InputDriver,
+    report: SelfTestReport,
+}
+
+impl Runner<'_> {
+    fn step(&mut self, name: impl Into<String>, f: impl FnOnce(&mut dyn InputDriver) -> Result<StepOutcome, DriverError>) {
+        let start = Instant::now();
+        let outcome = f(self.driver).and_then(|o| self.driver.flush().map(|_| o)).unwrap_or_else(StepOutcome::Fail);
+        let result = StepResult { name: name.into(), outcome, elapsed: start.elapsed() };
+        println!("  {}", result);
+        self.report.steps.push(result);
+        thread::sleep(STEP_GAP);
+    }
+
+    fn skip(&mut self, name: impl Into<String>, why: &'static str) {
+        let result = StepResult { name: name.into(), outcome: StepOutcome::Skip(why), elapsed: Duration::ZERO };
+        println!("  {}", result);
+        self.report.steps.push(result);
+    }
+}
+
+/// 读回系统光标并与预期位置比较；驱动不移动系统光标 (试运行 / 窗口消息) 时给出警告而非失败
+fn check_cursor(driver: &mut dyn InputDriver, expect: (f32, f32)) -> Result<StepOutcome, DriverError> {
+    driver.flush()?;
+    thread::sleep(SETTLE);
+    Ok(match cursor_pos() {
+        Ok((x, y)) if (x - expect.0).abs() <= TOLERANCE && (y - expect.1).abs() <= TOLERANCE => StepOutcome::Pass,
+        Ok((x, y)) => StepOutcome::Warn(format!("光标在 ({:.0}, {:.0})，预期 ({:.0}, {:.0})", x, y, expect.0, expect.1)),
+        Err(e) => StepOutcome::Warn(e),
+    })
+}
+
+/// 依次执行所有事件类型并逐步报告结果
+/// 会真实点击与按键，请先把焦点切到记事本等无害窗口
+pub fn run(driver: &mut dyn InputDriver, screen_w: u16, screen_h: u16) -> SelfTestReport {
+    let caps = driver.capabilities();
+    println!("🔧 驱动: {}", driver.info());
+    println!("🔧 驱动能力: {:?}", caps);
+    let mut r = Runner { driver, report: SelfTestReport::default() };
+    let center = (screen_w / 2, screen_h / 2);
+
+    r.step("心跳", |d| d.heartbeat().map(|_| StepOutcome::Pass));
+
+    // 绝对移动：四个角
+    let corners = [
+        ("左上角", INSET, INSET),
+        ("右上角", screen_w.saturating_sub(INSET), INSET),
+        ("右下角", screen_w.saturating_sub(INSET), screen_h.saturating_sub(INSET)),
+        ("左下角", INSET, screen_h.saturating_sub(INSET)),
+    ];
+    for (name, x, y) in corners {
+        if caps.absolute_mouse {
+            r.step(format!("绝对移动 {}", name), |d| {
+                d.mouse_abs(x, y)?;
+                check_cursor(d, (x as f32, y as f32))
+            });
+        } else {
+            r.skip(format!("绝对移动 {}", name), "驱动不支持绝对坐标");
+        }
+    }
+
+    // 相对移动：从中心画一个正方形，最后应回到起点
+    let start = if caps.absolute_mouse {
+        r.step("回到屏幕中心", |d| {
+            d.mouse_abs(center.0, center.1)?;
+            check_cursor(d, (center.0 as f32, center.1 as f32))
+        });
+        (center.0 as f32, center.1 as f32)
+    } else {
+        cursor_pos().unwrap_or((center.0 as f32, center.1 as f32))
+    };
+    let side = 200;
+    for (name, dx, dy) in [("右", side, 0), ("下", 0, side), ("左", -side, 0), ("上", 0, -side)] {
+        r.step(format!("相对移动 {} {}px", name, side), |d| d.mouse_move(dx, dy, 0).map(|_| StepOutcome::Pass));
+    }
+    r.step("相对移动 回到起点", |d| check_cursor(d, start));
+
+    for (name, button) in BUTTONS {
+        r.step(format!("鼠标 {}", name), |d| {
+            // 按下失败也要松开，避免后续步骤在按键卡住的状态下进行
+            let pressed = d.mouse_down(button);
+            thread::sleep(Duration::from_millis(50));
+            pressed.and(d.mouse_up()).map(|_| StepOutcome::Pass)
+        });
+    }
+
+    r.step("滚轮 向上", |d| d.mouse_move(0, 0, 1).map(|_| StepOutcome::Pass));
+    r.step("滚轮 向下", |d| d.mouse_move(0, 0, -1).map(|_| StepOutcome::Pass));
+    if caps.hscroll {
+        r.step("水平滚轮 向右", |d| d.mouse_hscroll(1).map(|_| StepOutcome::Pass));
+        r.step("水平滚轮 向左", |d| d.mouse_hscroll(-1).map(|_| StepOutcome::Pass));
+    } else {
+        r.skip("水平滚轮", "驱动不支持水平滚轮");
+    }
+
+    for (name, key, modifier) in KEYS {
+        r.step(format!("按键 {}", name), |d| {
+            let pressed = d.key_down(key, modifier);
+            thread::sleep(Duration::from_millis(40));
+            pressed.and(d.key_up()).map(|_| StepOutcome::Pass)
+        });
+    }
+    if caps.max_keys_down > 1 {
+        r.step("组合键 a+s", |d| {
+            let pressed = d.keys_down(&[0x04, 0x16], 0);
+            thread::sleep(Duration::from_millis(40));
+            pressed.and(d.key_up()).map(|_| StepOutcome::Pass)
+        });
+    } else {
+        r.skip("组合键 a+s", "驱动只支持单键");
+    }
+
+    if caps.identity {
+        r.step("身份切换 (0)", |d| d.switch_identity(0).map(|_| StepOutcome::Pass));
+    } else {
+        r.skip("身份切换", "驱动不支持身份切换");
+    }
+
+    r.step("心跳 (结束)", |d| d.heartbeat().map(|_| StepOutcome::Pass));
+    r.report
+}
@@ -0,0 +1,402 @@
+// src/sendinput.rs
+use crate::hardware::{chord_report, DriverCaps, DriverError, DriverFeatures, DriverInfo, InputDriver, MouseButtons};
+use crate::keymap::{hid_to_scancode, MODIFIER_SCANCODES};
+use crate::window::{self, WindowHandle};
+use std::thread;
+use std::time::Duration;
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    MapVirtualKeyW, SendInput, INPUT, INPUT_0, INPUT_KEYBOARD, INPUT_MOUSE, KEYBDINPUT, KEYEVENTF_EXTENDEDKEY,
+    KEYEVENTF_KEYUP, KEYEVENTF_SCANCODE, KEYEVENTF_UNICODE, MAPVK_VSC_TO_VK_EX, MOUSEEVENTF_ABSOLUTE, MOUSEEVENTF_HWHEEL, MOUSEEVENTF_LEFTDOWN, MOUSEEVENTF_LEFTUP, MOUSEEVENTF_MIDDLEDOWN, MOUSEEVENTF_MIDDLEUP,
+    MOUSEEVENTF_MOVE, MOUSEEVENTF_RIGHTDOWN, MOUSEEVENTF_RIGHTUP, MOUSEEVENTF_WHEEL, MOUSEEVENTF_XDOWN,
+    MOUSEEVENTF_XUP, MOUSEINPUT, MOUSE_EVENT_FLAGS, VIRTUAL_KEY,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetForegroundWindow, PostMessageW, WHEEL_DELTA, WM_CHAR, WM_KEYDOWN, WM_KEYUP, WM_LBUTTONDOWN, WM_LBUTTONUP,
+    WM_MBUTTONDOWN, WM_MBUTTONUP, WM_MOUSEHWHEEL, WM_MOUSEMOVE, WM_MOUSEWHEEL, WM_RBUTTONDOWN, WM_RBUTTONUP,
+    WM_SYSKEYDOWN, WM_SYSKEYUP, WM_XBUTTONDOWN, WM_XBUTTONUP, XBUTTON1, XBUTTON2,
+};
+
+// ==========================================
+// 1. 投递方式
+// ==========================================
+
+/// 输入如何送达目标窗口
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TargetMode {
+    /// 直接向窗口投递 WM_* 消息：游戏可以留在后台，但使用原始输入 (Raw Input) 的程序收不到
+    Post,
+    /// 先把窗口切到前台再用 SendInput 注入：兼容性与软件模拟相同，但会抢占焦点
+    Focus,
+}
+
+/// 按键消息 wParam 中的鼠标键 / 修饰键状态位 (MK_*)
+const MK_LBUTTON: usize = 0x0001;
+const MK_RBUTTON: usize = 0x0002;
+const MK_SHIFT: usize = 0x0004;
+const MK_CONTROL: usize = 0x0008;
+const MK_MBUTTON: usize = 0x0010;
+const MK_XBUTTON1: usize = 0x0020;
+const MK_XBUTTON2: usize = 0x0040;
+/// HID 修饰键字节中的 Alt 位 (左 / 右)
+const MOD_ALT_BITS: u8 = 0x04 | 0x40;
+/// 切换前台窗口后等待系统完成激活的时间
+const FOCUS_SETTLE: Duration = Duration::from_millis(30);
+
+/// 一个鼠标键对应的 (按下消息, 抬起消息, MK 位, XBUTTON 编号, SendInput 按下标志, 抬起标志)
+type ButtonMap = (MouseButtons, u32, u32, usize, u16, MOUSE_EVENT_FLAGS, MOUSE_EVENT_FLAGS);
+
+const BUTTONS: [ButtonMap; 5] = [
+    (MouseButtons::LEFT, WM_LBUTTONDOWN, WM_LBUTTONUP, MK_LBUTTON, 0, MOUSEEVENTF_LEFTDOWN, MOUSEEVENTF_LEFTUP),
+    (MouseButtons::RIGHT, WM_RBUTTONDOWN, WM_RBUTTONUP, MK_RBUTTON, 0, MOUSEEVENTF_RIGHTDOWN, MOUSEEVENTF_RIGHTUP),
+    (MouseButtons::MIDDLE, WM_MBUTTONDOWN, WM_MBUTTONUP, MK_MBUTTON, 0, MOUSEEVENTF_MIDDLEDOWN, MOUSEEVENTF_MIDDLEUP),
+    (MouseButtons::X1, WM_XBUTTONDOWN, WM_XBUTTONUP, MK_XBUTTON1, XBUTTON1, MOUSEEVENTF_XDOWN, MOUSEEVENTF_XUP),
+    (MouseButtons::X2, WM_XBUTTONDOWN, WM_XBUTTONUP, MK_XBUTTON2, XBUTTON2, MOUSEEVENTF_XDOWN, MOUSEEVENTF_XUP),
+];
+
+fn make_lparam(x: i32, y: i32) -> LPARAM {
+    LPARAM((((y as u16 as u32) << 16) | (x as u16 as u32)) as isize)
+}
+
+// ==========================================
+// 2. 驱动
+// ==========================================
+
+/// 面向单个窗口的输入驱动 (--port window:标题[#序号][@focus])，
+/// 与软件模拟的全局注入不同，游戏不在前台时也能运行 (Post 模式)
+pub struct SendInputDriver {
+    hwnd: WindowHandle,
+    title: String,
+    mode: TargetMode,
+    /// 虚拟光标的屏幕坐标 (Post 模式下系统光标不会移动)
+    x: i32,
+    y: i32,
+    screen_w: u16,
+    screen_h: u16,
+    held_keys: Vec<u8>,
+    held_modifier: u8,
+    held_buttons: MouseButtons,
+}
+
+impl SendInputDriver {
+    /// spec: "标题" / "标题#1" (同名窗口中的第 2 个)，末尾加 "@focus" 使用前台注入
+    pub fn new(spec: &str, screen_w: u16, screen_h: u16) -> Result<Self, String> {
+        let (rest, mode) = match spec.strip_suffix("@focus") {
+            Some(rest) => (rest, TargetMode::Focus),
+            None => (spec, TargetMode::Post),
+        };
+        let (title, index) = match rest.rsplit_once('#') {
+            Some((t, n)) => (t, n.parse::<usize>().map_err(|_| format!("无效的窗口序号: {}", n))?),
+            None => (rest, 0),
+        };
+        if title.is_empty() {
+            return Err("请指定目标窗口标题，例如 window:逆战".to_string());
+        }
+        let hwnd = window::find_window(title, index)
+            .ok_or_else(|| format!("未找到标题包含 \"{}\" 的窗口 (序号 {})", title, index))?;
+        println!("🪟 [SendInput] 目标窗口: {} (0x{:X}, {:?})", title, hwnd, mode);
+        Ok(Self {
+            hwnd,
+            title: title.to_string(),
+            mode,
+            x: screen_w as i32 / 2,
+            y: screen_h as i32 / 2,
+            screen_w,
+            screen_h,
+            held_keys: Vec::new(),
+            held_modifier: 0,
+            held_buttons: MouseButtons::empty(),
+        })
+    }
+
+    fn alive(&self) -> Result<(), DriverError> {
+        if window::is_alive(self.hwnd) {
+            Ok(())
+        } else {
+            Err(DriverError::Disconnected(format!("窗口 \"{}\" 已关闭", self.title)))
+        }
+    }
+
+    // ---------- Post 模式 ----------
+
+    fn post(&self, msg: u32, wparam: usize, lparam: LPARAM) -> Result<(), DriverError> {
+        self.alive()?;
+        unsafe { PostMessageW(HWND(self.hwnd), msg, WPARAM(wparam), lparam) }
+            .map_err(|e| DriverError::Io(format!("PostMessage 失败: {}", e)))
+    }
+
+    /// 当前鼠标键与修饰键对应的 MK_* 位
+    fn mk_state(&self) -> usize {
+        let mut state = BUTTONS.iter().filter(|b| self.held_buttons.contains(b.0)).fold(0, |s, b| s | b.3);
+        if self.held_modifier & (0x01 | 0x10) != 0 {
+            state |= MK_CONTROL;
+        }
+        if self.held_modifier & (0x02 | 0x20) != 0 {
+            state |= MK_SHIFT;
+        }
+        state
+    }
+
+    /// 鼠标消息的 lParam 为客户区坐标
+    fn client_lparam(&self) -> LPARAM {
+        let (ox, oy) = window::client_origin(self.hwnd).unwrap_or((0, 0));
+        make_lparam(self.x - ox, self.y - oy)
+    }
+
+    fn post_key(&self, (code, e0): (u16, bool), down: bool) -> Result<(), DriverError> {
+        let vk = unsafe { MapVirtualKeyW(code as u32 | if e0 { 0xE000 } else { 0 }, MAPVK_VSC_TO_VK_EX) } as usize;
+        // lParam: 重复次数 1 | 扫描码 | 扩展键 | 抬起时置前一状态与转换状态位
+        let mut lparam = 1 | ((code as isize & 0xFF) << 16);
+        if e0 {
+            lparam |= 1 << 24;
+        }
+        if !down {
+            lparam |= (1 << 30) | (1 << 31);
+        }
+        // 按住 Alt 时系统发送的是 WM_SYSKEY*
+        let sys = self.held_modifier & MOD_ALT_BITS != 0;
+        let msg = match (down, sys) {
+            (true, false) => WM_KEYDOWN,
+            (false, false) => WM_KEYUP,
+            (true, true) => WM_SYSKEYDOWN,
+            (false, true) => WM_SYSKEYUP,
+        };
+        self.post(msg, vk, LPARAM(lparam))
+    }
+
+    // ---------- Focus 模式 ----------
+
+    fn ensure_focus(&self) -> Result<(), DriverError> {
+        self.alive()?;
+        if unsafe { GetForegroundWindow() }.0 != self.hwnd {
+            if !window::focus(self.hwnd) {
+                return Err(DriverError::Io(format!("无法把窗口 \"{}\" 切到前台", self.title)));
+            }
+            thread::sleep(FOCUS_SETTLE);
+        }
+        Ok(())
+    }
+
+    fn send_inputs(&self, inputs: &[INPUT]) -> Result<(), DriverError> {
+        self.ensure_focus()?;
+        let sent = unsafe { SendInput(inputs, std::mem::size_of::<INPUT>() as i32) };
+        if sent as usize == inputs.len() {
+            Ok(())
+        } else {
+            Err(DriverError::Io("SendInput 被拦截 (目标窗口权限更高时需以管理员身份运行)".to_string()))
+        }
+    }
+
+    fn mouse_input(dx: i32, dy: i32, data: i32, flags: MOUSE_EVENT_FLAGS) -> INPUT {
+        INPUT {
+            r#type: INPUT_MOUSE,
+            Anonymous: INPUT_0 {
+                mi: MOUSEINPUT { dx, dy, mouseData: data as u32, dwFlags: flags, time: 0, dwExtraInfo: 0 },
+            },
+        }
+    }
+
+    fn key_input((code, e0): (u16, bool), down: bool) -> INPUT {
+        let mut flags = KEYEVENTF_SCANCODE;
+        if e0 {
+            flags |= KEYEVENTF_EXTENDEDKEY;
+        }
+        if !down {
+            flags |= KEYEVENTF_KEYUP;
+        }
+        INPUT {
+            r#type: INPUT_KEYBOARD,
+            Anonymous: INPUT_0 {
+                ki: KEYBDINPUT { wVk: VIRTUAL_KEY(0), wScan: code, dwFlags: flags, time: 0, dwExtraInfo: 0 },
+            },
+        }
+    }
+
+    /// KEYEVENTF_UNICODE 事件：wScan 携带 UTF-16 码元，系统转换为 WM_CHAR (不经过输入法)
+    fn unicode_input(unit: u16, down: bool) -> INPUT {
+        let flags = if down { KEYEVENTF_UNICODE } else { KEYEVENTF_UNICODE | KEYEVENTF_KEYUP };
+        INPUT {
+            r#type: INPUT_KEYBOARD,
+            Anonymous: INPUT_0 {
+                ki: KEYBDINPUT { wVk: VIRTUAL_KEY(0), wScan: unit, dwFlags: flags, time: 0, dwExtraInfo: 0 },
+            },
+        }
+    }
+
+    // ---------- 两种模式共用 ----------
+
+    fn send_move(&self) -> Result<(), DriverError> {
+        match self.mode {
+            TargetMode::Post => self.post(WM_MOUSEMOVE, self.mk_state(), self.client_lparam()),
+            TargetMode::Focus => {
+                // 绝对坐标范围 0~65535
+                let tx = (self.x as f32 / self.screen_w as f32 * 65535.0).clamp(0.0, 65535.0) as i32;
+                let ty = (self.y as f32 / self.screen_h as f32 * 65535.0).clamp(0.0, 65535.0) as i32;
+                self.send_inputs(&[Self::mouse_input(tx, ty, 0, MOUSEEVENTF_MOVE | MOUSEEVENTF_ABSOLUTE)])
+            }
+        }
+    }
+
+    fn send_wheel(&self, notches: i8, horizontal: bool) -> Result<(), DriverError> {
+        let delta = notches as i32 * WHEEL_DELTA as i32;
+        match self.mode {
+            TargetMode::Post => {
+                // 滚轮消息的 lParam 为屏幕坐标
+                let msg = if horizontal { WM_MOUSEHWHEEL } else { WM_MOUSEWHEEL };
+                let wparam = ((delta as i16 as u16 as usize) << 16) | self.mk_state();
+                self.post(msg, wparam, make_lparam(self.x, self.y))
+            }
+            TargetMode::Focus => {
+                let flags = if horizontal { MOUSEEVENTF_HWHEEL } else { MOUSEEVENTF_WHEEL };
+                self.send_inputs(&[Self::mouse_input(0, 0, delta, flags)])
+            }
+        }
+    }
+
+    fn send_button(&mut self, b: &ButtonMap, down: bool) -> Result<(), DriverError> {
+        self.held_buttons.set(b.0, down);
+        match self.mode {
+            TargetMode::Post => {
+                let msg = if down { b.1 } else { b.2 };
+                let wparam = ((b.4 as usize) << 16) | self.mk_state();
+                self.post(msg, wparam, self.client_lparam())
+            }
+            TargetMode::Focus => {
+                let flags = if down { b.5 } else { b.6 };
+                self.send_inputs(&[Self::mouse_input(0, 0, b.4 as i32, flags)])
+            }
+        }
+    }
+
+    fn send_key(&self, sc: (u16, bool), down: bool) -> Result<(), DriverError> {
+        match self.mode {
+            TargetMode::Post => self.post_key(sc, down),
+            TargetMode::Focus => self.send_inputs(&[Self::key_input(sc, down)]),
+        }
+    }
+
+    /// 与当前状态比较，只发送变化的按键 (先松开后按下)
+    fn set_held(&mut self, keys: Vec<u8>, modifier: u8) -> Result<(), DriverError> {
+        for key in self.held_keys.clone().into_iter().rev() {
+            if !keys.contains(&key) {
+                self.held_keys.retain(|k| *k != key);
+                if let Some(sc) = hid_to_scancode(key) {
+                    self.send_key(sc, false)?;
+                }
+            }
+        }
+        for (bit, sc) in MODIFIER_SCANCODES.iter().enumerate().rev() {
+            let mask = 1 << bit;
+            if self.held_modifier & mask != 0 && modifier & mask == 0 {
+                self.send_key(*sc, false)?;
+                self.held_modifier &= !mask;
+            }
+        }
+        for (bit, sc) in MODIFIER_SCANCODES.iter().enumerate() {
+            let mask = 1 << bit;
+            if modifier & mask != 0 && self.held_modifier & mask == 0 {
+                self.held_modifier |= mask;
+                self.send_key(*sc, true)?;
+            }
+        }
+        for key in keys {
+            if !self.held_keys.contains(&key) {
+                if let Some(sc) = hid_to_scancode(key) {
+                    self.send_key(sc, true)?;
+                }
+                self.held_keys.push(key);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl InputDriver for SendInputDriver {
+    fn info(&self) -> DriverInfo {
+        let name = match self.mode {
+            TargetMode::Post => "窗口消息 (后台)",
+            TargetMode::Focus => "SendInput (前台)",
+        };
+        DriverInfo::named(name, DriverFeatures::KEYBOARD_REPORT | DriverFeatures::HWHEEL)
+    }
+
+    fn capabilities(&self) -> DriverCaps {
+        // Post 模式只投递窗口消息，不移动系统光标
+        DriverCaps { identity: false, text_input: true, os_cursor: self.mode == TargetMode::Focus, ..DriverCaps::default() }
+    }
+
+    /// Post 模式逐个码元投递 WM_CHAR，Focus 模式注入 Unicode 键盘事件
+    fn type_text(&mut self, text: &str) -> Result<(), DriverError> {
+        for unit in text.encode_utf16() {
+            match self.mode {
+                TargetMode::Post => self.post(WM_CHAR, unit as usize, LPARAM(1))?,
+                TargetMode::Focus => {
+                    self.send_inputs(&[Self::unicode_input(unit, true), Self::unicode_input(unit, false)])?
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn is_online(&self) -> bool {
+        window::is_alive(self.hwnd)
+    }
+
+    fn heartbeat(&mut self) -> Result<(), DriverError> { self.alive() }
+    fn switch_identity(&mut self, _index: u8) -> Result<(), DriverError> { Ok(()) }
+
+    fn mouse_abs(&mut self, x: u16, y: u16) -> Result<(), DriverError> {
+        self.x = x as i32;
+        self.y = y as i32;
+        self.send_move()
+    }
+
+    fn mouse_move(&mut self, dx: i32, dy: i32, wheel: i8) -> Result<(), DriverError> {
+        if dx != 0 || dy != 0 {
+            self.x = (self.x + dx).clamp(0, self.screen_w as i32 - 1);
+            self.y = (self.y + dy).clamp(0, self.screen_h as i32 - 1);
+            self.send_move()?;
+        }
+        if wheel != 0 {
+            self.send_wheel(wheel, false)?;
+        }
+        Ok(())
+    }
+
+    fn mouse_hscroll(&mut self, delta: i8) -> Result<(), DriverError> {
+        self.send_wheel(delta, true)
+    }
+
+    fn mouse_down(&mut self, buttons: MouseButtons) -> Result<(), DriverError> {
+        let held = self.held_buttons;
+        for b in BUTTONS.iter().filter(|b| buttons.contains(b.0) && !held.contains(b.0)) {
+            self.send_button(b, true)?;
+        }
+        Ok(())
+    }
+
+    fn mouse_up(&mut self) -> Result<(), DriverError> {
+        let held = self.held_buttons;
+        for b in BUTTONS.iter().filter(|b| held.contains(b.0)) {
+            self.send_button(b, false)?;
+        }
+        Ok(())
+    }
+
+    fn key_down(&mut self, keycode: u8, modifier: u8) -> Result<(), DriverError> {
+        let mut keys = self.held_keys.clone();
+        if keycode != 0 && !keys.contains(&keycode) {
+            keys.push(keycode);
+        }
+        self.set_held(keys, modifier)
+    }
+
+    fn key_up(&mut self) -> Result<(), DriverError> {
+        self.set_held(Vec::new(), 0)
+    }
+
+    fn keys_down(&mut self, keycodes: &[u8], modifier: u8) -> Result<(), DriverError> {
+        let keys = chord_report(keycodes).into_iter().filter(|k| *k != 0).collect();
+        self.set_held(keys, modifier)
+    }
+}
@@ -0,0 +1,248 @@
+// src/stats.rs
+use chrono::{DateTime, Local, TimeZone};
+use rusqlite::{params, Connection};
+use std::fs::File;
+use std::io::Write;
+
+/// 运行统计与任务检查点共用的状态库
+pub const DEFAULT_STATE_DB: &str = "stats.db";
+
+// ==========================================
+// 1. 数据结构
+// ==========================================
+
+/// 单局运行记录 (一次 Handover 到处理器结束)
+#[derive(Debug, Clone)]
+pub struct RunRecord {
+    pub map: String,
+    pub handler: String,
+    pub started_at: DateTime<Local>,
+    pub duration_secs: f64,
+    pub success: bool,
+    pub waves: i32,
+    pub refresh_count: u32,
+}
+
+/// 聚合维度
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GroupBy {
+    Map,
+    Handler,
+    Day,
+}
+
+impl GroupBy {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s.to_lowercase().as_str() {
+            "map" => Ok(GroupBy::Map),
+            "handler" => Ok(GroupBy::Handler),
+            "day" => Ok(GroupBy::Day),
+            _ => Err(format!("未知的分组维度: {} (可选: map / handler / day)", s)),
+        }
+    }
+
+    fn sql_key(&self) -> &'static str {
+        match self {
+            GroupBy::Map => "map",
+            GroupBy::Handler => "handler",
+            GroupBy::Day => "date(started_at, 'unixepoch', 'localtime')",
+        }
+    }
+}
+
+/// 聚合后的一行统计
+#[derive(Debug, Clone)]
+pub struct StatsRow {
+    pub key: String,
+    pub runs: u32,
+    pub wins: u32,
+    pub avg_duration_secs: f64,
+    pub refresh_spend: u32,
+}
+
+impl StatsRow {
+    pub fn win_rate(&self) -> f64 {
+        if self.runs == 0 {
+            0.0
+        } else {
+            self.wins as f64 / self.runs as f64 * 100.0
+        }
+    }
+}
+
+// ==========================================
+// 2. 存储层 (SQLite)
+// ==========================================
+pub struct StatsStore {
+    conn: Connection,
+}
+
+impl StatsStore {
+    pub fn open(path: &str) -> Result<Self, String> {
+        let conn = Connection::open(path).map_err(|e| format!("无法打开统计库 {}: {}", path, e))?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS runs (
+                id            INTEGER PRIMARY KEY AUTOINCREMENT,
+                map           TEXT    NOT NULL,
+                handler       TEXT    NOT NULL,
+                started_at    INTEGER NOT NULL,
+                duration_secs REAL    NOT NULL,
+                success       INTEGER NOT NULL,
+                waves         INTEGER NOT NULL DEFAULT 0,
+                refresh_count INTEGER NOT NULL DEFAULT 0
+            );
+            CREATE INDEX IF NOT EXISTS idx_runs_started ON runs(started_at);",
+        )
+        .map_err(|e| format!("统计库初始化失败: {}", e))?;
+        Ok(Self { conn })
+    }
+
+    pub fn record_run(&self, r: &RunRecord) -> Result<(), String> {
+        self.conn
+            .execute(
+                "INSERT INTO runs (map, handler, started_at, duration_secs, success, waves, refresh_count)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    r.map,
+                    r.handler,
+                    r.started_at.timestamp(),
+                    r.duration_secs,
+                    r.success as i32,
+                    r.waves,
+                    r.refresh_count
+                ],
+            )
+            .map_err(|e| format!("写入统计失败: {}", e))?;
+        Ok(())
+    }
+
+    /// 按维度聚合 since 之后的记录
+    pub fn query(&self, since: Option<DateTime<Local>>, by: GroupBy) -> Result<Vec<StatsRow>, String> {
+        let key = by.sql_key();
+        let sql = format!(
+            "SELECT {key}, COUNT(*), SUM(success), AVG(duration_secs), SUM(refresh_count)
+             FROM runs WHERE started_at >= ?1 GROUP BY {key} ORDER BY COUNT(*) DESC",
+            key = key
+        );
+        let since_ts = since.map(|t| t.timestamp()).unwrap_or(0);
+
+        let mut stmt = self.conn.prepare(&sql).map_err(|e| format!("查询失败: {}", e))?;
+        let rows = stmt
+            .query_map(params![since_ts], |row| {
+                Ok(StatsRow {
+                    key: row.get(0)?,
+                    runs: row.get(1)?,
+                    wins: row.get(2)?,
+                    avg_duration_secs: row.get(3)?,
+                    refresh_spend: row.get(4)?,
+                })
+            })
+            .map_err(|e| format!("查询失败: {}", e))?;
+
+        rows.collect::<Result<Vec<_>, _>>().map_err(|e| format!("读取结果失败: {}", e))
+    }
+
+    /// 导出原始记录为 CSV，返回导出的行数
+    pub fn export_csv(&self, since: Option<DateTime<Local>>, path: &str) -> Result<usize, String> {
+        let since_ts = since.map(|t| t.timestamp()).unwrap_or(0);
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT map, handler, started_at, duration_secs, success, waves, refresh_count
+                 FROM runs WHERE started_at >= ?1 ORDER BY started_at",
+            )
+            .map_err(|e| format!("查询失败: {}", e))?;
+
+        let records = stmt
+            .query_map(params![since_ts], |row| {
+                let ts: i64 = row.get(2)?;
+                Ok(RunRecord {
+                    map: row.get(0)?,
+                    handler: row.get(1)?,
+                    started_at: Local.timestamp_opt(ts, 0).single().unwrap_or_else(Local::now),
+                    duration_secs: row.get(3)?,
+                    success: row.get::<_, i32>(4)? != 0,
+                    waves: row.get(5)?,
+                    refresh_count: row.get(6)?,
+                })
+            })
+            .map_err(|e| format!("查询失败: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("读取结果失败: {}", e))?;
+
+        let mut file = File::create(path).map_err(|e| format!("无法创建 {}: {}", path, e))?;
+        // 写入 BOM，方便 Excel 正确识别中文
+        let mut out = String::from("\u{FEFF}map,handler,started_at,duration_secs,success,waves,refresh_count\n");
+        for r in &records {
+            out.push_str(&format!(
+                "{},{},{},{:.1},{},{},{}\n",
+                csv_escape(&r.map),
+                csv_escape(&r.handler),
+                r.started_at.format("%Y-%m-%d %H:%M:%S"),
+                r.duration_secs,
+                r.success as i32,
+                r.waves,
+                r.refresh_count
+            ));
+        }
+        file.write_all(out.as_bytes()).map_err(|e| format!("写入 {} 失败: {}", path, e))?;
+        Ok(records.len())
+    }
+}
+
+// ==========================================
+// 3. 辅助函数
+// ==========================================
+
+/// 解析 "7d" / "12h" / "30m" 形式的时间跨度，返回起始时间
+pub fn parse_since(s: &str) -> Result<DateTime<Local>, String> {
+    let s = s.trim();
+    // 单位取最后一个字符 (可能是多字节字符，如 "7天")
+    let Some((i, unit)) = s.char_indices().last() else {
+        return Err(format!("无效的时间跨度: {}", s));
+    };
+    let n: i64 = match s[..i].parse() {
+        Ok(n) if n > 0 => n,
+        _ => return Err(format!("无效的时间跨度: {} (需为正整数加单位，如 7d)", s)),
+    };
+    let span = match unit {
+        'd' => chrono::Duration::try_days(n),
+        'h' => chrono::Duration::try_hours(n),
+        'm' => chrono::Duration::try_minutes(n),
+        _ => return Err(format!("无效的时间单位: {} (可选: d / h / m)", unit)),
+    };
+    span.and_then(|span| Local::now().checked_sub_signed(span)).ok_or_else(|| format!("时间跨度过大: {}", s))
+}
+
+fn csv_escape(s: &str) -> String {
+    if s.contains(',') || s.contains('"') || s.contains('\n') {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+pub fn print_table(rows: &[StatsRow], by: GroupBy) {
+    let title = match by {
+        GroupBy::Map => "地图",
+        GroupBy::Handler => "处理器",
+        GroupBy::Day => "日期",
+    };
+    println!("----------------------------------------------------------------");
+    println!(
+        "{:<16} {:>6} {:>8} {:>12} {:>8}",
+        title, "局数", "胜率", "平均时长", "刷新"
+    );
+    println!("----------------------------------------------------------------");
+    for r in rows {
+        println!(
+            "{:<16} {:>6} {:>7.1}% {:>11.0}s {:>8}",
+            r.key,
+            r.runs,
+            r.win_rate(),
+            r.avg_duration_secs,
+            r.refresh_spend
+        );
+    }
+    println!("----------------------------------------------------------------");
+}
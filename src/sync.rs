@@ -0,0 +1,346 @@
+// src/sync.rs
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+// ==========================================
+// 1. 配置与清单格式
+// ==========================================
+#[derive(Deserialize, Debug, Clone)]
+pub struct SyncConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// "http" 或 "git"
+    #[serde(default = "default_source")]
+    pub source: String,
+    /// http: 清单所在目录的 URL；git: 仓库地址
+    #[serde(default)]
+    pub url: String,
+    #[serde(default = "default_branch")]
+    pub branch: String,
+    /// 清单文件在远端的相对路径
+    #[serde(default = "default_manifest")]
+    pub manifest: String,
+    /// 保留的本地备份份数
+    #[serde(default = "default_keep_backups")]
+    pub keep_backups: usize,
+}
+
+fn default_source() -> String { "http".to_string() }
+fn default_branch() -> String { "main".to_string() }
+fn default_manifest() -> String { "manifest.json".to_string() }
+fn default_keep_backups() -> usize { 5 }
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            source: default_source(),
+            url: String::new(),
+            branch: default_branch(),
+            manifest: default_manifest(),
+            keep_backups: default_keep_backups(),
+        }
+    }
+}
+
+/// 远端清单：列出需要同步的文件与校验和
+#[derive(Deserialize, Debug, Clone)]
+pub struct Manifest {
+    #[serde(default)]
+    pub version: String,
+    pub files: Vec<ManifestFile>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ManifestFile {
+    pub path: String,
+    pub sha256: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct SyncState {
+    version: String,
+    /// 最近一次同步覆盖前的备份目录名；没有文件被覆盖时为 None
+    last_backup: Option<String>,
+    /// 最近一次同步新建的文件 (本地原本不存在)，回滚时删除
+    #[serde(default)]
+    created: Vec<String>,
+}
+
+const STATE_FILE: &str = "sync_state.json";
+const BACKUP_DIR: &str = "backup";
+const GIT_CACHE_DIR: &str = ".sync_cache";
+
+/// 允许同步的文件类型：地图 / 策略 (toml、json)、模板图片与字典等文本数据；可执行文件、脚本、动态库一律拒绝
+const SYNC_EXTENSIONS: &[&str] = &["toml", "json", "png", "jpg", "txt"];
+/// 即使扩展名允许也不可同步的本地配置与状态 (远端清单的校验和来自同一服务器，不能证明内容可信)
+const PROTECTED_FILES: &[&str] = &["config.toml", crate::secrets::DEFAULT_SECRETS_FILE, STATE_FILE, "Cargo.toml"];
+/// 不可写入的目录
+const PROTECTED_DIRS: &[&str] = &[BACKUP_DIR, GIT_CACHE_DIR, ".git", "src", "target"];
+
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub version: String,
+    pub updated: Vec<String>,
+    pub unchanged: usize,
+    pub backup: Option<String>,
+}
+
+// ==========================================
+// 2. 远端来源
+// ==========================================
+enum Source {
+    Http(String),
+    Git(PathBuf),
+}
+
+impl Source {
+    fn prepare(cfg: &SyncConfig) -> Result<Self, String> {
+        if cfg.url.is_empty() {
+            return Err("未配置同步地址 (sync.url)".to_string());
+        }
+        match cfg.source.to_lowercase().as_str() {
+            "http" => Ok(Source::Http(cfg.url.trim_end_matches('/').to_string())),
+            "git" => {
+                let dir = PathBuf::from(GIT_CACHE_DIR);
+                let status = if dir.join(".git").exists() {
+                    println!("🔄 [Sync] git pull ({})", cfg.branch);
+                    Command::new("git")
+                        .args(["-C", GIT_CACHE_DIR, "pull", "--ff-only", "origin", &cfg.branch])
+                        .status()
+                } else {
+                    println!("🔄 [Sync] git clone {} ({})", cfg.url, cfg.branch);
+                    Command::new("git")
+                        .args(["clone", "--depth", "1", "--branch", &cfg.branch, &cfg.url, GIT_CACHE_DIR])
+                        .status()
+                };
+                match status {
+                    Ok(s) if s.success() => Ok(Source::Git(dir)),
+                    Ok(s) => Err(format!("git 执行失败 (退出码 {:?})", s.code())),
+                    Err(e) => Err(format!("无法执行 git: {}", e)),
+                }
+            }
+            other => Err(format!("未知的同步来源: {} (可选: http / git)", other)),
+        }
+    }
+
+    fn fetch(&self, rel: &str) -> Result<Vec<u8>, String> {
+        match self {
+            Source::Http(base) => {
+                let url = format!("{}/{}", base, rel);
+                let resp = ureq::get(&url).call().map_err(|e| format!("下载 {} 失败: {}", url, e))?;
+                let mut buf = Vec::new();
+                resp.into_reader()
+                    .read_to_end(&mut buf)
+                    .map_err(|e| format!("读取 {} 失败: {}", url, e))?;
+                Ok(buf)
+            }
+            Source::Git(dir) => fs::read(dir.join(rel)).map_err(|e| format!("读取 {} 失败: {}", rel, e)),
+        }
+    }
+}
+
+// ==========================================
+// 3. 同步流程
+// ==========================================
+fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// 只允许同步到运行目录内部、属于数据文件 (见 SYNC_EXTENSIONS) 的相对路径；本地配置、凭据与程序文件一律拒绝
+fn safe_path(rel: &str) -> Result<PathBuf, String> {
+    let p = Path::new(rel);
+    if p.is_absolute() || p.components().any(|c| !matches!(c, Component::Normal(_))) {
+        return Err(format!("清单中的路径不安全: {}", rel));
+    }
+    let ext = p.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+    if !SYNC_EXTENSIONS.contains(&ext.as_str()) {
+        return Err(format!("清单中的文件类型不允许同步: {} (只允许 {})", rel, SYNC_EXTENSIONS.join(" / ")));
+    }
+    let name = p.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+    let top = p.components().next().map(|c| c.as_os_str().to_string_lossy().to_lowercase()).unwrap_or_default();
+    let top_is_dir = p.components().count() > 1;
+    if (!top_is_dir && PROTECTED_FILES.iter().any(|f| f.eq_ignore_ascii_case(&name)))
+        || (top_is_dir && PROTECTED_DIRS.iter().any(|d| d.eq_ignore_ascii_case(&top)))
+    {
+        return Err(format!("清单试图覆盖受保护的本地文件: {}", rel));
+    }
+    Ok(p.to_path_buf())
+}
+
+/// 基本格式校验：TOML / JSON 文件必须能被解析
+fn validate_content(rel: &str, data: &[u8]) -> Result<(), String> {
+    let text = || std::str::from_utf8(data).map_err(|_| format!("{} 不是有效的 UTF-8", rel));
+    if rel.ends_with(".toml") {
+        toml::from_str::<toml::Value>(text()?).map_err(|e| format!("{} 不是有效的 TOML: {}", rel, e))?;
+    } else if rel.ends_with(".json") {
+        serde_json::from_str::<serde_json::Value>(text()?).map_err(|e| format!("{} 不是有效的 JSON: {}", rel, e))?;
+    }
+    Ok(())
+}
+
+fn load_state() -> SyncState {
+    fs::read_to_string(STATE_FILE)
+        .ok()
+        .and_then(|c| serde_json::from_str(&c).ok())
+        .unwrap_or_default()
+}
+
+fn save_state(state: &SyncState) {
+    if let Ok(json) = serde_json::to_string_pretty(state) {
+        let _ = fs::write(STATE_FILE, json);
+    }
+}
+
+/// 拉取远端配置：全部下载并校验通过后才落盘，写入过程出错自动回滚
+pub fn sync(cfg: &SyncConfig) -> Result<SyncReport, String> {
+    let source = Source::prepare(cfg)?;
+    let manifest_bytes = source.fetch(&cfg.manifest)?;
+    let manifest: Manifest =
+        serde_json::from_slice(&manifest_bytes).map_err(|e| format!("清单解析失败: {}", e))?;
+    println!("📦 [Sync] 远端版本: {} ({} 个文件)", manifest.version, manifest.files.len());
+
+    // 1. 下载所有有变化的文件并校验
+    let mut pending: Vec<(PathBuf, String, Vec<u8>)> = Vec::new();
+    let mut unchanged = 0;
+    for f in &manifest.files {
+        let path = safe_path(&f.path)?;
+        let expected = f.sha256.to_lowercase();
+        if fs::read(&path).map(|d| sha256_hex(&d) == expected).unwrap_or(false) {
+            unchanged += 1;
+            continue;
+        }
+        let data = source.fetch(&f.path)?;
+        let actual = sha256_hex(&data);
+        if actual != expected {
+            return Err(format!("{} 校验失败 (期望 {}, 实际 {})，本次同步已取消", f.path, expected, actual));
+        }
+        validate_content(&f.path, &data)?;
+        pending.push((path, f.path.clone(), data));
+    }
+
+    let mut state = load_state();
+    if pending.is_empty() {
+        println!("✅ [Sync] 本地配置已是最新");
+        state.version = manifest.version.clone();
+        save_state(&state);
+        return Ok(SyncReport { version: manifest.version, updated: vec![], unchanged, backup: None });
+    }
+
+    // 2. 备份将被覆盖的本地文件；本地不存在的文件记为新建，回滚时删除
+    let backup_name = chrono::Local::now().format("%Y%m%d_%H%M%S").to_string();
+    let backup_root = Path::new(BACKUP_DIR).join(&backup_name);
+    let mut backed_up = 0;
+    let mut created = Vec::new();
+    for (path, rel, _) in &pending {
+        if !path.exists() {
+            created.push(rel.clone());
+            continue;
+        }
+        let dst = backup_root.join(path);
+        if let Some(parent) = dst.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("创建备份目录失败: {}", e))?;
+        }
+        fs::copy(path, &dst).map_err(|e| format!("备份 {} 失败: {}", path.display(), e))?;
+        backed_up += 1;
+    }
+
+    // 3. 写入，任一失败则回滚
+    let mut written: Vec<PathBuf> = Vec::new();
+    for (path, rel, data) in &pending {
+        let result = path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .map(fs::create_dir_all)
+            .unwrap_or(Ok(()))
+            .and_then(|_| fs::write(path, data));
+        if let Err(e) = result {
+            println!("❌ [Sync] 写入 {} 失败，正在回滚...", rel);
+            // 写入失败的文件可能已被截断或新建了一半，同样恢复 / 删除
+            written.push(path.clone());
+            restore_files(&backup_root, &written);
+            return Err(format!("写入 {} 失败: {}", rel, e));
+        }
+        written.push(path.clone());
+        println!("   ⬇️ 已更新: {}", rel);
+    }
+
+    let backup = (backed_up > 0).then_some(backup_name);
+    state.version = manifest.version.clone();
+    state.last_backup = backup.clone();
+    state.created = created;
+    save_state(&state);
+    prune_backups(cfg.keep_backups);
+
+    Ok(SyncReport {
+        version: manifest.version,
+        updated: pending.into_iter().map(|(_, rel, _)| rel).collect(),
+        unchanged,
+        backup,
+    })
+}
+
+/// 用备份覆盖指定文件；备份中不存在的文件 (新增文件) 直接删除
+fn restore_files(backup_root: &Path, files: &[PathBuf]) {
+    for path in files {
+        let src = backup_root.join(path);
+        if src.exists() {
+            let _ = fs::copy(&src, path);
+        } else {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// 回滚到最近一次同步前：恢复被覆盖的文件，删除同步新建的文件
+pub fn rollback() -> Result<Vec<String>, String> {
+    let state = load_state();
+    if state.last_backup.is_none() && state.created.is_empty() {
+        return Err("没有可回滚的备份".to_string());
+    }
+
+    let mut restored = Vec::new();
+    for rel in &state.created {
+        let path = safe_path(rel)?;
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| format!("删除 {} 失败: {}", rel, e))?;
+            restored.push(format!("{} (已删除)", rel));
+        }
+    }
+    let Some(name) = state.last_backup else {
+        save_state(&SyncState { version: "rollback".to_string(), ..SyncState::default() });
+        return Ok(restored);
+    };
+    let root = Path::new(BACKUP_DIR).join(&name);
+    let mut stack = vec![root.clone()];
+    while let Some(dir) = stack.pop() {
+        for entry in fs::read_dir(&dir).map_err(|e| format!("读取备份 {} 失败: {}", name, e))?.flatten() {
+            let p = entry.path();
+            if p.is_dir() {
+                stack.push(p);
+            } else if let Ok(rel) = p.strip_prefix(&root) {
+                fs::copy(&p, rel).map_err(|e| format!("恢复 {} 失败: {}", rel.display(), e))?;
+                restored.push(rel.display().to_string());
+            }
+        }
+    }
+
+    save_state(&SyncState { version: format!("rollback:{}", name), ..SyncState::default() });
+    Ok(restored)
+}
+
+fn prune_backups(keep: usize) {
+    let mut dirs: Vec<PathBuf> = match fs::read_dir(BACKUP_DIR) {
+        Ok(rd) => rd.flatten().map(|e| e.path()).filter(|p| p.is_dir()).collect(),
+        Err(_) => return,
+    };
+    // 目录名为时间戳，字典序即时间序
+    dirs.sort();
+    while dirs.len() > keep.max(1) {
+        let _ = fs::remove_dir_all(dirs.remove(0));
+    }
+}
@@ -0,0 +1,146 @@
+// src/template.rs
+use image::imageops::FilterType;
+use image::GrayImage;
+
+/// 缩放后模板的最小边长；再小就没有可区分的细节了
+const MIN_SIDE: u32 = 4;
+/// 粗搜索时模板短边缩到约这么长
+const COARSE_SIDE: u32 = 8;
+/// 粗搜索的最大缩小倍数
+const MAX_COARSE_FACTOR: u32 = 4;
+
+// ==========================================
+// 1. 模板
+// ==========================================
+
+/// 灰度模板；预先减去均值，匹配时只需计算与截图窗口的点积
+pub struct Template {
+    width: u32,
+    height: u32,
+    pixels: Vec<f32>,
+    /// sqrt(Σ (T - 均值)²)
+    norm: f32,
+    /// 原始灰度图，多尺度搜索时据此重新缩放
+    image: GrayImage,
+}
+
+/// 匹配结果：窗口左上角 (相对截图) 与归一化互相关得分 (-1 ~ 1，1 为完全一致)
+#[derive(Debug, Clone, Copy)]
+pub struct Match {
+    pub x: u32,
+    pub y: u32,
+    pub score: f32,
+}
+
+impl Template {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let img = image::open(path).map_err(|e| format!("无法加载模板图片 {}: {}", path, e))?;
+        Self::from_image(&img.to_luma8()).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn from_image(img: &GrayImage) -> Result<Self, String> {
+        let (width, height) = img.dimensions();
+        if width == 0 || height == 0 {
+            return Err("模板为空".to_string());
+        }
+        let n = (width * height) as f32;
+        let mean = img.pixels().map(|p| p[0] as f32).sum::<f32>() / n;
+        let pixels: Vec<f32> = img.pixels().map(|p| p[0] as f32 - mean).collect();
+        let norm = pixels.iter().map(|v| v * v).sum::<f32>().sqrt();
+        // 纯色模板与任何窗口的相关系数都没有意义，这种元素应改用颜色锚点
+        if norm < 1.0 {
+            return Err("模板是纯色的，请改用 color 锚点".to_string());
+        }
+        Ok(Self { width, height, pixels, norm, image: img.clone() })
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// 按比例缩放后的模板；任一边不足 MIN_SIDE 或缩放后变成纯色时为 None
+    pub fn scaled(&self, scale: f32) -> Option<Template> {
+        let w = (self.width as f32 * scale).round() as u32;
+        let h = (self.height as f32 * scale).round() as u32;
+        if w < MIN_SIDE || h < MIN_SIDE {
+            return None;
+        }
+        if (w, h) == (self.width, self.height) {
+            return Template::from_image(&self.image).ok();
+        }
+        Template::from_image(&image::imageops::resize(&self.image, w, h, FilterType::Triangle)).ok()
+    }
+
+    /// 由粗到精的大图搜索：先在缩小的截图上找出最佳位置，再回到原图在该位置附近精确匹配；
+    /// 全屏逐像素匹配太慢，元素位置不固定 (漂浮的宝箱、随机槽位的卡牌) 时用它代替 best_match
+    pub fn search(&self, img: &GrayImage) -> Option<Match> {
+        let factor = (self.width.min(self.height) / COARSE_SIDE).clamp(1, MAX_COARSE_FACTOR);
+        if factor == 1 {
+            return self.best_match(img);
+        }
+        let (w, h) = img.dimensions();
+        let coarse_img = image::imageops::resize(img, w / factor, h / factor, FilterType::Triangle);
+        let Some(coarse) = self.scaled(1.0 / factor as f32) else { return self.best_match(img) };
+        let m = coarse.best_match(&coarse_img)?;
+        // 粗匹配的位置误差在 factor 像素以内，留两倍余量
+        let margin = factor * 2;
+        let (x0, y0) = ((m.x * factor).saturating_sub(margin), (m.y * factor).saturating_sub(margin));
+        let (cw, ch) = ((self.width + margin * 2).min(w - x0), (self.height + margin * 2).min(h - y0));
+        let fine = self.best_match(&image::imageops::crop_imm(img, x0, y0, cw, ch).to_image())?;
+        Some(Match { x: x0 + fine.x, y: y0 + fine.y, score: fine.score })
+    }
+
+    // ==========================================
+    // 2. 归一化互相关 (NCC)
+    // ==========================================
+
+    /// 在截图中逐像素滑动模板，返回得分最高的位置；截图小于模板时返回 None
+    /// 窗口的均值与方差由积分图 O(1) 求出，对亮度整体变化 (背景动画、明暗闪烁) 不敏感
+    pub fn best_match(&self, img: &GrayImage) -> Option<Match> {
+        let (w, h) = img.dimensions();
+        if w < self.width || h < self.height {
+            return None;
+        }
+        let (iw, n) = (w as usize + 1, (self.width * self.height) as f64);
+        let mut sum = vec![0f64; iw * (h as usize + 1)];
+        let mut sq = vec![0f64; iw * (h as usize + 1)];
+        for y in 0..h as usize {
+            let (mut row, mut row_sq) = (0f64, 0f64);
+            for x in 0..w as usize {
+                let v = img.get_pixel(x as u32, y as u32)[0] as f64;
+                row += v;
+                row_sq += v * v;
+                sum[(y + 1) * iw + x + 1] = sum[y * iw + x + 1] + row;
+                sq[(y + 1) * iw + x + 1] = sq[y * iw + x + 1] + row_sq;
+            }
+        }
+        let window = |table: &[f64], x: usize, y: usize| {
+            let (x2, y2) = (x + self.width as usize, y + self.height as usize);
+            table[y2 * iw + x2] - table[y * iw + x2] - table[y2 * iw + x] + table[y * iw + x]
+        };
+
+        let raw = img.as_raw();
+        let mut best: Option<Match> = None;
+        for y in 0..=(h - self.height) as usize {
+            for x in 0..=(w - self.width) as usize {
+                let s = window(&sum, x, y);
+                let var = window(&sq, x, y) - s * s / n;
+                if var < 1.0 {
+                    continue;
+                }
+                // 模板已去均值，Σ (I - 均值) * T' 等于 Σ I * T'
+                let mut dot = 0f32;
+                for ty in 0..self.height as usize {
+                    let row = &raw[(y + ty) * w as usize + x..][..self.width as usize];
+                    let t = &self.pixels[ty * self.width as usize..][..self.width as usize];
+                    dot += row.iter().zip(t).map(|(&i, &t)| i as f32 * t).sum::<f32>();
+                }
+                let score = dot / (var.sqrt() as f32 * self.norm);
+                if best.is_none_or(|b| score > b.score) {
+                    best = Some(Match { x: x as u32, y: y as u32, score });
+                }
+            }
+        }
+        best
+    }
+}
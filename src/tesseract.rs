@@ -0,0 +1,255 @@
+// src/tesseract.rs
+// 通过 Tesseract C API 识别文字 (需将 Tesseract 动态库及其依赖放在程序目录，语言包放在 tessdata 目录)
+use crate::ocr::{OcrBackend, OcrConfig, OcrLine, OcrWord};
+use image::DynamicImage;
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use windows::core::{s, HSTRING};
+use windows::Win32::System::LibraryLoader::{GetProcAddress, LoadLibraryW};
+
+// ==========================================
+// 1. Tesseract 动态库接口 (运行时加载)
+// ==========================================
+
+type CreateFn = unsafe extern "C" fn() -> *mut c_void;
+type DeleteFn = unsafe extern "C" fn(*mut c_void);
+type Init3Fn = unsafe extern "C" fn(*mut c_void, *const c_char, *const c_char) -> c_int;
+type SetPageSegModeFn = unsafe extern "C" fn(*mut c_void, c_int);
+type SetImageFn = unsafe extern "C" fn(*mut c_void, *const u8, c_int, c_int, c_int, c_int);
+type GetUtf8TextFn = unsafe extern "C" fn(*mut c_void) -> *mut c_char;
+type DeleteTextFn = unsafe extern "C" fn(*mut c_char);
+type MeanTextConfFn = unsafe extern "C" fn(*mut c_void) -> c_int;
+type RecognizeFn = unsafe extern "C" fn(*mut c_void, *mut c_void) -> c_int;
+type GetIteratorFn = unsafe extern "C" fn(*mut c_void) -> *mut c_void;
+type IterTextFn = unsafe extern "C" fn(*mut c_void, c_int) -> *mut c_char;
+type IterConfidenceFn = unsafe extern "C" fn(*mut c_void, c_int) -> f32;
+type IterLevelFn = unsafe extern "C" fn(*mut c_void, c_int) -> c_int;
+type IterBoxFn = unsafe extern "C" fn(*mut c_void, c_int, *mut c_int, *mut c_int, *mut c_int, *mut c_int) -> c_int;
+type IterDeleteFn = unsafe extern "C" fn(*mut c_void);
+
+/// TessPageIteratorLevel
+const RIL_TEXTLINE: c_int = 2;
+const RIL_WORD: c_int = 3;
+
+/// 逐词遍历识别结果 (ResultIterator) 所需的导出函数
+struct LayoutApi {
+    recognize: RecognizeFn,
+    get_iterator: GetIteratorFn,
+    /// ResultIterator -> PageIterator (同一对象的基类视图)
+    page_iterator: GetIteratorFn,
+    text: IterTextFn,
+    confidence: IterConfidenceFn,
+    bounding_box: IterBoxFn,
+    at_beginning_of: IterLevelFn,
+    next: IterLevelFn,
+    delete: IterDeleteFn,
+}
+
+struct Api {
+    handle: *mut c_void,
+    delete: DeleteFn,
+    set_image: SetImageFn,
+    get_text: GetUtf8TextFn,
+    delete_text: DeleteTextFn,
+    /// 平均置信度 (0~100)；旧版本动态库可能没有此导出
+    mean_conf: Option<MeanTextConfFn>,
+    /// 缺少任一导出时为 None，recognize_lines 退回整图一个词
+    layout: Option<LayoutApi>,
+}
+
+// TessBaseAPI 句柄不可并发使用，由外层 Mutex 保证同一时刻只有一个线程调用
+unsafe impl Send for Api {}
+
+impl Api {
+    fn load(cfg: &OcrConfig) -> Result<Self, String> {
+        unsafe {
+            let module = LoadLibraryW(&HSTRING::from(cfg.library.as_str()))
+                .map_err(|e| format!("无法加载 {}: {}", cfg.library, e))?;
+            type Farproc = unsafe extern "system" fn() -> isize;
+            let create = GetProcAddress(module, s!("TessBaseAPICreate"));
+            let delete = GetProcAddress(module, s!("TessBaseAPIDelete"));
+            let init = GetProcAddress(module, s!("TessBaseAPIInit3"));
+            let psm = GetProcAddress(module, s!("TessBaseAPISetPageSegMode"));
+            let set_image = GetProcAddress(module, s!("TessBaseAPISetImage"));
+            let get_text = GetProcAddress(module, s!("TessBaseAPIGetUTF8Text"));
+            let delete_text = GetProcAddress(module, s!("TessDeleteText"));
+            let mean_conf = GetProcAddress(module, s!("TessBaseAPIMeanTextConf"));
+            let layout = (|| {
+                Some(LayoutApi {
+                    recognize: std::mem::transmute::<Farproc, RecognizeFn>(GetProcAddress(module, s!("TessBaseAPIRecognize"))?),
+                    get_iterator: std::mem::transmute::<Farproc, GetIteratorFn>(GetProcAddress(module, s!("TessBaseAPIGetIterator"))?),
+                    page_iterator: std::mem::transmute::<Farproc, GetIteratorFn>(GetProcAddress(module, s!("TessResultIteratorGetPageIterator"))?),
+                    text: std::mem::transmute::<Farproc, IterTextFn>(GetProcAddress(module, s!("TessResultIteratorGetUTF8Text"))?),
+                    confidence: std::mem::transmute::<Farproc, IterConfidenceFn>(GetProcAddress(module, s!("TessResultIteratorConfidence"))?),
+                    bounding_box: std::mem::transmute::<Farproc, IterBoxFn>(GetProcAddress(module, s!("TessPageIteratorBoundingBox"))?),
+                    at_beginning_of: std::mem::transmute::<Farproc, IterLevelFn>(GetProcAddress(module, s!("TessPageIteratorIsAtBeginningOf"))?),
+                    next: std::mem::transmute::<Farproc, IterLevelFn>(GetProcAddress(module, s!("TessResultIteratorNext"))?),
+                    delete: std::mem::transmute::<Farproc, IterDeleteFn>(GetProcAddress(module, s!("TessResultIteratorDelete"))?),
+                })
+            })();
+            let (Some(create), Some(delete), Some(init), Some(psm), Some(set_image), Some(get_text), Some(delete_text)) =
+                (create, delete, init, psm, set_image, get_text, delete_text)
+            else {
+                return Err(format!("{} 缺少必要的导出函数 (需要 Tesseract 4 / 5 的 C API)", cfg.library));
+            };
+            let create = std::mem::transmute::<Farproc, CreateFn>(create);
+            let delete = std::mem::transmute::<Farproc, DeleteFn>(delete);
+            let init = std::mem::transmute::<Farproc, Init3Fn>(init);
+            let psm = std::mem::transmute::<Farproc, SetPageSegModeFn>(psm);
+
+            let handle = create();
+            if handle.is_null() {
+                return Err("无法创建 TessBaseAPI".to_string());
+            }
+            let datapath = CString::new(cfg.tessdata.as_str()).map_err(|e| e.to_string())?;
+            let language = CString::new(cfg.language.as_str()).map_err(|e| e.to_string())?;
+            if init(handle, datapath.as_ptr(), language.as_ptr()) != 0 {
+                delete(handle);
+                return Err(format!("无法加载语言包 {} (目录 {})", cfg.language, cfg.tessdata));
+            }
+            psm(handle, cfg.psm);
+            Ok(Self {
+                handle,
+                delete,
+                set_image: std::mem::transmute::<Farproc, SetImageFn>(set_image),
+                get_text: std::mem::transmute::<Farproc, GetUtf8TextFn>(get_text),
+                delete_text: std::mem::transmute::<Farproc, DeleteTextFn>(delete_text),
+                mean_conf: mean_conf.map(|f| std::mem::transmute::<Farproc, MeanTextConfFn>(f)),
+                layout,
+            })
+        }
+    }
+}
+
+impl Api {
+    fn read(&self, img: &DynamicImage) -> Result<(String, Option<f32>), String> {
+        let gray = img.to_luma8();
+        let (w, h) = gray.dimensions();
+        unsafe {
+            (self.set_image)(self.handle, gray.as_raw().as_ptr(), w as c_int, h as c_int, 1, w as c_int);
+            let text = (self.get_text)(self.handle);
+            if text.is_null() {
+                return Err("Tesseract 识别失败".to_string());
+            }
+            let result = CStr::from_ptr(text).to_string_lossy().replace(|c: char| c.is_whitespace(), "");
+            (self.delete_text)(text);
+            // 必须在 GetUTF8Text 之后调用，否则会重新识别一遍
+            let confidence = self.mean_conf.map(|f| f(self.handle).clamp(0, 100) as f32 / 100.0);
+            Ok((result, confidence))
+        }
+    }
+}
+
+/// BCP-47 标签到 Tesseract 语言包名；其余原样使用 (也可直接写 "eng" 等包名)
+fn tess_language(lang: &str) -> &str {
+    match lang {
+        "en" | "en-US" => "eng",
+        "zh-Hans" | "zh-CN" => "chi_sim",
+        "zh-Hant" | "zh-TW" => "chi_tra",
+        "ja" => "jpn",
+        other => other,
+    }
+}
+
+impl Drop for Api {
+    fn drop(&mut self) {
+        unsafe { (self.delete)(self.handle) }
+    }
+}
+
+// ==========================================
+// 2. 后端
+// ==========================================
+
+pub struct TesseractOcr {
+    api: Mutex<Api>,
+    cfg: OcrConfig,
+    /// 按语言懒加载的其它实例；None 表示该语言包加载失败
+    by_lang: Mutex<HashMap<String, Option<Api>>>,
+}
+
+impl TesseractOcr {
+    pub fn new(cfg: &OcrConfig) -> Result<Self, String> {
+        Ok(Self { api: Mutex::new(Api::load(cfg)?), cfg: cfg.clone(), by_lang: Mutex::new(HashMap::new()) })
+    }
+}
+
+impl OcrBackend for TesseractOcr {
+    fn name(&self) -> &'static str { "tesseract" }
+
+    fn recognize(&self, img: &DynamicImage) -> Result<String, String> {
+        self.recognize_scored(img).map(|(text, _)| text)
+    }
+
+    fn recognize_scored(&self, img: &DynamicImage) -> Result<(String, Option<f32>), String> {
+        self.api.lock().map_err(|_| "Tesseract 状态异常".to_string())?.read(img)
+    }
+
+    /// 与 [ocr] language 相同时使用主实例，否则首次使用时按该语言再加载一个实例
+    fn recognize_lang(&self, img: &DynamicImage, lang: &str) -> Result<(String, Option<f32>), String> {
+        let language = tess_language(lang);
+        if language == self.cfg.language {
+            return self.recognize_scored(img);
+        }
+        let mut apis = self.by_lang.lock().map_err(|_| "Tesseract 状态异常".to_string())?;
+        let api = apis.entry(language.to_string()).or_insert_with(|| {
+            let cfg = OcrConfig { language: language.to_string(), ..self.cfg.clone() };
+            Api::load(&cfg).map_err(|e| println!("⚠️ Tesseract 无法加载语言 [{}]: {}，使用 {}", language, e, self.cfg.language)).ok()
+        });
+        match api {
+            Some(api) => api.read(img),
+            None => {
+                drop(apis);
+                self.recognize_scored(img)
+            }
+        }
+    }
+
+    fn recognize_lines(&self, img: &DynamicImage) -> Result<Vec<OcrLine>, String> {
+        let gray = img.to_luma8();
+        let (w, h) = gray.dimensions();
+        let api = self.api.lock().map_err(|_| "Tesseract 状态异常".to_string())?;
+        let Some(layout) = &api.layout else {
+            drop(api);
+            let (text, confidence) = self.recognize_scored(img)?;
+            let rect = [0, 0, w as i32, h as i32];
+            return Ok(if text.is_empty() { Vec::new() } else { vec![OcrLine::from_words(vec![OcrWord { text, rect, confidence }])] });
+        };
+        unsafe {
+            (api.set_image)(api.handle, gray.as_raw().as_ptr(), w as c_int, h as c_int, 1, w as c_int);
+            if (layout.recognize)(api.handle, std::ptr::null_mut()) != 0 {
+                return Err("Tesseract 识别失败".to_string());
+            }
+            let iter = (layout.get_iterator)(api.handle);
+            if iter.is_null() {
+                return Ok(Vec::new());
+            }
+            let page = (layout.page_iterator)(iter);
+            let (mut lines, mut words) = (Vec::new(), Vec::new());
+            loop {
+                if (layout.at_beginning_of)(page, RIL_TEXTLINE) != 0 && !words.is_empty() {
+                    lines.push(OcrLine::from_words(std::mem::take(&mut words)));
+                }
+                let text = (layout.text)(iter, RIL_WORD);
+                if !text.is_null() {
+                    let word = CStr::from_ptr(text).to_string_lossy().replace(|c: char| c.is_whitespace(), "");
+                    (api.delete_text)(text);
+                    let (mut x1, mut y1, mut x2, mut y2) = (0, 0, 0, 0);
+                    if !word.is_empty() && (layout.bounding_box)(page, RIL_WORD, &mut x1, &mut y1, &mut x2, &mut y2) != 0 {
+                        let confidence = ((layout.confidence)(iter, RIL_WORD) / 100.0).clamp(0.0, 1.0);
+                        words.push(OcrWord { text: word, rect: [x1, y1, x2, y2], confidence: Some(confidence) });
+                    }
+                }
+                if (layout.next)(iter, RIL_WORD) == 0 {
+                    break;
+                }
+            }
+            (layout.delete)(iter);
+            if !words.is_empty() {
+                lines.push(OcrLine::from_words(words));
+            }
+            Ok(lines)
+        }
+    }
+}
@@ -1,7 +1,11 @@
-use crate::human::HumanDriver;
+use crate::checkpoint::TaskCheckpoint;
+use crate::detector::Detection;
+use crate::hardware::{DriverError, MouseButtons};
+use crate::human::{ComboExecutor, ComboStep, HumanDriver};
 use crate::nav::NavEngine;
 use regex::Regex;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::sync::{Arc, Mutex};
@@ -20,8 +24,19 @@ pub enum PrepAction {
     KeyUpAll,
     Wait { ms: u64 },
     Log { msg: String },
+    /// 按住中键 (right = true 时为右键) 拖动镜头 (dx, dy) 像素
+    Pan {
+        dx: i32,
+        dy: i32,
+        #[serde(default)]
+        right: bool,
+        #[serde(default = "default_pan_ms")]
+        ms: u64,
+    },
 }
 
+fn default_pan_ms() -> u64 { 600 }
+
 #[derive(Deserialize, Debug, Clone)]
 #[serde(tag = "type")]
 pub enum InitAction {
@@ -169,6 +184,18 @@ fn get_hid_code(c: char) -> u8 {
     }
 }
 
+/// 塔防进度检查点：已确认的波次与已完成的建造/升级/拆除
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct TdCheckpoint {
+    wave: i32,
+    placed_uids: Vec<usize>,
+    completed_upgrade_keys: Vec<String>,
+    completed_demolish_uids: Vec<usize>,
+}
+
+/// 超过该时长的检查点视为上一局的残留
+const TD_CHECKPOINT_MAX_AGE: Duration = Duration::from_secs(2 * 3600);
+
 // ==========================================
 // 2. 塔防模块实现
 // ==========================================
@@ -194,6 +221,10 @@ pub struct TowerDefenseApp {
 
     camera_offset_y: f32,
     move_speed: f32,
+
+    checkpoint: TaskCheckpoint,
+    /// 首个输入失败的原因；记录后跳过后续动作，由监控循环中止本局
+    driver_error: RefCell<Option<DriverError>>,
 }
 
 impl TowerDefenseApp {
@@ -215,9 +246,52 @@ impl TowerDefenseApp {
             active_loadout: Vec::new(),
             camera_offset_y: 0.0,
             move_speed: 300.0,
+            checkpoint: TaskCheckpoint::disabled(),
+            driver_error: RefCell::new(None),
         }
     }
 
+    /// 启用进度检查点 (每波结束后记录，崩溃后可从中途恢复)
+    pub fn set_checkpoint(&mut self, checkpoint: TaskCheckpoint) {
+        self.checkpoint = checkpoint;
+    }
+
+    /// 在拟人驱动上执行一组动作，返回是否成功；已有输入失败时直接跳过
+    fn act(&self, f: impl FnOnce(&mut HumanDriver) -> Result<(), DriverError>) -> bool {
+        if self.driver_error.borrow().is_some() {
+            return false;
+        }
+        let result = match self.driver.lock() {
+            Ok(mut d) => f(&mut d),
+            Err(_) => Err(DriverError::Io("驱动锁中毒".to_string())),
+        };
+        match result {
+            Ok(()) => true,
+            Err(e) => {
+                println!("❌ [TD] 输入失败，停止后续操作: {}", e);
+                *self.driver_error.borrow_mut() = Some(e);
+                false
+            }
+        }
+    }
+
+    fn driver_failed(&self) -> bool {
+        self.driver_error.borrow().is_some()
+    }
+
+    /// 取出记录的输入错误 (监控循环据此中止)
+    fn check_driver(&self) -> Result<(), DriverError> {
+        match self.driver_error.borrow_mut().take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// 最近一次确认的波次 (用于统计本局推进到第几波)
+    pub fn last_confirmed_wave(&self) -> i32 {
+        self.last_confirmed_wave
+    }
+
     pub fn load_strategy(&mut self, path: &str) {
         if let Ok(c) = fs::read_to_string(path) {
             if let Ok(data) = serde_json::from_str::<MapBuildingsExport>(&c) {
@@ -239,34 +313,18 @@ impl TowerDefenseApp {
     pub fn recognize_wave_status(&self, rect: [i32; 4], use_tab: bool) -> Option<WaveStatus> {
         const KEY_TAB: u8 = 0x2B;
         if use_tab {
-            if let Ok(driver) = self.driver.lock() {
-                if let Ok(mut dev) = driver.device.lock() {
-                    dev.key_down(KEY_TAB, 0);
-                }
-            }
+            self.act(|d| d.raw(|dev| dev.key_down(KEY_TAB, 0)));
             thread::sleep(Duration::from_millis(500));
         }
 
         let text: String = self.nav.ocr_area(rect);
 
         if use_tab {
-            if let Ok(driver) = self.driver.lock() {
-                if let Ok(mut dev) = driver.device.lock() {
-                    dev.key_up();
-                }
-            }
+            self.act(|d| d.raw(|dev| dev.key_up()));
             thread::sleep(Duration::from_millis(500));
-            if let Ok(driver) = self.driver.lock() {
-                if let Ok(mut dev) = driver.device.lock() {
-                    dev.key_down(KEY_TAB, 0);
-                }
-            }
+            self.act(|d| d.raw(|dev| dev.key_down(KEY_TAB, 0)));
             thread::sleep(Duration::from_millis(100));
-            if let Ok(driver) = self.driver.lock() {
-                if let Ok(mut dev) = driver.device.lock() {
-                    dev.key_up();
-                }
-            }
+            self.act(|d| d.raw(|dev| dev.key_up()));
         }
 
         if text.is_empty() {
@@ -297,6 +355,13 @@ impl TowerDefenseApp {
         Some(WaveStatus { current_wave: val })
     }
 
+    /// 画面上标签为 label 的动态实体 (如 "enemy" / "drop")，按得分从高到低；未启用目标检测时为空
+    pub fn detect_entities(&self, label: &str) -> Vec<Detection> {
+        let mut found: Vec<Detection> = self.nav.detect_all().into_iter().filter(|d| d.label == label).collect();
+        found.sort_by(|a, b| b.score.total_cmp(&a.score));
+        found
+    }
+
     fn validate_wave_transition(&mut self, detected_wave: i32) -> bool {
         let now = Instant::now();
         let elapsed = now.duration_since(self.last_wave_change_time).as_secs();
@@ -330,6 +395,9 @@ impl TowerDefenseApp {
     }
 
     pub fn execute_wave_phase(&mut self, wave: i32, is_late: bool) {
+        if self.driver_failed() {
+            return;
+        }
         let phase_name = if is_late { "后期" } else { "前期" };
         println!(
             "🚀 优化执行第 {} 波 [{}] (拆除优先模式)...",
@@ -404,7 +472,7 @@ impl TowerDefenseApp {
                 "🏗️ [Step 2] 正在执行建造与升级任务 ({}个)...",
                 build_upgrade_tasks.len()
             );
-            build_upgrade_tasks.sort_by(|a, b| a.priority.cmp(&b.priority));
+            build_upgrade_tasks.sort_by_key(|t| t.priority);
             self.dispatch_tasks_by_region(build_upgrade_tasks);
         }
     }
@@ -492,29 +560,24 @@ impl TowerDefenseApp {
         let screen_x = (map_x - 0.0).clamp(sz_x1 as f32, sz_x2 as f32);
         let screen_y = (map_y - self.camera_offset_y).clamp(sz_y1 as f32, sz_y2 as f32);
 
-        if let Ok(mut driver) = self.driver.lock() {
-            // 1. 移动到位后强制停顿，确保准星彻底对齐格子
-            driver.move_to_humanly(screen_x as u16, screen_y as u16, 0.4);
-            thread::sleep(Duration::from_millis(50));
-
-            // 2. 点击选中 (增加 hold 时间到 60ms，防止点击过快游戏未响应)
-            driver.click_humanly(true, false, 60); 
-            
-            // 3. 等待选中框出现的延迟 (从 150ms 增加到 250ms)
-            thread::sleep(Duration::from_millis(150));
-
-            // 4. 🔥 双击 'E' 拆除 (Double Tap)
-            // 第一下 E：执行拆除
-            driver.key_click('e');
-            
-            // 间隔 100ms
-            thread::sleep(Duration::from_millis(100));
-            
-            // 第二下 E：保险措施 (防止第一下被吞，或者部分陷阱需要二次确认)
-            driver.key_click('e');
-        }
+        // 落稳 -> 按住约 60ms 选中 (防止点击过快游戏未响应) -> 等选中框出现 -> 🔥 双击 'E' 拆除
+        // 第二下 E 是保险措施 (防止第一下被吞，或者部分陷阱需要二次确认)
+        let demolish = ComboExecutor::new(vec![
+            ComboStep::Gap { min_ms: 40, max_ms: 70 },
+            ComboStep::Click { right: false, hold_ms: 60 },
+            ComboStep::Cast { ms: 150, jitter: 0.3 },
+            ComboStep::Key { key: 'e', hold_ms: 0 },
+            ComboStep::Gap { min_ms: 80, max_ms: 130 },
+            ComboStep::Key { key: 'e', hold_ms: 0 },
+        ]);
+        let done = self.act(|driver| {
+            driver.move_to_humanly(screen_x as u16, screen_y as u16, 0.4)?;
+            demolish.run(driver).map(|_| ())
+        });
         
-        self.completed_demolish_uids.insert(uid);
+        if done {
+            self.completed_demolish_uids.insert(uid);
+        }
         
         // 动作后摇 (稍微缩短一点，因为我们已经多按了一次E)
         thread::sleep(Duration::from_millis(200));
@@ -536,9 +599,9 @@ impl TowerDefenseApp {
         let screen_y = (map_y - self.camera_offset_y).clamp(sz_y1 as f32, sz_y2 as f32);
         let key = self.get_trap_key(name);
 
-        if let Ok(mut d) = self.driver.lock() {
+        let done = self.act(|d| {
             // 1. 移动鼠标
-            d.move_to_humanly(screen_x as u16, screen_y as u16, 0.35);
+            d.move_to_humanly(screen_x as u16, screen_y as u16, 0.35)?;
 
             // [稳定性] 移动到位后强制停顿，等待鼠标“落稳”
             thread::sleep(Duration::from_millis(50));
@@ -549,19 +612,20 @@ impl TowerDefenseApp {
             if screen_moved || last_key.is_none() {
                 let swap_key = if key == '4' { '5' } else { '4' };
                 
-                // 执行：目标键 -> 干扰键 -> 目标键 (强刷状态)
-                d.key_click(key);
-                thread::sleep(Duration::from_millis(120));
-                d.key_click(swap_key);
-                thread::sleep(Duration::from_millis(120));
-                d.key_click(key);
-
-                // 等待陷阱虚影完全浮现
-                thread::sleep(Duration::from_millis(250));
+                // 执行：目标键 -> 干扰键 -> 目标键 (强刷状态)，然后等待陷阱虚影完全浮现
+                ComboExecutor::new(vec![
+                    ComboStep::Key { key, hold_ms: 0 },
+                    ComboStep::Gap { min_ms: 100, max_ms: 150 },
+                    ComboStep::Key { key: swap_key, hold_ms: 0 },
+                    ComboStep::Gap { min_ms: 100, max_ms: 150 },
+                    ComboStep::Key { key, hold_ms: 0 },
+                    ComboStep::Cast { ms: 250, jitter: 0.2 },
+                ])
+                .run(d)?;
                 *last_key = Some(key);
             } else if Some(key) != *last_key {
                 // 如果不是第一座，且类型变了（原地换塔），则单次按键切换
-                d.key_click(key);
+                d.key_click(key)?;
                 *last_key = Some(key);
                 thread::sleep(Duration::from_millis(250));
             } else {
@@ -570,9 +634,11 @@ impl TowerDefenseApp {
             }
 
             // 执行双击放置
-            d.double_click_humanly(true, false, 150);
+            d.double_click_humanly(MouseButtons::LEFT)
+        });
+        if done {
+            self.placed_uids.insert(uid);
         }
-        self.placed_uids.insert(uid);
 
         // 动作后摇
         thread::sleep(Duration::from_millis(250));
@@ -580,12 +646,11 @@ impl TowerDefenseApp {
 
     fn execute_single_upgrade(&mut self, u: &UpgradeEvent) {
         let key = self.get_trap_key(&u.building_name);
-        if let Ok(mut d) = self.driver.lock() {
-            println!("   -> 长按 '{}' (800ms) 以升级: {}", key, u.building_name);
-            d.key_hold(key, 1500);
+        println!("   -> 长按 '{}' (800ms) 以升级: {}", key, u.building_name);
+        if self.act(|d| d.key_hold(key, 1500)) {
+            let key_str = format!("{}-{}-{}", u.building_name, u.wave_num, u.is_late);
+            self.completed_upgrade_keys.insert(key_str);
         }
-        let key_str = format!("{}-{}-{}", u.building_name, u.wave_num, u.is_late);
-        self.completed_upgrade_keys.insert(key_str);
         thread::sleep(Duration::from_millis(400));
     }
 
@@ -593,11 +658,9 @@ impl TowerDefenseApp {
         let meta = self.map_meta.as_ref().unwrap();
         let max_scroll_y = (meta.bottom - self.config.screen_height).max(0.0);
 
-        if let Ok(mut human) = self.driver.lock() {
-            let key = if top { 'w' } else { 's' };
-            println!("🔄 强制归零: {}", if top { "顶部" } else { "底部" });
-            human.key_hold(key, 2500);
-        }
+        let key = if top { 'w' } else { 's' };
+        println!("🔄 强制归零: {}", if top { "顶部" } else { "底部" });
+        self.act(|human| human.key_hold(key, 2500));
         self.camera_offset_y = if top { 0.0 } else { max_scroll_y };
         thread::sleep(Duration::from_millis(500));
     }
@@ -615,8 +678,8 @@ impl TowerDefenseApp {
         let units = (raw_ms + time_resolution_ms / 2) / time_resolution_ms;
         let final_ms = units.max(1) * time_resolution_ms;
 
-        if let Ok(mut human) = self.driver.lock() {
-            human.key_hold(direction, final_ms);
+        if !self.act(|human| human.key_hold(direction, final_ms)) {
+            return 0.0;
         }
         (final_ms as f32 / 1000.0) * self.move_speed
     }
@@ -677,25 +740,25 @@ impl TowerDefenseApp {
 
     pub fn setup_view(&mut self) {
         println!("🔭 对齐左上角边界...");
-        if let Ok(mut human) = self.driver.lock() {
-            human.key_click('o');
+        self.act(|human| {
+            human.key_click('o')?;
             thread::sleep(Duration::from_secs(2));
             for _ in 1..=4 {
                 for _ in 0..10 {
-                    human.mouse_scroll(-120);
+                    human.mouse_scroll(-120)?;
                     thread::sleep(Duration::from_millis(30));
                 }
                 thread::sleep(Duration::from_millis(100));
             }
             for _ in 1..=2 {
-                human.key_hold('w', 200);
+                human.key_hold('w', 200)?;
                 thread::sleep(Duration::from_millis(50));
-                human.key_hold('a', 200);
+                human.key_hold('a', 200)?;
                 thread::sleep(Duration::from_millis(50));
             }
-            human.key_hold('w', 200);
-            human.key_hold('a', 200);
-        }
+            human.key_hold('w', 200)?;
+            human.key_hold('a', 200)
+        });
         self.camera_offset_y = 0.0;
     }
 
@@ -705,46 +768,46 @@ impl TowerDefenseApp {
         if let Some(meta) = &self.map_meta {
             if !meta.prep_actions.is_empty() {
                 println!("   -> 加载自定义战术动作 ({} 步)", meta.prep_actions.len());
-                if let Ok(human) = self.driver.lock() {
-                    if let Ok(mut dev) = human.device.lock() {
-                        for action in &meta.prep_actions {
-                            match action {
-                                PrepAction::KeyDown { key } => {
-                                    let code = get_hid_code(*key);
-                                    if code != 0 {
-                                        dev.key_down(code, 0);
-                                    }
-                                }
-                                PrepAction::KeyUpAll => {
-                                    dev.key_up();
-                                }
-                                PrepAction::Wait { ms } => {
-                                    drop(dev);
-                                    thread::sleep(Duration::from_millis(*ms));
-                                    dev = human.device.lock().unwrap();
-                                }
-                                PrepAction::Log { msg } => {
-                                    println!("   [Prep] {}", msg);
+                self.act(|human| {
+                    // 连续的 KeyDown 累积为组合键 (如 W+A 斜向移动)
+                    let mut held: Vec<u8> = Vec::new();
+                    for action in &meta.prep_actions {
+                        match action {
+                            PrepAction::KeyDown { key } => {
+                                let code = get_hid_code(*key);
+                                if code != 0 && !held.contains(&code) {
+                                    held.push(code);
+                                    human.raw(|dev| dev.keys_down(&held, 0))?;
                                 }
                             }
+                            PrepAction::KeyUpAll => {
+                                held.clear();
+                                human.raw(|dev| dev.key_up())?;
+                            }
+                            PrepAction::Wait { ms } => {
+                                thread::sleep(Duration::from_millis(*ms));
+                            }
+                            PrepAction::Log { msg } => {
+                                println!("   [Prep] {}", msg);
+                            }
+                            PrepAction::Pan { dx, dy, right, ms } => {
+                                let button = if *right { MouseButtons::RIGHT } else { MouseButtons::MIDDLE };
+                                human.pan_humanly(button, *dx, *dy, *ms as f32 / 1000.0)?;
+                            }
                         }
-                        dev.key_up();
                     }
-                }
+                    human.raw(|dev| dev.key_up())
+                });
             }
         }
 
-        if let Ok(mut human) = self.driver.lock() {
-            human.key_click('n');
-            thread::sleep(Duration::from_millis(500));
-        }
+        self.act(|human| human.key_click('n'));
+        thread::sleep(Duration::from_millis(500));
 
         self.select_loadout();
 
-        if let Ok(mut human) = self.driver.lock() {
-            human.key_click('n');
-            thread::sleep(Duration::from_millis(500));
-        }
+        self.act(|human| human.key_click('n'));
+        thread::sleep(Duration::from_millis(500));
     }
 
     pub fn select_loadout(&self) {
@@ -761,9 +824,9 @@ impl TowerDefenseApp {
                     _ => (172, 294),
                 };
 
-                if let Ok(mut d) = self.driver.lock() {
-                    d.move_to_humanly(tab_x, tab_y, 0.4);
-                    d.click_humanly(true, false, 0);
+                self.act(|d| {
+                    d.move_to_humanly(tab_x, tab_y, 0.4)?;
+                    d.click_humanly(MouseButtons::LEFT, 0)?;
                     thread::sleep(Duration::from_millis(350));
 
                     let col = config.grid_index[0];
@@ -771,9 +834,9 @@ impl TowerDefenseApp {
                     let target_x = GRID_START_X + col * GRID_STEP_X;
                     let target_y = GRID_START_Y + row * GRID_STEP_Y;
 
-                    d.move_to_humanly(target_x as u16, target_y as u16, 0.4);
-                    d.click_humanly(true, false, 0);
-                }
+                    d.move_to_humanly(target_x as u16, target_y as u16, 0.4)?;
+                    d.click_humanly(MouseButtons::LEFT, 0)
+                });
                 thread::sleep(Duration::from_millis(400));
             } else {
                 println!("⚠️ [Config Error] 未找到陷阱配置: {}", name);
@@ -809,7 +872,28 @@ impl TowerDefenseApp {
         }
     }
 
-    pub fn run(&mut self, terrain_p: &str, strategy_p: &str, trap_p: &str) {
+    /// 执行一整局；输入设备失败时中止并返回错误 (检查点保留，设备恢复后可续跑)
+    pub fn run(&mut self, terrain_p: &str, strategy_p: &str, trap_p: &str) -> Result<(), DriverError> {
+        self.prepare(terrain_p, strategy_p, trap_p);
+        if !self.try_resume() {
+            self.checkpoint.clear();
+            self.start_battle()?;
+        }
+        self.monitor_battle()
+    }
+
+    /// 仅当检查点有效且游戏仍在该局战斗中时接管，返回是否成功恢复
+    pub fn resume(&mut self, terrain_p: &str, strategy_p: &str, trap_p: &str) -> Result<bool, DriverError> {
+        self.prepare(terrain_p, strategy_p, trap_p);
+        if !self.try_resume() {
+            self.check_driver()?;
+            return Ok(false);
+        }
+        self.monitor_battle()?;
+        Ok(true)
+    }
+
+    fn prepare(&mut self, terrain_p: &str, strategy_p: &str, trap_p: &str) {
         self.load_map_terrain(terrain_p);
         self.load_trap_config(trap_p);
         self.load_strategy(strategy_p);
@@ -836,33 +920,90 @@ impl TowerDefenseApp {
             println!("📋 自动分析策略，生成装备列表: {:?}", derived_loadout);
         }
         self.active_loadout = derived_loadout;
+    }
+
+    /// 读取检查点并重新确认场景：TAB 面板中的波次不低于检查点时才恢复
+    fn try_resume(&mut self) -> bool {
+        let Some(cp) = self.checkpoint.load::<TdCheckpoint>(TD_CHECKPOINT_MAX_AGE) else {
+            return false;
+        };
+        println!("💾 发现第 {} 波的检查点，正在确认是否仍在该局战斗中...", cp.wave);
+
+        let detected = self
+            .recognize_wave_status(self.config.hud_wave_loop_rect, true)
+            .map(|s| s.current_wave);
+        let current = match detected {
+            Some(w) if w >= cp.wave => w,
+            _ => {
+                println!("⚠️ 场景校验失败 (当前波次: {:?})，检查点无法使用", detected);
+                return false;
+            }
+        };
+
+        println!("♻️ 从检查点恢复: 已完成第 {} 波，当前第 {} 波", cp.wave, current);
+        self.placed_uids = cp.placed_uids.into_iter().collect();
+        self.completed_upgrade_keys = cp.completed_upgrade_keys.into_iter().collect();
+        self.completed_demolish_uids = cp.completed_demolish_uids.into_iter().collect();
+
+        // 中断期间镜头位置未知，重新对齐
+        self.setup_view();
 
-        if let Ok(mut human) = self.driver.lock() {
-            println!("👆 点击游戏入口...");
-            human.move_to_humanly(1700, 950, 0.5);
-            human.click_humanly(true, false, 0);
-            human.move_to_humanly(1110, 670, 0.5);
-            human.click_humanly(true, false, 0);
+        // 补做中断期间错过的波次，当前波次交给监控循环处理
+        for wave in (cp.wave + 1)..current {
+            println!("⏩ 补做错过的第 {} 波", wave);
+            self.execute_wave_phase(wave, false);
+            self.execute_wave_phase(wave, true);
         }
+        self.last_confirmed_wave = if current > cp.wave { current - 1 } else { current };
+        self.last_wave_change_time = Instant::now()
+            .checked_sub(Duration::from_secs(60))
+            .unwrap_or_else(Instant::now);
+        self.save_checkpoint();
+        true
+    }
+
+    fn save_checkpoint(&self) {
+        self.checkpoint.save(&TdCheckpoint {
+            wave: self.last_confirmed_wave,
+            placed_uids: self.placed_uids.iter().copied().collect(),
+            completed_upgrade_keys: self.completed_upgrade_keys.iter().cloned().collect(),
+            completed_demolish_uids: self.completed_demolish_uids.iter().copied().collect(),
+        });
+    }
+
+    /// 从大厅进入战斗并完成赛前准备
+    fn start_battle(&mut self) -> Result<(), DriverError> {
+        println!("👆 点击游戏入口...");
+        self.act(|human| {
+            human.move_to_humanly(1700, 950, 0.5)?;
+            human.click_humanly(MouseButtons::LEFT, 0)?;
+            human.move_to_humanly(1110, 670, 0.5)?;
+            human.click_humanly(MouseButtons::LEFT, 0)
+        });
+        self.check_driver()?;
 
         println!("⏳ 等待战斗开始...");
-        loop {
-            if let Some(status) = self.recognize_wave_status(self.config.hud_check_rect, false) {
-                if status.current_wave > 0 {
-                    println!("🎮 战斗开始! 初始波次: {}", status.current_wave);
-                    self.last_wave_change_time = Instant::now();
-                    break;
-                }
-            }
-            thread::sleep(Duration::from_millis(1000));
-        }
+        let mut initial_wave = 0;
+        let _ = self.nav.wait_until(Duration::MAX, Duration::from_millis(1000), || {
+            initial_wave = self
+                .recognize_wave_status(self.config.hud_check_rect, false)
+                .map_or(0, |status| status.current_wave);
+            initial_wave > 0
+        });
+        println!("🎮 战斗开始! 初始波次: {}", initial_wave);
+        self.last_wave_change_time = Instant::now();
 
         self.execute_prep_logic();
         self.setup_view();
+        self.check_driver()
+    }
 
+    fn monitor_battle(&mut self) -> Result<(), DriverError> {
         println!("🤖 自动化监控中...");
         let mut no_wave_count = 0;
         loop {
+            self.check_driver()?;
+
             // 尝试检测波次 (带 Tab 切换)
             // 我们把结果存下来，以便处理 "未检测到" 的情况
             let wave_status_opt = self.recognize_wave_status(self.config.hud_wave_loop_rect, true);
@@ -872,52 +1013,63 @@ impl TowerDefenseApp {
                 no_wave_count = 0; // 重置计数器
                 if self.validate_wave_transition(status.current_wave) {
                     let current_wave = status.current_wave;
+                    if self.nav.has_detector() {
+                        println!("🎯 第 {} 波开始前场上敌人: {} 个", current_wave, self.detect_entities("enemy").len());
+                    }
                     self.execute_wave_phase(current_wave, false);
                     println!("🔔 波次 {} 前期完成，按 G 开战", current_wave);
-                    if let Ok(mut d) = self.driver.lock() {
-                        d.key_click('g');
-                    }
+                    self.act(|d| d.key_click('g'));
                     thread::sleep(Duration::from_secs(1));
                     self.execute_wave_phase(current_wave, true);
+                    // 本波动作未全部送达时不记录进度，恢复后会重做该波
+                    if !self.driver_failed() {
+                        self.save_checkpoint();
+                    }
                 }
             } else {
                 // === 情况 B: 未检测到波次 (可能是结算界面) ===
+                // 已经识别到地图中的界面 (结算 / 大厅等)，说明本局已结束
+                if let Some((id, _)) = self.nav.detect_current_scene() {
+                    println!("🏁 识别到界面 [{}]，判定为游戏结束。", id);
+                    self.checkpoint.clear();
+                    return Ok(());
+                }
                 no_wave_count += 1;
                 println!(
                     "⚠️ [Monitor] 未检测到波次信息 ({}/2)，尝试跳过结算...",
                     no_wave_count
                 );
 
-                if let Ok(mut d) = self.driver.lock() {
-                    println!("   -> 点击空格 (Space) + 双击 ESC");
-
+                println!("   -> 点击空格 (Space) + 双击 ESC");
+                self.act(|d| {
                     // 直接操作底层设备发送 HID 码 0x29 (ESC)
-                    if let Ok(mut dev) = d.device.lock() {
+                    d.raw(|dev| {
                         // 第一次 ESC
-                        dev.key_down(0x29, 0);
+                        dev.key_down(0x29, 0)?;
                         thread::sleep(Duration::from_millis(100)); // 按下持续时间
-                        dev.key_up();
-
-                        thread::sleep(Duration::from_millis(300)); // 两次按键间隔
-                    }
+                        dev.key_up()
+                    })?;
+                    thread::sleep(Duration::from_millis(300)); // 两次按键间隔
 
                     // 点击空格 (跳过结算动画)
-                    d.key_click(' ');
+                    d.key_click(' ')?;
                     thread::sleep(Duration::from_millis(500));
 
-                    if let Ok(mut dev) = d.device.lock() {
+                    d.raw(|dev| {
                         // 第二次 ESC
-                        dev.key_down(0x29, 0);
+                        dev.key_down(0x29, 0)?;
                         thread::sleep(Duration::from_millis(100));
-                        dev.key_up();
-                    }
-                }
+                        dev.key_up()
+                    })
+                });
+                self.check_driver()?;
 
                 // 2. 检查退出条件
                 if no_wave_count >= 3 {
                     println!("🏁 连续 2 次未检测到波次，判定为游戏结束。");
                     println!("🔄 退出当前循环，返回主程序...");
-                    break; // 跳出 loop，函数结束，控制权交还给 main 的 loop
+                    self.checkpoint.clear();
+                    return Ok(()); // 跳出 loop，函数结束，控制权交还给 main 的 loop
                 }
             }
 
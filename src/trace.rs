@@ -0,0 +1,244 @@
+// src/trace.rs
+use crate::hardware::{
+    DriverCaps, DriverError, DriverEventCallback, DriverInfo, InputDriver, LatencyMetrics, MouseButtons,
+};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{LineWriter, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// ==========================================
+// 1. 轨迹格式 (JSONL，每行一条指令)
+// ==========================================
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type")]
+pub enum TraceCommand {
+    /// 文件头：录制开始的本地时间，便于对照 "03:12 误点" 之类的反馈
+    Start { at: String },
+    MouseAbs { x: u16, y: u16 },
+    MouseMove { dx: i32, dy: i32, wheel: i8 },
+    MouseDown { buttons: u8 },
+    MouseUp,
+    KeyDown { keycode: u8, modifier: u8 },
+    KeyUp,
+    KeysDown { keycodes: Vec<u8>, modifier: u8 },
+    SwitchIdentity { index: u8 },
+    MouseHScroll { delta: i8 },
+    TypeText { text: String },
+    Custom { event_type: u8, payload: [u8; 6], delay: u16 },
+    /// 敏感输入 (账号密码) 的占位：一段连续的按键只记一条，不含按键内容，回放时跳过
+    Redacted,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TraceEntry {
+    /// 距录制开始的毫秒数
+    pub t_ms: u64,
+    #[serde(flatten)]
+    pub cmd: TraceCommand,
+}
+
+impl TraceCommand {
+    /// 在指定驱动上执行这条指令
+    pub(crate) fn apply(&self, driver: &mut dyn InputDriver) -> Result<(), DriverError> {
+        match self {
+            TraceCommand::Start { .. } | TraceCommand::Redacted => Ok(()),
+            TraceCommand::MouseAbs { x, y } => driver.mouse_abs(*x, *y),
+            TraceCommand::MouseMove { dx, dy, wheel } => driver.mouse_move(*dx, *dy, *wheel),
+            TraceCommand::MouseDown { buttons } => driver.mouse_down(MouseButtons::from_bits_truncate(*buttons)),
+            TraceCommand::MouseUp => driver.mouse_up(),
+            TraceCommand::KeyDown { keycode, modifier } => driver.key_down(*keycode, *modifier),
+            TraceCommand::KeyUp => driver.key_up(),
+            TraceCommand::KeysDown { keycodes, modifier } => driver.keys_down(keycodes, *modifier),
+            TraceCommand::SwitchIdentity { index } => driver.switch_identity(*index),
+            TraceCommand::MouseHScroll { delta } => driver.mouse_hscroll(*delta),
+            TraceCommand::TypeText { text } => driver.type_text(text),
+            TraceCommand::Custom { event_type, payload, delay } => driver.send_custom(*event_type, *payload, *delay),
+        }
+    }
+}
+
+// ==========================================
+// 2. 录制驱动 (装饰器)
+// ==========================================
+
+/// 把经过的每条指令透传给内部驱动，同时以 JSONL 追加写入轨迹文件
+/// 写盘失败只提示一次并停止录制，不影响实际输入
+pub struct RecordingDriver {
+    inner: Box<dyn InputDriver>,
+    out: Option<LineWriter<File>>,
+    started: Instant,
+    /// 上一条写入的是 Redacted 占位 (同一段敏感输入不再重复写)
+    redacting: bool,
+}
+
+/// 正在输入敏感内容的层数；大于 0 时所有录制驱动只写 Redacted 占位
+static REDACT_DEPTH: AtomicUsize = AtomicUsize::new(0);
+
+/// 敏感输入期间持有的守卫 (由 redact() 取得)，离开作用域后恢复正常录制
+pub struct RedactGuard(());
+
+/// 输入账号密码等敏感内容前调用：守卫存活期间轨迹中不记录任何按键与文字
+/// 驱动通常被 HumanDriver 等层层包装，拿不到 RecordingDriver 本身，因此用全局开关
+pub fn redact() -> RedactGuard {
+    REDACT_DEPTH.fetch_add(1, Ordering::SeqCst);
+    RedactGuard(())
+}
+
+impl Drop for RedactGuard {
+    fn drop(&mut self) {
+        REDACT_DEPTH.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl RecordingDriver {
+    pub fn new(inner: Box<dyn InputDriver>, path: &str) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| format!("无法创建轨迹文件 {}: {}", path, e))?;
+        let mut driver = Self { inner, out: Some(LineWriter::new(file)), started: Instant::now(), redacting: false };
+        driver.record(TraceCommand::Start { at: Local::now().format("%Y-%m-%d %H:%M:%S%.3f").to_string() });
+        Ok(driver)
+    }
+
+    fn record(&mut self, cmd: TraceCommand) {
+        let is_key = matches!(
+            cmd,
+            TraceCommand::KeyDown { .. } | TraceCommand::KeyUp | TraceCommand::KeysDown { .. } | TraceCommand::TypeText { .. }
+        );
+        let cmd = if is_key && REDACT_DEPTH.load(Ordering::SeqCst) > 0 {
+            if self.redacting {
+                return;
+            }
+            self.redacting = true;
+            TraceCommand::Redacted
+        } else {
+            self.redacting = false;
+            cmd
+        };
+        let Some(out) = self.out.as_mut() else { return };
+        let entry = TraceEntry { t_ms: self.started.elapsed().as_millis() as u64, cmd };
+        let result = serde_json::to_string(&entry)
+            .map_err(|e| e.to_string())
+            .and_then(|line| writeln!(out, "{}", line).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            println!("⚠️ [Trace] 写入轨迹失败，停止录制: {}", e);
+            self.out = None;
+        }
+    }
+
+    /// 先记录再转发：即使设备报错，轨迹里也能看到这条指令
+    fn pass(
+        &mut self,
+        cmd: TraceCommand,
+        f: impl FnOnce(&mut dyn InputDriver) -> Result<(), DriverError>,
+    ) -> Result<(), DriverError> {
+        self.record(cmd);
+        f(self.inner.as_mut())
+    }
+}
+
+impl InputDriver for RecordingDriver {
+    /// 心跳不写入轨迹
+    fn heartbeat(&mut self) -> Result<(), DriverError> { self.inner.heartbeat() }
+    fn mouse_abs(&mut self, x: u16, y: u16) -> Result<(), DriverError> {
+        self.pass(TraceCommand::MouseAbs { x, y }, |d| d.mouse_abs(x, y))
+    }
+    fn mouse_move(&mut self, dx: i32, dy: i32, wheel: i8) -> Result<(), DriverError> {
+        self.pass(TraceCommand::MouseMove { dx, dy, wheel }, |d| d.mouse_move(dx, dy, wheel))
+    }
+    fn mouse_down(&mut self, buttons: MouseButtons) -> Result<(), DriverError> {
+        self.pass(TraceCommand::MouseDown { buttons: buttons.bits() }, |d| d.mouse_down(buttons))
+    }
+    fn mouse_up(&mut self) -> Result<(), DriverError> { self.pass(TraceCommand::MouseUp, |d| d.mouse_up()) }
+    fn key_down(&mut self, keycode: u8, modifier: u8) -> Result<(), DriverError> {
+        self.pass(TraceCommand::KeyDown { keycode, modifier }, |d| d.key_down(keycode, modifier))
+    }
+    fn key_up(&mut self) -> Result<(), DriverError> { self.pass(TraceCommand::KeyUp, |d| d.key_up()) }
+    fn keys_down(&mut self, keycodes: &[u8], modifier: u8) -> Result<(), DriverError> {
+        self.pass(TraceCommand::KeysDown { keycodes: keycodes.to_vec(), modifier }, |d| d.keys_down(keycodes, modifier))
+    }
+    fn switch_identity(&mut self, index: u8) -> Result<(), DriverError> {
+        self.pass(TraceCommand::SwitchIdentity { index }, |d| d.switch_identity(index))
+    }
+    fn mouse_hscroll(&mut self, delta: i8) -> Result<(), DriverError> {
+        self.pass(TraceCommand::MouseHScroll { delta }, |d| d.mouse_hscroll(delta))
+    }
+    fn type_text(&mut self, text: &str) -> Result<(), DriverError> {
+        self.pass(TraceCommand::TypeText { text: text.to_string() }, |d| d.type_text(text))
+    }
+    fn send_custom(&mut self, event_type: u8, payload: [u8; 6], delay: u16) -> Result<(), DriverError> {
+        self.pass(TraceCommand::Custom { event_type, payload, delay }, |d| d.send_custom(event_type, payload, delay))
+    }
+    fn flush(&mut self) -> Result<(), DriverError> { self.inner.flush() }
+    fn pending(&self) -> usize { self.inner.pending() }
+    fn is_online(&self) -> bool { self.inner.is_online() }
+    fn info(&self) -> DriverInfo { self.inner.info() }
+    fn latency_metrics(&self) -> Option<LatencyMetrics> { self.inner.latency_metrics() }
+    fn capabilities(&self) -> DriverCaps { self.inner.capabilities() }
+    fn set_event_callback(&mut self, callback: DriverEventCallback) { self.inner.set_event_callback(callback); }
+}
+
+// ==========================================
+// 3. 回放
+// ==========================================
+
+/// 按原始时间间隔把轨迹重新送入任意驱动
+pub struct ReplayDriver {
+    entries: Vec<TraceEntry>,
+}
+
+impl ReplayDriver {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("无法读取轨迹文件 {}: {}", path, e))?;
+        let mut entries = Vec::new();
+        for (lineno, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: TraceEntry =
+                serde_json::from_str(line).map_err(|e| format!("轨迹第 {} 行解析失败: {}", lineno + 1, e))?;
+            entries.push(entry);
+        }
+        Ok(Self { entries })
+    }
+
+    /// 录制开始时间 (文件头缺失时为 None)
+    pub fn started_at(&self) -> Option<&str> {
+        self.entries.iter().find_map(|e| match &e.cmd {
+            TraceCommand::Start { at } => Some(at.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn entries(&self) -> &[TraceEntry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 回放全部指令；speed > 1 加速；设备出错即中止并松开按键
+    pub fn run(&self, driver: &mut dyn InputDriver, speed: f32) -> Result<(), DriverError> {
+        let speed = if speed > 0.0 { speed } else { 1.0 };
+        let begin = Instant::now();
+        let result = self.entries.iter().try_for_each(|entry| {
+            let due = Duration::from_secs_f64(entry.t_ms as f64 / 1000.0 / speed as f64);
+            if let Some(wait) = due.checked_sub(begin.elapsed()) {
+                thread::sleep(wait);
+            }
+            entry.cmd.apply(driver)
+        });
+
+        let _ = driver.key_up();
+        let _ = driver.mouse_up();
+        let _ = driver.flush();
+        result
+    }
+}
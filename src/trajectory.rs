@@ -0,0 +1,341 @@
+// src/trajectory.rs
+use rand::Rng;
+use serde::Deserialize;
+
+// ==========================================
+// 1. 配置 (config.toml 中的 [motion])
+// ==========================================
+
+/// 轨迹模型 (按 type 选择)
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "type")]
+pub enum PathModel {
+    /// 三次贝塞尔曲线 + 缓入缓出 (默认)
+    Bezier {
+        /// 控制点的随机偏移幅度 (px)
+        #[serde(default = "default_spread")]
+        spread: f32,
+    },
+    /// WindMouse：受「引力」牵向目标、受随机「风」扰动，接近目标时逐渐减速
+    WindMouse {
+        #[serde(default = "default_gravity")]
+        gravity: f32,
+        #[serde(default = "default_wind")]
+        wind: f32,
+        /// 单步最大位移 (px)，决定移动速度上限
+        #[serde(default = "default_max_step")]
+        max_step: f32,
+        /// 距目标小于该距离时风力衰减、步长收敛
+        #[serde(default = "default_target_area")]
+        target_area: f32,
+    },
+}
+
+fn default_spread() -> f32 { 40.0 }
+fn default_gravity() -> f32 { 9.0 }
+fn default_wind() -> f32 { 3.0 }
+fn default_max_step() -> f32 { 15.0 }
+fn default_target_area() -> f32 { 12.0 }
+
+impl Default for PathModel {
+    fn default() -> Self {
+        PathModel::Bezier { spread: default_spread() }
+    }
+}
+
+/// 拟人移动参数；可在 [[instances]] 中按实例覆盖 (motion = { ... })
+#[derive(Deserialize, Debug, Clone)]
+pub struct MotionConfig {
+    #[serde(default)]
+    pub path: PathModel,
+    /// 长距离移动时冲过目标再回拉的概率 (0~1)
+    #[serde(default = "default_overshoot_chance")]
+    pub overshoot_chance: f32,
+    /// 移动距离超过该值 (px) 才可能冲过目标
+    #[serde(default = "default_overshoot_min_distance")]
+    pub overshoot_min_distance: f32,
+    /// 冲过的距离占总距离的比例上限
+    #[serde(default = "default_overshoot_ratio")]
+    pub overshoot_ratio: f32,
+    /// 冲过目标后停顿多久再回拉 (毫秒)
+    #[serde(default = "default_settle_ms")]
+    pub settle_ms: u64,
+}
+
+fn default_overshoot_chance() -> f32 { 0.0 }
+fn default_overshoot_min_distance() -> f32 { 300.0 }
+fn default_overshoot_ratio() -> f32 { 0.06 }
+fn default_settle_ms() -> u64 { 60 }
+
+impl Default for MotionConfig {
+    fn default() -> Self {
+        Self {
+            path: PathModel::default(),
+            overshoot_chance: default_overshoot_chance(),
+            overshoot_min_distance: default_overshoot_min_distance(),
+            overshoot_ratio: default_overshoot_ratio(),
+            settle_ms: default_settle_ms(),
+        }
+    }
+}
+
+// ==========================================
+// 2. 轨迹规划
+// ==========================================
+
+/// 一段连续的移动：points 在 duration_sec 内匀速发送，结束后停顿 pause_ms
+pub struct Stroke {
+    pub points: Vec<(f32, f32)>,
+    pub duration_sec: f32,
+    pub pause_ms: u64,
+}
+
+/// 主段占总时长的比例 (冲过目标时，剩余时间用于回拉)
+const MAIN_SHARE: f32 = 0.85;
+/// 贝塞尔曲线的采样率 (点/秒)
+const BEZIER_RATE: f32 = 80.0;
+/// WindMouse 的最大迭代次数，防止参数异常时死循环
+const WIND_MAX_POINTS: usize = 10_000;
+/// 折线路径的几何采样间距 (px)
+const POLYLINE_STEP: f32 = 2.0;
+/// 180° 折返的拐角处速度降为直线段的 1 / (1 + 该值)
+const CORNER_SLOWDOWN: f32 = 2.0;
+/// 折线路径速度起伏的幅度
+const SPEED_WOBBLE: f32 = 0.2;
+
+/// 规划从 start 到 end 的移动，可能拆成「冲过目标」与「回拉」两段
+pub fn plan(cfg: &MotionConfig, start: (f32, f32), end: (f32, f32), duration_sec: f32, rng: &mut impl Rng) -> Vec<Stroke> {
+    let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+    let dist = dx.hypot(dy);
+
+    if dist < cfg.overshoot_min_distance || !rng.gen_bool(cfg.overshoot_chance.clamp(0.0, 1.0) as f64) {
+        return vec![Stroke { points: path(&cfg.path, start, end, duration_sec, rng), duration_sec, pause_ms: 0 }];
+    }
+
+    // 沿运动方向冲过，并带一点横向偏差
+    let over = dist * rng.gen_range(0.3..1.0) * cfg.overshoot_ratio;
+    let side = rng.gen_range(-0.3..0.3) * over;
+    let (ux, uy) = (dx / dist, dy / dist);
+    let beyond = (end.0 + ux * over - uy * side, end.1 + uy * over + ux * side);
+
+    let main_sec = duration_sec * MAIN_SHARE;
+    let back_sec = duration_sec - main_sec;
+    vec![
+        Stroke { points: path(&cfg.path, start, beyond, main_sec, rng), duration_sec: main_sec, pause_ms: cfg.settle_ms },
+        // 回拉距离很短，用贴近直线的曲线即可
+        Stroke {
+            points: path(&PathModel::Bezier { spread: over * 0.2 }, beyond, end, back_sec, rng),
+            duration_sec: back_sec,
+            pause_ms: 0,
+        },
+    ]
+}
+
+/// 按住拖拽时手腕带动的下垂：沿 start -> end 的进度按正弦叠加垂直于运动方向的偏移 (偏向屏幕下方)，
+/// 两端为 0、中点最大；冲过目标的部分不再下垂
+pub fn sag(strokes: &mut [Stroke], start: (f32, f32), end: (f32, f32), amount: f32) {
+    let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+    let dist = dx.hypot(dy);
+    if dist < 1.0 || amount == 0.0 {
+        return;
+    }
+    let (ux, uy) = (dx / dist, dy / dist);
+    // 法线取朝下的一侧 (屏幕坐标 y 向下)
+    let (nx, ny) = if ux >= 0.0 { (-uy, ux) } else { (uy, -ux) };
+    for p in strokes.iter_mut().flat_map(|s| s.points.iter_mut()) {
+        let progress = (((p.0 - start.0) * ux + (p.1 - start.1) * uy) / dist).clamp(0.0, 1.0);
+        let offset = (progress * std::f32::consts::PI).sin() * amount;
+        p.0 += nx * offset;
+        p.1 += ny * offset;
+    }
+}
+
+/// 生成单段轨迹点 (不含起点，最后一点恰为 end)
+pub fn path(
+    model: &PathModel,
+    start: (f32, f32),
+    end: (f32, f32),
+    duration_sec: f32,
+    rng: &mut impl Rng,
+) -> Vec<(f32, f32)> {
+    let mut points = match *model {
+        PathModel::Bezier { spread } => bezier_path(start, end, spread, duration_sec, rng),
+        PathModel::WindMouse { gravity, wind, max_step, target_area } => {
+            wind_mouse(start, end, gravity, wind, max_step, target_area, rng)
+        }
+    };
+    points.push(end);
+    points
+}
+
+fn bezier_path(start: (f32, f32), end: (f32, f32), spread: f32, duration_sec: f32, rng: &mut impl Rng) -> Vec<(f32, f32)> {
+    let mut jitter = |lo: f32, hi: f32| if spread > 0.0 { rng.gen_range(lo * spread..hi * spread) } else { 0.0 };
+    let ctrl1 = (
+        start.0 + (end.0 - start.0) * 0.2 + jitter(-1.0, 1.0),
+        start.1 + (end.1 - start.1) * 0.2 + jitter(-1.0, 1.0),
+    );
+    let ctrl2 = (
+        start.0 + (end.0 - start.0) * 0.8 + jitter(-0.5, 1.5),
+        start.1 + (end.1 - start.1) * 0.8 + jitter(-0.5, 1.5),
+    );
+
+    let steps = ((duration_sec * BEZIER_RATE) as u32).max(1);
+    (1..steps)
+        .map(|i| bezier_cubic(ease_in_out_cubic(i as f32 / steps as f32), start, ctrl1, ctrl2, end))
+        .collect()
+}
+
+/// WindMouse (Benjamin J. Land)：速度受引力与风的合力驱动，超过步长上限时随机截断
+fn wind_mouse(
+    start: (f32, f32),
+    end: (f32, f32),
+    gravity: f32,
+    wind: f32,
+    mut max_step: f32,
+    target_area: f32,
+    rng: &mut impl Rng,
+) -> Vec<(f32, f32)> {
+    let (sqrt3, sqrt5) = (3f32.sqrt(), 5f32.sqrt());
+    let (mut x, mut y) = start;
+    let (mut wx, mut wy, mut vx, mut vy) = (0.0f32, 0.0f32, 0.0f32, 0.0f32);
+    let mut points = Vec::new();
+
+    while points.len() < WIND_MAX_POINTS {
+        let dist = (end.0 - x).hypot(end.1 - y);
+        if dist < 1.0 {
+            break;
+        }
+        let w = wind.min(dist);
+        if dist >= target_area {
+            wx = wx / sqrt3 + rng.gen_range(-1.0..=1.0) * w / sqrt5;
+            wy = wy / sqrt3 + rng.gen_range(-1.0..=1.0) * w / sqrt5;
+        } else {
+            wx /= sqrt3;
+            wy /= sqrt3;
+            max_step = if max_step < 3.0 { rng.gen_range(3.0..6.0) } else { max_step / sqrt5 };
+        }
+        vx += wx + gravity * (end.0 - x) / dist;
+        vy += wy + gravity * (end.1 - y) / dist;
+        let v = vx.hypot(vy);
+        if v > max_step {
+            let clip = max_step / 2.0 + rng.gen_range(0.0..=max_step / 2.0);
+            vx = vx / v * clip;
+            vy = vy / v * clip;
+        }
+        x += vx;
+        y += vy;
+        points.push((x, y));
+    }
+    points
+}
+
+/// 沿折线 waypoints (首点为起点) 移动的轨迹：每个拐角在 corner_px 范围内用二次贝塞尔倒圆，
+/// 转得越急越慢；整体缓入缓出并叠加随机的速度起伏
+/// 返回的点按 duration_sec 匀速发送 (不含起点，最后一点恰为终点)
+pub fn polyline(waypoints: &[(f32, f32)], corner_px: f32, duration_sec: f32, rng: &mut impl Rng) -> Vec<(f32, f32)> {
+    let dist = |a: (f32, f32), b: (f32, f32)| (b.0 - a.0).hypot(b.1 - a.1);
+    // 去掉重合的相邻点，避免拐角方向无定义
+    let mut pts: Vec<(f32, f32)> = Vec::with_capacity(waypoints.len());
+    for &p in waypoints {
+        if !pts.last().is_some_and(|&q| dist(q, p) < 0.5) {
+            pts.push(p);
+        }
+    }
+    if pts.len() < 2 {
+        return waypoints.last().map(|&p| vec![p]).unwrap_or_default();
+    }
+
+    // 1. 几何：倒圆后的密集采样点 (点, 减速权重)
+    let toward = |from: (f32, f32), to: (f32, f32), r: f32| {
+        let d = dist(from, to);
+        (from.0 + (to.0 - from.0) / d * r, from.1 + (to.1 - from.1) / d * r)
+    };
+    let mut geo: Vec<((f32, f32), f32)> = vec![(pts[0], 1.0)];
+    let mut from = pts[0];
+    for i in 1..pts.len() {
+        let p = pts[i];
+        if i + 1 == pts.len() {
+            push_line(&mut geo, from, p);
+            break;
+        }
+        let (prev, next) = (pts[i - 1], pts[i + 1]);
+        let r = corner_px.max(0.0).min(dist(prev, p) / 2.0).min(dist(p, next) / 2.0);
+        let (entry, exit) = (toward(p, prev, r), toward(p, next, r));
+        push_line(&mut geo, from, entry);
+        // 转角 0 (直行) ~ 1 (折返)
+        let (a, b) = ((p.0 - prev.0, p.1 - prev.1), (next.0 - p.0, next.1 - p.1));
+        let cos = ((a.0 * b.0 + a.1 * b.1) / (dist(prev, p) * dist(p, next))).clamp(-1.0, 1.0);
+        let turn = cos.acos() / std::f32::consts::PI;
+        push_quad(&mut geo, entry, p, exit, 1.0 + CORNER_SLOWDOWN * turn);
+        from = exit;
+    }
+
+    // 2. 时间：每个采样点的耗时 = 距离 × 减速权重 × 随机起伏
+    let total_len: f32 = geo.windows(2).map(|w| dist(w[0].0, w[1].0)).sum::<f32>().max(1.0);
+    let (freq, phase) = (rng.gen_range(1.0..3.0), rng.gen_range(0.0..std::f32::consts::TAU));
+    let mut travelled = 0.0;
+    let mut cost = vec![0.0f32; geo.len()];
+    for k in 1..geo.len() {
+        let len = dist(geo[k - 1].0, geo[k].0);
+        travelled += len;
+        let wobble = 1.0 + SPEED_WOBBLE * (std::f32::consts::TAU * freq * travelled / total_len + phase).sin();
+        cost[k] = cost[k - 1] + len * geo[k].1 * wobble;
+    }
+    let total_cost = cost[geo.len() - 1];
+
+    // 3. 按缓入缓出的进度在累计耗时上取点
+    let steps = ((duration_sec * BEZIER_RATE) as usize).max(1);
+    let mut out = Vec::with_capacity(steps);
+    let mut k = 1;
+    for i in 1..steps {
+        let target = ease_in_out_cubic(i as f32 / steps as f32) * total_cost;
+        while k < geo.len() - 1 && cost[k] < target {
+            k += 1;
+        }
+        let span = (cost[k] - cost[k - 1]).max(f32::EPSILON);
+        let t = ((target - cost[k - 1]) / span).clamp(0.0, 1.0);
+        let (a, b) = (geo[k - 1].0, geo[k].0);
+        out.push((a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t));
+    }
+    out.push(pts[pts.len() - 1]);
+    out
+}
+
+/// 直线段采样 (不含起点)
+fn push_line(geo: &mut Vec<((f32, f32), f32)>, a: (f32, f32), b: (f32, f32)) {
+    let n = ((b.0 - a.0).hypot(b.1 - a.1) / POLYLINE_STEP).ceil() as usize;
+    for i in 1..=n {
+        let t = i as f32 / n as f32;
+        geo.push(((a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t), 1.0));
+    }
+}
+
+/// 以拐点为控制点的二次贝塞尔倒角采样 (不含起点)
+fn push_quad(geo: &mut Vec<((f32, f32), f32)>, a: (f32, f32), ctrl: (f32, f32), b: (f32, f32), weight: f32) {
+    let approx = (ctrl.0 - a.0).hypot(ctrl.1 - a.1) + (b.0 - ctrl.0).hypot(b.1 - ctrl.1);
+    let n = ((approx / POLYLINE_STEP).ceil() as usize).max(2);
+    for i in 1..=n {
+        let t = i as f32 / n as f32;
+        let u = 1.0 - t;
+        let x = u * u * a.0 + 2.0 * u * t * ctrl.0 + t * t * b.0;
+        let y = u * u * a.1 + 2.0 * u * t * ctrl.1 + t * t * b.1;
+        geo.push(((x, y), weight));
+    }
+}
+
+// ==========================================
+// 3. 数学辅助函数
+// ==========================================
+
+fn ease_in_out_cubic(t: f32) -> f32 {
+    if t < 0.5 { 4.0 * t * t * t } else { 1.0 - (-2.0 * t + 2.0).powi(3) / 2.0 }
+}
+
+fn bezier_cubic(t: f32, p0: (f32, f32), p1: (f32, f32), p2: (f32, f32), p3: (f32, f32)) -> (f32, f32) {
+    let u = 1.0 - t;
+    let tt = t * t;
+    let uu = u * u;
+    let x = uu * u * p0.0 + 3.0 * uu * t * p1.0 + 3.0 * u * tt * p2.0 + tt * t * p3.0;
+    let y = uu * u * p0.1 + 3.0 * uu * t * p1.1 + 3.0 * u * tt * p2.1 + tt * t * p3.1;
+    (x, y)
+}
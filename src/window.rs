@@ -0,0 +1,111 @@
+// src/window.rs
+use windows::Win32::Foundation::{BOOL, HWND, LPARAM, POINT, RECT};
+use windows::Win32::Graphics::Gdi::ClientToScreen;
+use windows::Win32::UI::WindowsAndMessaging::{
+    EnumWindows, GetClassNameW, GetClientRect, GetWindowTextW, IsWindow, IsWindowVisible, SetForegroundWindow,
+};
+
+/// 窗口句柄以 isize 保存，便于跨线程传递
+pub type WindowHandle = isize;
+
+unsafe extern "system" fn enum_proc(hwnd: HWND, lparam: LPARAM) -> BOOL {
+    let out = &mut *(lparam.0 as *mut Vec<(WindowHandle, String)>);
+    if IsWindowVisible(hwnd).as_bool() {
+        let mut buf = [0u16; 256];
+        let len = GetWindowTextW(hwnd, &mut buf);
+        if len > 0 {
+            out.push((hwnd.0, String::from_utf16_lossy(&buf[..len as usize])));
+        }
+    }
+    BOOL(1)
+}
+
+/// 枚举标题包含 title 的可见顶层窗口 (按 Z 序)
+pub fn find_windows(title: &str) -> Vec<(WindowHandle, String)> {
+    let mut all: Vec<(WindowHandle, String)> = Vec::new();
+    unsafe {
+        let _ = EnumWindows(Some(enum_proc), LPARAM(&mut all as *mut _ as isize));
+    }
+    all.into_iter().filter(|(_, t)| t.contains(title)).collect()
+}
+
+/// 按标题与序号查找窗口
+pub fn find_window(title: &str, index: usize) -> Option<WindowHandle> {
+    find_windows(title).get(index).map(|(h, _)| *h)
+}
+
+unsafe extern "system" fn enum_class_proc(hwnd: HWND, lparam: LPARAM) -> BOOL {
+    let out = &mut *(lparam.0 as *mut Vec<(WindowHandle, String)>);
+    if IsWindowVisible(hwnd).as_bool() {
+        let mut buf = [0u16; 256];
+        let len = GetClassNameW(hwnd, &mut buf);
+        if len > 0 {
+            out.push((hwnd.0, String::from_utf16_lossy(&buf[..len as usize])));
+        }
+    }
+    BOOL(1)
+}
+
+/// 枚举窗口类名等于 class 的可见顶层窗口 (按 Z 序)
+pub fn find_windows_by_class(class: &str) -> Vec<WindowHandle> {
+    let mut all: Vec<(WindowHandle, String)> = Vec::new();
+    unsafe {
+        let _ = EnumWindows(Some(enum_class_proc), LPARAM(&mut all as *mut _ as isize));
+    }
+    all.into_iter().filter(|(_, c)| c == class).map(|(h, _)| h).collect()
+}
+
+/// 按 "标题[#序号]" 或 "class:类名[#序号]" 查找窗口
+pub fn find_by_spec(spec: &str) -> Result<WindowHandle, String> {
+    let (name, index) = match spec.rsplit_once('#') {
+        Some((t, n)) => (t, n.parse::<usize>().map_err(|_| format!("无效的窗口序号: {}", n))?),
+        None => (spec, 0),
+    };
+    if let Some(class) = name.strip_prefix("class:") {
+        return find_windows_by_class(class)
+            .get(index)
+            .copied()
+            .ok_or_else(|| format!("未找到类名为 \"{}\" 的窗口 (序号 {})", class, index));
+    }
+    find_window(name, index).ok_or_else(|| format!("未找到标题包含 \"{}\" 的窗口 (序号 {})", name, index))
+}
+
+pub fn is_alive(hwnd: WindowHandle) -> bool {
+    unsafe { IsWindow(HWND(hwnd)).as_bool() }
+}
+
+/// 客户区左上角在屏幕上的坐标
+pub fn client_origin(hwnd: WindowHandle) -> Option<(i32, i32)> {
+    let mut pt = POINT { x: 0, y: 0 };
+    unsafe { ClientToScreen(HWND(hwnd), &mut pt).as_bool().then_some((pt.x, pt.y)) }
+}
+
+/// 客户区尺寸 (宽, 高)
+pub fn client_size(hwnd: WindowHandle) -> Option<(u32, u32)> {
+    let mut rect = RECT::default();
+    unsafe { GetClientRect(HWND(hwnd), &mut rect) }.ok()?;
+    Some(((rect.right - rect.left).max(0) as u32, (rect.bottom - rect.top).max(0) as u32))
+}
+
+/// 截图与点击共用坐标系的原点在虚拟桌面上的位置
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Origin {
+    /// 固定偏移 (如副显示器的左上角)
+    Fixed(i32, i32),
+    /// 窗口客户区左上角，每次使用时重新读取 (窗口可被拖动)
+    Window(WindowHandle),
+}
+
+impl Origin {
+    pub fn resolve(&self) -> Option<(i32, i32)> {
+        match *self {
+            Origin::Fixed(x, y) => Some((x, y)),
+            Origin::Window(hwnd) => client_origin(hwnd),
+        }
+    }
+}
+
+/// 将窗口切到前台 (输入只会送达前台窗口)
+pub fn focus(hwnd: WindowHandle) -> bool {
+    unsafe { SetForegroundWindow(HWND(hwnd)).as_bool() }
+}
@@ -0,0 +1,180 @@
+// src/yolo.rs
+// 通过 ONNX Runtime 运行 YOLOv5 / YOLOv8 检测模型 (需将 onnxruntime.dll 放在程序目录，模型路径见 [detector] model)
+use crate::detector::{Detection, DetectorConfig, ObjectDetector};
+use crate::onnx::{Runtime, Session};
+use image::{imageops::FilterType, Rgb, RgbImage, RgbaImage};
+use std::path::Path;
+
+// ==========================================
+// 1. 预处理 (letterbox)
+// ==========================================
+
+/// letterbox 填充色 (与 ultralytics 训练时一致)
+const PAD_GRAY: u8 = 114;
+
+/// 等比缩放到 size×size 内并居中，四周用灰色填充；返回 (张量, 缩放比例, x 偏移, y 偏移)
+fn letterbox(img: &RgbaImage, size: u32) -> (Vec<f32>, f32, f32, f32) {
+    let (w, h) = img.dimensions();
+    let scale = (size as f32 / w as f32).min(size as f32 / h as f32);
+    let (nw, nh) = (((w as f32 * scale).round() as u32).clamp(1, size), ((h as f32 * scale).round() as u32).clamp(1, size));
+    let rgb = image::DynamicImage::ImageRgba8(img.clone()).into_rgb8();
+    let resized = image::imageops::resize(&rgb, nw, nh, FilterType::Triangle);
+    let (dx, dy) = ((size - nw) / 2, (size - nh) / 2);
+    let mut canvas = RgbImage::from_pixel(size, size, Rgb([PAD_GRAY; 3]));
+    image::imageops::replace(&mut canvas, &resized, dx as i64, dy as i64);
+
+    // HWC -> NCHW，归一化到 0~1
+    let plane = (size * size) as usize;
+    let mut data = vec![0f32; plane * 3];
+    for (i, p) in canvas.pixels().enumerate() {
+        for c in 0..3 {
+            data[c * plane + i] = p[c] as f32 / 255.0;
+        }
+    }
+    (data, scale, dx as f32, dy as f32)
+}
+
+// ==========================================
+// 2. 后处理 (解码 + NMS)
+// ==========================================
+
+/// 模型输入坐标系中的候选框
+#[derive(Debug, Clone, Copy)]
+struct Candidate {
+    class: usize,
+    score: f32,
+    x1: f32,
+    y1: f32,
+    x2: f32,
+    y2: f32,
+}
+
+impl Candidate {
+    fn iou(&self, other: &Candidate) -> f32 {
+        let w = (self.x2.min(other.x2) - self.x1.max(other.x1)).max(0.0);
+        let h = (self.y2.min(other.y2) - self.y1.max(other.y1)).max(0.0);
+        let inter = w * h;
+        let area = |c: &Candidate| (c.x2 - c.x1) * (c.y2 - c.y1);
+        let union = area(self) + area(other) - inter;
+        if union > 0.0 { inter / union } else { 0.0 }
+    }
+}
+
+/// 解码输出张量。两种常见导出格式：
+/// - YOLOv8: 1×(4+C)×N，按属性排列 (cx, cy, w, h, 各类得分)
+/// - YOLOv5: 1×N×(5+C)，按候选排列 (cx, cy, w, h, 目标置信度, 各类概率)
+///
+/// 属性数小于候选数即为按属性排列；按候选排列时，若属性数恰为 4+类别数则视为转置导出的 YOLOv8
+fn decode(shape: &[i64], data: &[f32], classes: usize, threshold: f32) -> Result<Vec<Candidate>, String> {
+    let (a, b) = match shape {
+        [1, a, b] | [a, b] => (*a as usize, *b as usize),
+        _ => return Err(format!("检测模型输出形状异常: {:?}", shape)),
+    };
+    let (count, attrs, by_attribute) = if a < b { (b, a, true) } else { (a, b, false) };
+    if a * b > data.len() || attrs < 5 {
+        return Err(format!("检测模型输出形状异常: {:?}", shape));
+    }
+    let v5 = !by_attribute && (classes == 0 || attrs != 4 + classes);
+    let at = |i: usize, k: usize| if by_attribute { data[k * count + i] } else { data[i * attrs + k] };
+
+    let first_class = if v5 { 5 } else { 4 };
+    let mut candidates = Vec::new();
+    for i in 0..count {
+        let objectness = if v5 { at(i, 4) } else { 1.0 };
+        if objectness < threshold {
+            continue;
+        }
+        let (class, best) = (first_class..attrs)
+            .map(|k| (k - first_class, at(i, k)))
+            .max_by(|x, y| x.1.total_cmp(&y.1))
+            .unwrap_or((0, 0.0));
+        let score = objectness * best;
+        if score < threshold {
+            continue;
+        }
+        let (cx, cy, w, h) = (at(i, 0), at(i, 1), at(i, 2), at(i, 3));
+        candidates.push(Candidate { class, score, x1: cx - w / 2.0, y1: cy - h / 2.0, x2: cx + w / 2.0, y2: cy + h / 2.0 });
+    }
+    Ok(candidates)
+}
+
+/// 按类别做非极大值抑制：得分从高到低，丢弃与已保留的同类框重叠超过 iou 的框
+fn nms(mut candidates: Vec<Candidate>, iou: f32) -> Vec<Candidate> {
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+    let mut kept: Vec<Candidate> = Vec::new();
+    for c in candidates {
+        if kept.iter().all(|k| k.class != c.class || k.iou(&c) <= iou) {
+            kept.push(c);
+        }
+    }
+    kept
+}
+
+// ==========================================
+// 3. 后端
+// ==========================================
+
+pub struct YoloDetector {
+    runtime: Runtime,
+    session: Session,
+    labels: Vec<String>,
+    input_size: u32,
+    confidence: f32,
+    iou: f32,
+}
+
+// OrtSession::Run 允许多线程并发调用，会话创建后不再修改
+unsafe impl Send for YoloDetector {}
+unsafe impl Sync for YoloDetector {}
+
+impl YoloDetector {
+    pub fn new(cfg: &DetectorConfig) -> Result<Self, String> {
+        if cfg.input_size < 32 {
+            return Err(format!("检测模型输入边长 {} 过小", cfg.input_size));
+        }
+        let runtime = Runtime::load(&cfg.onnxruntime)?;
+        let session = runtime.open(Path::new(&cfg.model))?;
+        Ok(Self {
+            runtime,
+            session,
+            labels: cfg.labels.clone(),
+            input_size: cfg.input_size,
+            confidence: cfg.confidence,
+            iou: cfg.iou,
+        })
+    }
+
+    fn label(&self, class: usize) -> String {
+        self.labels.get(class).cloned().unwrap_or_else(|| format!("class_{}", class))
+    }
+}
+
+impl Drop for YoloDetector {
+    fn drop(&mut self) {
+        self.runtime.close(&self.session);
+    }
+}
+
+impl ObjectDetector for YoloDetector {
+    fn name(&self) -> &'static str { "yolo" }
+
+    fn detect(&self, img: &RgbaImage) -> Result<Vec<Detection>, String> {
+        let (w, h) = img.dimensions();
+        if w == 0 || h == 0 {
+            return Ok(Vec::new());
+        }
+        let size = self.input_size;
+        let (mut data, scale, dx, dy) = letterbox(img, size);
+        let (shape, output) = self.runtime.run(&self.session, &mut data, &[1, 3, size as i64, size as i64])?;
+        let candidates = decode(&shape, &output, self.labels.len(), self.confidence)?;
+
+        // 从模型输入坐标换算回原图坐标，并裁到图像范围内
+        let to_x = |v: f32| (((v - dx) / scale).round() as i32).clamp(0, w as i32);
+        let to_y = |v: f32| (((v - dy) / scale).round() as i32).clamp(0, h as i32);
+        Ok(nms(candidates, self.iou)
+            .into_iter()
+            .map(|c| Detection { label: self.label(c.class), score: c.score, rect: [to_x(c.x1), to_y(c.y1), to_x(c.x2), to_y(c.y2)] })
+            .filter(|d| d.rect[2] > d.rect[0] && d.rect[3] > d.rect[1])
+            .collect())
+    }
+}